@@ -86,6 +86,14 @@ pub enum PluginOutputMessages {
 
     /// Output from the plugin that renders pixels in the terminal.
     OutputPixels(Vec<Pixel>),
+
+    /// Ask Tattoy to celebrate something, with a burst of particles out of a cell.
+    Celebrate {
+        /// The kind of celebration, either "confetti" or "sparkles".
+        kind: String,
+        /// The coordinates of the cell. [0, 0] is in the top-left.
+        coordinates: (u32, u32),
+    },
 }
 
 #[expect(clippy::default_numeric_fallback, reason = "Tests aren't so strict")]
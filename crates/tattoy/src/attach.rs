@@ -0,0 +1,247 @@
+//! Named sessions that survive the user's terminal being closed, like `abduco` or `dtach`.
+//!
+//! Rather than Tattoy becoming a daemon itself, `tattoy attach <name>` runs the shell inside
+//! `dtach`, with a socket for each named session. The shell keeps running when Tattoy exits, and
+//! attaching again, from any terminal, reconnects to it. The `detach` keybinding sends `dtach` its
+//! detach key, which ends the current Tattoy but not the session.
+//!
+//! With `always` on, every Tattoy runs in a session of its own, so that closing a window by
+//! accident doesn't kill its long-running jobs. `tattoy attach`, without a name, reattaches to the
+//! newest session.
+
+use color_eyre::eyre::{bail, Result};
+
+/// The key that `dtach` detaches on by default, `^\`.
+const DETACH_KEY: u8 = 0x1c;
+
+/// The extension of session sockets.
+const EXTENSION: &str = "sock";
+
+/// User-configurable settings for named sessions.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to run every Tattoy in a named session, even without `tattoy attach`.
+    pub always: bool,
+    /// The `dtach` executable.
+    pub dtach: String,
+    /// Where the sessions' sockets are kept.
+    pub directory: std::path::PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let directory = dirs::runtime_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("tattoy")
+            .join("sessions");
+        Self {
+            always: false,
+            dtach: "dtach".to_owned(),
+            directory,
+        }
+    }
+}
+
+/// The socket of a named session. Names are used as file names, so they can't contain paths.
+fn socket_path(directory: &std::path::Path, name: &str) -> Result<std::path::PathBuf> {
+    let is_valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|character| character.is_alphanumeric() || "-_.".contains(character))
+        && !name.starts_with('.');
+    if !is_valid {
+        bail!("Session names can only contain letters, numbers, `-`, `_` and `.`: {name:?}");
+    }
+    Ok(directory.join(format!("{name}.{EXTENSION}")))
+}
+
+/// Change the startup command so that it runs in, or attaches to, a named session.
+pub(crate) fn in_session(
+    config: &Config,
+    name: &str,
+    command: Vec<std::ffi::OsString>,
+) -> Result<Vec<std::ffi::OsString>> {
+    std::fs::create_dir_all(&config.directory)?;
+    let socket = socket_path(&config.directory, name)?;
+    tracing::debug!("Attaching to session: {socket:?}");
+
+    let mut attaching: Vec<std::ffi::OsString> = vec![
+        config.dtach.clone().into(),
+        "-A".into(),
+        socket.into(),
+        // Ask the shell to redraw itself when we reattach.
+        "-r".into(),
+        "winch".into(),
+        "-z".into(),
+    ];
+    attaching.extend(command);
+    Ok(attaching)
+}
+
+/// All the sessions that are still running, or at least still have a socket, and when their
+/// sockets were last modified.
+fn sessions(config: &Config) -> Vec<(String, Option<std::time::SystemTime>)> {
+    let Ok(entries) = std::fs::read_dir(&config.directory) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == EXTENSION)
+        })
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            path.file_stem()
+                .map(|stem| (stem.to_string_lossy().into_owned(), modified))
+        })
+        .collect()
+}
+
+/// The names of all the sessions.
+pub(crate) fn names(config: &Config) -> Vec<String> {
+    let mut names: Vec<String> = sessions(config).into_iter().map(|(name, _)| name).collect();
+    names.sort();
+    names
+}
+
+/// The newest session.
+fn newest(config: &Config) -> Option<String> {
+    sessions(config)
+        .into_iter()
+        .max_by_key(|(_, modified)| *modified)
+        .map(|(name, _)| name)
+}
+
+/// The name of the session that this Tattoy should run in, if any. `tattoy attach` names one, or
+/// reattaches to the newest when it doesn't. Otherwise, with `always` on, a new one is made.
+pub(crate) fn session_to_attach(
+    config: &Config,
+    subcommand: Option<&crate::cli_args::Subcommand>,
+) -> Result<Option<String>> {
+    match subcommand {
+        Some(crate::cli_args::Subcommand::Attach { name: Some(name) }) => Ok(Some(name.clone())),
+        Some(crate::cli_args::Subcommand::Attach { name: None }) => match newest(config) {
+            Some(name) => Ok(Some(name)),
+            None => bail!("There aren't any sessions to attach to"),
+        },
+        _ if config.always => {
+            let started = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            Ok(Some(format!("session-{started}-{}", std::process::id())))
+        }
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "Only attaching starts a session"
+        )]
+        _ => Ok(None),
+    }
+}
+
+/// Print the running sessions.
+pub(crate) async fn list(state: &crate::shared_state::SharedState) {
+    let config = state.config.read().await.attach.clone();
+    let names = names(&config);
+    #[expect(
+        clippy::print_stdout,
+        reason = "It's the whole point of the subcommand"
+    )]
+    {
+        if names.is_empty() {
+            println!("No sessions. Start one with `tattoy attach <name>`.");
+        }
+        for name in names {
+            println!("{name}");
+        }
+    }
+}
+
+impl crate::terminal_proxy::proxy::Proxy {
+    /// Detach from the current named session, leaving its shell running. Does nothing when Tattoy
+    /// wasn't started with `tattoy attach`, because the detach key is also the shell's `SIGQUIT`.
+    pub(crate) async fn detach(&self) -> Result<bool> {
+        let Some(name) = self.state.attached_session.read().await.clone() else {
+            return Ok(false);
+        };
+
+        tracing::info!("Detaching from session: {name}");
+        let mut buffer: crate::raw_input::BytesFromSTDIN = [0; 128];
+        if let Some(first) = buffer.first_mut() {
+            *first = DETACH_KEY;
+        }
+        self.shadow_terminal.send_input(buffer).await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn session_names_cant_be_paths() {
+        let directory = std::path::Path::new("/tmp/sessions");
+        assert_eq!(
+            socket_path(directory, "work").unwrap(),
+            std::path::PathBuf::from("/tmp/sessions/work.sock")
+        );
+        assert!(socket_path(directory, "../work").is_err());
+        assert!(socket_path(directory, "").is_err());
+        assert!(socket_path(directory, ".hidden").is_err());
+    }
+
+    #[test]
+    fn commands_run_in_dtach() {
+        let directory =
+            std::env::temp_dir().join(format!("tattoy-attach-test-{}", std::process::id()));
+        let config = Config {
+            directory: directory.clone(),
+            ..Config::default()
+        };
+        let command = in_session(&config, "work", vec!["zsh".into()]).unwrap();
+        assert_eq!(command.first().unwrap(), "dtach");
+        assert_eq!(
+            command.get(2).unwrap(),
+            directory.join("work.sock").as_os_str()
+        );
+        assert_eq!(command.last().unwrap(), "zsh");
+
+        std::fs::write(directory.join("work.sock"), "").unwrap();
+        std::fs::write(directory.join("notes.txt"), "").unwrap();
+        assert_eq!(names(&config), ["work"]);
+        assert_eq!(
+            session_to_attach(
+                &config,
+                Some(&crate::cli_args::Subcommand::Attach { name: None })
+            )
+            .unwrap(),
+            Some("work".to_owned())
+        );
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn sessions_are_only_started_when_asked_for() {
+        let mut config = Config {
+            directory: std::path::PathBuf::from("/nonexistent"),
+            ..Config::default()
+        };
+        assert_eq!(session_to_attach(&config, None).unwrap(), None);
+        assert!(session_to_attach(
+            &config,
+            Some(&crate::cli_args::Subcommand::Attach { name: None })
+        )
+        .is_err());
+
+        config.always = true;
+        let name = session_to_attach(&config, None).unwrap().unwrap();
+        assert!(socket_path(&config.directory, &name).is_ok());
+    }
+}
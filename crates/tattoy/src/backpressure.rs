@@ -0,0 +1,270 @@
+//! What tattoys do when the renderer can't keep up with their frames.
+//!
+//! Usually frames are sent over the `FrameUpdate` channel, and when it's full tattoys wait for the
+//! renderer to catch up. Under heavy PTY output that can cause lag, so tattoys can instead stash
+//! their latest frame in the shared state. The renderer picks up stashed frames whenever it
+//! handles any frame update, so a stashed frame is never left behind: a full channel means the
+//! renderer has more updates still to handle.
+//!
+//! Tattoys that render on every frame tick, like GPU shaders, can also pace themselves: they skip
+//! rendering whilst their previous frame still hasn't been composited by the renderer.
+
+use color_eyre::eyre::Result;
+
+/// How a tattoy sends its frames to the renderer.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Policy {
+    /// Wait for the renderer to have room for the frame. No frames are ever lost.
+    #[default]
+    Block,
+    /// Send frames as usual, but when the renderer is backed up, stash the frame instead. Only the
+    /// newest stashed frame is kept, older ones are dropped.
+    DropOldest,
+    /// Always stash frames, so that the renderer only ever has the newest frame of the tattoy to
+    /// render. Good for tattoys that render every frame, like shaders.
+    Coalesce,
+}
+
+/// User-configurable settings for tattoys' backpressure.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// The policy for any tattoy that doesn't have its own.
+    pub default: Policy,
+    /// Policies for individual tattoys, by their ID, eg: `shader` or `minimap`.
+    pub tattoys: std::collections::BTreeMap<String, Policy>,
+}
+
+impl Config {
+    /// The policy of a tattoy.
+    pub fn policy(&self, id: &str) -> Policy {
+        self.tattoys.get(id).copied().unwrap_or(self.default)
+    }
+}
+
+/// Measurements of how well the renderer is keeping up with frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct FrameMetrics {
+    /// The number of frame updates waiting in the channel.
+    pub backlog: usize,
+    /// The biggest backlog there's been.
+    pub peak_backlog: usize,
+    /// The number of stashed frames waiting for the renderer.
+    pub stashed: usize,
+    /// The number of stashed frames that were replaced before the renderer got to them.
+    pub dropped: u64,
+}
+
+/// Which tattoys' latest frames are still waiting to be composited.
+#[derive(Debug, Default)]
+pub(crate) struct Pacing {
+    /// Tattoys whose latest frame hasn't been composited yet.
+    awaiting: std::collections::BTreeSet<String>,
+    /// Tattoys whose frames the renderer has received since it last composited.
+    received: std::collections::BTreeSet<String>,
+    /// The number of renders that each tattoy has skipped because its last frame was waiting.
+    pub skipped: std::collections::BTreeMap<String, u64>,
+}
+
+impl Pacing {
+    /// A tattoy sent a frame.
+    pub fn sent(&mut self, id: &str) {
+        self.awaiting.insert(id.to_owned());
+    }
+
+    /// The renderer received a tattoy's frame.
+    pub fn received(&mut self, id: &str) {
+        self.received.insert(id.to_owned());
+    }
+
+    /// The renderer composited all the frames that it's received.
+    pub fn composited(&mut self) {
+        for id in std::mem::take(&mut self.received) {
+            self.awaiting.remove(&id);
+        }
+    }
+
+    /// Whether a tattoy should render a new frame. If not, the skipped frame is counted.
+    pub fn is_ready(&mut self, id: &str) -> bool {
+        if !self.awaiting.contains(id) {
+            return true;
+        }
+        *self.skipped.entry(id.to_owned()).or_default() += 1;
+        false
+    }
+}
+
+/// Send a tattoy's frame to the renderer, following its backpressure policy.
+pub(crate) async fn send_frame(
+    state: &crate::shared_state::SharedState,
+    channel: &tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+    policy: Policy,
+    surface: crate::surface::Surface,
+) -> Result<()> {
+    state.frame_pacing.write().await.sent(&surface.id);
+    match policy {
+        Policy::Block => {
+            channel
+                .send(crate::run::FrameUpdate::TattoySurface(surface))
+                .await?;
+        }
+        Policy::DropOldest => {
+            match channel.try_send(crate::run::FrameUpdate::TattoySurface(surface)) {
+                Ok(()) => (),
+                Err(tokio::sync::mpsc::error::TrySendError::Full(
+                    crate::run::FrameUpdate::TattoySurface(surface),
+                )) => {
+                    stash(state, surface).await;
+                }
+                Err(tokio::sync::mpsc::error::TrySendError::Full(_)) => (),
+                Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => {
+                    color_eyre::eyre::bail!("Frame update channel is closed");
+                }
+            }
+        }
+        Policy::Coalesce => {
+            let is_newly_stashed = stash(state, surface).await;
+            if is_newly_stashed {
+                notify(channel)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Stash a frame for the renderer to pick up, replacing any frame of the same tattoy that's
+/// already waiting. Returns whether there wasn't one waiting.
+async fn stash(state: &crate::shared_state::SharedState, surface: crate::surface::Surface) -> bool {
+    let mut stashed = state.stashed_frames.write().await;
+    let replaced = stashed.insert(surface.id.clone(), surface);
+    let count = stashed.len();
+    drop(stashed);
+
+    let is_new = replaced.is_none();
+    if let Some(old) = replaced {
+        state.surface_pool.write().await.recycle(old);
+    }
+
+    let mut metrics = state.frame_metrics.write().await;
+    metrics.stashed = count;
+    if !is_new {
+        metrics.dropped += 1;
+    }
+    is_new
+}
+
+/// Let the renderer know that there's a stashed frame. If the channel is full then the renderer
+/// will find the frame anyway.
+fn notify(channel: &tokio::sync::mpsc::Sender<crate::run::FrameUpdate>) -> Result<()> {
+    match channel.try_send(crate::run::FrameUpdate::Stashed) {
+        Ok(()) | Err(tokio::sync::mpsc::error::TrySendError::Full(_)) => Ok(()),
+        Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => {
+            color_eyre::eyre::bail!("Frame update channel is closed")
+        }
+    }
+}
+
+/// Take all the stashed frames, for rendering.
+pub(crate) async fn take_stashed_frames(
+    state: &crate::shared_state::SharedState,
+) -> std::collections::BTreeMap<String, crate::surface::Surface> {
+    let frames = std::mem::take(&mut *state.stashed_frames.write().await);
+    state.frame_metrics.write().await.stashed = 0;
+    frames
+}
+
+/// Record the size of the renderer's backlog.
+pub(crate) async fn record_backlog(state: &crate::shared_state::SharedState, backlog: usize) {
+    let mut metrics = state.frame_metrics.write().await;
+    metrics.backlog = backlog;
+    metrics.peak_backlog = metrics.peak_backlog.max(backlog);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn surface(id: &str) -> crate::surface::Surface {
+        crate::surface::Surface::new(id.to_owned(), 1, 1, 1, 1.0)
+    }
+
+    async fn state() -> std::sync::Arc<crate::shared_state::SharedState> {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1);
+        crate::shared_state::SharedState::init(1, 1, protocol_tx)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn tattoys_can_have_their_own_policy() {
+        let config = Config {
+            default: Policy::DropOldest,
+            tattoys: std::iter::once(("shader".to_owned(), Policy::Coalesce)).collect(),
+        };
+        assert_eq!(config.policy("shader"), Policy::Coalesce);
+        assert_eq!(config.policy("minimap"), Policy::DropOldest);
+    }
+
+    #[tokio::test]
+    async fn frames_are_stashed_when_the_renderer_is_backed_up() {
+        let state = state().await;
+        let (channel, _receiver) = tokio::sync::mpsc::channel(1);
+
+        send_frame(&state, &channel, Policy::DropOldest, surface("a"))
+            .await
+            .unwrap();
+        assert!(state.stashed_frames.read().await.is_empty());
+
+        send_frame(&state, &channel, Policy::DropOldest, surface("a"))
+            .await
+            .unwrap();
+        send_frame(&state, &channel, Policy::DropOldest, surface("a"))
+            .await
+            .unwrap();
+        let metrics = *state.frame_metrics.read().await;
+        assert_eq!(metrics.stashed, 1);
+        assert_eq!(metrics.dropped, 1);
+
+        assert_eq!(take_stashed_frames(&state).await.len(), 1);
+        assert_eq!(state.frame_metrics.read().await.stashed, 0);
+    }
+
+    #[tokio::test]
+    async fn coalesced_frames_only_notify_once() {
+        let state = state().await;
+        let (channel, mut receiver) = tokio::sync::mpsc::channel(10);
+
+        send_frame(&state, &channel, Policy::Coalesce, surface("a"))
+            .await
+            .unwrap();
+        send_frame(&state, &channel, Policy::Coalesce, surface("a"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(crate::run::FrameUpdate::Stashed)
+        ));
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(take_stashed_frames(&state).await.len(), 1);
+    }
+
+    #[test]
+    fn renders_wait_for_the_last_frame_to_be_composited() {
+        let mut pacing = Pacing::default();
+        assert!(pacing.is_ready("shader"));
+
+        pacing.sent("shader");
+        assert!(!pacing.is_ready("shader"));
+
+        // Compositing only counts for frames that the renderer has actually received.
+        pacing.composited();
+        assert!(!pacing.is_ready("shader"));
+
+        pacing.received("shader");
+        pacing.composited();
+        assert!(pacing.is_ready("shader"));
+        assert_eq!(pacing.skipped.get("shader"), Some(&2));
+    }
+}
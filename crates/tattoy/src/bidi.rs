@@ -0,0 +1,84 @@
+//! Bidirectional text, following the Unicode Bidirectional Algorithm (UAX #9).
+//!
+//! The PTY's cells are always in logical order, the order that the text was written in. But
+//! terminals that support bidirectional text show right-to-left scripts, like Arabic and Hebrew,
+//! reversed. So a tattoy that covers part of the text, like a redaction, needs to map the logical
+//! columns of the text to the visual columns that the user actually sees it in.
+
+/// The visual column of each logical column of a line, given as the text of each of its cells.
+pub(crate) fn visual_columns(cells: &[&str]) -> Vec<usize> {
+    let characters: Vec<char> = cells
+        .iter()
+        .map(|cell| cell.chars().next().unwrap_or(' '))
+        .collect();
+    let mut columns: Vec<usize> = (0..characters.len()).collect();
+    if characters.iter().all(char::is_ascii) {
+        return columns;
+    }
+
+    let mut context = wezterm_bidi::BidiContext::new();
+    context.resolve_paragraph(
+        &characters,
+        wezterm_bidi::ParagraphDirectionHint::LeftToRight,
+    );
+    let visual_order = context
+        .reordered_runs(0..characters.len())
+        .into_iter()
+        .flat_map(|run| run.indices);
+    for (visual, logical) in visual_order.enumerate() {
+        if let Some(column) = columns.get_mut(logical) {
+            *column = visual;
+        }
+    }
+    columns
+}
+
+/// The visual spans, as starting column and width, that a logical range of columns is shown in.
+/// Text that mixes directions can be split into more than one span.
+pub(crate) fn visual_spans(
+    columns: &[usize],
+    logical: core::ops::Range<usize>,
+) -> Vec<(usize, usize)> {
+    let mut visual: Vec<usize> = columns
+        .get(logical)
+        .map(<[usize]>::to_vec)
+        .unwrap_or_default();
+    visual.sort_unstable();
+
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    for column in visual {
+        match spans.last_mut() {
+            Some((start, width)) if *start + *width == column => *width += 1,
+            _ => spans.push((column, 1)),
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cells(text: &str) -> Vec<String> {
+        text.chars().map(String::from).collect()
+    }
+
+    #[test]
+    fn right_to_left_text_is_reversed() {
+        let ascii = cells("abc");
+        let ascii: Vec<&str> = ascii.iter().map(String::as_str).collect();
+        assert_eq!(visual_columns(&ascii), [0, 1, 2]);
+
+        let mixed = cells("ab אבג");
+        let mixed: Vec<&str> = mixed.iter().map(String::as_str).collect();
+        assert_eq!(visual_columns(&mixed), [0, 1, 2, 5, 4, 3]);
+    }
+
+    #[test]
+    fn logical_ranges_are_split_into_visual_spans() {
+        let columns = [0, 1, 2, 5, 4, 3, 6];
+        assert_eq!(visual_spans(&columns, 3..6), [(3, 3)]);
+        assert_eq!(visual_spans(&columns, 1..4), [(1, 2), (5, 1)]);
+        assert!(visual_spans(&columns, 9..10).is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+//! A canvas of braille dots, for monochrome effects like rain and particles. Each cell has 2x4
+//! dots, rather than the 1x2 pixels of half blocks, so movement looks much smoother, even on plain
+//! terminals. The catch is that each cell can only have 1 colour, the average of its dots.
+//!
+//! Tattoys are drawn with braille when their ID is in `render.braille`. They still position things
+//! in pixels, but with fractions, see `Tattoyer::add_point()`.
+
+/// The number of dots across a cell.
+const DOTS_ACROSS: usize = 2;
+
+/// The number of dots down a cell.
+const DOTS_DOWN: usize = 4;
+
+/// The number of dots per pixel, along each axis.
+const DOTS_PER_PIXEL: f32 = 2.0;
+
+/// A canvas of braille dots.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Canvas {
+    /// The width, in dots.
+    width: usize,
+    /// The height, in dots.
+    height: usize,
+    /// The colour of every dot that's been drawn, in rows from the top left.
+    dots: Vec<Option<crate::surface::Colour>>,
+}
+
+impl Canvas {
+    /// Make sure the canvas fits a terminal of the given size, in cells, and clear it.
+    pub fn reset(&mut self, columns: usize, rows: usize) {
+        self.width = columns * DOTS_ACROSS;
+        self.height = rows * DOTS_DOWN;
+        self.dots.clear();
+        self.dots.resize(self.width * self.height, None);
+    }
+
+    /// Draw a dot at a point given in pixels, where a cell is 1x2 pixels. Points beyond the edge
+    /// of the canvas are clipped.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "Negative values are checked for and positions are always small"
+    )]
+    pub fn add_point(&mut self, x: f32, y: f32, colour: crate::surface::Colour) {
+        if x < 0.0 || y < 0.0 {
+            return;
+        }
+        let dot_x = (x * DOTS_PER_PIXEL).floor() as usize;
+        let dot_y = (y * DOTS_PER_PIXEL).floor() as usize;
+        if dot_x >= self.width || dot_y >= self.height {
+            return;
+        }
+        if let Some(dot) = self.dots.get_mut(dot_y * self.width + dot_x) {
+            *dot = Some(colour);
+        }
+    }
+
+    /// The glyph of a cell, if it has any dots.
+    fn glyph(&self, column: usize, row: usize) -> Option<crate::pixel_encoders::Glyph> {
+        let mut mask = 0_u32;
+        let mut colours = Vec::new();
+        for dot_y in 0..DOTS_DOWN {
+            for dot_x in 0..DOTS_ACROSS {
+                let index = (row * DOTS_DOWN + dot_y) * self.width + column * DOTS_ACROSS + dot_x;
+                if let Some(Some(colour)) = self.dots.get(index) {
+                    mask |= 1_u32 << (dot_y * DOTS_ACROSS + dot_x);
+                    colours.push(*colour);
+                }
+            }
+        }
+        if mask == 0 {
+            return None;
+        }
+        Some(crate::pixel_encoders::Glyph {
+            character: crate::pixel_encoders::braille_character(mask),
+            foreground: crate::pixel_encoders::average(colours.iter()),
+            background: None,
+        })
+    }
+
+    /// Draw every cell that has dots onto a surface.
+    pub fn draw(&self, surface: &mut crate::surface::Surface) {
+        for row in 0..self.height.div_euclid(DOTS_DOWN) {
+            for column in 0..self.width.div_euclid(DOTS_ACROSS) {
+                if let Some(glyph) = self.glyph(column, row) {
+                    surface.add_glyph(column, row, glyph);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RED: crate::surface::Colour = (1.0, 0.0, 0.0, 1.0);
+    const BLUE: crate::surface::Colour = (0.0, 0.0, 1.0, 1.0);
+
+    #[test]
+    fn points_become_dots() {
+        let mut canvas = Canvas::default();
+        canvas.reset(2, 1);
+        canvas.add_point(0.0, 0.0, RED);
+        canvas.add_point(0.6, 1.9, BLUE);
+        canvas.add_point(-1.0, 0.0, RED);
+        canvas.add_point(9.0, 0.0, RED);
+
+        let glyph = canvas.glyph(0, 0).unwrap();
+        assert_eq!(glyph.character, '⢁');
+        assert_eq!(glyph.foreground, Some((0.5, 0.0, 0.5, 1.0)));
+        assert!(canvas.glyph(1, 0).is_none());
+    }
+
+    #[test]
+    fn resetting_clears_the_dots() {
+        let mut canvas = Canvas::default();
+        canvas.reset(1, 1);
+        canvas.add_point(0.5, 0.5, RED);
+        assert!(canvas.glyph(0, 0).is_some());
+        canvas.reset(1, 1);
+        assert!(canvas.glyph(0, 0).is_none());
+    }
+}
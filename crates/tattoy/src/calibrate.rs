@@ -0,0 +1,232 @@
+//! `tattoy calibrate`: find the real aspect ratio of the pixels that shaders render.
+//!
+//! Shaders render 2 pixels per cell, one above the other, which assumes that a cell is exactly
+//! twice as tall as it's wide. Most fonts aren't quite, so circles come out as ovals. This shows a
+//! circle and a square, made of half blocks, that the user stretches until they look right. The
+//! result is saved as `pixel_aspect` in the main config, which shaders get as `iResolution.z`.
+
+#![expect(clippy::print_stdout, reason = "We need to give user feedback")]
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+use termwiz::terminal::Terminal as _;
+
+/// How much each keypress changes the aspect ratio.
+const STEP: f32 = 0.02;
+
+/// The narrowest pixels that can be calibrated to.
+const MIN_ASPECT: f32 = 0.25;
+
+/// The widest pixels that can be calibrated to.
+const MAX_ASPECT: f32 = 4.0;
+
+/// The height of the test patterns, in pixels.
+const PATTERN_HEIGHT: usize = 24;
+
+/// The number of columns between the test patterns.
+const GAP: usize = 6;
+
+/// The number of rows of instructions above the test patterns.
+const HEADER_ROWS: usize = 4;
+
+/// What the user asked the calibration to do next.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    /// Keep asking.
+    Redraw,
+    /// Write the aspect ratio to the config.
+    Save,
+    /// Leave without writing anything.
+    Quit,
+}
+
+/// Change the aspect ratio with a keypress.
+fn handle_key(aspect: &mut f32, key_event: &termwiz::input::KeyEvent) -> Action {
+    use termwiz::input::KeyCode;
+
+    #[expect(
+        clippy::wildcard_enum_match_arm,
+        reason = "Only a few keys mean anything to the calibration"
+    )]
+    match key_event.key {
+        KeyCode::Char('c') if key_event.modifiers == termwiz::input::Modifiers::CTRL => {
+            return Action::Quit
+        }
+        // Wider pixels need fewer columns, so the shapes get narrower.
+        KeyCode::LeftArrow | KeyCode::Char('h') => *aspect += STEP,
+        KeyCode::RightArrow | KeyCode::Char('l') => *aspect -= STEP,
+        KeyCode::Char('r') => *aspect = 1.0,
+        KeyCode::Enter => return Action::Save,
+        KeyCode::Escape | KeyCode::Char('q') => return Action::Quit,
+        _ => (),
+    }
+    *aspect = aspect.clamp(MIN_ASPECT, MAX_ASPECT);
+    Action::Redraw
+}
+
+/// The pixels of the test patterns: a filled circle and the outline of a square, side by side.
+/// They should look round and square when `aspect` is the real aspect ratio of a pixel.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss,
+    reason = "The patterns are always small and the aspect ratio is always positive"
+)]
+fn pattern(aspect: f32) -> Vec<Vec<bool>> {
+    let height = PATTERN_HEIGHT as f32;
+    let width = (height / aspect).round() as usize;
+    let radius = height / 2.0;
+
+    let mut pixels = Vec::new();
+    for y in 0..PATTERN_HEIGHT {
+        let mut row = Vec::new();
+        let distance_y = y as f32 + 0.5 - radius;
+        for x in 0..width {
+            let distance_x = (x as f32 + 0.5).mul_add(aspect, -radius);
+            row.push(distance_x.hypot(distance_y) <= radius);
+        }
+        row.resize(width + GAP, false);
+        for x in 0..width {
+            row.push(x == 0 || x + 1 == width || y == 0 || y + 1 == PATTERN_HEIGHT);
+        }
+        pixels.push(row);
+    }
+    pixels
+}
+
+/// The lines of half blocks that show pairs of rows of pixels.
+fn to_half_blocks(pixels: &[Vec<bool>]) -> Vec<String> {
+    pixels
+        .chunks(2)
+        .map(|rows| {
+            let top = rows.first().map(Vec::as_slice).unwrap_or_default();
+            let bottom = rows.get(1).map(Vec::as_slice).unwrap_or_default();
+            (0..top.len().max(bottom.len()))
+                .map(|x| {
+                    let is_top = top.get(x).copied().unwrap_or_default();
+                    let is_bottom = bottom.get(x).copied().unwrap_or_default();
+                    match (is_top, is_bottom) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The main config, with `pixel_aspect` set. Everything else, including comments, is kept.
+fn set_pixel_aspect(config: &str, aspect: f32) -> Result<String> {
+    let mut document = config.parse::<toml_edit::DocumentMut>()?;
+    let rounded = (f64::from(aspect) * 100.0).round() / 100.0;
+    document.insert("pixel_aspect", toml_edit::value(rounded));
+    Ok(document.to_string())
+}
+
+/// Our main entrypoint.
+pub(crate) async fn run(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Result<()> {
+    let config_path = crate::config::main::Config::main_config_path(state).await;
+    let mut aspect = state.config.read().await.pixel_aspect;
+
+    let mut terminal = crate::renderer::Renderer::get_termwiz_terminal()?;
+    terminal.set_raw_mode()?;
+    terminal.enter_alternate_screen()?;
+    let result = handle_input(&mut terminal, &mut aspect);
+    terminal.exit_alternate_screen()?;
+    terminal.set_cooked_mode()?;
+
+    if result? == Action::Save {
+        let config = std::fs::read_to_string(&config_path).unwrap_or_default();
+        std::fs::write(&config_path, set_pixel_aspect(&config, aspect)?)?;
+        println!(
+            "Saved `pixel_aspect = {aspect:.2}` to: {}",
+            config_path.display()
+        );
+    } else {
+        println!("Calibration cancelled, nothing was changed.");
+    }
+    Ok(())
+}
+
+/// Redraw the test patterns after every keypress, until the user saves or quits.
+fn handle_input(
+    terminal: &mut termwiz::terminal::SystemTerminal,
+    aspect: &mut f32,
+) -> Result<Action> {
+    loop {
+        draw(terminal, *aspect)?;
+        let Some(event) = terminal.poll_input(None)? else {
+            continue;
+        };
+        if let termwiz::input::InputEvent::Key(key_event) = event {
+            let action = handle_key(aspect, &key_event);
+            if action != Action::Redraw {
+                return Ok(action);
+            }
+        }
+    }
+}
+
+/// Draw the instructions and the test patterns.
+fn draw(terminal: &mut termwiz::terminal::SystemTerminal, aspect: f32) -> Result<()> {
+    let mut lines = vec![
+        "Tattoy calibration".to_owned(),
+        "Stretch the shapes until the circle is round and the square is square.".to_owned(),
+        format!("Pixel aspect ratio: {aspect:.2}"),
+        "←/→: narrower/wider   r: reset   Enter: save   q: quit".to_owned(),
+    ];
+    lines.resize(HEADER_ROWS + 1, String::new());
+    lines.extend(to_half_blocks(&pattern(aspect)));
+
+    let mut changes = vec![
+        termwiz::surface::Change::ClearScreen(termwiz::color::ColorAttribute::Default),
+        termwiz::surface::Change::CursorVisibility(termwiz::surface::CursorVisibility::Hidden),
+    ];
+    for (y, line) in lines.into_iter().enumerate() {
+        changes.push(termwiz::surface::Change::CursorPosition {
+            x: termwiz::surface::Position::Absolute(0),
+            y: termwiz::surface::Position::Absolute(y),
+        });
+        changes.push(termwiz::surface::Change::Text(line));
+    }
+    changes.push(termwiz::surface::Change::CursorVisibility(
+        termwiz::surface::CursorVisibility::Visible,
+    ));
+    terminal.render(&changes)?;
+    terminal.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[expect(clippy::indexing_slicing, reason = "Tests aren't so strict")]
+    #[test]
+    fn patterns_stretch_with_the_aspect_ratio() {
+        let square_pixels = pattern(1.0);
+        assert_eq!(square_pixels.len(), PATTERN_HEIGHT);
+        assert_eq!(square_pixels[0].len(), PATTERN_HEIGHT * 2 + GAP);
+        assert!(square_pixels[PATTERN_HEIGHT / 2][PATTERN_HEIGHT / 2]);
+        assert!(!square_pixels[0][0]);
+
+        let wide_pixels = pattern(2.0);
+        assert_eq!(wide_pixels[0].len(), PATTERN_HEIGHT + GAP);
+        assert_eq!(to_half_blocks(&wide_pixels).len(), PATTERN_HEIGHT / 2);
+    }
+
+    #[test]
+    fn the_aspect_ratio_is_saved_without_losing_comments() {
+        let config = "# A comment\nframe_rate = 30\n\n[minimap]\nenabled = true\n";
+        let saved = set_pixel_aspect(config, 0.914_321).unwrap();
+        assert!(saved.starts_with("# A comment\n"));
+        let parsed: crate::config::main::Config = toml::from_str(&saved).unwrap();
+        assert!((parsed.pixel_aspect - 0.91).abs() < f32::EPSILON);
+
+        let resaved = set_pixel_aspect(&saved, 1.2).unwrap();
+        assert_eq!(resaved.matches("pixel_aspect").count(), 1);
+    }
+}
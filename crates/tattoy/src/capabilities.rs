@@ -0,0 +1,280 @@
+//! Detect what the user's terminal can do, so that the renderer and tattoys don't have to guess.
+//!
+//! Once at startup, before Tattoy starts reading input, we send the user's terminal a batch of
+//! queries and listen for its answers. The primary device attributes query (DA1) goes last: every
+//! terminal answers it, and answers arrive in order, so its answer means that there are no more
+//! to wait for. Terminals that don't answer at all just leave us with what `COLORTERM` says.
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+use termwiz::terminal::Terminal as _;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+/// How long to wait for the user's terminal to answer all our queries.
+const PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// The device attributes parameter that means the terminal supports sixel graphics.
+const SIXEL_ATTRIBUTE: u16 = 4;
+
+/// The private mode for synchronised output.
+const SYNCHRONISED_OUTPUT_MODE: u16 = 2026;
+
+/// The ID of the kitty graphics query image. It's never displayed.
+const KITTY_GRAPHICS_QUERY_ID: u16 = 31;
+
+/// The terminfo capabilities that mean a terminal supports true colour, hex encoded as
+/// `XTGETTCAP` requires: `Tc` and `RGB`.
+const TRUECOLOR_TERMINFO_NAMES: [&str; 2] = ["5463", "524742"];
+
+/// What the user's terminal supports.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Capabilities {
+    /// Whether the terminal answered any of our queries.
+    pub is_probed: bool,
+    /// 24 bit colour.
+    pub truecolor: bool,
+    /// The kitty graphics protocol.
+    pub kitty_graphics: bool,
+    /// Sixel graphics.
+    pub sixel: bool,
+    /// Synchronised output, so that a frame is shown all at once rather than as it's written.
+    pub synchronised_output: bool,
+    /// The kitty keyboard protocol.
+    pub kitty_keyboard: bool,
+    /// The parameters of the terminal's primary device attributes (DA1).
+    pub primary_attributes: Vec<u16>,
+    /// The parameters of the terminal's secondary device attributes (DA2). Usually the
+    /// terminal's type, its version and its ROM cartridge number.
+    pub secondary_attributes: Vec<u16>,
+}
+
+impl Capabilities {
+    /// Probe the user's terminal and save what it supports to the shared state.
+    pub(crate) async fn detect(state: &crate::shared_state::SharedState) {
+        let response = match Self::probe().await {
+            Ok(response) => response,
+            Err(error) => {
+                tracing::debug!("Couldn't probe the terminal's capabilities: {error:?}");
+                String::new()
+            }
+        };
+        let colorterm = std::env::var("COLORTERM").ok();
+        let capabilities = Self::parse(&response, colorterm.as_deref());
+        tracing::info!("Terminal supports: {}", capabilities.summary());
+        *state.capabilities.write().await = capabilities;
+    }
+
+    /// Send all the queries to the user's terminal and collect the answers.
+    async fn probe() -> Result<String> {
+        let mut termwiz_terminal = crate::renderer::Renderer::get_termwiz_terminal()?;
+        termwiz_terminal.set_raw_mode()?;
+        let result = Self::query_terminal().await;
+        termwiz_terminal.set_cooked_mode()?;
+        result
+    }
+
+    /// Write the queries and read until the terminal has answered them all, or until it's taking
+    /// too long.
+    async fn query_terminal() -> Result<String> {
+        let mut tty = tokio::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .await?;
+
+        let escape = crate::utils::ESCAPE;
+        let terminator = format!("{escape}\\");
+        let mut queries = vec![
+            // Secondary device attributes.
+            format!("{escape}[>c"),
+            // Synchronised output mode.
+            format!("{escape}[?{SYNCHRONISED_OUTPUT_MODE}$p"),
+            // Kitty keyboard protocol flags.
+            format!("{escape}[?u"),
+            // A 1x1 kitty graphics image that's only checked, not displayed.
+            format!("{escape}_Gi={KITTY_GRAPHICS_QUERY_ID},s=1,v=1,a=q,t=d,f=24;AAAA{terminator}"),
+        ];
+        for name in TRUECOLOR_TERMINFO_NAMES {
+            queries.push(format!("{escape}P+q{name}{terminator}"));
+        }
+        // Primary device attributes. This must go last.
+        queries.push(format!("{escape}[c"));
+
+        tty.write_all(queries.concat().as_bytes()).await?;
+        tty.flush().await?;
+
+        let deadline = tokio::time::Instant::now() + PROBE_TIMEOUT;
+        let mut all = Vec::new();
+        loop {
+            let mut buffer = vec![0; 1024];
+            let Ok(read) = tokio::time::timeout_at(deadline, tty.read(&mut buffer)).await else {
+                tracing::debug!("Timed out waiting for the terminal to answer capability queries");
+                break;
+            };
+            let read = read?;
+            if read == 0 {
+                break;
+            }
+            all.extend(buffer.get(..read).unwrap_or_default());
+
+            let response = String::from_utf8_lossy(&all);
+            if !Self::device_attributes(&response, '?').is_empty() {
+                break;
+            }
+        }
+
+        let response = String::from_utf8_lossy(&all).into_owned();
+        tracing::trace!("Capability query response: {response:?}");
+        Ok(response)
+    }
+
+    /// Work out the capabilities from the terminal's answers to our queries and the value of the
+    /// `COLORTERM` environment variable.
+    fn parse(response: &str, colorterm: Option<&str>) -> Self {
+        static KITTY_KEYBOARD: std::sync::LazyLock<regex::Regex> = std::sync::LazyLock::new(|| {
+            #[expect(clippy::unwrap_used, reason = "It's a hardcoded valid regex")]
+            regex::Regex::new(r"\x1b\[\?\d+u").unwrap()
+        });
+
+        let escape = crate::utils::ESCAPE;
+        let primary_attributes = Self::device_attributes(response, '?');
+        let secondary_attributes = Self::device_attributes(response, '>');
+
+        let is_truecolor_env = colorterm.is_some_and(|value| {
+            value.eq_ignore_ascii_case("truecolor") || value.eq_ignore_ascii_case("24bit")
+        });
+        let is_truecolor_terminfo = TRUECOLOR_TERMINFO_NAMES
+            .iter()
+            .any(|name| response.contains(&format!("{escape}P1+r{name}")));
+        let is_synchronised_output = ["1", "2"].iter().any(|setting| {
+            response.contains(&format!("{escape}[?{SYNCHRONISED_OUTPUT_MODE};{setting}$y"))
+        });
+
+        Self {
+            is_probed: !primary_attributes.is_empty(),
+            truecolor: is_truecolor_env || is_truecolor_terminfo,
+            kitty_graphics: response.contains(&format!("{escape}_Gi={KITTY_GRAPHICS_QUERY_ID};OK")),
+            sixel: primary_attributes.contains(&SIXEL_ATTRIBUTE),
+            synchronised_output: is_synchronised_output,
+            kitty_keyboard: KITTY_KEYBOARD.is_match(response),
+            primary_attributes,
+            secondary_attributes,
+        }
+    }
+
+    /// The parameters of a device attributes answer. `kind` is `?` for primary attributes and `>`
+    /// for secondary attributes.
+    fn device_attributes(response: &str, kind: char) -> Vec<u16> {
+        static DEVICE_ATTRIBUTES: std::sync::LazyLock<regex::Regex> =
+            std::sync::LazyLock::new(|| {
+                #[expect(clippy::unwrap_used, reason = "It's a hardcoded valid regex")]
+                regex::Regex::new(r"\x1b\[([?>])([\d;]*)c").unwrap()
+            });
+
+        DEVICE_ATTRIBUTES
+            .captures_iter(response)
+            .find(|captures| {
+                captures
+                    .get(1)
+                    .is_some_and(|found| found.as_str().starts_with(kind))
+            })
+            .and_then(|captures| captures.get(2))
+            .map(|parameters| {
+                parameters
+                    .as_str()
+                    .split(';')
+                    .filter_map(|parameter| parameter.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// A short list of the supported capabilities, for logging.
+    pub fn summary(&self) -> String {
+        let supported = [
+            (self.truecolor, "truecolor"),
+            (self.kitty_graphics, "kitty graphics"),
+            (self.sixel, "sixel"),
+            (self.synchronised_output, "synchronised output"),
+            (self.kitty_keyboard, "kitty keyboard"),
+        ]
+        .into_iter()
+        .filter_map(|(is_supported, name)| is_supported.then_some(name))
+        .collect::<Vec<&str>>();
+
+        let features = if supported.is_empty() {
+            "nothing special".to_owned()
+        } else {
+            supported.join(", ")
+        };
+        if !self.is_probed {
+            return format!("{features} (it didn't answer any queries)");
+        }
+        if self.secondary_attributes.is_empty() {
+            return features;
+        }
+        let attributes = self
+            .secondary_attributes
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<String>>();
+        format!("{features} (DA2: {})", attributes.join(";"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_a_full_response() {
+        let response = concat!(
+            "\x1b[>1;4000;29c",
+            "\x1b[?2026;2$y",
+            "\x1b[?0u",
+            "\x1b_Gi=31;OK\x1b\\",
+            "\x1bP1+r5463=\x1b\\",
+            "\x1bP0+r524742\x1b\\",
+            "\x1b[?62;4;22c",
+        );
+        let capabilities = Capabilities::parse(response, None);
+        assert_eq!(
+            capabilities,
+            Capabilities {
+                is_probed: true,
+                truecolor: true,
+                kitty_graphics: true,
+                sixel: true,
+                synchronised_output: true,
+                kitty_keyboard: true,
+                primary_attributes: vec![62, 4, 22],
+                secondary_attributes: vec![1, 4000, 29],
+            }
+        );
+    }
+
+    #[test]
+    fn unsupported_features_are_not_detected() {
+        let response = concat!(
+            "\x1b[?2026;0$y",
+            "\x1bP0+r5463\x1b\\",
+            "\x1bP0+r524742\x1b\\",
+            "\x1b[?1;2c",
+        );
+        let capabilities = Capabilities::parse(response, None);
+        assert!(capabilities.is_probed);
+        assert!(!capabilities.truecolor);
+        assert!(!capabilities.sixel);
+        assert!(!capabilities.synchronised_output);
+        assert!(!capabilities.kitty_keyboard);
+        assert!(!capabilities.kitty_graphics);
+    }
+
+    #[test]
+    fn falls_back_to_the_environment() {
+        let capabilities = Capabilities::parse("", Some("truecolor"));
+        assert!(!capabilities.is_probed);
+        assert!(capabilities.truecolor);
+        assert!(!Capabilities::parse("", Some("256color")).truecolor);
+    }
+}
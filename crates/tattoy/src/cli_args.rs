@@ -7,10 +7,18 @@ pub const DEFAULT_CONFIG_FILE_NAME: &str = "tattoy.toml";
 #[derive(clap::Parser, Debug, Clone)]
 #[command(version, about, long_about = "Tattoy argument description")]
 pub(crate) struct CliArgs {
+    /// Do something other than running Tattoy.
+    #[command(subcommand)]
+    pub subcommand: Option<Subcommand>,
+
     /// Name of the Tattoy(s) to use.
     #[arg(long("use"))]
     pub enabled_tattoys: Vec<String>,
 
+    /// Resume the last session: its size, scene, tattoys and, if it was saved, its scrollback.
+    #[arg(long)]
+    pub restore: bool,
+
     /// Disable the little blue indicator in the top-right of the terminal.
     #[arg(long)]
     pub disable_indicator: bool,
@@ -32,6 +40,11 @@ pub(crate) struct CliArgs {
     #[arg(long, value_name = "Path to screenshot file")]
     pub parse_palette: Option<String>,
 
+    /// Print a snippet for your shell's rc file that tells Tattoy about the shell's current
+    /// directory and the commands it runs.
+    #[arg(long, value_name = "Shell")]
+    pub shell_integration: Option<crate::shell_integration::Shell>,
+
     /// Path to config file directory. A directory must be used because Tattoy has various config
     /// files.
     #[arg(long, value_name = "Path to config directory")]
@@ -54,3 +67,74 @@ pub(crate) struct CliArgs {
     #[arg(long, value_name = "Level to log at")]
     pub log_level: Option<crate::config::main::LogLevel>,
 }
+
+/// Things that Tattoy can do instead of running.
+#[derive(clap::Subcommand, Debug, Clone)]
+pub(crate) enum Subcommand {
+    /// Print the JSON schema of the main config file. Editors can use it to complete and validate
+    /// `tattoy.toml`.
+    ConfigSchema,
+    /// Interactively pick some tattoys and a shader to start with, and write them to a commented
+    /// config file.
+    Setup,
+    /// Show test patterns to find the real aspect ratio of your font's cells, so that shaders can
+    /// keep circles round. It's saved to the main config file.
+    Calibrate,
+    /// Install a community shader pack or tattoy preset into the config directory. Lists all
+    /// the packages when no name is given.
+    Install {
+        /// The name of the package.
+        name: Option<String>,
+        /// Replace existing files that are different. They're backed up first.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Export the current config, and every shader and sound that it uses, to a single file that
+    /// can be shared.
+    Export {
+        /// Where to write the bundle.
+        path: std::path::PathBuf,
+    },
+    /// Import a bundle made with `export`. It goes in its own directory, so nothing of yours is
+    /// changed.
+    Import {
+        /// The bundle to import.
+        path: std::path::PathBuf,
+        /// What to call the imported bundle. Defaults to the name of the bundle's file.
+        #[arg(long)]
+        name: Option<String>,
+        /// Keep the bundle's settings that run commands, like hooks and plugins.
+        #[arg(long)]
+        allow_commands: bool,
+    },
+    /// Preview a shader full-screen, without running a shell. The shader is reloaded whenever it's
+    /// saved, and keys tweak its variables, like the position of the cursor.
+    ShaderDev {
+        /// The shader to preview.
+        path: std::path::PathBuf,
+    },
+    /// Start, or attach to, a named session. Its shell keeps running after the terminal is closed,
+    /// or after detaching with the `detach` keybinding. Needs `dtach`.
+    Attach {
+        /// The name of the session. Reattaches to the newest session when it's not given.
+        name: Option<String>,
+    },
+    /// List the named sessions that can be attached to.
+    Sessions,
+    /// Remove an installed package. Files that have been changed since they were installed are
+    /// kept.
+    Uninstall {
+        /// The name of the package.
+        name: String,
+    },
+    /// Run a command inside the PTY relay. Tattoy does this itself, see `crate::pty_relay`.
+    #[command(hide = true)]
+    Relay {
+        /// The socket to report events to.
+        #[arg(long)]
+        events: std::path::PathBuf,
+        /// The command to run.
+        #[arg(last = true, required = true)]
+        command: Vec<std::ffi::OsString>,
+    },
+}
@@ -0,0 +1,249 @@
+//! Simulating colour blindness, so that you can check that your tattoys and shaders are still
+//! legible to colour blind users, and daltonising, which shifts the colours that colour blind
+//! users can't tell apart towards colours that they can. It's applied to the whole of each frame,
+//! after everything else.
+//!
+//! The simulations are the matrices from Machado, Oliveira and Fernandes' "A Physiologically-based
+//! Model for Simulation of Color Vision Deficiency" (2009), at full severity.
+
+use shadow_terminal::termwiz;
+
+/// A 3x3 matrix that transforms linear RGB colours, row by row.
+type Matrix = [[f32; 3]; 3];
+
+/// What a protanope, who is missing red cones, sees.
+const PROTANOPIA: Matrix = [
+    [0.152_286, 1.052_583, -0.204_868],
+    [0.114_503, 0.786_281, 0.099_216],
+    [-0.003_882, -0.048_116, 1.051_998],
+];
+
+/// What a deuteranope, who is missing green cones, sees.
+const DEUTERANOPIA: Matrix = [
+    [0.367_322, 0.860_646, -0.227_968],
+    [0.280_085, 0.672_501, 0.047_413],
+    [-0.011_820, 0.042_940, 0.968_881],
+];
+
+/// What a tritanope, who is missing blue cones, sees.
+const TRITANOPIA: Matrix = [
+    [1.255_528, -0.076_749, -0.178_779],
+    [-0.078_411, 0.930_809, 0.147_602],
+    [0.004_733, 0.691_367, 0.303_900],
+];
+
+/// Moves the red that protanopes and deuteranopes can't see into green and blue.
+const RED_GREEN_SHIFT: Matrix = [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]];
+
+/// Moves the blue that tritanopes can't see into red and green.
+const BLUE_YELLOW_SHIFT: Matrix = [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]];
+
+/// A type of colour blindness.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Deficiency {
+    /// Red blindness.
+    Protanopia,
+    /// Green blindness, the most common.
+    #[default]
+    Deuteranopia,
+    /// Blue blindness.
+    Tritanopia,
+}
+
+impl Deficiency {
+    /// The matrix that simulates the deficiency.
+    const fn simulation(self) -> Matrix {
+        match self {
+            Self::Protanopia => PROTANOPIA,
+            Self::Deuteranopia => DEUTERANOPIA,
+            Self::Tritanopia => TRITANOPIA,
+        }
+    }
+
+    /// The matrix that moves the colours lost to the deficiency into ones that can be seen.
+    const fn shift(self) -> Matrix {
+        match self {
+            Self::Protanopia | Self::Deuteranopia => RED_GREEN_SHIFT,
+            Self::Tritanopia => BLUE_YELLOW_SHIFT,
+        }
+    }
+}
+
+/// What's done to the colours.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Mode {
+    /// Show the colours as someone with the deficiency sees them.
+    #[default]
+    Simulate,
+    /// Adjust the colours so that they're easier to tell apart for someone with the deficiency.
+    Daltonise,
+}
+
+/// User config for colour blindness.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether the colours are transformed when Tattoy starts. The keybinding toggles it.
+    pub enabled: bool,
+    /// The type of colour blindness.
+    pub deficiency: Deficiency,
+    /// Whether to simulate the colour blindness or to assist with it.
+    pub mode: Mode,
+}
+
+/// Multiply a colour by a matrix.
+fn multiply(matrix: Matrix, [red, green, blue]: [f32; 3]) -> [f32; 3] {
+    matrix.map(|[from_red, from_green, from_blue]| {
+        from_red.mul_add(red, from_green.mul_add(green, from_blue * blue))
+    })
+}
+
+/// Convert an sRGB channel to linear light.
+fn to_linear(channel: f32) -> f32 {
+    if channel <= 0.040_45 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert a linear light channel to sRGB.
+fn to_srgb(channel: f32) -> f32 {
+    let channel = channel.clamp(0.0, 1.0);
+    if channel <= 0.003_130_8 {
+        channel * 12.92
+    } else {
+        1.055f32.mul_add(channel.powf(2.4f32.recip()), -0.055)
+    }
+}
+
+/// Transform a single colour. The alpha channel is never changed.
+fn transform(config: &Config, colour: termwiz::color::SrgbaTuple) -> termwiz::color::SrgbaTuple {
+    let linear = [
+        to_linear(colour.0),
+        to_linear(colour.1),
+        to_linear(colour.2),
+    ];
+    let simulated = multiply(config.deficiency.simulation(), linear);
+    let [red, green, blue] = match config.mode {
+        Mode::Simulate => simulated,
+        Mode::Daltonise => {
+            let [red, green, blue] = linear;
+            let [seen_red, seen_green, seen_blue] = simulated;
+            let lost = [red - seen_red, green - seen_green, blue - seen_blue];
+            let [shift_red, shift_green, shift_blue] = multiply(config.deficiency.shift(), lost);
+            [red + shift_red, green + shift_green, blue + shift_blue]
+        }
+    };
+    termwiz::color::SrgbaTuple(to_srgb(red), to_srgb(green), to_srgb(blue), colour.3)
+}
+
+/// Transform the colours of every cell of a frame. `is_toggled` is whether the keybinding has
+/// switched the transform from what the config says.
+pub(crate) fn apply(config: &Config, is_toggled: bool, frame: &mut termwiz::surface::Surface) {
+    if config.enabled == is_toggled {
+        return;
+    }
+
+    for line in &mut frame.screen_cells().iter_mut() {
+        for cell in line.iter_mut() {
+            let attributes = cell.attrs_mut();
+            if let Some(colour) = crate::blender::Blender::extract_colour(attributes.foreground()) {
+                attributes.set_foreground(
+                    termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(transform(
+                        config, colour,
+                    )),
+                );
+            }
+            if let Some(colour) = crate::blender::Blender::extract_colour(attributes.background()) {
+                attributes.set_background(
+                    termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(transform(
+                        config, colour,
+                    )),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RED: termwiz::color::SrgbaTuple = termwiz::color::SrgbaTuple(1.0, 0.0, 0.0, 1.0);
+    const GREEN: termwiz::color::SrgbaTuple = termwiz::color::SrgbaTuple(0.0, 1.0, 0.0, 1.0);
+    const GREY: termwiz::color::SrgbaTuple = termwiz::color::SrgbaTuple(0.5, 0.5, 0.5, 0.5);
+
+    fn distance(first: termwiz::color::SrgbaTuple, second: termwiz::color::SrgbaTuple) -> f32 {
+        ((first.0 - second.0).powi(2) + (first.1 - second.1).powi(2) + (first.2 - second.2).powi(2))
+            .sqrt()
+    }
+
+    #[test]
+    fn greys_are_unchanged() {
+        for deficiency in [
+            Deficiency::Protanopia,
+            Deficiency::Deuteranopia,
+            Deficiency::Tritanopia,
+        ] {
+            for mode in [Mode::Simulate, Mode::Daltonise] {
+                let config = Config {
+                    enabled: true,
+                    deficiency,
+                    mode,
+                };
+                let grey = transform(&config, GREY);
+                assert!(distance(grey, GREY) < 0.01, "{deficiency:?} {mode:?}");
+                assert!((grey.3 - 0.5).abs() < f32::EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn red_and_green_are_confused_and_then_assisted() {
+        let simulate = Config {
+            enabled: true,
+            ..Config::default()
+        };
+        let original = distance(RED, GREEN);
+        let simulated = distance(transform(&simulate, RED), transform(&simulate, GREEN));
+        assert!(simulated < original / 2.0);
+
+        let daltonise = Config {
+            mode: Mode::Daltonise,
+            ..simulate.clone()
+        };
+        let assisted_red = transform(&simulate, transform(&daltonise, RED));
+        let assisted_green = transform(&simulate, transform(&daltonise, GREEN));
+        assert!(distance(assisted_red, assisted_green) > simulated);
+    }
+
+    #[test]
+    fn the_keybinding_toggles_the_config() {
+        let mut attributes = termwiz::cell::CellAttributes::default();
+        attributes
+            .set_background(termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(RED));
+        let mut frame = termwiz::surface::Surface::new(1, 1);
+        frame.add_change(termwiz::surface::Change::AllAttributes(attributes));
+        frame.add_change(" ");
+        let background = |frame: &mut termwiz::surface::Surface| {
+            frame
+                .screen_cells()
+                .first()
+                .and_then(|line| line.first())
+                .and_then(|cell| crate::blender::Blender::extract_colour(cell.attrs().background()))
+                .unwrap()
+        };
+
+        let config = Config {
+            enabled: true,
+            ..Config::default()
+        };
+        apply(&config, true, &mut frame);
+        assert!(distance(background(&mut frame), RED) < 0.01);
+        apply(&config, false, &mut frame);
+        assert!(distance(background(&mut frame), RED) > 0.1);
+    }
+}
@@ -0,0 +1,130 @@
+//! Colour correction for individual tattoys, so that, for example, an over-bright downloaded
+//! shader can be toned down without editing its source. The adjustments are made to a copy of
+//! each of the tattoy's cells just before it's composited, so they never build up from frame to
+//! frame.
+
+use shadow_terminal::termwiz;
+
+/// The adjustments to the colours of a tattoy.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub(crate) struct Adjustments {
+    /// Lighten (positive) or darken (negative) the colours, from `-1.0` to `1.0`.
+    #[schemars(range(min = -1.0, max = 1.0))]
+    pub brightness: f32,
+    /// Saturate (positive) or desaturate (negative) the colours, from `-1.0` to `1.0`.
+    #[schemars(range(min = -1.0, max = 1.0))]
+    pub saturation: f32,
+    /// Rotate the hue of the colours, in degrees.
+    pub hue_shift: f32,
+    /// `1.0` makes no change, more than `1.0` brightens the mid-tones and less than `1.0` darkens
+    /// them.
+    #[schemars(range(min = 0.01))]
+    pub gamma: f32,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            saturation: 0.0,
+            hue_shift: 0.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl Adjustments {
+    /// Whether the adjustments don't change anything, so that the tattoy's cells don't need
+    /// copying.
+    pub fn is_unchanged(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// A copy of the cell with its colours adjusted.
+    pub fn adjust_cell(&self, cell: &termwiz::cell::Cell) -> termwiz::cell::Cell {
+        let mut adjusted = cell.clone();
+        let attributes = adjusted.attrs_mut();
+        if let Some(colour) = crate::blender::Blender::extract_colour(attributes.foreground()) {
+            attributes.set_foreground(
+                termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(self.adjust(colour)),
+            );
+        }
+        if let Some(colour) = crate::blender::Blender::extract_colour(attributes.background()) {
+            attributes.set_background(
+                termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(self.adjust(colour)),
+            );
+        }
+        adjusted
+    }
+
+    /// Adjust a single colour. The alpha channel is never changed.
+    fn adjust(&self, colour: termwiz::color::SrgbaTuple) -> termwiz::color::SrgbaTuple {
+        let adjusted = colour
+            .saturate(f64::from(self.saturation))
+            .lighten(f64::from(self.brightness))
+            .adjust_hue_fixed(f64::from(self.hue_shift));
+        if self.gamma <= 0.0 || (self.gamma - 1.0).abs() < f32::EPSILON {
+            return adjusted;
+        }
+
+        let exponent = self.gamma.recip();
+        let correct = |channel: f32| channel.clamp(0.0, 1.0).powf(exponent);
+        termwiz::color::SrgbaTuple(
+            correct(adjusted.0),
+            correct(adjusted.1),
+            correct(adjusted.2),
+            colour.3,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cell(background: termwiz::color::SrgbaTuple) -> termwiz::cell::Cell {
+        let mut attributes = termwiz::cell::CellAttributes::default();
+        attributes.set_background(
+            termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(background),
+        );
+        termwiz::cell::Cell::new('▀', attributes)
+    }
+
+    fn background(cell: &termwiz::cell::Cell) -> termwiz::color::SrgbaTuple {
+        crate::blender::Blender::extract_colour(cell.attrs().background()).unwrap()
+    }
+
+    #[test]
+    fn no_adjustments_change_nothing() {
+        let adjustments = Adjustments::default();
+        assert!(adjustments.is_unchanged());
+
+        let grey = termwiz::color::SrgbaTuple(0.5, 0.5, 0.5, 1.0);
+        let adjusted = background(&adjustments.adjust_cell(&cell(grey)));
+        assert!((adjusted.0 - 0.5).abs() < 0.01);
+        assert!((adjusted.3 - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn colours_are_toned_down() {
+        let grey = termwiz::color::SrgbaTuple(0.5, 0.5, 0.5, 0.5);
+        let darker = Adjustments {
+            brightness: -0.2,
+            ..Adjustments::default()
+        };
+        assert!(!darker.is_unchanged());
+        assert!(background(&darker.adjust_cell(&cell(grey))).0 < 0.45);
+
+        let brighter_mid_tones = Adjustments {
+            gamma: 2.0,
+            ..Adjustments::default()
+        };
+        let corrected = background(&brighter_mid_tones.adjust_cell(&cell(grey)));
+        assert!((corrected.0 - 0.5f32.sqrt()).abs() < 0.01);
+        assert!((corrected.3 - 0.5).abs() < f32::EPSILON);
+
+        let palette_cell = termwiz::cell::Cell::new('a', termwiz::cell::CellAttributes::default());
+        assert_eq!(darker.adjust_cell(&palette_cell), palette_cell);
+    }
+}
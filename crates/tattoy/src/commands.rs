@@ -0,0 +1,183 @@
+//! Broadcast the commands that the shell in the PTY finishes running, along with how long they
+//! took and their exit status.
+//!
+//! The shell integration appends a line to the file in the `TATTOY_COMMANDS_FILE` environment
+//! variable for every finished command. Each line is the command's duration in milliseconds, its
+//! exit status and then the command itself, separated by tabs.
+
+use color_eyre::eyre::Result;
+
+/// How long the PTY's output needs to settle before we look for newly finished commands.
+const SETTLE_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// A command that the shell finished running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Command {
+    /// The command as the user typed it. Multiple lines are joined with spaces.
+    pub text: String,
+    /// How long the command took.
+    pub duration: std::time::Duration,
+    /// The command's exit status.
+    pub exit_status: i32,
+}
+
+impl Command {
+    /// Parse a line reported by the shell integration.
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, '\t');
+        let milliseconds = fields.next()?.trim().parse::<u64>().ok()?;
+        let exit_status = fields.next()?.trim().parse::<i32>().ok()?;
+        let text = fields.next()?.trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            text: text.to_owned(),
+            duration: std::time::Duration::from_millis(milliseconds),
+            exit_status,
+        })
+    }
+}
+
+/// `Commands`
+pub(crate) struct Commands {
+    /// The application shared state
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+    /// The file that the shell integration reports to.
+    file: std::path::PathBuf,
+    /// How much of the file we've already read.
+    read_bytes: usize,
+}
+
+impl Commands {
+    /// Start the task that watches for finished commands.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let mut protocol = state.protocol_tx.subscribe();
+            let mut commands = Self {
+                state,
+                file: crate::shell_integration::commands_file(),
+                read_bytes: 0,
+            };
+            let mut check_at = None;
+
+            #[expect(
+                clippy::integer_division_remainder_used,
+                reason = "This is caused by the `tokio::select!`"
+            )]
+            loop {
+                tokio::select! {
+                    () = crate::cwd::Cwd::sleep_until(check_at), if check_at.is_some() => {
+                        check_at = None;
+                        commands.check().await?;
+                    },
+                    message = protocol.recv() => match message {
+                        Ok(crate::run::Protocol::End)
+                        | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        Ok(message) => {
+                            if crate::tattoys::tattoyer::Tattoyer::is_screen_output_changed(&message) {
+                                check_at = Some(tokio::time::Instant::now() + SETTLE_DELAY);
+                            }
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Command tracking lagged behind by {skipped} messages");
+                        }
+                    }
+                }
+            }
+
+            if let Err(error) = tokio::fs::remove_file(&commands.file).await {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    tracing::debug!("Couldn't remove {:?}: {error:?}", commands.file);
+                }
+            }
+            tracing::debug!("Leaving command tracking loop");
+            Ok(())
+        })
+    }
+
+    /// Broadcast any commands that have been reported since we last looked.
+    async fn check(&mut self) -> Result<()> {
+        let Ok(report) = tokio::fs::read_to_string(&self.file).await else {
+            return Ok(());
+        };
+        for command in self.new_commands(&report) {
+            tracing::debug!("Shell finished running: {command:?}");
+            self.state
+                .exit_summary
+                .write()
+                .await
+                .command_finished(&command);
+            self.state
+                .protocol_tx
+                .send(crate::run::Protocol::CommandFinished(command))?;
+        }
+        Ok(())
+    }
+
+    /// Parse the complete lines of the report that we haven't seen yet.
+    fn new_commands(&mut self, report: &str) -> Vec<Command> {
+        if report.len() < self.read_bytes {
+            // The file has been truncated, so start again.
+            self.read_bytes = 0;
+        }
+        let Some(unread) = report.get(self.read_bytes..) else {
+            return Vec::new();
+        };
+        let Some(complete) = unread.rfind('\n').and_then(|end| unread.get(..=end)) else {
+            return Vec::new();
+        };
+
+        self.read_bytes += complete.len();
+        complete.lines().filter_map(Command::parse).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_reported_commands() {
+        assert_eq!(
+            Command::parse("2345\t1\tcargo build --release"),
+            Some(Command {
+                text: "cargo build --release".to_owned(),
+                duration: std::time::Duration::from_millis(2345),
+                exit_status: 1,
+            })
+        );
+        assert!(Command::parse("12\t0\t").is_none());
+        assert!(Command::parse("oops").is_none());
+    }
+
+    #[tokio::test]
+    async fn only_new_complete_lines_are_read() {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(16);
+        let mut commands = Commands {
+            state: crate::shared_state::SharedState::init(1, 1, protocol_tx)
+                .await
+                .unwrap(),
+            file: std::path::PathBuf::new(),
+            read_bytes: 0,
+        };
+
+        let mut report = "10\t0\tls\n20\t0\tpw".to_owned();
+        let texts = |commands: Vec<Command>| {
+            commands
+                .into_iter()
+                .map(|command| command.text)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(texts(commands.new_commands(&report)), vec!["ls"]);
+
+        report.push_str("d\n");
+        assert_eq!(texts(commands.new_commands(&report)), vec!["pwd"]);
+        assert!(commands.new_commands(&report).is_empty());
+
+        assert_eq!(texts(commands.new_commands("5\t0\ttop\n")), vec!["top"]);
+    }
+}
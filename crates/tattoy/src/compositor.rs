@@ -2,6 +2,26 @@
 use color_eyre::eyre::{ContextCompat as _, Result};
 use shadow_terminal::termwiz;
 
+/// Limits on which cells of the frame a tattoy is rendered into, based on the PTY's cell at the
+/// same position.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Mask {
+    /// Skip cells that have a background colour.
+    pub under_text_only: bool,
+    /// Skip cells that have any glyphs at all, so that the tattoy flows around the text.
+    pub only_in_blank_cells: bool,
+}
+
+impl Mask {
+    /// Whether the tattoy shouldn't be rendered over the PTY cell, `None` being no cell.
+    pub fn is_masked(self, maybe_pty_cell: Option<&termwiz::cell::Cell>) -> bool {
+        maybe_pty_cell.is_some_and(|cell| {
+            (self.under_text_only && Compositor::has_background(cell))
+                || (self.only_in_blank_cells && crate::tattoys::gpu::text_mask::is_text(cell))
+        })
+    }
+}
+
 /// Composite cells together, honouring alpha blending, text and pixels.
 #[derive(Default)]
 pub(crate) struct Compositor;
@@ -38,6 +58,19 @@ impl Compositor {
         format!("No {axis} coord ({coord}) for cell")
     }
 
+    /// Whether a cell contains text, rather than being empty or a pixel.
+    pub fn is_text(cell: &termwiz::cell::Cell) -> bool {
+        !cell
+            .str()
+            .chars()
+            .all(|character| character.is_whitespace() || character == '▀' || character == '▄')
+    }
+
+    /// Whether a cell has a background colour, like the coloured bars of TUI apps such as `htop`.
+    pub fn has_background(cell: &termwiz::cell::Cell) -> bool {
+        cell.attrs().background() != termwiz::color::ColorAttribute::Default
+    }
+
     /// Simply use the incoming cell's foreground colour for the base cell's foreground
     /// colour.
     pub fn composite_fg_colour_only(
@@ -45,11 +78,7 @@ impl Compositor {
         cell_above: &termwiz::cell::Cell,
         default_bg_colour: termwiz::color::SrgbaTuple,
     ) {
-        if base_cell
-            .str()
-            .chars()
-            .all(|character| character.is_whitespace() || character == '▀' || character == '▄')
-        {
+        if !Self::is_text(base_cell) {
             return;
         }
 
@@ -86,6 +115,70 @@ impl Compositor {
         }
     }
 
+    /// Blend the pixel(s) of the cell above into both the colours of a text cell, without
+    /// replacing its character. This lets pixel effects change how text looks whilst keeping it
+    /// readable.
+    pub fn tint_text_cell(
+        base_cell: &mut termwiz::cell::Cell,
+        cell_above: &termwiz::cell::Cell,
+        opacity: f32,
+        default_bg_colour: termwiz::color::SrgbaTuple,
+    ) {
+        let Some(colour) = Self::average_pixel_colour(cell_above) else {
+            return;
+        };
+
+        let mut blender = crate::blender::Blender::new(base_cell, default_bg_colour, opacity);
+        blender.blend(&crate::blender::Kind::Foreground, colour);
+        blender.blend(&crate::blender::Kind::Background, colour);
+    }
+
+    /// Blend the average colour of the cell above into just the background of a cell. Its
+    /// character and foreground are left untouched, so no glyphs are ever added to the frame.
+    pub fn tint_background(
+        base_cell: &mut termwiz::cell::Cell,
+        cell_above: &termwiz::cell::Cell,
+        opacity: f32,
+        default_bg_colour: termwiz::color::SrgbaTuple,
+    ) {
+        let Some(colour) = Self::average_pixel_colour(cell_above) else {
+            return;
+        };
+
+        let mut blender = crate::blender::Blender::new(base_cell, default_bg_colour, opacity);
+        blender.blend(&crate::blender::Kind::Background, colour);
+    }
+
+    /// The average colour of the 2 pixels of a pixel cell.
+    pub fn average_pixel_colour(cell: &termwiz::cell::Cell) -> Option<termwiz::color::SrgbaTuple> {
+        let maybe_top = crate::blender::Blender::extract_colour(cell.attrs().foreground());
+        let maybe_bottom = crate::blender::Blender::extract_colour(cell.attrs().background());
+        match (maybe_top, maybe_bottom) {
+            (Some(top), Some(bottom)) => Some(top.interpolate(bottom, 0.5)),
+            (Some(colour), None) | (None, Some(colour)) => Some(colour),
+            (None, None) => None,
+        }
+    }
+
+    /// Linearly blend between 2 RGBA pixels. An `amount` of `0.0` is entirely the `from` pixel and
+    /// `1.0` is entirely the `to` pixel.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "The blended channel is always clamped to within the range of a `u8`"
+    )]
+    pub fn crossfade_pixel(from: [u8; 4], to: [u8; 4], amount: f32) -> [u8; 4] {
+        let amount = amount.clamp(0.0, 1.0);
+        let mut pixel = [0; 4];
+        for ((channel, from_channel), to_channel) in pixel.iter_mut().zip(from).zip(to) {
+            let blended =
+                f32::from(to_channel).mul_add(amount, f32::from(from_channel) * (1.0 - amount));
+            *channel = blended.round().clamp(0.0, 255.0) as u8;
+        }
+        pixel
+    }
+
     /// Composite 2 cells together.
     pub fn composite_cells(
         composited_cell: &mut termwiz::cell::Cell,
@@ -101,11 +194,11 @@ impl Compositor {
         let is_pixel_onto_non_pixel = is_character_above_pixel && !is_composited_cell_pixel;
 
         if is_character_above_text || is_pixel_onto_non_pixel {
-            let character = character_above.chars().nth(0).unwrap_or(' ');
-            let attributes = cell_above.attrs().clone();
+            // The whole cell is copied, rather than just its first character, so that grapheme
+            // clusters like emoji ZWJ sequences and combining marks are kept intact.
             let foreground = composited_cell.attrs().foreground();
             let background = composited_cell.attrs().background();
-            *composited_cell = termwiz::cell::Cell::new(character, attributes);
+            cell_above.clone_into(composited_cell);
             composited_cell.attrs_mut().set_foreground(foreground);
             composited_cell.attrs_mut().set_background(background);
         }
@@ -122,6 +215,67 @@ impl Compositor {
         }
     }
 
+    /// Blank the cells that are covered by the wide characters, like CJK and emoji, that a layer
+    /// has just been composited with. Otherwise the cells from the layers below would be drawn
+    /// over the right half of the wide characters.
+    pub fn clear_wide_continuations(
+        frame_line: &mut [termwiz::cell::Cell],
+        layer_line: &[termwiz::cell::Cell],
+        unicode: crate::unicode::Config,
+    ) {
+        for (x, layer_cell) in layer_line.iter().enumerate() {
+            if !Self::is_text(layer_cell) {
+                continue;
+            }
+            let Some(frame_cell) = frame_line.get(x) else {
+                break;
+            };
+            if frame_cell.str() != layer_cell.str() {
+                // The layer was masked, or clipped, here.
+                continue;
+            }
+
+            let width = unicode.width(frame_cell.str());
+            let attributes = frame_cell.attrs().clone();
+            for covered in frame_line
+                .iter_mut()
+                .skip(x + 1)
+                .take(width.saturating_sub(1))
+            {
+                *covered = termwiz::cell::Cell::new(' ', attributes.clone());
+            }
+        }
+    }
+
+    /// Remove the wide characters that have had a cell that they cover drawn on by a layer above.
+    /// Terminals can't draw half a wide character, so they're replaced with a space, which keeps
+    /// everything after them lined up with the grid. This must be done after every layer has been
+    /// composited, see `clear_wide_continuations()`.
+    pub fn remove_overlapped_wide_characters(
+        frame_line: &mut [termwiz::cell::Cell],
+        unicode: crate::unicode::Config,
+    ) {
+        for x in 0..frame_line.len() {
+            let Some(cell) = frame_line.get(x) else {
+                break;
+            };
+            if !Self::is_text(cell) {
+                continue;
+            }
+            let width = unicode.width(cell.str());
+            let is_overlapped = frame_line
+                .iter()
+                .skip(x + 1)
+                .take(width.saturating_sub(1))
+                .any(|covered| !covered.str().trim().is_empty());
+            if is_overlapped {
+                if let Some(wide) = frame_line.get_mut(x) {
+                    *wide = termwiz::cell::Cell::new(' ', wide.attrs().clone());
+                }
+            }
+        }
+    }
+
     /// Automatically adjust text contrast.
     pub fn auto_text_contrast(
         composited_cell: &mut termwiz::cell::Cell,
@@ -169,3 +323,94 @@ impl Compositor {
         }
     }
 }
+
+#[cfg(test)]
+#[expect(clippy::indexing_slicing, reason = "Tests aren't so strict")]
+mod test {
+    use super::*;
+
+    #[test]
+    fn masks_skip_coloured_and_glyph_cells() {
+        let mut attributes = termwiz::cell::CellAttributes::default();
+        attributes.set_background(termwiz::color::ColorAttribute::PaletteIndex(4));
+        let coloured = termwiz::cell::Cell::new(' ', attributes);
+        let glyph = termwiz::cell::Cell::new('a', termwiz::cell::CellAttributes::default());
+        let blank = termwiz::cell::Cell::new(' ', termwiz::cell::CellAttributes::default());
+
+        let under_text_only = Mask {
+            under_text_only: true,
+            ..Mask::default()
+        };
+        assert!(under_text_only.is_masked(Some(&coloured)));
+        assert!(!under_text_only.is_masked(Some(&glyph)));
+
+        let only_in_blank_cells = Mask {
+            only_in_blank_cells: true,
+            ..Mask::default()
+        };
+        assert!(only_in_blank_cells.is_masked(Some(&glyph)));
+        assert!(!only_in_blank_cells.is_masked(Some(&coloured)));
+
+        for mask in [Mask::default(), under_text_only, only_in_blank_cells] {
+            assert!(!mask.is_masked(Some(&blank)));
+            assert!(!mask.is_masked(None));
+        }
+    }
+
+    #[test]
+    fn grapheme_clusters_are_composited_whole() {
+        let mut frame_cell = termwiz::cell::Cell::blank();
+        let cell_above = termwiz::cell::Cell::new_grapheme(
+            "👩\u{200d}🚀",
+            termwiz::cell::CellAttributes::default(),
+            None,
+        );
+        Compositor::composite_cells(
+            &mut frame_cell,
+            &cell_above,
+            1.0,
+            termwiz::color::SrgbaTuple::default(),
+        );
+        assert_eq!(frame_cell.str(), "👩\u{200d}🚀");
+    }
+
+    #[test]
+    fn background_only_compositing_never_adds_glyphs() {
+        let mut frame_cell = termwiz::cell::Cell::blank();
+        let mut attributes = termwiz::cell::CellAttributes::default();
+        let green = termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(
+            termwiz::color::SrgbaTuple(0.0, 1.0, 0.0, 1.0),
+        );
+        attributes.set_foreground(green);
+        attributes.set_background(green);
+        let pixel = termwiz::cell::Cell::new('▀', attributes);
+        Compositor::tint_background(
+            &mut frame_cell,
+            &pixel,
+            1.0,
+            termwiz::color::SrgbaTuple::default(),
+        );
+        assert_eq!(frame_cell.str(), " ");
+        assert_eq!(frame_cell.attrs().background(), green);
+    }
+
+    #[test]
+    fn wide_characters_line_up_with_the_grid() {
+        let unicode = crate::unicode::Config::default();
+        let attributes = termwiz::cell::CellAttributes::default();
+        let wide = termwiz::cell::Cell::new_grapheme("刺", attributes.clone(), None);
+        let text = |character| termwiz::cell::Cell::new(character, attributes.clone());
+
+        let mut frame_line = vec![text('a'), text('b'), text('c')];
+        let layer_line = vec![wide.clone(), text(' '), text(' ')];
+        frame_line[0] = wide.clone();
+        Compositor::clear_wide_continuations(&mut frame_line, &layer_line, unicode);
+        assert_eq!(frame_line[1].str(), " ");
+        assert_eq!(frame_line[2].str(), "c");
+
+        frame_line[1] = text('x');
+        Compositor::remove_overlapped_wide_characters(&mut frame_line, unicode);
+        assert_eq!(frame_line[0].str(), " ");
+        assert_eq!(frame_line[1].str(), "x");
+    }
+}
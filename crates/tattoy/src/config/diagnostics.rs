@@ -0,0 +1,280 @@
+//! Human-friendly diagnostics for the main config file.
+//!
+//! Serde only tells us about the parts of the config that can't be parsed at all. But it happily
+//! ignores keys that it doesn't know about, so a typo like `opactiy` silently does nothing. So
+//! once the config has parsed, we also check it against the config's JSON schema.
+
+/// How similar, by Jaro-Winkler distance, an unknown key has to be to a real key for the real key
+/// to be suggested.
+const SUGGESTION_SIMILARITY: f64 = 0.8;
+
+/// Keys whose ranges are already checked, and reported, elsewhere. Layers are clamped, with an
+/// explanation, by `Config::validate_layers()`.
+const CHECKED_ELSEWHERE: &[&str] = &["layer"];
+
+/// The JSON schema of the main config file.
+pub(crate) fn schema() -> schemars::Schema {
+    schemars::schema_for!(super::main::Config)
+}
+
+/// Explain why the config couldn't be parsed, pointing at the offending part of the file.
+pub(crate) fn describe_parse_error(
+    path: &std::path::Path,
+    data: &str,
+    error: &toml::de::Error,
+) -> String {
+    let message = error.message().trim_end();
+    let Some(span) = error.span() else {
+        return format!("{}: {message}", path.display());
+    };
+
+    let (line_number, column, line) = locate(data, span.start);
+    let width = data
+        .get(span)
+        .and_then(|spanned| spanned.lines().next())
+        .map_or(1, |spanned| spanned.chars().count())
+        .max(1);
+    let gutter = " ".repeat(line_number.to_string().len());
+    format!(
+        "{}:{line_number}:{column}: {message}\n\
+        {gutter} |\n\
+        {line_number} | {line}\n\
+        {gutter} | {}{}",
+        path.display(),
+        " ".repeat(column.saturating_sub(1)),
+        "^".repeat(width)
+    )
+}
+
+/// The line number, column and contents of the line at a byte offset. Line numbers and columns
+/// start at 1.
+fn locate(data: &str, offset: usize) -> (usize, usize, &str) {
+    let before = data.get(..offset).unwrap_or(data);
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let line_number = before.matches('\n').count() + 1;
+    let column = before.get(line_start..).unwrap_or_default().chars().count() + 1;
+    let line = data
+        .get(line_start..)
+        .and_then(|rest| rest.lines().next())
+        .unwrap_or_default();
+    (line_number, column, line)
+}
+
+/// Find the parts of a config that parse, but that are probably mistakes: keys that Tattoy
+/// doesn't know about and numbers outside of their valid range.
+pub(crate) fn check(data: &str) -> Vec<String> {
+    let Ok(table) = data.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+    let schema = schema();
+    let root = schema.as_value();
+    let mut checker = Checker {
+        definitions: root.get("$defs"),
+        messages: Vec::new(),
+    };
+    checker.check_table("", &table, root);
+
+    // Scenes are written just like the rest of the config, so they're checked against the whole
+    // config's schema.
+    if let Some(scenes) = table.get("scenes").and_then(toml::Value::as_table) {
+        for (name, scene) in scenes {
+            if let Some(scene) = scene.as_table() {
+                checker.check_table(&format!("scenes.{name}"), scene, root);
+            }
+        }
+    }
+    checker.messages
+}
+
+/// Walks a config alongside its schema.
+struct Checker<'schema> {
+    /// The schema's shared definitions, that `$ref`s point to.
+    definitions: Option<&'schema serde_json::Value>,
+    /// Everything that looks wrong with the config.
+    messages: Vec<String>,
+}
+
+impl<'schema> Checker<'schema> {
+    /// Follow references, and see through `Option`s, to the schema that actually describes a
+    /// value.
+    fn resolve(&self, schema: &'schema serde_json::Value) -> &'schema serde_json::Value {
+        if let Some(reference) = schema.get("$ref").and_then(serde_json::Value::as_str) {
+            let name = reference.trim_start_matches("#/$defs/");
+            if let Some(definition) = self
+                .definitions
+                .and_then(|definitions| definitions.get(name))
+            {
+                return self.resolve(definition);
+            }
+        }
+
+        if let Some(variants) = schema.get("anyOf").and_then(serde_json::Value::as_array) {
+            let not_null = variants.iter().find(|variant| {
+                variant.get("type").and_then(serde_json::Value::as_str) != Some("null")
+            });
+            if let Some(variant) = not_null {
+                return self.resolve(variant);
+            }
+        }
+
+        schema
+    }
+
+    /// Check every key of a table.
+    fn check_table(&mut self, path: &str, table: &toml::Table, schema: &'schema serde_json::Value) {
+        let Some(properties) = schema
+            .get("properties")
+            .and_then(serde_json::Value::as_object)
+        else {
+            return;
+        };
+
+        for (key, value) in table {
+            let key_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+            match properties.get(key) {
+                Some(property) => {
+                    if !CHECKED_ELSEWHERE.contains(&key.as_str()) {
+                        self.check_value(&key_path, value, property);
+                    }
+                }
+                None => self
+                    .messages
+                    .push(unknown_key(&key_path, key, properties.keys())),
+            }
+        }
+    }
+
+    /// Check a single value, and anything inside it.
+    fn check_value(&mut self, path: &str, value: &toml::Value, schema: &'schema serde_json::Value) {
+        let schema = self.resolve(schema);
+        match value {
+            toml::Value::Table(table) => self.check_table(path, table, schema),
+            toml::Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        self.check_value(&format!("{path}.{index}"), item, item_schema);
+                    }
+                }
+            }
+            toml::Value::Integer(integer) => {
+                #[expect(
+                    clippy::as_conversions,
+                    clippy::cast_precision_loss,
+                    reason = "Config integers are nowhere near big enough to lose precision"
+                )]
+                self.check_range(path, *integer as f64, schema);
+            }
+            toml::Value::Float(float) => self.check_range(path, *float, schema),
+            toml::Value::String(_) | toml::Value::Boolean(_) | toml::Value::Datetime(_) => (),
+        }
+    }
+
+    /// Check that a number is within the range that its schema allows.
+    fn check_range(&mut self, path: &str, number: f64, schema: &serde_json::Value) {
+        let minimum = schema.get("minimum").and_then(serde_json::Value::as_f64);
+        let maximum = schema.get("maximum").and_then(serde_json::Value::as_f64);
+        let is_too_small = minimum.is_some_and(|minimum| number < minimum);
+        let is_too_big = maximum.is_some_and(|maximum| number > maximum);
+        if !is_too_small && !is_too_big {
+            return;
+        }
+
+        let range = match (minimum, maximum) {
+            (Some(minimum), Some(maximum)) => format!("between {minimum} and {maximum}"),
+            (Some(minimum), None) => format!("at least {minimum}"),
+            (None, Some(maximum)) => format!("at most {maximum}"),
+            (None, None) => return,
+        };
+        self.messages
+            .push(format!("`{path}` is {number}, but it must be {range}"));
+    }
+}
+
+/// Describe a key that Tattoy doesn't know about, suggesting the key that was most likely meant.
+fn unknown_key<'key>(path: &str, key: &str, known: impl Iterator<Item = &'key String>) -> String {
+    let suggestion = known
+        .map(|candidate| (strsim::jaro_winkler(key, candidate), candidate))
+        .filter(|(similarity, _)| *similarity >= SUGGESTION_SIMILARITY)
+        .max_by(|left, right| left.0.total_cmp(&right.0));
+
+    match suggestion {
+        Some((_, candidate)) => {
+            format!("Unknown config key `{path}`, did you mean `{candidate}`?")
+        }
+        None => format!("Unknown config key `{path}`"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn the_schema_describes_the_config() {
+        let schema = schema();
+        let properties = schema.get("properties").unwrap();
+        assert!(properties.get("minimap").is_some());
+        assert!(properties.get("keybindings").is_some());
+    }
+
+    #[test]
+    fn the_default_config_has_no_warnings() {
+        assert_eq!(
+            check(include_str!("../../default_config.toml")),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn unknown_keys_get_suggestions() {
+        let messages = check("frame_rat = 30\n[minimap]\nenabeld = true\nzzz = 1\n");
+        assert_eq!(
+            messages,
+            [
+                "Unknown config key `frame_rat`, did you mean `frame_rate`?",
+                "Unknown config key `minimap.enabeld`, did you mean `enabled`?",
+                "Unknown config key `minimap.zzz`",
+            ]
+        );
+    }
+
+    #[test]
+    fn out_of_range_values_are_reported() {
+        let messages = check("[bloom]\nopacity = 1.5\n[[shaders]]\nopacity = -1\nlayer = 5000\n");
+        assert_eq!(
+            messages,
+            [
+                "`bloom.opacity` is 1.5, but it must be between 0 and 1",
+                "`shaders.0.opacity` is -1, but it must be between 0 and 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn scenes_are_checked_like_the_rest_of_the_config() {
+        let messages = check("[scenes.focus]\nminimap = { enabld = false }\n");
+        assert_eq!(
+            messages,
+            ["Unknown config key `scenes.focus.minimap.enabld`, did you mean `enabled`?"]
+        );
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_problem() {
+        let data = "frame_rate = 30\n[bloom]\nopacity = \"lots\"\n";
+        let error = toml::from_str::<crate::config::main::Config>(data).unwrap_err();
+        let described = describe_parse_error(std::path::Path::new("tattoy.toml"), data, &error);
+        let mut lines = described.lines();
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with("tattoy.toml:3:11: invalid type"));
+        assert_eq!(lines.next().unwrap(), "  |");
+        assert_eq!(lines.next().unwrap(), "3 | opacity = \"lots\"");
+        assert_eq!(lines.next().unwrap(), "  |           ^^^^^^");
+    }
+}
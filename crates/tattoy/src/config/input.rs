@@ -3,7 +3,9 @@
 use shadow_terminal::termwiz;
 
 /// The user config for defining keybindings.
-#[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, Debug, Clone)]
+#[derive(
+    serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq, Debug, Clone,
+)]
 pub(crate) struct KeybindingConfigRaw {
     /// The modifier keys, like `CTRL`, `SHIFT`, etc.
     pub mods: Option<String>,
@@ -12,7 +14,9 @@ pub(crate) struct KeybindingConfigRaw {
 }
 
 /// All the possible actions a user can trigger in Tattoy
-#[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, Debug, Clone, Hash)]
+#[derive(
+    serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq, Debug, Clone, Hash,
+)]
 #[serde(rename_all = "snake_case")]
 pub(crate) enum KeybindingAction {
     /// Toggle Tattoy's rendering. Doesn't effect the TTY.
@@ -31,6 +35,30 @@ pub(crate) enum KeybindingAction {
     ShaderPrev,
     /// Cycle to next shader in user's config shader directory.
     ShaderNext,
+    /// Enter/exit the Vim-style copy mode.
+    ToggleCopyMode,
+    /// Split the terminal by adding a new pane.
+    SplitPane,
+    /// Move input focus to the next pane.
+    FocusNextPane,
+    /// Start or pause the timer.
+    TimerToggle,
+    /// Reset the timer back to the start of a work session.
+    TimerReset,
+    /// Lock the terminal until the passphrase is typed.
+    LockScreen,
+    /// Temporarily show the secrets that are being redacted.
+    RevealSecrets,
+    /// Switch to the next scene in the config's `[scenes]`.
+    NextScene,
+    /// Turn the colour blindness simulation, or assistance, on or off.
+    ToggleColourBlindness,
+    /// Detach from the named session, from `tattoy attach`, leaving it running.
+    Detach,
+    /// Turn stream mode, from the config's `[stream_mode]`, on or off.
+    ToggleStreamMode,
+    /// Show/hide the heatmap of where the screen changes.
+    ToggleHeatmap,
 }
 
 /// All the active user-configured keybindings.
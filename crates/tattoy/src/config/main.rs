@@ -7,7 +7,7 @@ use shadow_terminal::termwiz;
 
 /// A copy of the default config file. It gets copied to the user's config folder the first time
 /// they start Tattoy.
-static DEFAULT_CONFIG: &str = include_str!("../../default_config.toml");
+pub(crate) static DEFAULT_CONFIG: &str = include_str!("../../default_config.toml");
 
 /// Bundle an example shader with Tattoy.
 static EXAMPLE_SHADER: &str = include_str!("../tattoys/gpu/shaders/soft_shadows.glsl");
@@ -28,7 +28,16 @@ pub const SHADER_DIRECTORY_NAME: &str = "shaders";
 pub const CURSOR_SHADER_DIRECTORY_NAME: &str = "shaders/cursors";
 
 /// The valid log levels. Based on our `tracing` crate.
-#[derive(serde::Serialize, serde::Deserialize, clap::ValueEnum, Debug, Clone, PartialEq, Eq)]
+#[derive(
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    clap::ValueEnum,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+)]
 #[serde(rename_all = "snake_case")]
 pub(crate) enum LogLevel {
     /// Error
@@ -50,7 +59,7 @@ pub(crate) enum LogLevel {
     clippy::unsafe_derive_deserialize,
     reason = "Are the unsafe methods on the `f32`s?"
 )]
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
 #[serde(default)]
 pub(crate) struct Config {
     /// The command to run in the underlying PTY, defaults to the users shell as dedfined in the
@@ -62,30 +71,145 @@ pub(crate) struct Config {
     pub log_path: std::path::PathBuf,
     /// Keybindings
     pub keybindings: super::input::KeybindingsRaw,
+    /// The kitty keyboard protocol
+    pub kitty_keyboard: crate::kitty_keyboard::Config,
     /// Target frame rate
     pub frame_rate: u32,
+    /// The most times per second to write to the user's terminal. `0` means no limit.
+    pub max_output_rate: u32,
     /// Whether to show the little tattoy indicator in the top-right of the terminal.
     pub show_tattoy_indicator: bool,
     /// Whether to show the startup logo.
     pub show_startup_logo: bool,
+    /// Calm animations down for users that are sensitive to motion. When it's not set, the
+    /// operating system's preference is used.
+    pub reduced_motion: Option<bool>,
     /// The size of the scrollback. Lines after this will be removed.
     pub scrollback_size: u32,
+    /// The real width-to-height ratio of the pixels that shaders render, which are half a cell
+    /// tall. It's `1.0` when a cell is exactly twice as tall as it's wide. Set by `tattoy
+    /// calibrate`.
+    #[schemars(range(min = 0.25, max = 4.0))]
+    pub pixel_aspect: f32,
+    /// The URL of the curated index of shader packs and presets that `tattoy install` uses.
+    pub package_index: String,
+    /// What tattoys do when the renderer can't keep up with their frames.
+    pub backpressure: crate::backpressure::Config,
+    /// Pausing expensive tattoys during bursts of output from the PTY.
+    pub output_burst: crate::output_burst::Config,
+    /// Setting the title of the user's terminal.
+    pub title: crate::title::Config,
+    /// Saving the scrollback to disk.
+    pub scrollback: crate::scrollback_log::Config,
+    /// Saving the session when Tattoy exits, for `tattoy --restore`.
+    pub session: crate::session::Config,
+    /// Named sessions, for `tattoy attach`.
+    pub attach: crate::attach::Config,
+    /// Serving metrics about Tattoy's overhead.
+    pub metrics: crate::metrics::Config,
+    /// The HTTP API for controlling Tattoy from other tools.
+    pub remote_control: crate::remote_control::Config,
+    /// Tweaking tattoys in real time with a MIDI controller.
+    pub controllers: crate::controllers::Config,
+    /// A summary of the session when Tattoy exits.
+    pub exit_summary: crate::exit_summary::Config,
+    /// Noticing when the renderer gets stuck.
+    pub watchdog: crate::watchdog::Config,
     /// Colour grading
     pub color: Color,
+    /// Settings for rendering every tattoy.
+    pub render: Render,
+    /// Colour correction for individual tattoys, by their ID, eg: `shader` or `minimap`.
+    pub colour_correction:
+        std::collections::BTreeMap<String, crate::colour_correction::Adjustments>,
+    /// Keeping decorative characters out of the terminal, for screen readers.
+    pub screen_reader: crate::screen_reader::Config,
+    /// Simulating, or assisting with, colour blindness.
+    pub colour_blindness: crate::colour_blindness::Config,
+    /// Warming the colours of the whole terminal, eg: at night.
+    pub night_light: crate::night_light::Config,
     /// Auto adjusting of text contrast
     pub text_contrast: TextContrast,
+    /// How the widths of Unicode characters are measured
+    pub unicode: crate::unicode::Config,
     /// Plugins config
     pub plugins: Vec<crate::tattoys::plugins::Config>,
+    /// Named events that are broadcast when the PTY's output matches a regex.
+    pub events: Vec<crate::output_events::Config>,
+    /// Shell commands and webhooks that are run when events happen.
+    pub hooks: Vec<crate::hooks::Config>,
+    /// Sounds that are played when events happen, like keypresses.
+    pub sounds: crate::sounds::Config,
     /// The minimap
     pub minimap: crate::tattoys::minimap::Config,
     /// The shaders
     pub shader: crate::tattoys::shader::Config,
+    /// Extra shaders, each rendered to their own layer.
+    pub shaders: Vec<crate::tattoys::shader::Config>,
+    /// The built-in CRT effect
+    pub crt: crate::tattoys::crt::Config,
+    /// Glowing bright text
+    pub bloom: crate::tattoys::bloom::Config,
+    /// Falling snow, rain or leaves
+    pub weather: crate::tattoys::weather::Config,
+    /// Celebratory fireworks
+    pub fireworks: crate::tattoys::fireworks::Config,
+    /// The Pomodoro timer
+    pub timer: crate::tattoys::timer::Config,
+    /// The clock and system stats widget
+    pub widget: crate::tattoys::widget::Config,
+    /// The weather widget
+    pub weather_widget: crate::tattoys::weather_widget::Config,
+    /// The git status watermark
+    pub git_watermark: crate::tattoys::git_watermark::Config,
+    /// Confetti and sparkles for celebrating
+    pub celebration: crate::tattoys::celebration::Config,
+    /// Inline images, from iTerm2's image protocol
+    pub inline_images: crate::tattoys::inline_images::Config,
+    /// The status line
+    pub status_line: crate::tattoys::status_line::Config,
+    /// The screensaver
+    pub screensaver: crate::tattoys::screensaver::Config,
+    /// The lock screen
+    pub lock: crate::tattoys::lock::Config,
+    /// Confirming suspicious pastes
+    pub paste_guard: crate::tattoys::paste_guard::Config,
+    /// Redacting secrets
+    pub redaction: crate::tattoys::redaction::Config,
+    /// Showing which cells change
+    pub repaints: crate::tattoys::repaints::Config,
+    /// The heatmap of where the screen changes
+    pub heatmap: crate::tattoys::heatmap::Config,
+    /// The profile for live-streaming, switched on with the `toggle_stream_mode` keybinding.
+    pub stream_mode: crate::stream_mode::Config,
+    /// Guides at chosen columns
+    pub column_guides: crate::tattoys::column_guides::Config,
+    /// Durations of finished commands
+    pub command_durations: crate::tattoys::command_durations::Config,
+    /// Separators between commands
+    pub command_separators: crate::tattoys::command_separators::Config,
+    /// The progress bar
+    pub progress_bar: crate::tattoys::progress_bar::Config,
+    /// The visual bell
+    pub visual_bell: crate::tattoys::visual_bell::Config,
+    /// The typing speed widget
+    pub typing_speed: crate::tattoys::typing_speed::Config,
+    /// The highlight of the cursor's row
+    pub cursor_line: crate::tattoys::cursor_line::Config,
     /// The animated Cursor
     pub animated_cursor: crate::tattoys::animated_cursor::Config,
     /// Background command
     pub bg_command: crate::tattoys::bg_command::Config,
     /// Notifications
     pub notifications: crate::tattoys::notifications::main::Config,
+    /// Split panes
+    pub panes: crate::panes::manager::Config,
+    /// Named sets of settings that are applied on top of the rest of the config, see
+    /// `crate::scenes`.
+    #[schemars(
+        with = "std::collections::BTreeMap<String, std::collections::BTreeMap<String, serde_json::Value>>"
+    )]
+    pub scenes: std::collections::BTreeMap<String, toml::Table>,
 }
 
 impl Default for Config {
@@ -112,24 +236,77 @@ impl Default for Config {
             log_level: LogLevel::Off,
             log_path,
             frame_rate: 30,
+            max_output_rate: 60,
             keybindings: super::input::KeybindingsRaw::new(),
+            kitty_keyboard: crate::kitty_keyboard::Config::default(),
             show_tattoy_indicator: true,
+            reduced_motion: None,
             show_startup_logo: true,
             scrollback_size: 1000,
+            pixel_aspect: 1.0,
+            package_index: crate::packages::DEFAULT_INDEX.to_owned(),
+            backpressure: crate::backpressure::Config::default(),
+            output_burst: crate::output_burst::Config::default(),
+            title: crate::title::Config::default(),
+            scrollback: crate::scrollback_log::Config::default(),
+            session: crate::session::Config::default(),
+            attach: crate::attach::Config::default(),
+            metrics: crate::metrics::Config::default(),
+            remote_control: crate::remote_control::Config::default(),
+            controllers: crate::controllers::Config::default(),
+            exit_summary: crate::exit_summary::Config::default(),
+            watchdog: crate::watchdog::Config::default(),
             color: Color::default(),
+            render: Render::default(),
+            colour_correction: std::collections::BTreeMap::new(),
+            screen_reader: crate::screen_reader::Config::default(),
+            colour_blindness: crate::colour_blindness::Config::default(),
+            night_light: crate::night_light::Config::default(),
             text_contrast: TextContrast::default(),
+            unicode: crate::unicode::Config::default(),
             plugins: Vec::default(),
+            events: crate::output_events::default_events(),
+            hooks: Vec::default(),
+            sounds: crate::sounds::Config::default(),
             minimap: crate::tattoys::minimap::Config::default(),
             shader: crate::tattoys::shader::Config::default(),
+            shaders: Vec::default(),
+            crt: crate::tattoys::crt::Config::default(),
+            bloom: crate::tattoys::bloom::Config::default(),
+            weather: crate::tattoys::weather::Config::default(),
+            fireworks: crate::tattoys::fireworks::Config::default(),
+            timer: crate::tattoys::timer::Config::default(),
+            widget: crate::tattoys::widget::Config::default(),
+            weather_widget: crate::tattoys::weather_widget::Config::default(),
+            git_watermark: crate::tattoys::git_watermark::Config::default(),
+            celebration: crate::tattoys::celebration::Config::default(),
+            inline_images: crate::tattoys::inline_images::Config::default(),
+            status_line: crate::tattoys::status_line::Config::default(),
+            screensaver: crate::tattoys::screensaver::Config::default(),
+            lock: crate::tattoys::lock::Config::default(),
+            paste_guard: crate::tattoys::paste_guard::Config::default(),
+            redaction: crate::tattoys::redaction::Config::default(),
+            repaints: crate::tattoys::repaints::Config::default(),
+            heatmap: crate::tattoys::heatmap::Config::default(),
+            stream_mode: crate::stream_mode::Config::default(),
+            column_guides: crate::tattoys::column_guides::Config::default(),
+            command_durations: crate::tattoys::command_durations::Config::default(),
+            command_separators: crate::tattoys::command_separators::Config::default(),
+            progress_bar: crate::tattoys::progress_bar::Config::default(),
+            visual_bell: crate::tattoys::visual_bell::Config::default(),
+            typing_speed: crate::tattoys::typing_speed::Config::default(),
+            cursor_line: crate::tattoys::cursor_line::Config::default(),
             animated_cursor: crate::tattoys::animated_cursor::Config::default(),
             bg_command: crate::tattoys::bg_command::Config::default(),
             notifications: crate::tattoys::notifications::main::Config::default(),
+            panes: crate::panes::manager::Config::default(),
+            scenes: std::collections::BTreeMap::new(),
         }
     }
 }
 
 /// Final colour grading for the whole terminal render.
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
 pub(crate) struct Color {
     /// Saturation
     pub saturation: f32,
@@ -149,8 +326,57 @@ impl Default for Color {
     }
 }
 
+/// Settings for rendering every tattoy.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Render {
+    /// Rows and columns at the edges of the terminal that decorative tattoys never render to.
+    pub margins: Margins,
+    /// Repaint whole runs of text around changed cells, so that terminals that render ligatures
+    /// can shape them properly.
+    pub ligature_safe: bool,
+    /// The glyphs that pixels are drawn with.
+    pub pixel_glyphs: crate::pixel_encoders::PixelGlyphs,
+    /// The IDs of the tattoys that are drawn with braille dots, see `crate::braille`.
+    pub braille: Vec<String>,
+    /// How long, in milliseconds, the user's terminal has to stay the same size after a resize
+    /// before the tattoys are resized.
+    pub resize_debounce: u64,
+    /// The most GPU memory, in megabytes, that shaders should use between them. 0 means that
+    /// there's no limit.
+    pub gpu_memory_budget: u64,
+}
+
+impl Default for Render {
+    fn default() -> Self {
+        Self {
+            margins: Margins::default(),
+            ligature_safe: false,
+            pixel_glyphs: crate::pixel_encoders::PixelGlyphs::default(),
+            braille: Vec::new(),
+            resize_debounce: 150,
+            gpu_memory_budget: 512,
+        }
+    }
+}
+
+/// The number of cells at each edge of the terminal that are kept clean of decorations, eg: for
+/// a prompt framework's status bar.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub(crate) struct Margins {
+    /// Rows at the top.
+    pub top: u16,
+    /// Rows at the bottom.
+    pub bottom: u16,
+    /// Columns on the left.
+    pub left: u16,
+    /// Columns on the right.
+    pub right: u16,
+}
+
 /// Config for auto adjusting text contrast.
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
 pub(crate) struct TextContrast {
     /// Whether it's enabled
     pub enabled: bool,
@@ -169,6 +395,46 @@ impl Default for TextContrast {
         }
     }
 }
+
+/// The `enabled` setting of every tattoy that the config can turn on and off, by the tattoy's
+/// ID. It's a macro so that the same list can be borrowed both immutably and mutably.
+macro_rules! enabled_setting {
+    ($config:expr, $id:expr, $($borrow:tt)+) => {
+        match $id {
+            "notifications" => Some($($borrow)+ $config.notifications.enabled),
+            "minimap" => Some($($borrow)+ $config.minimap.enabled),
+            "shader" => Some($($borrow)+ $config.shader.enabled),
+            "crt" => Some($($borrow)+ $config.crt.enabled),
+            "bloom" => Some($($borrow)+ $config.bloom.enabled),
+            "weather" => Some($($borrow)+ $config.weather.enabled),
+            "fireworks" => Some($($borrow)+ $config.fireworks.enabled),
+            "timer" => Some($($borrow)+ $config.timer.enabled),
+            "widget" => Some($($borrow)+ $config.widget.enabled),
+            "weather_widget" => Some($($borrow)+ $config.weather_widget.enabled),
+            "git_watermark" => Some($($borrow)+ $config.git_watermark.enabled),
+            "celebration" => Some($($borrow)+ $config.celebration.enabled),
+            "inline_images" => Some($($borrow)+ $config.inline_images.enabled),
+            "status_line" => Some($($borrow)+ $config.status_line.enabled),
+            "screensaver" => Some($($borrow)+ $config.screensaver.enabled),
+            "lock" => Some($($borrow)+ $config.lock.enabled),
+            "paste_guard" => Some($($borrow)+ $config.paste_guard.enabled),
+            "redaction" => Some($($borrow)+ $config.redaction.enabled),
+            "repaints" => Some($($borrow)+ $config.repaints.enabled),
+            "heatmap" => Some($($borrow)+ $config.heatmap.enabled),
+            "column_guides" => Some($($borrow)+ $config.column_guides.enabled),
+            "command_durations" => Some($($borrow)+ $config.command_durations.enabled),
+            "command_separators" => Some($($borrow)+ $config.command_separators.enabled),
+            "progress_bar" => Some($($borrow)+ $config.progress_bar.enabled),
+            "visual_bell" => Some($($borrow)+ $config.visual_bell.enabled),
+            "typing_speed" => Some($($borrow)+ $config.typing_speed.enabled),
+            "cursor_line" => Some($($borrow)+ $config.cursor_line.enabled),
+            "animated_cursor" => Some($($borrow)+ $config.animated_cursor.enabled),
+            "bg_command" => Some($($borrow)+ $config.bg_command.enabled),
+            _ => None,
+        }
+    };
+}
+
 impl Config {
     /// Canonical path to the config directory.
     pub async fn directory(
@@ -254,7 +520,49 @@ impl Config {
         match result {
             Ok(data) => {
                 tracing::trace!("Using config file:\n{data}");
-                let config = toml::from_str::<Self>(&data)?;
+                let mut config = match toml::from_str::<Self>(&data) {
+                    Ok(config) => config,
+                    Err(error) => color_eyre::eyre::bail!(
+                        super::diagnostics::describe_parse_error(&config_path, &data, &error)
+                    ),
+                };
+                if let Some(scene) = state.scene.read().await.clone() {
+                    match crate::scenes::apply(&data, &scene)? {
+                        Some(with_scene) => config = with_scene,
+                        None => tracing::warn!("There's no `{scene}` scene in the config"),
+                    }
+                }
+                let toggles = state.tattoy_toggles.read().await.clone();
+                for (id, is_enabled) in toggles {
+                    if let Some(enabled) = config.tattoy_enabled_mut(&id) {
+                        *enabled = is_enabled;
+                    }
+                }
+                if *state.is_stream_mode.read().await {
+                    crate::stream_mode::apply(&mut config);
+                }
+                for message in super::diagnostics::check(&data) {
+                    tracing::warn!("{message}");
+                    state
+                        .send_notification(
+                            "Config warning",
+                            crate::tattoys::notifications::message::Level::Warn,
+                            Some(message),
+                            false,
+                        )
+                        .await;
+                }
+                for message in config.validate_layers() {
+                    tracing::warn!("{message}");
+                    state
+                        .send_notification(
+                            "Invalid layer",
+                            crate::tattoys::notifications::message::Level::Warn,
+                            Some(message),
+                            false,
+                        )
+                        .await;
+                }
                 Self::load_keybindings(state, &config).await?;
                 Ok(config)
             }
@@ -268,6 +576,164 @@ impl Config {
         }
     }
 
+    /// Get the config for a shader. Index `0` is the `[shader]` section, higher indexes are the
+    /// entries of the `[[shaders]]` array.
+    pub fn shader_at(&self, index: usize) -> Option<&crate::tattoys::shader::Config> {
+        if index == 0 {
+            Some(&self.shader)
+        } else {
+            self.shaders.get(index - 1)
+        }
+    }
+
+    /// Every configured shader, in the order used by `Self::shader_at`.
+    pub fn all_shaders(&self) -> impl Iterator<Item = &crate::tattoys::shader::Config> {
+        core::iter::once(&self.shader).chain(&self.shaders)
+    }
+
+    /// Whether the config enables a tattoy, by the tattoy's ID. `None` is for tattoys that are
+    /// always running, or that can't be turned on and off by the config.
+    pub fn is_tattoy_enabled(&self, id: &str) -> Option<bool> {
+        if let Some(index) = id.strip_prefix("shader_") {
+            return Some(
+                self.shader_at(index.parse().ok()?)
+                    .is_some_and(|shader| shader.enabled),
+            );
+        }
+
+        enabled_setting!(self, id, &).copied()
+    }
+
+    /// The setting that enables a tattoy, by the tattoy's ID, so that it can be changed whilst
+    /// Tattoy is running. It covers the same tattoys as `Self::is_tattoy_enabled`.
+    pub fn tattoy_enabled_mut(&mut self, id: &str) -> Option<&mut bool> {
+        if let Some(index) = id.strip_prefix("shader_") {
+            let index: usize = index.parse().ok()?;
+            if index == 0 {
+                return Some(&mut self.shader.enabled);
+            }
+            return self
+                .shaders
+                .get_mut(index - 1)
+                .map(|shader| &mut shader.enabled);
+        }
+
+        enabled_setting!(self, id, &mut)
+    }
+
+    /// Which cells a tattoy, by the tattoy's ID, is rendered into.
+    pub fn compositor_mask(&self, id: &str) -> crate::compositor::Mask {
+        let mask_for = |under_text_only, only_in_blank_cells| crate::compositor::Mask {
+            under_text_only,
+            only_in_blank_cells,
+        };
+
+        if let Some(index) = id.strip_prefix("shader_") {
+            return index
+                .parse()
+                .ok()
+                .and_then(|index| self.shader_at(index))
+                .map(|shader| mask_for(shader.under_text_only, shader.only_in_blank_cells))
+                .unwrap_or_default();
+        }
+
+        match id {
+            "shader" => mask_for(self.shader.under_text_only, self.shader.only_in_blank_cells),
+            "bg_command" => mask_for(
+                self.bg_command.under_text_only,
+                self.bg_command.only_in_blank_cells,
+            ),
+            _ => self
+                .plugins
+                .iter()
+                .find(|plugin| plugin.name() == id)
+                .map(|plugin| mask_for(plugin.under_text_only, plugin.only_in_blank_cells))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The gradient of opacity across a tattoy, by the tattoy's ID, if it has one.
+    pub fn opacity_gradient(&self, id: &str) -> Option<crate::opacity_gradient::Gradient> {
+        if let Some(index) = id.strip_prefix("shader_") {
+            return self.shader_at(index.parse().ok()?)?.opacity_gradient;
+        }
+
+        match id {
+            "shader" => self.shader.opacity_gradient,
+            "bg_command" => self.bg_command.opacity_gradient,
+            _ => self
+                .plugins
+                .iter()
+                .find(|plugin| plugin.name() == id)
+                .and_then(|plugin| plugin.opacity_gradient),
+        }
+    }
+
+    /// Whether a tattoy, by the tattoy's ID, only changes the background colours of cells. Only
+    /// shaders support it.
+    pub fn is_background_only(&self, id: &str) -> bool {
+        if let Some(index) = id.strip_prefix("shader_") {
+            return index
+                .parse()
+                .ok()
+                .and_then(|index| self.shader_at(index))
+                .is_some_and(|shader| shader.background_only);
+        }
+
+        id == "shader" && self.shader.background_only
+    }
+
+    /// The indexes, as used by `Self::shader_at`, of all the enabled shaders.
+    pub fn enabled_shaders(&self) -> Vec<usize> {
+        self.all_shaders()
+            .enumerate()
+            .filter_map(|(index, shader)| shader.enabled.then_some(index))
+            .collect()
+    }
+
+    /// Make sure that all the user-configured layers are within the ranges described in
+    /// `crate::layers`. Returns a message for every layer that had to be changed.
+    fn validate_layers(&mut self) -> Vec<String> {
+        let mut messages = Vec::new();
+
+        let (layer, message) = crate::layers::validate(
+            "shader",
+            self.shader.layer,
+            crate::layers::Placement::NotText,
+        );
+        self.shader.layer = layer;
+        messages.extend(message);
+
+        for (index, shader) in self.shaders.iter_mut().enumerate() {
+            let (layer, message) = crate::layers::validate(
+                &format!("shaders.{index}"),
+                shader.layer,
+                crate::layers::Placement::NotText,
+            );
+            shader.layer = layer;
+            messages.extend(message);
+        }
+
+        let (layer, message) =
+            crate::layers::validate("crt", self.crt.layer, crate::layers::Placement::NotText);
+        self.crt.layer = layer;
+        messages.extend(message);
+
+        let (layer, message) = crate::layers::validate(
+            "bg_command",
+            self.bg_command.layer,
+            crate::layers::Placement::NotText,
+        );
+        self.bg_command.layer = layer;
+        messages.extend(message);
+
+        for plugin in &mut self.plugins {
+            messages.extend(plugin.validate_layer());
+        }
+
+        messages
+    }
+
     /// Parse the shipped default config.
     fn parse_default_config() -> Result<Self> {
         Ok(toml::from_str::<Self>(DEFAULT_CONFIG)?)
@@ -285,6 +751,20 @@ impl Config {
         Ok(new_config)
     }
 
+    /// Reload the main config and send it to everything that uses it. It's sent as a single
+    /// message so that all the tattoys get the same config at the same time.
+    pub async fn reload(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Result<()> {
+        let config = Self::load_config_into_shared_state(state).await?;
+        state
+            .protocol_tx
+            .send(crate::run::Protocol::Config(config))
+            .unwrap_or_else(|send_error| {
+                tracing::error!("Couldn't send config update on protocol channel: {send_error:?}");
+                0
+            });
+        Ok(())
+    }
+
     /// Load all user keybindings.
     #[expect(clippy::iter_over_hash_type, reason = "The ordering doesn't matter")]
     async fn load_keybindings(
@@ -388,18 +868,8 @@ impl Config {
             event.paths
         );
 
-        match Self::load_config_into_shared_state(state).await {
-            Ok(config) => {
-                state
-                    .protocol_tx
-                    .send(crate::run::Protocol::Config(config))
-                    .unwrap_or_else(|send_error| {
-                        tracing::error!(
-                            "Couldn't send config update on protocol channel: {send_error:?}"
-                        );
-                        0
-                    });
-
+        match Self::reload(state).await {
+            Ok(()) => {
                 state
                     .send_notification(
                         "Config updated",
@@ -414,7 +884,12 @@ impl Config {
                     .send_notification(
                         "Config update error",
                         crate::tattoys::notifications::message::Level::Error,
-                        Some(error.root_cause().to_string()),
+                        error
+                            .root_cause()
+                            .to_string()
+                            .lines()
+                            .next()
+                            .map(str::to_owned),
                         false,
                     )
                     .await;
@@ -453,3 +928,69 @@ impl Config {
         Ok(palette)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn shader(path: &str, enabled: bool) -> crate::tattoys::shader::Config {
+        crate::tattoys::shader::Config {
+            path: path.into(),
+            enabled,
+            ..crate::tattoys::shader::Config::default()
+        }
+    }
+
+    #[test]
+    fn extra_shaders_come_after_the_main_one() {
+        let config = Config {
+            shader: shader("main.glsl", true),
+            shaders: vec![shader("extra.glsl", true), shader("off.glsl", false)],
+            ..Config::default()
+        };
+        let path = |index| config.shader_at(index).map(|shader| shader.path.clone());
+        assert_eq!(path(0), Some("main.glsl".into()));
+        assert_eq!(path(1), Some("extra.glsl".into()));
+        assert_eq!(path(2), Some("off.glsl".into()));
+        assert_eq!(path(3), None);
+    }
+
+    #[test]
+    fn only_enabled_shaders_are_rendered() {
+        let mut config = Config {
+            shader: shader("main.glsl", false),
+            shaders: vec![shader("extra.glsl", true), shader("off.glsl", false)],
+            ..Config::default()
+        };
+        assert_eq!(config.enabled_shaders(), [1]);
+
+        config.shader.enabled = true;
+        assert_eq!(config.enabled_shaders(), [0, 1]);
+    }
+
+    #[test]
+    fn tattoys_can_be_kept_under_text_only() {
+        let config = toml::from_str::<Config>(
+            "[shader]\nunder_text_only = true\n\
+             [[shaders]]\npath = \"extra.glsl\"\nunder_text_only = true\n\
+             [bg_command]\nunder_text_only = true\n\
+             [[plugins]]\nname = \"sparkles\"\npath = \"sparkles\"\nunder_text_only = true\n",
+        )
+        .unwrap();
+        for id in ["shader", "shader_1", "bg_command", "sparkles"] {
+            assert!(config.compositor_mask(id).under_text_only, "{id}");
+        }
+        for id in ["minimap", "shader_2", "shader_nope"] {
+            assert_eq!(
+                config.compositor_mask(id),
+                crate::compositor::Mask::default()
+            );
+        }
+
+        let mut attributes = termwiz::cell::CellAttributes::default();
+        attributes.set_background(termwiz::color::ColorAttribute::PaletteIndex(1));
+        let coloured = termwiz::cell::Cell::new(' ', attributes);
+        assert!(config.compositor_mask("shader").is_masked(Some(&coloured)));
+        assert!(!config.compositor_mask("minimap").is_masked(Some(&coloured)));
+    }
+}
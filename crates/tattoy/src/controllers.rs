@@ -0,0 +1,445 @@
+//! Tweaking tattoys in real time with a MIDI controller, like a bank of sliders and knobs, for
+//! live-streamers and VJs. MIDI control changes and notes are mapped to actions in the config:
+//!
+//! ```toml
+//! [controllers]
+//! enabled = true
+//! midi_device = "/dev/snd/midiC1D0"
+//!
+//! [[controllers.mappings]]
+//! cc = 1
+//! action = "opacity"
+//! tattoy = "shader"
+//!
+//! [[controllers.mappings]]
+//! note = 36
+//! action = "scene"
+//! name = "focus"
+//! ```
+//!
+//! The controller is read as a raw MIDI device file, which Linux provides for every MIDI
+//! controller that's plugged in. Tattoy has to be built with the `controllers` feature. Stream
+//! Decks can use the `remote_control` HTTP API instead.
+
+use color_eyre::eyre::Result;
+
+/// The lowest value of a control change that counts as pressing a button.
+#[cfg(feature = "controllers")]
+const PRESSED: u8 = 64;
+
+/// User-configurable settings for controllers.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to listen to the MIDI controller.
+    pub enabled: bool,
+    /// The MIDI device file of the controller.
+    pub midi_device: std::path::PathBuf,
+    /// What each control change or note does.
+    pub mappings: Vec<Mapping>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            midi_device: std::path::PathBuf::from("/dev/snd/midiC1D0"),
+            mappings: Vec::new(),
+        }
+    }
+}
+
+/// What a control change or note does.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
+pub(crate) struct Mapping {
+    /// The MIDI channel, from 1 to 16. Any channel when it's not set.
+    pub channel: Option<u8>,
+    /// The number of a control change, like a slider or a knob.
+    pub cc: Option<u8>,
+    /// The number of a note, like a key or a drum pad.
+    pub note: Option<u8>,
+    /// What to do.
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+/// The actions that controllers can do.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub(crate) enum Action {
+    /// Scale the opacity of a tattoy, from invisible at the bottom of a slider to the opacity in
+    /// the config at the top.
+    Opacity {
+        /// The ID of the tattoy, eg: `shader` or `minimap`.
+        tattoy: String,
+    },
+    /// Change the speed of the shaders, from stopped at the bottom of a slider to `max` at the
+    /// top.
+    ShaderSpeed {
+        /// The speed at the top of the slider.
+        #[serde(default = "default_max_shader_speed")]
+        max: f32,
+    },
+    /// Switch to a scene when a button or note is pressed.
+    Scene {
+        /// The name of the scene, or no scene when it's not set.
+        name: Option<String>,
+    },
+    /// Switch to the next scene when a button or note is pressed.
+    NextScene,
+}
+
+/// The default fastest speed of the shaders.
+const fn default_max_shader_speed() -> f32 {
+    4.0
+}
+
+/// The live values that controllers have set. They're applied on top of the config.
+#[derive(Debug, Clone)]
+pub(crate) struct Live {
+    /// How much each tattoy's opacity is scaled by, by the tattoy's ID.
+    pub opacity: std::collections::BTreeMap<String, f32>,
+    /// How much faster the shaders' time runs.
+    pub shader_speed: f32,
+}
+
+impl Default for Live {
+    fn default() -> Self {
+        Self {
+            opacity: std::collections::BTreeMap::new(),
+            shader_speed: 1.0,
+        }
+    }
+}
+
+impl Live {
+    /// How much a tattoy's opacity is scaled by.
+    pub fn opacity(&self, id: &str) -> f32 {
+        self.opacity.get(id).copied().unwrap_or(1.0)
+    }
+}
+
+/// What a MIDI event came from.
+#[cfg(feature = "controllers")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    /// A control change, by its number.
+    ControlChange(u8),
+    /// A note, by its number.
+    Note(u8),
+}
+
+/// A control change or a note from a controller.
+#[cfg(feature = "controllers")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Event {
+    /// The channel, from 1 to 16.
+    channel: u8,
+    /// What the event came from.
+    control: Control,
+    /// The value of a control change, or the velocity of a note, from 0 to 127. Notes that are
+    /// released have a value of 0.
+    value: u8,
+}
+
+#[cfg(feature = "controllers")]
+impl Event {
+    /// Whether the event is for a mapping.
+    fn matches(&self, mapping: &Mapping) -> bool {
+        let is_control = match self.control {
+            Control::ControlChange(number) => mapping.cc == Some(number),
+            Control::Note(number) => mapping.note == Some(number),
+        };
+        is_control
+            && mapping
+                .channel
+                .is_none_or(|channel| channel == self.channel)
+    }
+
+    /// The value, from `0.0` to `1.0`.
+    fn fraction(&self) -> f32 {
+        f32::from(self.value) / 127.0
+    }
+}
+
+/// Parses a stream of raw MIDI bytes into events.
+#[cfg(feature = "controllers")]
+#[derive(Debug, Default)]
+struct Parser {
+    /// The status byte of the current message. MIDI's "running status" lets messages with the
+    /// same status leave it out.
+    status: Option<u8>,
+    /// The data bytes of the current message.
+    data: Vec<u8>,
+}
+
+#[cfg(feature = "controllers")]
+impl Parser {
+    /// Parse the next byte, returning an event once a message is complete.
+    fn push(&mut self, byte: u8) -> Option<Event> {
+        if byte >= 0xF8 {
+            // Real-time messages, like the clock, can be in the middle of other messages.
+            return None;
+        }
+        if byte >= 0x80 {
+            // System messages, like SysEx, cancel the running status.
+            self.status = (byte < 0xF0).then_some(byte);
+            self.data.clear();
+            return None;
+        }
+
+        let status = self.status?;
+        self.data.push(byte);
+        let kind = status & 0xF0;
+        let length = if kind == 0xC0 || kind == 0xD0 { 1 } else { 2 };
+        if self.data.len() < length {
+            return None;
+        }
+        let data = core::mem::take(&mut self.data);
+
+        let channel = (status & 0x0F) + 1;
+        let (control, value) = match (kind, data.as_slice()) {
+            (0xB0, [number, value]) => (Control::ControlChange(*number), *value),
+            (0x90, [number, velocity]) => (Control::Note(*number), *velocity),
+            (0x80, [number, _]) => (Control::Note(*number), 0),
+            _ => return None,
+        };
+        Some(Event {
+            channel,
+            control,
+            value,
+        })
+    }
+}
+
+/// Listens to controllers.
+pub(crate) struct Controllers;
+
+impl Controllers {
+    /// Without the `controllers` feature there's nothing to listen with.
+    #[cfg(not(feature = "controllers"))]
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let config = state.config.read().await.controllers.clone();
+            if config.enabled {
+                tracing::warn!(
+                    "Can't listen to {:?} because Tattoy wasn't built with the `controllers` feature",
+                    config.midi_device
+                );
+            }
+            Ok(())
+        })
+    }
+
+    /// Start the task that listens to the MIDI controller, if it's enabled.
+    #[cfg(feature = "controllers")]
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let config = state.config.read().await.controllers.clone();
+            if !config.enabled {
+                return Ok(());
+            }
+
+            let mut device = match tokio::fs::File::open(&config.midi_device).await {
+                Ok(device) => device,
+                Err(error) => {
+                    tracing::error!("Couldn't open {:?}: {error:?}", config.midi_device);
+                    state
+                        .send_notification(
+                            "Couldn't open the MIDI controller",
+                            crate::tattoys::notifications::message::Level::Error,
+                            Some(error.to_string()),
+                            false,
+                        )
+                        .await;
+                    return Ok(());
+                }
+            };
+            tracing::info!("Listening to MIDI controller: {:?}", config.midi_device);
+            let mut protocol = state.protocol_tx.subscribe();
+            let mut parser = Parser::default();
+            let mut values = std::collections::BTreeMap::new();
+            let mut buffer = [0; 256];
+
+            #[expect(
+                clippy::integer_division_remainder_used,
+                reason = "This is caused by the `tokio::select!`"
+            )]
+            loop {
+                tokio::select! {
+                    result = tokio::io::AsyncReadExt::read(&mut device, &mut buffer) => {
+                        let count = result?;
+                        if count == 0 {
+                            tracing::warn!("The MIDI controller was disconnected");
+                            break;
+                        }
+                        for byte in buffer.get(..count).unwrap_or_default() {
+                            if let Some(event) = parser.push(*byte) {
+                                Self::handle(&state, event, &mut values).await?;
+                            }
+                        }
+                    },
+                    message = protocol.recv() => match message {
+                        Ok(crate::run::Protocol::End)
+                        | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => (),
+                    }
+                }
+            }
+
+            tracing::debug!("Leaving MIDI controller loop");
+            Ok(())
+        })
+    }
+
+    /// Do the actions that an event is mapped to. Buttons only do their action when they're
+    /// pressed, so the previous value of each control is kept.
+    #[cfg(feature = "controllers")]
+    async fn handle(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        event: Event,
+        values: &mut std::collections::BTreeMap<(u8, u8, bool), u8>,
+    ) -> Result<()> {
+        let key = match event.control {
+            Control::ControlChange(number) => (event.channel, number, false),
+            Control::Note(number) => (event.channel, number, true),
+        };
+        let previous = values.insert(key, event.value).unwrap_or_default();
+        let is_pressed = previous < PRESSED && event.value >= PRESSED;
+
+        let mappings = state.config.read().await.controllers.mappings.clone();
+        for mapping in mappings.iter().filter(|mapping| event.matches(mapping)) {
+            tracing::trace!("MIDI {event:?} for {:?}", mapping.action);
+            match &mapping.action {
+                Action::Opacity { tattoy } => {
+                    state
+                        .live_controls
+                        .write()
+                        .await
+                        .opacity
+                        .insert(tattoy.clone(), event.fraction());
+                    state.protocol_tx.send(crate::run::Protocol::Repaint)?;
+                }
+                Action::ShaderSpeed { max } => {
+                    state.live_controls.write().await.shader_speed = event.fraction() * max;
+                }
+                Action::Scene { name } if is_pressed => {
+                    crate::scenes::switch(state, name.clone()).await;
+                }
+                Action::NextScene if is_pressed => crate::scenes::switch_to_next(state).await,
+                Action::Scene { .. } | Action::NextScene => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "controllers")]
+    fn parse(bytes: &[u8]) -> Vec<Event> {
+        let mut parser = Parser::default();
+        bytes.iter().filter_map(|byte| parser.push(*byte)).collect()
+    }
+
+    #[cfg(feature = "controllers")]
+    #[test]
+    fn parses_midi_with_running_status() {
+        let events = parse(&[
+            0xB0, 7, 100, 0xF8, 7, 20, 0x91, 36, 90, 0x81, 36, 0, 0xF0, 1, 2,
+        ]);
+        assert_eq!(
+            events,
+            [
+                Event {
+                    channel: 1,
+                    control: Control::ControlChange(7),
+                    value: 100
+                },
+                Event {
+                    channel: 1,
+                    control: Control::ControlChange(7),
+                    value: 20
+                },
+                Event {
+                    channel: 2,
+                    control: Control::Note(36),
+                    value: 90
+                },
+                Event {
+                    channel: 2,
+                    control: Control::Note(36),
+                    value: 0
+                },
+            ]
+        );
+        assert!(parse(&[0xC0, 5, 0xB0, 1]).is_empty());
+    }
+
+    #[test]
+    fn mappings_in_the_config() {
+        let config: Config = toml::from_str(
+            r#"
+                [[mappings]]
+                cc = 1
+                channel = 2
+                action = "opacity"
+                tattoy = "shader"
+
+                [[mappings]]
+                note = 36
+                action = "next_scene"
+            "#,
+        )
+        .unwrap();
+        let [slider, pad] = config.mappings.as_slice() else {
+            panic!("There should be 2 mappings");
+        };
+        assert_eq!(
+            slider.action,
+            Action::Opacity {
+                tattoy: "shader".to_owned()
+            }
+        );
+        assert_eq!(slider.channel, Some(2));
+        assert_eq!(pad.action, Action::NextScene);
+        assert!((Live::default().opacity("shader") - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[cfg(feature = "controllers")]
+    #[test]
+    fn events_match_their_mappings() {
+        let slider = Mapping {
+            channel: Some(2),
+            cc: Some(1),
+            note: None,
+            action: Action::NextScene,
+        };
+        let pad = Mapping {
+            channel: None,
+            cc: None,
+            note: Some(36),
+            action: Action::NextScene,
+        };
+        let event = Event {
+            channel: 2,
+            control: Control::ControlChange(1),
+            value: 127,
+        };
+        assert!(event.matches(&slider));
+        assert!(!event.matches(&pad));
+        assert!((event.fraction() - 1.0).abs() < f32::EPSILON);
+        assert!(!Event {
+            channel: 3,
+            ..event
+        }
+        .matches(&slider));
+    }
+}
@@ -0,0 +1,193 @@
+//! Where the PTY's cursor has recently been, and when it moved there. Terminals only ever report
+//! the cursor jumping from cell to cell, sometimes many cells at once, so this lets cursor
+//! shaders draw it smoothly moving between those cells instead.
+
+/// How many of the cursor's most recent moves are remembered.
+const MAX_MOVES: usize = 8;
+
+/// How the interpolated cursor speeds up and slows down between cells.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Easing {
+    /// The same speed all the way.
+    Linear,
+    /// Start quickly and slow down as the cursor arrives.
+    #[default]
+    EaseOut,
+    /// Speed up, then slow down.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Ease the progress, from `0.0` to `1.0`, of a move.
+    pub fn apply(self, progress: f32) -> f32 {
+        let progress = progress.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => progress,
+            Self::EaseOut => 1.0 - (1.0 - progress).powi(3),
+            Self::EaseInOut => {
+                if progress < 0.5 {
+                    4.0 * progress.powi(3)
+                } else {
+                    1.0 - 2.0f32.mul_add(-progress, 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// A cell that the cursor moved to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Move {
+    /// The column and row of the cell.
+    pub position: (usize, usize),
+    /// When the cursor moved there.
+    pub at: tokio::time::Instant,
+}
+
+/// The cursor's most recent moves, oldest first.
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    /// The moves, oldest first.
+    moves: std::collections::VecDeque<Move>,
+}
+
+impl History {
+    /// Remember the cursor's current position, if it's moved.
+    pub fn record(&mut self, position: (usize, usize)) {
+        self.record_at(position, tokio::time::Instant::now());
+    }
+
+    /// Remember the cursor's position at a given time, if it's moved.
+    fn record_at(&mut self, position: (usize, usize), at: tokio::time::Instant) {
+        if self.moves.back().map(|last| last.position) == Some(position) {
+            return;
+        }
+        self.moves.push_back(Move { position, at });
+        while self.moves.len() > MAX_MOVES {
+            self.moves.pop_front();
+        }
+    }
+
+    /// Where the cursor is on its way between cells, in fractional columns and rows. Each move
+    /// takes `duration`, starting from wherever the cursor had got to when it moved again, so that
+    /// quick successive moves don't make the cursor jump.
+    pub fn interpolated(
+        &self,
+        now: tokio::time::Instant,
+        duration: std::time::Duration,
+        easing: Easing,
+    ) -> Option<(f32, f32)> {
+        let mut moves = self.moves.iter();
+        let first = moves.next()?;
+        let mut from = to_floats(first.position);
+        let mut previous = *first;
+        for next in moves {
+            from = Self::position_at(from, &previous, next.at, duration, easing);
+            previous = *next;
+        }
+
+        Some(Self::position_at(from, &previous, now, duration, easing))
+    }
+
+    /// Where the cursor is, at a given time, on its way from a position to a move's cell.
+    fn position_at(
+        from: (f32, f32),
+        to: &Move,
+        now: tokio::time::Instant,
+        duration: std::time::Duration,
+        easing: Easing,
+    ) -> (f32, f32) {
+        let target = to_floats(to.position);
+        if duration.is_zero() {
+            return target;
+        }
+
+        let elapsed = now.saturating_duration_since(to.at);
+        let progress = easing.apply(elapsed.as_secs_f32() / duration.as_secs_f32());
+        (
+            (target.0 - from.0).mul_add(progress, from.0),
+            (target.1 - from.1).mul_add(progress, from.1),
+        )
+    }
+}
+
+/// Convert a cell position to floats.
+fn to_floats(position: (usize, usize)) -> (f32, f32) {
+    let to_float = |coord: usize| f32::from(u16::try_from(coord).unwrap_or(u16::MAX));
+    (to_float(position.0), to_float(position.1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DURATION: std::time::Duration = std::time::Duration::from_millis(100);
+
+    fn is_close(actual: (f32, f32), expected: (f32, f32)) -> bool {
+        (actual.0 - expected.0).abs() < 0.001 && (actual.1 - expected.1).abs() < 0.001
+    }
+
+    #[test]
+    fn only_moves_are_recorded() {
+        let mut history = History::default();
+        let start = tokio::time::Instant::now();
+        history.record_at((1, 1), start);
+        history.record_at((1, 1), start + DURATION);
+        history.record_at((2, 1), start + DURATION);
+        assert_eq!(history.moves.len(), 2);
+
+        for column in 0..20 {
+            history.record_at((column, 5), start);
+        }
+        assert_eq!(history.moves.len(), MAX_MOVES);
+    }
+
+    #[test]
+    fn the_cursor_moves_smoothly_between_cells() {
+        let mut history = History::default();
+        let start = tokio::time::Instant::now();
+        history.record_at((0, 0), start);
+        history.record_at((10, 4), start);
+
+        let halfway = history.interpolated(start + DURATION / 2, DURATION, Easing::Linear);
+        assert!(is_close(halfway.unwrap(), (5.0, 2.0)));
+        let arrived = history.interpolated(start + DURATION * 2, DURATION, Easing::Linear);
+        assert!(is_close(arrived.unwrap(), (10.0, 4.0)));
+    }
+
+    #[test]
+    fn moving_again_starts_from_where_the_cursor_had_got_to() {
+        let mut history = History::default();
+        let start = tokio::time::Instant::now();
+        history.record_at((0, 0), start);
+        history.record_at((10, 0), start);
+        history.record_at((10, 10), start + DURATION / 2);
+
+        let moved = history.interpolated(start + DURATION / 2, DURATION, Easing::Linear);
+        assert!(is_close(moved.unwrap(), (5.0, 0.0)));
+    }
+
+    #[test]
+    fn no_duration_means_no_interpolation() {
+        let mut history = History::default();
+        let start = tokio::time::Instant::now();
+        assert!(history
+            .interpolated(start, DURATION, Easing::Linear)
+            .is_none());
+        history.record_at((0, 0), start);
+        history.record_at((3, 3), start);
+        let position = history.interpolated(start, std::time::Duration::ZERO, Easing::EaseOut);
+        assert!(is_close(position.unwrap(), (3.0, 3.0)));
+    }
+
+    #[test]
+    fn easings_start_and_end_in_the_same_places() {
+        for easing in [Easing::Linear, Easing::EaseOut, Easing::EaseInOut] {
+            assert!(easing.apply(0.0).abs() < f32::EPSILON);
+            assert!((easing.apply(1.0) - 1.0).abs() < f32::EPSILON);
+            assert!(easing.apply(0.5) > 0.0 && easing.apply(0.5) < 1.0);
+        }
+        assert!(Easing::EaseOut.apply(0.5) > Easing::Linear.apply(0.5));
+    }
+}
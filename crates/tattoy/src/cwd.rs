@@ -0,0 +1,329 @@
+//! Track the current working directory of the shell running in the PTY, so that tattoys can react
+//! when it changes. For example the git watermark, or anything else that is specific to a project.
+//!
+//! Shells report their directory with OSC 7 sequences, like `ESC]7;file://host/path BEL`. Tattoy's
+//! shell integration writes them to a file that Tattoy tells the shell about with the
+//! `TATTOY_OSC7_FILE` environment variable. When there is no shell integration we fall back to
+//! looking up the working directory of the shell process itself, which only works on Linux.
+
+use color_eyre::eyre::Result;
+
+/// The start of an OSC 7 sequence.
+const OSC7_START: &str = "\x1b]7;";
+
+/// How long the PTY's output needs to settle before we look for a new directory.
+const SETTLE_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// The longest process name that Linux keeps in `/proc/<pid>/comm`.
+const MAX_PROCESS_NAME_LENGTH: usize = 15;
+
+/// Get the directory from the last OSC 7 sequence in some text.
+pub(crate) fn parse_osc7(text: &str) -> Option<std::path::PathBuf> {
+    let (_, sequence) = text.rsplit_once(OSC7_START)?;
+    let end = sequence.find(['\x07', '\x1b']).unwrap_or(sequence.len());
+    let url = sequence.get(..end)?.trim();
+    let without_scheme = url.strip_prefix("file://")?;
+    let path = without_scheme.get(without_scheme.find('/')?..)?;
+    Some(std::path::PathBuf::from(percent_decode(path)))
+}
+
+/// Decode the `%20` style escapes in a URL.
+fn percent_decode(encoded: &str) -> String {
+    let mut decoded = Vec::new();
+    let mut bytes = encoded.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte != b'%' {
+            decoded.push(byte);
+            continue;
+        }
+
+        let hex = bytes.by_ref().take(2).collect::<Vec<u8>>();
+        let value = std::str::from_utf8(&hex)
+            .ok()
+            .filter(|hex| hex.len() == 2)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match value {
+            Some(value) => decoded.push(value),
+            None => {
+                decoded.push(byte);
+                decoded.extend(hex);
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Whether a directory matches a glob like `~/work/**`. `*` and `?` match within a single path
+/// component and `**` matches any number of whole components, including none.
+pub(crate) fn is_glob_match(glob: &str, directory: &std::path::Path) -> bool {
+    let expanded = match (glob.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => format!("{}/{rest}", home.display()),
+        _ => glob.to_owned(),
+    };
+    let directory = directory.to_string_lossy();
+    let components = |path: &str| {
+        path.split(['/', '\\'])
+            .filter(|component| !component.is_empty())
+            .map(str::to_owned)
+            .collect::<Vec<String>>()
+    };
+
+    is_components_match(&components(&expanded), &components(&directory))
+}
+
+/// Match the components of a glob against the components of a path.
+fn is_components_match(glob: &[String], path: &[String]) -> bool {
+    let Some((glob_first, glob_rest)) = glob.split_first() else {
+        return path.is_empty();
+    };
+
+    if glob_first == "**" {
+        return is_components_match(glob_rest, path)
+            || path
+                .split_first()
+                .is_some_and(|(_, path_rest)| is_components_match(glob, path_rest));
+    }
+
+    path.split_first().is_some_and(|(path_first, path_rest)| {
+        let glob_chars = glob_first.chars().collect::<Vec<char>>();
+        let path_chars = path_first.chars().collect::<Vec<char>>();
+        is_wildcard_match(&glob_chars, &path_chars) && is_components_match(glob_rest, path_rest)
+    })
+}
+
+/// Match a single path component against a glob component containing `*` and `?`.
+fn is_wildcard_match(glob: &[char], text: &[char]) -> bool {
+    match glob.split_first() {
+        None => text.is_empty(),
+        Some(('*', glob_rest)) => {
+            is_wildcard_match(glob_rest, text)
+                || text
+                    .split_first()
+                    .is_some_and(|(_, text_rest)| is_wildcard_match(glob, text_rest))
+        }
+        Some((glob_char, glob_rest)) => text.split_first().is_some_and(|(text_char, text_rest)| {
+            (*glob_char == '?' || glob_char == text_char) && is_wildcard_match(glob_rest, text_rest)
+        }),
+    }
+}
+
+/// The name of the process running in the PTY, as it would appear in `/proc/<pid>/comm`.
+fn process_name(command: &str) -> Option<String> {
+    let program = command.split_whitespace().next()?;
+    let name = std::path::Path::new(program).file_name()?.to_string_lossy();
+    Some(name.chars().take(MAX_PROCESS_NAME_LENGTH).collect())
+}
+
+/// The IDs of a process's children, oldest first.
+async fn child_pids(process: &std::path::Path) -> Vec<u32> {
+    let mut children = Vec::new();
+    let Ok(mut threads) = tokio::fs::read_dir(process.join("task")).await else {
+        return children;
+    };
+    while let Ok(Some(thread)) = threads.next_entry().await {
+        let Ok(pids) = tokio::fs::read_to_string(thread.path().join("children")).await else {
+            continue;
+        };
+        children.extend(
+            pids.split_whitespace()
+                .filter_map(|pid| pid.parse::<u32>().ok()),
+        );
+    }
+    children.sort_unstable();
+    children
+}
+
+/// Find the current directory of the shell process. The shell is one of Tattoy's own child
+/// processes, or a child of the PTY relay (see `crate::pty_relay`), so we look for the oldest
+/// child or grandchild with the right name.
+async fn shell_process_directory(command: &str) -> Option<std::path::PathBuf> {
+    let name = process_name(command)?;
+    let children = child_pids(std::path::Path::new("/proc/self")).await;
+    let mut candidates = children.clone();
+    for child in children {
+        candidates.extend(child_pids(&std::path::PathBuf::from(format!("/proc/{child}"))).await);
+    }
+
+    for pid in candidates {
+        let process = std::path::PathBuf::from(format!("/proc/{pid}"));
+        let Ok(comm) = tokio::fs::read_to_string(process.join("comm")).await else {
+            continue;
+        };
+        if comm.trim_end() == name {
+            return tokio::fs::read_link(process.join("cwd")).await.ok();
+        }
+    }
+
+    None
+}
+
+/// `Cwd`
+pub(crate) struct Cwd {
+    /// The application shared state
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+    /// The file that the shell integration reports to.
+    osc7_file: std::path::PathBuf,
+}
+
+impl Cwd {
+    /// Start the task that tracks the shell's directory.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let mut protocol = state.protocol_tx.subscribe();
+            let cwd = Self {
+                state,
+                osc7_file: crate::shell_integration::osc7_file(),
+            };
+            if let Ok(directory) = std::env::current_dir() {
+                cwd.update(directory).await?;
+            }
+            let mut check_at = None;
+
+            #[expect(
+                clippy::integer_division_remainder_used,
+                reason = "This is caused by the `tokio::select!`"
+            )]
+            loop {
+                tokio::select! {
+                    () = Self::sleep_until(check_at), if check_at.is_some() => {
+                        check_at = None;
+                        if let Some(directory) = cwd.find().await {
+                            cwd.update(directory).await?;
+                        }
+                    },
+                    message = protocol.recv() => match message {
+                        Ok(crate::run::Protocol::End)
+                        | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        Ok(message) => {
+                            if crate::tattoys::tattoyer::Tattoyer::is_screen_output_changed(&message) {
+                                check_at = Some(tokio::time::Instant::now() + SETTLE_DELAY);
+                            }
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Directory tracking lagged behind by {skipped} messages");
+                        }
+                    }
+                }
+            }
+
+            if let Err(error) = tokio::fs::remove_file(&cwd.osc7_file).await {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    tracing::debug!("Couldn't remove {:?}: {error:?}", cwd.osc7_file);
+                }
+            }
+            tracing::debug!("Leaving directory tracking loop");
+            Ok(())
+        })
+    }
+
+    /// Sleep until the next check, if there is one.
+    pub(crate) async fn sleep_until(check_at: Option<tokio::time::Instant>) {
+        if let Some(instant) = check_at {
+            tokio::time::sleep_until(instant).await;
+        }
+    }
+
+    /// Find the shell's current directory. Prefers the shell integration's OSC 7 reports.
+    async fn find(&self) -> Option<std::path::PathBuf> {
+        if let Ok(report) = tokio::fs::read_to_string(&self.osc7_file).await {
+            if let Some(directory) = parse_osc7(&report) {
+                return Some(directory);
+            }
+        }
+
+        let command = self.state.config.read().await.command.clone();
+        shell_process_directory(&command).await
+    }
+
+    /// Save the directory and let everyone know if it has changed.
+    async fn update(&self, directory: std::path::PathBuf) -> Result<()> {
+        let mut cwd = self.state.cwd.write().await;
+        if cwd.as_ref() == Some(&directory) {
+            return Ok(());
+        }
+
+        tracing::debug!("Shell's current directory changed to {directory:?}");
+        *cwd = Some(directory.clone());
+        drop(cwd);
+        self.state
+            .protocol_tx
+            .send(crate::run::Protocol::DirectoryChanged(directory))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_osc7_sequences() {
+        assert_eq!(
+            parse_osc7("\x1b]7;file://laptop/home/me/My%20Project\x07"),
+            Some(std::path::PathBuf::from("/home/me/My Project"))
+        );
+        assert_eq!(
+            parse_osc7("\x1b]7;file://laptop/tmp\x07\x1b]7;file:///etc\x1b\\"),
+            Some(std::path::PathBuf::from("/etc"))
+        );
+        assert!(parse_osc7("\x1b]7;https://example.com/\x07").is_none());
+        assert!(parse_osc7("no sequence here").is_none());
+    }
+
+    #[test]
+    fn bad_percent_escapes_are_kept() {
+        assert_eq!(percent_decode("/100%"), "/100%");
+        assert_eq!(percent_decode("/a%zzb"), "/a%zzb");
+        assert_eq!(percent_decode("/caf%C3%A9"), "/café");
+    }
+
+    #[test]
+    fn directory_globs() {
+        let path = std::path::Path::new;
+        assert!(is_glob_match("/work/**", path("/work")));
+        assert!(is_glob_match("/work/**", path("/work/tattoy/src")));
+        assert!(is_glob_match("/work/*/src", path("/work/tattoy/src")));
+        assert!(is_glob_match("/work/tat?oy", path("/work/tattoy")));
+        assert!(is_glob_match("/**/src", path("/work/tattoy/src")));
+        assert!(!is_glob_match("/work/*", path("/work/tattoy/src")));
+        assert!(!is_glob_match("/work/**", path("/home/work")));
+
+        let home = dirs::home_dir().unwrap();
+        assert!(is_glob_match("~/**", &home.join("projects")));
+    }
+
+    #[test]
+    fn process_names_are_like_proc_comm() {
+        assert_eq!(process_name("/usr/bin/zsh --login"), Some("zsh".to_owned()));
+        assert_eq!(
+            process_name("a-very-long-shell-name"),
+            Some("a-very-long-she".to_owned())
+        );
+        assert!(process_name("").is_none());
+    }
+
+    #[tokio::test]
+    async fn broadcasts_directory_changes() {
+        let (protocol_tx, mut protocol_rx) = tokio::sync::broadcast::channel(16);
+        let cwd = Cwd {
+            state: crate::shared_state::SharedState::init(1, 1, protocol_tx)
+                .await
+                .unwrap(),
+            osc7_file: std::path::PathBuf::new(),
+        };
+
+        cwd.update("/tmp".into()).await.unwrap();
+        cwd.update("/tmp".into()).await.unwrap();
+        assert!(matches!(
+            protocol_rx.try_recv(),
+            Ok(crate::run::Protocol::DirectoryChanged(directory)) if directory.as_os_str() == "/tmp"
+        ));
+        assert!(protocol_rx.try_recv().is_err());
+        assert_eq!(
+            cwd.state.cwd.read().await.clone(),
+            Some(std::path::PathBuf::from("/tmp"))
+        );
+    }
+}
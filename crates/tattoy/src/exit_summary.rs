@@ -0,0 +1,164 @@
+//! What happened in the session, for when Tattoy exits.
+//!
+//! Tattoy exits with the exit code of the shell that it wrapped, so that scripts and terminal
+//! emulators can tell when it failed. The Shadow Terminal doesn't tell us the shell's own exit
+//! status, so it's taken to be the status of the last command reported by the shell integration,
+//! which is what shells exit with when `exit` isn't given one. If the PTY itself failed then the
+//! exit code is 1.
+//!
+//! The optional summary shows how long the session was, how many commands were run, how many
+//! frames were rendered and the input latency, see `crate::latency`. It's printed once the user's
+//! terminal has been restored, or appended to a file:
+//!
+//! ```toml
+//! [exit_summary]
+//! enabled = true
+//! path = "/path/to/sessions.log"
+//! ```
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for the exit summary.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to give a summary of the session when Tattoy exits.
+    pub enabled: bool,
+    /// A file to append the summary to, rather than printing it.
+    pub path: Option<std::path::PathBuf>,
+}
+
+/// The running totals of the session.
+#[derive(Debug)]
+pub(crate) struct Tally {
+    /// When Tattoy started.
+    started: std::time::Instant,
+    /// The number of commands that the shell finished running.
+    commands: u64,
+    /// The exit status of the last command that the shell finished running.
+    last_exit_status: Option<i32>,
+    /// Whether the PTY ended because of an error, rather than its shell exiting.
+    is_pty_failed: bool,
+}
+
+impl Default for Tally {
+    fn default() -> Self {
+        Self {
+            started: std::time::Instant::now(),
+            commands: 0,
+            last_exit_status: None,
+            is_pty_failed: false,
+        }
+    }
+}
+
+impl Tally {
+    /// The shell finished running a command.
+    pub fn command_finished(&mut self, command: &crate::commands::Command) {
+        self.commands += 1;
+        self.last_exit_status = Some(command.exit_status);
+    }
+
+    /// The PTY ended because of an error.
+    pub const fn pty_failed(&mut self) {
+        self.is_pty_failed = true;
+    }
+
+    /// The exit code that Tattoy should exit with.
+    pub fn exit_code(&self) -> u8 {
+        if self.is_pty_failed {
+            return 1;
+        }
+        // Exit statuses are only ever a byte, but negative ones wrap around like they do in shells.
+        self.last_exit_status.map_or(0, |status| {
+            u8::try_from(status.rem_euclid(256)).unwrap_or(1)
+        })
+    }
+
+    /// The one line summary of the session.
+    pub fn summary(&self, frames: u64, maybe_latency: Option<crate::latency::Report>) -> String {
+        let latency =
+            maybe_latency.map_or_else(String::new, |report| format!(", input latency {report}"));
+        format!(
+            "Tattoy session: {}, {} commands run, {frames} frames rendered{latency}, exit code {}",
+            crate::tattoys::command_durations::format_duration(self.started.elapsed()),
+            self.commands,
+            self.exit_code()
+        )
+    }
+}
+
+/// Give the summary of the session, if the user wants one. This happens after the user's
+/// terminal has been restored, so that printing it goes to their normal screen.
+#[expect(
+    clippy::print_stdout,
+    reason = "The summary is for the user's normal terminal"
+)]
+pub(crate) async fn finish(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Result<()> {
+    let config = state.config.read().await.exit_summary.clone();
+    if !config.enabled {
+        return Ok(());
+    }
+
+    let frames = state.metrics.read().await.frames;
+    let latency = state.latency.read().await.report();
+    let summary = state.exit_summary.read().await.summary(frames, latency);
+    match config.path {
+        Some(path) => {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            std::io::Write::write_all(&mut file, format!("{summary}\n").as_bytes())?;
+        }
+        None => println!("{summary}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn command(exit_status: i32) -> crate::commands::Command {
+        crate::commands::Command {
+            text: "make".to_owned(),
+            duration: std::time::Duration::from_millis(10),
+            exit_status,
+        }
+    }
+
+    #[test]
+    fn the_exit_code_is_the_last_commands() {
+        let mut tally = Tally::default();
+        assert_eq!(tally.exit_code(), 0);
+        tally.command_finished(&command(2));
+        tally.command_finished(&command(130));
+        assert_eq!(tally.exit_code(), 130);
+        tally.command_finished(&command(-1));
+        assert_eq!(tally.exit_code(), 255);
+        tally.pty_failed();
+        assert_eq!(tally.exit_code(), 1);
+    }
+
+    #[test]
+    fn the_summary_has_the_totals() {
+        let mut tally = Tally::default();
+        tally.command_finished(&command(0));
+        let summary = tally.summary(42, None);
+        assert!(summary.contains("1 commands run"));
+        assert!(summary.contains("42 frames rendered, exit code"));
+        assert!(summary.ends_with("exit code 0"));
+    }
+
+    #[test]
+    fn the_summary_has_the_input_latency() {
+        let latency = crate::latency::Report {
+            samples: 20,
+            p50: std::time::Duration::from_millis(8),
+            p99: std::time::Duration::from_millis(12),
+        };
+        let summary = Tally::default().summary(1, Some(latency));
+        assert!(summary.contains(", input latency p50 8.0ms, p99 12.0ms (20 keystrokes),"));
+    }
+}
@@ -0,0 +1,81 @@
+//! Knowing when the user's terminal gains and loses focus, eg: when the user switches to another
+//! window. We ask the user's terminal to report focus changes, and it then sends `CSI I` when it
+//! gains focus and `CSI O` when it loses it.
+//!
+//! The application in the PTY never asked for these reports, so they're never forwarded to it.
+
+/// What the user's terminal sends when it gains focus.
+const FOCUS_IN: &[u8] = b"\x1b[I";
+
+/// What the user's terminal sends when it loses focus.
+const FOCUS_OUT: &[u8] = b"\x1b[O";
+
+/// The escape code that asks the user's terminal to report focus changes.
+pub(crate) fn enable_sequence() -> String {
+    format!("{}[?1004h", crate::utils::ESCAPE)
+}
+
+/// The escape code that stops the user's terminal from reporting focus changes.
+pub(crate) fn disable_sequence() -> String {
+    format!("{}[?1004l", crate::utils::ESCAPE)
+}
+
+/// A part of the user's input.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Segment<'bytes> {
+    /// The user's terminal gained (`true`) or lost (`false`) focus.
+    Focus(bool),
+    /// Anything else, which is parsed as usual.
+    Other(&'bytes [u8]),
+}
+
+/// Split the user's input into focus changes and everything else.
+pub(crate) fn split(bytes: &[u8]) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut other_start = 0;
+    let mut position = 0;
+    while let Some(rest) = bytes.get(position..).filter(|rest| !rest.is_empty()) {
+        let is_focused = if rest.starts_with(FOCUS_IN) {
+            true
+        } else if rest.starts_with(FOCUS_OUT) {
+            false
+        } else {
+            position += 1;
+            continue;
+        };
+
+        if let Some(other) = bytes
+            .get(other_start..position)
+            .filter(|other| !other.is_empty())
+        {
+            segments.push(Segment::Other(other));
+        }
+        segments.push(Segment::Focus(is_focused));
+        position += FOCUS_IN.len();
+        other_start = position;
+    }
+    if let Some(other) = bytes.get(other_start..).filter(|other| !other.is_empty()) {
+        segments.push(Segment::Other(other));
+    }
+    segments
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn focus_changes_are_split_from_other_input() {
+        assert_eq!(
+            split(b"ab\x1b[Ic\x1b[O"),
+            [
+                Segment::Other(b"ab"),
+                Segment::Focus(true),
+                Segment::Other(b"c"),
+                Segment::Focus(false),
+            ]
+        );
+        assert_eq!(split(b"\x1b[A"), [Segment::Other(b"\x1b[A")]);
+        assert!(split(b"").is_empty());
+    }
+}
@@ -0,0 +1,297 @@
+//! Run external shell commands, or call webhooks, when things happen in Tattoy. For example when a
+//! user-configured output event matches, or when a notification is shown.
+//!
+//! Hooks are run asynchronously, so they never block the rendering of the terminal.
+
+use color_eyre::eyre::Result;
+
+/// The hook event for every notification.
+const NOTIFICATION_EVENT: &str = "notification";
+
+/// The hook event for every keypress.
+const KEYPRESS_EVENT: &str = "keypress";
+
+/// The hook event for every BEL character that the PTY's command prints.
+pub(crate) const BELL_EVENT: &str = "bell";
+
+/// The hook event for when the command that Tattoy is running exits.
+const EXIT_EVENT: &str = "exit";
+
+/// How long to wait for hooks to finish when Tattoy is exiting.
+const EXIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// A user-configured hook.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Config {
+    /// The event that runs the hook. Either the name of an output event, "notification",
+    /// "keypress", "bell" or "exit".
+    pub on: String,
+    /// A shell command to run.
+    pub command: Option<String>,
+    /// A URL to POST details about the event to, as JSON.
+    pub webhook: Option<String>,
+}
+
+/// Something that happened that hooks can be run for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Event {
+    /// The name of the event, as used in the `on` field of the hook's config.
+    name: String,
+    /// Any extra details about the event, like the text of a notification.
+    text: String,
+}
+
+impl Event {
+    /// The hook event for a protocol message, if there is one.
+    fn from_protocol(message: &crate::run::Protocol) -> Option<Self> {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "Only some messages are hookable"
+        )]
+        match message {
+            crate::run::Protocol::OutputEvent(name) => Some(Self {
+                name: name.clone(),
+                text: String::new(),
+            }),
+            crate::run::Protocol::Notification(notification) => Some(Self {
+                name: NOTIFICATION_EVENT.to_owned(),
+                text: match &notification.body {
+                    Some(body) => format!("{}\n{body}", notification.title),
+                    None => notification.title.clone(),
+                },
+            }),
+            // The key itself isn't passed on, it could be part of a password.
+            crate::run::Protocol::Input(input)
+                if matches!(
+                    input.event,
+                    shadow_terminal::termwiz::input::InputEvent::Key(_)
+                ) =>
+            {
+                Some(Self {
+                    name: KEYPRESS_EVENT.to_owned(),
+                    text: String::new(),
+                })
+            }
+            crate::run::Protocol::BellCharacter => Some(Self {
+                name: BELL_EVENT.to_owned(),
+                text: String::new(),
+            }),
+            crate::run::Protocol::End => Some(Self {
+                name: EXIT_EVENT.to_owned(),
+                text: String::new(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The JSON sent to webhooks.
+    fn to_json(&self) -> String {
+        serde_json::json!({
+            "event": self.name,
+            "text": self.text,
+        })
+        .to_string()
+    }
+}
+
+/// `Hooks`
+pub(crate) struct Hooks;
+
+impl Hooks {
+    /// Start the task that listens for hookable events.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let mut protocol = state.protocol_tx.subscribe();
+            let mut running = tokio::task::JoinSet::new();
+
+            loop {
+                let message = match protocol.recv().await {
+                    Ok(message) => message,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Hooks lagged behind by {skipped} messages");
+                        continue;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                };
+
+                if let Some(event) = Event::from_protocol(&message) {
+                    let hooks = state.config.read().await.hooks.clone();
+                    for hook in Self::hooks_for(&hooks, &event) {
+                        running.spawn(Self::run(hook.clone(), event.clone()));
+                    }
+                }
+
+                // Hooks that have already finished don't need to be kept around.
+                while running.try_join_next().is_some() {}
+
+                if matches!(message, crate::run::Protocol::End) {
+                    break;
+                }
+            }
+
+            // Give any hooks for the `exit` event a chance to finish.
+            let result = tokio::time::timeout(EXIT_TIMEOUT, running.join_all()).await;
+            if result.is_err() {
+                tracing::warn!("Hooks didn't finish within {EXIT_TIMEOUT:?} of exiting");
+            }
+
+            tracing::debug!("Leaving hooks loop");
+            Ok(())
+        })
+    }
+
+    /// All the hooks that should be run for the event.
+    fn hooks_for<'hooks>(hooks: &'hooks [Config], event: &Event) -> Vec<&'hooks Config> {
+        hooks.iter().filter(|hook| hook.on == event.name).collect()
+    }
+
+    /// Run a single hook. Errors are only logged, because sending a notification could itself
+    /// trigger another hook.
+    async fn run(hook: Config, event: Event) {
+        if let Some(command) = &hook.command {
+            tracing::debug!("Running '{}' hook command: {command}", event.name);
+            let result = Self::run_command(command, &event).await;
+            if let Err(error) = result {
+                tracing::error!("Hook command for '{}' failed: {error:?}", event.name);
+            }
+        }
+
+        if let Some(url) = &hook.webhook {
+            tracing::debug!("Calling '{}' hook webhook: {url}", event.name);
+            let result = Self::call_webhook(url, &event).await;
+            if let Err(error) = result {
+                tracing::error!("Hook webhook for '{}' failed: {error:?}", event.name);
+            }
+        }
+    }
+
+    /// Run the hook's shell command. Details about the event are passed in environment variables.
+    async fn run_command(command: &str, event: &Event) -> Result<()> {
+        let mut shell = if cfg!(windows) {
+            let mut shell = tokio::process::Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = tokio::process::Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+
+        let output = shell
+            .arg(command)
+            .env("TATTOY_EVENT", &event.name)
+            .env("TATTOY_EVENT_TEXT", &event.text)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .await?;
+        Self::check_output(&output)
+    }
+
+    /// POST the event to the webhook.
+    async fn call_webhook(url: &str, event: &Event) -> Result<()> {
+        crate::utils::curl(
+            &[
+                "--request",
+                "POST",
+                "--header",
+                "Content-Type: application/json",
+                "--data",
+                &event.to_json(),
+            ],
+            url,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Turn an unsuccessful process into an error.
+    fn check_output(output: &std::process::Output) -> Result<()> {
+        if output.status.success() {
+            return Ok(());
+        }
+
+        color_eyre::eyre::bail!(
+            "{}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn events_from_protocol_messages() {
+        let event = Event::from_protocol(&crate::run::Protocol::OutputEvent(
+            "tests_passed".to_owned(),
+        ));
+        assert_eq!(
+            event.map(|event| event.name),
+            Some("tests_passed".to_owned())
+        );
+
+        let notification = crate::tattoys::notifications::message::Message::make(
+            "Hello",
+            crate::tattoys::notifications::message::Level::Info,
+            Some("World".to_owned()),
+        );
+        let event = Event::from_protocol(&notification).unwrap();
+        assert_eq!(event.name, NOTIFICATION_EVENT);
+        assert_eq!(event.text, "Hello\nWorld");
+
+        let keypress = crate::run::Protocol::Input(crate::raw_input::ParsedInput {
+            bytes: b"a".to_vec(),
+            event: shadow_terminal::termwiz::input::InputEvent::Key(
+                shadow_terminal::termwiz::input::KeyEvent {
+                    key: shadow_terminal::termwiz::input::KeyCode::Char('a'),
+                    modifiers: shadow_terminal::termwiz::input::Modifiers::NONE,
+                },
+            ),
+        });
+        let event = Event::from_protocol(&keypress).unwrap();
+        assert_eq!(event.name, KEYPRESS_EVENT);
+        assert!(event.text.is_empty());
+
+        let event = Event::from_protocol(&crate::run::Protocol::BellCharacter).unwrap();
+        assert_eq!(event.name, BELL_EVENT);
+
+        assert!(Event::from_protocol(&crate::run::Protocol::Repaint).is_none());
+    }
+
+    #[test]
+    fn only_matching_hooks_run() {
+        let hook = |on: &str| Config {
+            on: on.to_owned(),
+            command: Some("true".to_owned()),
+            webhook: None,
+        };
+        let hooks = vec![
+            hook("tests_passed"),
+            hook("build_failed"),
+            hook("tests_passed"),
+        ];
+        let event = Event {
+            name: "tests_passed".to_owned(),
+            text: String::new(),
+        };
+
+        assert_eq!(Hooks::hooks_for(&hooks, &event).len(), 2);
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn commands_get_event_details() {
+        let event = Event {
+            name: "tests_passed".to_owned(),
+            text: String::new(),
+        };
+        Hooks::run_command(r#"test "$TATTOY_EVENT" = tests_passed"#, &event)
+            .await
+            .unwrap();
+        assert!(Hooks::run_command("exit 1", &event).await.is_err());
+    }
+}
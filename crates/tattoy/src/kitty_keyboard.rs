@@ -0,0 +1,367 @@
+//! Support for the kitty keyboard protocol: https://sw.kovidgoyal.net/kitty/keyboard-protocol
+//!
+//! When enabled, and the user's terminal supports it, we ask the terminal to report every key as
+//! an escape code, along with key releases and the unshifted and shifted versions of keys. That
+//! lets keybindings use modifiers that can't otherwise be told apart, like `CTRL+i` and `Tab` or
+//! `SUPER`, and lets tattoys and shaders know when keys are released.
+//!
+//! The application in the PTY talks to the shadow terminal rather than the user's terminal, so
+//! it never negotiated the protocol itself. Therefore we translate every enhanced key into the
+//! legacy encoding that it expects, and key releases aren't forwarded to it at all.
+
+use shadow_terminal::termwiz;
+
+/// The flags we ask the user's terminal for: disambiguate escape codes (1), report event types
+/// (2), report alternate keys (4) and report all keys as escape codes (8).
+const FLAGS: u8 = 0b1111;
+
+/// The kitty modifier bits, after removing the protocol's offset of 1.
+const SHIFT: u16 = 0b1;
+/// See `SHIFT`.
+const ALT: u16 = 0b10;
+/// See `SHIFT`.
+const CTRL: u16 = 0b100;
+/// See `SHIFT`.
+const SUPER: u16 = 0b1000;
+
+/// User-configurable settings for the kitty keyboard protocol.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to use the kitty keyboard protocol when the user's terminal supports it.
+    pub enabled: bool,
+}
+
+/// Whether the protocol is used for this session. It's only decided once, at startup, because the
+/// user's terminal needs to be told about it.
+pub(crate) async fn is_enabled(state: &crate::shared_state::SharedState) -> bool {
+    state.config.read().await.kitty_keyboard.enabled
+        && state.capabilities.read().await.kitty_keyboard
+}
+
+/// The escape code that enables the protocol in the user's terminal.
+pub(crate) fn enable_sequence() -> String {
+    format!("{}[>{FLAGS}u", crate::utils::ESCAPE)
+}
+
+/// The escape code that returns the user's terminal to how it was before we enabled the protocol.
+pub(crate) fn disable_sequence() -> String {
+    format!("{}[<u", crate::utils::ESCAPE)
+}
+
+/// The kinds of key events that the protocol reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    /// The key was pressed.
+    Press,
+    /// The key is being held down.
+    Repeat,
+    /// The key was released.
+    Release,
+}
+
+/// A key event reported with the kitty keyboard protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct KeyEvent {
+    /// The key and its modifiers.
+    pub event: termwiz::input::KeyEvent,
+    /// Whether the key was pressed, repeated or released.
+    pub kind: Kind,
+}
+
+impl KeyEvent {
+    /// The key in the legacy encoding, for the application in the PTY. Keys that don't have a
+    /// legacy encoding, like modifier keys on their own, are empty.
+    pub fn legacy_bytes(&self) -> Vec<u8> {
+        let modes = termwiz::input::KeyCodeEncodeModes {
+            encoding: termwiz::input::KeyboardEncoding::Xterm,
+            application_cursor_keys: false,
+            newline_mode: false,
+            modify_other_keys: None,
+        };
+        self.event
+            .key
+            .encode(self.event.modifiers, modes, true)
+            .map(String::into_bytes)
+            .unwrap_or_default()
+    }
+}
+
+/// A part of the user's input.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Segment<'bytes> {
+    /// A key reported with the kitty keyboard protocol. `None` when we don't know the key.
+    Key(Option<KeyEvent>),
+    /// Anything else, like mouse events and pastes, which are parsed as usual.
+    Other(&'bytes [u8]),
+}
+
+/// Split the user's input into kitty key events and everything else.
+pub(crate) fn split(bytes: &[u8]) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut other_start = 0;
+    let mut position = 0;
+    while position < bytes.len() {
+        let Some((length, key)) = bytes.get(position..).and_then(parse) else {
+            position += 1;
+            continue;
+        };
+
+        if let Some(other) = bytes
+            .get(other_start..position)
+            .filter(|other| !other.is_empty())
+        {
+            segments.push(Segment::Other(other));
+        }
+        segments.push(Segment::Key(key));
+        position += length;
+        other_start = position;
+    }
+    if let Some(other) = bytes.get(other_start..).filter(|other| !other.is_empty()) {
+        segments.push(Segment::Other(other));
+    }
+    segments
+}
+
+/// Parse a kitty key escape code at the start of the bytes. Returns the length of the escape code
+/// and the key, if we know it.
+fn parse(bytes: &[u8]) -> Option<(usize, Option<KeyEvent>)> {
+    let parameters_and_final = bytes.strip_prefix(b"\x1b[")?;
+    let final_index = parameters_and_final
+        .iter()
+        .position(|byte| !byte.is_ascii_digit() && *byte != b';' && *byte != b':')?;
+    let final_byte = *parameters_and_final.get(final_index)?;
+    if !b"u~ABCDFHPQS".contains(&final_byte) {
+        return None;
+    }
+    let parameters = std::str::from_utf8(parameters_and_final.get(..final_index)?).ok()?;
+    let length = final_index + 3;
+
+    let mut fields = parameters.split(';');
+    let mut codes = fields.next().unwrap_or_default().split(':');
+    let mut modifier_fields = fields.next().unwrap_or_default().split(':');
+    let number = codes.next().and_then(|code| code.parse::<u32>().ok());
+    let shifted = codes
+        .next()
+        .and_then(|code| code.parse::<u32>().ok())
+        .and_then(char::from_u32);
+    let modifiers = modifier_fields
+        .next()
+        .and_then(|field| field.parse::<u16>().ok())
+        .map_or(0, |field| field.saturating_sub(1));
+    let kind = match modifier_fields.next() {
+        Some("2") => Kind::Repeat,
+        Some("3") => Kind::Release,
+        _ => Kind::Press,
+    };
+
+    // These are bracketed paste markers, which aren't keys.
+    if final_byte == b'~' && matches!(number, Some(200 | 201)) {
+        return None;
+    }
+
+    let key = key_code(final_byte, number.unwrap_or(1)).map(|code| {
+        let is_shift_applied = matches!(code, termwiz::input::KeyCode::Char(_))
+            && modifiers & SHIFT != 0
+            && modifiers & (CTRL | ALT | SUPER) == 0;
+        let code = match code {
+            termwiz::input::KeyCode::Char(character) if is_shift_applied => {
+                termwiz::input::KeyCode::Char(
+                    shifted.unwrap_or_else(|| character.to_ascii_uppercase()),
+                )
+            }
+            other => other,
+        };
+        let modifiers = if is_shift_applied {
+            modifiers & !SHIFT
+        } else {
+            modifiers
+        };
+        KeyEvent {
+            event: termwiz::input::KeyEvent {
+                key: code,
+                modifiers: termwiz_modifiers(modifiers),
+            },
+            kind,
+        }
+    });
+
+    Some((length, key))
+}
+
+/// Convert kitty modifier bits to termwiz modifiers.
+fn termwiz_modifiers(modifiers: u16) -> termwiz::input::Modifiers {
+    let mut converted = termwiz::input::Modifiers::NONE;
+    for (bit, modifier) in [
+        (SHIFT, termwiz::input::Modifiers::SHIFT),
+        (ALT, termwiz::input::Modifiers::ALT),
+        (CTRL, termwiz::input::Modifiers::CTRL),
+        (SUPER, termwiz::input::Modifiers::SUPER),
+    ] {
+        if modifiers & bit != 0 {
+            converted |= modifier;
+        }
+    }
+    converted
+}
+
+/// The key of an escape code, from its final byte and its key number.
+const fn key_code(final_byte: u8, number: u32) -> Option<termwiz::input::KeyCode> {
+    use termwiz::input::KeyCode;
+
+    let key = match final_byte {
+        b'A' => KeyCode::UpArrow,
+        b'B' => KeyCode::DownArrow,
+        b'C' => KeyCode::RightArrow,
+        b'D' => KeyCode::LeftArrow,
+        b'F' => KeyCode::End,
+        b'H' => KeyCode::Home,
+        b'P' => KeyCode::Function(1),
+        b'Q' => KeyCode::Function(2),
+        b'S' => KeyCode::Function(4),
+        b'~' => match number {
+            2 => KeyCode::Insert,
+            3 => KeyCode::Delete,
+            5 => KeyCode::PageUp,
+            6 => KeyCode::PageDown,
+            7 => KeyCode::Home,
+            8 => KeyCode::End,
+            11 => KeyCode::Function(1),
+            12 => KeyCode::Function(2),
+            13 => KeyCode::Function(3),
+            14 => KeyCode::Function(4),
+            15 => KeyCode::Function(5),
+            17 => KeyCode::Function(6),
+            18 => KeyCode::Function(7),
+            19 => KeyCode::Function(8),
+            20 => KeyCode::Function(9),
+            21 => KeyCode::Function(10),
+            23 => KeyCode::Function(11),
+            24 => KeyCode::Function(12),
+            _ => return None,
+        },
+        b'u' => match number {
+            9 => KeyCode::Tab,
+            13 | 57414 => KeyCode::Enter,
+            27 => KeyCode::Escape,
+            127 => KeyCode::Backspace,
+            57358 => KeyCode::CapsLock,
+            57359 => KeyCode::ScrollLock,
+            57360 => KeyCode::NumLock,
+            57361 => KeyCode::PrintScreen,
+            57362 => KeyCode::Pause,
+            57399 => KeyCode::Numpad0,
+            57400 => KeyCode::Numpad1,
+            57401 => KeyCode::Numpad2,
+            57402 => KeyCode::Numpad3,
+            57403 => KeyCode::Numpad4,
+            57404 => KeyCode::Numpad5,
+            57405 => KeyCode::Numpad6,
+            57406 => KeyCode::Numpad7,
+            57407 => KeyCode::Numpad8,
+            57408 => KeyCode::Numpad9,
+            57409 => KeyCode::Decimal,
+            57410 => KeyCode::Divide,
+            57411 => KeyCode::Multiply,
+            57412 => KeyCode::Subtract,
+            57413 => KeyCode::Add,
+            57417 => KeyCode::LeftArrow,
+            57418 => KeyCode::RightArrow,
+            57419 => KeyCode::UpArrow,
+            57420 => KeyCode::DownArrow,
+            57421 => KeyCode::PageUp,
+            57422 => KeyCode::PageDown,
+            57423 => KeyCode::Home,
+            57424 => KeyCode::End,
+            57425 => KeyCode::Insert,
+            57426 => KeyCode::Delete,
+            57441 => KeyCode::LeftShift,
+            57442 => KeyCode::LeftControl,
+            57443 => KeyCode::LeftAlt,
+            57444 => KeyCode::LeftWindows,
+            57447 => KeyCode::RightShift,
+            57448 => KeyCode::RightControl,
+            57449 => KeyCode::RightAlt,
+            57450 => KeyCode::RightWindows,
+            // The rest of the private use area is for keys we don't support.
+            57344..=63743 => return None,
+            _ => match char::from_u32(number) {
+                Some(character) => KeyCode::Char(character),
+                None => return None,
+            },
+        },
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(bytes: &[u8]) -> KeyEvent {
+        parse(bytes).unwrap().1.unwrap()
+    }
+
+    #[test]
+    fn parses_keys_with_modifiers() {
+        assert_eq!(
+            key(b"\x1b[105;5u").event,
+            termwiz::input::KeyEvent {
+                key: termwiz::input::KeyCode::Char('i'),
+                modifiers: termwiz::input::Modifiers::CTRL,
+            }
+        );
+        assert_eq!(key(b"\x1b[9u").event.key, termwiz::input::KeyCode::Tab);
+        assert_eq!(
+            key(b"\x1b[1;9A").event,
+            termwiz::input::KeyEvent {
+                key: termwiz::input::KeyCode::UpArrow,
+                modifiers: termwiz::input::Modifiers::SUPER,
+            }
+        );
+    }
+
+    #[test]
+    fn shifted_characters_use_the_shifted_key() {
+        assert_eq!(
+            key(b"\x1b[49:33;2u").event,
+            termwiz::input::KeyEvent {
+                key: termwiz::input::KeyCode::Char('!'),
+                modifiers: termwiz::input::Modifiers::NONE,
+            }
+        );
+        assert_eq!(
+            key(b"\x1b[120;2u").event.key,
+            termwiz::input::KeyCode::Char('X')
+        );
+    }
+
+    #[test]
+    fn parses_event_kinds() {
+        assert_eq!(key(b"\x1b[97u").kind, Kind::Press);
+        assert_eq!(key(b"\x1b[97;1:2u").kind, Kind::Repeat);
+        assert_eq!(key(b"\x1b[97;1:3u").kind, Kind::Release);
+        assert_eq!(
+            key(b"\x1b[57441;2:3u").event.key,
+            termwiz::input::KeyCode::LeftShift
+        );
+    }
+
+    #[test]
+    fn other_input_is_left_alone() {
+        let bytes = b"\x1b[97u\x1b[<0;5;5M\x1b[200~hi\x1b[201~";
+        let segments = split(bytes);
+        assert_eq!(segments.len(), 2);
+        assert!(matches!(segments.first(), Some(Segment::Key(Some(_)))));
+        assert_eq!(
+            segments.get(1),
+            Some(&Segment::Other(b"\x1b[<0;5;5M\x1b[200~hi\x1b[201~"))
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_swallowed() {
+        assert_eq!(split(b"\x1b[57376u"), vec![Segment::Key(None)]);
+    }
+}
@@ -0,0 +1,144 @@
+//! Measure input-to-glass latency: the time from a keystroke entering Tattoy to the PTY's echo of
+//! it being painted to the user's terminal.
+//!
+//! We can't know which PTY output is the echo of which keystroke, so we assume that the first PTY
+//! update after a keystroke is its echo. Keystrokes that don't get an echo soon enough, like those
+//! that a TUI app silently handles, are forgotten rather than counted.
+
+/// The longest that a keystroke waits for its echo before it's forgotten.
+const MAX_LATENCY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// The number of recent measurements that the percentiles are calculated from.
+const MAX_SAMPLES: usize = 1000;
+
+/// Keystrokes waiting for their echo, and recent measurements.
+#[derive(Debug, Default)]
+pub(crate) struct Probe {
+    /// Keystrokes that haven't been echoed yet.
+    typed: std::collections::VecDeque<std::time::Instant>,
+    /// Keystrokes whose echo hasn't been painted yet.
+    echoed: Vec<std::time::Instant>,
+    /// The most recent input-to-glass latencies.
+    samples: std::collections::VecDeque<std::time::Duration>,
+}
+
+impl Probe {
+    /// A keystroke was sent to the PTY.
+    pub fn keystroke(&mut self, now: std::time::Instant) {
+        while self
+            .typed
+            .front()
+            .is_some_and(|typed| now.duration_since(*typed) > MAX_LATENCY)
+        {
+            self.typed.pop_front();
+        }
+        self.typed.push_back(now);
+    }
+
+    /// The PTY sent output, which we assume is the echo of all the keystrokes waiting for one.
+    pub fn pty_output(&mut self, now: std::time::Instant) {
+        for typed in self.typed.drain(..) {
+            if now.duration_since(typed) <= MAX_LATENCY {
+                self.echoed.push(typed);
+            }
+        }
+    }
+
+    /// A frame was painted to the user's terminal.
+    pub fn painted(&mut self, now: std::time::Instant) {
+        for echoed in self.echoed.drain(..) {
+            if self.samples.len() >= MAX_SAMPLES {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(now.duration_since(echoed));
+        }
+    }
+
+    /// A summary of the recent measurements, if there are any.
+    pub fn report(&self) -> Option<Report> {
+        let mut sorted = self.samples.iter().copied().collect::<Vec<_>>();
+        sorted.sort_unstable();
+        Some(Report {
+            samples: sorted.len(),
+            p50: percentile(&sorted, 50)?,
+            p99: percentile(&sorted, 99)?,
+        })
+    }
+}
+
+/// The nearest-rank percentile of sorted measurements.
+fn percentile(sorted: &[std::time::Duration], percent: usize) -> Option<std::time::Duration> {
+    let rank = sorted.len().saturating_mul(percent).div_ceil(100);
+    sorted.get(rank.saturating_sub(1)).copied()
+}
+
+/// Input-to-glass latency percentiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Report {
+    /// The number of measurements.
+    pub samples: usize,
+    /// The median latency.
+    pub p50: std::time::Duration,
+    /// The 99th percentile latency.
+    pub p99: std::time::Duration,
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "p50 {:.1?}, p99 {:.1?} ({} keystrokes)",
+            self.p50, self.p99, self.samples
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn millis(milliseconds: u64) -> std::time::Duration {
+        std::time::Duration::from_millis(milliseconds)
+    }
+
+    #[test]
+    fn measures_from_keystroke_to_paint() {
+        let start = std::time::Instant::now();
+        let mut probe = Probe::default();
+        assert_eq!(probe.report(), None);
+
+        probe.keystroke(start);
+        probe.keystroke(start + millis(2));
+        probe.painted(start + millis(3));
+        assert_eq!(probe.report(), None);
+
+        probe.pty_output(start + millis(5));
+        probe.painted(start + millis(10));
+        assert_eq!(
+            probe.report(),
+            Some(Report {
+                samples: 2,
+                p50: millis(8),
+                p99: millis(10),
+            })
+        );
+    }
+
+    #[test]
+    fn keystrokes_without_an_echo_are_forgotten() {
+        let start = std::time::Instant::now();
+        let mut probe = Probe::default();
+        probe.keystroke(start);
+        probe.pty_output(start + MAX_LATENCY + millis(1));
+        probe.painted(start + MAX_LATENCY + millis(2));
+        assert_eq!(probe.report(), None);
+    }
+
+    #[test]
+    fn percentiles_are_nearest_rank() {
+        let samples = (1..=100).map(millis).collect::<Vec<_>>();
+        assert_eq!(percentile(&samples, 50), Some(millis(50)));
+        assert_eq!(percentile(&samples, 99), Some(millis(99)));
+        assert_eq!(percentile(&[], 50), None);
+    }
+}
@@ -0,0 +1,193 @@
+//! Named groups of compositing layers.
+//!
+//! Every tattoy is rendered to a layer, or z-index, where the PTY's text is always layer `0`. To
+//! save tattoys from having to agree on magic numbers, the layers are divided into groups:
+//!
+//! * `Background` (`-999` to `-1`): beneath the PTY's text, eg: shaders, background commands.
+//! * `Text` (`0`): the PTY itself. Any tattoy on this layer entirely replaces the PTY.
+//! * `Effects` (`1` to `99`): eye-candy on top of the PTY's text, eg: the minimap.
+//! * `Overlay` (`100` to `999`): UI that should always be visible, eg: the scrollbar, notifications.
+//!
+//! The animated cursor is a special case. Its layer is `i16::MIN` because it's rendered between
+//! the foreground and background of the PTY's text.
+
+/// The lowest layer that a tattoy can be rendered to.
+pub const MIN_LAYER: i16 = -999;
+
+/// The highest layer that a tattoy can be rendered to.
+pub const MAX_LAYER: i16 = 999;
+
+/// The first layer of the overlay group.
+const OVERLAY_START: i16 = 100;
+
+/// A named range of layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Group {
+    /// Layers beneath the PTY's text.
+    Background,
+    /// The PTY's text.
+    Text,
+    /// Layers above the PTY's text.
+    Effects,
+    /// Layers above everything else.
+    Overlay,
+}
+
+impl Group {
+    /// The lowest and highest layers of the group.
+    pub const fn bounds(self) -> (i16, i16) {
+        match self {
+            Self::Background => (MIN_LAYER, -1),
+            Self::Text => (0, 0),
+            Self::Effects => (1, OVERLAY_START - 1),
+            Self::Overlay => (OVERLAY_START, MAX_LAYER),
+        }
+    }
+
+    /// Which group a layer belongs to, if any.
+    pub const fn of(layer: i16) -> Option<Self> {
+        if layer < MIN_LAYER || layer > MAX_LAYER {
+            None
+        } else if layer < 0 {
+            Some(Self::Background)
+        } else if layer == 0 {
+            Some(Self::Text)
+        } else if layer < OVERLAY_START {
+            Some(Self::Effects)
+        } else {
+            Some(Self::Overlay)
+        }
+    }
+
+    /// Force a layer to be within the group.
+    pub const fn clamp(self, layer: i16) -> i16 {
+        let (lowest, highest) = self.bounds();
+        if layer < lowest {
+            lowest
+        } else if layer > highest {
+            highest
+        } else {
+            layer
+        }
+    }
+
+    /// Get a layer within the group. The offset counts away from the PTY's text, so for the
+    /// background group higher offsets are further below the text, and for the other groups
+    /// higher offsets are further above it.
+    pub const fn layer(self, offset: i16) -> i16 {
+        let (lowest, highest) = self.bounds();
+        let layer = match self {
+            Self::Background => highest.saturating_sub(offset),
+            Self::Text => 0,
+            Self::Effects | Self::Overlay => lowest.saturating_add(offset),
+        };
+        self.clamp(layer)
+    }
+}
+
+/// Where a tattoy is allowed to be rendered relative to the PTY's text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Placement {
+    /// Always beneath the text.
+    BelowText,
+    /// Always above the text.
+    AboveText,
+    /// Above or below the text, but never replacing it.
+    NotText,
+    /// Any layer, including the PTY's own layer.
+    #[default]
+    Anywhere,
+}
+
+impl Placement {
+    /// Move a layer so that it honours the placement.
+    pub const fn apply(self, layer: i16) -> i16 {
+        match self {
+            Self::BelowText => Group::Background.clamp(layer),
+            Self::AboveText => {
+                if layer >= OVERLAY_START {
+                    Group::Overlay.clamp(layer)
+                } else {
+                    Group::Effects.clamp(layer)
+                }
+            }
+            Self::NotText => {
+                if layer == 0 {
+                    -1
+                } else if layer < 0 {
+                    Group::Background.clamp(layer)
+                } else {
+                    Self::AboveText.apply(layer)
+                }
+            }
+            Self::Anywhere => {
+                if layer < MIN_LAYER {
+                    MIN_LAYER
+                } else if layer > MAX_LAYER {
+                    MAX_LAYER
+                } else {
+                    layer
+                }
+            }
+        }
+    }
+}
+
+/// Validate a user-configured layer. If the layer had to be changed then a message explaining
+/// why is also returned.
+pub(crate) fn validate(name: &str, layer: i16, placement: Placement) -> (i16, Option<String>) {
+    let valid = placement.apply(layer);
+    if valid == layer {
+        return (layer, None);
+    }
+
+    let reason = if layer == 0 {
+        "layer 0 is reserved for the terminal's text".to_owned()
+    } else {
+        format!("layers must be between {MIN_LAYER} and {MAX_LAYER}")
+    };
+    let message = format!("`{name}` layer changed from {layer} to {valid}: {reason}");
+    (valid, Some(message))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn groups_of_layers() {
+        assert_eq!(Group::of(-10), Some(Group::Background));
+        assert_eq!(Group::of(0), Some(Group::Text));
+        assert_eq!(Group::of(90), Some(Group::Effects));
+        assert_eq!(Group::of(200), Some(Group::Overlay));
+        assert_eq!(Group::of(i16::MIN), None);
+    }
+
+    #[test]
+    fn layers_within_groups() {
+        assert_eq!(Group::Background.layer(9), -10);
+        assert_eq!(Group::Effects.layer(0), 1);
+        assert_eq!(Group::Overlay.layer(100), 200);
+        assert_eq!(Group::Effects.layer(500), 99);
+        assert_eq!(Group::Background.layer(i16::MAX), MIN_LAYER);
+    }
+
+    #[test]
+    fn placements() {
+        assert_eq!(Placement::BelowText.apply(5), -1);
+        assert_eq!(Placement::AboveText.apply(-5), 1);
+        assert_eq!(Placement::AboveText.apply(150), 150);
+        assert_eq!(Placement::NotText.apply(0), -1);
+        assert_eq!(Placement::Anywhere.apply(0), 0);
+        assert_eq!(Placement::Anywhere.apply(5000), MAX_LAYER);
+    }
+
+    #[test]
+    fn validating_layers() {
+        assert_eq!(validate("shader", -10, Placement::NotText), (-10, None));
+
+        let (layer, message) = validate("shader", 0, Placement::NotText);
+        assert_eq!(layer, -1);
+        assert!(message.unwrap().contains("reserved"));
+    }
+}
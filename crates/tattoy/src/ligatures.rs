@@ -0,0 +1,124 @@
+//! Ligature-safe painting of frames to the user's terminal.
+//!
+//! Normally only the cells that have changed since the last frame are written to the user's
+//! terminal. But terminals that render ligatures, like "->" or "!=" in programming fonts, shape
+//! each run of text as it's written, so updating a single cell of a ligature can leave the rest
+//! of it unshaped. Instead, this repaints the whole run of text around every changed cell.
+
+use shadow_terminal::termwiz;
+
+/// The ranges of columns of a line that need repainting: each changed cell, widened to the whole
+/// run of text that it's part of, in either the old or the new line.
+fn dirty_runs(
+    old: &[termwiz::cell::Cell],
+    new: &[termwiz::cell::Cell],
+) -> Vec<core::ops::Range<usize>> {
+    let is_text = |x: usize| {
+        [old, new].into_iter().any(|line| {
+            line.get(x)
+                .is_some_and(crate::compositor::Compositor::is_text)
+        })
+    };
+
+    let mut runs: Vec<core::ops::Range<usize>> = Vec::new();
+    for x in 0..new.len() {
+        if old.get(x) == new.get(x) {
+            continue;
+        }
+        if runs.last().is_some_and(|run| run.contains(&x)) {
+            continue;
+        }
+
+        let mut start = x;
+        let mut end = x + 1;
+        if is_text(x) {
+            while start > 0 && is_text(start - 1) {
+                start -= 1;
+            }
+            while is_text(end) {
+                end += 1;
+            }
+        }
+        match runs.last_mut() {
+            Some(run) if run.end >= start => run.end = end,
+            _ => runs.push(start..end),
+        }
+    }
+    runs
+}
+
+/// The changes that paint the `new` frame over the `old` one, repainting whole runs of text.
+/// `None` when the frames are different sizes, as everything needs repainting anyway.
+pub(crate) fn changes(
+    old: &[&[termwiz::cell::Cell]],
+    new: &[&[termwiz::cell::Cell]],
+) -> Option<Vec<termwiz::surface::Change>> {
+    if old.len() != new.len() {
+        return None;
+    }
+
+    let mut changes = Vec::new();
+    for (y, (old_line, new_line)) in old.iter().zip(new).enumerate() {
+        if old_line.len() != new_line.len() {
+            return None;
+        }
+        for run in dirty_runs(old_line, new_line) {
+            changes.push(termwiz::surface::Change::CursorPosition {
+                x: termwiz::surface::Position::Absolute(run.start),
+                y: termwiz::surface::Position::Absolute(y),
+            });
+            let mut covered = 0;
+            for cell in new_line.get(run).unwrap_or_default() {
+                if covered > 0 {
+                    // The rest of a wide character.
+                    covered -= 1;
+                    continue;
+                }
+                covered = cell.width().saturating_sub(1);
+                changes.push(termwiz::surface::Change::AllAttributes(
+                    cell.attrs().clone(),
+                ));
+                changes.push(termwiz::surface::Change::Text(cell.str().to_owned()));
+            }
+        }
+    }
+    Some(changes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn line(text: &str) -> Vec<termwiz::cell::Cell> {
+        text.chars()
+            .map(|character| {
+                termwiz::cell::Cell::new(character, termwiz::cell::CellAttributes::default())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn changed_cells_repaint_their_whole_run_of_text() {
+        assert_eq!(dirty_runs(&line("a -> b"), &line("a -> b")), []);
+        assert_eq!(dirty_runs(&line("a -> b"), &line("a => b")), [2..4]);
+        assert_eq!(dirty_runs(&line("a -> b"), &line("a -  b")), [2..4]);
+        assert_eq!(dirty_runs(&line("x != y"), &line("z != w")), [0..1, 5..6]);
+    }
+
+    #[test]
+    fn only_changed_lines_are_repainted() {
+        let old = [line("fn a() -> u8"), line("let b = 1;")];
+        let new = [line("fn a() -> u8"), line("let b = 2;")];
+        let old: Vec<&[termwiz::cell::Cell]> = old.iter().map(Vec::as_slice).collect();
+        let new: Vec<&[termwiz::cell::Cell]> = new.iter().map(Vec::as_slice).collect();
+        let changes = changes(&old, &new).unwrap();
+        assert_eq!(
+            changes.first(),
+            Some(&termwiz::surface::Change::CursorPosition {
+                x: termwiz::surface::Position::Absolute(8),
+                y: termwiz::surface::Position::Absolute(1),
+            })
+        );
+        assert_eq!(changes.len(), 5);
+    }
+}
@@ -24,15 +24,17 @@ async fn convert_cli_enabled_args(
     enabled_tattoys: &Vec<String>,
     state: &Arc<crate::shared_state::SharedState>,
 ) {
+    let mut config = state.config.write().await;
     for tattoy in enabled_tattoys {
         match tattoy.as_ref() {
-            "startup_logo" => state.config.write().await.show_startup_logo = true,
-            "notifications" => state.config.write().await.notifications.enabled = true,
-            "minimap" => state.config.write().await.minimap.enabled = true,
-            "shaders" => state.config.write().await.shader.enabled = true,
-            "animated_cursor" => state.config.write().await.animated_cursor.enabled = true,
-            "bg_command" => state.config.write().await.bg_command.enabled = true,
-            _ => (),
+            "startup_logo" => config.show_startup_logo = true,
+            // The CLI's name for the `shader` tattoy.
+            "shaders" => config.shader.enabled = true,
+            id => {
+                if let Some(enabled) = config.tattoy_enabled_mut(id) {
+                    *enabled = true;
+                }
+            }
         }
     }
 }
@@ -52,7 +54,15 @@ pub(crate) fn spawn(
             let palette = crate::config::main::Config::load_palette(Arc::clone(&state)).await?;
             let mut tattoy_futures = tokio::task::JoinSet::new();
 
-            if state.config.read().await.show_startup_logo {
+            // Tattoys that a scene enables are started too, so that they're ready to be
+            // switched on.
+            let config = state.config.read().await.clone();
+            let mut enabled_by_scenes = crate::scenes::sections_enabled_by_scenes(&config);
+            enabled_by_scenes.extend(crate::stream_mode::sections_enabled(&config.stream_mode));
+            let is_startable =
+                |section: &str, is_enabled: bool| is_enabled || enabled_by_scenes.contains(section);
+
+            if config.show_startup_logo && !state.is_reduced_motion().await {
                 tracing::info!("Starting 'startup_logo' tattoy...");
                 tattoy_futures.spawn(crate::tattoys::startup_logo::StartupLogo::start(
                     output.clone(),
@@ -61,7 +71,7 @@ pub(crate) fn spawn(
                 ));
             }
 
-            if state.config.read().await.notifications.enabled {
+            if is_startable("notifications", config.notifications.enabled) {
                 tracing::info!("Starting 'notifications' tattoy...");
                 tattoy_futures.spawn(crate::tattoys::notifications::main::Notifications::start(
                     output.clone(),
@@ -77,6 +87,19 @@ pub(crate) fn spawn(
                 Arc::clone(&state),
             ));
 
+            tracing::info!("Starting pane manager...");
+            tattoy_futures.spawn(crate::panes::manager::Manager::start(
+                output.clone(),
+                Arc::clone(&state),
+                palette.clone(),
+            ));
+
+            tracing::info!("Starting 'copy_mode' tattoy...");
+            tattoy_futures.spawn(crate::tattoys::copy_mode::CopyMode::start(
+                output.clone(),
+                Arc::clone(&state),
+            ));
+
             if enabled_tattoys.contains(&"random_walker".to_owned()) {
                 tracing::info!("Starting 'random_walker' tattoy...");
                 tattoy_futures.spawn(crate::tattoys::random_walker::RandomWalker::start(
@@ -85,7 +108,7 @@ pub(crate) fn spawn(
                 ));
             }
 
-            if state.config.read().await.minimap.enabled {
+            if is_startable("minimap", config.minimap.enabled) {
                 tracing::info!("Starting 'minimap' tattoy...");
                 tattoy_futures.spawn(crate::tattoys::minimap::Minimap::start(
                     output.clone(),
@@ -93,23 +116,241 @@ pub(crate) fn spawn(
                 ));
             }
 
-            if state.config.read().await.shader.enabled {
-                tracing::info!("Starting 'shaders' tattoy...");
+            let mut shaders = config.enabled_shaders();
+            if is_startable("shader", false) && !shaders.contains(&0) {
+                shaders.insert(0, 0);
+            }
+            for index in shaders {
+                tracing::info!("Starting 'shaders' tattoy ({index})...");
                 tattoy_futures.spawn(crate::tattoys::shader::Shaders::start(
                     output.clone(),
                     Arc::clone(&state),
+                    index,
+                ));
+            }
+
+            if is_startable("bloom", config.bloom.enabled) {
+                tracing::info!("Starting 'bloom' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::bloom::Bloom::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("weather", config.weather.enabled) {
+                tracing::info!("Starting 'weather' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::weather::Weather::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("fireworks", config.fireworks.enabled) {
+                tracing::info!("Starting 'fireworks' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::fireworks::Fireworks::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("timer", config.timer.enabled) {
+                tracing::info!("Starting 'timer' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::timer::Timer::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("widget", config.widget.enabled) {
+                tracing::info!("Starting 'widget' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::widget::Widget::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("weather_widget", config.weather_widget.enabled) {
+                tracing::info!("Starting 'weather_widget' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::weather_widget::WeatherWidget::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("git_watermark", config.git_watermark.enabled) {
+                tracing::info!("Starting 'git_watermark' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::git_watermark::GitWatermark::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("celebration", config.celebration.enabled) {
+                tracing::info!("Starting 'celebration' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::celebration::Celebration::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("inline_images", config.inline_images.enabled) {
+                tracing::info!("Starting 'inline_images' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::inline_images::InlineImages::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("status_line", config.status_line.enabled) {
+                tracing::info!("Starting 'status_line' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::status_line::StatusLine::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            let screensaver = config.screensaver.clone();
+            if is_startable("screensaver", screensaver.enabled) {
+                tracing::info!("Starting 'screensaver' tattoy...");
+                if screensaver.kind == crate::tattoys::screensaver::Kind::Shader {
+                    tattoy_futures.spawn(crate::tattoys::screensaver::ScreensaverShader::start(
+                        output.clone(),
+                        Arc::clone(&state),
+                        0,
+                    ));
+                } else {
+                    tattoy_futures.spawn(crate::tattoys::screensaver::Screensaver::start(
+                        output.clone(),
+                        Arc::clone(&state),
+                    ));
+                }
+            }
+
+            if is_startable("lock", config.lock.enabled) {
+                tracing::info!("Starting 'lock' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::lock::Lock::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("paste_guard", config.paste_guard.enabled) {
+                tracing::info!("Starting 'paste_guard' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::paste_guard::PasteGuard::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("redaction", config.redaction.enabled) {
+                tracing::info!("Starting 'redaction' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::redaction::Redactor::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("repaints", config.repaints.enabled) {
+                tracing::info!("Starting 'repaints' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::repaints::Repaints::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("heatmap", config.heatmap.enabled) {
+                tracing::info!("Starting 'heatmap' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::heatmap::Heatmap::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if !config.stream_mode.watermark.is_empty() {
+                tracing::info!("Starting 'stream_watermark' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::stream_watermark::StreamWatermark::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("column_guides", config.column_guides.enabled) {
+                tracing::info!("Starting 'column_guides' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::column_guides::ColumnGuides::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("command_durations", config.command_durations.enabled) {
+                tracing::info!("Starting 'command_durations' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::command_durations::CommandDurations::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("command_separators", config.command_separators.enabled) {
+                tracing::info!("Starting 'command_separators' tattoy...");
+                tattoy_futures.spawn(
+                    crate::tattoys::command_separators::CommandSeparators::start(
+                        output.clone(),
+                        Arc::clone(&state),
+                    ),
+                );
+            }
+
+            if is_startable("progress_bar", config.progress_bar.enabled) {
+                tracing::info!("Starting 'progress_bar' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::progress_bar::ProgressBar::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("visual_bell", config.visual_bell.enabled) {
+                tracing::info!("Starting 'visual_bell' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::visual_bell::VisualBell::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("typing_speed", config.typing_speed.enabled) {
+                tracing::info!("Starting 'typing_speed' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::typing_speed::TypingSpeed::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                ));
+            }
+
+            if is_startable("crt", config.crt.enabled) {
+                tracing::info!("Starting 'crt' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::crt::CRT::start(
+                    output.clone(),
+                    Arc::clone(&state),
+                    0,
+                ));
+            }
+
+            if is_startable("cursor_line", config.cursor_line.enabled) {
+                tracing::info!("Starting 'cursor_line' tattoy...");
+                tattoy_futures.spawn(crate::tattoys::cursor_line::CursorLine::start(
+                    output.clone(),
+                    Arc::clone(&state),
                 ));
             }
 
-            if state.config.read().await.animated_cursor.enabled {
+            if is_startable("animated_cursor", config.animated_cursor.enabled) {
                 tracing::info!("Starting 'animated_cursor' tattoy...");
                 tattoy_futures.spawn(crate::tattoys::animated_cursor::AnimatedCursor::start(
                     output.clone(),
                     Arc::clone(&state),
+                    0,
                 ));
             }
 
-            if state.config.read().await.bg_command.enabled {
+            if is_startable("bg_command", config.bg_command.enabled) {
                 tracing::info!("Starting 'bg_command' tattoy...");
                 tattoy_futures.spawn(crate::tattoys::bg_command::BGCommand::start(
                     output.clone(),
@@ -169,8 +410,9 @@ async fn wait_for_enabled_tattoys_to_start(
         crate::run::wait_for_system(state, "random_walker").await;
     }
 
-    if state.config.read().await.shader.enabled {
-        crate::run::wait_for_system(state, "shader").await;
+    for index in state.config.read().await.enabled_shaders() {
+        let id = crate::tattoys::shader::Shaders::id(index);
+        crate::run::wait_for_system(state, &id).await;
     }
 
     if state.config.read().await.minimap.enabled {
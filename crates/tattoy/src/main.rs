@@ -4,16 +4,54 @@
 // this approach is that when moving files/modules, you _also_ have to move these module
 // definitions.
 
+pub mod attach;
+pub mod backpressure;
 pub mod cli_args;
+pub mod colour_blindness;
+pub mod colour_correction;
 /// All the user-configurable settings.
 pub mod config {
+    pub mod diagnostics;
     pub mod input;
     pub mod main;
 }
+pub mod bidi;
 pub mod blender;
+pub mod braille;
+pub mod bundles;
+pub mod calibrate;
+pub mod capabilities;
+pub mod commands;
 pub mod compositor;
+pub mod controllers;
+pub mod cursor_history;
+pub mod cwd;
+pub mod exit_summary;
+pub mod focus;
+pub mod hooks;
+pub mod kitty_keyboard;
+pub mod latency;
+pub mod layers;
+pub mod ligatures;
 pub mod loader;
+pub mod metrics;
+pub mod night_light;
+pub mod opacity_gradient;
+pub mod osc_notifications;
+pub mod output_burst;
+pub mod output_events;
+pub mod packages;
+/// Splitting the user's terminal into multiple panes, each with its own PTY.
+pub mod panes {
+    pub mod layout;
+    pub mod manager;
+}
+pub mod pixel_encoders;
+pub mod pixels;
+pub mod pty_relay;
 pub mod raw_input;
+pub mod reduced_motion;
+pub mod remote_control;
 /// The palette code is for helping convert a terminal's palette to true colour.
 pub mod palette {
     pub mod converter;
@@ -24,19 +62,47 @@ pub mod palette {
 }
 pub mod renderer;
 pub mod run;
+pub mod scenes;
+pub mod screen_reader;
+pub mod scrollback_log;
+pub mod session;
+pub mod setup_wizard;
+pub mod shader_dev;
 pub mod shared_state;
+pub mod shell_integration;
+pub mod sounds;
+pub mod stream_mode;
 pub mod surface;
+pub mod suspend;
 /// A layer between Tattoy and the Shadow Terminal
 pub mod terminal_proxy {
     pub mod input_handler;
     pub mod proxy;
 }
+pub mod terminal_restore;
+pub mod title;
+pub mod unicode;
 pub mod utils;
+pub mod watchdog;
 
 /// This is where all the various tattoys are kept
 pub mod tattoys {
     pub mod animated_cursor;
     pub mod bg_command;
+    pub mod bloom;
+    pub mod celebration;
+    pub mod column_guides;
+    pub mod command_durations;
+    pub mod command_separators;
+    pub mod copy_mode;
+    pub mod crt;
+    pub mod cursor_line;
+    pub mod cursor_presets;
+    pub mod fireworks;
+    pub mod git_watermark;
+    pub mod heatmap;
+    pub mod inline_images;
+    pub mod lock;
     pub mod minimap;
     pub mod startup_logo;
 
@@ -46,20 +112,43 @@ pub mod tattoys {
         pub mod message;
     }
 
+    pub mod paste_guard;
     pub mod plugins;
+    pub mod progress_bar;
     pub mod random_walker;
+    pub mod redaction;
+    pub mod repaints;
+    pub mod screensaver;
     pub mod scrollbar;
     pub mod shader;
+    pub mod status_line;
+    pub mod stream_watermark;
 
     /// GPU management code
     pub mod gpu {
+        pub mod cell_changes;
+        pub mod cell_metadata;
+        pub mod compute;
         pub mod handle_messages;
         pub mod ichannel;
+        pub mod keyboard;
+        pub mod memory;
         pub mod pipeline;
+        pub mod rotation;
+        pub mod shader_error;
         pub mod shaderer;
+        pub mod text_mask;
+        pub mod transition;
+        pub mod tty_pixels;
     }
 
     pub mod tattoyer;
+    pub mod timer;
+    pub mod typing_speed;
+    pub mod visual_bell;
+    pub mod weather;
+    pub mod weather_widget;
+    pub mod widget;
 }
 
 use color_eyre::eyre::Result;
@@ -71,13 +160,21 @@ use color_eyre::eyre::Result;
     reason = "It's our central place for communicating with the user on CLI"
 )]
 #[tokio::main(flavor = "multi_thread")]
-async fn main() -> Result<()> {
+async fn main() -> Result<std::process::ExitCode> {
     color_eyre::install()?;
+    if let Some(exit_code) = pty_relay::relay_if_asked().await? {
+        return Ok(std::process::ExitCode::from(exit_code));
+    }
     run::check_for_tattoy_in_tattoy();
+    terminal_restore::save_original();
     let (protocol_tx, _) = tokio::sync::broadcast::channel(1024);
     let state_arc = shared_state::SharedState::init_with_users_tty_size(protocol_tx).await?;
     let result = run::run(&std::sync::Arc::clone(&state_arc)).await;
     println!("{}", utils::RESET_SCREEN);
+    terminal_restore::restore();
+    if let Err(error) = exit_summary::finish(&state_arc).await {
+        tracing::error!("Couldn't give the exit summary: {error:?}");
+    }
 
     let logpath = state_arc.config.read().await.log_path.clone();
     let is_logging = *state_arc.is_logging.read().await;
@@ -88,15 +185,19 @@ async fn main() -> Result<()> {
             if is_logging {
                 println!("Logs saved to {}", logpath.display());
             }
+            let exit_code = state_arc.exit_summary.read().await.exit_code();
+            Ok(std::process::ExitCode::from(exit_code))
         }
         Err(error) => {
             tracing::error!("{error:?}");
             eprintln!("Error: {error}");
+            for report in terminal_restore::take_panic_reports() {
+                eprintln!("Panic {report}");
+            }
             if is_logging {
                 eprintln!("See {} for more details", logpath.display());
             }
+            Ok(std::process::ExitCode::FAILURE)
         }
     }
-
-    Ok(())
 }
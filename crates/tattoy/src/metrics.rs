@@ -0,0 +1,337 @@
+//! Measurements of Tattoy's own overhead, for users running it on servers. When Tattoy is built
+//! with the `metrics` feature they're served over HTTP in the Prometheus text format:
+//!
+//! ```toml
+//! [metrics]
+//! enabled = true
+//! address = "127.0.0.1:9464"
+//! ```
+
+use color_eyre::eyre::Result;
+
+/// How long a client has to send its request and read the metrics. A stalled client mustn't hold
+/// up the others, or Tattoy exiting.
+#[cfg(feature = "metrics")]
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// User-configurable settings for the metrics endpoint.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to serve the metrics. Tattoy has to be built with the `metrics` feature.
+    pub enabled: bool,
+    /// The address to serve the metrics on.
+    pub address: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:9464".to_owned(),
+        }
+    }
+}
+
+/// Measurements that aren't already kept elsewhere in the shared state.
+#[derive(Debug, Default)]
+pub(crate) struct Recorder {
+    /// The number of frames painted to the user's terminal.
+    pub frames: u64,
+    /// The total time spent compositing and painting frames.
+    pub frame_time: std::time::Duration,
+    /// The time that the last frame took.
+    pub last_frame_time: std::time::Duration,
+    /// The CPU time each tattoy has spent in its compute jobs.
+    pub tattoy_cpu_time: std::collections::BTreeMap<String, std::time::Duration>,
+}
+
+impl Recorder {
+    /// A frame was painted.
+    pub fn frame(&mut self, duration: std::time::Duration) {
+        self.frames += 1;
+        self.frame_time += duration;
+        self.last_frame_time = duration;
+    }
+
+    /// A tattoy's compute job finished.
+    pub fn tattoy_cpu(&mut self, id: &str, duration: std::time::Duration) {
+        *self.tattoy_cpu_time.entry(id.to_owned()).or_default() += duration;
+    }
+}
+
+/// A single metric in the Prometheus text format.
+#[cfg(feature = "metrics")]
+fn metric(
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: &[(Option<(&str, &str)>, String)],
+) -> String {
+    let mut lines = vec![
+        format!("# HELP tattoy_{name} {help}"),
+        format!("# TYPE tattoy_{name} {kind}"),
+    ];
+    lines.extend(
+        samples
+            .iter()
+            .map(|(maybe_label, value)| match maybe_label {
+                Some((label, label_value)) => {
+                    let escaped = label_value.replace('\\', "\\\\").replace('"', "\\\"");
+                    format!("tattoy_{name}{{{label}=\"{escaped}\"}} {value}")
+                }
+                None => format!("tattoy_{name} {value}"),
+            }),
+    );
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// All the metrics, in the Prometheus text format.
+#[cfg(feature = "metrics")]
+pub(crate) async fn render(state: &crate::shared_state::SharedState) -> String {
+    let frame_metrics = *state.frame_metrics.read().await;
+    let skipped = state.frame_pacing.read().await.skipped.clone();
+    let recorder = state.metrics.read().await;
+    let frames = recorder.frames;
+    let frame_time = recorder.frame_time;
+    let last_frame_time = recorder.last_frame_time;
+    let tattoy_cpu_time = recorder.tattoy_cpu_time.clone();
+    drop(recorder);
+
+    let mut sections = vec![
+        metric(
+            "frames_total",
+            "counter",
+            "Frames painted to the user's terminal.",
+            &[(None, frames.to_string())],
+        ),
+        metric(
+            "frame_seconds_total",
+            "counter",
+            "Time spent compositing and painting frames.",
+            &[(None, frame_time.as_secs_f64().to_string())],
+        ),
+        metric(
+            "last_frame_seconds",
+            "gauge",
+            "Time that the last frame took to composite and paint.",
+            &[(None, last_frame_time.as_secs_f64().to_string())],
+        ),
+        metric(
+            "dropped_frames_total",
+            "counter",
+            "Stashed tattoy frames that were replaced before being rendered.",
+            &[(None, frame_metrics.dropped.to_string())],
+        ),
+        metric(
+            "skipped_frames_total",
+            "counter",
+            "Frames that each tattoy skipped because its last frame was still waiting.",
+            &skipped
+                .iter()
+                .map(|(id, count)| (Some(("tattoy", id.as_str())), count.to_string()))
+                .collect::<Vec<_>>(),
+        ),
+        metric(
+            "frame_channel_depth",
+            "gauge",
+            "Frame updates waiting in the renderer's channel.",
+            &[(None, frame_metrics.backlog.to_string())],
+        ),
+        metric(
+            "frame_channel_peak_depth",
+            "gauge",
+            "The deepest that the renderer's channel has been.",
+            &[(None, frame_metrics.peak_backlog.to_string())],
+        ),
+        metric(
+            "stashed_frames",
+            "gauge",
+            "Tattoy frames stashed for the renderer.",
+            &[(None, frame_metrics.stashed.to_string())],
+        ),
+        metric(
+            "tattoy_cpu_seconds_total",
+            "counter",
+            "CPU time that each tattoy has spent in its compute jobs.",
+            &tattoy_cpu_time
+                .iter()
+                .map(|(id, duration)| {
+                    (
+                        Some(("tattoy", id.as_str())),
+                        duration.as_secs_f64().to_string(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        ),
+    ];
+    if let Some(report) = state
+        .gpu_device
+        .read()
+        .await
+        .as_ref()
+        .and_then(|device| device.device.generate_allocator_report())
+    {
+        sections.push(metric(
+            "gpu_memory_bytes",
+            "gauge",
+            "GPU memory reserved by Tattoy's shaders.",
+            &[(None, report.total_reserved_bytes.to_string())],
+        ));
+    }
+    if let Some(report) = state.latency.read().await.report() {
+        sections.push(metric(
+            "input_latency_seconds",
+            "gauge",
+            "Recent input-to-glass latency.",
+            &[
+                (
+                    Some(("quantile", "0.5")),
+                    report.p50.as_secs_f64().to_string(),
+                ),
+                (
+                    Some(("quantile", "0.99")),
+                    report.p99.as_secs_f64().to_string(),
+                ),
+            ],
+        ));
+    }
+
+    sections.concat()
+}
+
+/// Serves the metrics over HTTP.
+pub(crate) struct Metrics;
+
+impl Metrics {
+    /// Without the `metrics` feature there's nothing to serve the metrics with.
+    #[cfg(not(feature = "metrics"))]
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let config = state.config.read().await.metrics.clone();
+            if config.enabled {
+                tracing::warn!(
+                    "Can't serve metrics on {} because Tattoy wasn't built with the `metrics` feature",
+                    config.address
+                );
+            }
+            Ok(())
+        })
+    }
+
+    /// Start the task that serves the metrics, if they're enabled.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let config = state.config.read().await.metrics.clone();
+            if !config.enabled {
+                return Ok(());
+            }
+
+            let listener = tokio::net::TcpListener::bind(&config.address).await?;
+            tracing::info!("Serving metrics on: http://{}", config.address);
+            let mut protocol = state.protocol_tx.subscribe();
+
+            #[expect(
+                clippy::integer_division_remainder_used,
+                reason = "This is caused by the `tokio::select!`"
+            )]
+            loop {
+                tokio::select! {
+                    connection = listener.accept() => match connection {
+                        Ok((stream, _)) => {
+                            // Each request gets its own task so that a slow client doesn't hold up
+                            // other scrapes.
+                            let request_state = std::sync::Arc::clone(&state);
+                            tokio::spawn(async move {
+                                let request = Self::respond(&request_state, stream);
+                                match tokio::time::timeout(REQUEST_TIMEOUT, request).await {
+                                    Ok(Ok(())) => (),
+                                    Ok(Err(error)) => tracing::debug!("Couldn't serve metrics: {error:?}"),
+                                    Err(_) => tracing::debug!("Metrics request timed out"),
+                                }
+                            });
+                        }
+                        Err(error) => tracing::warn!("Couldn't accept metrics connection: {error:?}"),
+                    },
+                    message = protocol.recv() => match message {
+                        Ok(crate::run::Protocol::End)
+                        | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => (),
+                    }
+                }
+            }
+
+            tracing::debug!("Leaving metrics loop");
+            Ok(())
+        })
+    }
+
+    /// Respond to a request. Every path serves the metrics, so the request is barely read.
+    #[cfg(feature = "metrics")]
+    async fn respond(
+        state: &crate::shared_state::SharedState,
+        mut stream: tokio::net::TcpStream,
+    ) -> Result<()> {
+        let mut request = [0; 1024];
+        let _bytes = tokio::io::AsyncReadExt::read(&mut stream, &mut request).await?;
+
+        let body = render(state).await;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\n\
+             Content-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            body.len()
+        );
+        tokio::io::AsyncWriteExt::write_all(&mut stream, response.as_bytes()).await?;
+        tokio::io::AsyncWriteExt::shutdown(&mut stream).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn metrics_are_in_the_prometheus_format() {
+        let text = metric(
+            "skipped_frames_total",
+            "counter",
+            "Skipped frames.",
+            &[(Some(("tattoy", "sha\"der")), "3".to_owned())],
+        );
+        assert_eq!(
+            text,
+            "# HELP tattoy_skipped_frames_total Skipped frames.\n\
+             # TYPE tattoy_skipped_frames_total counter\n\
+             tattoy_skipped_frames_total{tattoy=\"sha\\\"der\"} 3\n"
+        );
+    }
+
+    #[test]
+    fn records_frames_and_cpu_time() {
+        let mut recorder = Recorder::default();
+        recorder.frame(std::time::Duration::from_millis(4));
+        recorder.frame(std::time::Duration::from_millis(6));
+        recorder.tattoy_cpu("shader", std::time::Duration::from_millis(1));
+        recorder.tattoy_cpu("shader", std::time::Duration::from_millis(2));
+        assert_eq!(recorder.frames, 2);
+        assert_eq!(recorder.frame_time, std::time::Duration::from_millis(10));
+        assert_eq!(
+            recorder.last_frame_time,
+            std::time::Duration::from_millis(6)
+        );
+        assert_eq!(
+            recorder.tattoy_cpu_time.get("shader"),
+            Some(&std::time::Duration::from_millis(3))
+        );
+    }
+}
@@ -0,0 +1,302 @@
+//! A night light, or blue light filter, that warms the colours of the whole terminal. It's the
+//! very last change made to each frame, so it applies to every tattoy, whatever its layer. It
+//! can be on all the time, or just between sunset and sunrise.
+
+use shadow_terminal::termwiz;
+
+/// The colour temperature, in Kelvin, that is close enough to white to make no change.
+const NEUTRAL_TEMPERATURE: f32 = 6600.0;
+
+/// How many seconds there are in a day.
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// The Julian day of the Unix epoch.
+const UNIX_EPOCH_JULIAN_DAY: f64 = 2_440_587.5;
+
+/// The Julian day of the J2000 epoch, which the sunrise equation counts from.
+const J2000_JULIAN_DAY: f64 = 2_451_545.0;
+
+/// The tilt of the Earth's axis, in degrees.
+const AXIAL_TILT: f64 = 23.4397;
+
+/// How far below the horizon, in degrees, the centre of the sun is at sunrise and sunset. It
+/// accounts for the refraction of the atmosphere and the size of the sun.
+const HORIZON: f64 = -0.833;
+
+/// When the night light is on.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Schedule {
+    /// All the time.
+    #[default]
+    Always,
+    /// Between sunset and sunrise, at the `latitude` and `longitude` in the config.
+    Sun,
+}
+
+/// User config for the night light.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether the night light is on.
+    pub enabled: bool,
+    /// The colour temperature, in Kelvin. Lower is warmer, `6600` makes no change.
+    #[schemars(range(min = 1000.0, max = 6600.0))]
+    pub temperature: f32,
+    /// Whether to also warm the colours of the terminal's text, rather than just the tattoys and
+    /// backgrounds.
+    pub include_text: bool,
+    /// When the night light is on.
+    pub schedule: Schedule,
+    /// Your latitude in degrees, north is positive. Only used by the `sun` schedule.
+    #[schemars(range(min = -90.0, max = 90.0))]
+    pub latitude: f32,
+    /// Your longitude in degrees, east is positive. Only used by the `sun` schedule.
+    #[schemars(range(min = -180.0, max = 180.0))]
+    pub longitude: f32,
+    /// How many minutes, centred on sunset and sunrise, the night light takes to fade in and out.
+    #[schemars(range(min = 0.0))]
+    pub fade_minutes: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            temperature: 3400.0,
+            include_text: false,
+            schedule: Schedule::default(),
+            latitude: 0.0,
+            longitude: 0.0,
+            fade_minutes: 30.0,
+        }
+    }
+}
+
+/// The amount that each of the red, green and blue channels of a colour are multiplied by, to
+/// give white the colour of a light at `temperature` Kelvin. This is Tanner Helland's
+/// approximation of the colours of black body radiation.
+fn temperature_to_multipliers(temperature: f32) -> (f32, f32, f32) {
+    let hundreds = temperature.clamp(1000.0, 40000.0) / 100.0;
+
+    let red = if hundreds <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (hundreds - 60.0).powf(-0.133_204_76)
+    };
+    let green = if hundreds <= 66.0 {
+        99.470_8_f32.mul_add(hundreds.ln(), -161.119_57)
+    } else {
+        288.122_17 * (hundreds - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if hundreds >= 66.0 {
+        255.0
+    } else if hundreds <= 19.0 {
+        0.0
+    } else {
+        138.517_73_f32.mul_add((hundreds - 10.0).ln(), -305.044_8)
+    };
+
+    let normalise = |channel: f32| (channel / 255.0).clamp(0.0, 1.0);
+    (normalise(red), normalise(green), normalise(blue))
+}
+
+/// How far the night light is on at a moment in time, from `0.0` for off to `1.0` for fully on.
+fn strength(config: &Config, unix_seconds: f64) -> f32 {
+    match config.schedule {
+        Schedule::Always => 1.0,
+        Schedule::Sun => {
+            let Some(minutes_after_sunset) = minutes_after_sunset(
+                unix_seconds,
+                f64::from(config.latitude),
+                f64::from(config.longitude),
+            ) else {
+                return 1.0;
+            };
+            if config.fade_minutes <= 0.0 {
+                return if minutes_after_sunset > 0.0 { 1.0 } else { 0.0 };
+            }
+
+            let fade = f64::from(config.fade_minutes);
+            #[expect(
+                clippy::as_conversions,
+                clippy::cast_possible_truncation,
+                reason = "The progress is always between 0.0 and 1.0"
+            )]
+            let progress = ((minutes_after_sunset + fade / 2.0) / fade).clamp(0.0, 1.0) as f32;
+            progress
+        }
+    }
+}
+
+/// How many minutes it's been since the nearest sunset, or until the nearest sunrise, at a moment
+/// in time. It's negative during the day: the number of minutes until the nearest sunset, or
+/// since the nearest sunrise. Calculated with the sunrise equation.
+///
+/// During polar nights the sun never rises, so `None` is returned. During polar days the sun
+/// never sets, so a whole day is returned.
+fn minutes_after_sunset(unix_seconds: f64, latitude: f64, longitude: f64) -> Option<f64> {
+    let julian_day = unix_seconds / SECONDS_PER_DAY + UNIX_EPOCH_JULIAN_DAY;
+    let day = (julian_day - J2000_JULIAN_DAY + 0.0008).ceil();
+
+    // The moment that the sun is highest in the sky on the solar day nearest to `unix_seconds`.
+    let mut noon = solar_noon(day, longitude);
+    if julian_day - noon.0 > 0.5 {
+        noon = solar_noon(day + 1.0, longitude);
+    } else if noon.0 - julian_day > 0.5 {
+        noon = solar_noon(day - 1.0, longitude);
+    }
+    let (transit, ecliptic_longitude) = noon;
+
+    let declination = (ecliptic_longitude.sin() * AXIAL_TILT.to_radians().sin()).asin();
+    let latitude = latitude.to_radians();
+    let hour_angle_cosine = latitude
+        .sin()
+        .mul_add(-declination.sin(), HORIZON.to_radians().sin())
+        / (latitude.cos() * declination.cos());
+    if hour_angle_cosine > 1.0 {
+        return None;
+    }
+    if hour_angle_cosine < -1.0 {
+        return Some(-SECONDS_PER_DAY / 60.0);
+    }
+
+    // Half the length of the day, as a fraction of a whole day.
+    let half_day = hour_angle_cosine.acos().to_degrees() / 360.0;
+    let days_from_daylight = (julian_day - transit).abs() - half_day;
+    Some(days_from_daylight * SECONDS_PER_DAY / 60.0)
+}
+
+/// The Julian day of solar noon on a day counted from J2000, and the ecliptic longitude of the
+/// sun, in radians.
+fn solar_noon(day: f64, longitude: f64) -> (f64, f64) {
+    let mean_solar_time = day - longitude / 360.0;
+    let mean_anomaly = 0.985_600_28f64
+        .mul_add(mean_solar_time, 357.5291)
+        .rem_euclid(360.0)
+        .to_radians();
+    let centre = 0.0003f64.mul_add(
+        (3.0 * mean_anomaly).sin(),
+        1.9148f64.mul_add(mean_anomaly.sin(), 0.02 * (2.0 * mean_anomaly).sin()),
+    );
+    let ecliptic_longitude = (mean_anomaly.to_degrees() + centre + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit = 0.0069f64.mul_add(
+        -(2.0 * ecliptic_longitude).sin(),
+        0.0053f64.mul_add(mean_anomaly.sin(), J2000_JULIAN_DAY + mean_solar_time),
+    );
+    (transit, ecliptic_longitude)
+}
+
+/// Warm a colour. The alpha channel is never changed.
+fn warm(
+    colour: termwiz::color::SrgbaTuple,
+    multipliers: (f32, f32, f32),
+) -> termwiz::color::SrgbaTuple {
+    termwiz::color::SrgbaTuple(
+        colour.0 * multipliers.0,
+        colour.1 * multipliers.1,
+        colour.2 * multipliers.2,
+        colour.3,
+    )
+}
+
+/// Warm the colours of every cell of a frame, if the night light is on.
+pub(crate) fn apply(config: &Config, frame: &mut termwiz::surface::Surface) {
+    if !config.enabled || config.temperature >= NEUTRAL_TEMPERATURE {
+        return;
+    }
+
+    let unix_seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let strength = strength(config, unix_seconds);
+    if strength <= 0.0 {
+        return;
+    }
+
+    let full = temperature_to_multipliers(config.temperature);
+    let blend = |multiplier: f32| (multiplier - 1.0).mul_add(strength, 1.0);
+    let multipliers = (blend(full.0), blend(full.1), blend(full.2));
+
+    for line in &mut frame.screen_cells().iter_mut() {
+        for cell in line.iter_mut() {
+            let is_text = crate::compositor::Compositor::is_text(cell);
+            let attributes = cell.attrs_mut();
+            if config.include_text || !is_text {
+                if let Some(colour) =
+                    crate::blender::Blender::extract_colour(attributes.foreground())
+                {
+                    attributes.set_foreground(
+                        termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(warm(
+                            colour,
+                            multipliers,
+                        )),
+                    );
+                }
+            }
+            if let Some(colour) = crate::blender::Blender::extract_colour(attributes.background()) {
+                attributes.set_background(
+                    termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(warm(
+                        colour,
+                        multipliers,
+                    )),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Noon, UTC, on the 21st of June 2025.
+    const MIDSUMMER_NOON: f64 = 1_750_507_200.0;
+
+    #[test]
+    fn lower_temperatures_are_warmer() {
+        let neutral = temperature_to_multipliers(NEUTRAL_TEMPERATURE);
+        assert!((neutral.0 - 1.0).abs() < 0.01);
+        assert!((neutral.1 - 1.0).abs() < 0.02);
+        assert!((neutral.2 - 1.0).abs() < 0.01);
+
+        let warm = temperature_to_multipliers(3400.0);
+        assert!((warm.0 - 1.0).abs() < f32::EPSILON);
+        assert!(warm.1 < neutral.1);
+        assert!(warm.2 < warm.1);
+    }
+
+    #[test]
+    fn the_sun_schedule_is_on_at_night() {
+        let london = Config {
+            schedule: Schedule::Sun,
+            latitude: 51.5,
+            longitude: 0.0,
+            ..Config::default()
+        };
+        assert!(strength(&london, MIDSUMMER_NOON) <= 0.0);
+        assert!((strength(&london, MIDSUMMER_NOON + 12.0 * 3600.0) - 1.0).abs() < f32::EPSILON);
+
+        // Sunset in London on midsummer's day is at about 20:21 UTC.
+        let sunset = MIDSUMMER_NOON + 8.35 * 3600.0;
+        let fading = strength(&london, sunset);
+        assert!(fading > 0.2 && fading < 0.8);
+
+        let always = Config::default();
+        assert!((strength(&always, MIDSUMMER_NOON) - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn polar_days_and_nights() {
+        let svalbard = (78.0, 15.0);
+        assert!(minutes_after_sunset(MIDSUMMER_NOON, svalbard.0, svalbard.1).unwrap() < 0.0);
+        let midwinter_noon = MIDSUMMER_NOON + 183.0 * SECONDS_PER_DAY;
+        assert_eq!(
+            minutes_after_sunset(midwinter_noon, svalbard.0, svalbard.1),
+            None
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//! Gradients of opacity for background tattoys, so that, for example, a shader can fade out
+//! towards the bottom of the terminal, where the prompt is and where most reading happens.
+
+/// Which way the opacity changes.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Direction {
+    /// From the top row to the bottom row.
+    #[default]
+    Rows,
+    /// From the left-hand column, as `top`, to the right-hand column, as `bottom`.
+    Columns,
+}
+
+/// A linear gradient of opacity across a tattoy. The values multiply the tattoy's own opacity.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub(crate) struct Gradient {
+    /// The opacity of the first row, or column.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub top: f32,
+    /// The opacity of the last row, or column.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub bottom: f32,
+    /// Whether the opacity changes across rows or columns.
+    pub direction: Direction,
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self {
+            top: 1.0,
+            bottom: 1.0,
+            direction: Direction::default(),
+        }
+    }
+}
+
+impl Gradient {
+    /// The amount to multiply a tattoy's opacity by, for the cell at `x`, `y` of a frame of the
+    /// given size.
+    pub fn multiplier(&self, x: usize, y: usize, (width, height): (usize, usize)) -> f32 {
+        let (position, length) = match self.direction {
+            Direction::Rows => (y, height),
+            Direction::Columns => (x, width),
+        };
+        let to_float = |value: usize| u16::try_from(value).map_or(f32::from(u16::MAX), f32::from);
+        let last = to_float(length.saturating_sub(1));
+        if last <= 0.0 {
+            return self.top.clamp(0.0, 1.0);
+        }
+
+        let progress = (to_float(position) / last).clamp(0.0, 1.0);
+        (self.bottom - self.top)
+            .mul_add(progress, self.top)
+            .clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn opacity_fades_from_top_to_bottom() {
+        let gradient = Gradient {
+            top: 1.0,
+            bottom: 0.2,
+            ..Gradient::default()
+        };
+        let size = (10, 5);
+        assert!((gradient.multiplier(3, 0, size) - 1.0).abs() < f32::EPSILON);
+        assert!((gradient.multiplier(3, 2, size) - 0.6).abs() < 0.001);
+        assert!((gradient.multiplier(3, 4, size) - 0.2).abs() < 0.001);
+        assert!((gradient.multiplier(0, 0, (10, 1)) - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn opacity_can_fade_across_columns() {
+        let gradient = Gradient {
+            top: 0.0,
+            bottom: 1.0,
+            direction: Direction::Columns,
+        };
+        let size = (11, 5);
+        assert!(gradient.multiplier(0, 4, size).abs() < f32::EPSILON);
+        assert!((gradient.multiplier(5, 4, size) - 0.5).abs() < 0.001);
+        assert!((gradient.multiplier(10, 0, size) - 1.0).abs() < f32::EPSILON);
+    }
+}
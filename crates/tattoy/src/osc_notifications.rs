@@ -0,0 +1,105 @@
+//! Notifications from the applications and scripts running in the PTY, sent with Tattoy's own
+//! `OSC 5522` escape sequence:
+//!
+//! ```sh
+//! printf '\e]5522;info;Build finished;All 42 tests passed\a'
+//! ```
+//!
+//! The parts are the level, one of "error", "warn", "info", "debug" or "trace", the title and an
+//! optional body. The sequence can end with either `BEL` or `ESC \`.
+
+/// The longest title, or body, that's shown. Anything longer is cut short, so that a misbehaving
+/// application can't fill the screen.
+const MAX_LENGTH: usize = 256;
+
+/// A notification from an application.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Notification {
+    /// The level.
+    level: crate::tattoys::notifications::message::Level,
+    /// The title.
+    title: String,
+    /// The optional body.
+    body: Option<String>,
+}
+
+impl Notification {
+    /// Parse the contents of a sequence, between `OSC 5522;` and its terminator.
+    fn parse(contents: &str) -> Option<Self> {
+        let mut parts = contents.splitn(3, ';');
+        let level = serde_json::from_value(serde_json::Value::String(
+            parts.next()?.trim().to_lowercase(),
+        ))
+        .ok()?;
+        let clean = |text: &str| -> String {
+            text.chars()
+                .filter(|character| !character.is_control() || *character == '\n')
+                .take(MAX_LENGTH)
+                .collect()
+        };
+        let title = clean(parts.next()?.trim());
+        if title.is_empty() {
+            return None;
+        }
+        let body = parts.next().map(clean).filter(|body| !body.is_empty());
+
+        Some(Self { level, title, body })
+    }
+}
+
+/// Show the notification in the contents of a sequence, unless the user ignores them. The PTY
+/// relay finds the sequences, see `crate::pty_relay`.
+pub(crate) async fn show(state: &crate::shared_state::SharedState, contents: &str) {
+    let Some(notification) = Notification::parse(contents) else {
+        tracing::debug!("Invalid escape sequence notification: {contents:?}");
+        return;
+    };
+    if state
+        .config
+        .read()
+        .await
+        .notifications
+        .ignore_escape_sequences
+    {
+        tracing::debug!("Ignoring escape sequence notification: {notification:?}");
+        return;
+    }
+
+    tracing::debug!("Notification from the PTY: {notification:?}");
+    state
+        .send_notification(
+            &notification.title,
+            notification.level,
+            notification.body,
+            false,
+        )
+        .await;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tattoys::notifications::message::Level;
+
+    #[test]
+    fn parses_notifications() {
+        assert_eq!(
+            Notification::parse("INFO;Build finished;All tests passed; in 3s"),
+            Some(Notification {
+                level: Level::Info,
+                title: "Build finished".to_owned(),
+                body: Some("All tests passed; in 3s".to_owned()),
+            })
+        );
+        assert_eq!(
+            Notification::parse("error;Oops"),
+            Some(Notification {
+                level: Level::Error,
+                title: "Oops".to_owned(),
+                body: None,
+            })
+        );
+        assert!(Notification::parse("loud;Oops").is_none());
+        assert!(Notification::parse("info;").is_none());
+    }
+}
@@ -0,0 +1,237 @@
+//! Pausing expensive tattoys, like shaders, during bursts of output from the PTY, for example
+//! whilst `cat`ing a huge file. That way the terminal stays responsive, and the tattoys resume
+//! once the output has calmed down. Paused tattoys keep showing their last frame.
+//!
+//! The start and end of each burst are broadcast as `Protocol::OutputBurst`, and can optionally
+//! be shown as notifications.
+
+use color_eyre::eyre::Result;
+
+/// How often the output's throughput is measured.
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// User-configurable settings for pausing tattoys during bursts of output.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to pause tattoys during bursts of output.
+    pub enabled: bool,
+    /// How many characters of output per second count as a burst.
+    pub threshold: u32,
+    /// How many seconds the output has to be below the threshold before tattoys resume.
+    pub resume_after: f32,
+    /// The IDs of the tattoys to pause, eg: `shader` or `minimap`.
+    pub tattoys: Vec<String>,
+    /// Whether to show a notification when tattoys are paused and resumed.
+    pub notify: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 50_000,
+            resume_after: 1.0,
+            tattoys: ["shader", "bloom", "crt", "minimap"]
+                .map(str::to_owned)
+                .to_vec(),
+            notify: false,
+        }
+    }
+}
+
+/// The number of characters of text in some output from the PTY. Only changes to the screen are
+/// counted, because changes to the scrollback are the same output again.
+fn characters_in(output: &shadow_terminal::output::native::Output) -> usize {
+    let shadow_terminal::output::native::Output::Diff(
+        shadow_terminal::output::native::SurfaceDiff::Screen(diff),
+    ) = output
+    else {
+        return 0;
+    };
+
+    diff.changes
+        .iter()
+        .map(|change| match change {
+            shadow_terminal::termwiz::surface::Change::Text(text) => text.chars().count(),
+            #[expect(
+                clippy::wildcard_enum_match_arm,
+                reason = "Only text is output that the user reads"
+            )]
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Measures the throughput of the PTY's output.
+#[derive(Debug, Default)]
+struct Throughput {
+    /// The characters of output since the last check.
+    characters: usize,
+    /// The last time that the output was above the threshold.
+    busy_at: Option<tokio::time::Instant>,
+    /// Whether there's currently a burst.
+    is_bursting: bool,
+}
+
+impl Throughput {
+    /// Check the throughput since the last check, `elapsed` ago. Returns whether there's a burst,
+    /// but only when that's changed.
+    fn check(
+        &mut self,
+        config: &Config,
+        elapsed: std::time::Duration,
+        now: tokio::time::Instant,
+    ) -> Option<bool> {
+        let characters = u32::try_from(std::mem::take(&mut self.characters)).unwrap_or(u32::MAX);
+        if !config.enabled {
+            return std::mem::take(&mut self.is_bursting).then_some(false);
+        }
+
+        let seconds = elapsed.as_secs_f64();
+        if seconds > 0.0 && f64::from(characters) / seconds >= f64::from(config.threshold) {
+            self.busy_at = Some(now);
+            if !self.is_bursting {
+                self.is_bursting = true;
+                return Some(true);
+            }
+            return None;
+        }
+
+        let is_calm = self
+            .busy_at
+            .is_none_or(|busy_at| now.duration_since(busy_at).as_secs_f32() >= config.resume_after);
+        if self.is_bursting && is_calm {
+            self.is_bursting = false;
+            return Some(false);
+        }
+        None
+    }
+}
+
+/// Watches the PTY's output for bursts.
+pub(crate) struct OutputBurst;
+
+impl OutputBurst {
+    /// Start the task that watches for bursts of output.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let mut protocol = state.protocol_tx.subscribe();
+            let mut throughput = Throughput::default();
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            let mut checked_at = tokio::time::Instant::now();
+
+            #[expect(
+                clippy::integer_division_remainder_used,
+                reason = "This is caused by the `tokio::select!`"
+            )]
+            loop {
+                tokio::select! {
+                    now = interval.tick() => {
+                        let config = state.config.read().await.output_burst.clone();
+                        let maybe_change = throughput.check(&config, now - checked_at, now);
+                        checked_at = now;
+                        if let Some(is_bursting) = maybe_change {
+                            Self::change(&state, &config, is_bursting).await?;
+                        }
+                    },
+                    message = protocol.recv() => match message {
+                        Ok(crate::run::Protocol::End)
+                        | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        Ok(crate::run::Protocol::Output(output)) => {
+                            throughput.characters += characters_in(&output);
+                        }
+                        Ok(_) => (),
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Output burst detection lagged behind by {skipped} messages");
+                        }
+                    }
+                }
+            }
+
+            tracing::debug!("Leaving output burst detection loop");
+            Ok(())
+        })
+    }
+
+    /// Pause or resume the expensive tattoys.
+    async fn change(
+        state: &crate::shared_state::SharedState,
+        config: &Config,
+        is_bursting: bool,
+    ) -> Result<()> {
+        tracing::debug!("Output burst: {is_bursting}");
+        *state.is_output_bursting.write().await = is_bursting;
+        state
+            .protocol_tx
+            .send(crate::run::Protocol::OutputBurst(is_bursting))?;
+
+        if config.notify {
+            let title = if is_bursting {
+                "Pausing tattoys during lots of output"
+            } else {
+                "Resuming tattoys"
+            };
+            state
+                .send_notification(
+                    title,
+                    crate::tattoys::notifications::message::Level::Info,
+                    None,
+                    false,
+                )
+                .await;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const QUARTER_SECOND: std::time::Duration = std::time::Duration::from_millis(250);
+
+    #[test]
+    fn bursts_start_and_then_end_once_calm() {
+        let config = Config::default();
+        let mut throughput = Throughput::default();
+        let start = tokio::time::Instant::now();
+
+        throughput.characters = 100;
+        assert_eq!(throughput.check(&config, QUARTER_SECOND, start), None);
+
+        throughput.characters = 20_000;
+        let busy = start + QUARTER_SECOND;
+        assert_eq!(throughput.check(&config, QUARTER_SECOND, busy), Some(true));
+        throughput.characters = 20_000;
+        assert_eq!(throughput.check(&config, QUARTER_SECOND, busy), None);
+
+        let not_calm_yet = busy + std::time::Duration::from_millis(500);
+        assert_eq!(
+            throughput.check(&config, QUARTER_SECOND, not_calm_yet),
+            None
+        );
+        let calm = busy + std::time::Duration::from_secs(1);
+        assert_eq!(throughput.check(&config, QUARTER_SECOND, calm), Some(false));
+        assert_eq!(throughput.check(&config, QUARTER_SECOND, calm), None);
+    }
+
+    #[test]
+    fn disabling_ends_a_burst() {
+        let mut config = Config::default();
+        let mut throughput = Throughput::default();
+        let now = tokio::time::Instant::now();
+
+        throughput.characters = 1_000_000;
+        assert_eq!(throughput.check(&config, QUARTER_SECOND, now), Some(true));
+
+        config.enabled = false;
+        throughput.characters = 1_000_000;
+        assert_eq!(throughput.check(&config, QUARTER_SECOND, now), Some(false));
+        throughput.characters = 1_000_000;
+        assert_eq!(throughput.check(&config, QUARTER_SECOND, now), None);
+    }
+}
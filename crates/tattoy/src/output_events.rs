@@ -0,0 +1,326 @@
+//! Match the PTY's output against user-configured regexes and broadcast named events, like
+//! `tests_passed` or `build_failed`, on the Tattoy protocol. Any tattoy can then react to them.
+
+use color_eyre::eyre::Result;
+
+/// A user-configured output event.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Config {
+    /// The name of the event that is broadcast.
+    pub name: String,
+    /// The regex that is matched against every line of the PTY's screen.
+    pub regex: String,
+    /// Whether to also show a notification when the event happens.
+    #[serde(default)]
+    pub notify: bool,
+    /// A celebration, like `confetti`, that bursts out of the matching text.
+    #[serde(default)]
+    pub celebrate: Option<crate::tattoys::celebration::Kind>,
+}
+
+/// The events that Tattoy comes with.
+pub(crate) fn default_events() -> Vec<Config> {
+    vec![
+        Config {
+            name: "tests_passed".to_owned(),
+            regex: r"(?i)(test result: ok\.|all tests passed)".to_owned(),
+            notify: false,
+            celebrate: None,
+        },
+        Config {
+            name: "build_failed".to_owned(),
+            regex: r"^error(\[E\d+\])?:".to_owned(),
+            notify: false,
+            celebrate: None,
+        },
+    ]
+}
+
+/// A compiled output event.
+struct Matcher {
+    /// The name of the event.
+    name: String,
+    /// The compiled regex.
+    regex: regex::Regex,
+    /// Whether to also show a notification.
+    notify: bool,
+    /// The celebration, if any.
+    celebrate: Option<crate::tattoys::celebration::Kind>,
+}
+
+/// `OutputEvents`
+pub(crate) struct OutputEvents {
+    /// The application shared state
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+    /// The events exactly as the user configured them, so we know when they change.
+    configs: Vec<Config>,
+    /// The compiled events.
+    matchers: Vec<Matcher>,
+    /// How many times each event's matching lines appeared on the screen the last time we looked.
+    /// This is how we know the difference between new matches and old ones that are still on the
+    /// screen.
+    previous_matches: std::collections::BTreeMap<(String, String), usize>,
+}
+
+impl OutputEvents {
+    /// Start the task that watches the PTY's output.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let mut protocol = state.protocol_tx.subscribe();
+            let configs = state.config.read().await.events.clone();
+            let mut events = Self {
+                state,
+                configs: Vec::new(),
+                matchers: Vec::new(),
+                previous_matches: std::collections::BTreeMap::new(),
+            };
+            events.update(&configs).await;
+
+            loop {
+                match protocol.recv().await {
+                    Ok(crate::run::Protocol::End) => break,
+                    Ok(message) => events.handle_protocol_message(&message).await?,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Output events lagged behind by {skipped} messages");
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+
+            tracing::debug!("Leaving output events loop");
+            Ok(())
+        })
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: &crate::run::Protocol) -> Result<()> {
+        if let crate::run::Protocol::Config(config) = message {
+            self.update(&config.events).await;
+        }
+
+        if crate::tattoys::tattoyer::Tattoyer::is_screen_output_changed(message) {
+            self.check().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Recompile the events if the user has changed them.
+    async fn update(&mut self, configs: &[Config]) {
+        if configs == self.configs {
+            return;
+        }
+
+        let errors = self.compile(configs);
+        if errors.is_empty() {
+            return;
+        }
+
+        let title = "Invalid output event";
+        tracing::warn!("{title}: {errors:?}");
+        self.state
+            .send_notification(
+                title,
+                crate::tattoys::notifications::message::Level::Warn,
+                Some(errors.join("\n")),
+                false,
+            )
+            .await;
+    }
+
+    /// Compile the user's regexes. Invalid ones are skipped, and their errors returned.
+    fn compile(&mut self, configs: &[Config]) -> Vec<String> {
+        self.configs = configs.to_vec();
+        self.matchers.clear();
+        let mut errors = Vec::new();
+        for config in configs {
+            match regex::Regex::new(&config.regex) {
+                Ok(regex) => self.matchers.push(Matcher {
+                    name: config.name.clone(),
+                    regex,
+                    notify: config.notify,
+                    celebrate: config.celebrate,
+                }),
+                Err(error) => errors.push(format!("{}: {error}", config.name)),
+            }
+        }
+        errors
+    }
+
+    /// Broadcast any events that match new lines of the PTY's screen.
+    async fn check(&mut self) -> Result<()> {
+        if self.matchers.is_empty() {
+            return Ok(());
+        }
+
+        let screen = self
+            .state
+            .shadow_tty_screen
+            .read()
+            .await
+            .screen_chars_to_string();
+        for (name, notify) in self.new_events(&screen) {
+            tracing::debug!("Output event: {name}");
+            self.state
+                .protocol_tx
+                .send(crate::run::Protocol::OutputEvent(name.clone()))?;
+            if notify {
+                self.state
+                    .send_notification(
+                        &name,
+                        crate::tattoys::notifications::message::Level::Info,
+                        None,
+                        false,
+                    )
+                    .await;
+            }
+            self.celebrate(&name, &screen)?;
+        }
+
+        Ok(())
+    }
+
+    /// Celebrate an event, if it's configured to, from where it matched.
+    fn celebrate(&self, name: &str, screen: &str) -> Result<()> {
+        let Some(matcher) = self.matchers.iter().find(|matcher| matcher.name == name) else {
+            return Ok(());
+        };
+        let Some(kind) = matcher.celebrate else {
+            return Ok(());
+        };
+        if let Some(origin) = origin(&matcher.regex, screen) {
+            self.state
+                .protocol_tx
+                .send(crate::run::Protocol::Celebrate { kind, origin })?;
+        }
+        Ok(())
+    }
+
+    /// Find the events that match lines of output that weren't on the screen the last time we
+    /// looked.
+    fn new_events(&mut self, screen: &str) -> Vec<(String, bool)> {
+        let mut matches = std::collections::BTreeMap::<(String, String), usize>::new();
+        for line in screen.lines() {
+            for matcher in &self.matchers {
+                if matcher.regex.is_match(line) {
+                    let key = (matcher.name.clone(), line.trim_end().to_owned());
+                    *matches.entry(key).or_default() += 1;
+                }
+            }
+        }
+
+        let mut events = Vec::new();
+        for matcher in &self.matchers {
+            let is_new_match = matches.iter().any(|((name, line), count)| {
+                name == &matcher.name
+                    && self
+                        .previous_matches
+                        .get(&(name.clone(), line.clone()))
+                        .is_none_or(|previous| count > previous)
+            });
+            if is_new_match {
+                events.push((matcher.name.clone(), matcher.notify));
+            }
+        }
+        self.previous_matches = matches;
+
+        events
+    }
+}
+
+/// The cell, as column and row, where the regex last matches the screen. The last match is the
+/// newest one, because new output is at the bottom.
+fn origin(regex: &regex::Regex, screen: &str) -> Option<(u16, u16)> {
+    screen
+        .lines()
+        .enumerate()
+        .filter_map(|(row, line)| {
+            let found = regex.find(line)?;
+            let column = line.get(..found.start())?.chars().count();
+            Some((column.try_into().ok()?, row.try_into().ok()?))
+        })
+        .last()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn events() -> OutputEvents {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1024);
+        let mut events = OutputEvents {
+            state: crate::shared_state::SharedState::init(1, 1, protocol_tx)
+                .await
+                .unwrap(),
+            configs: Vec::new(),
+            matchers: Vec::new(),
+            previous_matches: std::collections::BTreeMap::new(),
+        };
+        let errors = events.compile(&default_events());
+        assert!(errors.is_empty());
+        events
+    }
+
+    fn names(events: &[(String, bool)]) -> Vec<&str> {
+        events.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn only_new_matches_are_events() {
+        let mut events = events().await;
+
+        assert!(events.new_events("$ cargo test\nrunning 1 test").is_empty());
+        assert_eq!(
+            names(&events.new_events("$ cargo test\ntest result: ok. 1 passed")),
+            vec!["tests_passed"]
+        );
+        assert!(events
+            .new_events("$ cargo test\ntest result: ok. 1 passed\n$")
+            .is_empty());
+        assert_eq!(
+            names(
+                &events.new_events(
+                    "test result: ok. 1 passed\n$ cargo test\ntest result: ok. 1 passed"
+                )
+            ),
+            vec!["tests_passed"]
+        );
+        assert_eq!(
+            names(&events.new_events("error[E0308]: mismatched types")),
+            vec!["build_failed"]
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_regexes_are_reported() {
+        let mut events = events().await;
+        let errors = events.compile(&[
+            Config {
+                name: "broken".to_owned(),
+                regex: "(".to_owned(),
+                notify: false,
+                celebrate: None,
+            },
+            Config {
+                name: "working".to_owned(),
+                regex: "ok".to_owned(),
+                notify: false,
+                celebrate: None,
+            },
+        ]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(events.matchers.len(), 1);
+    }
+
+    #[test]
+    fn celebrations_start_at_the_newest_match() {
+        let regex = regex::Regex::new("ok").unwrap();
+        assert_eq!(
+            origin(&regex, "ok\n$ cargo test\n  → ok. 1 passed"),
+            Some((4, 2))
+        );
+        assert_eq!(origin(&regex, "failed"), None);
+    }
+}
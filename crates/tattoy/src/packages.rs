@@ -0,0 +1,358 @@
+//! `tattoy install` and `tattoy uninstall`: community shader packs and tattoy presets, from a
+//! curated index.
+//!
+//! The index is a static JSON file listing every package and the files that it's made of. Every
+//! file has a SHA-256 checksum, and nothing is installed unless all of a package's files match
+//! their checksums. Files are installed relative to the config directory, so a shader pack might
+//! install `shaders/pack/glow.glsl` and a preset might install `presets/neon.toml`, to be used
+//! with `--main-config presets/neon.toml`.
+//!
+//! What's installed, and the checksums of what was installed, is recorded so that uninstalling
+//! only removes files that the user hasn't since changed.
+
+#![expect(clippy::print_stdout, reason = "We need to give user feedback")]
+
+use color_eyre::eyre::{ContextCompat as _, Result};
+use sha2::Digest as _;
+
+/// The default index of packages.
+pub const DEFAULT_INDEX: &str =
+    "https://raw.githubusercontent.com/tattoy-org/packages/main/index.json";
+
+/// The name of the file, in the config directory, that records which packages are installed.
+const INSTALLED_FILE_NAME: &str = "installed_packages.toml";
+
+/// The curated index of packages.
+#[derive(serde::Deserialize, Debug)]
+struct Index {
+    /// Every package that can be installed.
+    packages: Vec<Package>,
+}
+
+/// A shader pack, or tattoy preset.
+#[derive(serde::Deserialize, Debug, Clone)]
+struct Package {
+    /// The name that the package is installed by.
+    name: String,
+    /// What the package is.
+    #[serde(default)]
+    description: String,
+    /// The files that make up the package.
+    files: Vec<File>,
+}
+
+/// A single file of a package.
+#[derive(serde::Deserialize, Debug, Clone)]
+struct File {
+    /// Where the file goes, relative to the config directory.
+    path: std::path::PathBuf,
+    /// Where the file is downloaded from.
+    url: String,
+    /// The hex-encoded SHA-256 checksum of the file.
+    sha256: String,
+}
+
+/// A record of what's been installed.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, PartialEq, Eq)]
+struct Installed {
+    /// The installed packages, by name.
+    #[serde(default)]
+    packages: std::collections::BTreeMap<String, InstalledPackage>,
+}
+
+/// A package that's been installed.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, PartialEq, Eq)]
+struct InstalledPackage {
+    /// The checksums of the installed files, by their path relative to the config directory.
+    files: std::collections::BTreeMap<String, String>,
+}
+
+impl Installed {
+    /// Load the record of installed packages. No record means that nothing's been installed.
+    fn load(directory: &std::path::Path) -> Result<Self> {
+        let path = directory.join(INSTALLED_FILE_NAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Save the record of installed packages.
+    fn save(&self, directory: &std::path::Path) -> Result<()> {
+        std::fs::write(directory.join(INSTALLED_FILE_NAME), toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// The checksum that a file had when a package installed it.
+    fn checksum_of(&self, package: &str, path: &str) -> Option<&String> {
+        self.packages.get(package)?.files.get(path)
+    }
+}
+
+/// Install a package, or list all the packages if no name is given. Files that would replace
+/// different files of the same name are only replaced when forced, and are then backed up.
+pub(crate) async fn install(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    name: Option<&str>,
+    is_forced: bool,
+) -> Result<()> {
+    let index_url = state.config.read().await.package_index.clone();
+    let index =
+        serde_json::from_str::<Index>(&crate::utils::curl(&["--location"], &index_url).await?)?;
+
+    let Some(name) = name else {
+        for package in &index.packages {
+            println!("{:<24} {}", package.name, package.description);
+        }
+        println!("\nInstall one with `tattoy install <name>`.");
+        return Ok(());
+    };
+
+    let package = index
+        .packages
+        .iter()
+        .find(|package| package.name == name)
+        .with_context(|| {
+            format!("There's no package called `{name}`. Run `tattoy install` to see them all.")
+        })?;
+    for file in &package.files {
+        validate_path(&file.path)?;
+    }
+
+    let directory = crate::config::main::Config::directory(state).await;
+    let mut installed = Installed::load(&directory)?;
+    let downloads = download(package).await?;
+
+    let conflicts = conflicts(&directory, &installed, package, &downloads);
+    if !conflicts.is_empty() && !is_forced {
+        color_eyre::eyre::bail!(
+            "Nothing was installed, because these files already exist:\n  {}\n\nUse `--force` \
+            to replace them. The originals will be backed up with a `.bak` extension.",
+            conflicts
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<String>>()
+                .join("\n  ")
+        );
+    }
+    for conflict in &conflicts {
+        let target = directory.join(conflict);
+        let backup = target.with_extension(backup_extension(&target));
+        std::fs::copy(&target, &backup)?;
+        println!("Backed up {} to {}", conflict.display(), backup.display());
+    }
+
+    let mut record = InstalledPackage::default();
+    for (file, bytes) in package.files.iter().zip(&downloads) {
+        let target = directory.join(&file.path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, bytes)?;
+        record
+            .files
+            .insert(file.path.display().to_string(), sha256(bytes));
+    }
+    installed.packages.insert(package.name.clone(), record);
+    installed.save(&directory)?;
+
+    println!("Installed `{name}` into {}", directory.display());
+    Ok(())
+}
+
+/// Uninstall a package. Files that have been changed since they were installed are kept.
+pub(crate) async fn uninstall(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    name: &str,
+) -> Result<()> {
+    let directory = crate::config::main::Config::directory(state).await;
+    let mut installed = Installed::load(&directory)?;
+    let package = installed
+        .packages
+        .remove(name)
+        .with_context(|| format!("`{name}` isn't installed"))?;
+
+    for (path, checksum) in &package.files {
+        validate_path(std::path::Path::new(path))?;
+        let target = directory.join(path);
+        let Ok(bytes) = std::fs::read(&target) else {
+            continue;
+        };
+        if sha256(&bytes) == *checksum {
+            std::fs::remove_file(&target)?;
+        } else {
+            println!("Kept {path}, because it's been changed since it was installed");
+        }
+    }
+    installed.save(&directory)?;
+
+    println!("Uninstalled `{name}`");
+    Ok(())
+}
+
+/// Download all of a package's files, checking that they all match their checksums.
+async fn download(package: &Package) -> Result<Vec<Vec<u8>>> {
+    let temporary = tempfile::tempdir()?;
+    let mut downloads = Vec::new();
+    for (index, file) in package.files.iter().enumerate() {
+        let output = temporary.path().join(index.to_string());
+        let output_argument = output.to_str().context("Temporary path isn't UTF-8")?;
+        crate::utils::curl(&["--location", "--output", output_argument], &file.url).await?;
+
+        let bytes = std::fs::read(&output)?;
+        let checksum = sha256(&bytes);
+        if !checksum.eq_ignore_ascii_case(&file.sha256) {
+            color_eyre::eyre::bail!(
+                "Nothing was installed, because {} didn't match its checksum. Expected {}, got \
+                {checksum}.",
+                file.url,
+                file.sha256
+            );
+        }
+        downloads.push(bytes);
+    }
+    Ok(downloads)
+}
+
+/// Package files must stay inside the config directory.
+pub(crate) fn validate_path(path: &std::path::Path) -> Result<()> {
+    let is_inside = path.components().count() > 0
+        && path
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)));
+    if !is_inside {
+        color_eyre::eyre::bail!(
+            "Package file `{}` isn't inside the config directory",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// The files that installing would overwrite with something different. A file that this package
+/// installed before, and that hasn't been changed since, is just upgraded.
+fn conflicts(
+    directory: &std::path::Path,
+    installed: &Installed,
+    package: &Package,
+    downloads: &[Vec<u8>],
+) -> Vec<std::path::PathBuf> {
+    package
+        .files
+        .iter()
+        .zip(downloads)
+        .filter(|(file, bytes)| {
+            let Ok(existing) = std::fs::read(directory.join(&file.path)) else {
+                return false;
+            };
+            let existing_checksum = sha256(&existing);
+            let previously_installed =
+                installed.checksum_of(&package.name, &file.path.display().to_string());
+            existing_checksum != sha256(bytes) && previously_installed != Some(&existing_checksum)
+        })
+        .map(|(file, _)| file.path.clone())
+        .collect()
+}
+
+/// The extension of a backup, keeping the original extension so that it's still recognisable.
+fn backup_extension(path: &std::path::Path) -> String {
+    match path.extension() {
+        Some(extension) => format!("{}.bak", extension.to_string_lossy()),
+        None => "bak".to_owned(),
+    }
+}
+
+/// The hex-encoded SHA-256 checksum of some bytes.
+fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", sha2::Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn package(files: &[(&str, &[u8])]) -> (Package, Vec<Vec<u8>>) {
+        let package = Package {
+            name: "pack".to_owned(),
+            description: String::new(),
+            files: files
+                .iter()
+                .map(|(path, bytes)| File {
+                    path: path.into(),
+                    url: String::new(),
+                    sha256: sha256(bytes),
+                })
+                .collect(),
+        };
+        let downloads = files.iter().map(|(_, bytes)| bytes.to_vec()).collect();
+        (package, downloads)
+    }
+
+    #[test]
+    fn checksums_are_hex_sha256() {
+        assert_eq!(
+            sha256(b"tattoy"),
+            "fbc7f35d64d32e16b0a292b1831084210c318e6847593fa3644c18bcd9634583"
+        );
+    }
+
+    #[test]
+    fn package_files_stay_in_the_config_directory() {
+        assert!(validate_path(std::path::Path::new("shaders/pack/glow.glsl")).is_ok());
+        assert!(validate_path(std::path::Path::new("../.bashrc")).is_err());
+        assert!(validate_path(std::path::Path::new("shaders/../../.bashrc")).is_err());
+        assert!(validate_path(std::path::Path::new("/etc/passwd")).is_err());
+        assert!(validate_path(std::path::Path::new("")).is_err());
+    }
+
+    #[test]
+    fn only_changed_files_conflict() {
+        let directory = tempfile::tempdir().unwrap();
+        std::fs::write(directory.path().join("same.glsl"), b"same").unwrap();
+        std::fs::write(directory.path().join("mine.glsl"), b"mine").unwrap();
+        std::fs::write(directory.path().join("old.glsl"), b"old").unwrap();
+        let (package, downloads) = package(&[
+            ("same.glsl", b"same"),
+            ("mine.glsl", b"theirs"),
+            ("old.glsl", b"new"),
+            ("missing.glsl", b"new"),
+        ]);
+
+        let mut installed = Installed::default();
+        let mut record = InstalledPackage::default();
+        record.files.insert("old.glsl".to_owned(), sha256(b"old"));
+        installed.packages.insert("pack".to_owned(), record);
+
+        assert_eq!(
+            conflicts(directory.path(), &installed, &package, &downloads),
+            [std::path::PathBuf::from("mine.glsl")]
+        );
+    }
+
+    #[test]
+    fn the_installed_record_round_trips() {
+        let directory = tempfile::tempdir().unwrap();
+        assert_eq!(
+            Installed::load(directory.path()).unwrap(),
+            Installed::default()
+        );
+
+        let mut installed = Installed::default();
+        let mut record = InstalledPackage::default();
+        record
+            .files
+            .insert("shaders/glow.glsl".to_owned(), sha256(b"glow"));
+        installed.packages.insert("pack".to_owned(), record);
+        installed.save(directory.path()).unwrap();
+        assert_eq!(Installed::load(directory.path()).unwrap(), installed);
+    }
+
+    #[test]
+    fn backups_keep_their_extension() {
+        assert_eq!(
+            backup_extension(std::path::Path::new("a/b.glsl")),
+            "glsl.bak"
+        );
+        assert_eq!(backup_extension(std::path::Path::new("a/b")), "bak");
+    }
+}
@@ -0,0 +1,187 @@
+//! How the user's terminal is divided into panes.
+
+/// The narrowest that a pane can be. Splitting is refused if it would create narrower panes.
+const MINIMUM_PANE_WIDTH: usize = 10;
+
+/// The width of the separator drawn between panes.
+pub const SEPARATOR_WIDTH: usize = 1;
+
+/// A rectangular area of the user's terminal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Region {
+    /// The column of the left edge.
+    pub x: usize,
+    /// The row of the top edge.
+    pub y: usize,
+    /// The width in columns.
+    pub width: usize,
+    /// The height in rows.
+    pub height: usize,
+}
+
+impl Region {
+    /// Is the given coordinate inside the region?
+    pub const fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+/// The arrangement of panes. Panes sit side-by-side and share the width of the terminal equally.
+///
+/// The first pane is always Tattoy's main PTY, the one that was started with Tattoy and that the
+/// terminal proxy manages. All other panes are managed by the pane manager.
+#[derive(Debug, Clone)]
+pub(crate) struct Layout {
+    /// The number of panes, including the main PTY.
+    pub count: usize,
+    /// The index of the pane that receives user input.
+    pub focused: usize,
+    /// The position of the cursor on the user's terminal when a pane other than the main PTY is
+    /// focused.
+    pub focused_cursor: (usize, usize),
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            focused: 0,
+            focused_cursor: (0, 0),
+        }
+    }
+}
+
+impl Layout {
+    /// Is the terminal currently split into more than one pane?
+    pub const fn is_split(&self) -> bool {
+        self.count > 1
+    }
+
+    /// Is the main PTY the focused pane?
+    pub const fn is_main_focused(&self) -> bool {
+        self.focused == 0
+    }
+
+    /// The regions of every pane, for a terminal of the given size.
+    pub fn regions(&self, width: usize, height: usize) -> Vec<Region> {
+        let count = self.count.max(1);
+        let separators = (count - 1) * SEPARATOR_WIDTH;
+        let available = width.saturating_sub(separators);
+        let pane_width = available.div_euclid(count);
+        let remainder = available - (pane_width * count);
+
+        let mut regions = Vec::with_capacity(count);
+        let mut x = 0;
+        for index in 0..count {
+            let is_last = index == count - 1;
+            let this_width = if is_last {
+                pane_width + remainder
+            } else {
+                pane_width
+            };
+            regions.push(Region {
+                x,
+                y: 0,
+                width: this_width,
+                height,
+            });
+            x += this_width + SEPARATOR_WIDTH;
+        }
+
+        regions
+    }
+
+    /// The region of a single pane.
+    pub fn region(&self, index: usize, width: usize, height: usize) -> Option<Region> {
+        self.regions(width, height).get(index).copied()
+    }
+
+    /// The region of the focused pane.
+    pub fn focused_region(&self, width: usize, height: usize) -> Option<Region> {
+        self.region(self.focused, width, height)
+    }
+
+    /// Add a new pane and focus it. Returns `false` if there isn't enough room.
+    pub fn split(&mut self, width: usize) -> bool {
+        let new_count = self.count + 1;
+        let separators = (new_count - 1) * SEPARATOR_WIDTH;
+        let pane_width = width.saturating_sub(separators).div_euclid(new_count);
+        if pane_width < MINIMUM_PANE_WIDTH {
+            return false;
+        }
+
+        self.count = new_count;
+        self.focused = new_count - 1;
+        true
+    }
+
+    /// Remove a pane. The main PTY can't be removed.
+    pub fn close(&mut self, index: usize) {
+        if index == 0 || index >= self.count {
+            return;
+        }
+
+        self.count -= 1;
+        if self.focused >= index {
+            self.focused = self.focused.saturating_sub(1);
+        }
+    }
+
+    /// Give focus to the next pane, wrapping round to the first.
+    pub const fn focus_next(&mut self) {
+        self.focused = (self.focused + 1).rem_euclid(self.count);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn single_pane_fills_terminal() {
+        let layout = Layout::default();
+        assert_eq!(
+            layout.regions(80, 24),
+            vec![Region {
+                x: 0,
+                y: 0,
+                width: 80,
+                height: 24
+            }]
+        );
+    }
+
+    #[test]
+    fn split_panes_share_width() {
+        let mut layout = Layout::default();
+        assert!(layout.split(80));
+        assert!(layout.split(80));
+        let regions = layout.regions(80, 24);
+        let widths: Vec<usize> = regions.iter().map(|region| region.width).collect();
+        let xs: Vec<usize> = regions.iter().map(|region| region.x).collect();
+        assert_eq!(widths, vec![26, 26, 26]);
+        assert_eq!(xs, vec![0, 27, 54]);
+        assert_eq!(layout.focused, 2);
+    }
+
+    #[test]
+    fn refuses_to_split_narrow_terminals() {
+        let mut layout = Layout::default();
+        assert!(!layout.split(15));
+        assert_eq!(layout.count, 1);
+    }
+
+    #[test]
+    fn closing_keeps_focus_sensible() {
+        let mut layout = Layout::default();
+        layout.split(80);
+        layout.split(80);
+        layout.close(2);
+        assert_eq!(layout.count, 2);
+        assert_eq!(layout.focused, 1);
+        layout.close(0);
+        assert_eq!(layout.count, 2);
+        layout.focus_next();
+        assert_eq!(layout.focused, 0);
+    }
+}
@@ -0,0 +1,364 @@
+//! Runs and renders all the panes other than Tattoy's main PTY.
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+
+/// The tattoy's ID. The renderer uses it to find the text of the other panes.
+pub const ID: &str = "panes";
+
+/// The compositing layer for panes. They're just above the main PTY, which means that tattoys
+/// below the PTY can still be seen through them.
+const LAYER: i16 = crate::layers::Group::Effects.layer(0);
+
+/// The colour of the separator between panes.
+const SEPARATOR_COLOUR: crate::surface::Colour = (0.4, 0.4, 0.4, 1.0);
+
+/// The colour of the separators either side of the focused pane.
+const FOCUSED_SEPARATOR_COLOUR: crate::surface::Colour = (0.0, 0.204, 0.631, 1.0);
+
+/// User-configurable settings for split panes.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// The command to run in new panes. Defaults to the main `command` setting.
+    command: Option<String>,
+    /// The IDs of tattoys that should only be rendered in the focused pane, eg: "shader".
+    pub focused_pane_only: Vec<String>,
+}
+
+/// A single pane with its own PTY.
+struct Pane {
+    /// An instance of a headless terminal running the pane's command.
+    terminal: shadow_terminal::active_terminal::ActiveTerminal,
+    /// Our own copy of the pane's screen.
+    screen: termwiz::surface::Surface,
+}
+
+/// Manages every pane apart from the main PTY.
+pub(crate) struct Manager {
+    /// The base Tattoy struct
+    tattoy: crate::tattoys::tattoyer::Tattoyer,
+    /// All the extra panes. The first item is the second pane in the layout, because the first
+    /// pane in the layout is always the main PTY.
+    panes: Vec<Pane>,
+    /// The user's terminal's colour palette in true colour values.
+    palette: crate::palette::converter::Palette,
+}
+
+impl Manager {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Self {
+        let mut tattoy = crate::tattoys::tattoyer::Tattoyer::new(
+            ID.to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            panes: Vec::new(),
+            palette,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        palette: crate::palette::converter::Palette,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut manager = Self::new(output, state, palette).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        manager.kill_all();
+                        break;
+                    }
+                    manager.handle_protocol_message(&message).await?;
+                    manager.tattoy.handle_common_protocol_messages(message)?;
+                }
+                () = manager.tattoy.sleep_until_next_frame_tick() => {
+                    manager.tick().await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Custom behaviour for protocol messages.
+    async fn handle_protocol_message(&mut self, message: &crate::run::Protocol) -> Result<()> {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We're only interested in a few messages"
+        )]
+        match message {
+            crate::run::Protocol::PanesChanged => self.reconcile().await?,
+            crate::run::Protocol::Resize { width, height } => {
+                self.tattoy.set_tty_size(*width, *height);
+                self.resize_panes().await?;
+            }
+            crate::run::Protocol::PaneInput(input) => self.forward_input(input).await?,
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Make sure that our panes match the layout in the shared state.
+    async fn reconcile(&mut self) -> Result<()> {
+        let layout = self.tattoy.state.panes.read().await.clone();
+        let extra_panes = layout.count.saturating_sub(1);
+
+        while self.panes.len() > extra_panes {
+            if let Some(pane) = self.panes.pop() {
+                pane.terminal.kill()?;
+            }
+        }
+
+        while self.panes.len() < extra_panes {
+            let index = self.panes.len() + 1;
+            let region = self
+                .regions(&layout)
+                .await
+                .get(index)
+                .copied()
+                .unwrap_or_default();
+            let pane = self.spawn_pane(region).await?;
+            self.panes.push(pane);
+        }
+
+        self.resize_panes().await?;
+        self.render().await
+    }
+
+    /// The regions of every pane, which don't include any row taken by the status line.
+    async fn regions(&self, layout: &super::layout::Layout) -> Vec<super::layout::Region> {
+        let config = self.tattoy.state.config.read().await;
+        let reserved_rows = config.status_line.reserved_rows();
+        let rows_above = usize::from(config.status_line.rows_above_pty());
+        drop(config);
+
+        layout
+            .regions(
+                self.tattoy.width.into(),
+                self.tattoy.height.saturating_sub(reserved_rows).into(),
+            )
+            .into_iter()
+            .map(|region| super::layout::Region {
+                y: region.y + rows_above,
+                ..region
+            })
+            .collect()
+    }
+
+    /// Start a new pane in the given region.
+    async fn spawn_pane(&self, region: super::layout::Region) -> Result<Pane> {
+        let config = self.tattoy.state.config.read().await;
+        let command = config
+            .panes
+            .command
+            .clone()
+            .unwrap_or_else(|| config.command.clone());
+        let scrollback_size = config.scrollback_size;
+        drop(config);
+
+        tracing::debug!("Starting new pane with command: `{command}`");
+        let _span = tracing::span!(tracing::Level::TRACE, "Pane").entered();
+        let terminal = shadow_terminal::active_terminal::ActiveTerminal::start(
+            shadow_terminal::shadow_terminal::Config {
+                width: region.width.try_into()?,
+                height: region.height.try_into()?,
+                command: command
+                    .split_whitespace()
+                    .map(std::convert::Into::into)
+                    .collect(),
+                scrollback_size: scrollback_size.try_into()?,
+                ..Default::default()
+            },
+        );
+
+        Ok(Pane {
+            terminal,
+            screen: termwiz::surface::Surface::new(region.width, region.height),
+        })
+    }
+
+    /// Resize every pane to fit its region of the layout.
+    async fn resize_panes(&mut self) -> Result<()> {
+        let layout = self.tattoy.state.panes.read().await.clone();
+        let regions = self.regions(&layout).await;
+        for (pane, region) in self.panes.iter_mut().zip(regions.iter().skip(1)) {
+            pane.terminal
+                .resize(region.width.try_into()?, region.height.try_into()?)?;
+            pane.screen.resize(region.width, region.height);
+        }
+
+        Ok(())
+    }
+
+    /// Send the user's input to the focused pane.
+    async fn forward_input(&self, input: &crate::raw_input::ParsedInput) -> Result<()> {
+        let focused = self.tattoy.state.panes.read().await.focused;
+        let Some(pane) = self.panes.get(focused.saturating_sub(1)) else {
+            tracing::warn!("Received input for a pane that doesn't exist: {focused}");
+            return Ok(());
+        };
+
+        for chunk in input.clone().pty_chunks()? {
+            let result = pane.terminal.send_input(chunk).await;
+            if let Err(error) = result {
+                tracing::error!("Couldn't forward STDIN bytes to pane: {error:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gather new output from every pane and remove any panes whose commands have exited.
+    async fn tick(&mut self) -> Result<()> {
+        if self.panes.is_empty() {
+            return Ok(());
+        }
+
+        let mut is_changed = false;
+        for pane in &mut self.panes {
+            while let Ok(mut output) = pane.terminal.surface_output_rx.try_recv() {
+                self.palette.convert_cells_to_true_colour(&mut output);
+                Self::apply_output(&mut pane.screen, output);
+                is_changed = true;
+            }
+        }
+
+        if let Some(exited) = self
+            .panes
+            .iter()
+            .position(|pane| pane.terminal.task_handle.is_finished())
+        {
+            tracing::debug!("Pane {} exited", exited + 1);
+            self.panes.remove(exited);
+            self.tattoy.state.panes.write().await.close(exited + 1);
+            self.tattoy
+                .state
+                .protocol_tx
+                .send(crate::run::Protocol::PanesChanged)?;
+            is_changed = true;
+        }
+
+        if is_changed {
+            self.render().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Update a pane's screen with output from its PTY.
+    fn apply_output(
+        screen: &mut termwiz::surface::Surface,
+        output: shadow_terminal::output::native::Output,
+    ) {
+        #[expect(
+            clippy::collapsible_match,
+            clippy::single_match,
+            clippy::wildcard_enum_match_arm,
+            reason = "There's some deep types going on and I think it's easier to read"
+        )]
+        match output {
+            shadow_terminal::output::native::Output::Diff(surface_diff) => match surface_diff {
+                shadow_terminal::output::native::SurfaceDiff::Screen(screen_diff) => {
+                    screen.add_changes(screen_diff.changes);
+                }
+                _ => (),
+            },
+            shadow_terminal::output::native::Output::Complete(complete_surface) => {
+                match complete_surface {
+                    shadow_terminal::output::native::CompleteSurface::Screen(complete_screen) => {
+                        *screen = complete_screen.surface;
+                    }
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Render all the panes and their separators.
+    async fn render(&mut self) -> Result<()> {
+        let mut layout = self.tattoy.state.panes.read().await.clone();
+        if !layout.is_split() {
+            self.tattoy.send_blank_output().await?;
+            return Ok(());
+        }
+
+        self.tattoy.initialise_surface();
+        let regions = self.regions(&layout).await;
+
+        let mut cells = self.tattoy.surface.surface.screen_cells();
+        for (pane, region) in self.panes.iter().zip(regions.iter().skip(1)) {
+            for (y, line) in pane.screen.get_screen_cells().iter().enumerate() {
+                for (x, pane_cell) in line.iter().enumerate() {
+                    let Some(cell) = cells
+                        .get_mut(region.y + y)
+                        .and_then(|row| row.get_mut(region.x + x))
+                    else {
+                        continue;
+                    };
+                    *cell = pane_cell.clone();
+                }
+            }
+        }
+        drop(cells);
+
+        for (index, region) in regions.iter().enumerate().skip(1) {
+            let is_next_to_focus = index == layout.focused || index == layout.focused + 1;
+            let colour = if is_next_to_focus {
+                FOCUSED_SEPARATOR_COLOUR
+            } else {
+                SEPARATOR_COLOUR
+            };
+            let separator_x = region.x.saturating_sub(super::layout::SEPARATOR_WIDTH);
+            for y in region.y..region.y + region.height {
+                self.tattoy
+                    .surface
+                    .add_text(separator_x, y, "│".into(), None, Some(colour));
+            }
+        }
+
+        if !layout.is_main_focused() {
+            if let (Some(pane), Some(region)) = (
+                self.panes.get(layout.focused.saturating_sub(1)),
+                regions.get(layout.focused),
+            ) {
+                let (cursor_x, cursor_y) = pane.screen.cursor_position();
+                layout.focused_cursor = (region.x + cursor_x, region.y + cursor_y);
+                self.tattoy.state.panes.write().await.focused_cursor = layout.focused_cursor;
+            }
+        }
+
+        self.tattoy.send_output().await
+    }
+
+    /// Kill all the panes' PTYs.
+    fn kill_all(&self) {
+        for pane in &self.panes {
+            let result = pane.terminal.kill();
+            if let Err(error) = result {
+                tracing::error!("Couldn't kill pane: {error:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,384 @@
+//! Pixel encoders turn a block of pixels into the glyph, and colours, of a single cell.
+//!
+//! Tattoy's pixels are normally half blocks, "▀" and "▄", giving 2 pixels per cell. But some fonts
+//! draw half blocks with gaps between them, so there are alternatives, trading resolution for
+//! compatibility. The glyphs that can show more than 2 pixels per cell can still only show 2
+//! colours, so their pixels are split into a light and a dark set.
+
+use shadow_terminal::termwiz;
+
+/// The glyphs of the quadrant blocks, indexed by which quadrants are filled: 1 is the upper left,
+/// 2 the upper right, 4 the lower left and 8 the lower right.
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// The first sextant glyph, from Unicode 13's "Symbols for Legacy Computing". There are sextants
+/// for every pattern except the empty, full, left half and right half ones, which already exist
+/// as other glyphs.
+const SEXTANTS_START: u32 = 0x1FB00;
+
+/// The sextant patterns that aren't sextant glyphs, with the glyphs that are used instead. The
+/// patterns are masks of the 2x3 pixels, where the top left pixel is bit 0.
+const NON_SEXTANTS: [(u32, char); 4] = [
+    (0, ' '),
+    (0b01_0101, '▌'),
+    (0b10_1010, '▐'),
+    (0b11_1111, '█'),
+];
+
+/// The bit of each dot of a braille glyph, in the order of the pixels of a 2x4 block.
+const BRAILLE_DOTS: [u32; 8] = [0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80];
+
+/// The first braille glyph, which has no dots.
+const BRAILLE_BLANK: u32 = 0x2800;
+
+/// How close in brightness pixels need to be to count as the same colour.
+const UNIFORM_TOLERANCE: f32 = 0.001;
+
+/// A cell's glyph and colours. `None` colours are the terminal's default, so transparent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Glyph {
+    /// The character.
+    pub character: char,
+    /// The foreground colour.
+    pub foreground: Option<crate::surface::Colour>,
+    /// The background colour.
+    pub background: Option<crate::surface::Colour>,
+}
+
+impl Glyph {
+    /// A cell of the glyph, with its colours set on top of the other attributes.
+    pub fn to_cell(self, mut attributes: termwiz::cell::CellAttributes) -> termwiz::cell::Cell {
+        let to_attribute = |maybe_colour: Option<crate::surface::Colour>| {
+            maybe_colour.map_or(
+                termwiz::color::ColorAttribute::Default,
+                crate::surface::Surface::make_colour_attribute,
+            )
+        };
+        attributes.set_foreground(to_attribute(self.foreground));
+        attributes.set_background(to_attribute(self.background));
+        termwiz::cell::Cell::new(self.character, attributes)
+    }
+}
+
+/// Something that can encode a block of pixels as a glyph.
+pub(crate) trait PixelEncoder {
+    /// The width and height, in pixels, of the block that fits in a cell.
+    fn block_size(&self) -> (usize, usize);
+
+    /// Encode a block of pixels, in rows from the top left, as a glyph.
+    fn encode(&self, block: &[crate::surface::Colour]) -> Glyph;
+}
+
+/// The glyphs used for pixels.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PixelGlyphs {
+    /// Half blocks, "▀" and "▄", 1x2 pixels per cell.
+    #[default]
+    HalfBlocks,
+    /// Quadrant blocks, like "▚", 2x2 pixels per cell.
+    Quadrants,
+    /// Sextant blocks, like "🬗", 2x3 pixels per cell. They need a font that supports Unicode 13.
+    Sextants,
+    /// Braille, like "⣿", 2x4 pixels per cell. The dots always have gaps between them.
+    Braille,
+    /// Just the background colour of the whole cell, which never has gaps. 1 pixel per cell.
+    FullBlocks,
+}
+
+impl PixelGlyphs {
+    /// The encoder for the glyphs.
+    pub fn encoder(self) -> &'static dyn PixelEncoder {
+        match self {
+            Self::HalfBlocks => &HalfBlocks,
+            Self::Quadrants => &Quadrants,
+            Self::Sextants => &Sextants,
+            Self::Braille => &Braille,
+            Self::FullBlocks => &FullBlocks,
+        }
+    }
+}
+
+/// Half blocks, following the rules of `Surface::add_pixel()`.
+struct HalfBlocks;
+
+impl PixelEncoder for HalfBlocks {
+    fn block_size(&self) -> (usize, usize) {
+        (1, 2)
+    }
+
+    fn encode(&self, block: &[crate::surface::Colour]) -> Glyph {
+        let visible = |index: usize| block.get(index).copied().filter(|colour| colour.3 > 0.0);
+        match (visible(0), visible(1)) {
+            (None, Some(bottom)) => Glyph {
+                character: '▄',
+                foreground: Some(bottom),
+                background: None,
+            },
+            (None, None) => Glyph {
+                character: ' ',
+                foreground: None,
+                background: None,
+            },
+            (top, bottom) => Glyph {
+                character: '▀',
+                foreground: top,
+                background: bottom,
+            },
+        }
+    }
+}
+
+/// Quadrant blocks.
+struct Quadrants;
+
+impl PixelEncoder for Quadrants {
+    fn block_size(&self) -> (usize, usize) {
+        (2, 2)
+    }
+
+    fn encode(&self, block: &[crate::surface::Colour]) -> Glyph {
+        two_colours(block, |mask| {
+            QUADRANTS
+                .get(usize::try_from(mask).unwrap_or_default())
+                .copied()
+                .unwrap_or(' ')
+        })
+    }
+}
+
+/// Sextant blocks.
+struct Sextants;
+
+impl PixelEncoder for Sextants {
+    fn block_size(&self) -> (usize, usize) {
+        (2, 3)
+    }
+
+    fn encode(&self, block: &[crate::surface::Colour]) -> Glyph {
+        two_colours(block, sextant_character)
+    }
+}
+
+/// The sextant glyph for a mask of the pixels of a 2x3 block.
+fn sextant_character(mask: u32) -> char {
+    if let Some((_, character)) = NON_SEXTANTS.iter().find(|(pattern, _)| *pattern == mask) {
+        return *character;
+    }
+    // The sextant glyphs are in the order of their masks, skipping the patterns that they don't
+    // have.
+    let skipped = NON_SEXTANTS
+        .iter()
+        .filter(|(pattern, _)| *pattern < mask)
+        .count();
+    let offset = mask - u32::try_from(skipped).unwrap_or_default();
+    char::from_u32(SEXTANTS_START + offset).unwrap_or(' ')
+}
+
+/// Braille.
+struct Braille;
+
+impl PixelEncoder for Braille {
+    fn block_size(&self) -> (usize, usize) {
+        (2, 4)
+    }
+
+    fn encode(&self, block: &[crate::surface::Colour]) -> Glyph {
+        two_colours(block, braille_character)
+    }
+}
+
+/// The braille glyph for a mask of the dots of a 2x4 block, where the first dot is bit 0.
+pub(crate) fn braille_character(mask: u32) -> char {
+    let dots = BRAILLE_DOTS
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| mask & (1_u32 << index) != 0)
+        .fold(0, |dots, (_, dot)| dots | dot);
+    char::from_u32(BRAILLE_BLANK + dots).unwrap_or(' ')
+}
+
+/// The background colour of the whole cell.
+struct FullBlocks;
+
+impl PixelEncoder for FullBlocks {
+    fn block_size(&self) -> (usize, usize) {
+        (1, 1)
+    }
+
+    fn encode(&self, block: &[crate::surface::Colour]) -> Glyph {
+        Glyph {
+            character: ' ',
+            foreground: None,
+            background: average(block.iter()),
+        }
+    }
+}
+
+/// The perceived brightness of a colour.
+fn luminance(colour: crate::surface::Colour) -> f32 {
+    0.0722_f32.mul_add(colour.2, 0.2126_f32.mul_add(colour.0, 0.7152 * colour.1))
+}
+
+/// The average of some colours, ignoring transparent ones. `None` if they're all transparent.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_precision_loss,
+    reason = "Blocks only ever have a handful of pixels"
+)]
+pub(crate) fn average<'colour>(
+    colours: impl Iterator<Item = &'colour crate::surface::Colour>,
+) -> Option<crate::surface::Colour> {
+    let visible: Vec<_> = colours.filter(|colour| colour.3 > 0.0).collect();
+    if visible.is_empty() {
+        return None;
+    }
+    let count = visible.len() as f32;
+    let sum = visible.iter().fold((0.0, 0.0, 0.0, 0.0), |sum, colour| {
+        (
+            sum.0 + colour.0,
+            sum.1 + colour.1,
+            sum.2 + colour.2,
+            sum.3 + colour.3,
+        )
+    });
+    Some((sum.0 / count, sum.1 / count, sum.2 / count, sum.3 / count))
+}
+
+/// Split a block into its lighter and darker pixels, which become the foreground and background
+/// colours of a glyph. The glyph is made from the mask of the lighter pixels, where the first
+/// pixel is bit 0. Transparent pixels are always in the darker, background, set.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_precision_loss,
+    reason = "Blocks only ever have a handful of pixels"
+)]
+fn two_colours(block: &[crate::surface::Colour], glyph: impl Fn(u32) -> char) -> Glyph {
+    let visible: Vec<_> = block.iter().filter(|colour| colour.3 > 0.0).collect();
+    let threshold = visible
+        .iter()
+        .map(|colour| luminance(**colour))
+        .sum::<f32>()
+        / visible.len().max(1) as f32;
+    let is_uniform = visible
+        .iter()
+        .all(|colour| (luminance(**colour) - threshold).abs() < UNIFORM_TOLERANCE);
+    if is_uniform && visible.len() == block.len() {
+        return Glyph {
+            character: ' ',
+            foreground: None,
+            background: average(block.iter()),
+        };
+    }
+
+    // When the visible pixels are all alike, they're lit against the transparent ones.
+    let mut mask = 0_u32;
+    for (index, colour) in block.iter().enumerate() {
+        if colour.3 > 0.0 && (is_uniform || luminance(*colour) > threshold + UNIFORM_TOLERANCE) {
+            mask |= 1_u32 << index;
+        }
+    }
+    let (lit, unlit): (Vec<_>, Vec<_>) = block
+        .iter()
+        .enumerate()
+        .partition(|(index, _)| mask & (1_u32 << index) != 0);
+    Glyph {
+        character: if mask == 0 { ' ' } else { glyph(mask) },
+        foreground: average(lit.into_iter().map(|(_, colour)| colour)),
+        background: average(unlit.into_iter().map(|(_, colour)| colour)),
+    }
+}
+
+/// The 2 pixels of a half block cell, top then bottom. `None` if the cell isn't a pixel.
+pub(crate) fn half_block_pixels(cell: &termwiz::cell::Cell) -> Option<[crate::surface::Colour; 2]> {
+    let colour = |attribute| {
+        crate::blender::Blender::extract_colour(attribute).map_or((0.0, 0.0, 0.0, 0.0), |srgba| {
+            (srgba.0, srgba.1, srgba.2, srgba.3)
+        })
+    };
+    let foreground = colour(cell.attrs().foreground());
+    let background = colour(cell.attrs().background());
+    match cell.str() {
+        "▀" => Some([foreground, background]),
+        "▄" => Some([background, foreground]),
+        _ => None,
+    }
+}
+
+/// Re-encode a half block cell with another encoder. The half block's 2 pixels are stretched to
+/// fill the encoder's block.
+pub(crate) fn re_encode(cell: &mut termwiz::cell::Cell, encoder: &dyn PixelEncoder) {
+    let Some([top, bottom]) = half_block_pixels(cell) else {
+        return;
+    };
+    let (width, height) = encoder.block_size();
+    let block: Vec<_> = (0..height)
+        .flat_map(|row| {
+            let colour = if row < height.div_ceil(2) {
+                top
+            } else {
+                bottom
+            };
+            std::iter::repeat_n(colour, width)
+        })
+        .collect();
+    *cell = encoder.encode(&block).to_cell(cell.attrs().clone());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DARK: crate::surface::Colour = (0.1, 0.1, 0.1, 1.0);
+    const LIGHT: crate::surface::Colour = (0.9, 0.9, 0.9, 1.0);
+    const CLEAR: crate::surface::Colour = (0.0, 0.0, 0.0, 0.0);
+
+    #[test]
+    fn lighter_pixels_become_the_glyph() {
+        let glyph = Quadrants.encode(&[LIGHT, DARK, DARK, LIGHT]);
+        assert_eq!(glyph.character, '▚');
+        assert_eq!(glyph.foreground, Some(LIGHT));
+        assert_eq!(glyph.background, Some(DARK));
+
+        let glyph = Braille.encode(&[LIGHT, DARK, LIGHT, DARK, LIGHT, DARK, LIGHT, DARK]);
+        assert_eq!(glyph.character, '⡇');
+    }
+
+    #[test]
+    fn sextants_skip_the_patterns_that_have_other_glyphs() {
+        assert_eq!(sextant_character(0b00_0001), '\u{1FB00}');
+        assert_eq!(sextant_character(0b01_0101), '▌');
+        assert_eq!(sextant_character(0b01_0110), '\u{1FB14}');
+        assert_eq!(sextant_character(0b11_1110), '\u{1FB3B}');
+
+        let glyph = Sextants.encode(&[LIGHT, LIGHT, DARK, DARK, DARK, DARK]);
+        assert_eq!(glyph.character, '\u{1FB02}');
+    }
+
+    #[test]
+    fn uniform_and_transparent_blocks() {
+        let glyph = Quadrants.encode(&[DARK; 4]);
+        assert_eq!(glyph.character, ' ');
+        assert_eq!(glyph.background, Some(DARK));
+
+        let glyph = Braille.encode(&[DARK, CLEAR, DARK, CLEAR, DARK, CLEAR, DARK, CLEAR]);
+        assert_eq!(glyph.character, '⡇');
+        assert_eq!(glyph.background, None);
+
+        let glyph = HalfBlocks.encode(&[CLEAR, LIGHT]);
+        assert_eq!(glyph.character, '▄');
+        assert_eq!(FullBlocks.encode(&[CLEAR]).background, None);
+    }
+
+    #[test]
+    fn half_blocks_can_be_re_encoded() {
+        let mut attributes = termwiz::cell::CellAttributes::default();
+        attributes.set_foreground(crate::surface::Surface::make_colour_attribute(LIGHT));
+        attributes.set_background(crate::surface::Surface::make_colour_attribute(DARK));
+        let mut cell = termwiz::cell::Cell::new('▀', attributes);
+        re_encode(&mut cell, PixelGlyphs::Braille.encoder());
+        assert_eq!(cell.str(), "⠛");
+    }
+}
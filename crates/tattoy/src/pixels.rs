@@ -0,0 +1,179 @@
+//! Fast paths for work on whole rows of RGBA pixels.
+//!
+//! `std::simd` isn't stable, so instead the loops work on fixed-size chunks of several pixels at
+//! once, doing the same operation to every element of a plain array. That's the shape that the
+//! compiler auto-vectorises into SIMD instructions, whilst the code stays safe and portable.
+
+/// The number of bytes in an RGBA pixel.
+pub const CHANNELS: usize = 4;
+
+/// The number of bytes handled in one vectorised step. 4 pixels is 16 `f32` lanes, which fills
+/// one AVX-512 register or four SSE/NEON registers.
+const CHUNK: usize = CHANNELS * 4;
+
+/// Linearly blend 2 rows of RGBA pixels into `output`. An `amount` of `0.0` is entirely the
+/// `from` pixels and `1.0` is entirely the `to` pixels. Channels are within 1 of what
+/// `Compositor::crossfade_pixel()` gives for each pixel.
+#[expect(
+    clippy::suboptimal_flops,
+    reason = "`mul_add()` is a library call on targets without FMA, which stops vectorisation"
+)]
+pub(crate) fn crossfade_row(from: &[u8], to: &[u8], amount: f32, output: &mut [u8]) {
+    let amount = amount.clamp(0.0, 1.0);
+    let inverse = 1.0 - amount;
+
+    let mut from_chunks = from.chunks_exact(CHUNK);
+    let mut to_chunks = to.chunks_exact(CHUNK);
+    let mut output_chunks = output.chunks_exact_mut(CHUNK);
+    for ((output_chunk, from_chunk), to_chunk) in (&mut output_chunks)
+        .zip(&mut from_chunks)
+        .zip(&mut to_chunks)
+    {
+        let mut lanes = [0.0_f32; CHUNK];
+        for ((lane, from_channel), to_channel) in lanes.iter_mut().zip(from_chunk).zip(to_chunk) {
+            *lane = f32::from(*to_channel) * amount + f32::from(*from_channel) * inverse;
+        }
+        for (channel, lane) in output_chunk.iter_mut().zip(lanes) {
+            *channel = to_channel_byte(lane);
+        }
+    }
+
+    for ((channel, from_channel), to_channel) in output_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(from_chunks.remainder())
+        .zip(to_chunks.remainder())
+    {
+        let blended = f32::from(*to_channel) * amount + f32::from(*from_channel) * inverse;
+        *channel = to_channel_byte(blended);
+    }
+}
+
+/// Round a blended channel back to a byte. Adding a half and truncating is the same as rounding
+/// for positive values, but unlike `f32::round()` it vectorises on every target.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "The blended channel is always clamped to within the range of a `u8`"
+)]
+fn to_channel_byte(blended: f32) -> u8 {
+    (blended + 0.5).clamp(0.0, 255.0) as u8
+}
+
+/// The rows of an RGBA image, from the bottom row to the top. GPU renders are upside down
+/// compared to the terminal, so this lets them be read without copying every pixel into a
+/// flipped image first.
+pub(crate) fn rows_bottom_up(image: &image::RgbaImage) -> impl Iterator<Item = &[u8]> {
+    let row_bytes = usize::try_from(image.width())
+        .unwrap_or_default()
+        .saturating_mul(CHANNELS)
+        .max(1);
+    image.as_raw().chunks_exact(row_bytes).rev()
+}
+
+/// Convert an RGBA pixel to the `0.0` to `1.0` floats that surfaces use.
+pub(crate) fn to_unit_rgba(pixel: &[u8]) -> [f32; CHANNELS] {
+    let mut unit = [0.0; CHANNELS];
+    for (lane, channel) in unit.iter_mut().zip(pixel) {
+        *lane = f32::from(*channel) / 255.0;
+    }
+    unit
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn noise(length: usize, seed: usize) -> Vec<u8> {
+        (0..length)
+            .map(|index| {
+                u8::try_from(index.wrapping_mul(31).wrapping_add(seed).rem_euclid(256)).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn crossfading_rows_matches_crossfading_pixels() {
+        // An odd number of pixels so that the remainder is handled too.
+        let from = noise(CHANNELS * 37, 3);
+        let to = noise(CHANNELS * 37, 101);
+        for amount in [0.0, 0.25, 0.5, 0.9, 1.0] {
+            let mut output = vec![0; from.len()];
+            crossfade_row(&from, &to, amount, &mut output);
+
+            for ((from_pixel, to_pixel), output_pixel) in from
+                .chunks_exact(CHANNELS)
+                .zip(to.chunks_exact(CHANNELS))
+                .zip(output.chunks_exact(CHANNELS))
+            {
+                let expected = crate::compositor::Compositor::crossfade_pixel(
+                    from_pixel.try_into().unwrap(),
+                    to_pixel.try_into().unwrap(),
+                    amount,
+                );
+                for (channel, expected_channel) in output_pixel.iter().zip(expected) {
+                    assert!(channel.abs_diff(expected_channel) <= 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rows_are_read_bottom_up() {
+        let image = image::RgbaImage::from_fn(2, 3, |_, y| [u8::try_from(y).unwrap(); 4].into());
+        let firsts = rows_bottom_up(&image)
+            .map(|row| *row.first().unwrap())
+            .collect::<Vec<u8>>();
+        assert_eq!(firsts, [2, 1, 0]);
+    }
+
+    #[test]
+    fn pixels_convert_to_unit_floats() {
+        assert_eq!(to_unit_rgba(&[0, 255, 0, 255]), [0.0, 1.0, 0.0, 1.0]);
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture crossfade_benchmark`.
+    #[test]
+    #[ignore = "It's a benchmark"]
+    #[expect(clippy::print_stdout, reason = "It's a benchmark")]
+    fn crossfade_benchmark() {
+        // The pixel size of a 200x60 terminal.
+        let (width, height) = (200, 120);
+        let from =
+            image::RgbaImage::from_raw(width, height, noise(200 * 120 * CHANNELS, 3)).unwrap();
+        let to =
+            image::RgbaImage::from_raw(width, height, noise(200 * 120 * CHANNELS, 101)).unwrap();
+        let iterations = 200;
+
+        let started = std::time::Instant::now();
+        for _ in 0..iterations {
+            let blended = image::RgbaImage::from_fn(width, height, |x, y| {
+                crate::compositor::Compositor::crossfade_pixel(
+                    from.get_pixel(x, y).0,
+                    to.get_pixel(x, y).0,
+                    0.3,
+                )
+                .into()
+            });
+            std::hint::black_box(blended);
+        }
+        let scalar = started.elapsed();
+
+        let started = std::time::Instant::now();
+        for _ in 0..iterations {
+            let mut blended = image::RgbaImage::new(width, height);
+            crossfade_row(&from, &to, 0.3, &mut blended);
+            std::hint::black_box(blended);
+        }
+        let vectorised = started.elapsed();
+
+        println!(
+            "Crossfading {width}x{height} pixels: scalar {:?}, vectorised {:?}, {:.1}x faster",
+            scalar / iterations,
+            vectorised / iterations,
+            scalar.as_secs_f64() / vectorised.as_secs_f64()
+        );
+        assert!(vectorised < scalar);
+    }
+}
@@ -0,0 +1,466 @@
+//! Run the PTY's command inside a relay: a second Tattoy process that sits between the shadow
+//! terminal and the command. The relay gives the command a PTY of its own and copies everything
+//! between the two untouched. But on the way it sees the command's raw output, so it can find
+//! what never makes it onto the shadow terminal's screen, like BEL characters and the `OSC 5522`
+//! notifications of `crate::osc_notifications`. It reports what it finds to Tattoy over a Unix
+//! socket.
+//!
+//! The relay only runs when something needs it, see `is_needed`, and only on Unix. Whether it's
+//! needed is decided once, when Tattoy starts.
+
+use color_eyre::eyre::{ContextCompat as _, Result};
+
+/// The BEL character.
+const BEL: u8 = 0x07;
+
+/// The ESC character.
+const ESC: u8 = 0x1b;
+
+/// The start of the contents of a notification sequence, see `crate::osc_notifications`.
+const NOTIFICATION_PREFIX: &[u8] = b"5522;";
+
+/// The most of an `OSC` sequence that's kept. The rest is ignored, so that a misbehaving
+/// application can't use up all the memory.
+const MAX_OSC_LENGTH: usize = 4096;
+
+/// How long to wait for the last of the command's output once it has exited. Background
+/// processes can keep the command's PTY open forever.
+const OUTPUT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Something the relay found in the PTY's output.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Event {
+    /// A BEL character that wasn't part of an escape sequence.
+    Bell,
+    /// The contents of an `OSC 5522` notification sequence, after the `5522;`.
+    Notification(String),
+}
+
+/// Where the scanner is in the output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Plain output.
+    #[default]
+    Ground,
+    /// Just after an `ESC`.
+    Escape,
+    /// Inside an `OSC` sequence. They can end with BEL, which isn't a bell then.
+    Osc,
+    /// Inside a `DCS`, `SOS`, `PM` or `APC` string, which only ends with `ST`.
+    ControlString,
+    /// Just after an `ESC` inside an `OSC` sequence or a control string, so probably `ST`.
+    StringEscape,
+}
+
+/// Finds events in the PTY's output, even when they're split across reads.
+#[derive(Debug, Default)]
+pub(crate) struct Scanner {
+    /// Where the scanner is in the output.
+    state: State,
+    /// The contents of the current `OSC` sequence.
+    osc: Vec<u8>,
+}
+
+impl Scanner {
+    /// Scan the next part of the output.
+    pub(crate) fn scan(&mut self, bytes: &[u8]) -> Vec<Event> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (State::Ground | State::Escape, BEL) => {
+                    events.push(Event::Bell);
+                    State::Ground
+                }
+                (State::Ground | State::Escape, ESC) => State::Escape,
+                (State::Escape | State::StringEscape, b']') => {
+                    self.osc.clear();
+                    State::Osc
+                }
+                (State::Escape | State::StringEscape, b'P' | b'X' | b'^' | b'_') => {
+                    self.osc.clear();
+                    State::ControlString
+                }
+                (State::Osc, BEL) | (State::StringEscape, b'\\') => {
+                    events.extend(self.finish_osc());
+                    State::Ground
+                }
+                (State::Osc | State::ControlString | State::StringEscape, ESC) => {
+                    State::StringEscape
+                }
+                (State::Osc, _) => {
+                    if self.osc.len() < MAX_OSC_LENGTH {
+                        self.osc.push(byte);
+                    }
+                    State::Osc
+                }
+                (State::ControlString, _) => State::ControlString,
+                (State::Ground | State::Escape | State::StringEscape, _) => State::Ground,
+            };
+        }
+        events
+    }
+
+    /// The event of the `OSC` sequence that just ended, if it's one that Tattoy handles.
+    fn finish_osc(&mut self) -> Option<Event> {
+        let osc = core::mem::take(&mut self.osc);
+        let contents = osc.strip_prefix(NOTIFICATION_PREFIX)?;
+        Some(Event::Notification(
+            String::from_utf8_lossy(contents).into_owned(),
+        ))
+    }
+}
+
+/// The socket that the relay reports to. It's unique to each Tattoy process.
+fn socket_path() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("tattoy-{}.relay", std::process::id()))
+}
+
+/// Whether anything needs the events that only the relay can find.
+pub(crate) fn is_needed(config: &crate::config::main::Config) -> bool {
+    let is_bell_needed = config.visual_bell.enabled && config.visual_bell.on_bel;
+    let is_notifications_needed =
+        config.notifications.enabled && !config.notifications.ignore_escape_sequences;
+    let is_sound_needed = config.sounds.enabled && config.sounds.bell.is_some();
+    let is_hook_needed = config
+        .hooks
+        .iter()
+        .any(|hook| hook.on == crate::hooks::BELL_EVENT);
+    is_bell_needed || is_notifications_needed || is_sound_needed || is_hook_needed
+}
+
+/// Run the command inside the relay, when it's needed.
+pub(crate) fn wrap(
+    config: &crate::config::main::Config,
+    command: Vec<std::ffi::OsString>,
+) -> Result<Vec<std::ffi::OsString>> {
+    if !cfg!(unix) || !is_needed(config) {
+        return Ok(command);
+    }
+
+    let mut relayed = vec![
+        std::env::current_exe()?.into_os_string(),
+        "relay".into(),
+        "--events".into(),
+        socket_path().into_os_string(),
+        "--".into(),
+    ];
+    relayed.extend(command);
+    Ok(relayed)
+}
+
+/// Parse the output of `stty size`, which is the rows and then the columns.
+fn parse_size(text: &str) -> Option<portable_pty::PtySize> {
+    let mut numbers = text.split_whitespace().map(str::parse::<u16>);
+    let rows = numbers.next()?.ok()?;
+    let cols = numbers.next()?.ok()?;
+    Some(portable_pty::PtySize {
+        rows,
+        cols,
+        ..Default::default()
+    })
+}
+
+/// The size of the PTY that the relay itself is running in.
+fn own_size() -> Result<portable_pty::PtySize> {
+    let output = std::process::Command::new("stty")
+        .arg("size")
+        .stdin(std::process::Stdio::inherit())
+        .output()?;
+    parse_size(&String::from_utf8_lossy(&output.stdout)).context("Couldn't get the PTY's size")
+}
+
+/// Turn the errors of the PTY crate into our own.
+fn pty_error(error: impl core::fmt::Display) -> color_eyre::eyre::Error {
+    color_eyre::eyre::eyre!("PTY relay: {error}")
+}
+
+/// Be the relay, if that's what Tattoy was started as. Returns the exit code of the relayed
+/// command.
+///
+/// This happens before anything else, because all of Tattoy's own setup, like restoring the
+/// user's terminal when it exits, is for the outermost Tattoy.
+pub(crate) async fn relay_if_asked() -> Result<Option<u8>> {
+    let cli_args = <crate::cli_args::CliArgs as clap::Parser>::parse();
+    let Some(crate::cli_args::Subcommand::Relay { events, command }) = cli_args.subcommand else {
+        return Ok(None);
+    };
+
+    #[cfg(not(unix))]
+    {
+        drop((events, command));
+        color_eyre::eyre::bail!("The PTY relay only runs on Unix");
+    }
+
+    #[cfg(unix)]
+    Ok(Some(relay(&events, &command).await?))
+}
+
+/// Relay between the PTY that we're running in and a new one for the command, until the command
+/// exits.
+#[cfg(unix)]
+async fn relay(socket: &std::path::Path, command: &[std::ffi::OsString]) -> Result<u8> {
+    // The command should still run even if Tattoy isn't listening.
+    let maybe_reporter = std::os::unix::net::UnixStream::connect(socket).ok();
+
+    // Tattoy sends every keypress as it is, so they have to get to the command as they are too.
+    let status = std::process::Command::new("stty")
+        .args(["raw", "-echo"])
+        .status()?;
+    if !status.success() {
+        color_eyre::eyre::bail!("Couldn't put the PTY into raw mode: {status}");
+    }
+
+    let pty = portable_pty::native_pty_system()
+        .openpty(own_size()?)
+        .map_err(pty_error)?;
+    let (program, arguments) = command.split_first().context("No command to relay")?;
+    let mut builder = portable_pty::CommandBuilder::new(program);
+    builder.args(arguments);
+    builder.cwd(std::env::current_dir()?);
+    let mut child = pty.slave.spawn_command(builder).map_err(pty_error)?;
+    drop(pty.slave);
+
+    let reader = pty.master.try_clone_reader().map_err(pty_error)?;
+    let writer = pty.master.take_writer().map_err(pty_error)?;
+    let output = std::thread::spawn(move || copy_output(reader, maybe_reporter));
+    std::thread::spawn(move || copy_input(writer));
+
+    let mut resizes =
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::window_change())?;
+    let mut waiting = tokio::task::spawn_blocking(move || child.wait());
+    #[expect(
+        clippy::integer_division_remainder_used,
+        reason = "This is caused by the `tokio::select!`"
+    )]
+    let exit_status = loop {
+        tokio::select! {
+            result = &mut waiting => break result??,
+            Some(()) = resizes.recv() => pty.master.resize(own_size()?).map_err(pty_error)?,
+        }
+    };
+
+    let finishing = tokio::task::spawn_blocking(move || output.join());
+    if tokio::time::timeout(OUTPUT_TIMEOUT, finishing)
+        .await
+        .is_err()
+    {
+        tracing::debug!("The relayed command's PTY is still open after it exited");
+    }
+
+    Ok(u8::try_from(exit_status.exit_code()).unwrap_or(u8::MAX))
+}
+
+/// Copy the command's output to our own PTY, reporting any events found on the way.
+#[cfg(unix)]
+fn copy_output(
+    mut reader: Box<dyn std::io::Read + Send>,
+    mut maybe_reporter: Option<std::os::unix::net::UnixStream>,
+) {
+    use std::io::Write as _;
+
+    let mut scanner = Scanner::default();
+    let mut stdout = std::io::stdout().lock();
+    let mut buffer = vec![0; 8192];
+    loop {
+        let length = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(length) => length,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+            // The command's PTY closes with an error once the command has exited.
+            Err(_) => break,
+        };
+        let Some(bytes) = buffer.get(..length) else {
+            break;
+        };
+        if stdout
+            .write_all(bytes)
+            .and_then(|()| stdout.flush())
+            .is_err()
+        {
+            break;
+        }
+
+        for event in scanner.scan(bytes) {
+            let Some(reporter) = maybe_reporter.as_mut() else {
+                break;
+            };
+            if report(reporter, &event).is_err() {
+                // Tattoy has stopped listening.
+                maybe_reporter = None;
+            }
+        }
+    }
+}
+
+/// Send an event to Tattoy, as a line of JSON.
+#[cfg(unix)]
+fn report(reporter: &mut std::os::unix::net::UnixStream, event: &Event) -> Result<()> {
+    use std::io::Write as _;
+
+    let mut line = serde_json::to_string(event)?;
+    line.push('\n');
+    reporter.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Copy the input from Tattoy to the command.
+#[cfg(unix)]
+fn copy_input(mut writer: Box<dyn std::io::Write + Send>) {
+    let mut stdin = std::io::stdin().lock();
+    if let Err(error) = std::io::copy(&mut stdin, &mut writer) {
+        tracing::debug!("Stopped relaying input: {error:?}");
+    }
+}
+
+/// Listens for the relay's reports.
+pub(crate) struct PtyRelay;
+
+impl PtyRelay {
+    /// Start the task that turns the relay's reports into protocol messages.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            #[cfg(unix)]
+            Self::listen(&state).await?;
+            #[cfg(not(unix))]
+            drop(state);
+
+            tracing::debug!("Leaving PTY relay loop");
+            Ok(())
+        })
+    }
+
+    /// Accept the relay's connection, and read its reports until Tattoy exits.
+    #[cfg(unix)]
+    async fn listen(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let path = socket_path();
+        Self::remove_socket(&path).await;
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        let mut connections = tokio::task::JoinSet::new();
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                result = listener.accept() => match result {
+                    Ok((stream, _)) => {
+                        connections.spawn(Self::read_reports(std::sync::Arc::clone(state), stream));
+                    }
+                    Err(error) => tracing::warn!("Couldn't accept the PTY relay: {error:?}"),
+                },
+                message = protocol.recv() => match message {
+                    Ok(crate::run::Protocol::End)
+                    | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    Ok(_) => (),
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("PTY relay loop lagged behind by {skipped} messages");
+                    }
+                }
+            }
+        }
+
+        connections.abort_all();
+        Self::remove_socket(&path).await;
+        Ok(())
+    }
+
+    /// Remove the socket, because binding fails when it already exists.
+    #[cfg(unix)]
+    async fn remove_socket(path: &std::path::Path) {
+        if let Err(error) = tokio::fs::remove_file(path).await {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                tracing::debug!("Couldn't remove {path:?}: {error:?}");
+            }
+        }
+    }
+
+    /// Read the reports from a relay, one line of JSON each.
+    #[cfg(unix)]
+    async fn read_reports(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        stream: tokio::net::UnixStream,
+    ) {
+        use tokio::io::AsyncBufReadExt as _;
+
+        let mut lines = tokio::io::BufReader::new(stream).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            match serde_json::from_str::<Event>(&line) {
+                Ok(event) => Self::handle(&state, event).await,
+                Err(error) => tracing::warn!("Unknown report from the PTY relay: {error:?}"),
+            }
+        }
+    }
+
+    /// Let everything else know about an event.
+    async fn handle(state: &crate::shared_state::SharedState, event: Event) {
+        match event {
+            Event::Bell => {
+                if let Err(error) = state.protocol_tx.send(crate::run::Protocol::BellCharacter) {
+                    tracing::error!("Couldn't send the PTY relay's bell: {error:?}");
+                }
+            }
+            Event::Notification(contents) => {
+                crate::osc_notifications::show(state, &contents).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bells_are_found() {
+        let mut scanner = Scanner::default();
+        assert_eq!(scanner.scan(b"done\x07"), [Event::Bell]);
+        assert!(scanner.scan(b"\x1b[1mbold\x1b[0m").is_empty());
+    }
+
+    #[test]
+    fn escape_sequences_that_end_with_bel_arent_bells() {
+        let mut scanner = Scanner::default();
+        assert!(scanner.scan(b"\x1b]0;title\x07").is_empty());
+        assert!(scanner.scan(b"\x1b]7;file://host/tmp\x1b\\").is_empty());
+        assert!(scanner.scan(b"\x1bPtmux;\x07\x1b\\").is_empty());
+        assert_eq!(scanner.scan(b"\x07"), [Event::Bell]);
+    }
+
+    #[test]
+    fn sequences_can_be_split_across_reads() {
+        let mut scanner = Scanner::default();
+        assert!(scanner.scan(b"\x1b").is_empty());
+        assert!(scanner.scan(b"]2;ti").is_empty());
+        assert!(scanner.scan(b"tle\x07").is_empty());
+        assert_eq!(scanner.scan(b"\x07\x07"), [Event::Bell, Event::Bell]);
+    }
+
+    #[test]
+    fn notifications_are_found() {
+        let mut scanner = Scanner::default();
+        assert_eq!(
+            scanner.scan(b"noise\x1b]5522;info;One\x07\x1b]5522;warn;Two\x1b\\"),
+            [
+                Event::Notification("info;One".to_owned()),
+                Event::Notification("warn;Two".to_owned())
+            ]
+        );
+        assert!(scanner.scan(b"\x1b]5522;info;Thr").is_empty());
+        assert_eq!(
+            scanner.scan(b"ee\x07"),
+            [Event::Notification("info;Three".to_owned())]
+        );
+        assert!(scanner.scan(b"\x1b]52;c;aGk=\x07").is_empty());
+    }
+
+    #[test]
+    fn sizes_are_rows_then_columns() {
+        let size = parse_size("24 80\n").unwrap();
+        assert_eq!((size.rows, size.cols), (24, 80));
+        assert!(parse_size("").is_none());
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::io::Read as _;
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{ContextCompat as _, Result};
 use shadow_terminal::termwiz;
 
 /// Bytes from STDIN
@@ -17,10 +17,42 @@ pub(crate) struct ParsedInput {
     pub event: termwiz::input::InputEvent,
 }
 
+impl ParsedInput {
+    /// Split the input into the fixed-size buffers that the shadow terminal accepts.
+    pub fn pty_chunks(self) -> Result<Vec<BytesFromSTDIN>> {
+        // If the input is from an OSC paste event, then only forward the contents of the paste,
+        // and not the surrounding OSC codes.
+        let bytes = if let termwiz::input::InputEvent::Paste(string) = self.event {
+            string.into_bytes()
+        } else {
+            self.bytes
+        };
+
+        tracing::trace!(
+            "Terminal proxy received input bytes: {}",
+            String::from_utf8_lossy(&bytes)
+        );
+
+        let mut chunks = Vec::new();
+        for chunk in bytes.chunks(128) {
+            let mut buffer: BytesFromSTDIN = [0; 128];
+            for (i, chunk_byte) in chunk.iter().enumerate() {
+                let buffer_byte = buffer.get_mut(i).context("Couldn't get byte from buffer")?;
+                *buffer_byte = *chunk_byte;
+            }
+            chunks.push(buffer);
+        }
+
+        Ok(chunks)
+    }
+}
+
 /// Handle input from the user
 pub(crate) struct RawInput {
     /// The main Tattoy protocol channel.
     protocol_tx: tokio::sync::broadcast::Sender<crate::run::Protocol>,
+    /// Whether the user's terminal is reporting keys with the kitty keyboard protocol.
+    is_kitty_keyboard: bool,
 }
 
 impl RawInput {
@@ -28,12 +60,16 @@ impl RawInput {
     /// application.
     pub fn start(
         protocol_tx: tokio::sync::broadcast::Sender<crate::run::Protocol>,
+        is_kitty_keyboard: bool,
     ) -> std::thread::JoinHandle<std::result::Result<(), color_eyre::eyre::Error>> {
         // The Tokio docs actually suggest using `std::thread` to listen on STDIN for interactive
         // applications.
         std::thread::spawn(move || -> Result<()> {
             let protocol_for_shutdown = protocol_tx.clone();
-            let input = Self { protocol_tx };
+            let input = Self {
+                protocol_tx,
+                is_kitty_keyboard,
+            };
             let result = input.consume_stdin();
             if let Err(error) = result {
                 crate::run::broadcast_protocol_end(&protocol_for_shutdown);
@@ -74,14 +110,48 @@ impl RawInput {
                         tracing::trace!("Received STDIN input: {sample} ({bytes:?})");
 
                         let wait_for_more = is_accumulating;
-                        parser.parse(
-                            bytes,
-                            |event| {
-                                self.parsed_bytes_callback(event, accumulated.clone());
-                                is_accumulating = false;
-                            },
-                            wait_for_more,
-                        );
+                        let mut segments = Vec::new();
+                        let mut is_focus_changed = false;
+                        for part in crate::focus::split(bytes) {
+                            match part {
+                                crate::focus::Segment::Focus(is_focused) => {
+                                    self.focus_callback(is_focused);
+                                    is_focus_changed = true;
+                                }
+                                crate::focus::Segment::Other(other) if self.is_kitty_keyboard => {
+                                    segments.extend(crate::kitty_keyboard::split(other));
+                                }
+                                crate::focus::Segment::Other(other) => {
+                                    segments.push(crate::kitty_keyboard::Segment::Other(other));
+                                }
+                            }
+                        }
+                        let is_mixed = segments.len() > 1 || is_focus_changed;
+                        for segment in segments {
+                            let other = match segment {
+                                crate::kitty_keyboard::Segment::Key(key) => {
+                                    self.kitty_key_callback(key);
+                                    continue;
+                                }
+                                crate::kitty_keyboard::Segment::Other(other) => other,
+                            };
+
+                            // When kitty keys or focus changes were split out, only the rest of
+                            // the bytes belong to the parsed events.
+                            let event_bytes = if is_mixed && !is_accumulating {
+                                other.to_vec()
+                            } else {
+                                accumulated.clone()
+                            };
+                            parser.parse(
+                                other,
+                                |event| {
+                                    self.parsed_bytes_callback(event, event_bytes.clone());
+                                    is_accumulating = false;
+                                },
+                                wait_for_more,
+                            );
+                        }
                     } else {
                         tracing::warn!("Couldn't get bytes from STDIN input buffer");
                     }
@@ -102,4 +172,37 @@ impl RawInput {
             tracing::error!("Error sending input event from thread to task: {error:?}");
         }
     }
+
+    /// The callback for when the user's terminal gains or loses focus.
+    fn focus_callback(&self, is_focused: bool) {
+        tracing::debug!("User's terminal focus changed: {is_focused}");
+        let result = self
+            .protocol_tx
+            .send(crate::run::Protocol::Focus(is_focused));
+        if let Err(error) = result {
+            tracing::error!("Error sending focus event from thread to task: {error:?}");
+        }
+    }
+
+    /// The callback for keys reported with the kitty keyboard protocol. Presses are sent on as
+    /// normal input, in the legacy encoding, but releases are only of interest to Tattoy.
+    fn kitty_key_callback(&self, key: Option<crate::kitty_keyboard::KeyEvent>) {
+        let Some(key) = key else {
+            tracing::trace!("Ignoring unsupported kitty keyboard protocol key");
+            return;
+        };
+
+        let message = match key.kind {
+            crate::kitty_keyboard::Kind::Release => crate::run::Protocol::KeyReleased(key.event),
+            crate::kitty_keyboard::Kind::Press | crate::kitty_keyboard::Kind::Repeat => {
+                crate::run::Protocol::Input(ParsedInput {
+                    bytes: key.legacy_bytes(),
+                    event: termwiz::input::InputEvent::Key(key.event),
+                })
+            }
+        };
+        if let Err(error) = self.protocol_tx.send(message) {
+            tracing::error!("Error sending kitty key event from thread to task: {error:?}");
+        }
+    }
 }
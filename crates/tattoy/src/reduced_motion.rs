@@ -0,0 +1,92 @@
+//! Reduced motion, for users that are sensitive to motion. Animations are slowed down, fireworks
+//! aren't launched and the visual bell doesn't flash. Shaders are told about it with the
+//! `iReducedMotion` uniform, so that they can calm themselves down too.
+//!
+//! It's set with `reduced_motion` in the config. When that's not set, the operating system's
+//! preference is used, where Tattoy knows how to find it.
+
+/// How fast animations, like shaders, run with reduced motion, as a fraction of their normal
+/// speed.
+pub(crate) const SPEED: f32 = 0.25;
+
+/// Parse the output of `gsettings get org.gnome.desktop.interface enable-animations`. Animations
+/// being disabled means that the user prefers reduced motion.
+fn parse_gnome(output: &str) -> Option<bool> {
+    match output.trim() {
+        "true" => Some(false),
+        "false" => Some(true),
+        _ => None,
+    }
+}
+
+/// Parse the output of `defaults read com.apple.universalaccess reduceMotion`.
+fn parse_macos(output: &str) -> Option<bool> {
+    match output.trim() {
+        "1" => Some(true),
+        "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Run a command and return its output, if it succeeded.
+async fn output_of(command: &str, arguments: &[&str]) -> Option<String> {
+    let result = tokio::process::Command::new(command)
+        .args(arguments)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await;
+    match result {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        Ok(_) => None,
+        Err(error) => {
+            tracing::trace!("Couldn't run `{command}` to detect reduced motion: {error:?}");
+            None
+        }
+    }
+}
+
+/// Ask the operating system whether the user prefers reduced motion. There's no standard way to
+/// do this, so it only knows about GNOME's and macOS's settings, and otherwise assumes that they
+/// don't.
+pub(crate) async fn detect_os_preference() -> bool {
+    let maybe_preference = if cfg!(target_os = "macos") {
+        output_of(
+            "defaults",
+            &["read", "com.apple.universalaccess", "reduceMotion"],
+        )
+        .await
+        .as_deref()
+        .and_then(parse_macos)
+    } else {
+        output_of(
+            "gsettings",
+            &["get", "org.gnome.desktop.interface", "enable-animations"],
+        )
+        .await
+        .as_deref()
+        .and_then(parse_gnome)
+    };
+
+    let preference = maybe_preference.unwrap_or(false);
+    tracing::debug!("The OS preference for reduced motion is: {preference}");
+    preference
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_gnome_and_macos_preferences() {
+        assert_eq!(parse_gnome("false\n"), Some(true));
+        assert_eq!(parse_gnome("true\n"), Some(false));
+        assert_eq!(parse_gnome("No such key"), None);
+
+        assert_eq!(parse_macos("1\n"), Some(true));
+        assert_eq!(parse_macos("0\n"), Some(false));
+        assert_eq!(parse_macos(""), None);
+    }
+}
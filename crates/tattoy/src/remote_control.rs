@@ -0,0 +1,499 @@
+//! A small HTTP and JSON API, on localhost, for controlling Tattoy from other tools, like Raycast
+//! or Stream Deck integrations.
+//!
+//! * `POST /tattoys/<id>/toggle`, `/tattoys/<id>/enable` and `/tattoys/<id>/disable` turn a
+//!   tattoy, like `minimap` or `shader_1`, on and off. Only tattoys that were started can be
+//!   turned on, so it's best to enable them in the config and turn them off here.
+//! * `POST /notify` with `{"title": "...", "body": "...", "level": "info"}` shows a notification.
+//! * `POST /scene` with `{"name": "focus"}`, or `{"name": null}`, switches scenes, and
+//!   `POST /scene/next` switches to the next one.
+//! * `POST /celebrate` with `{"kind": "confetti", "origin": [10, 5]}` bursts confetti, or
+//!   sparkles, out of a cell. Both are optional, the origin defaults to the middle of the screen.
+//! * `GET /typing_speed` gives the session's typing statistics, when the `typing_speed` tattoy is
+//!   running.
+//!
+//! When a `token` is configured, requests need an `Authorization: Bearer <token>` header.
+//! Requests from web pages, which always have an `Origin` header, are refused, so that websites
+//! can't change your terminal.
+
+use color_eyre::eyre::{bail, Result};
+
+/// The biggest request that's read.
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+/// How long a client has to send its request and read the response. Every request gets its own
+/// task, but a stalled client still mustn't keep its task around forever.
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// User-configurable settings for the remote control API.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to serve the API.
+    pub enabled: bool,
+    /// The address to serve the API on.
+    pub address: String,
+    /// A secret that requests must send as a bearer token. Empty means no token is needed.
+    pub token: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:9465".to_owned(),
+            token: String::new(),
+        }
+    }
+}
+
+/// The parts of an HTTP request that the API uses.
+#[derive(Debug, PartialEq, Eq)]
+struct Request {
+    /// The method, like `POST`.
+    method: String,
+    /// The path, without any query.
+    path: String,
+    /// The headers, with lowercase names.
+    headers: std::collections::BTreeMap<String, String>,
+    /// The body.
+    body: String,
+}
+
+/// A request whose `Content-Length` is bigger than `MAX_REQUEST_SIZE`.
+#[derive(Debug)]
+struct TooLarge;
+
+impl std::fmt::Display for TooLarge {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "The request is bigger than {MAX_REQUEST_SIZE} bytes"
+        )
+    }
+}
+
+impl std::error::Error for TooLarge {}
+
+impl Request {
+    /// Parse a request, returning `None` if it isn't complete yet. Requests that say they're
+    /// bigger than `MAX_REQUEST_SIZE` are refused with `TooLarge`, before anything is sliced.
+    fn parse(bytes: &[u8]) -> Result<Option<Self>> {
+        let Some(head_end) = bytes.windows(4).position(|window| window == b"\r\n\r\n") else {
+            return Ok(None);
+        };
+        let head = String::from_utf8_lossy(bytes.get(..head_end).unwrap_or_default());
+        let mut lines = head.lines();
+        let request_line = lines.next().unwrap_or_default();
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            bail!("Invalid request line: {request_line:?}");
+        };
+        let headers: std::collections::BTreeMap<String, String> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_owned()))
+            .collect();
+
+        let length: usize = headers
+            .get("content-length")
+            .map_or(Ok(0), |length| length.parse())?;
+        if length > MAX_REQUEST_SIZE {
+            return Err(TooLarge.into());
+        }
+        let body_start = head_end + 4;
+        let Some(body_end) = body_start.checked_add(length) else {
+            return Err(TooLarge.into());
+        };
+        let Some(body) = bytes.get(body_start..body_end) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            method: method.to_owned(),
+            path: target.split('?').next().unwrap_or_default().to_owned(),
+            headers,
+            body: String::from_utf8_lossy(body).into_owned(),
+        }))
+    }
+}
+
+/// The body of a `/notify` request.
+#[derive(serde::Deserialize, Debug)]
+struct Notification {
+    /// The title.
+    title: String,
+    /// The optional body.
+    body: Option<String>,
+    /// The level, like `info` or `error`.
+    #[serde(default)]
+    level: crate::tattoys::notifications::message::Level,
+}
+
+/// The body of a `/scene` request.
+#[derive(serde::Deserialize, Debug)]
+struct Scene {
+    /// The name of the scene, `null` being no scene.
+    name: Option<String>,
+}
+
+/// The body of a `/celebrate` request.
+#[derive(serde::Deserialize, Debug)]
+struct Celebration {
+    /// The kind of celebration.
+    #[serde(default)]
+    kind: crate::tattoys::celebration::Kind,
+    /// The cell, as column and row, that the celebration bursts out of.
+    origin: Option<(u16, u16)>,
+}
+
+/// How a tattoy's toggle changes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Toggle {
+    /// Turn it on if it's off, and off if it's on.
+    Flip,
+    /// Turn it on.
+    On,
+    /// Turn it off.
+    Off,
+}
+
+/// What a request asks for.
+#[derive(Debug, PartialEq, Eq)]
+enum Route {
+    /// Turn a tattoy on or off.
+    Tattoy(String, Toggle),
+    /// Show a notification.
+    Notify,
+    /// Switch to a scene.
+    Scene,
+    /// Switch to the next scene.
+    NextScene,
+    /// Celebrate something.
+    Celebrate,
+    /// Get the typing statistics.
+    TypingSpeed,
+}
+
+/// Work out what a request asks for.
+fn route(method: &str, path: &str) -> Option<Route> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if method == "GET" {
+        return (segments == ["typing_speed"]).then_some(Route::TypingSpeed);
+    }
+    if method != "POST" {
+        return None;
+    }
+    match segments.as_slice() {
+        ["tattoys", id, action] => {
+            let toggle = match *action {
+                "toggle" => Toggle::Flip,
+                "enable" => Toggle::On,
+                "disable" => Toggle::Off,
+                _ => return None,
+            };
+            Some(Route::Tattoy((*id).to_owned(), toggle))
+        }
+        ["notify"] => Some(Route::Notify),
+        ["scene"] => Some(Route::Scene),
+        ["scene", "next"] => Some(Route::NextScene),
+        ["celebrate"] => Some(Route::Celebrate),
+        _ => None,
+    }
+}
+
+/// A JSON response.
+fn response(status: &str, json: &serde_json::Value) -> String {
+    let body = json.to_string();
+    format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// A JSON error response.
+fn error(status: &str, message: &str) -> String {
+    response(status, &serde_json::json!({ "error": message }))
+}
+
+/// Serves the remote control API.
+pub(crate) struct RemoteControl;
+
+impl RemoteControl {
+    /// Start the task that serves the API, if it's enabled.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let config = state.config.read().await.remote_control.clone();
+            if !config.enabled {
+                return Ok(());
+            }
+
+            let listener = tokio::net::TcpListener::bind(&config.address).await?;
+            tracing::info!(
+                "Serving the remote control API on: http://{}",
+                config.address
+            );
+            let mut protocol = state.protocol_tx.subscribe();
+
+            #[expect(
+                clippy::integer_division_remainder_used,
+                reason = "This is caused by the `tokio::select!`"
+            )]
+            loop {
+                tokio::select! {
+                    connection = listener.accept() => match connection {
+                        Ok((stream, _)) => {
+                            let request_state = std::sync::Arc::clone(&state);
+                            tokio::spawn(async move {
+                                let request = Self::respond(&request_state, stream);
+                                match tokio::time::timeout(REQUEST_TIMEOUT, request).await {
+                                    Ok(Ok(())) => (),
+                                    Ok(Err(error)) => tracing::debug!("Couldn't respond to remote control: {error:?}"),
+                                    Err(_) => tracing::debug!("Remote control request timed out"),
+                                }
+                            });
+                        }
+                        Err(error) => tracing::warn!("Couldn't accept remote control connection: {error:?}"),
+                    },
+                    message = protocol.recv() => match message {
+                        Ok(crate::run::Protocol::End)
+                        | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => (),
+                    }
+                }
+            }
+
+            tracing::debug!("Leaving remote control loop");
+            Ok(())
+        })
+    }
+
+    /// Read a request and write its response.
+    async fn respond(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        mut stream: tokio::net::TcpStream,
+    ) -> Result<()> {
+        let mut bytes = Vec::new();
+        let mut buffer = [0; 4096];
+        let request = loop {
+            let count = tokio::io::AsyncReadExt::read(&mut stream, &mut buffer).await?;
+            bytes.extend_from_slice(buffer.get(..count).unwrap_or_default());
+            match Request::parse(&bytes) {
+                Ok(Some(request)) => break request,
+                Ok(None) => (),
+                Err(report) if report.is::<TooLarge>() => {
+                    let response = error("413 Payload Too Large", &report.to_string());
+                    tokio::io::AsyncWriteExt::write_all(&mut stream, response.as_bytes()).await?;
+                    tokio::io::AsyncWriteExt::shutdown(&mut stream).await?;
+                    return Ok(());
+                }
+                Err(report) => return Err(report),
+            }
+            if count == 0 || bytes.len() > MAX_REQUEST_SIZE {
+                bail!("Incomplete remote control request");
+            }
+        };
+
+        let response = Self::handle(state, &request).await;
+        tokio::io::AsyncWriteExt::write_all(&mut stream, response.as_bytes()).await?;
+        tokio::io::AsyncWriteExt::shutdown(&mut stream).await?;
+        Ok(())
+    }
+
+    /// Do what a request asks for.
+    async fn handle(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        request: &Request,
+    ) -> String {
+        if request.headers.contains_key("origin") {
+            return error("403 Forbidden", "Requests from web pages aren't allowed");
+        }
+        let token = state.config.read().await.remote_control.token.clone();
+        if !token.is_empty()
+            && request.headers.get("authorization") != Some(&format!("Bearer {token}"))
+        {
+            return error("401 Unauthorized", "Missing or wrong token");
+        }
+
+        let Some(route) = route(&request.method, &request.path) else {
+            return error("404 Not Found", "Unknown endpoint");
+        };
+        tracing::debug!("Remote control: {route:?}");
+        let result = match route {
+            Route::Tattoy(id, toggle) => Self::toggle_tattoy(state, &id, toggle).await,
+            Route::Notify => Self::notify(state, &request.body).await,
+            Route::Scene => Self::switch_scene(state, &request.body).await,
+            Route::NextScene => {
+                crate::scenes::switch_to_next(state).await;
+                Ok(serde_json::json!({ "scene": *state.scene.read().await }))
+            }
+            Route::Celebrate => Self::celebrate(state, &request.body).await,
+            Route::TypingSpeed => Self::typing_speed(state).await,
+        };
+
+        match result {
+            Ok(json) => response("200 OK", &json),
+            Err(report) => error("400 Bad Request", &report.to_string()),
+        }
+    }
+
+    /// Turn a tattoy on or off. The change is kept until Tattoy exits, even when the config is
+    /// reloaded.
+    async fn toggle_tattoy(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        id: &str,
+        toggle: Toggle,
+    ) -> Result<serde_json::Value> {
+        let Some(is_enabled) = state.config.read().await.is_tattoy_enabled(id) else {
+            bail!("`{id}` isn't a tattoy that can be turned on and off");
+        };
+        let enabled = match toggle {
+            Toggle::Flip => !is_enabled,
+            Toggle::On => true,
+            Toggle::Off => false,
+        };
+
+        state
+            .tattoy_toggles
+            .write()
+            .await
+            .insert(id.to_owned(), enabled);
+        crate::config::main::Config::reload(state).await?;
+        Ok(serde_json::json!({ "id": id, "enabled": enabled }))
+    }
+
+    /// Show a notification.
+    async fn notify(
+        state: &crate::shared_state::SharedState,
+        body: &str,
+    ) -> Result<serde_json::Value> {
+        let notification: Notification = serde_json::from_str(body)?;
+        state
+            .send_notification(
+                &notification.title,
+                notification.level,
+                notification.body,
+                false,
+            )
+            .await;
+        Ok(serde_json::json!({ "sent": true }))
+    }
+
+    /// Switch to a scene.
+    async fn switch_scene(
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        body: &str,
+    ) -> Result<serde_json::Value> {
+        let scene: Scene = serde_json::from_str(body)?;
+        if let Some(name) = &scene.name {
+            if !state.config.read().await.scenes.contains_key(name) {
+                bail!("There's no `{name}` scene in the config");
+            }
+        }
+        crate::scenes::switch(state, scene.name).await;
+        Ok(serde_json::json!({ "scene": *state.scene.read().await }))
+    }
+
+    /// Celebrate something.
+    async fn celebrate(
+        state: &crate::shared_state::SharedState,
+        body: &str,
+    ) -> Result<serde_json::Value> {
+        let celebration: Celebration = serde_json::from_str(body)?;
+        let origin = match celebration.origin {
+            Some(origin) => origin,
+            None => {
+                let size = state.get_tty_size().await;
+                (size.width.div_euclid(2), size.height.div_euclid(2))
+            }
+        };
+        state.protocol_tx.send(crate::run::Protocol::Celebrate {
+            kind: celebration.kind,
+            origin,
+        })?;
+        Ok(serde_json::json!({ "origin": origin }))
+    }
+
+    /// The session's typing statistics.
+    async fn typing_speed(state: &crate::shared_state::SharedState) -> Result<serde_json::Value> {
+        if !state.config.read().await.typing_speed.enabled {
+            bail!("The `typing_speed` tattoy isn't enabled");
+        }
+        Ok(state.typing_speed.read().await.to_json())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn requests_are_parsed_once_complete() {
+        let bytes = b"POST /notify?x=1 HTTP/1.1\r\nContent-Length: 4\r\nOrigin: x\r\n\r\n{\"a\"";
+        assert_eq!(Request::parse(bytes.get(..20).unwrap()).unwrap(), None);
+        assert_eq!(
+            Request::parse(bytes.get(..bytes.len() - 1).unwrap()).unwrap(),
+            None
+        );
+
+        let request = Request::parse(bytes).unwrap().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/notify");
+        assert_eq!(request.body, "{\"a\"");
+        assert!(request.headers.contains_key("origin"));
+    }
+
+    #[test]
+    fn huge_requests_are_refused_before_reading_their_body() {
+        for length in ["65537", &usize::MAX.to_string()] {
+            let bytes = format!("POST /notify HTTP/1.1\r\nContent-Length: {length}\r\n\r\n{{}}");
+            let report = Request::parse(bytes.as_bytes()).unwrap_err();
+            assert!(report.is::<TooLarge>());
+        }
+    }
+
+    #[test]
+    fn routes() {
+        assert_eq!(
+            route("POST", "/tattoys/minimap/toggle"),
+            Some(Route::Tattoy("minimap".to_owned(), Toggle::Flip))
+        );
+        assert_eq!(
+            route("POST", "/tattoys/shader_1/disable/"),
+            Some(Route::Tattoy("shader_1".to_owned(), Toggle::Off))
+        );
+        assert_eq!(route("POST", "/scene/next"), Some(Route::NextScene));
+        assert_eq!(route("POST", "/celebrate"), Some(Route::Celebrate));
+        assert_eq!(route("GET", "/notify"), None);
+        assert_eq!(route("GET", "/typing_speed"), Some(Route::TypingSpeed));
+        assert_eq!(route("POST", "/typing_speed"), None);
+        assert_eq!(route("POST", "/tattoys/minimap/explode"), None);
+    }
+
+    #[test]
+    fn tattoys_that_can_be_enabled_can_be_toggled() {
+        let mut config = crate::config::main::Config::default();
+        for id in [
+            "minimap",
+            "shader",
+            "shader_0",
+            "status_line",
+            "bg_command",
+            "panes",
+        ] {
+            let is_enabled = config.is_tattoy_enabled(id);
+            assert_eq!(
+                config.tattoy_enabled_mut(id).map(|enabled| *enabled),
+                is_enabled
+            );
+        }
+        assert_eq!(config.tattoy_enabled_mut("shader_5"), None);
+
+        *config.tattoy_enabled_mut("minimap").unwrap() = true;
+        assert_eq!(config.is_tattoy_enabled("minimap"), Some(true));
+    }
+}
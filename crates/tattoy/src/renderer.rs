@@ -3,7 +3,9 @@
 use std::str::FromStr as _;
 use std::sync::Arc;
 
+use base64::Engine as _;
 use color_eyre::eyre::Result;
+use futures_util::FutureExt as _;
 
 use shadow_terminal::termwiz;
 use termwiz::surface::Surface as TermwizSurface;
@@ -65,6 +67,15 @@ pub(crate) struct Renderer {
     pub is_cursor_visible: bool,
     /// Default background colour
     pub default_bg_colour: termwiz::color::SrgbaTuple,
+    /// When we last wrote to the user's terminal.
+    pub last_paint: tokio::time::Instant,
+    /// Whether there are updates that haven't been written to the user's terminal yet.
+    pub is_paint_pending: bool,
+    /// Whether we enabled the kitty keyboard protocol in the user's terminal.
+    pub is_kitty_keyboard: bool,
+    /// When the user's terminal is being resized, the time at which the new size is considered
+    /// settled and sent to everything else.
+    pub resize_settles_at: Option<tokio::time::Instant>,
 }
 
 impl Renderer {
@@ -75,9 +86,11 @@ impl Renderer {
         let width = size.width;
         let height = size.height;
 
+        let is_kitty_keyboard =
+            with_user_terminal && crate::kitty_keyboard::is_enabled(&state).await;
         let users_terminal = if with_user_terminal {
             let mut termwiz_terminal = Self::get_termwiz_terminal()?;
-            termwiz_terminal.set_raw_mode()?;
+            Self::take_over_users_terminal(&mut termwiz_terminal, is_kitty_keyboard)?;
             Some(BufferedTerminal::new(termwiz_terminal)?)
         } else {
             None
@@ -94,11 +107,89 @@ impl Renderer {
             indicator_cell: Self::indicator_cell()?,
             is_cursor_visible: true,
             default_bg_colour,
+            last_paint: tokio::time::Instant::now(),
+            is_paint_pending: false,
+            is_kitty_keyboard,
+            resize_settles_at: None,
         };
 
         Ok(renderer)
     }
 
+    /// Put the user's terminal into raw mode and enable the extra modes that Tattoy uses.
+    fn take_over_users_terminal(
+        terminal: &mut termwiz::terminal::SystemTerminal,
+        is_kitty_keyboard: bool,
+    ) -> Result<()> {
+        terminal.set_raw_mode()?;
+        if is_kitty_keyboard {
+            tracing::debug!("Enabling the kitty keyboard protocol");
+            crate::terminal_restore::enabled_kitty_keyboard();
+        }
+        let sequence = Self::take_over_sequence(is_kitty_keyboard);
+        std::io::Write::write_all(terminal, sequence.as_bytes())?;
+        std::io::Write::flush(terminal)?;
+        Ok(())
+    }
+
+    /// The escape codes that enable the extra modes that Tattoy uses in the user's terminal.
+    fn take_over_sequence(is_kitty_keyboard: bool) -> String {
+        let mut sequence = crate::focus::enable_sequence();
+        if is_kitty_keyboard {
+            sequence.push_str(&crate::kitty_keyboard::enable_sequence());
+        }
+        sequence
+    }
+
+    /// The escape codes that disable everything that `Self::take_over_sequence` enabled.
+    fn give_back_sequence(is_kitty_keyboard: bool) -> String {
+        let mut sequence = crate::focus::disable_sequence();
+        if is_kitty_keyboard {
+            sequence.push_str(&crate::kitty_keyboard::disable_sequence());
+        }
+        sequence
+    }
+
+    /// Return the user's terminal to how it was before Tattoy started.
+    fn give_back_users_terminal(
+        terminal: &mut termwiz::terminal::SystemTerminal,
+        is_kitty_keyboard: bool,
+    ) -> Result<()> {
+        let sequence = Self::give_back_sequence(is_kitty_keyboard);
+        std::io::Write::write_all(terminal, sequence.as_bytes())?;
+        std::io::Write::flush(terminal)?;
+        terminal.set_cooked_mode()?;
+        Ok(())
+    }
+
+    /// Give the user's terminal back and stop Tattoy, until it's resumed.
+    fn suspend(&mut self) -> Result<()> {
+        if let Some(users_terminal) = self.users_terminal.as_mut() {
+            users_terminal.add_changes(vec![
+                TermwizChange::AllAttributes(termwiz::cell::CellAttributes::default()),
+                TermwizChange::CursorVisibility(termwiz::surface::CursorVisibility::Visible),
+            ]);
+            users_terminal.flush()?;
+            Self::give_back_users_terminal(users_terminal.terminal(), self.is_kitty_keyboard)?;
+        }
+        crate::suspend::Suspend::stop()
+    }
+
+    /// Take the user's terminal back after being suspended. Whatever ran in the meantime will have
+    /// drawn over the screen, and maybe resized it, so everything is repainted.
+    async fn resume(
+        &mut self,
+        protocol_tx: &tokio::sync::broadcast::Sender<crate::run::Protocol>,
+    ) -> Result<()> {
+        let Some(users_terminal) = self.users_terminal.as_mut() else {
+            return Ok(());
+        };
+        Self::take_over_users_terminal(users_terminal.terminal(), self.is_kitty_keyboard)?;
+        users_terminal.repaint()?;
+        self.check_for_user_resize(protocol_tx).await?;
+        self.request_paint().await
+    }
+
     /// Create the little indicator pixel that shows that Tattoy is running.
     fn indicator_cell() -> Result<termwiz::cell::Cell> {
         let mut attributes = termwiz::cell::CellAttributes::default();
@@ -128,7 +219,18 @@ impl Renderer {
             // the `?` syntax.
             match Self::new(Arc::clone(&state), true).await {
                 Ok(mut renderer) => {
-                    let result = renderer.run(surfaces_rx, protocol_tx.clone(), state).await;
+                    // Without the renderer nothing can be seen, so a panic ends Tattoy, rather
+                    // than leaving a frozen screen.
+                    let may_panic = std::panic::AssertUnwindSafe(renderer.run(
+                        surfaces_rx,
+                        protocol_tx.clone(),
+                        state,
+                    ));
+                    let result = may_panic.catch_unwind().await.unwrap_or_else(|_| {
+                        Err(color_eyre::eyre::eyre!(
+                            "The renderer panicked, see the report below"
+                        ))
+                    });
 
                     if let Err(error) = result {
                         crate::run::broadcast_protocol_end(&protocol_tx);
@@ -160,6 +262,11 @@ impl Renderer {
     }
 
     /// Get the user's current terminal size and propogate it.
+    ///
+    /// Dragging the edge of a window resizes it many times a second, and every resize makes the
+    /// tattoys rebuild their surfaces, including the GPU's. So the new size is only propogated
+    /// once it hasn't changed for `render.resize_debounce` milliseconds. Until then just the PTY
+    /// is rendered, as a cheap placeholder.
     pub async fn check_for_user_resize(
         &mut self,
         protocol_tx: &tokio::sync::broadcast::Sender<crate::run::Protocol>,
@@ -169,22 +276,49 @@ impl Renderer {
         };
 
         let is_resized = users_terminal.check_for_resize()?;
-        if !is_resized {
-            return Ok(());
+        if is_resized {
+            users_terminal.repaint()?;
+
+            let (width, height) = users_terminal.dimensions();
+            self.width = width.try_into()?;
+            self.height = height.try_into()?;
+            let debounce = self.state.config.read().await.render.resize_debounce;
+            self.resize_settles_at =
+                Some(tokio::time::Instant::now() + std::time::Duration::from_millis(debounce));
         }
 
-        users_terminal.repaint()?;
+        let is_propagated = self
+            .propagate_settled_resize(tokio::time::Instant::now(), protocol_tx)
+            .await?;
+        if is_resized && !is_propagated {
+            self.request_paint().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Propogate the new size of the user's terminal, if it's being resized and the size has
+    /// settled by `now`. Returns whether it was propogated.
+    async fn propagate_settled_resize(
+        &mut self,
+        now: tokio::time::Instant,
+        protocol_tx: &tokio::sync::broadcast::Sender<crate::run::Protocol>,
+    ) -> Result<bool> {
+        if self
+            .resize_settles_at
+            .is_none_or(|settles_at| now < settles_at)
+        {
+            return Ok(false);
+        }
 
-        let (width, height) = users_terminal.dimensions();
-        self.width = width.try_into()?;
-        self.height = height.try_into()?;
+        self.resize_settles_at = None;
         self.state.set_tty_size(self.width, self.height).await;
         protocol_tx.send(crate::run::Protocol::Resize {
             width: self.width,
             height: self.height,
         })?;
 
-        Ok(())
+        Ok(true)
 
         // Note: there's no reason to resize the existing `self.pty` and `self.tattoys` because
         // they're just old copies. There's no point resizing them if their contents' aren't also
@@ -217,6 +351,9 @@ impl Renderer {
             reason = "`tokio::select!` generates this."
         )]
         loop {
+            crate::watchdog::idle(&self.state);
+            let next_paint_at = self.next_paint_at().await;
+            let maybe_resize_settles_at = self.resize_settles_at;
             tokio::select! {
                 Some(update) = surfaces.recv() => {
                     self.handle_frame_update(
@@ -226,17 +363,34 @@ impl Renderer {
                     ).await?;
                 }
 
+                // Updates that arrived too soon after the last paint are painted together.
+                () = tokio::time::sleep_until(next_paint_at), if self.is_paint_pending => {
+                    self.paint().await?;
+                },
+
                 // When surface updates are not being sent frequently enough, then we depend
                 // on this select branch for checking whether the end user's terminal has
                 // resized. Recall that this branch's future is cancelled whenever another
                 // select branch triggers, so we shouldn't have an over-abundance of resize
                 // checks.
                 () = tokio::time::sleep(tokio::time::Duration::from_millis(CHECK_FOR_RESIZE_RATE)) => {
+                    crate::watchdog::enter(&self.state, "checking for a resize", None);
+                    self.check_for_user_resize(&protocol_tx).await?;
+                },
+
+                // Make sure that a resize is propogated as soon as it's settled.
+                () = tokio::time::sleep_until(
+                    maybe_resize_settles_at.unwrap_or(next_paint_at)
+                ), if maybe_resize_settles_at.is_some() => {
                     self.check_for_user_resize(&protocol_tx).await?;
                 },
 
                 Ok(message) = protocol_rx.recv() => {
+                    crate::watchdog::enter(&self.state, "handling a protocol message", None);
                     self.handle_protocol_message(&message).await?;
+                    if matches!(message, crate::run::Protocol::Resume) {
+                        self.resume(&protocol_tx).await?;
+                    }
                     if matches!(message, crate::run::Protocol::End) {
                         break;
                     }
@@ -244,10 +398,13 @@ impl Renderer {
             }
         }
         tracing::debug!("Exited render loop");
+        if let Some(report) = state.latency.read().await.report() {
+            tracing::info!("Input latency: {report}");
+        }
 
         tracing::debug!("Setting user's terminal to cooked mode");
         if let Some(users_terminal) = self.users_terminal.as_mut() {
-            users_terminal.terminal().set_cooked_mode()?;
+            Self::give_back_users_terminal(users_terminal.terminal(), self.is_kitty_keyboard)?;
         }
 
         Ok(())
@@ -275,45 +432,155 @@ impl Renderer {
             | crate::run::Protocol::Input(_)
             | crate::run::Protocol::Config(_)
             | crate::run::Protocol::KeybindEvent(_)
-            | crate::run::Protocol::Notification(_) => (),
+            | crate::run::Protocol::PaneInput(_)
+            | crate::run::Protocol::PanesChanged
+            | crate::run::Protocol::OutputEvent(_)
+            | crate::run::Protocol::DirectoryChanged(_)
+            | crate::run::Protocol::Bell
+            | crate::run::Protocol::BellCharacter
+            | crate::run::Protocol::CommandFinished(_)
+            | crate::run::Protocol::KeyReleased(_)
+            | crate::run::Protocol::Focus(_)
+            | crate::run::Protocol::OutputBurst(_)
+            | crate::run::Protocol::Celebrate { .. }
+            | crate::run::Protocol::Resume => (),
+            crate::run::Protocol::ResetTattoy(id) => self.reset_tattoy(id).await?,
+            crate::run::Protocol::Suspend => self.suspend()?,
             crate::run::Protocol::CursorVisibility(is_visible) => {
                 self.is_cursor_visible = *is_visible;
             }
-            crate::run::Protocol::Repaint => self.paint().await?,
+            crate::run::Protocol::Repaint => self.request_paint().await?,
+            crate::run::Protocol::CopyToClipboard(text) => self.copy_to_clipboard(text)?,
+            crate::run::Protocol::Progress(progress) => self.set_taskbar_progress(*progress)?,
+            crate::run::Protocol::Title(title) => self.set_title(title)?,
+            crate::run::Protocol::Notification(message) => {
+                self.send_desktop_notification(message).await?;
+            }
         }
 
         Ok(())
     }
 
-    /// Reset the frame for every render.
+    /// Throw away a tattoy's frame, so that it's no longer composited until it sends a new one.
+    async fn reset_tattoy(&mut self, id: &str) -> Result<()> {
+        if let Some(surface) = self.tattoys.remove(id) {
+            self.state.surface_pool.write().await.recycle(surface);
+        }
+        self.request_paint().await
+    }
+
+    /// Copy text to the user's clipboard using the OSC 52 escape code. It's up to the user's
+    /// terminal whether it supports, or allows, setting the clipboard this way.
+    fn copy_to_clipboard(&mut self, text: &str) -> Result<()> {
+        let Some(users_terminal) = self.users_terminal.as_mut() else {
+            return Ok(());
+        };
+
+        let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+        let sequence = format!(
+            "{}]52;c;{encoded}{}",
+            crate::utils::ESCAPE,
+            crate::utils::BELL
+        );
+        std::io::Write::write_all(users_terminal.terminal(), sequence.as_bytes())?;
+        std::io::Write::flush(users_terminal.terminal())?;
+
+        Ok(())
+    }
+
+    /// Show progress in the user's terminal's taskbar, using the `OSC 9;4` sequence supported by
+    /// terminals like Windows Terminal, Ghostty and WezTerm.
+    fn set_taskbar_progress(&mut self, progress: Option<u8>) -> Result<()> {
+        let Some(users_terminal) = self.users_terminal.as_mut() else {
+            return Ok(());
+        };
+
+        let state = match progress {
+            Some(percentage) => format!("1;{percentage}"),
+            None => "0".to_owned(),
+        };
+        let sequence = format!("{}]9;4;{state}{}", crate::utils::ESCAPE, crate::utils::BELL);
+        std::io::Write::write_all(users_terminal.terminal(), sequence.as_bytes())?;
+        std::io::Write::flush(users_terminal.terminal())?;
+
+        Ok(())
+    }
+
+    /// Set the title of the user's terminal's tab or window.
+    fn set_title(&mut self, title: &str) -> Result<()> {
+        let Some(users_terminal) = self.users_terminal.as_mut() else {
+            return Ok(());
+        };
+
+        let sequence = crate::title::sequence(title);
+        std::io::Write::write_all(users_terminal.terminal(), sequence.as_bytes())?;
+        std::io::Write::flush(users_terminal.terminal())?;
+
+        Ok(())
+    }
+
+    /// Send a notification to the user's terminal as a desktop notification, using the `OSC 777`
+    /// sequence, so that screen readers can announce it.
+    async fn send_desktop_notification(
+        &mut self,
+        message: &crate::tattoys::notifications::message::Message,
+    ) -> Result<()> {
+        if !self.state.config.read().await.screen_reader.enabled {
+            return Ok(());
+        }
+        let Some(users_terminal) = self.users_terminal.as_mut() else {
+            return Ok(());
+        };
+
+        let sequence =
+            crate::screen_reader::desktop_notification(&message.title, message.body.as_deref());
+        std::io::Write::write_all(users_terminal.terminal(), sequence.as_bytes())?;
+        std::io::Write::flush(users_terminal.terminal())?;
+
+        Ok(())
+    }
+
+    /// Reset the frame for every render. The frame is cleared in place unless the size changed.
     fn reset_frame(&mut self) {
-        self.frame = TermwizSurface::new(self.width.into(), self.height.into());
+        let size = (usize::from(self.width), usize::from(self.height));
+        if self.frame.dimensions() == size {
+            self.frame.add_change(TermwizChange::ClearScreen(
+                termwiz::color::ColorAttribute::Default,
+            ));
+            let seqno = self.frame.current_seqno();
+            self.frame.flush_changes_older_than(seqno);
+            return;
+        }
+        self.frame = TermwizSurface::new(size.0, size.1);
     }
 
     /// Do a single render to the user's actual terminal. It uses a diffing algorithm to make
     /// the minimum number of changes.
     async fn render(&mut self, backlog: usize, update: FrameUpdate) -> Result<()> {
+        crate::watchdog::enter(&self.state, "receiving a frame", None);
+        crate::backpressure::record_backlog(&self.state, backlog).await;
+
+        // Stashed frames are always older than frames in the channel, so they go first.
+        for (_, surface) in crate::backpressure::take_stashed_frames(&self.state).await {
+            self.update_tattoy_surface(surface).await;
+        }
+
         match update {
             FrameUpdate::TattoySurface(surface) => {
-                let surface_id = surface.id.clone();
-                if surface.width == 0 || surface.height == 0 {
-                    self.tattoys.remove(&surface_id);
-                } else {
-                    self.tattoys.insert(surface_id.clone(), surface);
-                }
-                // TODO: convert IDs to something more constant.
-                if surface_id != "random_walker"
-                    && surface_id != "shader"
-                    && surface_id != "startup_logo"
-                    && surface_id != "animated_cursor"
-                {
-                    tracing::trace!("Rendering {} frame update", surface_id);
-                }
+                crate::watchdog::enter(&self.state, "receiving", Some(&surface.id));
+                self.update_tattoy_surface(surface).await;
             }
             FrameUpdate::PTYSurface => {
                 tracing::trace!("Rendering PTY frame update");
+                crate::watchdog::enter(&self.state, "reading the PTY", None);
                 self.get_updated_pty_frame().await;
+                self.state
+                    .latency
+                    .write()
+                    .await
+                    .pty_output(std::time::Instant::now());
             }
+            FrameUpdate::Stashed => (),
         }
 
         if backlog > 0 {
@@ -323,28 +590,95 @@ impl Renderer {
             return Ok(());
         }
 
-        self.paint().await?;
+        self.request_paint().await?;
 
         Ok(())
     }
 
+    /// Save the latest frame of a tattoy, ready for compositing. The frame it replaces goes back
+    /// to the pool for the tattoy to reuse.
+    async fn update_tattoy_surface(&mut self, surface: crate::surface::Surface) {
+        let surface_id = surface.id.clone();
+        self.state.frame_pacing.write().await.received(&surface_id);
+        let replaced = if surface.width == 0 || surface.height == 0 {
+            self.tattoys.remove(&surface_id)
+        } else {
+            self.tattoys.insert(surface_id.clone(), surface)
+        };
+        if let Some(old) = replaced {
+            self.state.surface_pool.write().await.recycle(old);
+        }
+        // TODO: convert IDs to something more constant.
+        if surface_id != "random_walker"
+            && surface_id != "shader"
+            && surface_id != "startup_logo"
+            && surface_id != "animated_cursor"
+        {
+            tracing::trace!("Rendering {} frame update", surface_id);
+        }
+    }
+
+    /// The earliest that we can next write to the user's terminal.
+    async fn next_paint_at(&self) -> tokio::time::Instant {
+        let max_output_rate = self.state.config.read().await.max_output_rate;
+        next_paint_after(self.last_paint, max_output_rate)
+    }
+
+    /// Paint now if we haven't painted too recently, otherwise paint as soon as we're allowed to.
+    /// Either way, all the updates that arrive in the meantime are painted together.
+    async fn request_paint(&mut self) -> Result<()> {
+        self.is_paint_pending = true;
+        if tokio::time::Instant::now() >= self.next_paint_at().await {
+            self.paint().await?;
+        }
+        Ok(())
+    }
+
     /// Apply the changes to the user's terminal.
     async fn paint(&mut self) -> Result<()> {
+        self.is_paint_pending = false;
+        self.last_paint = tokio::time::Instant::now();
+
+        crate::watchdog::enter(&self.state, "compositing", None);
         self.composite().await?;
+        crate::watchdog::enter(&self.state, "painting", None);
+        self.state.frame_pacing.write().await.composited();
+        let is_synchronised = self.state.capabilities.read().await.synchronised_output;
+        let is_ligature_safe = self.state.config.read().await.render.ligature_safe;
 
         let Some(users_terminal) = self.users_terminal.as_mut() else {
             return Ok(());
         };
 
+        // Ask the user's terminal to only show the frame once it's been completely written.
+        if is_synchronised {
+            let sequence = format!("{}[?2026h", crate::utils::ESCAPE);
+            std::io::Write::write_all(users_terminal.terminal(), sequence.as_bytes())?;
+        }
+
         // Hide the cursor without flushing.
         users_terminal.add_change(TermwizChange::CursorVisibility(
             termwiz::surface::CursorVisibility::Hidden,
         ));
 
-        let changes = users_terminal.diff_screens(&self.frame);
+        let maybe_ligature_safe_changes = if is_ligature_safe {
+            crate::ligatures::changes(
+                &users_terminal.get_screen_cells(),
+                &self.frame.get_screen_cells(),
+            )
+        } else {
+            None
+        };
+        let changes =
+            maybe_ligature_safe_changes.unwrap_or_else(|| users_terminal.diff_screens(&self.frame));
         users_terminal.add_changes(changes);
 
-        let (cursor_x, cursor_y) = self.pty.cursor_position();
+        let layout = self.state.panes.read().await.clone();
+        let (cursor_x, cursor_y) = if layout.is_main_focused() {
+            self.pty.cursor_position()
+        } else {
+            layout.focused_cursor
+        };
         users_terminal.add_change(TermwizChange::CursorPosition {
             x: TermwizPosition::Absolute(cursor_x),
             y: TermwizPosition::Absolute(cursor_y),
@@ -372,13 +706,33 @@ impl Renderer {
         // This is where we actually render to the user's real terminal.
         users_terminal.flush()?;
 
+        if is_synchronised {
+            let sequence = format!("{}[?2026l", crate::utils::ESCAPE);
+            std::io::Write::write_all(users_terminal.terminal(), sequence.as_bytes())?;
+            std::io::Write::flush(users_terminal.terminal())?;
+        }
+
+        self.state
+            .latency
+            .write()
+            .await
+            .painted(std::time::Instant::now());
+        self.state
+            .metrics
+            .write()
+            .await
+            .frame(self.last_paint.elapsed());
+
         Ok(())
     }
 
     // TODO: A failed render shouldn't crash the whole tick.
     /// Composite all the tattoys and the PTY together into a single surface (frame).
     pub async fn composite(&mut self) -> Result<()> {
-        let is_rendering_enabled = *self.state.is_rendering_enabled.read().await;
+        // Whilst the user's terminal is being resized, the tattoys are still the old size, so the
+        // PTY is rendered on its own.
+        let is_rendering_enabled =
+            *self.state.is_rendering_enabled.read().await && self.resize_settles_at.is_none();
         self.reset_frame();
 
         if is_rendering_enabled {
@@ -393,14 +747,114 @@ impl Renderer {
 
         if is_rendering_enabled {
             self.render_tattoys_above().await?;
+            self.align_wide_characters().await;
             self.colour_grade().await?;
+            crate::night_light::apply(&self.state.config.read().await.night_light, &mut self.frame);
+            crate::colour_blindness::apply(
+                &self.state.config.read().await.colour_blindness,
+                *self.state.is_colour_blindness_toggled.read().await,
+                &mut self.frame,
+            );
             self.add_indicator().await?;
             if self.is_cursor_visible {
                 let cursor = self.pty.cursor_position();
                 Compositor::clean_cursor_cell(&mut self.frame.screen_cells(), cursor.0, cursor.1);
+                self.colour_cursor_cell(cursor).await?;
+            }
+            self.remove_decorative_characters().await;
+            self.encode_pixels().await;
+        }
+
+        Ok(())
+    }
+
+    /// Redraw the frame's half block pixels with the user's choice of pixel glyphs.
+    async fn encode_pixels(&mut self) {
+        let pixel_glyphs = self.state.config.read().await.render.pixel_glyphs;
+        if pixel_glyphs == crate::pixel_encoders::PixelGlyphs::HalfBlocks {
+            return;
+        }
+        let encoder = pixel_glyphs.encoder();
+        for line in &mut self.frame.screen_cells() {
+            for cell in line.iter_mut() {
+                crate::pixel_encoders::re_encode(cell, encoder);
             }
         }
+    }
+
+    /// Make sure that no wide characters have been partly drawn over by the layers above them.
+    async fn align_wide_characters(&mut self) {
+        let unicode = self.state.config.read().await.unicode;
+        for line in &mut self.frame.screen_cells() {
+            Compositor::remove_overlapped_wide_characters(line, unicode);
+        }
+    }
+
+    /// For screen readers, make sure that the only characters in the frame are the terminal's own
+    /// text, that is from the PTY, or the plugin replacing it, and from the other panes.
+    async fn remove_decorative_characters(&mut self) {
+        if !self.state.config.read().await.screen_reader.enabled {
+            return;
+        }
+
+        let main_text = match self.tattoys.values().find(|tattoy| tattoy.layer == 0) {
+            Some(plugin) => plugin.surface.get_screen_cells(),
+            None => self.pty.get_screen_cells(),
+        };
+        let panes_text = self
+            .tattoys
+            .get(crate::panes::manager::ID)
+            .map(|panes| panes.surface.get_screen_cells())
+            .unwrap_or_default();
+        let text_at = |cells: &[&[termwiz::cell::Cell]], x: usize, y: usize| {
+            cells
+                .get(y)
+                .and_then(|line| line.get(x))
+                .filter(|cell| Compositor::is_text(cell))
+        };
+
+        for (y, line) in self.frame.screen_cells().iter_mut().enumerate() {
+            for (x, cell) in line.iter_mut().enumerate() {
+                let maybe_text = text_at(&main_text, x, y).or_else(|| text_at(&panes_text, x, y));
+                crate::screen_reader::sanitise(cell, maybe_text);
+            }
+        }
+    }
+
+    /// Colour the text of the cell under the cursor from the shader beneath it, so that the user's
+    /// real cursor stays visible over bright, animated shaders. When several shaders are running,
+    /// it's the topmost one with a colour under the cursor.
+    async fn colour_cursor_cell(&mut self, cursor: (usize, usize)) -> Result<()> {
+        let config = self.state.config.read().await;
+        let sampling = config.animated_cursor.sample_shader;
+        if sampling == crate::tattoys::animated_cursor::ShaderSampling::Off {
+            return Ok(());
+        }
+        let shader_ids = (0..=config.shaders.len())
+            .map(crate::tattoys::shader::Shaders::id)
+            .filter(|id| config.is_tattoy_enabled(id) == Some(true))
+            .collect::<Vec<String>>();
+        drop(config);
 
+        let mut shaders = shader_ids
+            .iter()
+            .filter_map(|id| self.tattoys.get(id))
+            .collect::<Vec<_>>();
+        shaders.sort_by_key(|shader| core::cmp::Reverse(shader.layer));
+        let maybe_sample = shaders.iter().find_map(|shader| {
+            let shader_cells = shader.surface.get_screen_cells();
+            Compositor::get_cell(&shader_cells, cursor.0, cursor.1)
+                .ok()
+                .and_then(Compositor::average_pixel_colour)
+        });
+        let Some(colour) = maybe_sample.and_then(|sample| sampling.colour_for(sample)) else {
+            return Ok(());
+        };
+
+        let mut frame_cells = self.frame.screen_cells();
+        let cell = Compositor::get_cell_mut(&mut frame_cells, cursor.0, cursor.1)?;
+        cell.attrs_mut()
+            .set_foreground(crate::blender::Blender::make_true_colour_attribute(colour));
         Ok(())
     }
 
@@ -444,14 +898,62 @@ impl Renderer {
         tattoys.sort_by_key(|tattoy| tattoy.layer);
 
         let frame_size = self.frame.dimensions();
+        let focused_pane_only = self
+            .state
+            .config
+            .read()
+            .await
+            .panes
+            .focused_pane_only
+            .clone();
+        let layout = self.state.panes.read().await.clone();
+        let maybe_focused_region = if layout.is_split() {
+            layout.focused_region(frame_size.0, frame_size.1)
+        } else {
+            None
+        };
+
+        let config = self.state.config.read().await;
+        let hidden_shaders: Vec<String> = config
+            .all_shaders()
+            .enumerate()
+            .filter(|(_, shader)| !shader.render)
+            .map(|(index, _)| crate::tattoys::shader::Shaders::id(index))
+            .collect();
+        let colour_correction = config.colour_correction.clone();
+        let unicode = config.unicode;
+        let live_controls = self.state.live_controls.read().await;
+        let compositing: Vec<(
+            crate::compositor::Mask,
+            Option<crate::opacity_gradient::Gradient>,
+            f32,
+            bool,
+        )> = tattoys
+            .iter()
+            .map(|tattoy| {
+                (
+                    config.compositor_mask(&tattoy.id),
+                    config.opacity_gradient(&tattoy.id),
+                    tattoy.opacity * live_controls.opacity(&tattoy.id),
+                    config.is_background_only(&tattoy.id),
+                )
+            })
+            .collect();
+        drop(live_controls);
+        drop(config);
+
+        let pty_cells = self.pty.get_screen_cells();
         let mut frame_cells = self.frame.screen_cells();
-        for tattoy in &mut tattoys {
-            if tattoy.id == *"shader" && !self.state.config.read().await.shader.render {
+        for (tattoy, (mask, maybe_gradient, base_opacity, is_background_only)) in
+            tattoys.iter_mut().zip(compositing)
+        {
+            if hidden_shaders.contains(&tattoy.id) {
                 continue;
             }
             if tattoy.id == *"animated_cursor" {
                 continue;
             }
+            crate::watchdog::enter(&self.state, "compositing", Some(&tattoy.id));
 
             let tattoy_frame_size = tattoy.surface.dimensions();
             if tattoy_frame_size != frame_size {
@@ -462,16 +964,66 @@ impl Renderer {
                 continue;
             }
             let tattoy_cells = tattoy.surface.get_screen_cells();
+            let maybe_adjustments = colour_correction
+                .get(&tattoy.id)
+                .filter(|adjustments| !adjustments.is_unchanged());
+            let is_tint_over_text = tattoy.id == crate::tattoys::crt::ID;
+            let maybe_clip =
+                maybe_focused_region.filter(|_| focused_pane_only.contains(&tattoy.id));
+
+            for (y, (frame_line, tattoy_line)) in
+                frame_cells.iter_mut().zip(tattoy_cells).enumerate()
+            {
+                for (x, (frame_cell, tattoy_cell)) in
+                    frame_line.iter_mut().zip(tattoy_line).enumerate()
+                {
+                    if let Some(clip) = maybe_clip {
+                        if !clip.contains(x, y) {
+                            continue;
+                        }
+                    }
+                    if mask.is_masked(pty_cells.get(y).and_then(|line| line.get(x))) {
+                        continue;
+                    }
+
+                    let adjusted_cell;
+                    let tattoy_cell = if let Some(adjustments) = maybe_adjustments {
+                        adjusted_cell = adjustments.adjust_cell(tattoy_cell);
+                        &adjusted_cell
+                    } else {
+                        tattoy_cell
+                    };
+                    let opacity = maybe_gradient.map_or(base_opacity, |gradient| {
+                        base_opacity * gradient.multiplier(x, y, frame_size)
+                    });
+
+                    if is_tint_over_text && crate::tattoys::gpu::text_mask::is_text(frame_cell) {
+                        Compositor::tint_text_cell(
+                            frame_cell,
+                            tattoy_cell,
+                            opacity,
+                            self.default_bg_colour,
+                        );
+                        continue;
+                    }
+                    if is_background_only {
+                        Compositor::tint_background(
+                            frame_cell,
+                            tattoy_cell,
+                            opacity,
+                            self.default_bg_colour,
+                        );
+                        continue;
+                    }
 
-            for (frame_line, tattoy_line) in frame_cells.iter_mut().zip(tattoy_cells) {
-                for (frame_cell, tattoy_cell) in frame_line.iter_mut().zip(tattoy_line) {
                     Compositor::composite_cells(
                         frame_cell,
                         tattoy_cell,
-                        tattoy.opacity,
+                        opacity,
                         self.default_bg_colour,
                     );
                 }
+                Compositor::clear_wide_continuations(frame_line, tattoy_line, unicode);
             }
         }
 
@@ -486,8 +1038,27 @@ impl Renderer {
         let pty_size = self.pty.dimensions();
         let pty_cells = self.pty.get_screen_cells();
 
-        if pty_size != frame_size {
-            tracing::warn!("Not rendering PTY as its size doesn't match the current frame size");
+        // When the terminal is split into panes, the PTY only fills the first pane. The PTY
+        // frame's own rows include any rows above it that the status line has taken.
+        let status_line = self.state.config.read().await.status_line.clone();
+        let pty_height = frame_size
+            .1
+            .saturating_sub(usize::from(status_line.reserved_rows()));
+        let (expected_width, expected_height) = self
+            .state
+            .panes
+            .read()
+            .await
+            .region(0, frame_size.0, pty_height)
+            .map_or((frame_size.0, pty_height), |region| {
+                (region.width, region.height)
+            });
+        let expected_pty_size = (
+            expected_width,
+            expected_height + usize::from(status_line.rows_above_pty()),
+        );
+        if pty_size != expected_pty_size {
+            tracing::warn!("Not rendering PTY as its size doesn't match its pane's size");
             return Ok(());
         }
 
@@ -495,6 +1066,7 @@ impl Renderer {
         let text_contrast = config.text_contrast.clone();
         let apply_to_readable_text_only = config.text_contrast.apply_to_readable_text_only;
         let render_shader_colours_to_text = config.shader.render_shader_colours_to_text;
+        let unicode = config.unicode;
         drop(config);
 
         let maybe_shader_cells = if render_shader_colours_to_text {
@@ -550,6 +1122,7 @@ impl Renderer {
                     );
                 }
             }
+            Compositor::clear_wide_continuations(frame_line, pty_line, unicode);
         }
 
         Ok(())
@@ -615,9 +1188,23 @@ impl Renderer {
     /// Fetch the freshly made PTY frame from the shared state.
     async fn get_updated_pty_frame(&mut self) {
         self.pty.resize(self.width.into(), self.height.into());
+        let rows_above = usize::from(self.state.config.read().await.status_line.rows_above_pty());
         let surface = self.state.shadow_tty_screen.read().await;
-        let (cursor_x, cursor_y) = surface.cursor_position();
-        self.pty = surface.clone();
+        let (cursor_x, mut cursor_y) = surface.cursor_position();
+        if rows_above == 0 {
+            self.pty = surface.clone();
+        } else {
+            // The status line is above the PTY, so the PTY is moved down to make room for it.
+            let (width, height) = surface.dimensions();
+            self.pty = TermwizSurface::new(width, height + rows_above);
+            self.pty.draw_from_screen(&surface, 0, rows_above);
+            if let Some(shape) = surface.cursor_shape() {
+                self.pty.add_change(TermwizChange::CursorShape(shape));
+            }
+            self.pty
+                .add_change(TermwizChange::CursorVisibility(surface.cursor_visibility()));
+            cursor_y += rows_above;
+        }
         drop(surface);
 
         self.pty.add_change(TermwizChange::CursorPosition {
@@ -665,3 +1252,139 @@ impl Renderer {
         Ok(())
     }
 }
+
+/// The earliest that the terminal can be painted again after painting at `last_paint`, for a
+/// maximum number of paints per second. `0` means there's no limit.
+fn next_paint_after(
+    last_paint: tokio::time::Instant,
+    max_output_rate: u32,
+) -> tokio::time::Instant {
+    if max_output_rate == 0 {
+        return last_paint;
+    }
+    let interval = ONE_MICROSECOND.div_euclid(max_output_rate.into());
+    last_paint + std::time::Duration::from_micros(interval)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn paints_are_spaced_out_by_the_output_rate() {
+        let last_paint = tokio::time::Instant::now();
+        assert_eq!(
+            next_paint_after(last_paint, 100),
+            last_paint + std::time::Duration::from_millis(10)
+        );
+        assert_eq!(
+            next_paint_after(last_paint, 1),
+            last_paint + std::time::Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn no_output_rate_means_no_waiting() {
+        let last_paint = tokio::time::Instant::now();
+        assert_eq!(next_paint_after(last_paint, 0), last_paint);
+    }
+
+    #[tokio::test]
+    async fn the_cursor_samples_the_topmost_enabled_shader() {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1);
+        let state = crate::shared_state::SharedState::init(1, 1, protocol_tx)
+            .await
+            .unwrap();
+        {
+            let mut config = state.config.write().await;
+            config.animated_cursor.sample_shader =
+                crate::tattoys::animated_cursor::ShaderSampling::Invert;
+            config.shader.enabled = false;
+            config.shaders = vec![crate::tattoys::shader::Config {
+                enabled: true,
+                ..Default::default()
+            }];
+        }
+        let mut renderer = Renderer::new(state, false).await.unwrap();
+        let shader = |id: &str, layer: i16, colour: crate::surface::Colour| {
+            let mut surface = crate::surface::Surface::new(id.to_owned(), 1, 1, layer, 1.0);
+            surface.add_pixel(0, 0, colour).unwrap();
+            surface.add_pixel(0, 1, colour).unwrap();
+            (id.to_owned(), surface)
+        };
+        // The disabled `[shader]` is on top, so it would be sampled if it were enabled.
+        renderer.tattoys.extend([
+            shader("shader", -5, (1.0, 0.0, 0.0, 1.0)),
+            shader("shader_1", -10, (0.0, 0.0, 1.0, 1.0)),
+        ]);
+
+        renderer.colour_cursor_cell((0, 0)).await.unwrap();
+        let cells = renderer.frame.screen_cells();
+        let foreground = cells
+            .first()
+            .and_then(|line| line.first())
+            .and_then(|cell| crate::blender::Blender::extract_colour(cell.attrs().foreground()));
+        assert_eq!(
+            foreground,
+            Some(termwiz::color::SrgbaTuple(1.0, 1.0, 0.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn giving_back_the_terminal_undoes_taking_it_over() {
+        let focus = crate::focus::enable_sequence();
+        let kitty = crate::kitty_keyboard::enable_sequence();
+        assert_eq!(Renderer::take_over_sequence(false), focus);
+        assert_eq!(
+            Renderer::take_over_sequence(true),
+            format!("{focus}{kitty}")
+        );
+
+        let sequence = Renderer::give_back_sequence(false);
+        assert!(sequence.contains(&crate::focus::disable_sequence()));
+        assert!(!sequence.contains(&crate::kitty_keyboard::disable_sequence()));
+
+        let sequence = Renderer::give_back_sequence(true);
+        assert!(sequence.contains(&crate::focus::disable_sequence()));
+        assert!(sequence.contains(&crate::kitty_keyboard::disable_sequence()));
+    }
+
+    #[tokio::test]
+    async fn resizes_are_only_propagated_once_settled() {
+        let (protocol_tx, mut protocol_rx) = tokio::sync::broadcast::channel(1);
+        let state = crate::shared_state::SharedState::init(1, 1, protocol_tx.clone())
+            .await
+            .unwrap();
+        let mut renderer = Renderer::new(state, false).await.unwrap();
+        let now = tokio::time::Instant::now();
+        assert!(!renderer
+            .propagate_settled_resize(now, &protocol_tx)
+            .await
+            .unwrap());
+
+        renderer.width = 80;
+        renderer.height = 24;
+        renderer.resize_settles_at = Some(now + std::time::Duration::from_millis(150));
+        assert!(!renderer
+            .propagate_settled_resize(now, &protocol_tx)
+            .await
+            .unwrap());
+        assert!(protocol_rx.try_recv().is_err());
+
+        let settled = now + std::time::Duration::from_millis(150);
+        assert!(renderer
+            .propagate_settled_resize(settled, &protocol_tx)
+            .await
+            .unwrap());
+        assert!(renderer.resize_settles_at.is_none());
+        assert!(matches!(
+            protocol_rx.try_recv(),
+            Ok(crate::run::Protocol::Resize {
+                width: 80,
+                height: 24
+            })
+        ));
+        let size = *renderer.state.tty_size.read().await;
+        assert_eq!((size.width, size.height), (80, 24));
+    }
+}
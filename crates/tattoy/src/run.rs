@@ -23,6 +23,8 @@ pub(crate) enum FrameUpdate {
     TattoySurface(crate::surface::Surface),
     /// A frame of a PTY terminal has been updated in the shared state
     PTYSurface,
+    /// A tattoy has stashed a frame in the shared state, see `crate::backpressure`.
+    Stashed,
 }
 
 /// Commands to control the various tasks/threads
@@ -51,20 +53,120 @@ pub(crate) enum Protocol {
     Notification(crate::tattoys::notifications::message::Message),
     /// Force a repaint.
     Repaint,
+    /// Copy text to the end user's clipboard.
+    CopyToClipboard(String),
+    /// Input from the end user that is destined for a pane other than the main PTY.
+    PaneInput(crate::raw_input::ParsedInput),
+    /// The layout of the panes has changed. The new layout is in the shared state.
+    PanesChanged,
+    /// A user-configured regex matched new output from the PTY. Contains the event's name.
+    OutputEvent(String),
+    /// The shell running in the PTY changed directory. The new directory is also in the shared
+    /// state.
+    DirectoryChanged(std::path::PathBuf),
+    /// The visual bell rang.
+    Bell,
+    /// The PTY's command printed a BEL character. Found by the PTY relay, see `crate::pty_relay`.
+    BellCharacter,
+    /// Progress, as a percentage, was detected in the PTY's output. `None` when there's no longer
+    /// any progress.
+    Progress(Option<u8>),
+    /// The shell in the PTY finished running a command.
+    CommandFinished(crate::commands::Command),
+    /// The user released a key. Only reported when the kitty keyboard protocol is enabled.
+    KeyReleased(shadow_terminal::termwiz::input::KeyEvent),
+    /// The user's terminal gained (`true`) or lost (`false`) focus.
+    Focus(bool),
+    /// A burst of output from the PTY started (`true`) or ended (`false`). Expensive tattoys pause
+    /// during bursts.
+    OutputBurst(bool),
+    /// The title to set for the user's terminal, based on the title that the PTY set.
+    Title(String),
+    /// Something worth celebrating happened, like all the tests passing.
+    Celebrate {
+        /// The kind of celebration.
+        kind: crate::tattoys::celebration::Kind,
+        /// The cell, as column and row, that the celebration bursts out of.
+        origin: (u16, u16),
+    },
+    /// Tattoy was asked to suspend, see `crate::suspend`.
+    Suspend,
+    /// Tattoy was resumed after being suspended.
+    Resume,
+    /// The renderer got stuck on a tattoy's frame, so the tattoy, by its ID, should start again
+    /// from a blank frame, see `crate::watchdog`.
+    ResetTattoy(String),
 }
 
 /// Main entrypoint
 pub(crate) async fn run(state_arc: &std::sync::Arc<SharedState>) -> Result<()> {
     let protocol_tx = state_arc.protocol_tx.clone();
-    let cli_args = setup(state_arc).await?;
+    let mut cli_args = setup(state_arc).await?;
     let palette_config_exists = crate::palette::main::palette_config_exists(state_arc).await;
 
+    if let Some(crate::cli_args::Subcommand::Install { name, force }) = &cli_args.subcommand {
+        crate::packages::install(state_arc, name.as_deref(), *force).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
+    if let Some(crate::cli_args::Subcommand::Export { path }) = &cli_args.subcommand {
+        crate::bundles::export(state_arc, path).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
+    if let Some(crate::cli_args::Subcommand::Import {
+        path,
+        name,
+        allow_commands,
+    }) = &cli_args.subcommand
+    {
+        crate::bundles::import(state_arc, path, name.as_deref(), *allow_commands).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
+    if let Some(crate::cli_args::Subcommand::Uninstall { name }) = &cli_args.subcommand {
+        crate::packages::uninstall(state_arc, name).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
+    if matches!(
+        cli_args.subcommand,
+        Some(crate::cli_args::Subcommand::Sessions)
+    ) {
+        crate::attach::list(state_arc).await;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
+    let attach_config = state_arc.config.read().await.attach.clone();
+    *state_arc.attached_session.write().await =
+        crate::attach::session_to_attach(&attach_config, cli_args.subcommand.as_ref())?;
+
+    if let Some(crate::cli_args::Subcommand::ShaderDev { path }) = &cli_args.subcommand {
+        crate::shader_dev::run(state_arc, path).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
     if cli_args.capture_palette {
         crate::palette::main::get_palette(state_arc).await?;
         #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
         std::process::exit(0);
     }
 
+    if let Some(shell) = cli_args.shell_integration {
+        #[expect(clippy::print_stdout, reason = "It's the whole point of the argument")]
+        {
+            print!("{}", shell.integration());
+        }
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
     if let Some(screenshot) = cli_args.parse_palette {
         crate::palette::parser::Parser::run(state_arc, Some(&screenshot)).await?;
         #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
@@ -77,8 +179,44 @@ pub(crate) async fn run(state_arc: &std::sync::Arc<SharedState>) -> Result<()> {
 
     let palette = crate::config::main::Config::load_palette(Arc::clone(state_arc)).await?;
     *state_arc.default_background.write().await = palette.background_colour();
+    *state_arc.default_foreground.write().await = palette.foreground_colour();
+
+    // This must happen before we start reading input, otherwise the terminal's answers would be
+    // treated as input.
+    crate::capabilities::Capabilities::detect(state_arc).await;
+    *state_arc.os_prefers_reduced_motion.write().await =
+        crate::reduced_motion::detect_os_preference().await;
+
+    if matches!(
+        cli_args.subcommand,
+        Some(crate::cli_args::Subcommand::Setup)
+    ) {
+        crate::setup_wizard::run(state_arc).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
+    if matches!(
+        cli_args.subcommand,
+        Some(crate::cli_args::Subcommand::Calibrate)
+    ) {
+        crate::calibrate::run(state_arc).await?;
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
+    let maybe_session = if cli_args.restore {
+        crate::session::Session::load(state_arc).await
+    } else {
+        None
+    };
+    if let Some(session) = &maybe_session {
+        session.resize_users_terminal()?;
+        session.add_enabled_tattoys(&mut cli_args.enabled_tattoys);
+    }
 
-    let input_thread_handle = RawInput::start(protocol_tx.clone());
+    let is_kitty_keyboard = crate::kitty_keyboard::is_enabled(state_arc).await;
+    let input_thread_handle = RawInput::start(protocol_tx.clone(), is_kitty_keyboard);
 
     let users_tty_size = crate::renderer::Renderer::get_users_tty_size()?;
     state_arc
@@ -91,6 +229,20 @@ pub(crate) async fn run(state_arc: &std::sync::Arc<SharedState>) -> Result<()> {
     let (renderer, surfaces_tx) = Renderer::start(Arc::clone(state_arc), protocol_tx.clone());
 
     let config_handle = crate::config::main::Config::watch(Arc::clone(state_arc));
+    let output_events_handle = crate::output_events::OutputEvents::start(Arc::clone(state_arc));
+    let hooks_handle = crate::hooks::Hooks::start(Arc::clone(state_arc));
+    let sounds_handle = crate::sounds::Sounds::start(Arc::clone(state_arc));
+    let cwd_handle = crate::cwd::Cwd::start(Arc::clone(state_arc));
+    let commands_handle = crate::commands::Commands::start(Arc::clone(state_arc));
+    let scrollback_log_handle = crate::scrollback_log::ScrollbackLog::start(Arc::clone(state_arc));
+    let pty_relay_handle = crate::pty_relay::PtyRelay::start(Arc::clone(state_arc));
+    let output_burst_handle = crate::output_burst::OutputBurst::start(Arc::clone(state_arc));
+    let title_handle = crate::title::Title::start(Arc::clone(state_arc));
+    let metrics_handle = crate::metrics::Metrics::start(Arc::clone(state_arc));
+    let remote_control_handle = crate::remote_control::RemoteControl::start(Arc::clone(state_arc));
+    let controllers_handle = crate::controllers::Controllers::start(Arc::clone(state_arc));
+    let suspend_handle = crate::suspend::Suspend::start(Arc::clone(state_arc));
+    let watchdog_handle = crate::watchdog::Watchdog::start(Arc::clone(state_arc));
 
     override_on_panic_behaviour();
     let tattoys_handle = crate::loader::start_tattoys(
@@ -99,12 +251,26 @@ pub(crate) async fn run(state_arc: &std::sync::Arc<SharedState>) -> Result<()> {
         Arc::clone(state_arc),
     )
     .await;
+    if let Some(scene) = maybe_session
+        .as_ref()
+        .and_then(|session| session.scene.clone())
+    {
+        crate::scenes::switch(state_arc, Some(scene)).await;
+    }
 
-    let scrollback_size = state_arc.config.read().await.scrollback_size;
+    let enabled_tattoys = cli_args.enabled_tattoys.clone();
+    let is_restoring_scrollback = maybe_session.is_some_and(|session| session.has_scrollback);
+    let config = state_arc.config.read().await;
+    let scrollback_size = config.scrollback_size;
+    let reserved_rows = usize::from(config.status_line.reserved_rows());
+    drop(config);
     let shadow_terminal_config = shadow_terminal::shadow_terminal::Config {
         width: users_tty_size.cols.try_into()?,
-        height: users_tty_size.rows.try_into()?,
-        command: get_startup_command(state_arc, cli_args).await?,
+        height: users_tty_size
+            .rows
+            .saturating_sub(reserved_rows)
+            .try_into()?,
+        command: get_startup_command(state_arc, cli_args, is_restoring_scrollback).await?,
         scrollback_size: scrollback_size.try_into()?,
         ..Default::default()
     };
@@ -116,6 +282,9 @@ pub(crate) async fn run(state_arc: &std::sync::Arc<SharedState>) -> Result<()> {
     )
     .await?;
     tracing::debug!("🏁 left PTY thread, exiting Tattoy...");
+    if let Err(error) = crate::session::Session::save(state_arc, enabled_tattoys).await {
+        tracing::error!("Couldn't save the session: {error:?}");
+    }
     broadcast_protocol_end(&protocol_tx);
 
     tattoys_handle
@@ -130,6 +299,20 @@ pub(crate) async fn run(state_arc: &std::sync::Arc<SharedState>) -> Result<()> {
     }
     renderer.await??;
     config_handle.await??;
+    output_events_handle.await??;
+    hooks_handle.await??;
+    sounds_handle.await??;
+    cwd_handle.await??;
+    commands_handle.await??;
+    scrollback_log_handle.await??;
+    pty_relay_handle.await??;
+    output_burst_handle.await??;
+    title_handle.await??;
+    metrics_handle.await??;
+    remote_control_handle.await??;
+    controllers_handle.await??;
+    suspend_handle.await??;
+    watchdog_handle.await??;
 
     tracing::trace!("Leaving Tattoy's main `run()` function");
     Ok(())
@@ -168,25 +351,26 @@ pub(crate) async fn wait_for_system(state: &Arc<crate::shared_state::SharedState
 /// it only affects tattoy tasks. Currently the only main-thread system that we'd want to see
 /// panics for, is the Shadow Terminal. At least a log is made. But it would be good to figure out
 /// a way to notify developers especially, that the Shadow Terminal panicked.
+///
+/// Panics on the main thread end Tattoy straight away, so the user's terminal is restored and the
+/// report printed there and then. Other panics are kept, to print if they end up ending Tattoy,
+/// see `crate::terminal_restore`.
 fn override_on_panic_behaviour() {
     std::panic::set_hook(Box::new(|info| {
-        let message = if let Some(message) = info.payload().downcast_ref::<String>() {
-            message
-        } else if let Some(message) = info.payload().downcast_ref::<&str>() {
-            message
+        let report = crate::terminal_restore::panic_report(info);
+        tracing::error!("Caught panic {report}");
+        if std::thread::current().name() == Some("main") {
+            crate::terminal_restore::restore();
+            #[expect(
+                clippy::print_stderr,
+                reason = "The user needs to know what went wrong"
+            )]
+            {
+                eprintln!("{}Tattoy panicked {report}", crate::utils::RESET_SCREEN);
+            }
         } else {
-            "Caught a panic with an unknown type."
-        };
-        let location = match info.location() {
-            Some(location) => format!(
-                "{}@{}:{}",
-                location.file(),
-                location.line(),
-                location.column()
-            ),
-            None => "Unknown location".to_owned(),
-        };
-        tracing::error!("Caught panic ({}): {message:?}", location);
+            crate::terminal_restore::record_panic(report);
+        }
     }));
 }
 
@@ -194,6 +378,7 @@ fn override_on_panic_behaviour() {
 async fn get_startup_command(
     state: &std::sync::Arc<SharedState>,
     cli_args: CliArgs,
+    is_restoring_scrollback: bool,
 ) -> Result<Vec<std::ffi::OsString>> {
     let maybe_cli_command = cli_args.command;
     let command = match maybe_cli_command {
@@ -201,10 +386,22 @@ async fn get_startup_command(
         None => state.config.read().await.command.clone(),
     };
 
-    let parts = command
+    let mut parts = command
         .split_whitespace()
         .map(std::convert::Into::into)
         .collect();
+    let maybe_attached_session = state.attached_session.read().await.clone();
+    if let Some(name) = maybe_attached_session {
+        let attach_config = state.config.read().await.attach.clone();
+        parts = crate::attach::in_session(&attach_config, &name, parts)?;
+    }
+    let scrollback_config = state.config.read().await.scrollback.clone();
+    let parts = crate::scrollback_log::with_restored_scrollback(
+        &scrollback_config,
+        parts,
+        is_restoring_scrollback,
+    );
+    let parts = crate::pty_relay::wrap(&*state.config.read().await, parts)?;
 
     tracing::debug!("Starting Tattoy with command: '{command:?}'");
     Ok(parts)
@@ -227,6 +424,22 @@ pub(crate) fn broadcast_protocol_end(protocol_tx: &tokio::sync::broadcast::Sende
 async fn setup(state: &std::sync::Arc<SharedState>) -> Result<CliArgs> {
     let cli_args = CliArgs::parse();
 
+    if matches!(
+        cli_args.subcommand,
+        Some(crate::cli_args::Subcommand::ConfigSchema)
+    ) {
+        let schema = serde_json::to_string_pretty(&crate::config::diagnostics::schema())?;
+        #[expect(
+            clippy::print_stdout,
+            reason = "It's the whole point of the subcommand"
+        )]
+        {
+            println!("{schema}");
+        }
+        #[expect(clippy::exit, reason = "We don't want to actually run Tattoy")]
+        std::process::exit(0);
+    }
+
     let mut main_config_file = state.main_config_file.write().await;
     (*main_config_file).clone_from(&cli_args.main_config);
     drop(main_config_file);
@@ -241,7 +454,7 @@ async fn setup(state: &std::sync::Arc<SharedState>) -> Result<CliArgs> {
     if let Err(config_error) = config_result {
         let path = crate::config::main::Config::main_config_path(state).await;
         color_eyre::eyre::bail!(
-            "Bad config file: {config_error:?}\n\nConfig path: {}",
+            "Bad config file: {config_error}\n\nConfig path: {}",
             path.display()
         );
     }
@@ -257,6 +470,9 @@ async fn setup(state: &std::sync::Arc<SharedState>) -> Result<CliArgs> {
     //   true color terminal anyway.
     std::env::set_var("COLORTERM", "truecolor");
 
+    // So that the shell integration can tell us about the shell's directory and commands.
+    crate::shell_integration::set_environment();
+
     tracing::info!("Starting Tattoy v{}", env!("CARGO_PKG_VERSION"));
     tracing::debug!("Loaded config: {:?}", state.config.read().await);
 
@@ -0,0 +1,171 @@
+//! Named scenes: sets of settings that are applied on top of the main config, and that can be
+//! switched between whilst Tattoy is running.
+//!
+//! A scene is written just like the rest of the config, but inside a `[scenes.<name>]` table, so
+//! `[scenes.focus.minimap] enabled = false` turns off the minimap in the "focus" scene. Switching
+//! scenes reloads the whole config and sends it to every tattoy in a single message, so that all
+//! the tattoys change together.
+
+use color_eyre::eyre::{Context as _, Result};
+
+/// Apply a scene on top of a config file's data. Returns `None` when the config doesn't have a
+/// scene with that name.
+pub(crate) fn apply(data: &str, scene: &str) -> Result<Option<crate::config::main::Config>> {
+    let mut table = data.parse::<toml::Table>()?;
+    let Some(overlay) = table
+        .get("scenes")
+        .and_then(|scenes| scenes.get(scene))
+        .and_then(toml::Value::as_table)
+        .cloned()
+    else {
+        return Ok(None);
+    };
+
+    merge(&mut table, overlay);
+    let config = toml::Value::Table(table)
+        .try_into()
+        .with_context(|| format!("Couldn't apply the `{scene}` scene"))?;
+    Ok(Some(config))
+}
+
+/// Recursively merge one table into another. Tables are merged key by key, everything else,
+/// including arrays, is replaced outright.
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        if key == "scenes" {
+            continue;
+        }
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// The config sections that at least one scene enables. Their tattoys need to be started even if
+/// the main config doesn't enable them, so that they can be switched on.
+pub(crate) fn sections_enabled_by_scenes(
+    config: &crate::config::main::Config,
+) -> std::collections::BTreeSet<String> {
+    config
+        .scenes
+        .values()
+        .flat_map(|scene| scene.iter())
+        .filter(|(_, settings)| {
+            settings.get("enabled").and_then(toml::Value::as_bool) == Some(true)
+        })
+        .map(|(section, _)| section.clone())
+        .collect()
+}
+
+/// The scene after the current one. Scenes are cycled through in alphabetical order, and then back
+/// to no scene at all.
+fn next<'names>(
+    mut names: impl Iterator<Item = &'names String>,
+    current: Option<&str>,
+) -> Option<String> {
+    match current {
+        None => names.next().cloned(),
+        Some(current) => names.skip_while(|name| *name != current).nth(1).cloned(),
+    }
+}
+
+/// Switch to the next scene.
+pub(crate) async fn switch_to_next(state: &std::sync::Arc<crate::shared_state::SharedState>) {
+    let current = state.scene.read().await.clone();
+    let scene = next(state.config.read().await.scenes.keys(), current.as_deref());
+    switch(state, scene).await;
+}
+
+/// Switch to a scene, or back to no scene. The config is reloaded with the scene applied to it,
+/// and sent to all the tattoys at once. If the scene can't be applied then the current scene is
+/// kept.
+pub(crate) async fn switch(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    scene: Option<String>,
+) {
+    tracing::info!("Switching to scene: {scene:?}");
+    let previous = core::mem::replace(&mut *state.scene.write().await, scene.clone());
+    if let Err(error) = crate::config::main::Config::reload(state).await {
+        *state.scene.write().await = previous;
+        state
+            .send_notification(
+                "Couldn't switch scene",
+                crate::tattoys::notifications::message::Level::Error,
+                error
+                    .root_cause()
+                    .to_string()
+                    .lines()
+                    .next()
+                    .map(str::to_owned),
+                false,
+            )
+            .await;
+        return;
+    }
+
+    state
+        .send_notification(
+            &format!("Scene: {}", scene.as_deref().unwrap_or("none")),
+            crate::tattoys::notifications::message::Level::Info,
+            None,
+            false,
+        )
+        .await;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = "
+        frame_rate = 30
+
+        [shader]
+        enabled = true
+        opacity = 0.5
+
+        [scenes.focus]
+        frame_rate = 60
+        shader = { enabled = false }
+
+        [scenes.party.fireworks]
+        enabled = true
+    ";
+
+    #[test]
+    fn scenes_are_applied_over_the_config() {
+        let config = apply(CONFIG, "focus").unwrap().unwrap();
+        assert_eq!(config.frame_rate, 60);
+        assert!(!config.shader.enabled);
+        assert!((config.shader.opacity - 0.5).abs() < f32::EPSILON);
+        assert_eq!(config.scenes.len(), 2);
+    }
+
+    #[test]
+    fn missing_scenes_are_not_applied() {
+        assert!(apply(CONFIG, "nope").unwrap().is_none());
+    }
+
+    #[test]
+    fn scenes_can_start_tattoys() {
+        let config = toml::from_str::<crate::config::main::Config>(CONFIG).unwrap();
+        assert_eq!(
+            sections_enabled_by_scenes(&config),
+            ["fireworks".to_owned()].into()
+        );
+    }
+
+    #[test]
+    fn scenes_cycle_back_to_no_scene() {
+        let names = ["focus".to_owned(), "party".to_owned()];
+        assert_eq!(next(names.iter(), None).as_deref(), Some("focus"));
+        assert_eq!(next(names.iter(), Some("focus")).as_deref(), Some("party"));
+        assert_eq!(next(names.iter(), Some("party")), None);
+        assert_eq!(next(names.iter(), Some("removed")), None);
+    }
+}
@@ -0,0 +1,125 @@
+//! A mode for users of screen readers. Screen readers that are attached to the user's terminal
+//! read every character in it, so the characters of decorations, like the minimap, widgets or even
+//! the half blocks of pixels, would flood them with junk. In this mode every frame is checked, as
+//! the very last step of compositing, so that the only characters in it are the terminal's own
+//! text. Decorations can still change colours.
+//!
+//! Notifications are also sent to the user's terminal as `OSC 777` desktop notifications, which
+//! screen readers can announce.
+
+use shadow_terminal::termwiz;
+
+/// User config for the screen reader mode.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether the screen reader mode is on.
+    pub enabled: bool,
+}
+
+/// Make sure a cell of the frame only has the character of the terminal's text at that position,
+/// `None` being no text. A decoration's character is replaced, but its colour is kept as the
+/// cell's background.
+pub(crate) fn sanitise(cell: &mut termwiz::cell::Cell, maybe_text: Option<&termwiz::cell::Cell>) {
+    let Some(text) = maybe_text else {
+        if cell.str() == " " || cell.str().is_empty() {
+            return;
+        }
+
+        let mut attributes = cell.attrs().clone();
+        let is_pixel = cell.str() == "▀" || cell.str() == "▄";
+        if let Some(colour) =
+            crate::compositor::Compositor::average_pixel_colour(cell).filter(|_| is_pixel)
+        {
+            attributes.set_background(
+                termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(colour),
+            );
+        }
+        *cell = termwiz::cell::Cell::new(' ', attributes);
+        return;
+    };
+
+    if cell.str() == text.str() {
+        return;
+    }
+
+    let mut attributes = text.attrs().clone();
+    attributes.set_background(cell.attrs().background());
+    *cell = termwiz::cell::Cell::new_grapheme(text.str(), attributes, None);
+}
+
+/// The `OSC 777` sequence for a desktop notification. Semicolons separate the parts of the
+/// sequence, and control characters would end it early, so they're removed from the text.
+pub(crate) fn desktop_notification(title: &str, maybe_body: Option<&str>) -> String {
+    let clean = |text: &str| {
+        text.chars()
+            .filter(|character| !character.is_control())
+            .map(|character| if character == ';' { ',' } else { character })
+            .collect::<String>()
+    };
+    format!(
+        "{}]777;notify;{};{}{}",
+        crate::utils::ESCAPE,
+        clean(title),
+        clean(maybe_body.unwrap_or_default()),
+        crate::utils::BELL
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn coloured(character: char, background: termwiz::color::SrgbaTuple) -> termwiz::cell::Cell {
+        let mut attributes = termwiz::cell::CellAttributes::default();
+        attributes.set_foreground(
+            termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(
+                termwiz::color::SrgbaTuple(1.0, 1.0, 1.0, 1.0),
+            ),
+        );
+        attributes.set_background(
+            termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(background),
+        );
+        termwiz::cell::Cell::new(character, attributes)
+    }
+
+    #[test]
+    fn only_the_terminals_text_is_kept() {
+        let red = termwiz::color::SrgbaTuple(1.0, 0.0, 0.0, 1.0);
+        let text = termwiz::cell::Cell::new('a', termwiz::cell::CellAttributes::default());
+
+        let mut decoration_over_text = coloured('█', red);
+        sanitise(&mut decoration_over_text, Some(&text));
+        assert_eq!(decoration_over_text.str(), "a");
+        assert_eq!(
+            decoration_over_text.attrs().background(),
+            termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(red)
+        );
+
+        let mut decoration = coloured('│', red);
+        sanitise(&mut decoration, None);
+        assert_eq!(decoration.str(), " ");
+
+        let mut pixel = coloured('▀', termwiz::color::SrgbaTuple(0.0, 0.0, 0.0, 1.0));
+        sanitise(&mut pixel, None);
+        assert_eq!(pixel.str(), " ");
+        let grey = crate::blender::Blender::extract_colour(pixel.attrs().background()).unwrap();
+        assert!((grey.0 - 0.5).abs() < 0.01);
+
+        let mut untouched = text.clone();
+        sanitise(&mut untouched, Some(&text));
+        assert_eq!(untouched, text);
+    }
+
+    #[test]
+    fn desktop_notifications_are_escaped() {
+        assert_eq!(
+            desktop_notification("Build; failed\x07", Some("2 errors")),
+            "\x1b]777;notify;Build, failed;2 errors\x07"
+        );
+        assert_eq!(
+            desktop_notification("Done", None),
+            "\x1b]777;notify;Done;\x07"
+        );
+    }
+}
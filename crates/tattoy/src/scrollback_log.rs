@@ -0,0 +1,540 @@
+//! Save the terminal's scrollback to disk, so that a closed or crashed session's output can be
+//! reviewed later. Lines are appended to a file for each session as they scroll off the top of the
+//! screen, and whatever is left on the screen is added when Tattoy exits.
+//!
+//! The previous session can also be reloaded into the scrollback, by showing it in the PTY before
+//! the user's command starts. That needs a POSIX `sh`.
+//!
+//! The shadow terminal only keeps `scrollback_size` lines in memory. The lines that fall off the
+//! top of it are spilled to a temporary file of their own, whether or not the scrollback is being
+//! saved, so that copy mode can still page them back in and search them. Lines are only noticed
+//! when the output settles, so any that scroll through the whole in-memory scrollback before then
+//! are missed.
+
+use color_eyre::eyre::Result;
+
+/// How long the PTY's output needs to settle before we look for new scrollback lines.
+const SETTLE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// The extension of scrollback files.
+const EXTENSION: &str = "log";
+
+/// User-configurable settings for saving the scrollback.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to save the scrollback to disk.
+    pub persist: bool,
+    /// Where the scrollback files are saved.
+    pub directory: std::path::PathBuf,
+    /// The size, in kilobytes, that a session's file can grow to before it's rotated.
+    pub max_file_size: u64,
+    /// How many rotated files to keep for each session.
+    pub max_files: usize,
+    /// Scrollback files older than this many days are deleted when Tattoy starts.
+    pub retention_days: f32,
+    /// Whether to reload the previous session's scrollback when Tattoy starts.
+    pub restore: bool,
+    /// Whether to keep the lines that fall off the top of the in-memory scrollback in a temporary
+    /// file, for copy mode.
+    pub spill: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let directory = dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .unwrap_or_default()
+            .join("tattoy")
+            .join("scrollback");
+        Self {
+            persist: false,
+            directory,
+            max_file_size: 1024,
+            max_files: 3,
+            retention_days: 7.0,
+            restore: false,
+            spill: true,
+        }
+    }
+}
+
+/// The lines that have fallen off the top of the shadow terminal's in-memory scrollback, oldest
+/// first. Only an index of where each line starts is kept in memory, the lines themselves are
+/// read back from the file when they're needed.
+#[derive(Debug)]
+pub(crate) struct Spill {
+    /// The file that the lines are kept in.
+    path: std::path::PathBuf,
+    /// Where each line starts in the file.
+    offsets: Vec<u64>,
+    /// The size of the file.
+    size: u64,
+}
+
+impl Default for Spill {
+    fn default() -> Self {
+        Self::new(std::env::temp_dir().join(format!("tattoy-{}.spill", std::process::id())))
+    }
+}
+
+impl Spill {
+    /// Instantiate, with the file that the lines will be kept in.
+    pub(crate) const fn new(path: std::path::PathBuf) -> Self {
+        Self {
+            path,
+            offsets: Vec::new(),
+            size: 0,
+        }
+    }
+
+    /// The number of spilled lines.
+    pub(crate) const fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Add lines to the end of the spill.
+    pub(crate) async fn append(&mut self, lines: &[String]) -> Result<()> {
+        let mut text = String::new();
+        let mut offsets = Vec::with_capacity(lines.len());
+        for line in lines {
+            offsets.push(self.size.saturating_add(u64::try_from(text.len())?));
+            text.push_str(line);
+            text.push('\n');
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        tokio::io::AsyncWriteExt::write_all(&mut file, text.as_bytes()).await?;
+        self.offsets.extend(offsets);
+        self.size = self.size.saturating_add(u64::try_from(text.len())?);
+        Ok(())
+    }
+
+    /// Page the lines in the range back in from the file.
+    pub(crate) async fn read(&self, lines: core::ops::Range<usize>) -> Result<Vec<String>> {
+        use tokio::io::{AsyncReadExt as _, AsyncSeekExt as _};
+
+        let Some(start) = self.offsets.get(lines.start).copied() else {
+            return Ok(Vec::new());
+        };
+        let end = self.offsets.get(lines.end).copied().unwrap_or(self.size);
+        let mut file = tokio::fs::File::open(&self.path).await?;
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+        let mut buffer = vec![0; usize::try_from(end.saturating_sub(start))?];
+        file.read_exact(&mut buffer).await?;
+        Ok(String::from_utf8_lossy(&buffer)
+            .lines()
+            .map(str::to_owned)
+            .collect())
+    }
+
+    /// Every spilled line that contains the query, along with its index. The file is read a line
+    /// at a time, so the spill is never all in memory at once.
+    pub(crate) async fn find(&self, query: &str) -> Result<Vec<(usize, String)>> {
+        use tokio::io::AsyncBufReadExt as _;
+
+        let mut found = Vec::new();
+        if self.offsets.is_empty() || query.is_empty() {
+            return Ok(found);
+        }
+        let file = tokio::fs::File::open(&self.path).await?;
+        let mut lines = tokio::io::BufReader::new(file).lines();
+        let mut index = 0;
+        while let Some(line) = lines.next_line().await? {
+            if index >= self.len() {
+                break;
+            }
+            if line.contains(query) {
+                found.push((index, line));
+            }
+            index = index.saturating_add(1);
+        }
+        Ok(found)
+    }
+
+    /// Forget every spilled line, and remove the file.
+    async fn remove(&mut self) {
+        self.offsets.clear();
+        self.size = 0;
+        if let Err(error) = tokio::fs::remove_file(&self.path).await {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                tracing::debug!("Couldn't remove {:?}: {error:?}", self.path);
+            }
+        }
+    }
+}
+
+/// The path of a session's file. The first rotation is `0`, which is the file currently being
+/// written to.
+fn session_file(directory: &std::path::Path, session: &str, rotation: usize) -> std::path::PathBuf {
+    if rotation == 0 {
+        return directory.join(format!("{session}.{EXTENSION}"));
+    }
+    directory.join(format!("{session}.{rotation}.{EXTENSION}"))
+}
+
+/// Find the lines that have been removed from the top of the scrollback, and the lines that have
+/// been added to the bottom, since we last looked. We look for where the previous lines overlap
+/// with the start of the current ones.
+fn scrolled_lines<'previous, 'current>(
+    previous: &'previous [String],
+    current: &'current [String],
+) -> (&'previous [String], &'current [String]) {
+    for start in 0..previous.len() {
+        let Some(overlap) = previous.get(start..) else {
+            continue;
+        };
+        if current.starts_with(overlap) {
+            return (
+                previous.get(..start).unwrap_or_default(),
+                current.get(overlap.len()..).unwrap_or_default(),
+            );
+        }
+    }
+    (previous, current)
+}
+
+/// The most recently written scrollback file of a previous session.
+fn previous_session_file(directory: &std::path::Path) -> Option<std::path::PathBuf> {
+    let entries = std::fs::read_dir(directory).ok()?;
+    entries
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            // Rotated files have a number before the extension.
+            let stem = path.file_stem().map(std::path::Path::new);
+            path.extension()
+                .is_some_and(|extension| extension == EXTENSION)
+                && stem.is_some_and(|stem| stem.extension().is_none())
+        })
+        .max_by_key(|path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+}
+
+/// Change the startup command so that it first shows the previous session's scrollback. It's
+/// shown when the config says to, or when the whole session is being restored.
+pub(crate) fn with_restored_scrollback(
+    config: &Config,
+    command: Vec<std::ffi::OsString>,
+    is_restoring_session: bool,
+) -> Vec<std::ffi::OsString> {
+    let is_restoring = config.restore || is_restoring_session;
+    if !config.persist || !is_restoring || command.is_empty() {
+        return command;
+    }
+    let Some(previous) = previous_session_file(&config.directory) else {
+        return command;
+    };
+
+    tracing::debug!("Restoring scrollback from: {previous:?}");
+    let mut restoring: Vec<std::ffi::OsString> = vec![
+        "sh".into(),
+        "-c".into(),
+        r#"cat -- "$1"; shift; exec "$@""#.into(),
+        "tattoy".into(),
+        previous.into(),
+    ];
+    restoring.extend(command);
+    restoring
+}
+
+/// `ScrollbackLog`
+pub(crate) struct ScrollbackLog {
+    /// The application shared state
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+    /// The name of this session's files.
+    session: String,
+    /// The lines of the scrollback, above the screen, that have already been saved.
+    saved: Vec<String>,
+}
+
+impl ScrollbackLog {
+    /// Start the task that saves the scrollback.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let mut protocol = state.protocol_tx.subscribe();
+            let started = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let mut log = Self {
+                state,
+                session: format!("session-{started}-{}", std::process::id()),
+                saved: Vec::new(),
+            };
+            log.remove_old_files().await;
+            let mut check_at = None;
+
+            #[expect(
+                clippy::integer_division_remainder_used,
+                reason = "This is caused by the `tokio::select!`"
+            )]
+            loop {
+                tokio::select! {
+                    () = crate::cwd::Cwd::sleep_until(check_at), if check_at.is_some() => {
+                        check_at = None;
+                        log.save(false).await?;
+                    },
+                    message = protocol.recv() => match message {
+                        Ok(crate::run::Protocol::End)
+                        | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        Ok(message) => {
+                            if crate::tattoys::tattoyer::Tattoyer::is_screen_output_changed(&message) {
+                                check_at = Some(tokio::time::Instant::now() + SETTLE_DELAY);
+                            }
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Scrollback saving lagged behind by {skipped} messages");
+                        }
+                    }
+                }
+            }
+
+            log.save(true).await?;
+            log.state.scrollback_spill.write().await.remove().await;
+            tracing::debug!("Leaving scrollback saving loop");
+            Ok(())
+        })
+    }
+
+    /// Spill any lines that have fallen off the top of the scrollback, and append any new lines
+    /// to this session's file. When exiting, the lines still on the screen are saved too.
+    async fn save(&mut self, is_exiting: bool) -> Result<()> {
+        let config = self.state.config.read().await.scrollback.clone();
+        if !config.persist && !config.spill {
+            return Ok(());
+        }
+        // Full screen apps like Vim don't add to the scrollback.
+        if !is_exiting && self.state.get_is_alternate_screen().await {
+            return Ok(());
+        }
+
+        let scrollback = self.state.shadow_tty_scrollback.read().await;
+        let mut lines = scrollback
+            .surface
+            .screen_chars_to_string()
+            .lines()
+            .map(|line| line.trim_end().to_owned())
+            .collect::<Vec<String>>();
+        drop(scrollback);
+
+        let screen_height = usize::from(self.state.get_tty_size().await.height);
+        let history_length = lines.len().saturating_sub(screen_height);
+        let (dropped, new) =
+            scrolled_lines(&self.saved, lines.get(..history_length).unwrap_or_default());
+        let mut unsaved = new.to_vec();
+        if config.spill && !dropped.is_empty() {
+            self.state
+                .scrollback_spill
+                .write()
+                .await
+                .append(dropped)
+                .await?;
+        }
+        if is_exiting {
+            let screen = lines.split_off(history_length);
+            let end = screen
+                .iter()
+                .rposition(|line| !line.is_empty())
+                .map_or(0, |last| last + 1);
+            unsaved.extend(screen.into_iter().take(end));
+        } else {
+            lines.truncate(history_length);
+            self.saved = lines;
+        }
+
+        if !config.persist || unsaved.is_empty() {
+            return Ok(());
+        }
+        self.append(&config, &unsaved).await
+    }
+
+    /// Append lines to the session's file, rotating it first if it's too big.
+    async fn append(&self, config: &Config, lines: &[String]) -> Result<()> {
+        tokio::fs::create_dir_all(&config.directory).await?;
+        let path = session_file(&config.directory, &self.session, 0);
+        let size = tokio::fs::metadata(&path)
+            .await
+            .map_or(0, |metadata| metadata.len());
+        if size >= config.max_file_size.saturating_mul(1024) {
+            self.rotate(config).await?;
+        }
+
+        let mut text = lines.join("\n");
+        text.push('\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        tokio::io::AsyncWriteExt::write_all(&mut file, text.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Move each of the session's files along by one, forgetting the oldest.
+    async fn rotate(&self, config: &Config) -> Result<()> {
+        let oldest = session_file(&config.directory, &self.session, config.max_files);
+        if tokio::fs::try_exists(&oldest).await? {
+            tokio::fs::remove_file(&oldest).await?;
+        }
+        for rotation in (0..config.max_files).rev() {
+            let from = session_file(&config.directory, &self.session, rotation);
+            if tokio::fs::try_exists(&from).await? {
+                let to = session_file(&config.directory, &self.session, rotation + 1);
+                tokio::fs::rename(from, to).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete the scrollback files of sessions that are older than the retention period.
+    async fn remove_old_files(&self) {
+        let config = self.state.config.read().await.scrollback.clone();
+        if !config.persist {
+            return;
+        }
+        let Ok(retention) =
+            std::time::Duration::try_from_secs_f32(config.retention_days * 86_400.0)
+        else {
+            return;
+        };
+        let Ok(mut entries) = tokio::fs::read_dir(&config.directory).await else {
+            return;
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != EXTENSION)
+            {
+                continue;
+            }
+            let age = entry
+                .metadata()
+                .await
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok());
+            if age.is_some_and(|age| age > retention) {
+                tracing::debug!("Removing old scrollback file: {path:?}");
+                if let Err(error) = tokio::fs::remove_file(&path).await {
+                    tracing::warn!("Couldn't remove {path:?}: {error:?}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| (*line).to_owned()).collect()
+    }
+
+    #[test]
+    fn finds_new_lines() {
+        let new_lines = |previous, current| scrolled_lines(previous, current).1.to_vec();
+        let previous = lines(&["a", "b", "c"]);
+        assert_eq!(new_lines(&previous, &lines(&["a", "b", "c", "d"])), ["d"]);
+        assert_eq!(
+            new_lines(&previous, &lines(&["b", "c", "d", "e"])),
+            ["d", "e"]
+        );
+        assert_eq!(new_lines(&previous, &lines(&["x", "y"])), ["x", "y"]);
+        assert!(new_lines(&previous, &previous).is_empty());
+        assert_eq!(new_lines(&[], &lines(&["a"])), ["a"]);
+    }
+
+    #[test]
+    fn finds_lines_that_fell_off_the_top() {
+        let previous = lines(&["a", "b", "c"]);
+        assert!(scrolled_lines(&previous, &lines(&["a", "b", "c", "d"]))
+            .0
+            .is_empty());
+        assert_eq!(
+            scrolled_lines(&previous, &lines(&["c", "d", "e"])).0,
+            ["a", "b"]
+        );
+        assert_eq!(scrolled_lines(&previous, &lines(&["x"])).0, previous);
+    }
+
+    #[tokio::test]
+    async fn spilled_lines_are_paged_back_in() {
+        let mut spill = Spill::new(
+            std::env::temp_dir().join(format!("tattoy-test-{}.spill", std::process::id())),
+        );
+        spill.append(&lines(&["one", "", "three"])).await.unwrap();
+        spill.append(&lines(&["four"])).await.unwrap();
+        assert_eq!(spill.len(), 4);
+
+        assert_eq!(spill.read(1..3).await.unwrap(), ["", "three"]);
+        assert_eq!(spill.read(3..10).await.unwrap(), ["four"]);
+        assert!(spill.read(4..5).await.unwrap().is_empty());
+        assert_eq!(
+            spill.find("o").await.unwrap(),
+            [(0, "one".to_owned()), (3, "four".to_owned())]
+        );
+
+        spill.remove().await;
+        assert_eq!(spill.len(), 0);
+        assert!(!spill.path.exists());
+    }
+
+    #[test]
+    fn session_files_are_rotated_by_number() {
+        let directory = std::path::Path::new("/tmp");
+        assert_eq!(
+            session_file(directory, "session-1", 0),
+            std::path::PathBuf::from("/tmp/session-1.log")
+        );
+        assert_eq!(
+            session_file(directory, "session-1", 2),
+            std::path::PathBuf::from("/tmp/session-1.2.log")
+        );
+    }
+
+    #[test]
+    fn restoring_wraps_the_command() {
+        let directory = std::env::temp_dir().join(format!("tattoy-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("session-1.log"), "hello\n").unwrap();
+        std::fs::write(directory.join("session-1.1.log"), "older\n").unwrap();
+
+        let config = Config {
+            persist: true,
+            restore: true,
+            directory: directory.clone(),
+            ..Config::default()
+        };
+        let command = with_restored_scrollback(&config, vec!["zsh".into()], false);
+        assert_eq!(command.first().unwrap(), "sh");
+        assert_eq!(
+            command.get(4).unwrap(),
+            directory.join("session-1.log").as_os_str()
+        );
+        assert_eq!(command.last().unwrap(), "zsh");
+
+        let not_restoring = Config {
+            restore: false,
+            ..config.clone()
+        };
+        let command = with_restored_scrollback(&not_restoring, vec!["zsh".into()], false);
+        assert_eq!(command, ["zsh"]);
+        let command = with_restored_scrollback(&not_restoring, vec!["zsh".into()], true);
+        assert_eq!(command.first().unwrap(), "sh");
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
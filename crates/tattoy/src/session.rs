@@ -0,0 +1,197 @@
+//! Saving the state of a session when Tattoy exits, so that `tattoy --restore` can resume where
+//! the user left off: the same size of terminal, the same scene, the same tattoys and, when the
+//! scrollback is being saved to disk, the same scrollback.
+//!
+//! Only the last session to exit is remembered.
+
+use color_eyre::eyre::Result;
+
+/// The name of the file that the last session is saved to.
+const FILE_NAME: &str = "session.toml";
+
+/// User-configurable settings for saving sessions.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to save the session when Tattoy exits, so that it can be restored with
+    /// `tattoy --restore`.
+    pub save: bool,
+    /// The directory that the session is saved in. When it's empty, because the system doesn't
+    /// have a standard place for it, the config directory is used.
+    pub directory: std::path::PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let directory = dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map_or_else(std::path::PathBuf::new, |directory| {
+                directory.join("tattoy")
+            });
+        Self {
+            save: true,
+            directory,
+        }
+    }
+}
+
+/// What's remembered about a session.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub(crate) struct Session {
+    /// The width of the user's terminal.
+    pub width: u16,
+    /// The height of the user's terminal.
+    pub height: u16,
+    /// The scene that was applied.
+    pub scene: Option<String>,
+    /// The tattoys that were enabled with `--use`.
+    pub enabled_tattoys: Vec<String>,
+    /// Whether the session's scrollback was saved to disk.
+    pub has_scrollback: bool,
+}
+
+impl Session {
+    /// Remember the current session.
+    pub(crate) async fn save(
+        state: &crate::shared_state::SharedState,
+        enabled_tattoys: Vec<String>,
+    ) -> Result<()> {
+        let config = state.config.read().await;
+        if !config.session.save {
+            return Ok(());
+        }
+        let has_scrollback = config.scrollback.persist;
+        drop(config);
+        let directory = Self::directory(state).await;
+
+        let size = state.get_tty_size().await;
+        let session = Self {
+            width: size.width,
+            height: size.height,
+            scene: state.scene.read().await.clone(),
+            enabled_tattoys,
+            has_scrollback,
+        };
+        tokio::fs::create_dir_all(&directory).await?;
+        let path = directory.join(FILE_NAME);
+        tracing::debug!("Saving the session to: {path:?}");
+        tokio::fs::write(path, toml::to_string(&session)?).await?;
+        Ok(())
+    }
+
+    /// Load the last session, if there is one.
+    pub(crate) async fn load(state: &crate::shared_state::SharedState) -> Option<Self> {
+        let path = Self::directory(state).await.join(FILE_NAME);
+        let data = match tokio::fs::read_to_string(&path).await {
+            Ok(data) => data,
+            Err(error) => {
+                tracing::warn!("Couldn't read the last session from {path:?}: {error:?}");
+                return None;
+            }
+        };
+        match toml::from_str(&data) {
+            Ok(session) => Some(session),
+            Err(error) => {
+                tracing::warn!("Couldn't parse the last session: {error:?}");
+                None
+            }
+        }
+    }
+
+    /// The directory that the session is saved in. Without one, it's the config directory rather
+    /// than wherever Tattoy happens to have been started.
+    async fn directory(state: &crate::shared_state::SharedState) -> std::path::PathBuf {
+        let directory = state.config.read().await.session.directory.clone();
+        if directory.as_os_str().is_empty() {
+            return state.config_path.read().await.clone();
+        }
+        directory
+    }
+
+    /// Ask the user's terminal to resize itself to the size of the session, using the xterm
+    /// `CSI 8 t` sequence. Plenty of terminals, especially tiling ones, ignore it, in which case
+    /// the current size is kept.
+    pub(crate) fn resize_users_terminal(&self) -> Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Ok(());
+        }
+        let sequence = resize_sequence(self.width, self.height);
+        let mut stdout = std::io::stdout();
+        std::io::Write::write_all(&mut stdout, sequence.as_bytes())?;
+        std::io::Write::flush(&mut stdout)?;
+        Ok(())
+    }
+
+    /// Add the session's tattoys to the ones enabled on the command line.
+    pub(crate) fn add_enabled_tattoys(&self, enabled_tattoys: &mut Vec<String>) {
+        for tattoy in &self.enabled_tattoys {
+            if !enabled_tattoys.contains(tattoy) {
+                enabled_tattoys.push(tattoy.clone());
+            }
+        }
+    }
+}
+
+/// The xterm sequence that asks a terminal to resize itself, in rows and columns.
+fn resize_sequence(width: u16, height: u16) -> String {
+    format!("{}[8;{height};{width}t", crate::utils::ESCAPE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sessions_survive_a_round_trip() {
+        let session = Session {
+            width: 120,
+            height: 40,
+            scene: Some("focus".to_owned()),
+            enabled_tattoys: vec!["minimap".to_owned()],
+            has_scrollback: true,
+        };
+        let data = toml::to_string(&session).unwrap();
+        assert_eq!(toml::from_str::<Session>(&data).unwrap(), session);
+        assert_eq!(
+            toml::from_str::<Session>("width = 80").unwrap(),
+            Session {
+                width: 80,
+                ..Session::default()
+            }
+        );
+    }
+
+    #[test]
+    fn restored_tattoys_are_not_duplicated() {
+        let session = Session {
+            enabled_tattoys: vec!["minimap".to_owned(), "shader".to_owned()],
+            ..Session::default()
+        };
+        let mut enabled = vec!["shader".to_owned()];
+        session.add_enabled_tattoys(&mut enabled);
+        assert_eq!(enabled, ["shader", "minimap"]);
+        assert_eq!(resize_sequence(80, 24), "\x1b[8;24;80t");
+    }
+
+    #[tokio::test]
+    async fn sessions_without_a_directory_go_in_the_config_directory() {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1);
+        let state = crate::shared_state::SharedState::init(1, 1, protocol_tx)
+            .await
+            .unwrap();
+        *state.config_path.write().await = "/config/tattoy".into();
+
+        state.config.write().await.session.directory = std::path::PathBuf::new();
+        assert_eq!(
+            Session::directory(&state).await,
+            std::path::PathBuf::from("/config/tattoy")
+        );
+
+        state.config.write().await.session.directory = "/state/tattoy".into();
+        assert_eq!(
+            Session::directory(&state).await,
+            std::path::PathBuf::from("/state/tattoy")
+        );
+    }
+}
@@ -0,0 +1,495 @@
+//! `tattoy setup`: an interactive first-run wizard. It shows what the user's terminal supports,
+//! lets them pick some starter tattoys and a shader, previews their picks by running Tattoy
+//! itself, and then writes a config file. The config is the fully commented default config with
+//! just the picked settings changed, so that it's still a good place to learn about everything
+//! else.
+
+#![expect(clippy::print_stdout, reason = "We need to give user feedback")]
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+use termwiz::terminal::Terminal as _;
+
+/// The name of the config file that previews are run with. It's in the config directory so that
+/// relative paths, like those of shaders, still work.
+const PREVIEW_CONFIG_FILE_NAME: &str = "setup_preview.toml";
+
+/// The comment at the top of the config files that the wizard writes.
+const HEADER: &str = "# Written by `tattoy setup`. Run it again at any time to start over.\n\n";
+
+/// A tattoy, or setting, that can be switched on from the wizard.
+struct Starter {
+    /// The config section that the starter's `enabled` setting is in.
+    section: &'static str,
+    /// What the starter does.
+    description: &'static str,
+}
+
+/// Everything that can be picked, in the order that it's shown.
+const STARTERS: &[Starter] = &[
+    Starter {
+        section: "minimap",
+        description: "A scaled-down overview of the scrollback",
+    },
+    Starter {
+        section: "shader",
+        description: "A GPU shader rendered behind your text",
+    },
+    Starter {
+        section: "crt",
+        description: "Retro CRT scanlines and curvature",
+    },
+    Starter {
+        section: "bloom",
+        description: "A soft glow around bright text",
+    },
+    Starter {
+        section: "weather",
+        description: "Falling snow, rain or leaves",
+    },
+    Starter {
+        section: "fireworks",
+        description: "Fireworks when a command succeeds",
+    },
+    Starter {
+        section: "animated_cursor",
+        description: "A shader-animated cursor",
+    },
+    Starter {
+        section: "git_watermark",
+        description: "The git status of the current directory, faintly in a corner",
+    },
+    Starter {
+        section: "progress_bar",
+        description: "A progress bar for apps that report their progress",
+    },
+    Starter {
+        section: "visual_bell",
+        description: "Flash the edges of the terminal for output events",
+    },
+    Starter {
+        section: "paste_guard",
+        description: "Ask before sending suspicious pastes",
+    },
+    Starter {
+        section: "redaction",
+        description: "Cover up secrets, like API keys and tokens",
+    },
+    Starter {
+        section: "kitty_keyboard",
+        description: "Use the kitty keyboard protocol for richer keybindings",
+    },
+];
+
+/// What the user asked the wizard to do next.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    /// Keep asking.
+    Redraw,
+    /// Run Tattoy with the current picks.
+    Preview,
+    /// Write the config.
+    Save,
+    /// Leave without writing anything.
+    Quit,
+}
+
+/// The state of the wizard.
+struct Wizard {
+    /// What the user's terminal supports.
+    capabilities: crate::capabilities::Capabilities,
+    /// Whether each of the starters is picked.
+    picked: Vec<bool>,
+    /// The row of the starter that the cursor is on.
+    cursor: usize,
+    /// The shaders in the user's shader directory, relative to the config directory.
+    shaders: Vec<String>,
+    /// The index of the picked shader.
+    shader: usize,
+}
+
+impl Wizard {
+    /// Instantiate, with recommendations based on what the terminal supports.
+    fn new(capabilities: crate::capabilities::Capabilities, shaders: Vec<String>) -> Self {
+        let picked = STARTERS
+            .iter()
+            .map(|starter| match starter.section {
+                "minimap" | "paste_guard" => true,
+                "shader" => capabilities.truecolor,
+                "kitty_keyboard" => capabilities.kitty_keyboard,
+                _ => false,
+            })
+            .collect();
+        let shader = shaders
+            .iter()
+            .position(|shader| shader.ends_with(crate::config::main::DEFAULT_SHADER_FILENAME))
+            .unwrap_or_default();
+
+        Self {
+            capabilities,
+            picked,
+            cursor: 0,
+            shaders,
+            shader,
+        }
+    }
+
+    /// Handle a keypress.
+    fn handle_key(&mut self, key_event: &termwiz::input::KeyEvent) -> Action {
+        use termwiz::input::KeyCode;
+
+        let is_on_shader = STARTERS
+            .get(self.cursor)
+            .is_some_and(|starter| starter.section == "shader");
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "Only a few keys mean anything to the wizard"
+        )]
+        match key_event.key {
+            KeyCode::Char('c') if key_event.modifiers == termwiz::input::Modifiers::CTRL => {
+                return Action::Quit
+            }
+            KeyCode::UpArrow | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::DownArrow | KeyCode::Char('j') => {
+                self.cursor = (self.cursor + 1).min(STARTERS.len().saturating_sub(1));
+            }
+            KeyCode::Char(' ') => {
+                if let Some(is_picked) = self.picked.get_mut(self.cursor) {
+                    *is_picked = !*is_picked;
+                }
+            }
+            KeyCode::LeftArrow | KeyCode::Char('h') if is_on_shader => self.cycle_shader(-1),
+            KeyCode::RightArrow | KeyCode::Char('l') if is_on_shader => self.cycle_shader(1),
+            KeyCode::Char('p') => return Action::Preview,
+            KeyCode::Enter => return Action::Save,
+            KeyCode::Escape | KeyCode::Char('q') => return Action::Quit,
+            _ => (),
+        }
+        Action::Redraw
+    }
+
+    /// Pick the next, or previous, shader.
+    fn cycle_shader(&mut self, direction: isize) {
+        let Ok(count) = isize::try_from(self.shaders.len()) else {
+            return;
+        };
+        if count == 0 {
+            return;
+        }
+        let current = isize::try_from(self.shader).unwrap_or_default();
+        self.shader = usize::try_from((current + direction).rem_euclid(count)).unwrap_or_default();
+    }
+
+    /// Whether the starter for a config section is picked. `None` if there's no such starter.
+    fn is_picked(&self, section: &str) -> Option<bool> {
+        let index = STARTERS
+            .iter()
+            .position(|starter| starter.section == section)?;
+        self.picked.get(index).copied()
+    }
+
+    /// The text of the wizard.
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            "Tattoy setup".to_owned(),
+            String::new(),
+            format!("Your terminal supports: {}", self.capabilities.summary()),
+        ];
+        if !self.capabilities.truecolor {
+            lines.push(
+                "Your terminal doesn't seem to support true colour, so shaders may look banded."
+                    .to_owned(),
+            );
+        }
+        lines.push(String::new());
+        lines.push("Pick some tattoys to start with:".to_owned());
+        lines.push(String::new());
+
+        for (index, (starter, is_picked)) in STARTERS.iter().zip(&self.picked).enumerate() {
+            let pointer = if index == self.cursor { '›' } else { ' ' };
+            let checkbox = if *is_picked { 'x' } else { ' ' };
+            let mut line = format!(
+                "{pointer} [{checkbox}] {:<16} {}",
+                starter.section, starter.description
+            );
+            if starter.section == "shader" {
+                if let Some(shader) = self.shaders.get(self.shader) {
+                    line.push_str(" ‹");
+                    line.push_str(shader);
+                    line.push('›');
+                }
+            }
+            lines.push(line);
+        }
+
+        lines.push(String::new());
+        lines.push(
+            "↑/↓: move   Space: toggle   ←/→: change shader   p: preview   Enter: save   q: quit"
+                .to_owned(),
+        );
+        lines
+    }
+
+    /// The config file for the current picks. It's the given config, normally the default
+    /// config, with only the picked settings changed, so all its comments are kept.
+    fn configure(&self, template: &str) -> String {
+        let mut config = HEADER.to_owned();
+        let mut section = "";
+        let mut is_enabled_set = false;
+
+        for line in template.lines() {
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                section = name;
+                is_enabled_set = false;
+            }
+
+            let mut changed = None;
+            if !is_enabled_set && line.starts_with("enabled = ") {
+                if let Some(is_picked) = self.is_picked(section) {
+                    is_enabled_set = true;
+                    changed = Some(format!("enabled = {is_picked}"));
+                }
+            } else if section == "shader" && line.starts_with("path = ") {
+                changed = self
+                    .shaders
+                    .get(self.shader)
+                    .map(|shader| format!("path = {}", toml::Value::String(shader.clone())));
+            }
+
+            config.push_str(changed.as_deref().unwrap_or(line));
+            config.push('\n');
+        }
+
+        config
+    }
+}
+
+/// Our main entrypoint.
+pub(crate) async fn run(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Result<()> {
+    let directory = crate::config::main::Config::directory(state).await;
+    let config_path = crate::config::main::Config::main_config_path(state).await;
+    let mut wizard = Wizard::new(state.get_capabilities().await, find_shaders(&directory));
+
+    loop {
+        match ask(&mut wizard)? {
+            Action::Preview => {
+                preview(
+                    &directory,
+                    &wizard.configure(crate::config::main::DEFAULT_CONFIG),
+                )?;
+            }
+            Action::Save => {
+                save(
+                    &config_path,
+                    &wizard.configure(crate::config::main::DEFAULT_CONFIG),
+                )?;
+                return Ok(());
+            }
+            Action::Quit | Action::Redraw => {
+                println!("Setup cancelled, nothing was changed.");
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// The user's shaders, relative to the config directory.
+fn find_shaders(directory: &std::path::Path) -> Vec<String> {
+    let shaders_directory = directory.join(crate::config::main::SHADER_DIRECTORY_NAME);
+    let Ok(entries) = std::fs::read_dir(shaders_directory) else {
+        return Vec::new();
+    };
+
+    let mut shaders = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| {
+            std::path::Path::new(name)
+                .extension()
+                .is_some_and(|extension| extension == "glsl")
+        })
+        .map(|name| format!("{}/{name}", crate::config::main::SHADER_DIRECTORY_NAME))
+        .collect::<Vec<String>>();
+    shaders.sort();
+    shaders
+}
+
+/// Show the wizard until the user wants to do something other than change their picks.
+fn ask(wizard: &mut Wizard) -> Result<Action> {
+    let mut terminal = crate::renderer::Renderer::get_termwiz_terminal()?;
+    terminal.set_raw_mode()?;
+    terminal.enter_alternate_screen()?;
+
+    let result = handle_input(&mut terminal, wizard);
+
+    terminal.exit_alternate_screen()?;
+    terminal.set_cooked_mode()?;
+    result
+}
+
+/// Redraw the wizard after every keypress, until a key does more than change the picks.
+fn handle_input(
+    terminal: &mut termwiz::terminal::SystemTerminal,
+    wizard: &mut Wizard,
+) -> Result<Action> {
+    loop {
+        draw(terminal, &wizard.lines())?;
+        let Some(event) = terminal.poll_input(None)? else {
+            continue;
+        };
+        if let termwiz::input::InputEvent::Key(key_event) = event {
+            let action = wizard.handle_key(&key_event);
+            if action != Action::Redraw {
+                return Ok(action);
+            }
+        }
+    }
+}
+
+/// Draw the wizard's text.
+fn draw(terminal: &mut termwiz::terminal::SystemTerminal, lines: &[String]) -> Result<()> {
+    let mut changes = vec![
+        termwiz::surface::Change::ClearScreen(termwiz::color::ColorAttribute::Default),
+        termwiz::surface::Change::CursorVisibility(termwiz::surface::CursorVisibility::Hidden),
+    ];
+    for (y, line) in lines.iter().enumerate() {
+        changes.push(termwiz::surface::Change::CursorPosition {
+            x: termwiz::surface::Position::Absolute(0),
+            y: termwiz::surface::Position::Absolute(y),
+        });
+        changes.push(termwiz::surface::Change::Text(line.clone()));
+    }
+    changes.push(termwiz::surface::Change::CursorVisibility(
+        termwiz::surface::CursorVisibility::Visible,
+    ));
+    terminal.render(&changes)?;
+    terminal.flush()?;
+    Ok(())
+}
+
+/// Run Tattoy with the picks, back in the user's normal screen.
+fn preview(directory: &std::path::Path, config: &str) -> Result<()> {
+    let preview_path = directory.join(PREVIEW_CONFIG_FILE_NAME);
+    std::fs::write(&preview_path, config)?;
+    println!("Previewing your picks. Exit the shell, eg with `exit`, to get back to the setup.");
+
+    let status = std::process::Command::new(std::env::current_exe()?)
+        .arg("--config-dir")
+        .arg(directory)
+        .arg("--main-config")
+        .arg(PREVIEW_CONFIG_FILE_NAME)
+        // Otherwise the preview would think that it's Tattoy running inside Tattoy.
+        .env_remove("TATTOY_RUNNING")
+        .status();
+    std::fs::remove_file(&preview_path)?;
+
+    let status = status?;
+    if !status.success() {
+        tracing::warn!("Setup preview exited with: {status}");
+    }
+    Ok(())
+}
+
+/// Write the config. A config that the user has changed is backed up first.
+fn save(path: &std::path::Path, config: &str) -> Result<()> {
+    let existing = std::fs::read_to_string(path).ok();
+    if existing.is_some_and(|existing| existing != crate::config::main::DEFAULT_CONFIG) {
+        let backup = path.with_extension("toml.bak");
+        std::fs::copy(path, &backup)?;
+        println!("Your previous config was saved to: {}", backup.display());
+    }
+
+    std::fs::write(path, config)?;
+    println!("Config saved to: {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(key: termwiz::input::KeyCode) -> termwiz::input::KeyEvent {
+        termwiz::input::KeyEvent {
+            key,
+            modifiers: termwiz::input::Modifiers::NONE,
+        }
+    }
+
+    fn wizard() -> Wizard {
+        Wizard::new(
+            crate::capabilities::Capabilities {
+                truecolor: true,
+                ..crate::capabilities::Capabilities::default()
+            },
+            vec!["shaders/a.glsl".to_owned(), "shaders/b.glsl".to_owned()],
+        )
+    }
+
+    #[test]
+    fn recommendations_depend_on_the_terminal() {
+        let wizard = wizard();
+        assert_eq!(wizard.is_picked("minimap"), Some(true));
+        assert_eq!(wizard.is_picked("shader"), Some(true));
+        assert_eq!(wizard.is_picked("kitty_keyboard"), Some(false));
+        assert_eq!(wizard.is_picked("panes"), None);
+
+        let basic = Wizard::new(crate::capabilities::Capabilities::default(), Vec::new());
+        assert_eq!(basic.is_picked("shader"), Some(false));
+    }
+
+    #[test]
+    fn keys_change_the_picks() {
+        let mut wizard = wizard();
+        assert_eq!(
+            wizard.handle_key(&key(termwiz::input::KeyCode::Char(' '))),
+            Action::Redraw
+        );
+        assert_eq!(wizard.is_picked("minimap"), Some(false));
+
+        wizard.handle_key(&key(termwiz::input::KeyCode::DownArrow));
+        wizard.handle_key(&key(termwiz::input::KeyCode::LeftArrow));
+        assert_eq!(wizard.shader, 1);
+        wizard.handle_key(&key(termwiz::input::KeyCode::RightArrow));
+        assert_eq!(wizard.shader, 0);
+
+        assert_eq!(
+            wizard.handle_key(&key(termwiz::input::KeyCode::Char('p'))),
+            Action::Preview
+        );
+        assert_eq!(
+            wizard.handle_key(&key(termwiz::input::KeyCode::Enter)),
+            Action::Save
+        );
+    }
+
+    #[test]
+    fn configs_keep_their_comments() {
+        let mut wizard = wizard();
+        wizard.shader = 1;
+        let template = "# Top\n[minimap]\n# Comment\nenabled = false\n[shader]\nenabled = false\n\
+                        path = \"shaders/x.glsl\"\n[crt]\nenabled = true\n[color]\nhue = 0.0\n";
+        assert_eq!(
+            wizard.configure(template),
+            format!(
+                "{HEADER}# Top\n[minimap]\n# Comment\nenabled = true\n[shader]\nenabled = true\n\
+                path = \"shaders/b.glsl\"\n[crt]\nenabled = false\n[color]\nhue = 0.0\n"
+            )
+        );
+    }
+
+    #[test]
+    fn the_default_config_is_configurable() {
+        let config = wizard().configure(crate::config::main::DEFAULT_CONFIG);
+        let parsed = toml::from_str::<crate::config::main::Config>(&config).unwrap();
+        assert!(parsed.minimap.enabled);
+        assert!(parsed.shader.enabled);
+        assert_eq!(
+            parsed.shader.path,
+            std::path::PathBuf::from("shaders/a.glsl")
+        );
+        assert!(!parsed.crt.enabled);
+    }
+}
@@ -0,0 +1,498 @@
+//! `tattoy shader-dev`: a lightweight, local Shadertoy for writing shaders. It runs only the GPU
+//! pipeline, without a shell, against some pretend terminal text, and renders the shader over the
+//! whole of the user's terminal. The shader is reloaded whenever it's saved, and keys tweak the
+//! shader's variables, like the position of the cursor or whether the bell just rang.
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+use termwiz::terminal::Terminal as _;
+
+/// The pretend terminal text that the shader is rendered against.
+const SAMPLE_TEXT: &[&str] = &[
+    "~/projects/tattoy $ ls",
+    "Cargo.lock  Cargo.toml  README.md  crates  website",
+    "~/projects/tattoy $ git status",
+    "On branch main",
+    "nothing to commit, working tree clean",
+    "~/projects/tattoy $ ",
+];
+
+/// How many seconds the `[` and `]` keys move time by.
+const SCRUB_SECONDS: f32 = 1.0;
+
+/// The colour of the pretend text, both in the pixels uploaded to the shader and on top of the
+/// render.
+const TEXT_COLOUR: crate::surface::Colour = (0.85, 0.85, 0.85, 1.0);
+
+/// `TEXT_COLOUR` as the bytes of a pixel.
+const TEXT_PIXEL: [u8; 4] = [217, 217, 217, 255];
+
+/// The keys, shown in the status line.
+const HELP: &str = "arrows: cursor  space: pause  [ ]: time  b: bell  s/e: command  f: focus  \
+    t: text  r: reload  q: quit";
+
+/// Something that a key does to the shader.
+#[derive(Debug, PartialEq)]
+enum Tweak {
+    /// The key doesn't do anything special.
+    Nothing,
+    /// Stop previewing.
+    Quit,
+    /// Compile the shader again.
+    Reload,
+    /// Move time forwards, or backwards when negative, by some seconds.
+    Scrub(f32),
+    /// Ring the bell.
+    Bell,
+    /// Pretend that a command finished, with an exit status.
+    CommandFinished(i32),
+    /// Pretend that the terminal gained (`true`) or lost (`false`) focus.
+    Focus(bool),
+    /// Something about the pretend terminal changed, so it needs uploading again.
+    Upload,
+}
+
+/// Everything about the preview that the user can tweak.
+struct Preview {
+    /// The shader file.
+    path: std::path::PathBuf,
+    /// When the shader file was last saved.
+    modified: Option<std::time::SystemTime>,
+    /// The size of the user's terminal, in columns and rows.
+    size: (u16, u16),
+    /// The column and row of the pretend cursor.
+    cursor: (u16, u16),
+    /// Whether time is stopped.
+    is_paused: bool,
+    /// Whether the pretend terminal text is shown.
+    is_text_shown: bool,
+    /// Whether the pretend terminal is focused.
+    is_focused: bool,
+    /// Frames rendered since `fps_since`.
+    frames: u32,
+    /// When the frames started being counted.
+    fps_since: std::time::Instant,
+    /// The frames per second over the last second.
+    fps: u32,
+}
+
+impl Preview {
+    /// Instantiate.
+    fn new(path: std::path::PathBuf, size: (u16, u16)) -> Self {
+        let modified = Self::modified_at(&path);
+        let last_line = u16::try_from(SAMPLE_TEXT.len().saturating_sub(1)).unwrap_or_default();
+        let prompt_length = SAMPLE_TEXT
+            .last()
+            .map_or(0, |line| u16::try_from(line.len()).unwrap_or_default());
+        Self {
+            path,
+            modified,
+            size,
+            cursor: (prompt_length, last_line),
+            is_paused: false,
+            is_text_shown: true,
+            is_focused: true,
+            frames: 0,
+            fps_since: std::time::Instant::now(),
+            fps: 0,
+        }
+    }
+
+    /// When a file was last saved.
+    fn modified_at(path: &std::path::Path) -> Option<std::time::SystemTime> {
+        std::fs::metadata(path).ok()?.modified().ok()
+    }
+
+    /// Whether the shader has been saved since it was last checked.
+    fn is_shader_saved(&mut self) -> bool {
+        let modified = Self::modified_at(&self.path);
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+
+    /// React to a key press.
+    fn handle_key(&mut self, key_event: &termwiz::input::KeyEvent) -> Tweak {
+        let (width, height) = self.size;
+        let (column, row) = self.cursor;
+        let is_control = key_event
+            .modifiers
+            .contains(termwiz::input::Modifiers::CTRL);
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "There are too many keys, most of which don't do anything"
+        )]
+        match key_event.key {
+            termwiz::input::KeyCode::Char('c') if is_control => Tweak::Quit,
+            termwiz::input::KeyCode::Char('q') | termwiz::input::KeyCode::Escape => Tweak::Quit,
+            termwiz::input::KeyCode::Char('r') => Tweak::Reload,
+            termwiz::input::KeyCode::Char('[') => Tweak::Scrub(-SCRUB_SECONDS),
+            termwiz::input::KeyCode::Char(']') => Tweak::Scrub(SCRUB_SECONDS),
+            termwiz::input::KeyCode::Char('b') => Tweak::Bell,
+            termwiz::input::KeyCode::Char('s') => Tweak::CommandFinished(0),
+            termwiz::input::KeyCode::Char('e') => Tweak::CommandFinished(1),
+            termwiz::input::KeyCode::Char(' ') => {
+                self.is_paused = !self.is_paused;
+                Tweak::Nothing
+            }
+            termwiz::input::KeyCode::Char('f') => {
+                self.is_focused = !self.is_focused;
+                Tweak::Focus(self.is_focused)
+            }
+            termwiz::input::KeyCode::Char('t') => {
+                self.is_text_shown = !self.is_text_shown;
+                Tweak::Upload
+            }
+            termwiz::input::KeyCode::LeftArrow => {
+                self.cursor = (column.saturating_sub(1), row);
+                Tweak::Nothing
+            }
+            termwiz::input::KeyCode::RightArrow => {
+                self.cursor = ((column + 1).min(width.saturating_sub(1)), row);
+                Tweak::Nothing
+            }
+            termwiz::input::KeyCode::UpArrow => {
+                self.cursor = (column, row.saturating_sub(1));
+                Tweak::Nothing
+            }
+            termwiz::input::KeyCode::DownArrow => {
+                self.cursor = (column, (row + 1).min(height.saturating_sub(1)));
+                Tweak::Nothing
+            }
+            _ => Tweak::Nothing,
+        }
+    }
+
+    /// Count a rendered frame.
+    fn count_frame(&mut self) {
+        self.frames += 1;
+        if self.fps_since.elapsed() >= std::time::Duration::from_secs(1) {
+            self.fps = self.frames;
+            self.frames = 0;
+            self.fps_since = std::time::Instant::now();
+        }
+    }
+
+    /// The line at the bottom of the screen.
+    fn status_line(&self, time: f32) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let paused = if self.is_paused { " (paused)" } else { "" };
+        format!(" {name} │ {} fps │ {time:.1}s{paused} │ {HELP}", self.fps)
+    }
+
+    /// The cells of the pretend terminal.
+    fn cells(&self) -> Vec<Vec<termwiz::cell::Cell>> {
+        let (width, height) = self.size;
+        let text = if self.is_text_shown { SAMPLE_TEXT } else { &[] };
+        let blank = " ".repeat(usize::from(width));
+        (0..usize::from(height))
+            .map(|row| {
+                let line = text.get(row).copied().unwrap_or_default();
+                line.chars()
+                    .chain(blank.chars())
+                    .take(usize::from(width))
+                    .map(|character| {
+                        termwiz::cell::Cell::new(
+                            character,
+                            termwiz::cell::CellAttributes::default(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// The pixels of the pretend terminal, in the same format as the real terminal's pixels: 2 pixels
+/// for every cell and upside down to match the GPU's coordinates.
+fn tty_pixels(cells: &[&[termwiz::cell::Cell]]) -> image::RgbaImage {
+    let text_mask = crate::tattoys::gpu::text_mask::from_cells(cells);
+    image::RgbaImage::from_fn(text_mask.width(), text_mask.height(), |x, y| {
+        if text_mask.get_pixel(x, y).0 == [0] {
+            [0, 0, 0, 255].into()
+        } else {
+            TEXT_PIXEL.into()
+        }
+    })
+}
+
+/// Upload the pretend terminal to the GPU.
+fn upload(gpu: &mut crate::tattoys::gpu::pipeline::GPU, preview: &Preview) {
+    let cells = preview.cells();
+    let lines = cells.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+    gpu.tty_pixels = tty_pixels(&lines);
+    gpu.update_ichannel_texture_data();
+    gpu.text_mask = crate::tattoys::gpu::text_mask::from_cells(&lines);
+    gpu.update_text_mask_texture_data();
+    gpu.cell_metadata = crate::tattoys::gpu::cell_metadata::from_cells(
+        &lines,
+        termwiz::color::SrgbaTuple(TEXT_COLOUR.0, TEXT_COLOUR.1, TEXT_COLOUR.2, TEXT_COLOUR.3),
+        termwiz::color::SrgbaTuple(0.0, 0.0, 0.0, 1.0),
+    );
+    gpu.update_cell_metadata_texture_data();
+    gpu.update_cell_changes(cells);
+}
+
+/// Our main entrypoint.
+pub(crate) async fn run(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    path: &std::path::Path,
+) -> Result<()> {
+    if !path.is_file() {
+        color_eyre::eyre::bail!("Couldn't find shader: {}", path.display());
+    }
+
+    let terminal = crate::renderer::Renderer::get_termwiz_terminal()?;
+    let mut terminal = termwiz::terminal::buffered::BufferedTerminal::new(terminal)?;
+    terminal.terminal().set_raw_mode()?;
+    terminal.terminal().enter_alternate_screen()?;
+    terminal.add_change(termwiz::surface::Change::CursorVisibility(
+        termwiz::surface::CursorVisibility::Hidden,
+    ));
+
+    let result = preview(state, &mut terminal, path).await;
+
+    terminal.add_change(termwiz::surface::Change::CursorVisibility(
+        termwiz::surface::CursorVisibility::Visible,
+    ));
+    terminal.flush()?;
+    terminal.terminal().exit_alternate_screen()?;
+    terminal.terminal().set_cooked_mode()?;
+    result
+}
+
+/// Render the shader until the user quits.
+async fn preview(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    terminal: &mut termwiz::terminal::buffered::BufferedTerminal<termwiz::terminal::SystemTerminal>,
+    path: &std::path::Path,
+) -> Result<()> {
+    let (width, height) = terminal_size(terminal)?;
+    let mut preview = Preview::new(path.to_path_buf(), (width, height));
+    let mut gpu = crate::tattoys::gpu::pipeline::GPU::new(
+        path.to_path_buf(),
+        width,
+        height * 2,
+        state.protocol_tx.clone(),
+        state.get_gpu_device().await?,
+    )
+    .await?;
+    gpu.set_pixel_aspect(state.config.read().await.pixel_aspect);
+    upload(&mut gpu, &preview);
+
+    let frame_rate = state.config.read().await.frame_rate.max(1);
+    let frame_duration = std::time::Duration::from_secs(1) / frame_rate;
+    let mut last_frame = tokio::time::Instant::now();
+    loop {
+        let next_frame = last_frame + frame_duration;
+        tokio::time::sleep_until(next_frame).await;
+        let elapsed = last_frame.elapsed().as_secs_f32();
+        last_frame = tokio::time::Instant::now();
+
+        if terminal.check_for_resize()? {
+            preview.size = terminal_size(terminal)?;
+            gpu.update_resolution(preview.size.0, preview.size.1 * 2)?;
+            upload(&mut gpu, &preview);
+        }
+
+        while let Some(event) = terminal
+            .terminal()
+            .poll_input(Some(std::time::Duration::ZERO))?
+        {
+            #[expect(
+                clippy::wildcard_enum_match_arm,
+                reason = "Only keys and the mouse are used"
+            )]
+            match event {
+                termwiz::input::InputEvent::Key(key_event) => {
+                    gpu.keyboard.press(&key_event.key);
+                    match preview.handle_key(&key_event) {
+                        Tweak::Quit => return Ok(()),
+                        Tweak::Reload => gpu.switch_shader(path.to_path_buf()).await?,
+                        Tweak::Scrub(seconds) => gpu.shift_time(seconds),
+                        Tweak::Bell => gpu.ring_bell(),
+                        Tweak::CommandFinished(exit_status) => gpu.finish_command(exit_status),
+                        Tweak::Focus(is_focused) => gpu.change_focus(is_focused),
+                        Tweak::Upload => upload(&mut gpu, &preview),
+                        Tweak::Nothing => (),
+                    }
+                }
+                termwiz::input::InputEvent::Mouse(mouse) => {
+                    gpu.update_mouse_position(mouse.x, mouse.y);
+                }
+                _ => (),
+            }
+        }
+
+        if preview.is_shader_saved() {
+            tracing::info!("Reloading saved shader: {path:?}");
+            gpu.switch_shader(path.to_path_buf()).await?;
+        }
+        if preview.is_paused {
+            gpu.shift_time(-elapsed);
+        }
+        let (column, row) = preview.cursor;
+        gpu.update_cursor(column, row, [1.0, 1.0, 1.0, 1.0], 1.0);
+        gpu.update_interpolated_cursor(f32::from(column), f32::from(row), 1.0);
+
+        let image = gpu.render().await?;
+        preview.count_frame();
+        draw(terminal, &image, &preview, &gpu);
+        terminal.flush()?;
+    }
+}
+
+/// The size of the user's terminal, in columns and rows.
+fn terminal_size(
+    terminal: &termwiz::terminal::buffered::BufferedTerminal<termwiz::terminal::SystemTerminal>,
+) -> Result<(u16, u16)> {
+    let (width, height) = terminal.dimensions();
+    Ok((width.try_into()?, height.try_into()?))
+}
+
+/// Draw the render, the pretend text, any shader error and the status line.
+fn draw(
+    terminal: &mut termwiz::terminal::buffered::BufferedTerminal<termwiz::terminal::SystemTerminal>,
+    image: &image::RgbaImage,
+    preview: &Preview,
+    gpu: &crate::tattoys::gpu::pipeline::GPU,
+) {
+    let cells = preview.cells();
+    let rows = crate::pixels::rows_bottom_up(image).collect::<Vec<_>>();
+    let mut changes = Vec::new();
+    for (y, (pair, line)) in rows.chunks(2).zip(&cells).enumerate() {
+        let [top, bottom] = pair else {
+            continue;
+        };
+        changes.push(termwiz::surface::Change::CursorPosition {
+            x: termwiz::surface::Position::Absolute(0),
+            y: termwiz::surface::Position::Absolute(y),
+        });
+        let pixels = top
+            .chunks_exact(crate::pixels::CHANNELS)
+            .zip(bottom.chunks_exact(crate::pixels::CHANNELS));
+        for ((top_pixel, bottom_pixel), cell) in pixels.zip(line) {
+            let top_colour = crate::pixels::to_unit_rgba(top_pixel).into();
+            let bottom_colour = crate::pixels::to_unit_rgba(bottom_pixel).into();
+            if crate::tattoys::gpu::text_mask::is_text(cell) {
+                changes.push(crate::surface::Surface::make_fg_colour(TEXT_COLOUR));
+                changes.push(crate::surface::Surface::make_bg_colour(bottom_colour));
+                changes.push(termwiz::surface::Change::Text(cell.str().to_owned()));
+            } else {
+                changes.push(crate::surface::Surface::make_fg_colour(top_colour));
+                changes.push(crate::surface::Surface::make_bg_colour(bottom_colour));
+                changes.push(termwiz::surface::Change::Text("▀".to_owned()));
+            }
+        }
+    }
+
+    let mut overlay = gpu
+        .shader_error
+        .as_ref()
+        .map(crate::tattoys::gpu::shader_error::ShaderError::overlay)
+        .unwrap_or_default();
+    overlay.push(crate::tattoys::gpu::shader_error::Line::Source(
+        preview.status_line(gpu.get_current_time()),
+    ));
+    let status_row = usize::from(preview.size.1.saturating_sub(1));
+    let error_rows = (0..status_row).take(overlay.len().saturating_sub(1));
+    for (y, line) in error_rows.chain([status_row]).zip(overlay) {
+        let (text, colour) = line.into_text_and_colour();
+        let width = usize::from(preview.size.0);
+        let text = text.chars().take(width).collect::<String>();
+        let padding = " ".repeat(width.saturating_sub(text.chars().count()));
+        changes.extend([
+            termwiz::surface::Change::CursorPosition {
+                x: termwiz::surface::Position::Absolute(0),
+                y: termwiz::surface::Position::Absolute(y),
+            },
+            crate::surface::Surface::make_fg_colour(colour),
+            crate::surface::Surface::make_bg_colour((0.0, 0.0, 0.0, 1.0)),
+            termwiz::surface::Change::Text(format!("{text}{padding}")),
+        ]);
+    }
+
+    terminal.add_changes(changes);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(key: termwiz::input::KeyCode) -> termwiz::input::KeyEvent {
+        termwiz::input::KeyEvent {
+            key,
+            modifiers: termwiz::input::Modifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn keys_tweak_the_preview() {
+        let mut preview = Preview::new("shader.glsl".into(), (10, 5));
+        preview.cursor = (9, 0);
+
+        assert_eq!(
+            preview.handle_key(&key(termwiz::input::KeyCode::RightArrow)),
+            Tweak::Nothing
+        );
+        preview.handle_key(&key(termwiz::input::KeyCode::UpArrow));
+        preview.handle_key(&key(termwiz::input::KeyCode::DownArrow));
+        assert_eq!(preview.cursor, (9, 1));
+
+        preview.handle_key(&key(termwiz::input::KeyCode::Char(' ')));
+        assert!(preview.is_paused);
+        assert_eq!(
+            preview.handle_key(&key(termwiz::input::KeyCode::Char('t'))),
+            Tweak::Upload
+        );
+        assert!(!preview.is_text_shown);
+        assert_eq!(
+            preview.handle_key(&key(termwiz::input::KeyCode::Char('e'))),
+            Tweak::CommandFinished(1)
+        );
+        assert_eq!(
+            preview.handle_key(&key(termwiz::input::KeyCode::Char('['))),
+            Tweak::Scrub(-SCRUB_SECONDS)
+        );
+        assert_eq!(
+            preview.handle_key(&key(termwiz::input::KeyCode::Escape)),
+            Tweak::Quit
+        );
+    }
+
+    #[test]
+    fn the_pretend_terminal_fills_the_screen() {
+        let mut preview = Preview::new("shader.glsl".into(), (4, 8));
+        let cells = preview.cells();
+        assert_eq!(cells.len(), 8);
+        assert!(cells.iter().all(|line| line.len() == 4));
+        assert_eq!(
+            cells
+                .first()
+                .and_then(|line| line.first())
+                .map(|cell| cell.str()),
+            Some("~")
+        );
+
+        let lines = cells.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let pixels = tty_pixels(&lines);
+        assert_eq!(pixels.dimensions(), (4, 16));
+        // The top of the terminal is the bottom of the image.
+        assert_ne!(pixels.get_pixel(0, 15).0, [0, 0, 0, 255]);
+        assert_eq!(pixels.get_pixel(0, 0).0, [0, 0, 0, 255]);
+
+        preview.is_text_shown = false;
+        assert!(preview
+            .cells()
+            .iter()
+            .flatten()
+            .all(|cell| cell.str() == " "));
+    }
+}
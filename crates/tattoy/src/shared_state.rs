@@ -46,8 +46,36 @@ pub(crate) struct SharedState {
     /// This is the entire scrollback history of the shadow terminal.
     pub shadow_tty_scrollback:
         tokio::sync::RwLock<shadow_terminal::output::native::CompleteScrollback>,
+    /// The lines that have fallen off the top of the shadow terminal's scrollback, see
+    /// `crate::scrollback_log`.
+    pub scrollback_spill: tokio::sync::RwLock<crate::scrollback_log::Spill>,
+    /// The current working directory of the shell running in the PTY, if it's known.
+    pub cwd: tokio::sync::RwLock<Option<std::path::PathBuf>>,
     /// Is the user scrolling the scrollback?
     pub is_scrolling: tokio::sync::RwLock<bool>,
+    /// How the user's terminal is split into panes.
+    pub panes: tokio::sync::RwLock<crate::panes::layout::Layout>,
+    /// Is the user in copy mode? All input is captured by copy mode whilst it's active.
+    pub is_copy_mode: tokio::sync::RwLock<bool>,
+    /// When the user last typed something, or the PTY last output something.
+    pub last_activity: tokio::sync::RwLock<tokio::time::Instant>,
+    /// Is the screensaver hiding the terminal? All input is swallowed whilst it's active.
+    pub is_screensaver_active: tokio::sync::RwLock<bool>,
+    /// Whether the terminal is locked, and what's been typed to unlock it.
+    pub lock: tokio::sync::RwLock<crate::tattoys::lock::LockState>,
+    /// A paste that's waiting for the user to confirm it.
+    pub pending_paste: tokio::sync::RwLock<crate::tattoys::paste_guard::PendingPaste>,
+    /// Where the PTY's cursor has recently been, for smoothly animating it.
+    pub cursor_history: tokio::sync::RwLock<crate::cursor_history::History>,
+    /// The scene, from the config's `[scenes]`, that's currently applied.
+    pub scene: tokio::sync::RwLock<Option<String>>,
+    /// Tattoys that have been turned on or off whilst Tattoy is running, by their IDs. They're
+    /// applied on top of the config every time it's loaded.
+    pub tattoy_toggles: tokio::sync::RwLock<std::collections::BTreeMap<String, bool>>,
+    /// Whether stream mode, from the config's `[stream_mode]`, is on.
+    pub is_stream_mode: tokio::sync::RwLock<bool>,
+    /// The named session, from `tattoy attach`, that the PTY is running in.
+    pub attached_session: tokio::sync::RwLock<Option<String>>,
     /// Is the underlying shadow terminal in the so-called alternate screen state?
     ///
     /// * A terminal's behaviour alters slightly when it is in this state. Most notably scrolling
@@ -63,12 +91,58 @@ pub(crate) struct SharedState {
     pub is_logging: tokio::sync::RwLock<bool>,
     /// Is Tattoy rendering anything to the terminal?
     pub is_rendering_enabled: tokio::sync::RwLock<bool>,
+    /// Whether the keybinding has switched the colour blindness transform from what the config
+    /// says.
+    pub is_colour_blindness_toggled: tokio::sync::RwLock<bool>,
+    /// Whether the operating system says that the user prefers reduced motion.
+    pub os_prefers_reduced_motion: tokio::sync::RwLock<bool>,
+    /// Whether there's currently a burst of output from the PTY, during which expensive tattoys
+    /// pause.
+    pub is_output_bursting: tokio::sync::RwLock<bool>,
     // TODO: I tried adding the whole palette here, but it wasn't straightforward so I've just put
-    // the background for now.
+    // the default colours for now.
     //
     /// The default background colour from the palette. This is used when compositing or blending
     /// needs a base colour but it only has an ANSI default background colour.
     pub default_background: tokio::sync::RwLock<termwiz::color::SrgbaTuple>,
+    /// The default foreground colour from the palette, for when text needs a real colour but it
+    /// only has the ANSI default foreground colour.
+    pub default_foreground: tokio::sync::RwLock<termwiz::color::SrgbaTuple>,
+    /// The GPU device shared by all the GPU pipelines. It's only requested when the first pipeline
+    /// starts, so that users without shaders don't pay the cost. It's requested again if it's
+    /// lost.
+    pub gpu_device: tokio::sync::RwLock<Option<crate::tattoys::gpu::pipeline::Device>>,
+    /// The GPU memory used by each GPU pipeline, see `crate::tattoys::gpu::memory`.
+    pub gpu_memory: tokio::sync::RwLock<crate::tattoys::gpu::memory::Budget>,
+    /// The newest frame of each tattoy that stashed its frame rather than waiting for the
+    /// renderer.
+    pub stashed_frames:
+        tokio::sync::RwLock<std::collections::BTreeMap<String, crate::surface::Surface>>,
+    /// Surfaces that the renderer has finished with, for tattoys to reuse.
+    pub surface_pool: tokio::sync::RwLock<crate::surface::Pool>,
+    /// The TTY converted to pixels, shared by all the shaders that upload it.
+    pub tty_pixels: tokio::sync::RwLock<crate::tattoys::gpu::tty_pixels::Cache>,
+    /// Which tattoys are waiting for their last frame to be composited before rendering again.
+    pub frame_pacing: tokio::sync::RwLock<crate::backpressure::Pacing>,
+    /// Limits how many compute-heavy tattoy jobs run at once, see `Tattoyer::compute()`.
+    pub compute_permits: tokio::sync::Semaphore,
+    /// How well the renderer is keeping up with frames.
+    pub frame_metrics: tokio::sync::RwLock<crate::backpressure::FrameMetrics>,
+    /// What the user's terminal supports. Detected once at startup.
+    pub capabilities: tokio::sync::RwLock<crate::capabilities::Capabilities>,
+    /// Measurements of the time from a keystroke to its echo being painted.
+    pub latency: tokio::sync::RwLock<crate::latency::Probe>,
+    /// Measurements of Tattoy's overhead, for the metrics endpoint.
+    pub metrics: tokio::sync::RwLock<crate::metrics::Recorder>,
+    /// The live values that controllers, like MIDI sliders, have set.
+    pub live_controls: tokio::sync::RwLock<crate::controllers::Live>,
+    /// The running totals of the session, for when Tattoy exits.
+    pub exit_summary: tokio::sync::RwLock<crate::exit_summary::Tally>,
+    /// The session's typing statistics, kept by the typing speed tattoy.
+    pub typing_speed: tokio::sync::RwLock<crate::tattoys::typing_speed::Stats>,
+    /// What the renderer is currently doing, for the watchdog. It's a standard mutex so that it
+    /// can be read even when the async locks are stuck.
+    pub heartbeat: std::sync::Mutex<crate::watchdog::Heartbeat>,
 }
 
 impl SharedState {
@@ -88,12 +162,46 @@ impl SharedState {
             tty_size: RwLock::new(TTYSize { width, height }),
             shadow_tty_screen: RwLock::default(),
             shadow_tty_scrollback: RwLock::default(),
+            scrollback_spill: RwLock::default(),
+            cwd: RwLock::default(),
             is_scrolling: RwLock::default(),
+            panes: RwLock::default(),
+            is_copy_mode: RwLock::default(),
+            last_activity: RwLock::new(tokio::time::Instant::now()),
+            is_screensaver_active: RwLock::default(),
+            lock: RwLock::default(),
+            pending_paste: RwLock::default(),
+            cursor_history: RwLock::default(),
+            scene: RwLock::default(),
+            tattoy_toggles: RwLock::default(),
+            is_stream_mode: RwLock::default(),
+            attached_session: RwLock::default(),
             is_alternate_screen: RwLock::default(),
             pty_sequence: RwLock::default(),
             is_logging: RwLock::default(),
             is_rendering_enabled: RwLock::new(true),
+            is_colour_blindness_toggled: RwLock::default(),
+            os_prefers_reduced_motion: RwLock::default(),
+            is_output_bursting: RwLock::default(),
             default_background: RwLock::default(),
+            default_foreground: RwLock::new(termwiz::color::SrgbaTuple(1.0, 1.0, 1.0, 1.0)),
+            gpu_device: RwLock::default(),
+            gpu_memory: RwLock::default(),
+            stashed_frames: RwLock::default(),
+            surface_pool: RwLock::default(),
+            tty_pixels: RwLock::default(),
+            frame_metrics: RwLock::default(),
+            compute_permits: tokio::sync::Semaphore::new(
+                std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get),
+            ),
+            frame_pacing: RwLock::default(),
+            capabilities: RwLock::default(),
+            latency: RwLock::default(),
+            metrics: RwLock::default(),
+            live_controls: RwLock::default(),
+            exit_summary: RwLock::default(),
+            typing_speed: RwLock::default(),
+            heartbeat: std::sync::Mutex::default(),
         };
 
         state.set_tty_size(width, height).await;
@@ -154,6 +262,16 @@ impl SharedState {
         *tty_size = TTYSize { width, height };
     }
 
+    /// Whether animations should be calmed down, either because of the config or, when the config
+    /// doesn't say, because of the operating system's preference.
+    pub async fn is_reduced_motion(&self) -> bool {
+        let maybe_configured = self.config.read().await.reduced_motion;
+        match maybe_configured {
+            Some(is_reduced) => is_reduced,
+            None => *self.os_prefers_reduced_motion.read().await,
+        }
+    }
+
     /// Get a read lock and return whether the user is currently scrolling.
     pub async fn get_is_scrolling(&self) -> bool {
         let is_scrolling = self.is_scrolling.read().await;
@@ -166,6 +284,43 @@ impl SharedState {
         *is_scrolling = value;
     }
 
+    /// Get a read lock and return whether the user is currently in copy mode.
+    pub async fn get_is_copy_mode(&self) -> bool {
+        let is_copy_mode = self.is_copy_mode.read().await;
+        *is_copy_mode
+    }
+
+    /// Get a write lock and set the copy mode state.
+    pub async fn set_is_copy_mode(&self, value: bool) {
+        let mut is_copy_mode = self.is_copy_mode.write().await;
+        *is_copy_mode = value;
+    }
+
+    /// Record that the user or the PTY has just done something.
+    pub async fn touch_last_activity(&self) {
+        let mut last_activity = self.last_activity.write().await;
+        *last_activity = tokio::time::Instant::now();
+    }
+
+    /// Get a read lock and return how long it's been since the user or the PTY last did
+    /// something.
+    pub async fn get_inactivity(&self) -> std::time::Duration {
+        let last_activity = self.last_activity.read().await;
+        last_activity.elapsed()
+    }
+
+    /// Get a read lock and return whether the screensaver is active.
+    pub async fn get_is_screensaver_active(&self) -> bool {
+        let is_screensaver_active = self.is_screensaver_active.read().await;
+        *is_screensaver_active
+    }
+
+    /// Get a write lock and set whether the screensaver is active.
+    pub async fn set_is_screensaver_active(&self, value: bool) {
+        let mut is_screensaver_active = self.is_screensaver_active.write().await;
+        *is_screensaver_active = value;
+    }
+
     /// Get a read lock and return whether the alternate screen is currently active.
     pub async fn get_is_alternate_screen(&self) -> bool {
         let is_alternate_screen = self.is_alternate_screen.read().await;
@@ -177,4 +332,98 @@ impl SharedState {
         let mut is_alternate_screen = self.is_alternate_screen.write().await;
         *is_alternate_screen = value;
     }
+
+    /// Get a read lock and return what the user's terminal supports.
+    pub async fn get_capabilities(&self) -> crate::capabilities::Capabilities {
+        self.capabilities.read().await.clone()
+    }
+
+    /// Get the shared GPU device, requesting it from the GPU if this is the first time it's
+    /// needed, or if the last one was lost.
+    pub async fn get_gpu_device(&self) -> Result<crate::tattoys::gpu::pipeline::Device> {
+        reuse_or_request(
+            "GPU device",
+            &self.gpu_device,
+            crate::tattoys::gpu::pipeline::Device::is_lost,
+            crate::tattoys::gpu::pipeline::Device::request,
+        )
+        .await
+    }
+}
+
+/// Get a shared resource, like the GPU device, requesting it if this is the first time it's
+/// needed, or if the last one was lost.
+async fn reuse_or_request<T, F>(
+    name: &str,
+    slot: &RwLock<Option<T>>,
+    is_lost: impl Fn(&T) -> bool,
+    request: impl FnOnce() -> F,
+) -> Result<T>
+where
+    T: Clone,
+    F: core::future::Future<Output = Result<T>>,
+{
+    let mut maybe_resource = slot.write().await;
+    if let Some(resource) = maybe_resource
+        .as_ref()
+        .filter(|resource| !is_lost(resource))
+    {
+        return Ok(resource.clone());
+    }
+    if maybe_resource.is_some() {
+        tracing::warn!("Replacing the lost {name}");
+    }
+    let resource = request().await?;
+    *maybe_resource = Some(resource.clone());
+    drop(maybe_resource);
+    Ok(resource)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn lost_resources_are_replaced() {
+        let slot = RwLock::new(None);
+        let is_lost = |flag: &Arc<core::sync::atomic::AtomicBool>| {
+            flag.load(core::sync::atomic::Ordering::Relaxed)
+        };
+        let request = || async {
+            Ok::<_, color_eyre::eyre::Report>(Arc::new(core::sync::atomic::AtomicBool::new(false)))
+        };
+
+        let first = reuse_or_request("flag", &slot, is_lost, request)
+            .await
+            .unwrap();
+        let shared = reuse_or_request("flag", &slot, is_lost, request)
+            .await
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &shared));
+
+        first.store(true, core::sync::atomic::Ordering::Relaxed);
+        let replacement = reuse_or_request("flag", &slot, is_lost, request)
+            .await
+            .unwrap();
+        assert!(!Arc::ptr_eq(&first, &replacement));
+        assert!(!is_lost(&replacement));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a GPU"]
+    async fn lost_gpu_devices_are_replaced() {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1);
+        let state = SharedState::init(1, 1, protocol_tx).await.unwrap();
+        let device = state.get_gpu_device().await.unwrap();
+
+        let shared = state.get_gpu_device().await.unwrap();
+        assert!(Arc::ptr_eq(&device.device, &shared.device));
+
+        device
+            .is_lost
+            .store(true, core::sync::atomic::Ordering::Relaxed);
+        let replacement = state.get_gpu_device().await.unwrap();
+        assert!(!Arc::ptr_eq(&device.device, &replacement.device));
+        assert!(!replacement.is_lost());
+    }
 }
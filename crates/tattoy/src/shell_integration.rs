@@ -0,0 +1,120 @@
+//! Snippets for the user's shell rc file that tell Tattoy about what the shell is doing: its
+//! current directory and the commands it runs.
+//!
+//! Shells normally report these with escape sequences like OSC 7, but those are swallowed by the
+//! shadow terminal. So the snippets also write their reports to files that Tattoy tells the shell
+//! about with environment variables.
+
+/// The environment variable that tells the shell integration where to report its directory.
+pub(crate) const OSC7_FILE_ENV: &str = "TATTOY_OSC7_FILE";
+
+/// The environment variable that tells the shell integration where to report finished commands.
+pub(crate) const COMMANDS_FILE_ENV: &str = "TATTOY_COMMANDS_FILE";
+
+/// The shells that Tattoy has shell integration for.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Shell {
+    /// Bash
+    Bash,
+    /// Zsh
+    Zsh,
+    /// Fish
+    Fish,
+}
+
+impl Shell {
+    /// A snippet for the shell's rc file. It reports the current directory with OSC 7 before
+    /// every prompt, and the duration and exit status of every command once it finishes.
+    pub(crate) const fn integration(self) -> &'static str {
+        match self {
+            Self::Bash => {
+                r#"# Tattoy shell integration, add this to your ~/.bashrc
+__tattoy_preexec() {
+    [ -n "$__tattoy_is_at_prompt" ] || return
+    [ "$BASH_COMMAND" != "__tattoy_precmd" ] || return
+    unset __tattoy_is_at_prompt
+    __tattoy_command_start="${EPOCHREALTIME:-$SECONDS.000000}"
+    __tattoy_command="$BASH_COMMAND"
+}
+__tattoy_precmd() {
+    local exit_status=$?
+    printf '\e]7;file://%s%s\a' "$HOSTNAME" "$PWD"
+    if [ -n "$TATTOY_OSC7_FILE" ]; then
+        printf '\e]7;file://%s%s\a' "$HOSTNAME" "$PWD" >"$TATTOY_OSC7_FILE"
+    fi
+    if [ -n "$__tattoy_command_start" ] && [ -n "$TATTOY_COMMANDS_FILE" ]; then
+        local now="${EPOCHREALTIME:-$SECONDS.000000}"
+        local duration=$(( (10#${now/[.,]/} - 10#${__tattoy_command_start/[.,]/}) / 1000 ))
+        printf '%s\t%s\t%s\n' "$duration" "$exit_status" "${__tattoy_command//$'\n'/ }" \
+            >>"$TATTOY_COMMANDS_FILE"
+    fi
+    unset __tattoy_command_start
+    __tattoy_is_at_prompt=1
+}
+trap '__tattoy_preexec' DEBUG
+PROMPT_COMMAND="__tattoy_precmd${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+"#
+            }
+            Self::Zsh => {
+                r#"# Tattoy shell integration, add this to your ~/.zshrc
+zmodload zsh/datetime
+__tattoy_preexec() {
+    __tattoy_command_start=$EPOCHREALTIME
+    __tattoy_command=$1
+}
+__tattoy_precmd() {
+    local exit_status=$?
+    printf '\e]7;file://%s%s\a' "$HOST" "$PWD"
+    if [[ -n "$TATTOY_OSC7_FILE" ]]; then
+        printf '\e]7;file://%s%s\a' "$HOST" "$PWD" >"$TATTOY_OSC7_FILE"
+    fi
+    if [[ -n "$__tattoy_command_start" && -n "$TATTOY_COMMANDS_FILE" ]]; then
+        local -i duration=$(( (EPOCHREALTIME - __tattoy_command_start) * 1000 ))
+        printf '%s\t%s\t%s\n' "$duration" "$exit_status" "${__tattoy_command//$'\n'/ }" \
+            >>"$TATTOY_COMMANDS_FILE"
+    fi
+    unset __tattoy_command_start
+}
+autoload -Uz add-zsh-hook
+add-zsh-hook preexec __tattoy_preexec
+add-zsh-hook precmd __tattoy_precmd
+"#
+            }
+            Self::Fish => {
+                r#"# Tattoy shell integration, add this to your ~/.config/fish/config.fish
+function __tattoy_osc7 --on-event fish_prompt
+    printf '\e]7;file://%s%s\a' (hostname) "$PWD"
+    if set -q TATTOY_OSC7_FILE
+        printf '\e]7;file://%s%s\a' (hostname) "$PWD" >"$TATTOY_OSC7_FILE"
+    end
+end
+function __tattoy_postexec --on-event fish_postexec
+    set -l exit_status $status
+    if set -q TATTOY_COMMANDS_FILE; and test -n "$argv"
+        printf '%s\t%s\t%s\n' $CMD_DURATION $exit_status (string join ' ' -- (string split \n -- $argv)) \
+            >>"$TATTOY_COMMANDS_FILE"
+    end
+end
+"#
+            }
+        }
+    }
+}
+
+/// The file that the shell integration reports the directory to. It's unique to each Tattoy
+/// process.
+pub(crate) fn osc7_file() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("tattoy-{}.osc7", std::process::id()))
+}
+
+/// The file that the shell integration reports finished commands to. It's unique to each Tattoy
+/// process.
+pub(crate) fn commands_file() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("tattoy-{}.commands", std::process::id()))
+}
+
+/// Tell the shell integration where to report to. The environment is inherited by the PTY.
+pub(crate) fn set_environment() {
+    std::env::set_var(OSC7_FILE_ENV, osc7_file());
+    std::env::set_var(COMMANDS_FILE_ENV, commands_file());
+}
@@ -0,0 +1,248 @@
+//! Play sounds when things happen, like mechanical keyboard clicks for every keypress, or a chime
+//! when a program rings the bell.
+//!
+//! When Tattoy is built with the `sounds` feature it plays sounds itself. Otherwise they're played
+//! by the system's own command line player, `afplay` on macOS and `paplay` on Linux. Either way,
+//! the user can configure a player of their own. For anything more elaborate, like haptics, use a
+//! `keypress` hook.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for sounds.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable sounds.
+    pub enabled: bool,
+    /// The sound file to play for every keypress. Relative to Tattoy's config directory.
+    pub keypress: Option<std::path::PathBuf>,
+    /// The sound file to play for every BEL character that the PTY's command prints. Relative to
+    /// Tattoy's config directory.
+    pub bell: Option<std::path::PathBuf>,
+    /// The command that plays a sound file. The path to the file is added to the end. Defaults to
+    /// Tattoy's own player with the `sounds` feature, otherwise to the system's player.
+    pub player: Option<String>,
+    /// The most sounds that can be playing at once. Any more are skipped, so that fast typing
+    /// doesn't start hundreds of players.
+    pub max_playing: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keypress: None,
+            bell: None,
+            player: None,
+            max_playing: 4,
+        }
+    }
+}
+
+/// What plays the sound files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Player {
+    /// A command, and its arguments. The path to the file is added to the end.
+    Command(Vec<String>),
+    /// Tattoy itself.
+    #[cfg(feature = "sounds")]
+    BuiltIn,
+}
+
+impl Config {
+    /// What plays the sound files.
+    fn player(&self) -> Option<Player> {
+        let player = match &self.player {
+            Some(player) => player.clone(),
+            #[cfg(feature = "sounds")]
+            None => return Some(Player::BuiltIn),
+            #[cfg(not(feature = "sounds"))]
+            None if cfg!(target_os = "macos") => "afplay".to_owned(),
+            #[cfg(not(feature = "sounds"))]
+            None if cfg!(target_os = "linux") => "paplay".to_owned(),
+            #[cfg(not(feature = "sounds"))]
+            None => return None,
+        };
+        let words = player
+            .split_whitespace()
+            .map(str::to_owned)
+            .collect::<Vec<String>>();
+        (!words.is_empty()).then_some(Player::Command(words))
+    }
+
+    /// The sound file, if there is one, for a protocol message.
+    fn sound_for(&self, message: &crate::run::Protocol) -> Option<&std::path::Path> {
+        let sound = if is_keypress(message) {
+            &self.keypress
+        } else if matches!(message, crate::run::Protocol::BellCharacter) {
+            &self.bell
+        } else {
+            return None;
+        };
+        sound.as_deref()
+    }
+}
+
+/// Is the protocol message a keypress?
+fn is_keypress(message: &crate::run::Protocol) -> bool {
+    matches!(
+        message,
+        crate::run::Protocol::Input(crate::raw_input::ParsedInput {
+            event: shadow_terminal::termwiz::input::InputEvent::Key(_),
+            ..
+        })
+    )
+}
+
+/// `Sounds`
+pub(crate) struct Sounds;
+
+impl Sounds {
+    /// Start the task that listens for events that have sounds.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let mut protocol = state.protocol_tx.subscribe();
+            let mut playing = tokio::task::JoinSet::new();
+
+            loop {
+                let message = match protocol.recv().await {
+                    Ok(crate::run::Protocol::End)
+                    | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    Ok(message) => message,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Sounds lagged behind by {skipped} messages");
+                        continue;
+                    }
+                };
+
+                // Sounds that have already finished don't need to be kept around.
+                while playing.try_join_next().is_some() {}
+
+                let config = state.config.read().await.sounds.clone();
+                if !config.enabled || playing.len() >= config.max_playing {
+                    continue;
+                }
+                let Some(sound) = config.sound_for(&message) else {
+                    continue;
+                };
+                let Some(player) = config.player() else {
+                    tracing::warn!("No sound player for this system, set `player` in `[sounds]`");
+                    continue;
+                };
+
+                let path = state.config_path.read().await.join(sound);
+                playing.spawn(async move {
+                    if let Err(error) = Self::play(&player, &path).await {
+                        tracing::debug!("Couldn't play {path:?}: {error:?}");
+                    }
+                });
+            }
+
+            playing.abort_all();
+            tracing::debug!("Leaving sounds loop");
+            Ok(())
+        })
+    }
+
+    /// Play a sound file.
+    async fn play(player: &Player, path: &std::path::Path) -> Result<()> {
+        match player {
+            Player::Command(command) => Self::play_with_command(command, path).await,
+            #[cfg(feature = "sounds")]
+            Player::BuiltIn => {
+                let path = path.to_owned();
+                tokio::task::spawn_blocking(move || Self::play_built_in(&path)).await?
+            }
+        }
+    }
+
+    /// Play a sound file with Tattoy's own player. It blocks until the sound has finished.
+    #[cfg(feature = "sounds")]
+    fn play_built_in(path: &std::path::Path) -> Result<()> {
+        let (_stream, handle) = rodio::OutputStream::try_default()?;
+        let sink = rodio::Sink::try_new(&handle)?;
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        sink.append(rodio::Decoder::new(file)?);
+        sink.sleep_until_end();
+        Ok(())
+    }
+
+    /// Play a sound file with the player command.
+    async fn play_with_command(player: &[String], path: &std::path::Path) -> Result<()> {
+        let Some((program, arguments)) = player.split_first() else {
+            color_eyre::eyre::bail!("Empty sound player command");
+        };
+        let status = tokio::process::Command::new(program)
+            .args(arguments)
+            .arg(path)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .await?;
+        if !status.success() {
+            color_eyre::eyre::bail!("Sound player exited with: {status}");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn custom_players_are_split_into_arguments() {
+        let config = Config {
+            player: Some("mpv --really-quiet".to_owned()),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.player(),
+            Some(Player::Command(vec![
+                "mpv".to_owned(),
+                "--really-quiet".to_owned()
+            ]))
+        );
+
+        let config = Config {
+            player: Some("  ".to_owned()),
+            ..Config::default()
+        };
+        assert!(config.player().is_none());
+    }
+
+    #[test]
+    fn only_keys_are_keypresses() {
+        let keypress = crate::run::Protocol::Input(crate::raw_input::ParsedInput {
+            bytes: b"a".to_vec(),
+            event: shadow_terminal::termwiz::input::InputEvent::Key(
+                shadow_terminal::termwiz::input::KeyEvent {
+                    key: shadow_terminal::termwiz::input::KeyCode::Char('a'),
+                    modifiers: shadow_terminal::termwiz::input::Modifiers::NONE,
+                },
+            ),
+        });
+        assert!(is_keypress(&keypress));
+        assert!(!is_keypress(&crate::run::Protocol::Repaint));
+    }
+
+    #[test]
+    fn bells_have_their_own_sound() {
+        let config = Config {
+            keypress: Some("click.wav".into()),
+            bell: Some("chime.wav".into()),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.sound_for(&crate::run::Protocol::BellCharacter),
+            Some(std::path::Path::new("chime.wav"))
+        );
+        assert!(config.sound_for(&crate::run::Protocol::Bell).is_none());
+        assert!(Config::default()
+            .sound_for(&crate::run::Protocol::BellCharacter)
+            .is_none());
+    }
+}
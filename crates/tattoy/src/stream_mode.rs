@@ -0,0 +1,142 @@
+//! A profile for live-streaming and screen recording, switched on and off with a single
+//! keybinding before going live. It's applied on top of the config, and the current scene, so that
+//! it always wins:
+//!
+//! * Tattoys that can show private information, like the git branch or the weather in your town,
+//!   are turned off.
+//! * Secrets are redacted.
+//! * Every tattoy renders at the same, fixed, frame rate, so that the stream's encoder doesn't
+//!   have to cope with bursts of frames.
+//! * An optional watermark, like your channel's name, is shown in a corner.
+
+use color_eyre::eyre::Result;
+
+/// User-configurable settings for stream mode.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// The tattoys to turn off, because they can show private information.
+    pub disabled_tattoys: Vec<String>,
+    /// Whether to turn on the redaction of secrets.
+    pub redact: bool,
+    /// The frame rate that every tattoy renders at. `0` leaves the frame rates as they are.
+    pub frame_rate: u32,
+    /// The text of the watermark. No watermark is shown when it's empty.
+    pub watermark: String,
+    /// Which corner the watermark is shown in.
+    pub watermark_position: crate::utils::Corner,
+    /// The opacity of the watermark.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub watermark_opacity: f32,
+    /// The colour of the watermark's text.
+    pub watermark_colour: crate::surface::Colour,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            disabled_tattoys: [
+                "notifications",
+                "git_watermark",
+                "status_line",
+                "weather",
+                "weather_widget",
+                "command_durations",
+                "bg_command",
+            ]
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect(),
+            redact: true,
+            frame_rate: 30,
+            watermark: String::new(),
+            watermark_position: crate::utils::Corner::BottomRight,
+            watermark_opacity: 0.4,
+            watermark_colour: crate::surface::WHITE,
+        }
+    }
+}
+
+/// Apply stream mode to the config.
+pub(crate) fn apply(config: &mut crate::config::main::Config) {
+    let stream_mode = config.stream_mode.clone();
+    for id in &stream_mode.disabled_tattoys {
+        match config.tattoy_enabled_mut(id) {
+            Some(enabled) => *enabled = false,
+            None => tracing::warn!("Stream mode can't turn off the `{id}` tattoy"),
+        }
+    }
+    if stream_mode.redact {
+        config.redaction.enabled = true;
+    }
+    if stream_mode.frame_rate > 0 {
+        config.frame_rate = stream_mode.frame_rate;
+        config.shader.frame_rate = None;
+        for shader in &mut config.shaders {
+            shader.frame_rate = None;
+        }
+    }
+}
+
+/// The config sections that stream mode turns on. Their tattoys need to be started even if the
+/// main config doesn't enable them, so that they can be switched on.
+pub(crate) fn sections_enabled(config: &Config) -> Vec<String> {
+    if config.redact {
+        vec!["redaction".to_owned()]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Switch stream mode on or off.
+pub(crate) async fn toggle(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Result<()> {
+    let mut is_stream_mode = state.is_stream_mode.write().await;
+    *is_stream_mode = !*is_stream_mode;
+    tracing::info!("Stream mode is now: {}", *is_stream_mode);
+    drop(is_stream_mode);
+    crate::config::main::Config::reload(state).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stream_mode_hides_private_tattoys() {
+        let mut config: crate::config::main::Config = toml::from_str(
+            "
+                frame_rate = 60
+                [git_watermark]
+                enabled = true
+                [minimap]
+                enabled = true
+                [shader]
+                frame_rate = 120
+            ",
+        )
+        .unwrap();
+        apply(&mut config);
+        assert!(!config.git_watermark.enabled);
+        assert!(config.minimap.enabled);
+        assert!(config.redaction.enabled);
+        assert_eq!(config.frame_rate, 30);
+        assert_eq!(config.shader.frame_rate, None);
+    }
+
+    #[test]
+    fn frame_rates_can_be_left_alone() {
+        let mut config: crate::config::main::Config = toml::from_str(
+            "
+                frame_rate = 60
+                [stream_mode]
+                frame_rate = 0
+                redact = false
+            ",
+        )
+        .unwrap();
+        apply(&mut config);
+        assert_eq!(config.frame_rate, 60);
+        assert!(!config.redaction.enabled);
+        assert!(sections_enabled(&config.stream_mode).is_empty());
+    }
+}
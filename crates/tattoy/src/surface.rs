@@ -53,6 +53,38 @@ impl Surface {
         }
     }
 
+    /// Blank every cell, reusing the surface's existing buffers rather than allocating new ones.
+    pub fn clear(&mut self) {
+        self.surface.add_change(TermwizChange::ClearScreen(
+            termwiz::color::ColorAttribute::Default,
+        ));
+        // Cleared surfaces are only ever used for new frames, so the change log, which otherwise
+        // grows with every change, isn't needed.
+        let seqno = self.surface.current_seqno();
+        self.surface.flush_changes_older_than(seqno);
+    }
+
+    /// Blank every cell within the margins.
+    pub fn clear_margins(&mut self, margins: &crate::config::main::Margins) {
+        if *margins == crate::config::main::Margins::default() {
+            return;
+        }
+
+        let (width, height) = self.surface.dimensions();
+        let top = usize::from(margins.top);
+        let bottom = height.saturating_sub(usize::from(margins.bottom));
+        let left = usize::from(margins.left);
+        let right = width.saturating_sub(usize::from(margins.right));
+        for (y, line) in self.surface.screen_cells().iter_mut().enumerate() {
+            let is_margin_row = y < top || y >= bottom;
+            for (x, cell) in line.iter_mut().enumerate() {
+                if is_margin_row || x < left || x >= right {
+                    *cell = termwiz::cell::Cell::default();
+                }
+            }
+        }
+    }
+
     /// Add a pixel ("▀", "▄") to a tattoy surface.
     ///
     /// The rule is that we default to rendering any pair of colours using the upper half block.
@@ -64,16 +96,60 @@ impl Surface {
     /// *whilst retaining the ANSI-coded default background colour*.
     pub fn add_pixel(&mut self, x: usize, y: usize, colour: Colour) -> Result<()> {
         let (col, row) = self.coords_to_tty(x, y)?;
-        let colour_attribute = Self::make_colour_attribute(colour);
-
         let mut cells = self.surface.screen_cells();
         let cell = cells
             .get_mut(row)
             .context("No cell row")?
             .get_mut(col)
             .context("No cell column")?;
-        let is_empty_upper = cell.str() != "▀";
+        Self::paint_half_block(cell, y.rem_euclid(2) == 0, colour);
+
+        Ok(())
+    }
+
+    /// Add a row of pixels, starting at the pixel coords `x`, `y`. It's the same as calling
+    /// `add_pixel()` for every pixel, but the cells are only looked up once for the whole row.
+    /// Pixels beyond the edge of the surface are clipped.
+    pub fn add_pixel_row(&mut self, x: usize, y: usize, colours: impl IntoIterator<Item = Colour>) {
+        let mut cells = self.surface.screen_cells();
+        let Some(line) = cells.get_mut(y.div_euclid(2)) else {
+            return;
+        };
         let is_upper_half = y.rem_euclid(2) == 0;
+        for (cell, colour) in line.iter_mut().skip(x).zip(colours) {
+            Self::paint_half_block(cell, is_upper_half, colour);
+        }
+    }
+
+    /// Copy an image onto the surface, with its top-left corner at the pixel coords `x`, `y`.
+    /// Pixels beyond the edge of the surface are clipped.
+    pub fn blit_image(&mut self, x: usize, y: usize, image: &image::RgbaImage) {
+        let mut cells = self.surface.screen_cells();
+        for (pixel_y, row) in (y..).zip(image.rows()) {
+            let Some(line) = cells.get_mut(pixel_y.div_euclid(2)) else {
+                break;
+            };
+            let is_upper_half = pixel_y.rem_euclid(2) == 0;
+            for (cell, pixel) in line.iter_mut().skip(x).zip(row) {
+                let colour = crate::pixels::to_unit_rgba(&pixel.0).into();
+                Self::paint_half_block(cell, is_upper_half, colour);
+            }
+        }
+    }
+
+    /// Set a cell's glyph and colours, from a pixel encoder.
+    pub fn add_glyph(&mut self, x: usize, y: usize, glyph: crate::pixel_encoders::Glyph) {
+        let mut cells = self.surface.screen_cells();
+        let Some(cell) = cells.get_mut(y).and_then(|line| line.get_mut(x)) else {
+            return;
+        };
+        *cell = glyph.to_cell(termwiz::cell::CellAttributes::default());
+    }
+
+    /// Paint the upper or lower half of a cell, following the half block rules of `add_pixel()`.
+    fn paint_half_block(cell: &mut termwiz::cell::Cell, is_upper_half: bool, colour: Colour) {
+        let colour_attribute = Self::make_colour_attribute(colour);
+        let is_empty_upper = cell.str() != "▀";
         let is_lower_half = !is_upper_half;
         let is_adding_to_bottom_of_empty_upper = is_empty_upper && is_lower_half;
 
@@ -117,8 +193,6 @@ impl Surface {
         }
 
         *cell = scratch;
-
-        Ok(())
     }
 
     /// Overlay text at a given coord with the given colours.
@@ -194,6 +268,30 @@ impl Surface {
     }
 }
 
+/// Surfaces that the renderer has finished with, kept so that tattoys can build their next frames
+/// in them rather than allocating new ones. Each tattoy only ever needs one spare surface.
+#[derive(Default)]
+pub(crate) struct Pool {
+    /// The spare surface of each tattoy, by the tattoy's ID.
+    spares: std::collections::BTreeMap<String, Surface>,
+}
+
+impl Pool {
+    /// Keep a surface for its tattoy to reuse.
+    pub fn recycle(&mut self, surface: Surface) {
+        if surface.width == 0 || surface.height == 0 {
+            return;
+        }
+        self.spares.insert(surface.id.clone(), surface);
+    }
+
+    /// Take a tattoy's spare surface, as long as it's the right size.
+    pub fn take(&mut self, id: &str, width: usize, height: usize) -> Option<Surface> {
+        let spare = self.spares.remove(id)?;
+        (spare.width == width && spare.height == height).then_some(spare)
+    }
+}
+
 #[cfg(test)]
 #[expect(
     clippy::indexing_slicing,
@@ -252,6 +350,27 @@ mod test {
         );
     }
 
+    #[test]
+    fn margins_are_cleared() {
+        let mut surface = Surface::new("test".into(), 4, 4, -1, 1.0);
+        for y in 0..8 {
+            surface.add_pixel_row(0, y, [WHITE; 4]);
+        }
+        surface.clear_margins(&crate::config::main::Margins {
+            top: 1,
+            right: 2,
+            ..crate::config::main::Margins::default()
+        });
+
+        let cells = surface.surface.screen_cells();
+        assert_eq!(cells[0][0].str(), " ");
+        assert_eq!(cells[1][0].str(), "▀");
+        assert_eq!(cells[1][1].str(), "▀");
+        assert_eq!(cells[1][2].str(), " ");
+        assert_eq!(cells[3][3].str(), " ");
+        assert_eq!(cells[3][1].str(), "▀");
+    }
+
     #[test]
     fn add_pixel_at_bottom_of_empty_cell() {
         let mut surface = Surface::new("test".into(), 1, 1, -1, 1.0);
@@ -327,4 +446,66 @@ mod test {
         assert_eq!(first_cell.attrs().foreground(), fg);
         assert_eq!(first_cell.attrs().background(), bg);
     }
+
+    #[test]
+    fn clearing_blanks_every_cell() {
+        let mut surface = Surface::new("test".into(), 2, 2, -1, 1.0);
+        surface.add_pixel(0, 0, GREY).unwrap();
+        surface.add_text(1, 1, "x".into(), None, None);
+        surface.clear();
+
+        let blank = termwiz::surface::Surface::new(2, 2);
+        assert_eq!(surface.surface.screen_cells(), blank.screen_cells());
+    }
+
+    #[test]
+    fn pooled_surfaces_must_be_the_right_size() {
+        let mut pool = Pool::default();
+        pool.recycle(Surface::new("test".into(), 2, 2, -1, 1.0));
+        assert!(pool.take("other", 2, 2).is_none());
+        assert!(pool.take("test", 2, 2).is_some());
+        assert!(pool.take("test", 2, 2).is_none());
+
+        pool.recycle(Surface::new("test".into(), 2, 2, -1, 1.0));
+        assert!(pool.take("test", 3, 2).is_none());
+
+        pool.recycle(Surface::new("test".into(), 0, 0, -1, 1.0));
+        assert!(pool.take("test", 0, 0).is_none());
+    }
+
+    #[test]
+    fn pixel_rows_are_the_same_as_single_pixels() {
+        let colours = [RED, GREY, WHITE];
+        let mut single = Surface::new("test".into(), 3, 2, -1, 1.0);
+        let mut rows = Surface::new("test".into(), 3, 2, -1, 1.0);
+        for y in [1, 0, 2] {
+            for (x, colour) in colours.iter().enumerate() {
+                single.add_pixel(x, y, *colour).unwrap();
+            }
+            rows.add_pixel_row(0, y, colours);
+        }
+        assert_eq!(single.surface.screen_cells(), rows.surface.screen_cells());
+    }
+
+    #[test]
+    fn off_surface_pixels_are_clipped() {
+        let mut surface = Surface::new("test".into(), 2, 1, -1, 1.0);
+        surface.add_pixel_row(1, 0, [RED, RED, RED]);
+        surface.add_pixel_row(0, 2, [RED]);
+
+        let image = image::RgbaImage::from_pixel(3, 3, [0, 255, 0, 255].into());
+        surface.blit_image(1, 1, &image);
+
+        let cells = surface.surface.screen_cells();
+        assert_eq!(cells[0][0].str(), " ");
+        assert_eq!(cells[0][1].str(), "▀");
+        assert_eq!(
+            cells[0][1].attrs().foreground(),
+            Surface::make_colour_attribute(RED)
+        );
+        assert_eq!(
+            cells[0][1].attrs().background(),
+            Surface::make_colour_attribute((0.0, 1.0, 0.0, 1.0))
+        );
+    }
 }
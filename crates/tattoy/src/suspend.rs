@@ -0,0 +1,85 @@
+//! Suspending Tattoy with `SIGTSTP`, like when it's started from a shell whose job control sends
+//! it, or with `kill -TSTP`, and resuming it with `SIGCONT`, eg: from `fg`.
+//!
+//! Tattoy keeps the user's terminal in raw mode, with some extra modes like focus reporting, so
+//! simply stopping would leave the shell that it was started from unusable. Instead, the renderer
+//! first gives the terminal back in its normal state, and only then stops Tattoy. On resuming,
+//! the renderer sets its modes again and repaints everything, as the screen will have been used
+//! by something else in the meantime.
+
+use color_eyre::eyre::Result;
+
+/// Listens for the suspend and resume signals.
+pub(crate) struct Suspend;
+
+impl Suspend {
+    /// Start the task that turns signals into protocol messages.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            #[cfg(unix)]
+            Self::listen(&state).await?;
+            #[cfg(not(unix))]
+            drop(state);
+
+            tracing::debug!("Leaving suspend signal loop");
+            Ok(())
+        })
+    }
+
+    /// Send `Protocol::Suspend` and `Protocol::Resume` for every suspend and resume signal.
+    #[cfg(unix)]
+    async fn listen(state: &std::sync::Arc<crate::shared_state::SharedState>) -> Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut suspends = signal(SignalKind::from_raw(libc::SIGTSTP))?;
+        let mut resumes = signal(SignalKind::from_raw(libc::SIGCONT))?;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                Some(()) = suspends.recv() => {
+                    tracing::debug!("Suspending");
+                    state.protocol_tx.send(crate::run::Protocol::Suspend)?;
+                },
+                Some(()) = resumes.recv() => {
+                    tracing::debug!("Resuming");
+                    state.protocol_tx.send(crate::run::Protocol::Resume)?;
+                },
+                message = protocol.recv() => match message {
+                    Ok(crate::run::Protocol::End)
+                    | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    Ok(_) => (),
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Suspend signal loop lagged behind by {skipped} messages");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Actually stop Tattoy. This only returns once Tattoy has been resumed.
+    ///
+    /// Catching `SIGTSTP` replaces its default of stopping the process, so Tattoy has to stop
+    /// itself with the uncatchable `SIGSTOP`. It's sent with `kill` rather than adding `unsafe`.
+    pub(crate) fn stop() -> Result<()> {
+        #[cfg(unix)]
+        {
+            let status = std::process::Command::new("kill")
+                .arg("-STOP")
+                .arg(std::process::id().to_string())
+                .status()?;
+            if !status.success() {
+                color_eyre::eyre::bail!("Couldn't stop Tattoy: {status}");
+            }
+        }
+        Ok(())
+    }
+}
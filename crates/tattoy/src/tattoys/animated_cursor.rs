@@ -11,18 +11,87 @@ pub const CURSOR_DIMENSIONS_REAL: (f32, f32) = (1.0, 2.0);
 /// foreground and background of the PTY layer.
 const LAYER: i16 = i16::MIN;
 
+/// How the text of the cursor's cell is coloured, so that the user's real cursor stays visible
+/// over bright, animated shaders. Most terminals draw their cursor in the colour of the text
+/// beneath it.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ShaderSampling {
+    /// Leave the cursor's cell alone.
+    #[default]
+    Off,
+    /// A strong tint of the shader's colour beneath the cursor: dark over bright shaders and light
+    /// over dark ones.
+    Tint,
+    /// The inverse of the shader's colour beneath the cursor.
+    Invert,
+}
+
+impl ShaderSampling {
+    /// How much of the way to black or white a tint goes.
+    const TINT_STRENGTH: f32 = 0.75;
+
+    /// The colour for the text of the cursor's cell, given the average colour of the shader
+    /// beneath it.
+    pub fn colour_for(
+        self,
+        sample: shadow_terminal::termwiz::color::SrgbaTuple,
+    ) -> Option<shadow_terminal::termwiz::color::SrgbaTuple> {
+        let shadow_terminal::termwiz::color::SrgbaTuple(red, green, blue, _) = sample;
+        let colour = match self {
+            Self::Off => return None,
+            Self::Tint => {
+                let luminance = 0.0722f32.mul_add(blue, 0.2126f32.mul_add(red, 0.7152 * green));
+                let target = if luminance > 0.5 { 0.0 } else { 1.0 };
+                let tint = |channel: f32| (target - channel).mul_add(Self::TINT_STRENGTH, channel);
+                shadow_terminal::termwiz::color::SrgbaTuple(tint(red), tint(green), tint(blue), 1.0)
+            }
+            Self::Invert => {
+                shadow_terminal::termwiz::color::SrgbaTuple(1.0 - red, 1.0 - green, 1.0 - blue, 1.0)
+            }
+        };
+        Some(colour)
+    }
+}
+
 /// All the user config for the shader tattoy.
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
 #[serde(default)]
 pub(crate) struct Config {
     /// Enable/disable the shaders on and off
     pub enabled: bool,
     /// The path to a given GLSL shader file.
     pub path: std::path::PathBuf,
+    /// A built-in cursor animation to use instead of the shader at `path`.
+    pub preset: Option<super::cursor_presets::Preset>,
+    /// The colour of the preset, like `"#ff8800"`. Defaults to the colour of the cursor.
+    #[schemars(with = "Option<String>")]
+    pub preset_colour: Option<super::cursor_presets::HexColour>,
+    /// The size of the preset, in cells.
+    pub preset_size: f32,
     /// The opacity of the rendered shader layer.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub opacity: f32,
     /// The scale of the cursor.
     pub cursor_scale: f32,
+    /// Colour the text of the cursor's cell from the `[shader]` pixels beneath it.
+    pub sample_shader: ShaderSampling,
+    /// How long, in seconds, the `iInterpolatedCursor` shader variable takes to move from one
+    /// cell to the next. `0.0` means it jumps just like the real cursor.
+    pub interpolation_duration: f32,
+    /// How `iInterpolatedCursor` speeds up and slows down as it moves.
+    pub interpolation_easing: crate::cursor_history::Easing,
+    /// How long, in seconds, to crossfade from the old cursor shader when switching to a new one.
+    pub transition_duration: f32,
+    /// Switch to another shader from `rotate_directory` this often, like `"15m"`. When it isn't
+    /// set the shader only changes when the user changes it.
+    pub rotate_every: Option<String>,
+    /// Whether shaders are rotated through in a random or alphabetical order.
+    pub rotate_order: super::gpu::rotation::Order,
+    /// The directory of shaders to rotate through. Defaults to the directory of `path`.
+    pub rotate_directory: Option<std::path::PathBuf>,
+    /// Globs of shader filenames that are never rotated to, like `wip_*`.
+    pub rotate_exclude: Vec<String>,
 }
 
 impl Default for Config {
@@ -35,9 +104,45 @@ impl Default for Config {
                 crate::config::main::DEFAULT_CURSOR_SHADER_FILENAME
             )
             .into(),
+            preset: None,
+            preset_colour: None,
+            preset_size: 1.0,
             opacity: 0.75,
             cursor_scale: 1.0,
+            sample_shader: ShaderSampling::default(),
+            interpolation_duration: 0.1,
+            interpolation_easing: crate::cursor_history::Easing::default(),
+            transition_duration: 0.5,
+            rotate_every: None,
+            rotate_order: super::gpu::rotation::Order::default(),
+            rotate_directory: None,
+            rotate_exclude: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// The defines that set the preset's colour and size.
+    fn preset_defines(&self) -> Vec<(String, String)> {
+        super::cursor_presets::defines(self.preset_colour, self.preset_size)
+    }
+
+    /// How the shader is rotated, if it is. Presets aren't rotated.
+    pub fn rotation_settings(
+        &self,
+        config_directory: &std::path::Path,
+    ) -> Result<Option<super::gpu::rotation::Settings>> {
+        if self.preset.is_some() {
+            return Ok(None);
         }
+        super::gpu::rotation::Settings::from_config(
+            config_directory,
+            self.rotate_every.as_deref(),
+            self.rotate_order,
+            self.rotate_directory.as_deref(),
+            &self.path,
+            &self.rotate_exclude,
+        )
     }
 }
 
@@ -50,6 +155,8 @@ pub(crate) struct AnimatedCursor {
     /// A hash of the last GPU render, so we can decide whether it's worth applying the render to
     /// the user's terminal as well.
     hashed_render: super::gpu::shaderer::HashedRender,
+    /// Rotating through the cursor shaders in a directory.
+    rotation: Option<super::gpu::rotation::Rotation>,
 }
 
 impl crate::tattoys::gpu::shaderer::Shaderer for AnimatedCursor {
@@ -77,6 +184,10 @@ impl crate::tattoys::gpu::shaderer::Shaderer for AnimatedCursor {
         false
     }
 
+    fn is_cyclable(&self) -> bool {
+        self.gpu.builtin_source().is_none()
+    }
+
     fn is_should_hash_render(&self) -> bool {
         true
     }
@@ -109,23 +220,46 @@ impl crate::tattoys::gpu::shaderer::Shaderer for AnimatedCursor {
     async fn new(
         output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: std::sync::Arc<crate::shared_state::SharedState>,
+        _index: usize,
     ) -> Result<Self> {
         let config_directory = state.config_path.read().await.clone();
-        let shader_path = state.config.read().await.animated_cursor.path.clone();
+        let config = state.config.read().await.animated_cursor.clone();
         let tty_size = *state.tty_size.read().await;
-        let gpu = super::gpu::pipeline::GPU::new(
-            config_directory.join(shader_path),
-            tty_size.width,
-            tty_size.height * 2,
-            state.protocol_tx.clone(),
-        )
-        .await?;
-        let opacity = state.config.read().await.animated_cursor.opacity;
+        let device = state.get_gpu_device().await?;
+        let mut gpu = match config.preset {
+            Some(preset) => {
+                super::gpu::pipeline::GPU::new_builtin(
+                    preset.source(),
+                    config.preset_defines(),
+                    tty_size.width,
+                    tty_size.height * 2,
+                    state.protocol_tx.clone(),
+                    device,
+                )
+                .await?
+            }
+            None => {
+                super::gpu::pipeline::GPU::new(
+                    config_directory.join(&config.path),
+                    tty_size.width,
+                    tty_size.height * 2,
+                    state.protocol_tx.clone(),
+                    device,
+                )
+                .await?
+            }
+        };
+        gpu.set_pixel_aspect(state.config.read().await.pixel_aspect);
+        gpu.transition_duration = config.transition_duration;
+        let rotation =
+            super::gpu::rotation::or_report(&state, config.rotation_settings(&config_directory))
+                .await
+                .map(super::gpu::rotation::Rotation::new);
         let tattoy = Tattoyer::new(
             "animated_cursor".to_owned(),
             state,
             LAYER,
-            opacity,
+            config.opacity,
             output_channel,
         )
         .await;
@@ -134,9 +268,28 @@ impl crate::tattoys::gpu::shaderer::Shaderer for AnimatedCursor {
             tattoy,
             gpu,
             hashed_render: super::gpu::shaderer::HashedRender::NeedsRendering,
+            rotation,
         })
     }
 
+    async fn handle_config_update(&mut self, config: &crate::config::main::Config) -> Result<()> {
+        self.gpu.transition_duration = config.animated_cursor.transition_duration;
+        let config_directory = self.tattoy.state.config_path.read().await.clone();
+        self.update_preset(&config.animated_cursor, &config_directory)
+            .await?;
+        let settings = super::gpu::rotation::or_report(
+            &self.tattoy.state,
+            config.animated_cursor.rotation_settings(&config_directory),
+        )
+        .await;
+        self.rotation = super::gpu::rotation::Rotation::update(self.rotation.take(), settings);
+        Ok(())
+    }
+
+    fn rotation_mut(&mut self) -> Option<&mut super::gpu::rotation::Rotation> {
+        self.rotation.as_mut()
+    }
+
     async fn render_handler(&mut self) -> Result<()> {
         if matches!(
             self.hashed_render,
@@ -164,3 +317,76 @@ impl crate::tattoys::gpu::shaderer::Shaderer for AnimatedCursor {
         }
     }
 }
+
+impl AnimatedCursor {
+    /// Switch to, from or between presets when the config changes.
+    async fn update_preset(
+        &mut self,
+        config: &Config,
+        config_directory: &std::path::Path,
+    ) -> Result<()> {
+        match config.preset {
+            Some(preset) => {
+                let defines = config.preset_defines();
+                let is_changed = self.gpu.builtin_source() != Some(preset.source())
+                    || self.gpu.defines != defines;
+                if !is_changed {
+                    return Ok(());
+                }
+                tracing::info!("Switching to the {preset:?} cursor preset");
+                self.gpu
+                    .switch_builtin_shader(preset.source(), defines)
+                    .await?;
+            }
+            None => {
+                if self.gpu.builtin_source().is_none() {
+                    return Ok(());
+                }
+                self.gpu.defines = Vec::new();
+                self.gpu
+                    .switch_shader(config_directory.join(&config.path))
+                    .await?;
+            }
+        }
+
+        self.hashed_render = super::gpu::shaderer::HashedRender::NeedsRendering;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use shadow_terminal::termwiz::color::SrgbaTuple;
+
+    #[test]
+    fn tints_contrast_with_the_shader() {
+        let SrgbaTuple(red, green, blue, alpha) = ShaderSampling::Tint
+            .colour_for(SrgbaTuple(1.0, 1.0, 0.8, 1.0))
+            .unwrap();
+        assert!(red < 0.3 && green < 0.3 && blue < 0.3);
+        assert!((alpha - 1.0).abs() < f32::EPSILON);
+
+        let SrgbaTuple(red, green, blue, _) = ShaderSampling::Tint
+            .colour_for(SrgbaTuple(0.0, 0.0, 0.4, 1.0))
+            .unwrap();
+        assert!(red > 0.7 && green > 0.7 && blue > 0.7);
+    }
+
+    #[test]
+    fn inverting_flips_every_channel() {
+        let SrgbaTuple(red, green, blue, _) = ShaderSampling::Invert
+            .colour_for(SrgbaTuple(1.0, 0.25, 0.0, 0.5))
+            .unwrap();
+        assert!(red.abs() < f32::EPSILON);
+        assert!((green - 0.75).abs() < f32::EPSILON);
+        assert!((blue - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn sampling_can_be_off() {
+        assert!(ShaderSampling::Off
+            .colour_for(SrgbaTuple(1.0, 1.0, 1.0, 1.0))
+            .is_none());
+    }
+}
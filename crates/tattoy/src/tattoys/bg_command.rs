@@ -5,19 +5,27 @@ use std::sync::Arc;
 use color_eyre::eyre::{ContextCompat as _, Result};
 
 /// User-configurable settings for the background command.
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
 #[serde(default)]
 pub(crate) struct Config {
     /// Enable/disable the script
     pub enabled: bool,
     /// The transparency of the command output layer
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub opacity: f32,
     /// The layer of the compositor on which the command output is rendered.
+    #[schemars(range(min = crate::layers::MIN_LAYER, max = crate::layers::MAX_LAYER))]
     pub layer: i16,
     /// The command to run.
     command: Vec<String>,
     /// Whether the command is expected to exit or not.
     expect_exit: bool,
+    /// Only render the command output into cells that don't have a background colour.
+    pub under_text_only: bool,
+    /// Only render the command output into cells that don't have any text at all.
+    pub only_in_blank_cells: bool,
+    /// Linearly change the command output's opacity across the rows, or columns.
+    pub opacity_gradient: Option<crate::opacity_gradient::Gradient>,
 }
 
 impl Default for Config {
@@ -28,6 +36,9 @@ impl Default for Config {
             layer: -8,
             command: vec!["echo".to_owned(), "No command provided".to_owned()],
             expect_exit: false,
+            under_text_only: false,
+            only_in_blank_cells: false,
+            opacity_gradient: None,
         }
     }
 }
@@ -51,7 +62,7 @@ impl BGCommand {
         state: &std::sync::Arc<crate::shared_state::SharedState>,
         palette: crate::palette::converter::Palette,
     ) -> Self {
-        let tattoy = super::tattoyer::Tattoyer::new(
+        let mut tattoy = super::tattoyer::Tattoyer::new(
             "bg_command".to_owned(),
             Arc::clone(state),
             state.config.read().await.bg_command.layer,
@@ -59,6 +70,7 @@ impl BGCommand {
             output_channel,
         )
         .await;
+        tattoy.set_placement(crate::layers::Placement::NotText);
 
         let command = state.config.read().await.bg_command.command.clone();
         let _span = tracing::span!(tracing::Level::TRACE, "BGCommand").entered();
@@ -132,7 +144,8 @@ impl BGCommand {
     ) -> Result<()> {
         self.palette.convert_cells_to_true_colour(&mut output);
         self.tattoy.opacity = self.tattoy.state.config.read().await.bg_command.opacity;
-        self.tattoy.layer = self.tattoy.state.config.read().await.bg_command.layer;
+        let layer = self.tattoy.state.config.read().await.bg_command.layer;
+        self.tattoy.set_layer(layer);
 
         #[expect(
             clippy::collapsible_match,
@@ -159,7 +172,7 @@ impl BGCommand {
             _ => (),
         }
 
-        self.tattoy.send_output().await?;
+        self.tattoy.send_output_and_keep().await?;
 
         Ok(())
     }
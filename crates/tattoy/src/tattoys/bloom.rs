@@ -0,0 +1,226 @@
+//! Make bright text glow. Cells with a high-luminance foreground colour are blurred into a soft
+//! glow that's rendered just beneath the terminal's text.
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+
+/// The layer of the glow. It's directly beneath the text, so that it's above any other
+/// background tattoys.
+const LAYER: i16 = crate::layers::Group::Background.layer(0);
+
+/// Glow pixels fainter than this aren't rendered.
+const MINIMUM_ALPHA: f32 = 0.01;
+
+/// User-configurable settings for the bloom effect.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the bloom effect.
+    pub enabled: bool,
+    /// The opacity of the glow.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// How bright a cell's foreground colour has to be for it to glow, from `0.0` to `1.0`.
+    pub luminance_threshold: f32,
+    /// Whether bold text should always glow, regardless of its brightness.
+    pub include_bold: bool,
+    /// The size of the glow, in units of "pixels". Remember that there are 2 pixels per cell.
+    pub radius: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.6,
+            luminance_threshold: 0.7,
+            include_bold: true,
+            radius: 1.5,
+        }
+    }
+}
+
+/// `Bloom`
+pub(crate) struct Bloom {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// Whether the screen has changed since the last render.
+    is_dirty: bool,
+}
+
+impl Bloom {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.bloom.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "bloom".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_below_text();
+        Self {
+            tattoy,
+            is_dirty: true,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut bloom = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = bloom.tattoy.sleep_until_next_frame_tick() => {
+                    if bloom.is_dirty && !bloom.tattoy.is_paused_for_output_burst().await {
+                        bloom.render().await?;
+                    }
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if super::tattoyer::Tattoyer::is_screen_output_changed(&message)
+                        || matches!(message, crate::run::Protocol::Config(_))
+                    {
+                        bloom.is_dirty = true;
+                    }
+                    bloom.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.is_dirty = false;
+        let config = self.tattoy.state.config.read().await.bloom.clone();
+        self.tattoy.opacity = config.opacity;
+
+        let cells = self.tattoy.screen.surface.get_screen_cells();
+        let seeds = seed_image(&cells, &config);
+        drop(cells);
+        let radius = config.radius.max(0.1);
+        let glow = self
+            .tattoy
+            .compute(move || image::imageops::blur(&seeds, radius))
+            .await?;
+
+        self.tattoy.initialise_surface();
+        for (x, y, pixel) in glow.enumerate_pixels() {
+            let [red, green, blue, alpha] = pixel.0;
+            if alpha < MINIMUM_ALPHA {
+                continue;
+            }
+
+            // The seeds are premultiplied by their alpha, so the blurred colour needs to be
+            // divided by its alpha to get back to the original brightness.
+            let colour = (
+                (red / alpha).min(1.0),
+                (green / alpha).min(1.0),
+                (blue / alpha).min(1.0),
+                alpha.min(1.0),
+            );
+            self.tattoy
+                .surface
+                .add_pixel(usize::try_from(x)?, usize::try_from(y)?, colour)?;
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+/// Make an image, at pixel resolution, where only the pixels of glowing cells are coloured.
+fn seed_image(
+    cells: &[&[termwiz::cell::Cell]],
+    config: &Config,
+) -> image::ImageBuffer<image::Rgba<f32>, Vec<f32>> {
+    let pixels_per_line = 2;
+    let width = cells.first().map_or(0, |line| line.len());
+    let height = cells.len() * pixels_per_line;
+
+    image::ImageBuffer::from_fn(
+        u32::try_from(width).unwrap_or_default(),
+        u32::try_from(height).unwrap_or_default(),
+        |x, y| {
+            let maybe_cell = usize::try_from(x)
+                .ok()
+                .zip(usize::try_from(y).ok())
+                .and_then(|(cell_x, pixel_y)| {
+                    cells
+                        .get(pixel_y.div_euclid(pixels_per_line))
+                        .and_then(|line| line.get(cell_x))
+                });
+            match maybe_cell.and_then(|cell| glow_colour(cell, config)) {
+                Some(colour) => image::Rgba([colour.0, colour.1, colour.2, 1.0]),
+                None => image::Rgba([0.0, 0.0, 0.0, 0.0]),
+            }
+        },
+    )
+}
+
+/// The colour that a cell should glow with, if it should glow at all.
+fn glow_colour(cell: &termwiz::cell::Cell, config: &Config) -> Option<crate::surface::Colour> {
+    if !super::gpu::text_mask::is_text(cell) {
+        return None;
+    }
+
+    let colour = crate::blender::Blender::extract_colour(cell.attrs().foreground())?;
+    let luminance = 0.0722f32.mul_add(colour.2, 0.2126f32.mul_add(colour.0, 0.7152 * colour.1));
+    let is_bold = cell.attrs().intensity() == termwiz::cell::Intensity::Bold;
+    let is_glowing = luminance >= config.luminance_threshold || (config.include_bold && is_bold);
+
+    is_glowing.then_some((colour.0, colour.1, colour.2, 1.0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cell_with_colour(
+        character: char,
+        colour: termwiz::color::SrgbaTuple,
+    ) -> termwiz::cell::Cell {
+        let mut attributes = termwiz::cell::CellAttributes::default();
+        attributes
+            .set_foreground(termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(colour));
+        termwiz::cell::Cell::new(character, attributes)
+    }
+
+    #[test]
+    fn only_bright_text_glows() {
+        let config = Config::default();
+        let bright = termwiz::color::SrgbaTuple(1.0, 1.0, 0.8, 1.0);
+        let dark = termwiz::color::SrgbaTuple(0.2, 0.2, 0.2, 1.0);
+
+        assert!(glow_colour(&cell_with_colour('a', bright), &config).is_some());
+        assert!(glow_colour(&cell_with_colour('a', dark), &config).is_none());
+        assert!(glow_colour(&cell_with_colour(' ', bright), &config).is_none());
+    }
+
+    #[test]
+    fn bold_text_glows() {
+        let config = Config::default();
+        let dark = termwiz::color::SrgbaTuple(0.2, 0.2, 0.2, 1.0);
+        let mut cell = cell_with_colour('a', dark);
+        cell.attrs_mut()
+            .set_intensity(termwiz::cell::Intensity::Bold);
+
+        assert!(glow_colour(&cell, &config).is_some());
+    }
+}
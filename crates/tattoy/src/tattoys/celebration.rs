@@ -0,0 +1,267 @@
+//! Confetti and sparkles that burst out of a cell of the terminal, like the line that said all the
+//! tests passed. Anything can ask for a celebration by sending `Protocol::Celebrate`: output
+//! events with a `celebrate` kind, plugins and the remote control API all do.
+
+use color_eyre::eyre::Result;
+use rand::Rng as _;
+
+/// The layer of the celebrations. They're above the text, but only for a moment.
+const LAYER: i16 = crate::layers::Group::Effects.layer(20);
+
+/// How strongly confetti is pulled down, in pixels per frame per frame.
+const GRAVITY: f32 = 0.04;
+
+/// How much of its horizontal speed confetti keeps each frame, as it flutters down.
+const DRAG: f32 = 0.97;
+
+/// The colours of confetti.
+const CONFETTI_COLOURS: [crate::surface::Colour; 5] = [
+    (1.0, 0.3, 0.4, 1.0),
+    (1.0, 0.8, 0.2, 1.0),
+    (0.3, 0.9, 0.5, 1.0),
+    (0.3, 0.6, 1.0, 1.0),
+    (0.8, 0.4, 1.0, 1.0),
+];
+
+/// User-configurable settings for celebrations.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable celebrations.
+    pub enabled: bool,
+    /// The opacity of the celebrations.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// How many pieces of confetti, or sparkles, are in each celebration.
+    pub particles: u16,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            opacity: 1.0,
+            particles: 40,
+        }
+    }
+}
+
+/// The kinds of celebration.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Kind {
+    /// Colourful confetti that's thrown up and flutters down.
+    #[default]
+    Confetti,
+    /// Golden sparkles that twinkle out in every direction.
+    Sparkles,
+}
+
+/// A piece of confetti or a sparkle.
+#[derive(Debug, Clone)]
+struct Particle {
+    /// Horizontal position, in pixels.
+    x: f32,
+    /// Vertical position, in pixels.
+    y: f32,
+    /// Horizontal speed.
+    velocity_x: f32,
+    /// Vertical speed.
+    velocity_y: f32,
+    /// The kind of celebration that the particle is from.
+    kind: Kind,
+    /// The number of frames left before the particle disappears.
+    lifetime: u16,
+    /// The number of frames that the particle started with.
+    max_lifetime: u16,
+    /// The colour of the particle.
+    colour: crate::surface::Colour,
+}
+
+impl Particle {
+    /// The particles of a celebration, bursting out of a cell.
+    fn burst(kind: Kind, origin: (u16, u16), count: u16) -> Vec<Self> {
+        let mut rng = rand::thread_rng();
+        let x = f32::from(origin.0) + 0.5;
+        let y = f32::from(origin.1) * 2.0 + 1.0;
+        (0..count)
+            .map(|_| {
+                let (angle, speed, lifetime, colour) = match kind {
+                    Kind::Confetti => (
+                        rng.gen_range(-2.6..-0.5_f32),
+                        rng.gen_range(0.5..1.3_f32),
+                        rng.gen_range(50..80),
+                        *CONFETTI_COLOURS
+                            .get(rng.gen_range(0..CONFETTI_COLOURS.len()))
+                            .unwrap_or(&crate::surface::WHITE),
+                    ),
+                    Kind::Sparkles => (
+                        rng.gen_range(0.0..core::f32::consts::TAU),
+                        rng.gen_range(0.1..0.6_f32),
+                        rng.gen_range(20..40),
+                        (1.0, rng.gen_range(0.8..1.0), rng.gen_range(0.3..0.8), 1.0),
+                    ),
+                };
+                Self {
+                    x,
+                    y,
+                    velocity_x: angle.cos() * speed,
+                    velocity_y: angle.sin() * speed,
+                    kind,
+                    lifetime,
+                    max_lifetime: lifetime,
+                    colour,
+                }
+            })
+            .collect()
+    }
+
+    /// Move the particle on by one frame.
+    fn step(&mut self) {
+        self.x += self.velocity_x;
+        self.y += self.velocity_y;
+        self.lifetime = self.lifetime.saturating_sub(1);
+        let remaining = f32::from(self.lifetime) / f32::from(self.max_lifetime.max(1));
+
+        match self.kind {
+            Kind::Confetti => {
+                self.velocity_x *= DRAG;
+                self.velocity_y += GRAVITY;
+                self.colour.3 = remaining.min(0.3) / 0.3;
+            }
+            Kind::Sparkles => {
+                let twinkle = rand::thread_rng().gen_range(0.4..1.0);
+                self.colour.3 = remaining * twinkle;
+            }
+        }
+    }
+}
+
+/// `Celebration`
+pub(crate) struct Celebration {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// All the particles currently on the screen.
+    particles: Vec<Particle>,
+}
+
+impl Celebration {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.celebration.opacity;
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "celebration".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        Self {
+            tattoy,
+            particles: Vec::new(),
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut celebration = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = celebration.tattoy.sleep_until_next_frame_tick() => {
+                    if !celebration.particles.is_empty() {
+                        celebration.render().await?;
+                    }
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if let crate::run::Protocol::Celebrate { kind, origin } = &message {
+                        celebration.celebrate(*kind, *origin).await;
+                    }
+                    celebration.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Burst some particles out of a cell.
+    async fn celebrate(&mut self, kind: Kind, origin: (u16, u16)) {
+        if self.tattoy.is_switched_off {
+            return;
+        }
+        if self.tattoy.state.is_reduced_motion().await {
+            tracing::debug!("Not celebrating because of reduced motion");
+            return;
+        }
+
+        let count = self.tattoy.state.config.read().await.celebration.particles;
+        tracing::debug!("Celebrating with {kind:?} from {origin:?}");
+        self.particles.extend(Particle::burst(kind, origin, count));
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.opacity = self.tattoy.state.config.read().await.celebration.opacity;
+        for particle in &mut self.particles {
+            particle.step();
+        }
+        self.particles.retain(|particle| particle.lifetime > 0);
+
+        // Once the party is over, remove our layer from the screen entirely.
+        if self.particles.is_empty() {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.initialise_surface();
+        for particle in &self.particles {
+            self.tattoy
+                .add_point(particle.x, particle.y, particle.colour);
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn confetti_is_thrown_up_from_its_origin() {
+        let particles = Particle::burst(Kind::Confetti, (10, 5), 20);
+        assert_eq!(particles.len(), 20);
+        for particle in &particles {
+            assert!((particle.x - 10.5).abs() < f32::EPSILON);
+            assert!((particle.y - 11.0).abs() < f32::EPSILON);
+            assert!(particle.velocity_y < 0.0);
+        }
+    }
+
+    #[test]
+    fn particles_fade_away() {
+        for kind in [Kind::Confetti, Kind::Sparkles] {
+            let mut particle = Particle::burst(kind, (0, 0), 1).remove(0);
+            for _ in 0..particle.max_lifetime {
+                particle.step();
+            }
+            assert_eq!(particle.lifetime, 0);
+            assert!(particle.colour.3 < f32::EPSILON);
+        }
+    }
+}
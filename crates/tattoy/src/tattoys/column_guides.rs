@@ -0,0 +1,195 @@
+//! Faint vertical guides at chosen columns, like an editor's ruler, to help keep lines to a
+//! certain length.
+//!
+//! The guides can be limited to when an editor is running. That's when the PTY is on the
+//! alternate screen and the title that it set contains the name of one of the configured editors.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the guides. They're beneath the terminal's text so that they never hide it.
+const LAYER: i16 = crate::layers::Group::Background.layer(5);
+
+/// User-configurable settings for column guides.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable column guides.
+    pub enabled: bool,
+    /// The opacity of the guides.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// The columns that get a guide. The first column is 1, so a guide at 80 is on the last
+    /// column of an 80 character line.
+    pub columns: Vec<usize>,
+    /// The colour of the guides.
+    pub colour: crate::surface::Colour,
+    /// Only show the guides when one of the `editors` is running.
+    pub only_in_editors: bool,
+    /// The names of editors, as they appear in the titles that they set.
+    pub editors: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.15,
+            columns: vec![80, 100, 120],
+            colour: (0.5, 0.5, 0.5, 1.0),
+            only_in_editors: false,
+            editors: [
+                "vim", "nvim", "hx", "helix", "emacs", "nano", "micro", "kak",
+            ]
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect(),
+        }
+    }
+}
+
+/// Whether a title looks like it was set by one of the editors. Editors' titles are things like
+/// "main.rs - NVIM" or "vim src/main.rs", so any word of the title can be the editor's name.
+fn is_editor(title: &str, editors: &[String]) -> bool {
+    title
+        .split(|character: char| !character.is_alphanumeric())
+        .any(|word| {
+            editors
+                .iter()
+                .any(|editor| editor.eq_ignore_ascii_case(word))
+        })
+}
+
+/// The cells that the guides are drawn in, for the columns that fit on the screen.
+fn guide_cells(columns: &[usize], width: usize) -> Vec<usize> {
+    let mut cells = columns
+        .iter()
+        .filter(|column| (1..=width).contains(*column))
+        .map(|column| column - 1)
+        .collect::<Vec<usize>>();
+    cells.sort_unstable();
+    cells.dedup();
+    cells
+}
+
+/// `ColumnGuides`
+pub(crate) struct ColumnGuides {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// Whether something has changed that needs rendering.
+    is_dirty: bool,
+}
+
+impl ColumnGuides {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.column_guides.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "column_guides".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_below_text();
+        Self {
+            tattoy,
+            is_dirty: true,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut guides = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = guides.tattoy.sleep_until_next_frame_tick() => {
+                    guides.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    guides.handle_protocol_message(&message);
+                    guides.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    const fn handle_protocol_message(&mut self, message: &crate::run::Protocol) {
+        // Screen changes are what switch between the alternate screen and titles.
+        if super::tattoyer::Tattoyer::is_screen_output_changed(message)
+            || matches!(message, crate::run::Protocol::Config(_))
+        {
+            self.is_dirty = true;
+        }
+    }
+
+    /// Render if anything has changed.
+    async fn tick(&mut self) -> Result<()> {
+        if !self.is_dirty {
+            return Ok(());
+        }
+        self.is_dirty = false;
+
+        let config = self.tattoy.state.config.read().await.column_guides.clone();
+        self.render(&config).await
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config) -> Result<()> {
+        let is_in_editor = self.tattoy.is_alternate_screen()
+            && is_editor(self.tattoy.screen.surface.title(), &config.editors);
+        if config.only_in_editors && !is_in_editor {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+        for x in guide_cells(&config.columns, usize::from(self.tattoy.width)) {
+            for y in 0..usize::from(self.tattoy.height) {
+                self.tattoy
+                    .surface
+                    .add_text(x, y, " ".to_owned(), Some(config.colour), None);
+            }
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn editors_are_found_in_titles() {
+        let editors = Config::default().editors;
+        assert!(is_editor("main.rs (~/src) - NVIM", &editors));
+        assert!(is_editor("vim src/main.rs", &editors));
+        assert!(!is_editor("vimdiff", &editors));
+        assert!(!is_editor("user@host: ~/src", &editors));
+    }
+
+    #[test]
+    fn only_guides_that_fit_are_drawn() {
+        assert_eq!(guide_cells(&[120, 80, 100, 80], 110), vec![79, 99]);
+        assert_eq!(guide_cells(&[0, 1], 10), vec![0]);
+    }
+}
@@ -0,0 +1,287 @@
+//! Show how long each command took, faintly and right-aligned, on the line of the command's
+//! prompt. Like the command duration segments of shell prompt plugins, but for every command
+//! still on the screen. Requires Tattoy's shell integration, see `tattoy --shell-integration`.
+//!
+//! The prompt lines are found by looking for the command's text on the screen, so the durations
+//! move with the screen's content as it scrolls.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the durations. They're drawn next to the terminal's text.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(2);
+
+/// The most commands that are remembered. Older ones are very unlikely to still be on the screen.
+pub(crate) const MAX_COMMANDS: usize = 100;
+
+/// User-configurable settings for command durations.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable command durations.
+    pub enabled: bool,
+    /// The opacity of the durations.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// Commands that finish quicker than this, in seconds, don't get a duration.
+    pub minimum_duration: f32,
+    /// The colour of the durations of successful commands.
+    pub colour: crate::surface::Colour,
+    /// The colour of the durations of failed commands.
+    pub failed_colour: crate::surface::Colour,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.5,
+            minimum_duration: 0.0,
+            colour: (0.7, 0.7, 0.7, 1.0),
+            failed_colour: (0.9, 0.3, 0.3, 1.0),
+        }
+    }
+}
+
+/// A human friendly duration, like "120ms", "2.3s" or "1m 5s".
+pub(crate) fn format_duration(duration: std::time::Duration) -> String {
+    let milliseconds = duration.as_millis();
+    let seconds = duration.as_secs();
+    if milliseconds < 1000 {
+        return format!("{milliseconds}ms");
+    }
+    if seconds < 60 {
+        return format!("{:.1}s", duration.as_secs_f32());
+    }
+    if seconds < 60 * 60 {
+        return format!("{}m {}s", seconds.div_euclid(60), seconds.rem_euclid(60));
+    }
+    format!(
+        "{}h {}m",
+        seconds.div_euclid(60 * 60),
+        seconds.rem_euclid(60 * 60).div_euclid(60)
+    )
+}
+
+/// Find the prompt line of each command. Commands are given oldest first, and each one's prompt
+/// must be above the prompt of the command after it. Only the lines above `below` are searched.
+pub(crate) fn find_prompts<'command>(
+    lines: &[String],
+    commands: &'command [crate::commands::Command],
+    below: usize,
+) -> Vec<(usize, &'command crate::commands::Command)> {
+    let mut prompts = Vec::new();
+    let mut below = below.min(lines.len());
+    for command in commands.iter().rev() {
+        let Some(y) = lines
+            .get(..below)
+            .and_then(|above| above.iter().rposition(|line| line.contains(&command.text)))
+        else {
+            break;
+        };
+        prompts.push((y, command));
+        below = y;
+    }
+    prompts
+}
+
+/// `CommandDurations`
+pub(crate) struct CommandDurations {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The most recently finished commands, oldest first.
+    commands: Vec<crate::commands::Command>,
+    /// Whether something has changed that needs rendering.
+    is_dirty: bool,
+}
+
+impl CommandDurations {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.command_durations.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "command_durations".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            commands: Vec::new(),
+            is_dirty: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut durations = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = durations.tattoy.sleep_until_next_frame_tick() => {
+                    durations.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    durations.handle_protocol_message(&message);
+                    durations.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    fn handle_protocol_message(&mut self, message: &crate::run::Protocol) {
+        if super::tattoyer::Tattoyer::is_screen_output_changed(message) {
+            self.is_dirty = true;
+        }
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only care about a few messages"
+        )]
+        match message {
+            crate::run::Protocol::CommandFinished(command) => {
+                self.commands.push(command.clone());
+                let excess = self.commands.len().saturating_sub(MAX_COMMANDS);
+                self.commands.drain(..excess);
+                self.is_dirty = true;
+            }
+            crate::run::Protocol::Config(_) | crate::run::Protocol::Resize { .. } => {
+                self.is_dirty = true;
+            }
+            _ => (),
+        }
+    }
+
+    /// Render if anything has changed.
+    async fn tick(&mut self) -> Result<()> {
+        if !self.is_dirty {
+            return Ok(());
+        }
+        self.is_dirty = false;
+
+        let config = self
+            .tattoy
+            .state
+            .config
+            .read()
+            .await
+            .command_durations
+            .clone();
+        self.render(&config).await
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config) -> Result<()> {
+        if self.tattoy.is_scrolling() || self.tattoy.is_alternate_screen() {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        let lines = self
+            .tattoy
+            .screen
+            .surface
+            .screen_chars_to_string()
+            .lines()
+            .map(str::to_owned)
+            .collect::<Vec<String>>();
+        let (_, cursor_y) = self.tattoy.screen.surface.cursor_position();
+        let width = usize::from(self.tattoy.width);
+
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+        for (y, command) in find_prompts(&lines, &self.commands, cursor_y) {
+            if command.duration.as_secs_f32() < config.minimum_duration {
+                continue;
+            }
+
+            let label = format_duration(command.duration);
+            let x = width.saturating_sub(self.tattoy.text_width(&label) + 1);
+            let text_width = lines
+                .get(y)
+                .map_or(0, |line| self.tattoy.text_width(line.trim_end()));
+            if text_width + 1 > x {
+                continue;
+            }
+
+            let colour = if command.exit_status == 0 {
+                config.colour
+            } else {
+                config.failed_colour
+            };
+            self.tattoy
+                .surface
+                .add_text(x, y, label, None, Some(colour));
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn command(text: &str) -> crate::commands::Command {
+        crate::commands::Command {
+            text: text.to_owned(),
+            duration: std::time::Duration::from_secs(1),
+            exit_status: 0,
+        }
+    }
+
+    #[test]
+    fn durations_are_human_friendly() {
+        let duration = std::time::Duration::from_millis;
+        assert_eq!(format_duration(duration(120)), "120ms");
+        assert_eq!(format_duration(duration(2345)), "2.3s");
+        assert_eq!(format_duration(duration(65_000)), "1m 5s");
+        assert_eq!(format_duration(duration(7_500_000)), "2h 5m");
+    }
+
+    #[test]
+    fn prompts_are_found_in_order() {
+        let lines = [
+            "$ ls",
+            "Cargo.toml  src",
+            "$ ls",
+            "Cargo.toml  src",
+            "$ cargo build",
+            "error: oops",
+            "$ ls",
+        ]
+        .map(str::to_owned);
+        let commands = [command("ls"), command("ls"), command("cargo build")];
+
+        let prompts = find_prompts(&lines, &commands, 6)
+            .into_iter()
+            .map(|(y, found)| (y, found.text.as_str()))
+            .collect::<Vec<(usize, &str)>>();
+        assert_eq!(prompts, vec![(4, "cargo build"), (2, "ls"), (0, "ls")]);
+    }
+
+    #[test]
+    fn commands_that_scrolled_off_are_skipped() {
+        let lines = ["$ pwd", "/home", "$ "].map(str::to_owned);
+        let commands = [command("ls"), command("pwd")];
+        assert_eq!(find_prompts(&lines, &commands, 2).len(), 1);
+    }
+}
@@ -0,0 +1,257 @@
+//! A subtle separator on the prompt line of every command, so that long sessions are visually
+//! chunked into the blocks of each command and its output. Requires Tattoy's shell integration,
+//! see `tattoy --shell-integration`.
+//!
+//! The prompt lines are found in the same way as they are for command durations, so the
+//! separators scroll with the screen's content.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the separators. They're beneath the terminal's text so that they never hide it.
+const LAYER: i16 = crate::layers::Group::Background.layer(4);
+
+/// How the separator looks.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Style {
+    /// A horizontal rule of `character`, from the end of the prompt's text to the edge of the
+    /// screen.
+    Rule,
+    /// A background tint across the whole prompt line, that fades out towards the right.
+    Fade,
+}
+
+/// User-configurable settings for command separators.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable command separators.
+    pub enabled: bool,
+    /// The opacity of the separators.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// How the separators look.
+    pub style: Style,
+    /// The character that the rule is drawn with.
+    pub character: String,
+    /// The colour of the separators.
+    pub colour: crate::surface::Colour,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.3,
+            style: Style::Rule,
+            character: "─".to_owned(),
+            colour: (0.5, 0.5, 0.5, 1.0),
+        }
+    }
+}
+
+/// The rule for a prompt line, as the column it starts at and its text. There's always a gap of
+/// one cell after the prompt's text.
+fn rule(character: &str, text_width: usize, width: usize) -> Option<(usize, String)> {
+    let x = text_width + 1;
+    if character.is_empty() || x >= width {
+        return None;
+    }
+    Some((x, character.repeat(width - x)))
+}
+
+/// The colour of the fade at a column. It's strongest at the left edge, and gone by the right.
+fn fade(colour: crate::surface::Colour, x: u16, width: u16) -> crate::surface::Colour {
+    let remaining = 1.0 - f32::from(x) / f32::from(width.max(1));
+    (colour.0, colour.1, colour.2, colour.3 * remaining.max(0.0))
+}
+
+/// `CommandSeparators`
+pub(crate) struct CommandSeparators {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The most recently finished commands, oldest first.
+    commands: Vec<crate::commands::Command>,
+    /// Whether something has changed that needs rendering.
+    is_dirty: bool,
+}
+
+impl CommandSeparators {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.command_separators.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "command_separators".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_below_text();
+        Self {
+            tattoy,
+            commands: Vec::new(),
+            is_dirty: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut separators = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = separators.tattoy.sleep_until_next_frame_tick() => {
+                    separators.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    separators.handle_protocol_message(&message);
+                    separators.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    fn handle_protocol_message(&mut self, message: &crate::run::Protocol) {
+        if super::tattoyer::Tattoyer::is_screen_output_changed(message) {
+            self.is_dirty = true;
+        }
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only care about a few messages"
+        )]
+        match message {
+            crate::run::Protocol::CommandFinished(command) => {
+                self.commands.push(command.clone());
+                let excess = self
+                    .commands
+                    .len()
+                    .saturating_sub(super::command_durations::MAX_COMMANDS);
+                self.commands.drain(..excess);
+                self.is_dirty = true;
+            }
+            crate::run::Protocol::Config(_) | crate::run::Protocol::Resize { .. } => {
+                self.is_dirty = true;
+            }
+            _ => (),
+        }
+    }
+
+    /// Render if anything has changed.
+    async fn tick(&mut self) -> Result<()> {
+        if !self.is_dirty {
+            return Ok(());
+        }
+        self.is_dirty = false;
+
+        let config = self
+            .tattoy
+            .state
+            .config
+            .read()
+            .await
+            .command_separators
+            .clone();
+        self.render(&config).await
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config) -> Result<()> {
+        if self.tattoy.is_scrolling() || self.tattoy.is_alternate_screen() {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        let lines = self
+            .tattoy
+            .screen
+            .surface
+            .screen_chars_to_string()
+            .lines()
+            .map(str::to_owned)
+            .collect::<Vec<String>>();
+        let (_, cursor_y) = self.tattoy.screen.surface.cursor_position();
+
+        // The cursor's line is the prompt of the command that's about to be run, which starts a
+        // new block too. But the very first line of the screen doesn't need separating.
+        let mut prompts = super::command_durations::find_prompts(&lines, &self.commands, cursor_y)
+            .into_iter()
+            .map(|(y, _)| y)
+            .collect::<Vec<usize>>();
+        if !self.commands.is_empty() {
+            prompts.push(cursor_y);
+        }
+        prompts.retain(|y| *y > 0);
+
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+        for y in prompts {
+            match config.style {
+                Style::Rule => {
+                    let text_width = lines
+                        .get(y)
+                        .map_or(0, |line| self.tattoy.text_width(line.trim_end()));
+                    let width = usize::from(self.tattoy.width);
+                    let Some((x, text)) = rule(&config.character, text_width, width) else {
+                        continue;
+                    };
+                    self.tattoy
+                        .surface
+                        .add_text(x, y, text, None, Some(config.colour));
+                }
+                Style::Fade => {
+                    let width = self.tattoy.width;
+                    for x in 0..width {
+                        self.tattoy.surface.add_text(
+                            usize::from(x),
+                            y,
+                            " ".to_owned(),
+                            Some(fade(config.colour, x, width)),
+                            None,
+                        );
+                    }
+                }
+            }
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rules_fill_the_rest_of_the_line() {
+        assert_eq!(rule("─", 4, 10), Some((5, "─────".to_owned())));
+        assert_eq!(rule("-", 9, 10), None);
+        assert_eq!(rule("", 0, 10), None);
+    }
+
+    #[test]
+    fn fades_fade_to_the_right() {
+        let colour = (1.0, 0.5, 0.0, 0.8);
+        assert_eq!(fade(colour, 0, 10), colour);
+        assert!((fade(colour, 5, 10).3 - 0.4).abs() < f32::EPSILON);
+        assert!(fade(colour, 9, 10).3 < fade(colour, 8, 10).3);
+    }
+}
@@ -0,0 +1,1112 @@
+//! A keyboard-driven copy mode, similar to `tmux`'s. It freezes the currently visible screen so
+//! that its contents can be navigated with Vim-style keys, searched, selected and then yanked to
+//! the user's clipboard.
+//!
+//! Moving past the top or bottom of the frozen screen pages in more of the history: first the
+//! shadow terminal's in-memory scrollback, and then the older lines that have been spilled to
+//! disk, see `crate::scrollback_log`. Searches cover the whole history.
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+
+/// The compositing layer for copy mode. It needs to be above nearly everything so that the frozen
+/// screen completely covers the live PTY. Notifications are still shown on top of it though.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(50);
+
+/// The background colour of selected text.
+const SELECTION_COLOUR: crate::surface::Colour = (0.25, 0.35, 0.7, 1.0);
+
+/// The background colour of copy mode's own cursor.
+const CURSOR_COLOUR: crate::surface::Colour = (0.9, 0.9, 0.9, 1.0);
+
+/// The background colour of the status line. It's the official Tattoy blue.
+const STATUS_LINE_COLOUR: crate::surface::Colour = (0.0, 0.204, 0.631, 1.0);
+
+/// A coordinate on the frozen screen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Position {
+    /// The row. It's defined first so that positions are ordered by row and then column.
+    y: usize,
+    /// The column.
+    x: usize,
+}
+
+/// The kind of selection being made. Each variant contains the position where the selection
+/// started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Selection {
+    /// Select by character, like Vim's `v`.
+    Character(Position),
+    /// Select whole lines, like Vim's `V`.
+    Line(Position),
+}
+
+/// The broad kinds of characters, used for word-based motions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharacterClass {
+    /// Spaces and empty cells.
+    Whitespace,
+    /// Letters, numbers and underscores.
+    Word,
+    /// Everything else.
+    Punctuation,
+}
+
+impl Selection {
+    /// The same selection, after `count` lines have been paged in above it.
+    const fn shifted(self, count: usize) -> Self {
+        match self {
+            Self::Character(anchor) => Self::Character(Position {
+                y: anchor.y.saturating_add(count),
+                ..anchor
+            }),
+            Self::Line(anchor) => Self::Line(Position {
+                y: anchor.y.saturating_add(count),
+                ..anchor
+            }),
+        }
+    }
+}
+
+/// The lines, out of `total` lines of scrollback, that are visible when it's scrolled up by
+/// `position` lines, for a screen that's `height` lines tall.
+fn scrolled_range(total: usize, position: usize, height: usize) -> core::ops::Range<usize> {
+    let start = total.saturating_sub(height.saturating_add(position));
+    start..start.saturating_add(height).min(total)
+}
+
+/// The cells for a line of plain text, like a spilled line. They're padded with spaces to the
+/// width of the screen.
+fn text_cells(text: &str, width: usize) -> Vec<termwiz::cell::Cell> {
+    let mut cells = text
+        .chars()
+        .map(|character| {
+            termwiz::cell::Cell::new(character, termwiz::cell::CellAttributes::default())
+        })
+        .collect::<Vec<_>>();
+    if cells.len() < width {
+        cells.resize(
+            width,
+            termwiz::cell::Cell::new(' ', termwiz::cell::CellAttributes::default()),
+        );
+    }
+    cells
+}
+
+/// Give every cell a real colour. The frozen screen must completely cover the live PTY underneath
+/// it.
+fn fill_default_colours(
+    lines: &mut [Vec<termwiz::cell::Cell>],
+    default_background: termwiz::color::SrgbaTuple,
+    default_foreground: termwiz::color::SrgbaTuple,
+) {
+    for cell in lines.iter_mut().flatten() {
+        let attributes = cell.attrs_mut();
+        if crate::blender::Blender::extract_colour(attributes.background()).is_none() {
+            attributes.set_background(crate::blender::Blender::make_true_colour_attribute(
+                default_background,
+            ));
+        }
+        if crate::blender::Blender::extract_colour(attributes.foreground()).is_none() {
+            attributes.set_foreground(crate::blender::Blender::make_true_colour_attribute(
+                default_foreground,
+            ));
+        }
+    }
+}
+
+/// `CopyMode`
+pub(crate) struct CopyMode {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// Is copy mode currently active?
+    is_active: bool,
+    /// The lines of the history that have been paged in. At first it's a frozen copy of the
+    /// screen's cells from the moment copy mode was entered.
+    lines: Vec<Vec<termwiz::cell::Cell>>,
+    /// A frozen copy of the shadow terminal's in-memory scrollback, for paging in.
+    history: Vec<Vec<termwiz::cell::Cell>>,
+    /// How many lines had been spilled to disk when copy mode was entered. They come before the
+    /// in-memory scrollback in the history.
+    spilled: usize,
+    /// The line of the history that the first of the paged in lines is.
+    first_line: usize,
+    /// The first of the paged in lines that's shown on the screen.
+    top: usize,
+    /// Copy mode's own cursor.
+    cursor: Position,
+    /// The current selection, if any.
+    selection: Option<Selection>,
+    /// The search query whilst it is being typed.
+    search_input: Option<String>,
+    /// The most recently submitted search, used for jumping between matches.
+    last_search: Option<String>,
+    /// A short message to show in the status line.
+    message: Option<String>,
+}
+
+impl CopyMode {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "copy_mode".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            is_active: false,
+            lines: Vec::new(),
+            history: Vec::new(),
+            spilled: 0,
+            first_line: 0,
+            top: 0,
+            cursor: Position::default(),
+            selection: None,
+            search_input: None,
+            last_search: None,
+            message: None,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut copy_mode = Self::new(output, state).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                result = protocol.recv() => {
+                    if matches!(result, Ok(crate::run::Protocol::End)) {
+                        break;
+                    }
+                    copy_mode.handle_protocol_message(result).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(
+        &mut self,
+        result: std::result::Result<crate::run::Protocol, tokio::sync::broadcast::error::RecvError>,
+    ) -> Result<()> {
+        match result {
+            Ok(message) => {
+                #[expect(
+                    clippy::wildcard_enum_match_arm,
+                    reason = "We're only interested in a few messages"
+                )]
+                match &message {
+                    crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ToggleCopyMode,
+                    ) => {
+                        if self.tattoy.state.get_is_copy_mode().await {
+                            self.enter().await?;
+                        } else {
+                            self.exit().await?;
+                        }
+                    }
+                    crate::run::Protocol::Input(input) if self.is_active => {
+                        if let termwiz::input::InputEvent::Key(key_event) = &input.event {
+                            self.handle_key(key_event).await?;
+                        }
+                    }
+                    crate::run::Protocol::Resize { .. } if self.is_active => {
+                        tracing::debug!("Exiting copy mode because the terminal resized");
+                        self.exit().await?;
+                    }
+                    _ => (),
+                }
+
+                self.tattoy.handle_common_protocol_messages(message)?;
+            }
+            Err(error) => tracing::error!("Receiving protocol message: {error:?}"),
+        }
+
+        Ok(())
+    }
+
+    /// Freeze the currently visible screen and start copy mode.
+    async fn enter(&mut self) -> Result<()> {
+        tracing::debug!("Entering copy mode");
+        self.freeze_screen().await;
+        self.is_active = true;
+        self.selection = None;
+        self.search_input = None;
+        self.message = None;
+
+        self.tattoy
+            .state
+            .protocol_tx
+            .send(crate::run::Protocol::CursorVisibility(false))?;
+        self.render().await
+    }
+
+    /// Leave copy mode and remove its layer from the compositor.
+    async fn exit(&mut self) -> Result<()> {
+        if !self.is_active {
+            return Ok(());
+        }
+
+        tracing::debug!("Exiting copy mode");
+        self.is_active = false;
+        self.lines.clear();
+        self.history.clear();
+        self.tattoy.state.set_is_copy_mode(false).await;
+
+        let is_cursor_visible = !self.tattoy.state.get_is_scrolling().await;
+        self.tattoy
+            .state
+            .protocol_tx
+            .send(crate::run::Protocol::CursorVisibility(is_cursor_visible))?;
+        self.tattoy.send_blank_output().await
+    }
+
+    /// Take a copy of exactly what the user can currently see, including any scrolled position in
+    /// the scrollback.
+    async fn freeze_screen(&mut self) {
+        let default_background = *self.tattoy.state.default_background.read().await;
+        let default_foreground = *self.tattoy.state.default_foreground.read().await;
+        self.history = self
+            .tattoy
+            .scrollback
+            .surface
+            .get_screen_cells()
+            .iter()
+            .map(|line| line.to_vec())
+            .collect();
+        self.spilled = self.tattoy.state.scrollback_spill.read().await.len();
+        let screen = &self.tattoy.screen.surface;
+        let (mut cursor_x, mut cursor_y) = screen.cursor_position();
+        let visible = scrolled_range(
+            self.history.len(),
+            self.tattoy.scrollback.position,
+            screen.dimensions().1,
+        );
+        self.first_line = self.spilled.saturating_add(visible.start);
+        self.top = 0;
+        self.lines = if self.tattoy.is_scrolling() {
+            let lines = self.history.get(visible).unwrap_or_default().to_vec();
+            // The PTY's cursor isn't anywhere in the scrollback, so start at the bottom.
+            cursor_x = 0;
+            cursor_y = lines.len().saturating_sub(1);
+            lines
+        } else {
+            screen
+                .get_screen_cells()
+                .iter()
+                .map(|line| line.to_vec())
+                .collect()
+        };
+
+        fill_default_colours(&mut self.lines, default_background, default_foreground);
+
+        self.cursor = Position {
+            y: cursor_y,
+            x: cursor_x,
+        };
+        self.clamp_cursor();
+    }
+
+    /// Handle a key press whilst in copy mode.
+    async fn handle_key(&mut self, key_event: &termwiz::input::KeyEvent) -> Result<()> {
+        if self.search_input.is_some() {
+            self.handle_search_key(key_event.key).await?;
+            return self.render().await;
+        }
+
+        self.message = None;
+        match key_event.key {
+            termwiz::input::KeyCode::Char('h') | termwiz::input::KeyCode::LeftArrow => {
+                self.cursor.x = self.cursor.x.saturating_sub(1);
+            }
+            termwiz::input::KeyCode::Char('l') | termwiz::input::KeyCode::RightArrow => {
+                self.cursor.x = self.cursor.x.saturating_add(1);
+            }
+            termwiz::input::KeyCode::Char('k') | termwiz::input::KeyCode::UpArrow => {
+                if self.cursor.y == 0 {
+                    self.page_in_above().await?;
+                }
+                self.cursor.y = self.cursor.y.saturating_sub(1);
+            }
+            termwiz::input::KeyCode::Char('j') | termwiz::input::KeyCode::DownArrow => {
+                if self.cursor.y.saturating_add(1) >= self.lines.len() {
+                    self.page_in_below().await?;
+                }
+                self.cursor.y = self.cursor.y.saturating_add(1);
+            }
+            termwiz::input::KeyCode::Char('w') => self.cursor = self.word_forward(),
+            termwiz::input::KeyCode::Char('b') => self.cursor = self.word_backward(),
+            termwiz::input::KeyCode::Char('0') | termwiz::input::KeyCode::Home => {
+                self.cursor.x = 0;
+            }
+            termwiz::input::KeyCode::Char('^') => self.cursor.x = self.first_non_blank(),
+            termwiz::input::KeyCode::Char('$') | termwiz::input::KeyCode::End => {
+                self.cursor.x = self.last_non_blank();
+            }
+            termwiz::input::KeyCode::Char('g') => self.cursor = Position::default(),
+            termwiz::input::KeyCode::Char('G') => {
+                self.cursor = Position {
+                    y: self.lines.len().saturating_sub(1),
+                    x: 0,
+                };
+            }
+            termwiz::input::KeyCode::Char('v') => self.toggle_selection(Selection::Character),
+            termwiz::input::KeyCode::Char('V') => self.toggle_selection(Selection::Line),
+            termwiz::input::KeyCode::Char('/') => self.search_input = Some(String::new()),
+            termwiz::input::KeyCode::Char('n') => self.jump_to_match(true).await?,
+            termwiz::input::KeyCode::Char('N') => self.jump_to_match(false).await?,
+            termwiz::input::KeyCode::Char('y') | termwiz::input::KeyCode::Enter => {
+                return self.yank().await;
+            }
+            termwiz::input::KeyCode::Escape => {
+                if self.selection.is_none() {
+                    return self.exit().await;
+                }
+                self.selection = None;
+            }
+            termwiz::input::KeyCode::Char('q') => return self.exit().await,
+            _ => (),
+        }
+
+        self.clamp_cursor();
+        self.render().await
+    }
+
+    /// Handle a key press whilst the user is typing a search query.
+    async fn handle_search_key(&mut self, key: termwiz::input::KeyCode) -> Result<()> {
+        let Some(query) = self.search_input.as_mut() else {
+            return Ok(());
+        };
+
+        match key {
+            termwiz::input::KeyCode::Char(character) => query.push(character),
+            termwiz::input::KeyCode::Backspace => {
+                query.pop();
+            }
+            termwiz::input::KeyCode::Enter => {
+                if !query.is_empty() {
+                    self.last_search = Some(query.clone());
+                }
+                self.search_input = None;
+                self.jump_to_match(true).await?;
+            }
+            termwiz::input::KeyCode::Escape => self.search_input = None,
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Start a selection from the cursor, or cancel it if one of the same kind is already active.
+    fn toggle_selection(&mut self, kind: fn(Position) -> Selection) {
+        let selection = kind(self.cursor);
+        let is_same_kind = matches!(
+            (self.selection, selection),
+            (Some(Selection::Character(_)), Selection::Character(_))
+                | (Some(Selection::Line(_)), Selection::Line(_))
+        );
+
+        self.selection = if is_same_kind {
+            None
+        } else {
+            // Switching kinds of selection keeps the original starting point, just like Vim.
+            match self.selection {
+                Some(Selection::Character(anchor) | Selection::Line(anchor)) => Some(kind(anchor)),
+                None => Some(selection),
+            }
+        };
+    }
+
+    /// Copy the selection, or the current line if there's no selection, to the user's clipboard
+    /// and then leave copy mode.
+    async fn yank(&mut self) -> Result<()> {
+        let text = self.selected_text();
+        tracing::debug!("Yanking {} characters from copy mode", text.chars().count());
+        self.tattoy
+            .state
+            .protocol_tx
+            .send(crate::run::Protocol::CopyToClipboard(text))?;
+        self.exit().await
+    }
+
+    /// Jump to the next (or previous) match of the last search, anywhere in the history. Wraps
+    /// around.
+    async fn jump_to_match(&mut self, is_forwards: bool) -> Result<()> {
+        let Some(query) = self.last_search.clone() else {
+            self.message = Some("No previous search".to_owned());
+            return Ok(());
+        };
+
+        let matches = self.find_history_matches(&query).await?;
+        let cursor = Position {
+            y: self.first_line.saturating_add(self.cursor.y),
+            ..self.cursor
+        };
+        let maybe_match = if is_forwards {
+            matches
+                .iter()
+                .find(|position| **position > cursor)
+                .or_else(|| matches.first())
+        } else {
+            matches
+                .iter()
+                .rev()
+                .find(|position| **position < cursor)
+                .or_else(|| matches.last())
+        }
+        .copied();
+
+        match maybe_match {
+            Some(position) => {
+                self.page_in_line(position.y).await?;
+                self.cursor = Position {
+                    y: position.y.saturating_sub(self.first_line),
+                    x: position.x,
+                };
+                self.clamp_cursor();
+                self.message = Some(format!("{} matches", matches.len()));
+            }
+            None => self.message = Some(format!("Pattern not found: {query}")),
+        }
+
+        Ok(())
+    }
+
+    /// The number of lines in the whole history, spilled and in-memory.
+    const fn total_lines(&self) -> usize {
+        self.spilled.saturating_add(self.history.len())
+    }
+
+    /// The number of lines that fit on the screen.
+    fn page_height(&self) -> usize {
+        usize::from(self.tattoy.height).max(1)
+    }
+
+    /// Read lines of the history, from the spill on disk or the frozen in-memory scrollback.
+    async fn load(&self, range: core::ops::Range<usize>) -> Result<Vec<Vec<termwiz::cell::Cell>>> {
+        let mut lines = Vec::with_capacity(range.len());
+        if range.start < self.spilled {
+            let spilled = self
+                .tattoy
+                .state
+                .scrollback_spill
+                .read()
+                .await
+                .read(range.start..range.end.min(self.spilled))
+                .await?;
+            let width = usize::from(self.tattoy.width);
+            lines.extend(spilled.iter().map(|text| text_cells(text, width)));
+        }
+        let in_memory =
+            range.start.saturating_sub(self.spilled)..range.end.saturating_sub(self.spilled);
+        lines.extend(
+            self.history
+                .get(in_memory)
+                .unwrap_or_default()
+                .iter()
+                .cloned(),
+        );
+
+        let default_background = *self.tattoy.state.default_background.read().await;
+        let default_foreground = *self.tattoy.state.default_foreground.read().await;
+        fill_default_colours(&mut lines, default_background, default_foreground);
+        Ok(lines)
+    }
+
+    /// Page in a screen's worth of the lines above the ones already paged in.
+    async fn page_in_above(&mut self) -> Result<()> {
+        let start = self.first_line.saturating_sub(self.page_height());
+        self.page_in_from(start).await
+    }
+
+    /// Page in the lines of the history from `start` up to the ones already paged in.
+    async fn page_in_from(&mut self, start: usize) -> Result<()> {
+        if start >= self.first_line {
+            return Ok(());
+        }
+
+        let mut lines = self.load(start..self.first_line).await?;
+        let count = lines.len();
+        lines.append(&mut self.lines);
+        self.lines = lines;
+        self.first_line = self.first_line.saturating_sub(count);
+        // Everything already paged in has moved down.
+        self.cursor.y = self.cursor.y.saturating_add(count);
+        self.top = self.top.saturating_add(count);
+        self.selection = self.selection.map(|selection| selection.shifted(count));
+        Ok(())
+    }
+
+    /// Page in a screen's worth of the lines below the ones already paged in.
+    async fn page_in_below(&mut self) -> Result<()> {
+        let end = self
+            .first_line
+            .saturating_add(self.lines.len())
+            .saturating_add(self.page_height());
+        self.page_in_until(end).await
+    }
+
+    /// Page in the lines of the history after the ones already paged in, up to `end`.
+    async fn page_in_until(&mut self, end: usize) -> Result<()> {
+        let start = self.first_line.saturating_add(self.lines.len());
+        let end = end.min(self.total_lines());
+        if end <= start {
+            return Ok(());
+        }
+
+        let lines = self.load(start..end).await?;
+        self.lines.extend(lines);
+        Ok(())
+    }
+
+    /// Make sure that a line of the history is paged in. The paged in lines grow to reach it when
+    /// there's a selection that might be extended to it, otherwise they're replaced with a new
+    /// page, so that jumping far back doesn't page in everything in between.
+    async fn page_in_line(&mut self, line: usize) -> Result<()> {
+        let paged_in = self.first_line..self.first_line.saturating_add(self.lines.len());
+        if paged_in.contains(&line) {
+            return Ok(());
+        }
+
+        if self.selection.is_some() {
+            if line < self.first_line {
+                return self.page_in_from(line).await;
+            }
+            return self.page_in_until(line.saturating_add(1)).await;
+        }
+
+        let height = self.page_height();
+        let end = line.saturating_add(height).min(self.total_lines());
+        let start = end.saturating_sub(height).min(line);
+        self.lines = self.load(start..end).await?;
+        self.first_line = start;
+        self.top = 0;
+        Ok(())
+    }
+
+    /// The text of a line, along with the byte offset at which each cell starts in that text.
+    fn line_text(line: &[termwiz::cell::Cell]) -> (String, Vec<usize>) {
+        let mut text = String::new();
+        let mut offsets = Vec::with_capacity(line.len());
+        for cell in line {
+            offsets.push(text.len());
+            text.push_str(cell.str());
+        }
+        (text, offsets)
+    }
+
+    /// The columns at which each match of the query starts in a line.
+    fn line_matches(line: &[termwiz::cell::Cell], query: &str) -> Vec<usize> {
+        let (text, offsets) = Self::line_text(line);
+        text.match_indices(query)
+            .filter_map(|(byte, _)| offsets.iter().rposition(|offset| *offset <= byte))
+            .collect()
+    }
+
+    /// Find the positions of every match of the query in the paged in lines.
+    fn find_matches(&self, query: &str) -> Vec<Position> {
+        let mut matches = Vec::new();
+        if query.is_empty() {
+            return matches;
+        }
+
+        for (y, line) in self.lines.iter().enumerate() {
+            matches.extend(
+                Self::line_matches(line, query)
+                    .into_iter()
+                    .map(|x| Position { y, x }),
+            );
+        }
+
+        matches
+    }
+
+    /// Find the positions of every match of the query in the whole history, whether it's paged
+    /// in or not. The positions are of lines of the history, rather than of the paged in lines.
+    async fn find_history_matches(&self, query: &str) -> Result<Vec<Position>> {
+        let mut matches = Vec::new();
+        if query.is_empty() {
+            return Ok(matches);
+        }
+        let paged_in = self.first_line..self.first_line.saturating_add(self.lines.len());
+
+        let spilled = self
+            .tattoy
+            .state
+            .scrollback_spill
+            .read()
+            .await
+            .find(query)
+            .await?;
+        for (y, text) in spilled {
+            if y < self.spilled && !paged_in.contains(&y) {
+                matches.extend(
+                    Self::line_matches(&text_cells(&text, 0), query)
+                        .into_iter()
+                        .map(|x| Position { y, x }),
+                );
+            }
+        }
+
+        for (index, line) in self.history.iter().enumerate() {
+            let y = self.spilled.saturating_add(index);
+            if !paged_in.contains(&y) {
+                matches.extend(
+                    Self::line_matches(line, query)
+                        .into_iter()
+                        .map(|x| Position { y, x }),
+                );
+            }
+        }
+
+        matches.extend(
+            self.find_matches(query)
+                .into_iter()
+                .map(|position| Position {
+                    y: self.first_line.saturating_add(position.y),
+                    ..position
+                }),
+        );
+        matches.sort_unstable();
+        Ok(matches)
+    }
+
+    /// The text covered by the current selection. If there's no selection then it's the text of
+    /// the current line.
+    fn selected_text(&self) -> String {
+        let (start, end, is_linewise) = match self.selection {
+            Some(Selection::Character(anchor)) => {
+                (anchor.min(self.cursor), anchor.max(self.cursor), false)
+            }
+            Some(Selection::Line(anchor)) => {
+                (anchor.min(self.cursor), anchor.max(self.cursor), true)
+            }
+            None => (self.cursor, self.cursor, true),
+        };
+
+        let mut lines = Vec::new();
+        for y in start.y..=end.y {
+            let Some(line) = self.lines.get(y) else {
+                continue;
+            };
+
+            let from = if !is_linewise && y == start.y {
+                start.x
+            } else {
+                0
+            };
+            let to = if !is_linewise && y == end.y {
+                end.x.saturating_add(1).min(line.len())
+            } else {
+                line.len()
+            };
+
+            let text: String = line
+                .get(from..to)
+                .unwrap_or_default()
+                .iter()
+                .map(termwiz::cell::Cell::str)
+                .collect();
+            lines.push(text.trim_end().to_owned());
+        }
+
+        lines.join("\n")
+    }
+
+    /// Is the position within the current selection?
+    fn is_selected(&self, position: Position) -> bool {
+        match self.selection {
+            Some(Selection::Character(anchor)) => {
+                position >= anchor.min(self.cursor) && position <= anchor.max(self.cursor)
+            }
+            Some(Selection::Line(anchor)) => {
+                position.y >= anchor.y.min(self.cursor.y)
+                    && position.y <= anchor.y.max(self.cursor.y)
+            }
+            None => false,
+        }
+    }
+
+    /// The number of cells in the given line.
+    fn line_width(&self, y: usize) -> usize {
+        self.lines.get(y).map_or(0, Vec::len)
+    }
+
+    /// Keep the cursor on the paged in lines, and show the lines that it's on.
+    fn clamp_cursor(&mut self) {
+        self.cursor.y = self.cursor.y.min(self.lines.len().saturating_sub(1));
+        self.cursor.x = self
+            .cursor
+            .x
+            .min(self.line_width(self.cursor.y).saturating_sub(1));
+
+        let height = self.page_height();
+        if self.cursor.y < self.top {
+            self.top = self.cursor.y;
+        }
+        if self.cursor.y >= self.top.saturating_add(height) {
+            self.top = self.cursor.y.saturating_add(1).saturating_sub(height);
+        }
+    }
+
+    /// The kind of character at the given position.
+    fn class_at(&self, position: Position) -> CharacterClass {
+        let maybe_character = self
+            .lines
+            .get(position.y)
+            .and_then(|line| line.get(position.x))
+            .and_then(|cell| cell.str().chars().next());
+
+        match maybe_character {
+            Some(character) if character.is_alphanumeric() || character == '_' => {
+                CharacterClass::Word
+            }
+            Some(character) if !character.is_whitespace() => CharacterClass::Punctuation,
+            _ => CharacterClass::Whitespace,
+        }
+    }
+
+    /// The next position on the screen, wrapping onto the next line.
+    fn step_forward(&self, position: Position) -> Option<Position> {
+        if position.x.saturating_add(1) < self.line_width(position.y) {
+            return Some(Position {
+                x: position.x.saturating_add(1),
+                ..position
+            });
+        }
+
+        (position.y.saturating_add(1) < self.lines.len()).then_some(Position {
+            y: position.y.saturating_add(1),
+            x: 0,
+        })
+    }
+
+    /// The previous position on the screen, wrapping onto the previous line.
+    fn step_backward(&self, position: Position) -> Option<Position> {
+        if position.x > 0 {
+            return Some(Position {
+                x: position.x.saturating_sub(1),
+                ..position
+            });
+        }
+
+        let y = position.y.checked_sub(1)?;
+        Some(Position {
+            y,
+            x: self.line_width(y).saturating_sub(1),
+        })
+    }
+
+    /// The start of the next word, like Vim's `w`.
+    fn word_forward(&self) -> Position {
+        let mut position = self.cursor;
+        let start_class = self.class_at(position);
+
+        // Move past the rest of the current word.
+        loop {
+            let Some(next) = self.step_forward(position) else {
+                return position;
+            };
+            let is_new_line = next.y != position.y;
+            position = next;
+            if is_new_line || self.class_at(position) != start_class {
+                break;
+            }
+        }
+
+        // Then past any whitespace.
+        while self.class_at(position) == CharacterClass::Whitespace {
+            let Some(next) = self.step_forward(position) else {
+                break;
+            };
+            position = next;
+        }
+
+        position
+    }
+
+    /// The start of the previous word, like Vim's `b`.
+    fn word_backward(&self) -> Position {
+        let mut position = self.cursor;
+
+        // Move back over any whitespace.
+        loop {
+            let Some(previous) = self.step_backward(position) else {
+                return position;
+            };
+            position = previous;
+            if self.class_at(position) != CharacterClass::Whitespace {
+                break;
+            }
+        }
+
+        // Then to the start of the word.
+        let class = self.class_at(position);
+        while let Some(previous) = self.step_backward(position) {
+            if previous.y != position.y || self.class_at(previous) != class {
+                break;
+            }
+            position = previous;
+        }
+
+        position
+    }
+
+    /// The column of the first non-blank character on the cursor's line, like Vim's `^`.
+    fn first_non_blank(&self) -> usize {
+        (0..self.line_width(self.cursor.y))
+            .find(|x| {
+                self.class_at(Position {
+                    x: *x,
+                    ..self.cursor
+                }) != CharacterClass::Whitespace
+            })
+            .unwrap_or(0)
+    }
+
+    /// The column of the last non-blank character on the cursor's line. Terminal lines are
+    /// padded with spaces, so this is more useful than the literal end of the line.
+    fn last_non_blank(&self) -> usize {
+        (0..self.line_width(self.cursor.y))
+            .rev()
+            .find(|x| {
+                self.class_at(Position {
+                    x: *x,
+                    ..self.cursor
+                }) != CharacterClass::Whitespace
+            })
+            .unwrap_or(0)
+    }
+
+    /// The text for the status line.
+    fn status_text(&self) -> String {
+        let mode = match self.selection {
+            Some(Selection::Character(_)) => " VISUAL",
+            Some(Selection::Line(_)) => " V-LINE",
+            None => "",
+        };
+
+        let details = if let Some(query) = &self.search_input {
+            format!("/{query}")
+        } else if let Some(message) = &self.message {
+            message.clone()
+        } else {
+            "hjkl move, v select, / search, y yank, q quit".to_owned()
+        };
+
+        format!(
+            " COPY{mode} [{},{}] {details}",
+            self.cursor.y.saturating_add(1),
+            self.cursor.x.saturating_add(1)
+        )
+    }
+
+    /// Render the frozen screen, selection, cursor and status line.
+    async fn render(&mut self) -> Result<()> {
+        if !self.is_active {
+            return Ok(());
+        }
+
+        self.tattoy.initialise_surface();
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+
+        let mut cells = self.tattoy.surface.surface.screen_cells();
+        for (y, line) in self.lines.iter().enumerate().skip(self.top).take(height) {
+            let screen_y = y.saturating_sub(self.top);
+            for (x, frozen_cell) in line.iter().enumerate() {
+                let Some(cell) = cells.get_mut(screen_y).and_then(|row| row.get_mut(x)) else {
+                    continue;
+                };
+                *cell = frozen_cell.clone();
+
+                let position = Position { y, x };
+                if position == self.cursor {
+                    cell.attrs_mut()
+                        .set_background(crate::surface::Surface::make_colour_attribute(
+                            CURSOR_COLOUR,
+                        ))
+                        .set_foreground(crate::surface::Surface::make_colour_attribute(
+                            crate::surface::BLACK,
+                        ));
+                } else if self.is_selected(position) {
+                    cell.attrs_mut().set_background(
+                        crate::surface::Surface::make_colour_attribute(SELECTION_COLOUR),
+                    );
+                }
+            }
+        }
+        drop(cells);
+
+        // Keep the status line out of the way of the cursor.
+        let status_y = if self.cursor.y.saturating_sub(self.top) == height.saturating_sub(1) {
+            0
+        } else {
+            height.saturating_sub(1)
+        };
+        let status: String = format!("{:<width$}", self.status_text())
+            .chars()
+            .take(width)
+            .collect();
+        self.tattoy.surface.add_text(
+            0,
+            status_y,
+            status,
+            Some(STATUS_LINE_COLOUR),
+            Some(crate::surface::WHITE),
+        );
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn copy_mode(lines: &[&str]) -> CopyMode {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1);
+        let state = crate::shared_state::SharedState::init(1, 1, protocol_tx)
+            .await
+            .unwrap();
+        let (output, _) = tokio::sync::mpsc::channel(1);
+        let mut copy_mode = CopyMode::new(output, state).await;
+        copy_mode.lines = lines
+            .iter()
+            .map(|line| {
+                line.chars()
+                    .map(|character| {
+                        termwiz::cell::Cell::new(
+                            character,
+                            termwiz::cell::CellAttributes::default(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        copy_mode
+    }
+
+    #[tokio::test]
+    async fn word_motions_jump_between_words() {
+        let mut copy_mode = copy_mode(&["foo bar.baz", "  qux"]).await;
+        let mut stops = Vec::new();
+        for _ in 0..4 {
+            copy_mode.cursor = copy_mode.word_forward();
+            stops.push((copy_mode.cursor.y, copy_mode.cursor.x));
+        }
+        assert_eq!(stops, [(0, 4), (0, 7), (0, 8), (1, 2)]);
+
+        copy_mode.cursor = copy_mode.word_backward();
+        assert_eq!(copy_mode.cursor, Position { y: 0, x: 8 });
+    }
+
+    #[tokio::test]
+    async fn selections_are_yanked_as_text() {
+        let mut copy_mode = copy_mode(&["hello world  ", "second line "]).await;
+        copy_mode.cursor = Position { y: 0, x: 3 };
+        assert_eq!(copy_mode.selected_text(), "hello world");
+
+        copy_mode.selection = Some(Selection::Character(Position { y: 0, x: 6 }));
+        copy_mode.cursor = Position { y: 1, x: 5 };
+        assert_eq!(copy_mode.selected_text(), "world\nsecond");
+
+        copy_mode.selection = Some(Selection::Line(Position { y: 0, x: 6 }));
+        assert_eq!(copy_mode.selected_text(), "hello world\nsecond line");
+    }
+
+    #[tokio::test]
+    async fn every_match_is_found() {
+        let copy_mode = copy_mode(&["ab ab", "xab"]).await;
+        assert_eq!(
+            copy_mode.find_matches("ab"),
+            [
+                Position { y: 0, x: 0 },
+                Position { y: 0, x: 3 },
+                Position { y: 1, x: 1 }
+            ]
+        );
+        assert!(copy_mode.find_matches("").is_empty());
+        assert!(copy_mode.find_matches("nope").is_empty());
+    }
+
+    #[tokio::test]
+    async fn switching_selections_keeps_their_start() {
+        let mut copy_mode = copy_mode(&["one", "two"]).await;
+        copy_mode.toggle_selection(Selection::Character);
+        assert_eq!(
+            copy_mode.selection,
+            Some(Selection::Character(Position::default()))
+        );
+
+        copy_mode.cursor = Position { y: 1, x: 2 };
+        copy_mode.toggle_selection(Selection::Line);
+        assert_eq!(
+            copy_mode.selection,
+            Some(Selection::Line(Position::default()))
+        );
+
+        copy_mode.toggle_selection(Selection::Line);
+        assert_eq!(copy_mode.selection, None);
+    }
+
+    #[test]
+    fn the_scrolled_part_of_the_scrollback_is_visible() {
+        assert_eq!(scrolled_range(10, 0, 3), 7..10);
+        assert_eq!(scrolled_range(10, 2, 3), 5..8);
+        assert_eq!(scrolled_range(10, 20, 3), 0..3);
+        assert_eq!(scrolled_range(2, 0, 3), 0..2);
+    }
+
+    #[tokio::test]
+    async fn spilled_lines_are_paged_in_and_searched() {
+        let mut copy_mode = copy_mode(&["three", "four"]).await;
+        let path = std::env::temp_dir().join(format!(
+            "tattoy-test-copy-mode-{}.spill",
+            std::process::id()
+        ));
+        let mut spill = crate::scrollback_log::Spill::new(path.clone());
+        spill
+            .append(&["one".to_owned(), "two".to_owned()])
+            .await
+            .unwrap();
+        *copy_mode.tattoy.state.scrollback_spill.write().await = spill;
+        copy_mode.history = copy_mode.lines.clone();
+        copy_mode.spilled = 2;
+        copy_mode.first_line = 2;
+
+        copy_mode.page_in_above().await.unwrap();
+        assert_eq!(copy_mode.first_line, 1);
+        assert_eq!(copy_mode.cursor.y, 1);
+        assert_eq!(copy_mode.selected_text(), "three");
+
+        copy_mode.last_search = Some("one".to_owned());
+        copy_mode.jump_to_match(true).await.unwrap();
+        assert_eq!(copy_mode.first_line, 0);
+        assert_eq!(copy_mode.selected_text(), "one");
+
+        copy_mode.page_in_below().await.unwrap();
+        copy_mode.cursor.y = 1;
+        assert_eq!(copy_mode.selected_text(), "two");
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
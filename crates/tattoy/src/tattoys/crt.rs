@@ -0,0 +1,200 @@
+//! A built-in CRT post-processing pass: scanlines, curvature, chromatic aberration, vignette and
+//! phosphor glow. It runs on the pixel representation of the terminal and is composited over the
+//! terminal's text.
+
+use color_eyre::eyre::Result;
+
+use crate::tattoys::tattoyer::Tattoyer;
+
+/// The tattoy's ID. The renderer uses it to tint text rather than replace it.
+pub const ID: &str = "crt";
+
+/// The GLSL code for the effect.
+static CRT_SHADER: &str = include_str!("gpu/shaders/crt.glsl");
+
+/// User-configurable settings for the CRT effect. All the effect strengths are from `0.0` to
+/// `1.0`.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the effect.
+    pub enabled: bool,
+    /// The opacity of the effect.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// The layer (or z-index) into which the effect is rendered.
+    #[schemars(range(min = crate::layers::MIN_LAYER, max = crate::layers::MAX_LAYER))]
+    pub layer: i16,
+    /// The darkness of the gaps between scanlines.
+    pub scanlines: f32,
+    /// How much the screen bulges out.
+    pub curvature: f32,
+    /// How far the colour channels are split apart.
+    pub chromatic_aberration: f32,
+    /// How much the edges of the screen are darkened.
+    pub vignette: f32,
+    /// How much bright colours bleed into their surroundings.
+    pub glow: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.5,
+            layer: crate::layers::Group::Effects.layer(9),
+            scanlines: 0.5,
+            curvature: 0.1,
+            chromatic_aberration: 0.3,
+            vignette: 0.3,
+            glow: 0.3,
+        }
+    }
+}
+
+impl Config {
+    /// Convert the effect strengths into defines for the shader.
+    fn defines(&self) -> Vec<(String, String)> {
+        [
+            ("SCANLINES", self.scanlines),
+            ("CURVATURE", self.curvature),
+            ("CHROMATIC_ABERRATION", self.chromatic_aberration),
+            ("VIGNETTE", self.vignette),
+            ("GLOW", self.glow),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), format!("{:.4}", value.clamp(0.0, 1.0))))
+        .collect()
+    }
+}
+
+/// `CRT`
+pub(crate) struct CRT {
+    /// The base Tattoy struct
+    tattoy: Tattoyer,
+    /// All the special GPU handling code.
+    gpu: super::gpu::pipeline::GPU,
+}
+
+impl crate::tattoys::gpu::shaderer::Shaderer for CRT {
+    fn tattoy(&self) -> &crate::tattoys::tattoyer::Tattoyer {
+        &self.tattoy
+    }
+
+    fn tattoy_mut(&mut self) -> &mut crate::tattoys::tattoyer::Tattoyer {
+        &mut self.tattoy
+    }
+
+    fn gpu(&self) -> &super::gpu::pipeline::GPU {
+        &self.gpu
+    }
+
+    fn gpu_mut(&mut self) -> &mut super::gpu::pipeline::GPU {
+        &mut self.gpu
+    }
+
+    async fn is_upload_tty_as_pixels(&self) -> bool {
+        true
+    }
+
+    fn is_upload_tty_with_characters(&self) -> bool {
+        true
+    }
+
+    fn is_cyclable(&self) -> bool {
+        false
+    }
+
+    async fn get_layer(&self) -> i16 {
+        self.tattoy().state.config.read().await.crt.layer
+    }
+
+    async fn get_opacity(&self) -> f32 {
+        self.tattoy().state.config.read().await.crt.opacity
+    }
+
+    async fn handle_config_update(&mut self, config: &crate::config::main::Config) -> Result<()> {
+        let defines = config.crt.defines();
+        if defines != self.gpu.defines {
+            tracing::debug!("Rebuilding CRT effect with new settings");
+            self.gpu.defines = defines;
+            self.gpu.build_pipeline().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        _index: usize,
+    ) -> Result<Self> {
+        let config = state.config.read().await.crt.clone();
+        let tty_size = *state.tty_size.read().await;
+        let gpu = super::gpu::pipeline::GPU::new_builtin(
+            CRT_SHADER,
+            config.defines(),
+            tty_size.width,
+            tty_size.height * 2,
+            state.protocol_tx.clone(),
+            state.get_gpu_device().await?,
+        )
+        .await?;
+        let tattoy = Tattoyer::new(
+            ID.to_owned(),
+            state,
+            config.layer,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+        Ok(Self { tattoy, gpu })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn every_effect_is_a_define() {
+        let defines = Config::default().defines();
+        let names = defines
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "SCANLINES",
+                "CURVATURE",
+                "CHROMATIC_ABERRATION",
+                "VIGNETTE",
+                "GLOW"
+            ]
+        );
+        assert_eq!(
+            defines.first().map(|(_, value)| value.as_str()),
+            Some("0.5000")
+        );
+    }
+
+    #[test]
+    fn strengths_are_kept_in_range() {
+        let config = Config {
+            scanlines: 2.0,
+            glow: -1.0,
+            ..Config::default()
+        };
+        let defines = config.defines();
+        let value = |name: &str| {
+            defines
+                .iter()
+                .find(|(define, _)| define == name)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(value("SCANLINES"), Some("1.0000".to_owned()));
+        assert_eq!(value("GLOW"), Some("0.0000".to_owned()));
+    }
+}
@@ -0,0 +1,198 @@
+//! A subtle tint beneath the row that the cursor is on, like the cursorline of editors. The
+//! cursor's column can be tinted too, which makes a crosshair.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the highlight. It's beneath the terminal's text so that it never hides it.
+const LAYER: i16 = crate::layers::Group::Background.layer(6);
+
+/// User-configurable settings for the cursor line.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the cursor line.
+    pub enabled: bool,
+    /// The opacity of the highlight.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// The colour of the highlight.
+    pub colour: crate::surface::Colour,
+    /// Also highlight the cursor's column.
+    pub highlight_column: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.1,
+            colour: crate::surface::WHITE,
+            highlight_column: false,
+        }
+    }
+}
+
+/// The cells that are highlighted for a cursor. The cell of the cursor itself only appears once,
+/// even when the column is highlighted too.
+fn highlighted_cells(
+    cursor: (usize, usize),
+    width: usize,
+    height: usize,
+    is_column: bool,
+) -> Vec<(usize, usize)> {
+    let (cursor_x, cursor_y) = cursor;
+    if cursor_y >= height {
+        return Vec::new();
+    }
+
+    let mut cells = (0..width).map(|x| (x, cursor_y)).collect::<Vec<_>>();
+    if is_column && cursor_x < width {
+        cells.extend(
+            (0..height)
+                .filter(|y| *y != cursor_y)
+                .map(|y| (cursor_x, y)),
+        );
+    }
+    cells
+}
+
+/// `CursorLine`
+pub(crate) struct CursorLine {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The cursor position, the size of the screen and whether the column was highlighted, as they
+    /// were last rendered, so that only changes get rendered.
+    last_rendered: Option<((usize, usize), (u16, u16), bool)>,
+    /// Whether something has changed that needs rendering.
+    is_dirty: bool,
+}
+
+impl CursorLine {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.cursor_line.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "cursor_line".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_below_text();
+        Self {
+            tattoy,
+            last_rendered: None,
+            is_dirty: true,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut cursor_line = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = cursor_line.tattoy.sleep_until_next_frame_tick() => {
+                    cursor_line.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    cursor_line.handle_protocol_message(&message);
+                    cursor_line.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    const fn handle_protocol_message(&mut self, message: &crate::run::Protocol) {
+        if super::tattoyer::Tattoyer::is_screen_output_changed(message) {
+            self.is_dirty = true;
+        }
+        if matches!(message, crate::run::Protocol::Config(_)) {
+            self.last_rendered = None;
+            self.is_dirty = true;
+        }
+    }
+
+    /// Render if anything has changed.
+    async fn tick(&mut self) -> Result<()> {
+        if !self.is_dirty {
+            return Ok(());
+        }
+        self.is_dirty = false;
+
+        let config = self.tattoy.state.config.read().await.cursor_line.clone();
+        self.render(&config).await
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config) -> Result<()> {
+        // The cursor isn't anywhere on the screen whilst scrolling back.
+        if self.tattoy.is_scrolling() {
+            self.last_rendered = None;
+            return self.tattoy.send_blank_output().await;
+        }
+
+        let cursor = self.tattoy.screen.surface.cursor_position();
+        let size = (self.tattoy.width, self.tattoy.height);
+        let rendering = Some((cursor, size, config.highlight_column));
+        if self.last_rendered == rendering {
+            return Ok(());
+        }
+        self.last_rendered = rendering;
+
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+        let cells = highlighted_cells(
+            cursor,
+            usize::from(self.tattoy.width),
+            usize::from(self.tattoy.height),
+            config.highlight_column,
+        );
+        for (x, y) in cells {
+            self.tattoy
+                .surface
+                .add_text(x, y, " ".to_owned(), Some(config.colour), None);
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn the_whole_row_is_highlighted() {
+        let cells = highlighted_cells((2, 1), 4, 3, false);
+        assert_eq!(cells, vec![(0, 1), (1, 1), (2, 1), (3, 1)]);
+        assert!(highlighted_cells((0, 3), 4, 3, false).is_empty());
+    }
+
+    #[test]
+    fn the_column_is_highlighted_without_repeating_the_cursor() {
+        let cells = highlighted_cells((2, 1), 4, 3, true);
+        assert_eq!(cells.len(), 4 + 2);
+        assert!(cells.contains(&(2, 0)));
+        assert!(cells.contains(&(2, 2)));
+        assert_eq!(cells.iter().filter(|cell| **cell == (2, 1)).count(), 1);
+    }
+}
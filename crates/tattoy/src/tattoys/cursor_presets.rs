@@ -0,0 +1,118 @@
+//! Built-in cursor animations, so that the cursor can be animated without writing any GLSL. Each
+//! preset is a shader that's compiled into Tattoy, and its colour and size are set from the
+//! `[animated_cursor]` config with preprocessor defines.
+
+/// A built-in cursor animation.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Preset {
+    /// A soft glow around the cursor that gently pulses.
+    GlowPulse,
+    /// A tapering trail that follows the cursor when it moves.
+    Comet,
+    /// A ring that spreads out from the cursor whenever it moves.
+    Ripple,
+    /// A faint copy of the cursor that lingers where it was.
+    Ghost,
+}
+
+impl Preset {
+    /// The GLSL code for the preset.
+    pub const fn source(self) -> &'static str {
+        match self {
+            Self::GlowPulse => include_str!("gpu/shaders/cursor_presets/glow_pulse.glsl"),
+            Self::Comet => include_str!("gpu/shaders/cursor_presets/comet.glsl"),
+            Self::Ripple => include_str!("gpu/shaders/cursor_presets/ripple.glsl"),
+            Self::Ghost => include_str!("gpu/shaders/cursor_presets/ghost.glsl"),
+        }
+    }
+}
+
+/// A colour written in hex, like `"#ff8800"`.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String")]
+pub(crate) struct HexColour(pub [f32; 3]);
+
+impl TryFrom<String> for HexColour {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("`{text}` isn't a colour, try something like \"#ff8800\"");
+        let hex = text.strip_prefix('#').unwrap_or(&text);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+
+        let mut channels = [0.0; 3];
+        for (channel, start) in channels.iter_mut().zip([0, 2, 4]) {
+            let byte = hex
+                .get(start..start + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(invalid)?;
+            *channel = f32::from(byte) / 255.0;
+        }
+        Ok(Self(channels))
+    }
+}
+
+/// The preprocessor defines that set a preset's colour and size. Without a colour the preset uses
+/// the colour of the cursor itself.
+pub(crate) fn defines(colour: Option<HexColour>, size: f32) -> Vec<(String, String)> {
+    let mut defines = vec![(
+        "PRESET_SIZE".to_owned(),
+        format!("{:.4}", size.clamp(0.1, 10.0)),
+    )];
+    if let Some(HexColour([red, green, blue])) = colour {
+        defines.push((
+            "PRESET_COLOR".to_owned(),
+            format!("vec3({red:.4}, {green:.4}, {blue:.4})"),
+        ));
+    }
+    defines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hex_colours_are_parsed() {
+        let HexColour([red, green, blue]) = HexColour::try_from("#ff8000".to_owned()).unwrap();
+        assert!((red - 1.0).abs() < f32::EPSILON);
+        assert!((green - 128.0 / 255.0).abs() < f32::EPSILON);
+        assert!(blue.abs() < f32::EPSILON);
+
+        assert!(HexColour::try_from("00ff00".to_owned()).is_ok());
+        assert!(HexColour::try_from("#ff80".to_owned()).is_err());
+        assert!(HexColour::try_from("#gg8000".to_owned()).is_err());
+        assert!(HexColour::try_from("#ff80é0".to_owned()).is_err());
+    }
+
+    #[test]
+    fn presets_use_the_cursor_colour_by_default() {
+        assert_eq!(
+            defines(None, 2.0),
+            [("PRESET_SIZE".to_owned(), "2.0000".to_owned())]
+        );
+        let with_colour = defines(Some(HexColour([1.0, 0.5, 0.0])), 100.0);
+        assert_eq!(
+            with_colour,
+            [
+                ("PRESET_SIZE".to_owned(), "10.0000".to_owned()),
+                (
+                    "PRESET_COLOR".to_owned(),
+                    "vec3(1.0000, 0.5000, 0.0000)".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn presets_are_configured_by_name() {
+        let preset =
+            toml::from_str::<std::collections::BTreeMap<String, Preset>>("cursor = \"glow_pulse\"")
+                .unwrap();
+        assert_eq!(preset.get("cursor"), Some(&Preset::GlowPulse));
+        assert!(Preset::Comet.source().contains("mainImage"));
+    }
+}
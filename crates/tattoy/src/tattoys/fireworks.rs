@@ -0,0 +1,272 @@
+//! A short burst of fireworks to celebrate something good happening in the terminal, like a
+//! command succeeding or all the tests passing. What counts as "something good" is decided by
+//! the user's choice of output events.
+
+use color_eyre::eyre::Result;
+use rand::Rng as _;
+
+/// The layer of the fireworks. They're beneath the text so that they never hide anything
+/// important.
+const LAYER: i16 = crate::layers::Group::Background.layer(2);
+
+/// How strongly the sparks are pulled down, in pixels per frame per frame.
+const GRAVITY: f32 = 0.03;
+
+/// The number of sparks in each explosion.
+const SPARKS_PER_EXPLOSION: usize = 24;
+
+/// The number of frames that each spark lasts for.
+const SPARK_LIFETIME: u16 = 40;
+
+/// User-configurable settings for the fireworks.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the fireworks.
+    pub enabled: bool,
+    /// The opacity of the fireworks.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// The names of the output events that set off the fireworks.
+    pub events: Vec<String>,
+    /// How many rockets are launched for each celebration.
+    pub rockets: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 1.0,
+            events: vec!["tests_passed".to_owned()],
+            rockets: 3,
+        }
+    }
+}
+
+/// A rocket or one of the sparks from its explosion.
+#[derive(Debug, Clone)]
+struct Particle {
+    /// Horizontal position, in pixels.
+    x: f32,
+    /// Vertical position, in pixels.
+    y: f32,
+    /// Horizontal speed.
+    velocity_x: f32,
+    /// Vertical speed.
+    velocity_y: f32,
+    /// Rockets explode, sparks fade away.
+    is_rocket: bool,
+    /// The number of frames left before the particle disappears.
+    lifetime: u16,
+    /// The colour of the particle.
+    colour: crate::surface::Colour,
+}
+
+impl Particle {
+    /// Launch a rocket from the bottom of the terminal.
+    fn rocket(width: u16, height: u16) -> Self {
+        let mut rng = rand::thread_rng();
+        let height = f32::from(height);
+        // Just enough speed to reach somewhere in the top half of the terminal.
+        let apex = height * rng.gen_range(0.5..0.85);
+        let velocity_y = -(2.0 * GRAVITY * apex).sqrt();
+        Self {
+            x: rng.gen_range(0.0..f32::from(width.max(1))),
+            y: height,
+            velocity_x: rng.gen_range(-0.2..0.2),
+            velocity_y,
+            is_rocket: true,
+            lifetime: u16::MAX,
+            colour: crate::surface::WHITE,
+        }
+    }
+
+    /// The sparks from an exploding rocket.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "The number of sparks is always small"
+    )]
+    fn explode(&self) -> Vec<Self> {
+        let mut rng = rand::thread_rng();
+        let colour = (
+            rng.gen_range(0.4..1.0),
+            rng.gen_range(0.4..1.0),
+            rng.gen_range(0.4..1.0),
+            1.0,
+        );
+        (0..SPARKS_PER_EXPLOSION)
+            .map(|spark| {
+                let angle = core::f32::consts::TAU * spark as f32 / SPARKS_PER_EXPLOSION as f32;
+                let speed = rng.gen_range(0.3..0.8);
+                Self {
+                    x: self.x,
+                    y: self.y,
+                    velocity_x: angle.cos() * speed,
+                    velocity_y: angle.sin() * speed,
+                    is_rocket: false,
+                    lifetime: SPARK_LIFETIME,
+                    colour,
+                }
+            })
+            .collect()
+    }
+
+    /// Move the particle on by one frame. Returns any sparks if the rocket exploded.
+    fn step(&mut self) -> Vec<Self> {
+        self.x += self.velocity_x;
+        self.y += self.velocity_y;
+        self.velocity_y += GRAVITY;
+
+        if self.is_rocket {
+            if self.velocity_y >= 0.0 {
+                self.lifetime = 0;
+                return self.explode();
+            }
+            return Vec::new();
+        }
+
+        self.lifetime = self.lifetime.saturating_sub(1);
+        self.colour.3 = f32::from(self.lifetime) / f32::from(SPARK_LIFETIME);
+        Vec::new()
+    }
+}
+
+/// `Fireworks`
+pub(crate) struct Fireworks {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// All the rockets and sparks currently in the sky.
+    particles: Vec<Particle>,
+}
+
+impl Fireworks {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.fireworks.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "fireworks".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_below_text();
+        Self {
+            tattoy,
+            particles: Vec::new(),
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut fireworks = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = fireworks.tattoy.sleep_until_next_frame_tick() => {
+                    if !fireworks.particles.is_empty() {
+                        fireworks.render().await?;
+                    }
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if let crate::run::Protocol::OutputEvent(name) = &message {
+                        fireworks.handle_output_event(name).await;
+                    }
+                    fireworks.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Launch some rockets if the event is one that the user wants to celebrate.
+    async fn handle_output_event(&mut self, name: &str) {
+        let config = self.tattoy.state.config.read().await.fireworks.clone();
+        if !config.events.iter().any(|event| event == name) {
+            return;
+        }
+        if self.tattoy.state.is_reduced_motion().await {
+            tracing::debug!("Not launching fireworks for '{name}' because of reduced motion");
+            return;
+        }
+
+        tracing::debug!("Launching {} fireworks for '{name}'", config.rockets);
+        let height = self.tattoy.height.saturating_mul(2);
+        for _ in 0..config.rockets {
+            self.particles
+                .push(Particle::rocket(self.tattoy.width, height));
+        }
+    }
+
+    /// Move all the particles on by a frame.
+    fn step(&mut self) {
+        let mut sparks = Vec::new();
+        for particle in &mut self.particles {
+            sparks.extend(particle.step());
+        }
+        self.particles.retain(|particle| particle.lifetime > 0);
+        self.particles.extend(sparks);
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.tattoy.opacity = self.tattoy.state.config.read().await.fireworks.opacity;
+        self.step();
+
+        // Once the show is over, remove our layer from the screen entirely.
+        if self.particles.is_empty() {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.initialise_surface();
+        for particle in &self.particles {
+            self.tattoy
+                .add_point(particle.x, particle.y, particle.colour);
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rockets_explode_then_fade() {
+        let mut rocket = Particle::rocket(10, 20);
+        let mut sparks = Vec::new();
+        for _ in 0..100 {
+            sparks = rocket.step();
+            if !sparks.is_empty() {
+                break;
+            }
+        }
+        assert_eq!(sparks.len(), SPARKS_PER_EXPLOSION);
+        assert_eq!(rocket.lifetime, 0);
+
+        let mut spark = sparks.remove(0);
+        for _ in 0..SPARK_LIFETIME {
+            spark.step();
+        }
+        assert_eq!(spark.lifetime, 0);
+    }
+}
@@ -0,0 +1,371 @@
+//! A faint watermark showing the git status of the shell's current directory: the branch, whether
+//! there are uncommitted changes, and how far ahead or behind its upstream the branch is.
+//!
+//! `git status` isn't polled. It's only run when a command looks like it has finished, that is
+//! when the PTY's output goes quiet, when the shell changes directory, or when one of the
+//! configured output events happens.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the watermark. It's just beneath the PTY's text so that it never gets in the way.
+const LAYER: i16 = crate::layers::Group::Background.layer(3);
+
+/// User-configurable settings for the git watermark.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the watermark.
+    pub enabled: bool,
+    /// The opacity of the watermark.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// Which corner the watermark is shown in.
+    pub position: crate::utils::Corner,
+    /// The colour of the watermark's text.
+    pub colour: crate::surface::Colour,
+    /// Output events, as well as the PTY going quiet, that refresh the git status.
+    pub events: Vec<String>,
+    /// How long the PTY's output needs to be quiet, in seconds, before a command is considered
+    /// finished.
+    pub quiet_delay: f32,
+    /// The minimum time, in seconds, between runs of `git status`.
+    pub minimum_interval: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.3,
+            position: crate::utils::Corner::BottomRight,
+            colour: crate::surface::WHITE,
+            events: Vec::new(),
+            quiet_delay: 0.5,
+            minimum_interval: 2.0,
+        }
+    }
+}
+
+/// The parts of `git status` that are shown in the watermark.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Status {
+    /// The branch name, or the short commit hash when the `HEAD` is detached.
+    branch: String,
+    /// Whether there are any uncommitted changes, including untracked files.
+    is_dirty: bool,
+    /// The number of commits that the upstream doesn't have.
+    ahead: u32,
+    /// The number of commits that only the upstream has.
+    behind: u32,
+}
+
+impl Status {
+    /// Parse the output of `git status --porcelain=v2 --branch`.
+    fn parse(porcelain: &str) -> Option<Self> {
+        let mut head = None;
+        let mut commit = None;
+        let mut status = Self::default();
+
+        for line in porcelain.lines() {
+            let Some(header) = line.strip_prefix("# ") else {
+                status.is_dirty = true;
+                continue;
+            };
+            if let Some(name) = header.strip_prefix("branch.head ") {
+                head = Some(name.to_owned());
+            } else if let Some(oid) = header.strip_prefix("branch.oid ") {
+                commit = Some(oid.chars().take(7).collect::<String>());
+            } else if let Some(counts) = header.strip_prefix("branch.ab ") {
+                for count in counts.split_whitespace() {
+                    if let Some(ahead) = count.strip_prefix('+') {
+                        status.ahead = ahead.parse().unwrap_or_default();
+                    } else if let Some(behind) = count.strip_prefix('-') {
+                        status.behind = behind.parse().unwrap_or_default();
+                    }
+                }
+            }
+        }
+
+        status.branch = match head? {
+            detached if detached == "(detached)" => commit.unwrap_or(detached),
+            branch => branch,
+        };
+        Some(status)
+    }
+
+    /// The text of the watermark, like " main* ↑1 ↓2 ".
+    fn watermark(&self) -> String {
+        let dirty = if self.is_dirty { "*" } else { "" };
+        let mut parts = vec![format!("{}{dirty}", self.branch)];
+        if self.ahead > 0 {
+            parts.push(format!("↑{}", self.ahead));
+        }
+        if self.behind > 0 {
+            parts.push(format!("↓{}", self.behind));
+        }
+        format!(" {} ", parts.join(" "))
+    }
+}
+
+/// Decides when `git status` should be run, so that it's only run after commands finish.
+#[derive(Debug, Default)]
+struct Schedule {
+    /// When the PTY's output last changed, if it's changed since the last refresh.
+    output_changed_at: Option<tokio::time::Instant>,
+    /// Whether something, like an output event, has asked for a refresh.
+    is_requested: bool,
+    /// When `git status` was last run.
+    last_refresh: Option<tokio::time::Instant>,
+}
+
+impl Schedule {
+    /// Whether it's time to run `git status`. If it is, then the schedule starts waiting for the
+    /// next command to finish.
+    fn is_due(&mut self, config: &Config, now: tokio::time::Instant) -> bool {
+        let seconds =
+            |value: f32| std::time::Duration::try_from_secs_f32(value.max(0.0)).unwrap_or_default();
+
+        let is_quiet = self.output_changed_at.is_some_and(|changed_at| {
+            now.saturating_duration_since(changed_at) >= seconds(config.quiet_delay)
+        });
+        let is_rested = self.last_refresh.is_none_or(|last_refresh| {
+            now.saturating_duration_since(last_refresh) >= seconds(config.minimum_interval)
+        });
+        if !(is_quiet || self.is_requested) || !is_rested {
+            return false;
+        }
+
+        self.output_changed_at = None;
+        self.is_requested = false;
+        self.last_refresh = Some(now);
+        true
+    }
+}
+
+/// Get the git status of the shell's current directory. `None` when it's not in a git repo.
+async fn status(directory: std::path::PathBuf) -> Option<Status> {
+    tracing::trace!("Getting git status of {directory:?}");
+
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(&directory)
+        .args(["status", "--porcelain=v2", "--branch"])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await;
+    match output {
+        Ok(output) if output.status.success() => {
+            Status::parse(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(_) => None,
+        Err(error) => {
+            tracing::debug!("Couldn't run `git status`: {error:?}");
+            None
+        }
+    }
+}
+
+/// `GitWatermark`
+pub(crate) struct GitWatermark {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// When to next run `git status`.
+    schedule: Schedule,
+    /// Whether `git status` is currently running.
+    is_refreshing: bool,
+    /// The last known git status.
+    status: Option<Status>,
+    /// Whether something has changed that needs rendering.
+    is_dirty: bool,
+}
+
+impl GitWatermark {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.git_watermark.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "git_watermark".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_below_text();
+        Self {
+            tattoy,
+            schedule: Schedule {
+                is_requested: true,
+                ..Schedule::default()
+            },
+            is_refreshing: false,
+            status: None,
+            is_dirty: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut watermark = Self::new(output, std::sync::Arc::clone(&state)).await;
+        let (status_tx, mut status_rx) = tokio::sync::mpsc::channel(1);
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = watermark.tattoy.sleep_until_next_frame_tick() => {
+                    watermark.maybe_refresh(&status_tx).await;
+                    if watermark.is_dirty {
+                        watermark.render().await?;
+                    }
+                },
+                Some(status) = status_rx.recv() => {
+                    tracing::trace!("New git status: {status:?}");
+                    watermark.is_refreshing = false;
+                    watermark.is_dirty |= watermark.status != status;
+                    watermark.status = status;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    watermark.handle_protocol_message(&message).await;
+                    watermark.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Keep track of anything that could mean that the git status has changed.
+    async fn handle_protocol_message(&mut self, message: &crate::run::Protocol) {
+        if super::tattoyer::Tattoyer::is_screen_output_changed(message) {
+            self.schedule.output_changed_at = Some(tokio::time::Instant::now());
+        }
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only care about a few messages"
+        )]
+        match message {
+            crate::run::Protocol::OutputEvent(name) => {
+                let config = self.tattoy.state.config.read().await;
+                if config.git_watermark.events.contains(name) {
+                    self.schedule.is_requested = true;
+                }
+            }
+            crate::run::Protocol::DirectoryChanged(_) | crate::run::Protocol::Config(_) => {
+                self.schedule.is_requested = true;
+                self.is_dirty = true;
+            }
+            crate::run::Protocol::Resize { .. } => self.is_dirty = true,
+            _ => (),
+        }
+    }
+
+    /// Run `git status` in the background if a command has just finished.
+    async fn maybe_refresh(&mut self, sender: &tokio::sync::mpsc::Sender<Option<Status>>) {
+        let config = self.tattoy.state.config.read().await.git_watermark.clone();
+        if self.is_refreshing || !self.schedule.is_due(&config, tokio::time::Instant::now()) {
+            return;
+        }
+        let Some(directory) = self.tattoy.state.cwd.read().await.clone() else {
+            return;
+        };
+
+        self.is_refreshing = true;
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            if let Err(error) = sender.send(status(directory).await).await {
+                tracing::debug!("Couldn't send new git status: {error:?}");
+            }
+        });
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.is_dirty = false;
+        let Some(status) = self.status.clone() else {
+            return self.tattoy.send_blank_output().await;
+        };
+        let config = self.tattoy.state.config.read().await.git_watermark.clone();
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+
+        let text = status.watermark();
+        let (x, y) = config.position.place(
+            self.tattoy.width.into(),
+            self.tattoy.height.into(),
+            self.tattoy.text_width(&text),
+        );
+        self.tattoy
+            .surface
+            .add_text(x, y, text, None, Some(config.colour));
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_git_status() {
+        let porcelain = "\
+# branch.oid 1234567890abcdef
+# branch.head main
+# branch.upstream origin/main
+# branch.ab +2 -1
+1 .M N... 100644 100644 100644 abc abc src/main.rs
+? notes.txt
+";
+        let status = Status::parse(porcelain).unwrap();
+        assert_eq!(
+            status,
+            Status {
+                branch: "main".to_owned(),
+                is_dirty: true,
+                ahead: 2,
+                behind: 1,
+            }
+        );
+        assert_eq!(status.watermark(), " main* ↑2 ↓1 ");
+    }
+
+    #[test]
+    fn detached_heads_show_the_commit() {
+        let porcelain = "# branch.oid 1234567890abcdef\n# branch.head (detached)\n";
+        let status = Status::parse(porcelain).unwrap();
+        assert_eq!(status.watermark(), " 1234567 ");
+        assert!(Status::parse("").is_none());
+    }
+
+    #[test]
+    fn only_refreshes_after_output_goes_quiet() {
+        let config = Config::default();
+        let start = tokio::time::Instant::now();
+        let mut schedule = Schedule::default();
+        assert!(!schedule.is_due(&config, start));
+
+        schedule.output_changed_at = Some(start);
+        assert!(!schedule.is_due(&config, start));
+        let quiet = start + std::time::Duration::from_secs(1);
+        assert!(schedule.is_due(&config, quiet));
+        assert!(!schedule.is_due(&config, quiet));
+
+        schedule.is_requested = true;
+        assert!(!schedule.is_due(&config, quiet + std::time::Duration::from_secs(1)));
+        assert!(schedule.is_due(&config, quiet + std::time::Duration::from_secs(2)));
+    }
+}
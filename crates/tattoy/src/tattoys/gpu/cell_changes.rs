@@ -0,0 +1,182 @@
+//! The cells that text most recently appeared in, so that shaders can draw things like ripples or
+//! sparks wherever text appears, not just at the cursor.
+//!
+//! They're uploaded as a small storage buffer: a count, padded to 16 bytes, followed by
+//! `MAX_CHANGES` entries of `vec4(column, row, time, 0.0)`. The first row is the top of the
+//! terminal and the time is the `iTime` at which the text appeared.
+
+use shadow_terminal::termwiz;
+
+/// How many of the most recent changes are remembered. This must match the size of the
+/// `iCellChanges` array in `header.glsl`.
+const MAX_CHANGES: usize = 64;
+
+/// The size, in bytes, of the count and each of the changes.
+const ENTRY_SIZE: u64 = 16;
+
+/// The size, in bytes, of the storage buffer.
+pub const BUFFER_SIZE: u64 = ENTRY_SIZE * (1 + 64);
+
+/// When more than this fraction of the screen changes at once, it's treated as a redraw, like
+/// scrolling or clearing the screen, rather than text appearing.
+const REDRAW_FRACTION: f32 = 0.25;
+
+/// The cells that text most recently appeared in.
+#[derive(Default)]
+pub(crate) struct CellChanges {
+    /// Every cell as it was at the last update.
+    previous: Vec<Vec<termwiz::cell::Cell>>,
+    /// The most recent changes, oldest first.
+    changes: std::collections::VecDeque<[f32; 4]>,
+}
+
+impl CellChanges {
+    /// Compare the cells with the previous update, and remember where text has appeared. Returns
+    /// whether anything new was remembered.
+    pub fn update(&mut self, cells: Vec<Vec<termwiz::cell::Cell>>, now: f32) -> bool {
+        let is_same_size = self.previous.len() == cells.len()
+            && self
+                .previous
+                .iter()
+                .zip(&cells)
+                .all(|(previous, current)| previous.len() == current.len());
+        let appeared = if is_same_size {
+            Self::appeared(&self.previous, &cells)
+        } else {
+            Vec::new()
+        };
+
+        let total = cells.iter().map(Vec::len).sum::<usize>();
+        self.previous = cells;
+        if appeared.is_empty() || to_float(appeared.len()) > to_float(total) * REDRAW_FRACTION {
+            return false;
+        }
+
+        for (column, row) in appeared {
+            self.changes
+                .push_back([to_float(column), to_float(row), now, 0.0]);
+        }
+        while self.changes.len() > MAX_CHANGES {
+            self.changes.pop_front();
+        }
+        true
+    }
+
+    /// The positions of the cells whose text has changed to a visible glyph.
+    fn appeared(
+        previous: &[Vec<termwiz::cell::Cell>],
+        current: &[Vec<termwiz::cell::Cell>],
+    ) -> Vec<(usize, usize)> {
+        current
+            .iter()
+            .zip(previous)
+            .enumerate()
+            .flat_map(|(row, (line, previous_line))| {
+                line.iter()
+                    .zip(previous_line)
+                    .enumerate()
+                    .filter(|(_, (cell, previous_cell))| {
+                        cell.str() != previous_cell.str() && super::text_mask::is_text(cell)
+                    })
+                    .map(move |(column, _)| (column, row))
+            })
+            .collect()
+    }
+
+    /// The contents of the storage buffer.
+    fn to_bytes(&self) -> Vec<u8> {
+        let count = u32::try_from(self.changes.len()).unwrap_or_default();
+        let mut bytes = bytemuck::bytes_of(&[count, 0, 0, 0]).to_vec();
+        for change in &self.changes {
+            bytes.extend_from_slice(bytemuck::bytes_of(change));
+        }
+        bytes
+    }
+}
+
+/// Convert a cell count or coordinate to a float for the GPU.
+fn to_float(number: usize) -> f32 {
+    f32::from(u16::try_from(number).unwrap_or(u16::MAX))
+}
+
+impl super::pipeline::GPU {
+    /// Remember where text has appeared, and upload it to the GPU if anything's new.
+    pub fn update_cell_changes(&mut self, cells: Vec<Vec<termwiz::cell::Cell>>) {
+        let now = self.get_current_time();
+        if self.cell_changes.update(cells, now) {
+            self.queue
+                .write_buffer(&self.cell_changes_buffer, 0, &self.cell_changes.to_bytes());
+        }
+    }
+
+    /// The buffer descriptor for the cell changes.
+    pub const fn cell_changes_buffer_descriptor() -> wgpu::BufferDescriptor<'static> {
+        wgpu::BufferDescriptor {
+            label: Some("cell_changes_buffer"),
+            size: BUFFER_SIZE,
+            usage: wgpu::BufferUsages::STORAGE.union(wgpu::BufferUsages::COPY_DST),
+            mapped_at_creation: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn screen(lines: &[&str]) -> Vec<Vec<termwiz::cell::Cell>> {
+        lines
+            .iter()
+            .map(|line| {
+                line.chars()
+                    .map(|character| {
+                        termwiz::cell::Cell::new(
+                            character,
+                            termwiz::cell::CellAttributes::default(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn appearing_text_is_remembered() {
+        let mut changes = CellChanges::default();
+        assert!(!changes.update(screen(&["    ", "    "]), 0.0));
+        assert!(changes.update(screen(&["    ", " ab "]), 1.5));
+        assert_eq!(
+            changes.changes,
+            [[1.0, 1.0, 1.5, 0.0], [2.0, 1.0, 1.5, 0.0]]
+        );
+
+        assert!(!changes.update(screen(&["    ", " a  "]), 2.0));
+        assert_eq!(changes.changes.len(), 2);
+    }
+
+    #[test]
+    fn redraws_are_ignored() {
+        let mut changes = CellChanges::default();
+        changes.update(screen(&["    ", "    "]), 0.0);
+        assert!(!changes.update(screen(&["abcd", "    "]), 1.0));
+        assert!(!changes.update(screen(&["abcde", "     "]), 1.0));
+        assert!(changes.changes.is_empty());
+    }
+
+    #[test]
+    fn only_the_most_recent_changes_are_remembered() {
+        let blank = " ".repeat(100);
+        let mut changes = CellChanges::default();
+        changes.update(screen(&[&blank, &blank, &blank, &blank]), 0.0);
+        for index in 1..=4 {
+            let line = format!("{}{}", "x".repeat(index * 20), " ".repeat(100 - index * 20));
+            changes.update(screen(&[&line, &blank, &blank, &blank]), 0.0);
+        }
+        assert_eq!(changes.changes.len(), MAX_CHANGES);
+        assert_eq!(changes.changes.back().map(|change| change[0]), Some(79.0));
+
+        let bytes = changes.to_bytes();
+        assert_eq!(u64::try_from(bytes.len()).unwrap(), BUFFER_SIZE);
+        assert_eq!(bytes.first(), Some(&64));
+    }
+}
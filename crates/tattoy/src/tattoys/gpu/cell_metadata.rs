@@ -0,0 +1,199 @@
+//! An optional texture describing every cell of the terminal, rather than every pixel. It lets
+//! shaders do text-aware effects, like a CRT phosphor glow per glyph.
+//!
+//! Every cell is represented by 2 horizontally adjacent texels:
+//!   * The first is the cell's foreground colour.
+//!   * The second is the cell's background colour, with the cell's attribute flags encoded in its
+//!     alpha channel.
+//!
+//! Unlike `iChannel0`, the first row of the texture is the top row of the terminal.
+
+use shadow_terminal::termwiz;
+
+/// The number of texels used for each cell.
+const TEXELS_PER_CELL: u32 = 2;
+
+/// Flag for a cell containing a visible glyph.
+const FLAG_GLYPH: u8 = 1;
+/// Flag for bold text.
+const FLAG_BOLD: u8 = 1 << 1;
+/// Flag for italic text.
+const FLAG_ITALIC: u8 = 1 << 2;
+/// Flag for underlined text.
+const FLAG_UNDERLINE: u8 = 1 << 3;
+/// Flag for struck-through text.
+const FLAG_STRIKETHROUGH: u8 = 1 << 4;
+/// Flag for text with reversed colours.
+const FLAG_REVERSE: u8 = 1 << 5;
+/// Flag for blinking text.
+const FLAG_BLINK: u8 = 1 << 6;
+/// Flag for invisible text.
+const FLAG_INVISIBLE: u8 = 1 << 7;
+
+/// Build the cell metadata image for the given screen cells.
+pub(crate) fn from_cells(
+    cells: &[&[termwiz::cell::Cell]],
+    default_foreground: termwiz::color::SrgbaTuple,
+    default_background: termwiz::color::SrgbaTuple,
+) -> image::RgbaImage {
+    let width = cells.first().map_or(0, |line| line.len());
+
+    image::RgbaImage::from_fn(
+        u32::try_from(width)
+            .unwrap_or_default()
+            .saturating_mul(TEXELS_PER_CELL),
+        u32::try_from(cells.len()).unwrap_or_default(),
+        |x, y| {
+            let maybe_cell = usize::try_from(x.div_euclid(TEXELS_PER_CELL))
+                .ok()
+                .zip(usize::try_from(y).ok())
+                .and_then(|(cell_x, cell_y)| cells.get(cell_y).and_then(|line| line.get(cell_x)));
+            let Some(cell) = maybe_cell else {
+                return [0, 0, 0, 0].into();
+            };
+
+            let is_foreground_texel = x.rem_euclid(TEXELS_PER_CELL) == 0;
+            if is_foreground_texel {
+                let colour = crate::blender::Blender::extract_colour(cell.attrs().foreground())
+                    .unwrap_or(default_foreground);
+                image::Rgba(colour.to_srgb_u8().into())
+            } else {
+                let colour = crate::blender::Blender::extract_colour(cell.attrs().background())
+                    .unwrap_or(default_background);
+                let (red, green, blue, _) = colour.to_srgb_u8();
+                [red, green, blue, flags(cell)].into()
+            }
+        },
+    )
+}
+
+/// Encode a cell's attributes as bit flags.
+fn flags(cell: &termwiz::cell::Cell) -> u8 {
+    let attributes = cell.attrs();
+    let mut flags = 0;
+
+    if super::text_mask::is_text(cell) {
+        flags |= FLAG_GLYPH;
+    }
+    if attributes.intensity() == termwiz::cell::Intensity::Bold {
+        flags |= FLAG_BOLD;
+    }
+    if attributes.italic() {
+        flags |= FLAG_ITALIC;
+    }
+    if attributes.underline() != termwiz::cell::Underline::None {
+        flags |= FLAG_UNDERLINE;
+    }
+    if attributes.strikethrough() {
+        flags |= FLAG_STRIKETHROUGH;
+    }
+    if attributes.reverse() {
+        flags |= FLAG_REVERSE;
+    }
+    if attributes.blink() != termwiz::cell::Blink::None {
+        flags |= FLAG_BLINK;
+    }
+    if attributes.invisible() {
+        flags |= FLAG_INVISIBLE;
+    }
+
+    flags
+}
+
+impl super::pipeline::GPU {
+    /// Update the GPU with the current cell metadata.
+    pub fn update_cell_metadata_texture_data(&self) {
+        let (metadata_width, metadata_height) = self.cell_metadata.dimensions();
+        let expected_size = self.cell_metadata_texture.size();
+        if metadata_width != expected_size.width || metadata_height != expected_size.height {
+            return;
+        }
+
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.cell_metadata_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.cell_metadata,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * metadata_width),
+                rows_per_image: Some(metadata_height),
+            },
+            wgpu::Extent3d {
+                width: metadata_width,
+                height: metadata_height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Recreate the cell metadata texture. Most likely occurs when the user's terminal resizes.
+    pub fn recreate_cell_metadata_texture(&mut self) {
+        let image_size = self.get_image_size();
+        self.cell_metadata_texture =
+            self.device
+                .create_texture(&Self::cell_metadata_texture_descriptor(
+                    image_size.0,
+                    image_size.1,
+                ));
+    }
+
+    /// The texture descriptor for the cell metadata texture. The width and height are those of
+    /// the rendered image, not of the terminal.
+    pub fn cell_metadata_texture_descriptor(
+        width: u16,
+        height: u16,
+    ) -> wgpu::TextureDescriptor<'static> {
+        let pixels_per_line = 2;
+        wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: u32::from(width) * TEXELS_PER_CELL,
+                height: u32::from(height.div_euclid(pixels_per_line)).max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("cell_metadata_texture"),
+            view_formats: &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encodes_colours_and_flags() {
+        let mut surface = termwiz::surface::Surface::new(2, 1);
+        surface.add_changes(vec![
+            termwiz::surface::Change::Attribute(termwiz::cell::AttributeChange::Intensity(
+                termwiz::cell::Intensity::Bold,
+            )),
+            termwiz::surface::Change::Attribute(termwiz::cell::AttributeChange::Foreground(
+                termwiz::color::ColorAttribute::TrueColorWithDefaultFallback(
+                    termwiz::color::SrgbaTuple(1.0, 0.0, 0.0, 1.0),
+                ),
+            )),
+            "a".into(),
+        ]);
+        let white = termwiz::color::SrgbaTuple(1.0, 1.0, 1.0, 1.0);
+        let black = termwiz::color::SrgbaTuple(0.0, 0.0, 0.0, 1.0);
+        let metadata = from_cells(&surface.get_screen_cells(), white, black);
+
+        assert_eq!(metadata.dimensions(), (4, 1));
+        assert_eq!(metadata.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(
+            metadata.get_pixel(1, 0).0,
+            [0, 0, 0, FLAG_GLYPH | FLAG_BOLD]
+        );
+        assert_eq!(metadata.get_pixel(2, 0).0, [255, 255, 255, 255]);
+        assert_eq!(metadata.get_pixel(3, 0).0, [0, 0, 0, 0]);
+    }
+}
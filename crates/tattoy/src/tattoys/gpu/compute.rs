@@ -0,0 +1,320 @@
+//! An optional compute pass that runs before the fragment shader every frame. It's for
+//! simulations, like fluids or particles, that need to keep their state from frame to frame, much
+//! like Shadertoy's buffers.
+//!
+//! The compute shader writes to a texture that's the same size as the render, which the fragment
+//! shader reads with `iCompute()`. There are 2 of these textures, swapped every frame, so that
+//! the compute shader can also read what it wrote on the previous frame.
+
+use color_eyre::eyre::Result;
+
+/// The format of the textures that the compute shader writes to. Unlike full floats, half floats
+/// can be filtered, and they're precise enough for most simulations.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// The width and height of the compute shader's workgroups. This must match `local_size_x` and
+/// `local_size_y` in `compute_header.glsl`.
+const WORKGROUP_SIZE: u32 = 8;
+
+/// A compute shader, and the textures that it writes to.
+pub(crate) struct Compute {
+    /// Path to the compute shader file.
+    pub path: std::path::PathBuf,
+    /// The GPU compute pipeline.
+    pipeline: wgpu::ComputePipeline,
+    /// The layout of all the data that is bound to the compute shader.
+    bindgroup_layout: wgpu::BindGroupLayout,
+    /// The textures that are written to on alternate frames.
+    textures: [wgpu::Texture; 2],
+    /// Whether the second texture is the one that was written to most recently.
+    is_second_latest: bool,
+}
+
+impl Compute {
+    /// The GPU memory used by both textures.
+    pub fn memory_usage(&self) -> u64 {
+        self.textures.iter().map(super::memory::texture_bytes).sum()
+    }
+
+    /// The texture that the compute shader wrote to most recently.
+    pub const fn latest(&self) -> &wgpu::Texture {
+        let [first, second] = &self.textures;
+        if self.is_second_latest {
+            second
+        } else {
+            first
+        }
+    }
+
+    /// The texture that was written to most recently, and the texture to write to next.
+    const fn previous_and_next(&self) -> (&wgpu::Texture, &wgpu::Texture) {
+        let [first, second] = &self.textures;
+        if self.is_second_latest {
+            (second, first)
+        } else {
+            (first, second)
+        }
+    }
+
+    /// Create both textures, eg: when the user's terminal resizes. This clears the simulation.
+    fn create_textures(device: &wgpu::Device, width: u16, height: u16) -> [wgpu::Texture; 2] {
+        [
+            texture(device, width, height),
+            texture(device, width, height),
+        ]
+    }
+
+    /// Create the bind group layout that defines where the compute shader's data is located.
+    const fn bindgroup_layout() -> wgpu::BindGroupLayoutDescriptor<'static> {
+        wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("compute_bind_group_layout"),
+        }
+    }
+}
+
+/// Create a texture for the compute shader to write to. It's also used for the placeholder that
+/// the fragment shader reads when there isn't a compute shader.
+pub(crate) fn texture(device: &wgpu::Device, width: u16, height: u16) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width: u32::from(width.max(1)),
+            height: u32::from(height.max(1)),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+        label: Some("compute_texture"),
+        view_formats: &[],
+    })
+}
+
+/// The full compute shader, with Tattoy's boilerplate around the user's code. It's checked first,
+/// so that errors can be shown to the user at the lines of their own code.
+fn source(path: &std::path::Path, contents: &str, defines: &[(String, String)]) -> Result<String> {
+    let variables = include_str!("shaders/variables.glsl");
+    let header = include_str!("shaders/compute_header.glsl");
+    let footer = include_str!("shaders/compute_footer.glsl");
+    let shader = format!("{variables}\n{header}\n{contents}\n{footer}");
+
+    let name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    let preamble_lines = format!("{variables}\n{header}\n").lines().count();
+    super::shader_error::check(
+        &name,
+        contents,
+        preamble_lines,
+        &shader,
+        wgpu::naga::ShaderStage::Compute,
+        defines,
+    )?;
+
+    Ok(shader)
+}
+
+impl super::pipeline::GPU {
+    /// Use a compute shader, or stop using one. Nothing is rebuilt when it's the same shader.
+    pub async fn set_compute_shader(&mut self, path: Option<std::path::PathBuf>) -> Result<()> {
+        if self.compute.as_ref().map(|compute| &compute.path) == path.as_ref() {
+            return Ok(());
+        }
+
+        self.compute = match path {
+            Some(path) => {
+                tracing::info!("Building compute shader: {path:?}");
+                Some(self.build_compute(path).await?)
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Compile the compute shader and create everything that it needs.
+    async fn build_compute(&self, path: std::path::PathBuf) -> Result<Compute> {
+        let file = tokio::fs::read(&path).await?;
+        let shader = source(&path, &String::from_utf8_lossy(&file), &self.defines)?;
+
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Compute Shader"),
+                source: wgpu::ShaderSource::Glsl {
+                    shader: shader.into(),
+                    stage: wgpu::naga::ShaderStage::Compute,
+                    defines: self.defines.iter().cloned().collect(),
+                },
+            });
+        let bindgroup_layout = self
+            .device
+            .create_bind_group_layout(&Compute::bindgroup_layout());
+        let pipeline_layout = self
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Compute Pipeline Layout"),
+                bind_group_layouts: &[&bindgroup_layout],
+                push_constant_ranges: &[],
+            });
+        let pipeline = self
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Compute Pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some("main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            });
+
+        let (width, height) = self.get_image_size();
+        Ok(Compute {
+            path,
+            pipeline,
+            bindgroup_layout,
+            textures: Compute::create_textures(&self.device, width, height),
+            is_second_latest: false,
+        })
+    }
+
+    /// Recreate the compute shader's textures. Most likely occurs when the user's terminal
+    /// resizes.
+    pub fn recreate_compute_textures(&mut self) {
+        let (width, height) = self.get_image_size();
+        if let Some(compute) = self.compute.as_mut() {
+            compute.textures = Compute::create_textures(&self.device, width, height);
+        }
+    }
+
+    /// Run the compute shader, if there is one.
+    pub fn run_compute_pass(&mut self) {
+        let (width, height) = self.get_image_size();
+        let Some(compute) = self.compute.as_mut() else {
+            return;
+        };
+
+        let (previous, next) = compute.previous_and_next();
+        let sampler = self
+            .device
+            .create_sampler(&wgpu::SamplerDescriptor::default());
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &compute.bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.variables_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &self
+                            .ichannel_texture
+                            .create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(
+                        &previous.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(
+                        &next.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+            label: Some("compute_bind_group"),
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Compute Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&compute.pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                u32::from(width).div_ceil(WORKGROUP_SIZE),
+                u32::from(height).div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        compute.is_second_latest = !compute.is_second_latest;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn broken_compute_shaders_are_caught_before_the_gpu() {
+        let broken = "void mainCompute(inout vec4 state, ivec2 pixel) {\n    state = ;\n}";
+        let error = source(std::path::Path::new("/shaders/sim.glsl"), broken, &[]).unwrap_err();
+        let error = error
+            .downcast_ref::<super::super::shader_error::ShaderError>()
+            .unwrap();
+        assert!(error.to_string().starts_with("Couldn't compile sim.glsl"));
+    }
+}
@@ -13,17 +13,29 @@ impl super::pipeline::GPU {
             reason = "It's internal so we'll know when there's new arms"
         )]
         match message {
-            crate::run::Protocol::Output(_) | crate::run::Protocol::Config(_) => {
+            crate::run::Protocol::Output(_) => {
                 self.protocol.send(crate::run::Protocol::Repaint)?;
             }
+            crate::run::Protocol::Config(config) => {
+                self.set_pixel_aspect(config.pixel_aspect);
+                self.protocol.send(crate::run::Protocol::Repaint)?;
+            }
+            crate::run::Protocol::Bell => self.ring_bell(),
+            crate::run::Protocol::CommandFinished(command) => {
+                self.finish_command(command.exit_status);
+            }
+            crate::run::Protocol::Focus(is_focused) => self.change_focus(*is_focused),
             crate::run::Protocol::Resize { width, height } => {
                 self.update_resolution(*width, height * 2)?;
             }
-            crate::run::Protocol::Input(input) => {
-                if let termwiz::input::InputEvent::Mouse(mouse) = &input.event {
+            crate::run::Protocol::Input(input) => match &input.event {
+                termwiz::input::InputEvent::Mouse(mouse) => {
                     self.update_mouse_position(mouse.x, mouse.y);
                 }
-            }
+                termwiz::input::InputEvent::Key(key) => self.keyboard.press(&key.key),
+                _ => (),
+            },
+            crate::run::Protocol::KeyReleased(key) => self.keyboard.release(&key.key),
             crate::run::Protocol::KeybindEvent(event) => {
                 if matches!(event, crate::config::input::KeybindingAction::ShaderPrev) {
                     self.cycle_shader(false).await?;
@@ -80,8 +92,7 @@ impl super::pipeline::GPU {
         let shader_path = shader_directory.join(new_shader.clone());
         tracing::info!("Changing shader to: {new_shader:?}");
 
-        self.shader_path = shader_path;
-        self.build_pipeline().await?;
+        self.switch_shader(shader_path).await?;
         self.protocol.send(crate::run::Protocol::Repaint)?;
 
         Ok(())
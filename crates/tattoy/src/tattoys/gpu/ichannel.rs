@@ -1,41 +1,87 @@
 //! Support for the Shader Toy convention of a `iChannel0` buffer. In our case it typically
 //! contains a pixel representation of the TTY.
+//!
+//! Most of the time only a few lines of the TTY change between repaints, so only the rows of the
+//! texture that changed are uploaded.
 
 impl super::pipeline::GPU {
     /// Update the GPU with the current state of the terminal as RGB values.
-    pub fn update_ichannel_texture_data(&self) {
+    pub fn update_ichannel_texture_data(&mut self) {
+        let height = self.tty_pixels.height();
+        if self.write_ichannel_rows(0..height) {
+            self.is_ichannel_texture_new = false;
+        }
+    }
+
+    /// Update the GPU with only the rows of the terminal's pixels that are different from the
+    /// previously uploaded pixels.
+    pub fn update_changed_ichannel_texture_data(&mut self, previous: &image::RgbaImage) {
+        if self.is_ichannel_texture_new || previous.dimensions() != self.tty_pixels.dimensions() {
+            self.update_ichannel_texture_data();
+            return;
+        }
+
+        for rows in changed_rows(previous, &self.tty_pixels) {
+            // If the texture missed an update then it's no longer the same as `previous`.
+            if !self.write_ichannel_rows(rows) {
+                self.is_ichannel_texture_new = true;
+                return;
+            }
+        }
+    }
+
+    /// Upload some consecutive rows of the TTY's pixels to the iChannel texture. Returns whether
+    /// the pixels were the right size to upload.
+    fn write_ichannel_rows(&self, rows: std::ops::Range<u32>) -> bool {
         let tty_image_width = self.tty_pixels.dimensions().0;
         let tty_image_height = self.tty_pixels.dimensions().1;
         let output_image_size = self.get_image_size();
         if tty_image_width != u32::from(output_image_size.0)
             || tty_image_height != u32::from(output_image_size.1)
         {
-            return;
+            return false;
         }
 
+        let bytes_per_row = 4 * tty_image_width;
+        let Ok(start) = usize::try_from(rows.start * bytes_per_row) else {
+            return false;
+        };
+        let Ok(end) = usize::try_from(rows.end * bytes_per_row) else {
+            return false;
+        };
+        let Some(data) = self.tty_pixels.get(start..end) else {
+            return false;
+        };
+        let height = rows.end - rows.start;
+
         tracing::debug!(
-            "Updating GPU with new TTY image data: {}",
-            self.tty_pixels.len()
+            "Updating GPU with new TTY image data: rows {rows:?}, {} bytes",
+            data.len()
         );
         self.queue.write_texture(
             wgpu::TexelCopyTextureInfo {
                 texture: &self.ichannel_texture,
                 mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: rows.start,
+                    z: 0,
+                },
                 aspect: wgpu::TextureAspect::All,
             },
-            &self.tty_pixels,
+            data,
             wgpu::TexelCopyBufferLayout {
                 offset: 0,
-                bytes_per_row: Some(4 * tty_image_width),
-                rows_per_image: Some(tty_image_height),
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(height),
             },
             wgpu::Extent3d {
                 width: tty_image_width,
-                height: tty_image_height,
+                height,
                 depth_or_array_layers: 1,
             },
         );
+        true
     }
 
     /// Recreate the iChannel texture. Most likely occurs when the user's terminal resizes.
@@ -46,6 +92,7 @@ impl super::pipeline::GPU {
         );
 
         let image_size = self.get_image_size();
+        self.is_ichannel_texture_new = true;
         self.ichannel_texture = self
             .device
             .create_texture(&Self::ichannel_texture_descriptor(
@@ -75,3 +122,45 @@ impl super::pipeline::GPU {
         }
     }
 }
+
+/// The runs of consecutive rows that are different between 2 images of the same size.
+fn changed_rows(
+    previous: &image::RgbaImage,
+    current: &image::RgbaImage,
+) -> Vec<std::ops::Range<u32>> {
+    let row_bytes = usize::try_from(current.width())
+        .unwrap_or_default()
+        .saturating_mul(crate::pixels::CHANNELS)
+        .max(1);
+
+    let mut runs: Vec<std::ops::Range<u32>> = Vec::new();
+    let rows = previous
+        .chunks_exact(row_bytes)
+        .zip(current.chunks_exact(row_bytes));
+    for (y, (previous_row, current_row)) in (0..).zip(rows) {
+        if previous_row == current_row {
+            continue;
+        }
+        match runs.last_mut() {
+            Some(run) if run.end == y => run.end = y + 1,
+            _ => runs.push(y..y + 1),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_runs_of_changed_rows() {
+        let previous = image::RgbaImage::new(2, 6);
+        let mut current = previous.clone();
+        for y in [1, 2, 4] {
+            current.put_pixel(1, y, [1, 2, 3, 4].into());
+        }
+        assert_eq!(changed_rows(&previous, &current), vec![1..3, 4..5]);
+        assert!(changed_rows(&previous, &previous).is_empty());
+    }
+}
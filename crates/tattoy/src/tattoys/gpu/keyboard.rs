@@ -0,0 +1,201 @@
+//! A Shadertoy-style keyboard texture. It's 256 pixels wide, one for each key code, and 3 pixels
+//! high:
+//!   * Row 0: the key is being held down.
+//!   * Row 1: the key was pressed since the last frame.
+//!   * Row 2: the key toggles on and off with every press.
+//!
+//! Key codes are the same as Shadertoy's, which are JavaScript's `keyCode`s, eg: `65` is `A` and
+//! `37` is the left arrow.
+//!
+//! Only the kitty keyboard protocol reports key releases. Until we see a release, keys are
+//! assumed to be held for only a single frame.
+
+use shadow_terminal::termwiz;
+
+/// The number of key codes.
+const KEYS: u32 = 256;
+
+/// The row of keys that are being held down.
+const HELD_ROW: u32 = 0;
+
+/// The row of keys that were pressed since the last frame.
+const PRESSED_ROW: u32 = 1;
+
+/// The row of keys that toggle with every press.
+const TOGGLED_ROW: u32 = 2;
+
+/// The value of a pixel whose key is on.
+const ON: u8 = 255;
+
+/// The state of every key.
+#[derive(Debug)]
+pub(crate) struct Keyboard {
+    /// One pixel for every key in each row.
+    pub image: image::GrayImage,
+    /// Whether we've seen a key release. If not, then we can't know how long keys are held for.
+    is_release_reported: bool,
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self {
+            image: image::GrayImage::new(KEYS, TOGGLED_ROW + 1),
+            is_release_reported: false,
+        }
+    }
+}
+
+impl Keyboard {
+    /// A key was pressed, or is repeating.
+    pub fn press(&mut self, key: &termwiz::input::KeyCode) {
+        let Some(code) = key_code(key) else {
+            return;
+        };
+        let code = u32::from(code);
+        let is_held = self.image.get_pixel(code, HELD_ROW).0 == [ON];
+        if !is_held {
+            let toggled = self.image.get_pixel(code, TOGGLED_ROW).0 == [ON];
+            self.set(code, TOGGLED_ROW, !toggled);
+            self.set(code, PRESSED_ROW, true);
+        }
+        self.set(code, HELD_ROW, true);
+    }
+
+    /// A key was released.
+    pub fn release(&mut self, key: &termwiz::input::KeyCode) {
+        self.is_release_reported = true;
+        if let Some(code) = key_code(key) {
+            self.set(code.into(), HELD_ROW, false);
+        }
+    }
+
+    /// Forget the key presses that the last frame has already seen.
+    pub fn end_frame(&mut self) {
+        for code in 0..KEYS {
+            self.set(code, PRESSED_ROW, false);
+            if !self.is_release_reported {
+                self.set(code, HELD_ROW, false);
+            }
+        }
+    }
+
+    /// Set a key on or off.
+    fn set(&mut self, code: u32, row: u32, is_on: bool) {
+        self.image
+            .put_pixel(code, row, [if is_on { ON } else { 0 }].into());
+    }
+}
+
+/// The Shadertoy key code of a key.
+fn key_code(key: &termwiz::input::KeyCode) -> Option<u8> {
+    use termwiz::input::KeyCode;
+
+    let code = match *key {
+        KeyCode::Backspace => 8,
+        KeyCode::Tab => 9,
+        KeyCode::Enter => 13,
+        KeyCode::Shift | KeyCode::LeftShift | KeyCode::RightShift => 16,
+        KeyCode::Control | KeyCode::LeftControl | KeyCode::RightControl => 17,
+        KeyCode::Alt | KeyCode::LeftAlt | KeyCode::RightAlt => 18,
+        KeyCode::Escape => 27,
+        KeyCode::PageUp => 33,
+        KeyCode::PageDown => 34,
+        KeyCode::End => 35,
+        KeyCode::Home => 36,
+        KeyCode::LeftArrow => 37,
+        KeyCode::UpArrow => 38,
+        KeyCode::RightArrow => 39,
+        KeyCode::DownArrow => 40,
+        KeyCode::Insert => 45,
+        KeyCode::Delete => 46,
+        KeyCode::Function(number @ 1..=12) => 111 + number,
+        KeyCode::Char(' ') => 32,
+        KeyCode::Char(character) if character.is_ascii_alphanumeric() => {
+            return u8::try_from(character.to_ascii_uppercase()).ok();
+        }
+        _ => return None,
+    };
+    Some(code)
+}
+
+impl super::pipeline::GPU {
+    /// Update the GPU with the current state of the keyboard.
+    pub fn update_keyboard_texture_data(&self) {
+        let (width, height) = self.keyboard.image.dimensions();
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.keyboard_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.keyboard.image,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// The texture descriptor for the keyboard texture. Its size never changes.
+    pub fn keyboard_texture_descriptor() -> wgpu::TextureDescriptor<'static> {
+        wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: KEYS,
+                height: TOGGLED_ROW + 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("keyboard_texture"),
+            view_formats: &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn is_on(keyboard: &Keyboard, code: u32, row: u32) -> bool {
+        keyboard.image.get_pixel(code, row).0 == [ON]
+    }
+
+    #[test]
+    fn legacy_presses_only_last_a_frame() {
+        let mut keyboard = Keyboard::default();
+        keyboard.press(&termwiz::input::KeyCode::Char('a'));
+        assert!(is_on(&keyboard, 65, HELD_ROW));
+        assert!(is_on(&keyboard, 65, PRESSED_ROW));
+        assert!(is_on(&keyboard, 65, TOGGLED_ROW));
+
+        keyboard.end_frame();
+        assert!(!is_on(&keyboard, 65, HELD_ROW));
+        assert!(!is_on(&keyboard, 65, PRESSED_ROW));
+        assert!(is_on(&keyboard, 65, TOGGLED_ROW));
+    }
+
+    #[test]
+    fn keys_are_held_until_released() {
+        let mut keyboard = Keyboard::default();
+        keyboard.release(&termwiz::input::KeyCode::LeftShift);
+        keyboard.press(&termwiz::input::KeyCode::LeftArrow);
+        keyboard.end_frame();
+        keyboard.press(&termwiz::input::KeyCode::LeftArrow);
+        assert!(is_on(&keyboard, 37, HELD_ROW));
+        assert!(!is_on(&keyboard, 37, PRESSED_ROW));
+
+        keyboard.release(&termwiz::input::KeyCode::LeftArrow);
+        assert!(!is_on(&keyboard, 37, HELD_ROW));
+        assert!(is_on(&keyboard, 37, TOGGLED_ROW));
+    }
+}
@@ -0,0 +1,201 @@
+//! Keeping the GPU memory that shaders use within a budget.
+//!
+//! Every GPU pipeline has textures and buffers that are the size of the user's terminal, and
+//! compute shaders double that again. On very large terminals, with several shaders, that can run
+//! a GPU out of memory. So after every render each pipeline reports how much it's using. When the
+//! total is over `render.gpu_memory_budget`, the pipelines that haven't rendered for a while, eg:
+//! because a scene switched them off, are asked to evict their resources. They're recreated the
+//! next time the pipeline renders. If that still isn't enough then the user is warned, once.
+
+/// How long a pipeline has to go without rendering before its resources can be evicted. Evicting
+/// a pipeline that's still rendering would only make it recreate everything on its next frame.
+const IDLE_BEFORE_EVICTION: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// The number of bytes in a megabyte, which is how the budget is configured.
+pub const BYTES_PER_MEGABYTE: u64 = 1024 * 1024;
+
+/// A pipeline's use of GPU memory.
+#[derive(Debug, Clone, Copy)]
+struct Usage {
+    /// The bytes that its textures and buffers take.
+    bytes: u64,
+    /// When it last rendered.
+    last_used: std::time::Instant,
+}
+
+/// The GPU memory that every pipeline uses.
+#[derive(Debug, Default)]
+pub(crate) struct Budget {
+    /// The usage of each pipeline, by its tattoy's ID.
+    usage: std::collections::BTreeMap<String, Usage>,
+    /// The pipelines that have been asked to evict their resources, but haven't yet.
+    evictions: std::collections::BTreeSet<String>,
+    /// Whether the user has already been warned about the budget being exceeded.
+    is_warned: bool,
+}
+
+impl Budget {
+    /// A pipeline just rendered, with all its resources.
+    pub fn rendered(&mut self, id: &str, bytes: u64, now: std::time::Instant) {
+        self.evictions.remove(id);
+        self.usage.insert(
+            id.to_owned(),
+            Usage {
+                bytes,
+                last_used: now,
+            },
+        );
+    }
+
+    /// A pipeline evicted its resources, or stopped completely.
+    pub fn evicted(&mut self, id: &str, bytes: u64) {
+        if let Some(usage) = self.usage.get_mut(id) {
+            usage.bytes = bytes;
+        }
+    }
+
+    /// Whether a pipeline has been asked to evict its resources. It's only asked once.
+    pub fn take_eviction(&mut self, id: &str) -> bool {
+        self.evictions.remove(id)
+    }
+
+    /// The total bytes used by every pipeline.
+    pub fn total(&self) -> u64 {
+        self.usage.values().map(|usage| usage.bytes).sum()
+    }
+
+    /// Ask the least recently used pipelines to evict their resources until the total is within
+    /// the budget. Returns the total, when the user should be warned that it can't be.
+    pub fn enforce(&mut self, limit: u64, now: std::time::Instant) -> Option<u64> {
+        let mut total = self.total();
+        if total <= limit {
+            self.is_warned = false;
+            return None;
+        }
+
+        let mut idle: Vec<(&String, &Usage)> = self
+            .usage
+            .iter()
+            .filter(|(id, usage)| {
+                usage.bytes > 0
+                    && !self.evictions.contains(*id)
+                    && now.duration_since(usage.last_used) > IDLE_BEFORE_EVICTION
+            })
+            .collect();
+        idle.sort_by_key(|(_, usage)| usage.last_used);
+        let mut evictions = Vec::new();
+        for (id, usage) in idle {
+            if total <= limit {
+                break;
+            }
+            tracing::debug!("Asking '{id}' to evict {} bytes of GPU memory", usage.bytes);
+            evictions.push(id.clone());
+            total = total.saturating_sub(usage.bytes);
+        }
+        self.evictions.extend(evictions);
+
+        if total <= limit || self.is_warned {
+            return None;
+        }
+        self.is_warned = true;
+        Some(total)
+    }
+}
+
+/// The bytes that a texture takes on the GPU.
+pub(crate) fn texture_bytes(texture: &wgpu::Texture) -> u64 {
+    let size = texture.size();
+    let bytes_per_texel = texture.format().block_copy_size(None).unwrap_or(4);
+    u64::from(size.width)
+        * u64::from(size.height)
+        * u64::from(size.depth_or_array_layers)
+        * u64::from(bytes_per_texel)
+}
+
+impl super::pipeline::GPU {
+    /// The GPU memory used by all of the pipeline's textures and buffers.
+    pub fn memory_usage(&self) -> u64 {
+        let textures = [
+            &self.ichannel_texture,
+            &self.text_mask_texture,
+            &self.cell_metadata_texture,
+            &self.keyboard_texture,
+            &self.output_texture,
+            &self.empty_compute_texture,
+        ];
+        let buffers = [
+            &self.output_buffer,
+            &self.variables_buffer,
+            &self.cell_changes_buffer,
+        ];
+        textures.into_iter().map(texture_bytes).sum::<u64>()
+            + buffers.into_iter().map(wgpu::Buffer::size).sum::<u64>()
+            + self
+                .compute
+                .as_ref()
+                .map_or(0, super::compute::Compute::memory_usage)
+    }
+
+    /// Whether the pipeline's resources have been evicted, and need recreating before rendering.
+    pub const fn is_evicted(&self) -> bool {
+        self.evicted_resolution.is_some()
+    }
+
+    /// Shrink all the pipeline's resources down to a single pixel, to free the GPU memory that
+    /// they use.
+    pub fn evict(&mut self) -> color_eyre::eyre::Result<()> {
+        let resolution = self.get_image_size();
+        tracing::info!("Evicting GPU resources of {resolution:?} pipeline");
+        self.update_resolution(1, 1)?;
+        self.evicted_resolution = Some(resolution);
+        Ok(())
+    }
+
+    /// Recreate the pipeline's evicted resources at their original size.
+    pub fn restore(&mut self) -> color_eyre::eyre::Result<()> {
+        let Some((width, height)) = self.evicted_resolution else {
+            return Ok(());
+        };
+        tracing::info!("Restoring evicted GPU resources at {width}x{height}");
+        self.update_resolution(width, height)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn idle_pipelines_are_evicted_oldest_first() {
+        let start = std::time::Instant::now();
+        let now = start + IDLE_BEFORE_EVICTION * 3;
+        let mut budget = Budget::default();
+        budget.rendered("shader_0", 100, start);
+        budget.rendered("shader_1", 100, start + IDLE_BEFORE_EVICTION);
+        budget.rendered("crt", 100, now);
+
+        assert_eq!(budget.enforce(250, now), None);
+        assert!(budget.take_eviction("shader_0"));
+        assert!(!budget.take_eviction("shader_1"));
+        assert!(!budget.take_eviction("shader_0"));
+
+        budget.evicted("shader_0", 1);
+        assert_eq!(budget.total(), 201);
+        assert_eq!(budget.enforce(250, now), None);
+    }
+
+    #[test]
+    fn pipelines_in_use_are_never_evicted_but_the_user_is_warned_once() {
+        let now = std::time::Instant::now();
+        let mut budget = Budget::default();
+        budget.rendered("shader_0", 100, now);
+        budget.rendered("crt", 100, now);
+
+        assert_eq!(budget.enforce(150, now), Some(200));
+        assert_eq!(budget.enforce(150, now), None);
+        assert!(!budget.take_eviction("shader_0"));
+
+        assert_eq!(budget.enforce(300, now), None);
+        assert_eq!(budget.enforce(150, now), Some(200));
+    }
+}
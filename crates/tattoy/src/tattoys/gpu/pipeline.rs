@@ -3,6 +3,16 @@
 use color_eyre::eyre::{ContextCompat as _, Result};
 use wgpu::util::DeviceExt as _;
 
+/// The slowest that a shader's time can run.
+const MINIMUM_SPEED: f32 = 0.01;
+
+/// The preprocessor define that renders a shader at a fixed aspect ratio, see `footer.glsl`.
+pub const LETTERBOX_DEFINE: &str = "LETTERBOX_ASPECT";
+
+/// The preprocessor define that moves a shader's output along with the text when the user scrolls
+/// back through the scrollback, see `footer.glsl`.
+pub const FOLLOW_SCROLLBACK_DEFINE: &str = "FOLLOW_SCROLLBACK";
+
 /// Common variables used by Shadertoy shaders.
 #[expect(
     non_snake_case,
@@ -15,7 +25,7 @@ use wgpu::util::DeviceExt as _;
 #[repr(C)]
 #[derive(Default, Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Variables {
-    /// The dimensions of the TTY.
+    /// The dimensions of the TTY, in pixels. Like Shadertoy, `z` is the aspect ratio of a pixel.
     pub iResolution: [f32; 3],
     /// Padding.
     _padding1: u32,
@@ -47,8 +57,92 @@ pub struct Variables {
 
     /// The time at which the animated cursor last changed.
     iTimeCursorChange: f32,
+    /// The time at which the visual bell last rang.
+    iTimeBell: f32,
+    /// Padding.
+    _padding3: [u32; 2],
+
+    /// The position and size of the cursor, in the same format as `iCurrentCursor`, as it
+    /// smoothly moves from cell to cell.
+    iInterpolatedCursor: [f32; 4],
+
+    /// The time at which the shell last finished running a command.
+    iTimeCommandFinished: f32,
+    /// The exit status of the last command that the shell finished running.
+    iCommandExitStatus: i32,
+    /// The time at which the user's terminal last gained or lost focus.
+    iTimeFocusChange: f32,
+    /// Whether the user's terminal has focus, `1` when it does and `0` when it doesn't.
+    iFocused: i32,
+
+    /// Whether the user prefers reduced motion, `1` when they do and `0` when they don't.
+    iReducedMotion: i32,
+    /// How far, in pixels, the user has scrolled back through the scrollback.
+    iScrollOffset: f32,
     /// Padding.
-    _padding3: [u32; 3],
+    _padding4: [u32; 2],
+}
+
+/// A handle to the GPU. Requesting a device is slow and uses a lot of GPU memory, so it's only
+/// done once and then shared between every pipeline.
+#[derive(Clone)]
+pub(crate) struct Device {
+    /// The `wgpu` device.
+    pub device: std::sync::Arc<wgpu::Device>,
+    /// The GPU render queue.
+    pub queue: std::sync::Arc<wgpu::Queue>,
+    /// Whether the device has been lost, eg: after a driver reset, or ran out of memory. A lost
+    /// device never works again, so it has to be replaced, along with every pipeline built on it.
+    pub is_lost: std::sync::Arc<core::sync::atomic::AtomicBool>,
+}
+
+impl Device {
+    /// Request a device from the first available GPU adapter.
+    pub async fn request() -> Result<Self> {
+        tracing::info!("Requesting GPU device");
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .context("Couldn't get GPU adapter")?;
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .await?;
+
+        let is_lost = std::sync::Arc::new(core::sync::atomic::AtomicBool::new(false));
+        let is_lost_for_callback = std::sync::Arc::clone(&is_lost);
+        device.set_device_lost_callback(move |reason, message| {
+            tracing::error!("GPU device lost ({reason:?}): {message}");
+            is_lost_for_callback.store(true, core::sync::atomic::Ordering::Relaxed);
+        });
+        // `wgpu`'s default is to panic, but running out of memory is recoverable by starting
+        // again with a new device.
+        let is_lost_for_handler = std::sync::Arc::clone(&is_lost);
+        device.on_uncaptured_error(Box::new(move |error| {
+            tracing::error!("GPU error: {error}");
+            if !matches!(error, wgpu::Error::Validation { .. }) {
+                is_lost_for_handler.store(true, core::sync::atomic::Ordering::Relaxed);
+            }
+        }));
+
+        Ok(Self {
+            device: std::sync::Arc::new(device),
+            queue: std::sync::Arc::new(queue),
+            is_lost,
+        })
+    }
+
+    /// Whether the device has been lost and needs replacing.
+    pub fn is_lost(&self) -> bool {
+        self.is_lost.load(core::sync::atomic::Ordering::Relaxed)
+    }
 }
 
 /// Code for talking to the GPU.
@@ -58,13 +152,21 @@ pub(crate) struct GPU {
 
     /// Path to the current shader file.
     pub shader_path: std::path::PathBuf,
+    /// Shader code that's compiled into Tattoy. When set, it's used instead of `shader_path`.
+    builtin_source: Option<&'static str>,
+    /// Preprocessor defines for the fragment shader, eg: for configuring built-in shaders.
+    pub defines: Vec<(String, String)>,
     /// The time at which rendering began.
     started: std::time::Instant,
+    /// How fast the shader's time runs, `1.0` is the same speed as the wall time.
+    speed: f32,
 
-    /// The `wgpu` device.
-    pub device: wgpu::Device,
-    /// The GPU render queue.
-    pub queue: wgpu::Queue,
+    /// The `wgpu` device. Shared with all the other pipelines.
+    pub device: std::sync::Arc<wgpu::Device>,
+    /// The GPU render queue. Shared with all the other pipelines.
+    pub queue: std::sync::Arc<wgpu::Queue>,
+    /// Whether the device has been lost, see `Device::is_lost`.
+    is_device_lost: std::sync::Arc<core::sync::atomic::AtomicBool>,
 
     /// The layout of all the data that is bound to the shader.
     bindgroup_layout: wgpu::BindGroupLayout,
@@ -72,25 +174,58 @@ pub(crate) struct GPU {
     /// Useful varibale data for shaders. Eg, mouse coordinates, wall time, etc
     pub variables: Variables,
     /// The buffer containing shader variable data.
-    variables_buffer: wgpu::Buffer,
+    pub variables_buffer: wgpu::Buffer,
 
     /// The output texture descriptor
     output_texture_descriptor: wgpu::TextureDescriptor<'static>,
     /// The texture on which the final render is placed.
-    output_texture: wgpu::Texture,
+    pub output_texture: wgpu::Texture,
     /// The raw data for the final render.
-    output_buffer: wgpu::Buffer,
+    pub output_buffer: wgpu::Buffer,
 
     /// The texture for the contents of the TTY.
     pub ichannel_texture: wgpu::Texture,
+    /// The texture marking which pixels are covered by the TTY's text.
+    pub text_mask_texture: wgpu::Texture,
+    /// The texture describing the colours and attributes of every cell of the TTY.
+    pub cell_metadata_texture: wgpu::Texture,
+    /// The texture of which keys are pressed.
+    pub keyboard_texture: wgpu::Texture,
+    /// The storage buffer of the cells that text recently appeared in.
+    pub cell_changes_buffer: wgpu::Buffer,
+    /// The optional compute shader that runs before every render, see `super::compute`.
+    pub compute: Option<super::compute::Compute>,
+    /// What `iCompute()` reads when there isn't a compute shader.
+    pub empty_compute_texture: wgpu::Texture,
+    /// The size that the pipeline's resources were before they were evicted to save GPU memory,
+    /// see `super::memory`.
+    pub evicted_resolution: Option<(u16, u16)>,
 
     /// The GPU render pipeline.
     pipeline: Option<wgpu::RenderPipeline>,
 
+    /// How long, in seconds, to crossfade between shaders when switching to a new one.
+    pub transition_duration: f32,
+    /// The crossfade from the previous shader, if we're in the middle of one.
+    transition: Option<super::transition::Transition>,
+
     /// We keep a copy of the TTY pixels before it's uploaded so we can compare it with the final
     /// rendered image. This allows us to only apply the differences to the user's terminal,
     /// which helps remove certain after-image artefacts.
     pub tty_pixels: image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    /// Whether the iChannel texture has been created since `tty_pixels` was last uploaded. If so
+    /// then it needs a complete upload rather than just the rows that changed.
+    pub is_ichannel_texture_new: bool,
+    /// The latest text mask, see `super::text_mask`.
+    pub text_mask: image::GrayImage,
+    /// The latest cell metadata, see `super::cell_metadata`.
+    pub cell_metadata: image::RgbaImage,
+    /// The state of every key, see `super::keyboard`.
+    pub keyboard: super::keyboard::Keyboard,
+    /// Where text has recently appeared, see `super::cell_changes`.
+    pub cell_changes: super::cell_changes::CellChanges,
+    /// Why the current shader didn't compile, if it didn't. See `super::shader_error`.
+    pub shader_error: Option<super::shader_error::ShaderError>,
 }
 
 impl GPU {
@@ -100,31 +235,64 @@ impl GPU {
         width: u16,
         height: u16,
         protocol: tokio::sync::broadcast::Sender<crate::run::Protocol>,
+        shared_device: Device,
+    ) -> Result<Self> {
+        let mut gpu = Self::initialise(shader_path, width, height, protocol, shared_device)?;
+        if let Err(error) = gpu.build_pipeline().await {
+            gpu.keep_shader_error(error)?;
+        }
+        Ok(gpu)
+    }
+
+    /// Instantiate with shader code that's compiled into Tattoy, rather than read from a file.
+    pub async fn new_builtin(
+        source: &'static str,
+        defines: Vec<(String, String)>,
+        width: u16,
+        height: u16,
+        protocol: tokio::sync::broadcast::Sender<crate::run::Protocol>,
+        shared_device: Device,
+    ) -> Result<Self> {
+        let mut gpu = Self::initialise(
+            std::path::PathBuf::new(),
+            width,
+            height,
+            protocol,
+            shared_device,
+        )?;
+        gpu.builtin_source = Some(source);
+        gpu.defines = defines;
+        gpu.build_pipeline().await?;
+        Ok(gpu)
+    }
+
+    /// Setup all the GPU resources, apart from the render pipeline itself.
+    fn initialise(
+        shader_path: std::path::PathBuf,
+        width: u16,
+        height: u16,
+        protocol: tokio::sync::broadcast::Sender<crate::run::Protocol>,
+        shared_device: Device,
     ) -> Result<Self> {
         tracing::info!(
             "Initialising GPU pipeline for {shader_path:?} with dimensions {width}x{height}"
         );
 
         let variables = Variables {
-            iResolution: [width.into(), height.into(), 0.0],
+            iResolution: [width.into(), height.into(), 1.0],
+            // So that shaders don't think that any events happened at startup.
+            iTimeBell: f32::MIN,
+            iTimeCommandFinished: f32::MIN,
+            iTimeFocusChange: f32::MIN,
+            iFocused: 1,
             ..Default::default()
         };
 
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: None,
-                force_fallback_adapter: false,
-            })
-            .await
-            .context("Couldn't get GPU adapter")?;
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default(), None)
-            .await?;
+        let Device {
+            device,
+            queue,
+            is_lost: is_device_lost,
+        } = shared_device;
 
         let output_texture_descriptor =
             Self::output_texture_descriptor(width.into(), height.into());
@@ -144,14 +312,25 @@ impl GPU {
 
         let ichannel_texture =
             device.create_texture(&Self::ichannel_texture_descriptor(width, height));
-        let mut gpu = Self {
+        let text_mask_texture =
+            device.create_texture(&Self::text_mask_texture_descriptor(width, height));
+        let cell_metadata_texture =
+            device.create_texture(&Self::cell_metadata_texture_descriptor(width, height));
+        let keyboard_texture = device.create_texture(&Self::keyboard_texture_descriptor());
+        let cell_changes_buffer = device.create_buffer(&Self::cell_changes_buffer_descriptor());
+        let empty_compute_texture = super::compute::texture(&device, 1, 1);
+        Ok(Self {
             protocol,
 
             shader_path,
+            builtin_source: None,
+            defines: Vec::new(),
             started: std::time::Instant::now(),
+            speed: 1.0,
 
             device,
             queue,
+            is_device_lost,
 
             variables,
             variables_buffer,
@@ -162,15 +341,34 @@ impl GPU {
             output_buffer,
 
             ichannel_texture,
+            text_mask_texture,
+            cell_metadata_texture,
+            keyboard_texture,
+            cell_changes_buffer,
+            compute: None,
+            empty_compute_texture,
+            evicted_resolution: None,
 
             pipeline: None,
 
-            tty_pixels: image::ImageBuffer::default(),
-        };
+            transition_duration: 0.0,
+            transition: None,
 
-        gpu.build_pipeline().await?;
+            tty_pixels: image::ImageBuffer::default(),
+            is_ichannel_texture_new: true,
+            text_mask: image::GrayImage::default(),
+            cell_metadata: image::RgbaImage::default(),
+            keyboard: super::keyboard::Keyboard::default(),
+            cell_changes: super::cell_changes::CellChanges::default(),
+            shader_error: None,
+        })
+    }
 
-        Ok(gpu)
+    /// Whether the GPU device has been lost, in which case nothing rendered with it can be trusted
+    /// and the pipeline has to be rebuilt on a new device.
+    pub fn is_device_lost(&self) -> bool {
+        self.is_device_lost
+            .load(core::sync::atomic::Ordering::Relaxed)
     }
 
     /// The output texture descriptor.
@@ -253,6 +451,56 @@ impl GPU {
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
             ],
             label: Some("bind_group_layout"),
         }
@@ -322,11 +570,113 @@ impl GPU {
         Ok(())
     }
 
+    /// Change to a new shader. If a transition duration is set then the old shader keeps rendering
+    /// whilst it's crossfaded into the new one.
+    pub async fn switch_shader(&mut self, shader_path: std::path::PathBuf) -> Result<()> {
+        self.shader_path = shader_path;
+        self.builtin_source = None;
+        self.rebuild_with_transition().await
+    }
+
+    /// Change to shader code that's compiled into Tattoy, crossfading in the same way as
+    /// `switch_shader()`.
+    pub async fn switch_builtin_shader(
+        &mut self,
+        source: &'static str,
+        defines: Vec<(String, String)>,
+    ) -> Result<()> {
+        self.builtin_source = Some(source);
+        self.defines = defines;
+        self.rebuild_with_transition().await
+    }
+
+    /// Render the shader into a box of a fixed aspect ratio, as the user sees it, in the middle of
+    /// the terminal. `None` stretches the shader over the whole terminal.
+    pub async fn set_letterbox(&mut self, maybe_aspect_ratio: Option<f32>) -> Result<()> {
+        let value = maybe_aspect_ratio
+            .filter(|aspect_ratio| *aspect_ratio > 0.0)
+            .map(|aspect_ratio| format!("{aspect_ratio:?}"));
+        self.set_define(LETTERBOX_DEFINE, value).await
+    }
+
+    /// Move the shader's output down with the text when the user scrolls back, so that effects
+    /// that decorate particular lines stay with them.
+    pub async fn set_follow_scrollback(&mut self, is_following: bool) -> Result<()> {
+        self.set_define(
+            FOLLOW_SCROLLBACK_DEFINE,
+            is_following.then(|| "1".to_owned()),
+        )
+        .await
+    }
+
+    /// Set, or remove, one of the fragment shader's preprocessor defines. The shader is only
+    /// rebuilt when the define actually changes.
+    async fn set_define(&mut self, name: &str, maybe_value: Option<String>) -> Result<()> {
+        let wanted = maybe_value.map(|value| (name.to_owned(), value));
+        let current = self
+            .defines
+            .iter()
+            .find(|(define, _)| define == name)
+            .cloned();
+        if current == wanted {
+            return Ok(());
+        }
+
+        self.defines.retain(|(define, _)| define != name);
+        self.defines.extend(wanted);
+        self.rebuild_with_transition().await
+    }
+
+    /// The shader code that's compiled into Tattoy, if that's what's being used.
+    pub const fn builtin_source(&self) -> Option<&'static str> {
+        self.builtin_source
+    }
+
+    /// The name of the current shader, for showing to the user.
+    fn shader_name(&self) -> String {
+        if self.builtin_source.is_some() {
+            return "built-in shader".to_owned();
+        }
+        self.shader_path.file_name().map_or_else(
+            || self.shader_path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
+
+    /// Remember the error if it's because the shader doesn't compile, so that it can be shown to
+    /// the user rather than stopping the tattoy. Any other error is returned.
+    fn keep_shader_error(&mut self, error: color_eyre::eyre::Report) -> Result<()> {
+        let shader_error = error.downcast::<super::shader_error::ShaderError>()?;
+        tracing::warn!("{shader_error}");
+        self.shader_error = Some(shader_error);
+        Ok(())
+    }
+
+    /// Rebuild the pipeline with the current shader, crossfading from the old one.
+    async fn rebuild_with_transition(&mut self) -> Result<()> {
+        let maybe_previous = self.pipeline.take();
+        if let Err(error) = self.build_pipeline().await {
+            self.pipeline = maybe_previous;
+            return self.keep_shader_error(error);
+        }
+        self.shader_error = None;
+
+        self.transition = maybe_previous
+            .filter(|_| self.transition_duration > 0.0)
+            .map(|previous| super::transition::Transition::new(previous, self.transition_duration));
+
+        Ok(())
+    }
+
     /// The bind group for all data sent to the shader.
     fn create_bind_group(&self) -> wgpu::BindGroup {
         let ichannel_sampler = self
             .device
             .create_sampler(&wgpu::SamplerDescriptor::default());
+        let compute_texture = self
+            .compute
+            .as_ref()
+            .map_or(&self.empty_compute_texture, super::compute::Compute::latest);
 
         self.device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &self.bindgroup_layout,
@@ -347,6 +697,40 @@ impl GPU {
                     binding: 2,
                     resource: wgpu::BindingResource::Sampler(&ichannel_sampler),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(
+                        &self
+                            .text_mask_texture
+                            .create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(
+                        &self
+                            .cell_metadata_texture
+                            .create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(
+                        &self
+                            .keyboard_texture
+                            .create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: self.cell_changes_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: wgpu::BindingResource::TextureView(
+                        &compute_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
             ],
             label: Some("bind_group"),
         })
@@ -389,9 +773,49 @@ impl GPU {
         clippy::cast_precision_loss,
         reason = "The side effects are not serious. The value is only used on the GPU"
     )]
-    /// Get the wall time since the shader began.
-    fn get_current_time(&self) -> f32 {
+    /// Get the wall time since the shader began, at the shader's speed.
+    pub fn get_current_time(&self) -> f32 {
         (self.started.elapsed().as_millis() as f32) / crate::renderer::MILLIS_PER_SECOND
+            * self.speed
+    }
+
+    /// Move the wall time of the shader forwards, or backwards when negative, by some seconds. It
+    /// never goes back to before the shader started.
+    pub fn shift_time(&mut self, seconds: f32) {
+        let Ok(shift) = std::time::Duration::try_from_secs_f32(seconds.abs() / self.speed) else {
+            return;
+        };
+        let maybe_started = if seconds >= 0.0 {
+            self.started.checked_sub(shift)
+        } else {
+            self.started
+                .checked_add(shift)
+                .map(|started| started.min(std::time::Instant::now()))
+        };
+        if let Some(started) = maybe_started {
+            self.started = started;
+        }
+    }
+
+    /// Let the shaders know whether the user prefers reduced motion, and slow the shader's time
+    /// down when they do. The time also runs at the speed that controllers set. It carries on from
+    /// where it was, so that the shader doesn't jump.
+    pub fn set_speed(&mut self, is_reduced: bool, multiplier: f32) {
+        self.variables.iReducedMotion = i32::from(is_reduced);
+        let reduced = if is_reduced {
+            crate::reduced_motion::SPEED
+        } else {
+            1.0
+        };
+        // The time can't quite stop, otherwise it couldn't carry on from where it was.
+        let speed = reduced * multiplier.max(MINIMUM_SPEED);
+        if (speed - self.speed).abs() < f32::EPSILON {
+            return;
+        }
+
+        let time = self.get_current_time();
+        self.speed = speed;
+        self.shift_time(time - self.get_current_time());
     }
 
     /// Update the shader variables with the current elapsed wall time since the render began.
@@ -399,13 +823,46 @@ impl GPU {
         self.variables.iTime = self.get_current_time();
     }
 
+    /// Let the shaders know that the visual bell just rang.
+    pub fn ring_bell(&mut self) {
+        self.variables.iTimeBell = self.get_current_time();
+    }
+
+    /// Let the shaders know that the shell just finished running a command.
+    pub fn finish_command(&mut self, exit_status: i32) {
+        self.variables.iTimeCommandFinished = self.get_current_time();
+        self.variables.iCommandExitStatus = exit_status;
+    }
+
+    /// Let the shaders know how many rows the user has scrolled back through the scrollback.
+    pub fn set_scroll_offset(&mut self, rows: usize) {
+        self.variables.iScrollOffset = scroll_offset(rows);
+    }
+
+    /// Let the shaders know that the user's terminal just gained or lost focus.
+    pub fn change_focus(&mut self, is_focused: bool) {
+        self.variables.iTimeFocusChange = self.get_current_time();
+        self.variables.iFocused = i32::from(is_focused);
+    }
+
     /// Update the `iResolution` variable for the shaders to consume.
     pub fn update_resolution(&mut self, width: u16, height: u16) -> Result<()> {
-        self.variables.iResolution = [f32::from(width), f32::from(height), 0.0];
+        let pixel_aspect = self.variables.iResolution[2];
+        self.evicted_resolution = None;
+        self.variables.iResolution = [f32::from(width), f32::from(height), pixel_aspect];
         self.recreate_ichannel_texture();
+        self.recreate_text_mask_texture();
+        self.recreate_cell_metadata_texture();
+        self.recreate_compute_textures();
         self.rebuild_output_buffer()
     }
 
+    /// Set the real width-to-height ratio of a pixel, as found by `tattoy calibrate`, so that
+    /// shaders can correct for it with `iResolution.z`.
+    pub fn set_pixel_aspect(&mut self, pixel_aspect: f32) {
+        self.variables.iResolution[2] = pixel_aspect;
+    }
+
     /// Update the `iMouse` variable for the shaders to consume.
     pub fn update_mouse_position(&mut self, col: u16, row: u16) {
         let image_height = self.variables.iResolution[1];
@@ -434,21 +891,7 @@ impl GPU {
         reason = "There's no other `std` way to convert floats to integers"
     )]
     fn update_cursor_ghostty_format(&mut self, x: f32, y: f32, colour: [f32; 4], scale: f32) {
-        let cursor_width = 1.0 * scale;
-        let cursor_height = 2.0 * scale;
-        let cursor_top_left = (
-            (cursor_width / 2.0)
-                - (crate::tattoys::animated_cursor::CURSOR_DIMENSIONS_REAL.0 / 2.0),
-            (cursor_height / 2.0)
-                - (crate::tattoys::animated_cursor::CURSOR_DIMENSIONS_REAL.1 / 2.0),
-        );
-
-        let new_position_and_size = [
-            x - cursor_top_left.0,
-            y + cursor_top_left.1,
-            cursor_width,
-            cursor_height,
-        ];
+        let new_position_and_size = Self::ghostty_cursor(x, y, scale);
 
         let current_as_integer: Vec<i64> = self
             .variables
@@ -470,6 +913,33 @@ impl GPU {
         self.variables.iCurrentCursorColor = colour;
     }
 
+    /// Update the `iInterpolatedCursor` variable for the shaders to consume. The column and row
+    /// can be fractional, as the cursor moves between cells.
+    pub fn update_interpolated_cursor(&mut self, col: f32, row: f32, scale: f32) {
+        let image_height = self.variables.iResolution[1];
+        let y = row.mul_add(-2.0, image_height);
+        self.variables.iInterpolatedCursor = Self::ghostty_cursor(col, y, scale);
+    }
+
+    /// The position and size of the cursor, in Ghostty's format, from the centre of the cursor.
+    fn ghostty_cursor(x: f32, y: f32, scale: f32) -> [f32; 4] {
+        let cursor_width = 1.0 * scale;
+        let cursor_height = 2.0 * scale;
+        let cursor_top_left = (
+            (cursor_width / 2.0)
+                - (crate::tattoys::animated_cursor::CURSOR_DIMENSIONS_REAL.0 / 2.0),
+            (cursor_height / 2.0)
+                - (crate::tattoys::animated_cursor::CURSOR_DIMENSIONS_REAL.1 / 2.0),
+        );
+
+        [
+            x - cursor_top_left.0,
+            y + cursor_top_left.1,
+            cursor_width,
+            cursor_height,
+        ]
+    }
+
     /// Tick the render
     pub async fn render(&mut self) -> Result<image::ImageBuffer<image::Rgba<u8>, Vec<u8>>> {
         self.update_wall_time();
@@ -479,7 +949,32 @@ impl GPU {
             0,
             bytemuck::cast_slice(&[self.variables]),
         );
+        self.update_keyboard_texture_data();
+        self.run_compute_pass();
+
+        let image = self.render_pipeline(self.pipeline.as_ref()).await?;
+        self.keyboard.end_frame();
+
+        if self
+            .transition
+            .as_ref()
+            .is_some_and(super::transition::Transition::is_finished)
+        {
+            self.transition = None;
+        }
+        let Some(transition) = self.transition.as_ref() else {
+            return Ok(image);
+        };
+
+        let previous_image = self.render_pipeline(Some(&transition.pipeline)).await?;
+        Ok(transition.crossfade(&previous_image, &image))
+    }
 
+    /// Render a single pipeline and read the result back from the GPU.
+    async fn render_pipeline(
+        &self,
+        maybe_pipeline: Option<&wgpu::RenderPipeline>,
+    ) -> Result<image::ImageBuffer<image::Rgba<u8>, Vec<u8>>> {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
@@ -510,7 +1005,7 @@ impl GPU {
             };
             let mut render_pass = encoder.begin_render_pass(&render_pass_desc);
 
-            if let Some(pipeline) = self.pipeline.as_ref() {
+            if let Some(pipeline) = maybe_pipeline {
                 render_pass.set_pipeline(pipeline);
                 render_pass.set_bind_group(0, &self.create_bind_group(), &[]);
                 render_pass.draw(0..3, 0..1);
@@ -617,11 +1112,27 @@ impl GPU {
         // Therefore we also need to provide some header and footer boilerplate to allow
         // copy-pasting shaders without alteration. Just little things like `main()` calling
         // `mainImage()` and providing known globals such as `iResolution`.
-        let file = tokio::fs::read(self.shader_path.clone()).await?;
-        let contents = String::from_utf8_lossy(&file);
+        let contents = if let Some(source) = self.builtin_source {
+            source.to_owned()
+        } else {
+            let file = tokio::fs::read(self.shader_path.clone()).await?;
+            String::from_utf8_lossy(&file).into_owned()
+        };
+        let variables = include_str!("shaders/variables.glsl");
         let header = include_str!("shaders/header.glsl");
         let footer = include_str!("shaders/footer.glsl");
-        let shader = format!("{header}\n{contents}\n{footer}");
+        let shader = format!("{variables}\n{header}\n{contents}\n{footer}");
+
+        // Catch errors before the GPU does, so that they can be shown helpfully to the user.
+        let preamble_lines = format!("{variables}\n{header}\n").lines().count();
+        super::shader_error::check(
+            &self.shader_name(),
+            &contents,
+            preamble_lines,
+            &shader,
+            wgpu::naga::ShaderStage::Fragment,
+            &self.defines,
+        )?;
 
         let fragment_shader = self
             .device
@@ -630,10 +1141,38 @@ impl GPU {
                 source: wgpu::ShaderSource::Glsl {
                     shader: shader.into(),
                     stage: wgpu::naga::ShaderStage::Fragment,
-                    defines: std::collections::HashMap::default(),
+                    defines: self.defines.iter().cloned().collect(),
                 },
             });
 
         Ok((vertex_shader, fragment_shader))
     }
 }
+
+/// How far, in pixels, scrolling back a number of rows moves the text. Every row of the terminal
+/// is 2 pixels high.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_precision_loss,
+    reason = "The scrollback is never anywhere near big enough to lose precision"
+)]
+fn scroll_offset(rows: usize) -> f32 {
+    rows.saturating_mul(2) as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scroll_offsets_are_in_pixels() {
+        assert!(scroll_offset(0).abs() < f32::EPSILON);
+        assert!((scroll_offset(3) - 6.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn variables_are_aligned_for_the_uniform_buffer() {
+        assert_eq!(core::mem::size_of::<Variables>() % 16, 0);
+        assert!(Variables::default().iScrollOffset.abs() < f32::EPSILON);
+    }
+}
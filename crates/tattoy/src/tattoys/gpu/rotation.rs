@@ -0,0 +1,270 @@
+//! Rotating through the shaders in a directory, switching to another one every so often. This is
+//! shared by both the shaders tattoy and the animated cursor tattoy.
+
+use color_eyre::eyre::{ContextCompat as _, Result};
+use rand::seq::SliceRandom as _;
+
+/// The order that shaders are rotated through.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Order {
+    /// Pick any other shader in the directory.
+    #[default]
+    Random,
+    /// Go through the shaders in alphabetical order.
+    Sequential,
+}
+
+/// How a tattoy rotates its shaders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Settings {
+    /// How long each shader is shown for.
+    pub interval: std::time::Duration,
+    /// The order that shaders are rotated through.
+    pub order: Order,
+    /// The directory of shaders to rotate through.
+    pub directory: std::path::PathBuf,
+    /// Globs of shader filenames that are never rotated to.
+    pub exclude: Vec<String>,
+}
+
+impl Settings {
+    /// Build the settings from a tattoy's config. Returns `None` when the tattoy doesn't rotate.
+    /// Relative paths are relative to the config directory, and the directory defaults to the
+    /// directory of the tattoy's configured shader.
+    pub fn from_config(
+        config_directory: &std::path::Path,
+        every: Option<&str>,
+        order: Order,
+        directory: Option<&std::path::Path>,
+        shader_path: &std::path::Path,
+        exclude: &[String],
+    ) -> Result<Option<Self>> {
+        let Some(every) = every else {
+            return Ok(None);
+        };
+
+        let directory = match directory {
+            Some(directory) => directory,
+            None => shader_path
+                .parent()
+                .context("The shader path doesn't have a directory to rotate through")?,
+        };
+        Ok(Some(Self {
+            interval: parse_interval(every)?,
+            order,
+            directory: config_directory.join(directory),
+            exclude: exclude.to_vec(),
+        }))
+    }
+}
+
+/// A tattoy's rotation through its shaders.
+pub(crate) struct Rotation {
+    /// How the shaders are rotated.
+    pub settings: Settings,
+    /// When the next shader is due.
+    next_at: tokio::time::Instant,
+}
+
+impl Rotation {
+    /// Start rotating.
+    pub fn new(settings: Settings) -> Self {
+        let next_at = tokio::time::Instant::now() + settings.interval;
+        Self { settings, next_at }
+    }
+
+    /// Update the rotation after the config has changed. An unchanged rotation keeps counting
+    /// down to its next shader, rather than starting again.
+    pub fn update(current: Option<Self>, settings: Option<Settings>) -> Option<Self> {
+        match (current, settings) {
+            (Some(current), Some(settings)) if current.settings == settings => Some(current),
+            (_, settings) => settings.map(Self::new),
+        }
+    }
+
+    /// The shader to switch to, if it's time for the next one.
+    pub fn next_if_due(&mut self, current: &std::path::Path) -> Result<Option<std::path::PathBuf>> {
+        let now = tokio::time::Instant::now();
+        if now < self.next_at {
+            return Ok(None);
+        }
+        self.next_at = now + self.settings.interval;
+
+        let candidates = self.candidates(current)?;
+        let next = match self.settings.order {
+            Order::Random => candidates.choose(&mut rand::thread_rng()).cloned(),
+            Order::Sequential => next_in_sequence(&candidates, current),
+        };
+        Ok(next)
+    }
+
+    /// All the shaders that can be rotated to, in alphabetical order, apart from the current one.
+    fn candidates(&self, current: &std::path::Path) -> Result<Vec<std::path::PathBuf>> {
+        let mut candidates = std::fs::read_dir(&self.settings.directory)?
+            .map(|result| result.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<std::path::PathBuf>>>()?
+            .into_iter()
+            .filter(|path| path.is_file() && path != current && !self.is_excluded(path))
+            .collect::<Vec<std::path::PathBuf>>();
+        candidates.sort();
+        Ok(candidates)
+    }
+
+    /// Whether the user has excluded a shader from the rotation.
+    fn is_excluded(&self, path: &std::path::Path) -> bool {
+        let Some(filename) = path.file_name() else {
+            return true;
+        };
+        self.settings
+            .exclude
+            .iter()
+            .any(|glob| crate::cwd::is_glob_match(glob, std::path::Path::new(filename)))
+    }
+}
+
+/// Stop rotating, and tell the user why, when the rotation settings aren't valid.
+pub(crate) async fn or_report(
+    state: &std::sync::Arc<crate::shared_state::SharedState>,
+    settings: Result<Option<Settings>>,
+) -> Option<Settings> {
+    match settings {
+        Ok(settings) => settings,
+        Err(error) => {
+            tracing::warn!("Invalid shader rotation: {error:?}");
+            state
+                .send_notification(
+                    "Invalid shader rotation",
+                    crate::tattoys::notifications::message::Level::Warn,
+                    Some(error.to_string()),
+                    false,
+                )
+                .await;
+            None
+        }
+    }
+}
+
+/// The first shader after the current one, going back to the start after the last one.
+fn next_in_sequence(
+    candidates: &[std::path::PathBuf],
+    current: &std::path::Path,
+) -> Option<std::path::PathBuf> {
+    candidates
+        .iter()
+        .find(|candidate| candidate.as_path() > current)
+        .or_else(|| candidates.first())
+        .cloned()
+}
+
+/// Parse an interval like `"30s"`, `"15m"` or `"1h"`. A number without a unit is in seconds.
+pub(crate) fn parse_interval(text: &str) -> Result<std::time::Duration> {
+    let text = text.trim();
+    let split = text
+        .find(|character: char| !character.is_ascii_digit() && character != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number = number
+        .parse::<f64>()
+        .ok()
+        .with_context(|| format!("`{text}` isn't a valid interval, try something like \"15m\""))?;
+    let seconds = match unit.trim() {
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 60.0 * 60.0,
+        _ => color_eyre::eyre::bail!("`{text}` should end with `s`, `m` or `h`"),
+    };
+    if seconds <= 0.0 {
+        color_eyre::eyre::bail!("The interval `{text}` must be longer than 0");
+    }
+
+    std::time::Duration::try_from_secs_f64(seconds)
+        .ok()
+        .with_context(|| format!("The interval `{text}` is too long"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn settings(directory: &std::path::Path, order: Order, exclude: &[&str]) -> Settings {
+        Settings {
+            interval: std::time::Duration::ZERO,
+            order,
+            directory: directory.to_owned(),
+            exclude: exclude.iter().map(|glob| (*glob).to_owned()).collect(),
+        }
+    }
+
+    fn shaders() -> tempfile::TempDir {
+        let directory = tempfile::tempdir().unwrap();
+        for name in ["a.glsl", "b.glsl", "c.glsl", "wip_d.glsl"] {
+            std::fs::write(directory.path().join(name), "").unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn intervals_are_parsed() {
+        assert_eq!(
+            parse_interval("15m").unwrap(),
+            std::time::Duration::from_secs(900)
+        );
+        assert_eq!(
+            parse_interval("1.5h").unwrap(),
+            std::time::Duration::from_secs(5400)
+        );
+        assert_eq!(
+            parse_interval("30").unwrap(),
+            std::time::Duration::from_secs(30)
+        );
+        assert!(parse_interval("soon").is_err());
+        assert!(parse_interval("3d").is_err());
+        assert!(parse_interval("0s").is_err());
+    }
+
+    #[test]
+    fn sequential_rotation_wraps_around() {
+        let directory = shaders();
+        let mut rotation = Rotation::new(settings(directory.path(), Order::Sequential, &["wip_*"]));
+        let next = rotation
+            .next_if_due(&directory.path().join("b.glsl"))
+            .unwrap();
+        assert_eq!(next, Some(directory.path().join("c.glsl")));
+        let next = rotation
+            .next_if_due(&directory.path().join("c.glsl"))
+            .unwrap();
+        assert_eq!(next, Some(directory.path().join("a.glsl")));
+    }
+
+    #[test]
+    fn random_rotation_skips_the_current_and_excluded_shaders() {
+        let directory = shaders();
+        let mut rotation = Rotation::new(settings(
+            directory.path(),
+            Order::Random,
+            &["wip_*", "c.glsl"],
+        ));
+        for _ in 0..20 {
+            let next = rotation
+                .next_if_due(&directory.path().join("a.glsl"))
+                .unwrap();
+            assert_eq!(next, Some(directory.path().join("b.glsl")));
+        }
+    }
+
+    #[test]
+    fn unchanged_rotations_keep_counting_down() {
+        let mut long = settings(std::path::Path::new("shaders"), Order::Random, &[]);
+        long.interval = std::time::Duration::from_secs(60);
+        let rotation = Rotation::new(long.clone());
+        let next_at = rotation.next_at;
+
+        let kept = Rotation::update(Some(rotation), Some(long.clone())).unwrap();
+        assert_eq!(kept.next_at, next_at);
+
+        long.order = Order::Sequential;
+        assert!(Rotation::update(Some(kept), Some(long)).is_some());
+        assert!(Rotation::update(None, None).is_none());
+    }
+}
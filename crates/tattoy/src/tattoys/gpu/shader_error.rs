@@ -0,0 +1,330 @@
+//! Readable errors for shaders that don't compile. The GLSL is checked with `naga` before it's
+//! sent to the GPU, so that rather than just a terse notification, the user sees an overlay of the
+//! offending lines of their shader with each error underlined.
+
+/// How many lines of the shader to show before and after the line of each error.
+const CONTEXT_LINES: usize = 1;
+
+/// How many spaces a tab is shown as.
+const TAB_WIDTH: usize = 4;
+
+/// The background colour of the overlay.
+const BACKGROUND: crate::surface::Colour = (0.1, 0.0, 0.0, 1.0);
+
+/// The colour of the overlay's title and the error messages.
+const ERROR_COLOUR: crate::surface::Colour = (1.0, 0.4, 0.4, 1.0);
+
+/// The colour of the lines of the shader.
+const SOURCE_COLOUR: crate::surface::Colour = (0.8, 0.8, 0.8, 1.0);
+
+/// An error at a particular place in the user's shader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Annotation {
+    /// The line of the user's shader, starting from `1`. It's `None` when the error is in Tattoy's
+    /// own boilerplate, eg: when `mainImage()` is missing.
+    line: Option<usize>,
+    /// The byte in the line where the error starts, starting from `1`.
+    column: usize,
+    /// How many bytes the error spans.
+    length: usize,
+    /// What's wrong.
+    message: String,
+}
+
+/// A line of the overlay.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Line {
+    /// The title of the overlay, or an error that isn't in the user's shader.
+    Error(String),
+    /// A line of the user's shader, with its line number.
+    Source(String),
+    /// The underline beneath the part of the line that's wrong, and the error message.
+    Underline(String),
+}
+
+impl Line {
+    /// The text of the line and the colour that it's shown in.
+    pub fn into_text_and_colour(self) -> (String, crate::surface::Colour) {
+        match self {
+            Self::Error(text) | Self::Underline(text) => (text, ERROR_COLOUR),
+            Self::Source(text) => (text, SOURCE_COLOUR),
+        }
+    }
+}
+
+/// A shader that failed to compile.
+#[derive(Debug, Clone)]
+pub(crate) struct ShaderError {
+    /// The name of the shader, usually its filename.
+    name: String,
+    /// The user's shader code.
+    contents: String,
+    /// Each of the errors.
+    annotations: Vec<Annotation>,
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "Couldn't compile {}", self.name)?;
+        if let Some(annotation) = self.annotations.first() {
+            if let Some(line) = annotation.line {
+                write!(formatter, ", line {line}")?;
+            }
+            write!(formatter, ": {}", annotation.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// Check that a shader compiles. The shader is the user's code, `contents`, with
+/// `preamble_lines` lines of Tattoy's boilerplate before it, so that errors can be reported at
+/// the lines of the user's code.
+pub(crate) fn check(
+    name: &str,
+    contents: &str,
+    preamble_lines: usize,
+    shader: &str,
+    stage: wgpu::naga::ShaderStage,
+    defines: &[(String, String)],
+) -> Result<(), ShaderError> {
+    let options = wgpu::naga::front::glsl::Options {
+        stage,
+        defines: defines.iter().cloned().collect(),
+    };
+    let errors = match wgpu::naga::front::glsl::Frontend::default().parse(&options, shader) {
+        Ok(module) => {
+            let mut validator = wgpu::naga::valid::Validator::new(
+                wgpu::naga::valid::ValidationFlags::all(),
+                wgpu::naga::valid::Capabilities::all(),
+            );
+            let Err(error) = validator.validate(&module) else {
+                return Ok(());
+            };
+            let span = error
+                .spans()
+                .next()
+                .map(|(span, _)| *span)
+                .unwrap_or_default();
+            vec![(span, describe(error.as_inner()))]
+        }
+        Err(errors) => errors
+            .errors
+            .into_iter()
+            .map(|error| (error.meta, error.kind.to_string()))
+            .collect(),
+    };
+
+    let line_count = contents.lines().count();
+    let annotations = errors
+        .into_iter()
+        .map(|(span, message)| {
+            let location = span.location(shader);
+            let line = usize::try_from(location.line_number)
+                .ok()
+                .and_then(|line| line.checked_sub(preamble_lines))
+                .filter(|line| (1..=line_count).contains(line));
+            Annotation {
+                line,
+                column: usize::try_from(location.line_position).unwrap_or(1),
+                length: usize::try_from(location.length).unwrap_or(1),
+                message,
+            }
+        })
+        .collect();
+
+    Err(ShaderError {
+        name: name.to_owned(),
+        contents: contents.to_owned(),
+        annotations,
+    })
+}
+
+/// An error and all the errors that caused it, like "Function is invalid: Expression is invalid".
+fn describe(error: &dyn std::error::Error) -> String {
+    let mut description = error.to_string();
+    let mut maybe_source = error.source();
+    while let Some(source) = maybe_source {
+        description = format!("{description}: {source}");
+        maybe_source = source.source();
+    }
+    description
+}
+
+/// Show tabs as spaces, so that underlines line up with the text above them.
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+impl ShaderError {
+    /// The lines of the overlay: a title, then each error with the lines of the shader around
+    /// it, and the part of the line that's wrong underlined.
+    pub fn overlay(&self) -> Vec<Line> {
+        let source_lines = self.contents.lines().collect::<Vec<_>>();
+        let gutter = source_lines.len().to_string().len();
+
+        let mut lines = vec![Line::Error(format!("Shader error in {}", self.name))];
+        for annotation in &self.annotations {
+            lines.push(Line::Source(String::new()));
+            let Some(line_number) = annotation.line else {
+                lines.push(Line::Error(annotation.message.clone()));
+                continue;
+            };
+
+            let first = line_number.saturating_sub(CONTEXT_LINES).max(1);
+            let last = (line_number + CONTEXT_LINES).min(source_lines.len());
+            for (number, text) in (first..=last).zip(source_lines.iter().skip(first - 1)) {
+                lines.push(Line::Source(format!(
+                    "{number:>gutter$} │ {}",
+                    expand_tabs(text)
+                )));
+                if number == line_number {
+                    lines.push(Line::Underline(Self::underline(text, annotation, gutter)));
+                }
+            }
+        }
+        lines
+    }
+
+    /// The carets beneath the part of the line that's wrong, followed by the error message.
+    fn underline(text: &str, annotation: &Annotation, gutter: usize) -> String {
+        let start = annotation.column.saturating_sub(1);
+        let indent = text
+            .get(..start)
+            .map_or(start, |before| expand_tabs(before).chars().count());
+        // Errors can span more than one line, but only the first line is underlined.
+        let end = (start + annotation.length).min(text.len());
+        let length = text
+            .get(start..end)
+            .map_or(1, |error| expand_tabs(error).chars().count())
+            .max(1);
+        format!(
+            "{:gutter$} │ {}{} {}",
+            "",
+            " ".repeat(indent),
+            "^".repeat(length),
+            annotation.message
+        )
+    }
+
+    /// Draw the overlay over the whole of a surface.
+    pub fn draw(&self, surface: &mut crate::surface::Surface) {
+        let width = surface.width;
+        let blank_lines = std::iter::repeat_with(|| Line::Source(String::new()));
+        for (y, line) in (0..surface.height).zip(self.overlay().into_iter().chain(blank_lines)) {
+            let (text, colour) = line.into_text_and_colour();
+            let mut padded = format!(" {text}").chars().take(width).collect::<String>();
+            let padding = width.saturating_sub(padded.chars().count());
+            padded.push_str(&" ".repeat(padding));
+            surface.add_text(0, y, padded, Some(BACKGROUND), Some(colour));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PREAMBLE: &str = "const float BRIGHTNESS = 1.0;\n";
+
+    fn check_fragment(contents: &str) -> Result<(), ShaderError> {
+        let footer = "void main() {}";
+        let shader = format!("#version 450\n{PREAMBLE}{contents}\n{footer}");
+        check(
+            "broken.glsl",
+            contents,
+            format!("#version 450\n{PREAMBLE}").lines().count(),
+            &shader,
+            wgpu::naga::ShaderStage::Fragment,
+            &[],
+        )
+    }
+
+    #[test]
+    fn errors_are_reported_at_the_line_of_the_users_shader() {
+        assert!(check_fragment("float brightness() {\n    return 1.0;\n}").is_ok());
+
+        let error = check_fragment("float brightness() {\n    return unknown;\n}").unwrap_err();
+        let annotation = error.annotations.first().unwrap();
+        assert_eq!(annotation.line, Some(2));
+        assert!(error
+            .to_string()
+            .starts_with("Couldn't compile broken.glsl, line 2"));
+    }
+
+    #[test]
+    fn the_boilerplate_compiles_with_and_without_its_defines() {
+        let variables = include_str!("shaders/variables.glsl");
+        let header = include_str!("shaders/header.glsl");
+        let footer = include_str!("shaders/footer.glsl");
+        let contents = "void mainImage(out vec4 fragColor, in vec2 fragCoord) {\n    \
+            fragColor = vec4(fragCoord / iResolution.xy, 0.0, 1.0);\n}";
+        let shader = format!("{variables}\n{header}\n{contents}\n{footer}");
+        let preamble_lines = format!("{variables}\n{header}\n").lines().count();
+
+        for defines in [
+            Vec::new(),
+            vec![(
+                crate::tattoys::gpu::pipeline::LETTERBOX_DEFINE.to_owned(),
+                "1.78".to_owned(),
+            )],
+            vec![
+                (
+                    crate::tattoys::gpu::pipeline::LETTERBOX_DEFINE.to_owned(),
+                    "1.78".to_owned(),
+                ),
+                (
+                    crate::tattoys::gpu::pipeline::FOLLOW_SCROLLBACK_DEFINE.to_owned(),
+                    "1".to_owned(),
+                ),
+            ],
+        ] {
+            let result = check(
+                "defines.glsl",
+                contents,
+                preamble_lines,
+                &shader,
+                wgpu::naga::ShaderStage::Fragment,
+                &defines,
+            );
+            assert!(result.is_ok(), "{result:?}");
+        }
+    }
+
+    #[test]
+    fn the_overlay_underlines_the_error() {
+        let error = ShaderError {
+            name: "broken.glsl".to_owned(),
+            contents: "void a() {}\n\tfloat b = c;\nvoid d() {}\nvoid e() {}".to_owned(),
+            annotations: vec![
+                Annotation {
+                    line: Some(2),
+                    column: 12,
+                    length: 1,
+                    message: "Unknown variable: c".to_owned(),
+                },
+                Annotation {
+                    line: None,
+                    column: 1,
+                    length: 0,
+                    message: "Missing mainImage".to_owned(),
+                },
+            ],
+        };
+
+        assert_eq!(
+            error.overlay(),
+            [
+                Line::Error("Shader error in broken.glsl".to_owned()),
+                Line::Source(String::new()),
+                Line::Source("1 │ void a() {}".to_owned()),
+                Line::Source("2 │     float b = c;".to_owned()),
+                Line::Underline("  │               ^ Unknown variable: c".to_owned()),
+                Line::Source("3 │ void d() {}".to_owned()),
+                Line::Source(String::new()),
+                Line::Error("Missing mainImage".to_owned()),
+            ]
+        );
+    }
+}
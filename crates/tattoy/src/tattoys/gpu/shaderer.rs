@@ -3,6 +3,13 @@
 use color_eyre::eyre::{ContextCompat as _, Result};
 use futures_util::FutureExt as _;
 
+/// How many times to try rebuilding a pipeline on a new GPU device before giving up.
+const MAX_RECOVERY_ATTEMPTS: u32 = 3;
+
+/// How long to wait after a failed recovery, multiplied by the number of attempts so far. Drivers
+/// can take a moment to come back after a reset.
+const RECOVERY_BACKOFF: std::time::Duration = std::time::Duration::from_secs(1);
+
 /// A state machine representing the stages of a short animation. Currently only used for the
 /// animated cursor. It's basically to prevent rendering when it's not needed.
 #[derive(PartialEq, Eq)]
@@ -32,9 +39,34 @@ pub(crate) trait Shaderer: Sized {
     /// Is the config for this tattoy set to upload the TTY as pixels?
     async fn is_upload_tty_as_pixels(&self) -> bool;
 
+    /// Should the mask of where the text is be uploaded, see `super::text_mask`?
+    async fn is_upload_text_mask(&self) -> bool {
+        false
+    }
+
+    /// Should the colours and attributes of every cell be uploaded, see `super::cell_metadata`?
+    #[expect(
+        clippy::allow_attributes,
+        reason = "The lint behaves differently on CI"
+    )]
+    #[allow(clippy::unused_async, reason = "It's a default implementation")]
+    async fn is_upload_cell_metadata(&self) -> bool {
+        false
+    }
+
+    /// Should the cells that text recently appeared in be uploaded, see `super::cell_changes`?
+    async fn is_upload_cell_changes(&self) -> bool {
+        false
+    }
+
     /// Should the character colours be uploaded as part of the TTY pixels?
     fn is_upload_tty_with_characters(&self) -> bool;
 
+    /// Should the shader keybindings change this tattoy's shader?
+    fn is_cyclable(&self) -> bool {
+        true
+    }
+
     /// Should the final render be hashed? This is useful for quickly comparing renders to decide
     /// if they should receive further processing.
     fn is_should_hash_render(&self) -> bool {
@@ -52,6 +84,35 @@ pub(crate) trait Shaderer: Sized {
         self.render().await
     }
 
+    /// The tattoy's rotation through the shaders in a directory, if it's rotating.
+    fn rotation_mut(&mut self) -> Option<&mut super::rotation::Rotation> {
+        None
+    }
+
+    /// Switch to the next shader of the rotation, if it's due. The switch crossfades just like
+    /// cycling shaders does.
+    async fn rotate_if_due(&mut self) -> Result<()> {
+        let current = self.gpu().shader_path.clone();
+        let Some(rotation) = self.rotation_mut() else {
+            return Ok(());
+        };
+        let next = match rotation.next_if_due(&current) {
+            Ok(next) => next,
+            Err(error) => {
+                tracing::warn!("Couldn't rotate shaders: {error:?}");
+                None
+            }
+        };
+        let Some(next) = next else {
+            return Ok(());
+        };
+
+        tracing::info!("Rotating shader to: {next:?}");
+        self.gpu_mut().switch_shader(next).await?;
+        self.handle_render_hash(HashedRender::NeedsRendering);
+        Ok(())
+    }
+
     /// The hash of the render image can be used to decide whether it actually gets rendered to the
     /// user's terminal or not.
     fn handle_render_hash(&mut self, _hash: HashedRender) {}
@@ -66,19 +127,42 @@ pub(crate) trait Shaderer: Sized {
         1.0
     }
 
-    /// Instantiate
+    /// React to the user's config being changed.
+    #[expect(
+        clippy::allow_attributes,
+        reason = "The lint behaves differently on CI"
+    )]
+    #[allow(clippy::unused_async, reason = "It's a default implementation")]
+    async fn handle_config_update(&mut self, _config: &crate::config::main::Config) -> Result<()> {
+        Ok(())
+    }
+
+    /// React to the shell in the PTY changing directory.
+    #[expect(
+        clippy::allow_attributes,
+        reason = "The lint behaves differently on CI"
+    )]
+    #[allow(clippy::unused_async, reason = "It's a default implementation")]
+    async fn handle_directory_change(&mut self, _directory: &std::path::Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Instantiate. The index distinguishes between multiple instances of the same kind of
+    /// tattoy, eg: when more than one shader is configured.
     async fn new(
         output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: std::sync::Arc<crate::shared_state::SharedState>,
+        index: usize,
     ) -> Result<Self>;
 
     /// Our main entrypoint.
     async fn start(
         output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: std::sync::Arc<crate::shared_state::SharedState>,
+        index: usize,
     ) -> Result<()> {
         let may_panic = std::panic::AssertUnwindSafe(async {
-            let result = Self::main(output, &state).await;
+            let result = Self::main(output, &state, index).await;
 
             if let Err(error) = result {
                 tracing::error!("GPU pipeline error: {error:?}");
@@ -123,9 +207,10 @@ pub(crate) trait Shaderer: Sized {
     async fn main(
         output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: &std::sync::Arc<crate::shared_state::SharedState>,
+        index: usize,
     ) -> Result<()> {
         let mut protocol = state.protocol_tx.subscribe();
-        let mut shader = Self::new(output, std::sync::Arc::clone(state)).await?;
+        let mut shader = Self::new(output, std::sync::Arc::clone(state), index).await?;
 
         state
             .initialised_systems
@@ -140,7 +225,18 @@ pub(crate) trait Shaderer: Sized {
         loop {
             tokio::select! {
                 () = shader.tattoy_mut().sleep_until_next_frame_tick() => {
-                    shader.render_handler().await?;
+                    shader.evict_if_asked().await?;
+                    shader.rotate_if_due().await?;
+                    if shader.tattoy().is_ready_for_frame().await
+                        && !shader.tattoy().is_paused_for_output_burst().await
+                    {
+                        let result = shader.render_handler().await;
+                        if shader.gpu().is_device_lost() {
+                            shader = Self::recover(shader, state, index).await?;
+                        } else {
+                            result?;
+                        }
+                    }
                 },
                 result = protocol.recv() => {
                     if matches!(result, Ok(crate::run::Protocol::End)) {
@@ -154,6 +250,92 @@ pub(crate) trait Shaderer: Sized {
         Ok(())
     }
 
+    /// Rebuild the tattoy, and so its whole GPU pipeline, on a new GPU device after the old one
+    /// was lost, eg: after a suspend and resume or a driver reset. It's meant to be invisible to
+    /// the user, so it only gives up, with an error, after failing a few times.
+    async fn recover(
+        mut lost: Self,
+        state: &std::sync::Arc<crate::shared_state::SharedState>,
+        index: usize,
+    ) -> Result<Self> {
+        let output = lost.tattoy().output_channel.clone();
+        let screen = std::mem::take(&mut lost.tattoy_mut().screen);
+        let scrollback = std::mem::take(&mut lost.tattoy_mut().scrollback);
+        let id = lost.tattoy().id.clone();
+        drop(lost);
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            tracing::warn!("Rebuilding '{id}' on a new GPU device, attempt {attempt}");
+            let error = match Self::new(output.clone(), std::sync::Arc::clone(state), index).await {
+                Ok(shader) if shader.gpu().is_device_lost() => {
+                    color_eyre::eyre::eyre!("The new GPU device was lost too")
+                }
+                Ok(mut shader) => {
+                    shader.tattoy_mut().screen = screen;
+                    shader.tattoy_mut().scrollback = scrollback;
+                    shader.upload_tty_as_pixels().await?;
+                    tracing::info!("Recovered '{id}' from losing the GPU device");
+                    return Ok(shader);
+                }
+                Err(error) => error,
+            };
+            if attempt >= MAX_RECOVERY_ATTEMPTS {
+                return Err(error.wrap_err("Couldn't recover from losing the GPU device"));
+            }
+            tracing::warn!("Couldn't rebuild '{id}' on a new GPU device: {error:?}");
+            tokio::time::sleep(RECOVERY_BACKOFF * attempt).await;
+        }
+    }
+
+    /// Free the pipeline's GPU memory, if it's been asked to, see `super::memory`.
+    async fn evict_if_asked(&mut self) -> Result<()> {
+        let state = std::sync::Arc::clone(&self.tattoy().state);
+        let id = self.tattoy().id.clone();
+        if !state.gpu_memory.write().await.take_eviction(&id) {
+            return Ok(());
+        }
+        self.gpu_mut().evict()?;
+        let bytes = self.gpu().memory_usage();
+        state.gpu_memory.write().await.evicted(&id, bytes);
+        Ok(())
+    }
+
+    /// Report the pipeline's GPU memory after rendering, and warn the user if the shaders are
+    /// over the budget, even after evicting everything that can be.
+    async fn track_gpu_memory(&self) {
+        let state = std::sync::Arc::clone(&self.tattoy().state);
+        let limit = state.config.read().await.render.gpu_memory_budget;
+        let now = std::time::Instant::now();
+        let mut budget = state.gpu_memory.write().await;
+        budget.rendered(&self.tattoy().id, self.gpu().memory_usage(), now);
+        let maybe_over_budget = if limit == 0 {
+            None
+        } else {
+            budget.enforce(limit.saturating_mul(super::memory::BYTES_PER_MEGABYTE), now)
+        };
+        drop(budget);
+
+        let Some(total) = maybe_over_budget else {
+            return;
+        };
+        let message = format!(
+            "Shaders are using {}MB of GPU memory, more than the budget of {limit}MB. \
+            Try disabling some, or raising `render.gpu_memory_budget`.",
+            total.div_euclid(super::memory::BYTES_PER_MEGABYTE)
+        );
+        tracing::warn!("{message}");
+        state
+            .send_notification(
+                "GPU memory budget exceeded",
+                crate::tattoys::notifications::message::Level::Warn,
+                Some(message),
+                false,
+            )
+            .await;
+    }
+
     /// Handle messages from the main Tattoy app.
     async fn handle_protocol_message(
         &mut self,
@@ -164,12 +346,29 @@ pub(crate) trait Shaderer: Sized {
     ) -> Result<()> {
         match protocol_result {
             Ok(message) => {
+                if let crate::run::Protocol::Config(config) = &message {
+                    self.handle_config_update(config).await?;
+                }
+
+                if let crate::run::Protocol::DirectoryChanged(directory) = &message {
+                    self.handle_directory_change(directory).await?;
+                }
+
                 if matches!(&message, crate::run::Protocol::Repaint) {
                     self.upload_tty_as_pixels().await?;
                     self.handle_render_hash(HashedRender::NeedsRendering);
                 }
 
-                self.gpu_mut().handle_protocol_message(&message).await?;
+                let is_cycle_event = matches!(
+                    &message,
+                    crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ShaderPrev
+                            | crate::config::input::KeybindingAction::ShaderNext
+                    )
+                );
+                if !is_cycle_event || self.is_cyclable() {
+                    self.gpu_mut().handle_protocol_message(&message).await?;
+                }
                 self.tattoy_mut().handle_common_protocol_messages(message)?;
             }
             Err(error) => tracing::error!("Receiving protocol message: {error:?}"),
@@ -191,6 +390,25 @@ pub(crate) trait Shaderer: Sized {
             cursor_scale,
         );
 
+        let state = std::sync::Arc::clone(&self.tattoy().state);
+        let config = state.config.read().await;
+        let duration =
+            std::time::Duration::try_from_secs_f32(config.animated_cursor.interpolation_duration)
+                .unwrap_or_default();
+        let easing = config.animated_cursor.interpolation_easing;
+        drop(config);
+        let interpolated = state.cursor_history.read().await.interpolated(
+            tokio::time::Instant::now(),
+            duration,
+            easing,
+        );
+        let (col, row) = interpolated.unwrap_or((
+            f32::from(u16::try_from(cursor_position.0)?),
+            f32::from(u16::try_from(cursor_position.1)?),
+        ));
+        self.gpu_mut()
+            .update_interpolated_cursor(col, row, cursor_scale);
+
         Ok(())
     }
 
@@ -198,18 +416,63 @@ pub(crate) trait Shaderer: Sized {
     async fn upload_tty_as_pixels(&mut self) -> Result<()> {
         let is_upload_tty_as_pixels = self.is_upload_tty_as_pixels().await;
         let is_upload_tty_with_characters = self.is_upload_tty_with_characters();
-        self.gpu_mut().tty_pixels = self
+        let tty_pixels = self
             .tattoy_mut()
             .get_tty_image_for_upload(is_upload_tty_as_pixels, is_upload_tty_with_characters)
             .await?;
-        self.gpu_mut().update_ichannel_texture_data();
+        let previous = std::mem::replace(&mut self.gpu_mut().tty_pixels, tty_pixels);
+        self.gpu_mut()
+            .update_changed_ichannel_texture_data(&previous);
+
+        if self.is_upload_text_mask().await {
+            let cells = self.tattoy().screen.surface.get_screen_cells();
+            let text_mask = super::text_mask::from_cells(&cells);
+            self.gpu_mut().text_mask = text_mask;
+            self.gpu_mut().update_text_mask_texture_data();
+        }
+
+        if self.is_upload_cell_metadata().await {
+            let default_background = *self.tattoy().state.default_background.read().await;
+            let default_foreground = *self.tattoy().state.default_foreground.read().await;
+            let cells = self.tattoy().screen.surface.get_screen_cells();
+            let cell_metadata =
+                super::cell_metadata::from_cells(&cells, default_foreground, default_background);
+            self.gpu_mut().cell_metadata = cell_metadata;
+            self.gpu_mut().update_cell_metadata_texture_data();
+        }
+
+        if self.is_upload_cell_changes().await {
+            let cells = self
+                .tattoy()
+                .screen
+                .surface
+                .get_screen_cells()
+                .iter()
+                .map(|line| line.to_vec())
+                .collect();
+            self.gpu_mut().update_cell_changes(cells);
+        }
 
         Ok(())
     }
 
     /// Tick the render
     async fn render(&mut self) -> Result<()> {
+        if self.gpu().shader_error.is_some() {
+            return self.render_shader_error().await;
+        }
+
+        let is_reduced_motion = self.tattoy().state.is_reduced_motion().await;
+        let speed = self.tattoy().state.live_controls.read().await.shader_speed;
+        self.gpu_mut().set_speed(is_reduced_motion, speed);
+        let scroll_position = self.tattoy().scrollback.position;
+        self.gpu_mut().set_scroll_offset(scroll_position);
+        if self.gpu().is_evicted() {
+            self.gpu_mut().restore()?;
+            self.upload_tty_as_pixels().await?;
+        }
         let rendered_pixels = self.gpu_mut().render().await?;
+        self.track_gpu_memory().await;
 
         if self.is_upload_tty_as_pixels().await {
             if self.gpu().tty_pixels.dimensions().1 == 0 {
@@ -228,48 +491,50 @@ pub(crate) trait Shaderer: Sized {
 
         self.update_cursor().await?;
 
-        self.tattoy_mut().initialise_surface();
         self.tattoy_mut().opacity = self.get_opacity().await;
         self.tattoy_mut().layer = self.get_layer().await;
+        self.tattoy_mut().initialise_surface();
 
         let mut hashable_render = Vec::new();
         let is_upload_tty_as_pixels = self.is_upload_tty_as_pixels().await;
 
+        let tty_width = usize::from(self.tattoy().width);
         let tty_height_in_pixels = u32::from(self.tattoy().height) * 2;
-        for y in 0..tty_height_in_pixels {
-            for x in 0..self.tattoy().width {
-                let offset_for_reversal = 1;
-                let y_reversed = tty_height_in_pixels - y - offset_for_reversal;
-
-                let pixel_u8 = rendered_pixels
-                    .get_pixel_checked(x.into(), y_reversed)
-                    .context(format!("Couldn't get new pixel: {x}x{y_reversed}"))?
-                    .0;
-                let pixel = [
-                    f32::from(pixel_u8[0]) / 255.0,
-                    f32::from(pixel_u8[1]) / 255.0,
-                    f32::from(pixel_u8[2]) / 255.0,
-                    f32::from(pixel_u8[3]) / 255.0,
-                ];
-
-                if is_upload_tty_as_pixels {
-                    if self.are_pixels_different(x.into(), y_reversed, pixel_u8)? {
-                        if self.is_should_hash_render() {
-                            hashable_render.extend(
-                                Self::convert_pixel_to_binary(x, y_reversed, pixel_u8).to_vec(),
-                            );
-                        }
-                        self.tattoy_mut().surface.add_pixel(
-                            x.into(),
-                            y.try_into()?,
-                            pixel.into(),
-                        )?;
-                    }
-                } else {
-                    self.tattoy_mut()
-                        .surface
-                        .add_pixel(x.into(), y.try_into()?, pixel.into())?;
+        let extra_rows = rendered_pixels
+            .height()
+            .saturating_sub(tty_height_in_pixels);
+        let rows = crate::pixels::rows_bottom_up(&rendered_pixels).skip(extra_rows.try_into()?);
+        for (y, row) in (0..tty_height_in_pixels).zip(rows) {
+            if !is_upload_tty_as_pixels {
+                let colours = row
+                    .chunks_exact(crate::pixels::CHANNELS)
+                    .map(|pixel| crate::pixels::to_unit_rgba(pixel).into());
+                self.tattoy_mut()
+                    .surface
+                    .add_pixel_row(0, y.try_into()?, colours);
+                continue;
+            }
+
+            let offset_for_reversal = 1;
+            let y_reversed = tty_height_in_pixels - y - offset_for_reversal;
+
+            for (x, pixel_bytes) in (0..).zip(row.chunks_exact(crate::pixels::CHANNELS)) {
+                if usize::from(x) >= tty_width {
+                    break;
+                }
+                let pixel_u8: [u8; 4] = pixel_bytes.try_into()?;
+                if !self.are_pixels_different(x.into(), y_reversed, pixel_u8)? {
+                    continue;
                 }
+
+                if self.is_should_hash_render() {
+                    hashable_render
+                        .extend(Self::convert_pixel_to_binary(x, y_reversed, pixel_u8).to_vec());
+                }
+                let pixel = crate::pixels::to_unit_rgba(pixel_bytes);
+                self.tattoy_mut()
+                    .surface
+                    .add_pixel(x.into(), y.try_into()?, pixel.into())?;
             }
         }
 
@@ -283,6 +548,17 @@ pub(crate) trait Shaderer: Sized {
         Ok(())
     }
 
+    /// Show why the shader didn't compile, over the top of everything else.
+    async fn render_shader_error(&mut self) -> Result<()> {
+        self.tattoy_mut().opacity = 1.0;
+        self.tattoy_mut().layer = crate::layers::Group::Overlay.bounds().0;
+        self.tattoy_mut().initialise_surface();
+        if let Some(shader_error) = self.gpu().shader_error.clone() {
+            shader_error.draw(&mut self.tattoy_mut().surface);
+        }
+        self.tattoy_mut().send_output().await
+    }
+
     /// Convert the pixel to `u8`s so it can be hashed later.
     #[expect(
         clippy::as_conversions,
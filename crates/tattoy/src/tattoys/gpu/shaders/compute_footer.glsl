@@ -0,0 +1,10 @@
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    if (pixel.x >= int(iResolution.x) || pixel.y >= int(iResolution.y)) {
+        return;
+    }
+
+    vec4 state = vec4(0.0);
+    mainCompute(state, pixel);
+    imageStore(iComputeOutput, pixel, state);
+}
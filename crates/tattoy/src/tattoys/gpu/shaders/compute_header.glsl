@@ -0,0 +1,27 @@
+// Boilerplate for the optional compute shader, which runs before the fragment shader every frame.
+// Compute shaders define `mainCompute()`, which is called once for every pixel of the render, and
+// whatever it outputs can be read by the fragment shader with `iCompute()`.
+
+// This must match `WORKGROUP_SIZE` in `compute.rs`.
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(binding = 1) uniform texture2D iChannelTexture;
+layout(binding = 2) uniform sampler iChannel0;
+// What the compute shader wrote on the previous frame, see `iComputePrevious()`.
+layout(binding = 3) uniform texture2D iComputePreviousTexture;
+// Where the compute shader writes to. `mainCompute()` writes to its own pixel, but it can also be
+// written to directly with `imageStore()`, eg: for particles.
+layout(binding = 4, rgba16f) uniform writeonly image2D iComputeOutput;
+
+#define textureSampler texture
+
+// The same as `texture(iChannel0, coords)` in the fragment shader.
+vec4 textureSampler(sampler iChannelSampler, vec2 coords) {
+    return textureLod(sampler2D(iChannelTexture, iChannelSampler), coords, 0.0);
+}
+
+// What the compute shader wrote for the pixel on the previous frame. It's `vec4(0.0)` on the
+// first frame, and after the terminal is resized.
+vec4 iComputePrevious(ivec2 pixel) {
+    return texelFetch(sampler2D(iComputePreviousTexture, iChannel0), pixel, 0);
+}
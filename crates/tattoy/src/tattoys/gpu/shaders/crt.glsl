@@ -0,0 +1,69 @@
+// Tattoy's built-in CRT post-processing pass. It works on the pixel representation of the
+// terminal in `iChannel0`.
+//
+// The strength of each effect is set by Tattoy with the following defines, all from 0.0 to 1.0.
+#ifndef SCANLINES
+#define SCANLINES 0.5
+#endif
+#ifndef CURVATURE
+#define CURVATURE 0.1
+#endif
+#ifndef CHROMATIC_ABERRATION
+#define CHROMATIC_ABERRATION 0.3
+#endif
+#ifndef VIGNETTE
+#define VIGNETTE 0.3
+#endif
+#ifndef GLOW
+#define GLOW 0.3
+#endif
+
+// Bend the coordinates outwards, like the glass of an old CRT.
+vec2 curve(vec2 uv) {
+    vec2 centered = uv * 2.0 - 1.0;
+    vec2 offset = abs(centered.yx) * CURVATURE * 0.5;
+    centered = centered + centered * offset * offset;
+    return centered * 0.5 + 0.5;
+}
+
+// Sample the terminal, splitting the red and blue channels apart.
+vec3 aberrated(vec2 uv) {
+    vec2 shift = vec2(CHROMATIC_ABERRATION / iResolution.x, 0.0);
+    float red = texture(iChannel0, uv + shift).r;
+    float green = texture(iChannel0, uv).g;
+    float blue = texture(iChannel0, uv - shift).b;
+    return vec3(red, green, blue);
+}
+
+// Blur the brightest parts of the terminal into their surroundings.
+vec3 glow(vec2 uv) {
+    vec3 total = vec3(0.0);
+    for (int x = -2; x <= 2; x++) {
+        for (int y = -2; y <= 2; y++) {
+            vec2 offset = vec2(float(x), float(y)) / iResolution.xy;
+            total += texture(iChannel0, uv + offset).rgb;
+        }
+    }
+    return total / 25.0;
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = curve(fragCoord / iResolution.xy);
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        fragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec3 colour = aberrated(uv);
+    colour += glow(uv) * GLOW;
+
+    // Every other "pixel" row is darkened. Tattoy has 2 pixels per terminal row.
+    float scanline = mod(floor(fragCoord.y), 2.0);
+    colour *= 1.0 - (SCANLINES * 0.5 * scanline);
+
+    vec2 from_center = uv - 0.5;
+    float vignette = 1.0 - dot(from_center, from_center) * VIGNETTE * 2.0;
+    colour *= clamp(vignette, 0.0, 1.0);
+
+    fragColor = vec4(colour, 1.0);
+}
@@ -0,0 +1,37 @@
+// Tattoy's built-in "comet" cursor preset: a tapering trail that follows the cursor when it moves.
+//
+// Tattoy sets the colour and size with the following defines. The size is in cells.
+#ifndef PRESET_COLOR
+#define PRESET_COLOR iCurrentCursorColor.rgb
+#endif
+#ifndef PRESET_SIZE
+#define PRESET_SIZE 1.0
+#endif
+
+// How long, in seconds, the trail takes to catch up with the cursor.
+const float DURATION = 0.4;
+
+// The centre of a cursor, in cells. Tattoy's "pixels" are half a cell tall.
+vec2 cursorCentre(vec4 cursor) {
+    return (cursor.xy + vec2(cursor.z, -cursor.w) * 0.5) / vec2(1.0, 2.0);
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = texture(iChannel0, fragCoord / iResolution.xy);
+
+    vec2 point = fragCoord / vec2(1.0, 2.0);
+    vec2 head = cursorCentre(iInterpolatedCursor);
+    float progress = clamp((iTime - iTimeCursorChange) / DURATION, 0.0, 1.0);
+    vec2 tail = mix(cursorCentre(iPreviousCursor), head, progress);
+
+    // How far along the trail, from the tail at `0.0` to the head at `1.0`, the point is.
+    vec2 trail = head - tail;
+    float along = clamp(dot(point - tail, trail) / max(dot(trail, trail), 0.0001), 0.0, 1.0);
+    float distanceFromTrail = length(point - tail - trail * along);
+
+    float width = PRESET_SIZE * 0.5 * mix(0.2, 1.0, along);
+    float strength = (1.0 - smoothstep(width * 0.5, width, distanceFromTrail)) * along;
+    strength = max(strength, 1.0 - smoothstep(PRESET_SIZE * 0.25, PRESET_SIZE * 0.5, length(point - head)));
+
+    fragColor = mix(fragColor, vec4(PRESET_COLOR, 1.0), strength);
+}
@@ -0,0 +1,36 @@
+// Tattoy's built-in "ghost" cursor preset: a faint copy of the cursor that lingers where the
+// cursor was, and slowly fades away.
+//
+// Tattoy sets the colour and size with the following defines. The size is in cells.
+#ifndef PRESET_COLOR
+#define PRESET_COLOR iCurrentCursorColor.rgb
+#endif
+#ifndef PRESET_SIZE
+#define PRESET_SIZE 1.0
+#endif
+
+// How long, in seconds, the ghost takes to fade away.
+const float DURATION = 1.0;
+
+// The centre of a cursor, in cells. Tattoy's "pixels" are half a cell tall.
+vec2 cursorCentre(vec4 cursor) {
+    return (cursor.xy + vec2(cursor.z, -cursor.w) * 0.5) / vec2(1.0, 2.0);
+}
+
+// How much a point is inside a cell-sized box, with soft edges.
+float box(vec2 point, vec2 centre) {
+    vec2 distances = abs(point - centre) - vec2(0.5) * PRESET_SIZE;
+    float outside = length(max(distances, 0.0)) + min(max(distances.x, distances.y), 0.0);
+    return 1.0 - smoothstep(0.0, 0.5, outside);
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = texture(iChannel0, fragCoord / iResolution.xy);
+
+    vec2 point = fragCoord / vec2(1.0, 2.0);
+    float progress = clamp((iTime - iTimeCursorChange) / DURATION, 0.0, 1.0);
+    float ghost = box(point, cursorCentre(iPreviousCursor)) * (1.0 - progress) * 0.5;
+    float cursor = box(point, cursorCentre(iInterpolatedCursor)) * 0.8;
+
+    fragColor = mix(fragColor, vec4(PRESET_COLOR, 1.0), max(ghost, cursor));
+}
@@ -0,0 +1,25 @@
+// Tattoy's built-in "glow_pulse" cursor preset: a soft glow around the cursor that gently pulses.
+//
+// Tattoy sets the colour and size with the following defines. The size is in cells.
+#ifndef PRESET_COLOR
+#define PRESET_COLOR iCurrentCursorColor.rgb
+#endif
+#ifndef PRESET_SIZE
+#define PRESET_SIZE 1.0
+#endif
+
+// The centre of a cursor, in cells. Tattoy's "pixels" are half a cell tall.
+vec2 cursorCentre(vec4 cursor) {
+    return (cursor.xy + vec2(cursor.z, -cursor.w) * 0.5) / vec2(1.0, 2.0);
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = texture(iChannel0, fragCoord / iResolution.xy);
+
+    vec2 offset = fragCoord / vec2(1.0, 2.0) - cursorCentre(iCurrentCursor);
+    float pulse = 0.75 + 0.25 * sin(iTime * 3.0);
+    float radius = PRESET_SIZE * 1.5 * pulse;
+    float strength = 1.0 - smoothstep(0.0, radius, length(offset));
+
+    fragColor = mix(fragColor, vec4(PRESET_COLOR, 1.0), strength * 0.8);
+}
@@ -0,0 +1,29 @@
+// Tattoy's built-in "ripple" cursor preset: a ring that spreads out from the cursor whenever it
+// moves.
+//
+// Tattoy sets the colour and size with the following defines. The size is in cells.
+#ifndef PRESET_COLOR
+#define PRESET_COLOR iCurrentCursorColor.rgb
+#endif
+#ifndef PRESET_SIZE
+#define PRESET_SIZE 1.0
+#endif
+
+// How long, in seconds, each ripple lasts.
+const float DURATION = 0.6;
+
+// The centre of a cursor, in cells. Tattoy's "pixels" are half a cell tall.
+vec2 cursorCentre(vec4 cursor) {
+    return (cursor.xy + vec2(cursor.z, -cursor.w) * 0.5) / vec2(1.0, 2.0);
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = texture(iChannel0, fragCoord / iResolution.xy);
+
+    vec2 offset = fragCoord / vec2(1.0, 2.0) - cursorCentre(iCurrentCursor);
+    float progress = clamp((iTime - iTimeCursorChange) / DURATION, 0.0, 1.0);
+    float radius = progress * PRESET_SIZE * 3.0;
+    float ring = 1.0 - smoothstep(0.0, 0.6, abs(length(offset) - radius));
+
+    fragColor = mix(fragColor, vec4(PRESET_COLOR, 1.0), ring * (1.0 - progress));
+}
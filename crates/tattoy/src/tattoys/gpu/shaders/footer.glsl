@@ -1,3 +1,28 @@
+#undef iResolution
+
 void main() {
-	mainImage(fragColor, gl_FragCoord.xy);
+	vec2 coord = gl_FragCoord.xy;
+#ifdef LETTERBOX_ASPECT
+	// `iResolution.z` is the aspect ratio of a pixel, so this is the shape that the user sees.
+	float terminal_aspect = iResolution.x * iResolution.z / iResolution.y;
+	vec2 box = iResolution.xy;
+	if (terminal_aspect > LETTERBOX_ASPECT) {
+		box.x = iResolution.y * LETTERBOX_ASPECT / iResolution.z;
+	} else {
+		box.y = iResolution.x * iResolution.z / LETTERBOX_ASPECT;
+	}
+	iLetterboxResolution = vec3(box, iResolution.z);
+
+	coord -= floor((iResolution.xy - box) / 2.0);
+	if (any(lessThan(coord, vec2(0.0))) || any(greaterThanEqual(coord, box))) {
+		// The bars either side of the box are left empty.
+		fragColor = vec4(0.0);
+		return;
+	}
+#endif
+#ifdef FOLLOW_SCROLLBACK
+	// Scrolling back moves the text down the screen, so the shader moves down with it.
+	coord.y += iScrollOffset;
+#endif
+	mainImage(fragColor, coord);
 }
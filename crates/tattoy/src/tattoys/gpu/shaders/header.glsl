@@ -1,24 +1,38 @@
 // The pointer into which the final pixel colour is writen.
 out vec4 fragColor;
 
-// These are the standard variables used by all Shadertoy shaders.
-layout(binding = 0) uniform Variables
-{
-    vec3 iResolution;
-    vec2 iMouse;
-    vec2 iCursor;
-    float iTime;
-    int iFrame;
-
-    vec4 iCurrentCursor;
-    vec4 iPreviousCursor;
-    vec4 iCurrentCursorColor;
-    vec4 iPreviousCursorColor;
-    float iTimeCursorChange;
-};
+// The uniform variables, like `iResolution` and `iTime`, are in `variables.glsl` so that they
+// can be shared with compute shaders.
 
 layout(binding = 1) uniform texture2D iChannelTexture;
 layout(binding = 2) uniform sampler iChannel0;
+// A single channel texture where `1.0` means the pixel is covered by the terminal's text, only
+// uploaded when `upload_text_mask` is enabled.
+layout(binding = 3) uniform texture2D iTextMaskTexture;
+// The colours and attributes of every cell, only uploaded when `upload_cell_metadata` is enabled.
+layout(binding = 4) uniform texture2D iCellMetadataTexture;
+// Shadertoy's keyboard texture. See `iKeyDown()`, `iKeyPressed()` and `iKeyToggled()`.
+layout(binding = 5) uniform texture2D iKeyboardTexture;
+// The cells that text most recently appeared in, only uploaded when `upload_cell_changes` is
+// enabled. Each change is `vec4(column, row, time, 0.0)`, where the first row is the top of the
+// terminal and the time is the `iTime` at which the text appeared. See `iCellCentre()`.
+layout(std430, binding = 6) readonly buffer CellChanges {
+    int iCellChangeCount;
+    vec4 iCellChanges[64];
+};
+// What the compute shader wrote on this frame, only used when `compute_path` is set. See
+// `iCompute()`.
+layout(binding = 7) uniform texture2D iComputeTexture;
+
+// Attribute flags of cells, see `iCellFlags()`.
+#define CELL_GLYPH 1
+#define CELL_BOLD 2
+#define CELL_ITALIC 4
+#define CELL_UNDERLINE 8
+#define CELL_STRIKETHROUGH 16
+#define CELL_REVERSE 32
+#define CELL_BLINK 64
+#define CELL_INVISIBLE 128
 
 #define textureSampler texture
 #define textureSamplerLod textureLod
@@ -37,3 +51,72 @@ vec4 textureSampler(sampler iChannelSampler, vec2 coords, float bias) {
 vec4 textureSamplerLod(sampler iChannelSampler, vec2 coords, float lod) {
     return textureLod(sampler2D(iChannelTexture, iChannelSampler), coords, lod);
 }
+
+// What the compute shader wrote for the pixel at the given fragment coordinates.
+vec4 iCompute(vec2 fragCoord) {
+    return texelFetch(sampler2D(iComputeTexture, iChannel0), ivec2(fragCoord), 0);
+}
+
+// How much the pixel at the given coordinates is covered by text, from `0.0` to `1.0`.
+float iTextMask(vec2 coords) {
+    return texture(sampler2D(iTextMaskTexture, iChannel0), coords).r;
+}
+
+// The cell that contains the given fragment coordinates. The first row is the top of the terminal.
+ivec2 iCellAt(vec2 fragCoord) {
+    int rows = int(iResolution.y) / 2;
+    return ivec2(int(fragCoord.x), rows - 1 - int(fragCoord.y) / 2);
+}
+
+// The centre of a cell, in the same coordinates as `fragCoord`.
+vec2 iCellCentre(ivec2 cell) {
+    int rows = int(iResolution.y) / 2;
+    return vec2(float(cell.x) + 0.5, float((rows - 1 - cell.y) * 2) + 1.0);
+}
+
+// The foreground colour of a cell.
+vec4 iCellForeground(ivec2 cell) {
+    return texelFetch(sampler2D(iCellMetadataTexture, iChannel0), ivec2(cell.x * 2, cell.y), 0);
+}
+
+// The background colour of a cell.
+vec3 iCellBackground(ivec2 cell) {
+    return texelFetch(sampler2D(iCellMetadataTexture, iChannel0), ivec2(cell.x * 2 + 1, cell.y), 0).rgb;
+}
+
+// The attribute flags of a cell, eg: `(iCellFlags(cell) & CELL_BOLD) != 0`.
+int iCellFlags(ivec2 cell) {
+    float alpha = texelFetch(sampler2D(iCellMetadataTexture, iChannel0), ivec2(cell.x * 2 + 1, cell.y), 0).a;
+    return int(round(alpha * 255.0));
+}
+
+// Shadertoy key codes, for use with the keyboard functions.
+#define KEY_LEFT 37
+#define KEY_UP 38
+#define KEY_RIGHT 39
+#define KEY_DOWN 40
+#define KEY_SPACE 32
+
+// Whether the key is being held down. Without the kitty keyboard protocol keys are only held for
+// a single frame, because there's no way to know when they're released.
+bool iKeyDown(int key) {
+    return texelFetch(sampler2D(iKeyboardTexture, iChannel0), ivec2(key, 0), 0).r > 0.5;
+}
+
+// Whether the key was pressed since the last frame.
+bool iKeyPressed(int key) {
+    return texelFetch(sampler2D(iKeyboardTexture, iChannel0), ivec2(key, 1), 0).r > 0.5;
+}
+
+// Whether the key has been pressed an odd number of times.
+bool iKeyToggled(int key) {
+    return texelFetch(sampler2D(iKeyboardTexture, iChannel0), ivec2(key, 2), 0).r > 0.5;
+}
+
+// When the shader has an `aspect_ratio`, it's rendered into a box of that shape in the middle of
+// the terminal, rather than being stretched. So the shader's `iResolution` is the size of the box.
+// This must stay at the end of the header, so that the helpers above use the whole terminal.
+#ifdef LETTERBOX_ASPECT
+vec3 iLetterboxResolution;
+#define iResolution iLetterboxResolution
+#endif
@@ -4,6 +4,7 @@
 float circleSDF(vec2 p)
 {
     vec2 center = (2.0 * iMouse.xy - iResolution.xy) / iResolution.y;
+    center.x *= iResolution.z;
     float radius = 0.1;
     return length(p - center) - radius;
 }
@@ -50,9 +51,12 @@ void mainImage(out vec4 fragColor, in vec2 fragCoord)
 {
     // Convert fragment coordinate to normalized device coordinates (NDC)
     vec2 p = (2.0 * fragCoord - iResolution.xy) / iResolution.y;
+    // Correct for pixels that aren't square, see `tattoy calibrate`.
+    p.x *= iResolution.z;
 
     // Light position in screen space, normalized to [-1, 1]
     vec2 ligPos = (2.0 * vec2(iCursor.x + 0.5, iCursor.y - 1) - iResolution.xy) / iResolution.y;
+    ligPos.x *= iResolution.z;
 
     float lightSize = 0.001;
 
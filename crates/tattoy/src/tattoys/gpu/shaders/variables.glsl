@@ -0,0 +1,39 @@
+// The variables that are shared by the fragment shader and the optional compute shader.
+//
+// These are the standard variables used by all Shadertoy shaders.
+layout(binding = 0) uniform Variables
+{
+    vec3 iResolution;
+    vec2 iMouse;
+    vec2 iCursor;
+    float iTime;
+    int iFrame;
+
+    vec4 iCurrentCursor;
+    vec4 iPreviousCursor;
+    vec4 iCurrentCursorColor;
+    vec4 iPreviousCursorColor;
+    float iTimeCursorChange;
+    // The time at which the visual bell last rang.
+    float iTimeBell;
+    // The same as `iCurrentCursor`, but smoothly moving between cells rather than jumping. See
+    // `interpolation_duration` in the `[animated_cursor]` config.
+    vec4 iInterpolatedCursor;
+
+    // The time at which the shell last finished running a command, and that command's exit
+    // status. Only reported when shell integration is enabled.
+    float iTimeCommandFinished;
+    int iCommandExitStatus;
+    // The time at which the user's terminal last gained or lost focus, and whether it has focus
+    // now: `1` when it does and `0` when it doesn't.
+    float iTimeFocusChange;
+    int iFocused;
+
+    // Whether the user prefers reduced motion: `1` when they do and `0` when they don't. `iTime`
+    // already runs slower when they do, but shaders can also calm down flashes and the like.
+    int iReducedMotion;
+
+    // How far, in pixels, the user has scrolled back through the scrollback. Effects that
+    // decorate particular lines can add it to `fragCoord.y` to move with the text.
+    float iScrollOffset;
+};
@@ -0,0 +1,129 @@
+//! A single channel texture that marks which pixels are underneath the terminal's text. It lets
+//! shaders avoid drawing over text, or treat the areas under text differently, eg: a subtle glow
+//! behind characters.
+
+use shadow_terminal::termwiz;
+
+/// The value of a pixel that's covered by text.
+const TEXT: u8 = 255;
+
+/// The value of a pixel that isn't covered by text.
+const NO_TEXT: u8 = 0;
+
+/// Build the text mask for the given screen cells. Every cell is 2 pixels high, because of the
+/// UTF8 half-block trick, and the image is flipped vertically to match the GPU's coordinates.
+pub(crate) fn from_cells(cells: &[&[termwiz::cell::Cell]]) -> image::GrayImage {
+    let pixels_per_line = 2;
+    let width = cells.first().map_or(0, |line| line.len());
+    let height = cells.len() * pixels_per_line;
+
+    image::GrayImage::from_fn(
+        u32::try_from(width).unwrap_or_default(),
+        u32::try_from(height).unwrap_or_default(),
+        |x, y| {
+            let Ok(y_reversed) = usize::try_from(y).map(|pixel_y| height - pixel_y - 1) else {
+                return [NO_TEXT].into();
+            };
+            let maybe_cell = usize::try_from(x).ok().and_then(|cell_x| {
+                cells
+                    .get(y_reversed.div_euclid(pixels_per_line))
+                    .and_then(|line| line.get(cell_x))
+            });
+            match maybe_cell {
+                Some(cell) if is_text(cell) => [TEXT].into(),
+                _ => [NO_TEXT].into(),
+            }
+        },
+    )
+}
+
+/// Does the cell contain a visible glyph? Tattoy's own half-block pixels don't count as text.
+pub(crate) fn is_text(cell: &termwiz::cell::Cell) -> bool {
+    let character = cell.str();
+    !character.trim().is_empty() && character != "▀" && character != "▄"
+}
+
+impl super::pipeline::GPU {
+    /// Update the GPU with the current text mask.
+    pub fn update_text_mask_texture_data(&self) {
+        let (mask_width, mask_height) = self.text_mask.dimensions();
+        let output_image_size = self.get_image_size();
+        if mask_width != u32::from(output_image_size.0)
+            || mask_height != u32::from(output_image_size.1)
+        {
+            return;
+        }
+
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.text_mask_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.text_mask,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(mask_width),
+                rows_per_image: Some(mask_height),
+            },
+            wgpu::Extent3d {
+                width: mask_width,
+                height: mask_height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Recreate the text mask texture. Most likely occurs when the user's terminal resizes.
+    pub fn recreate_text_mask_texture(&mut self) {
+        let image_size = self.get_image_size();
+        self.text_mask_texture = self
+            .device
+            .create_texture(&Self::text_mask_texture_descriptor(
+                image_size.0,
+                image_size.1,
+            ));
+    }
+
+    /// The texture descriptor for the text mask texture.
+    pub fn text_mask_texture_descriptor(
+        width: u16,
+        height: u16,
+    ) -> wgpu::TextureDescriptor<'static> {
+        wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: width.into(),
+                height: height.into(),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("text_mask_texture"),
+            view_formats: &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn masks_only_text() {
+        let mut surface = termwiz::surface::Surface::new(3, 2);
+        surface.add_change("a ▀");
+        let mask = from_cells(&surface.get_screen_cells());
+
+        assert_eq!(mask.dimensions(), (3, 4));
+        // The first line of the terminal is at the bottom of the image.
+        assert_eq!(mask.get_pixel(0, 3).0, [TEXT]);
+        assert_eq!(mask.get_pixel(0, 2).0, [TEXT]);
+        assert_eq!(mask.get_pixel(1, 3).0, [NO_TEXT]);
+        assert_eq!(mask.get_pixel(2, 3).0, [NO_TEXT]);
+        assert_eq!(mask.get_pixel(0, 0).0, [NO_TEXT]);
+    }
+}
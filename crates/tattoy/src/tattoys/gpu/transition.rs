@@ -0,0 +1,77 @@
+//! Crossfade from an old shader to a new one, rather than making a hard cut between them.
+
+/// An in-progress crossfade between two shaders.
+pub(crate) struct Transition {
+    /// The pipeline of the shader that is being faded out.
+    pub pipeline: wgpu::RenderPipeline,
+    /// When the transition started.
+    started: std::time::Instant,
+    /// How long the transition lasts, in seconds.
+    duration: f32,
+}
+
+impl Transition {
+    /// Instantiate
+    pub fn new(pipeline: wgpu::RenderPipeline, duration: f32) -> Self {
+        Self {
+            pipeline,
+            started: std::time::Instant::now(),
+            duration,
+        }
+    }
+
+    /// How far through the transition we are, from `0.0` to `1.0`.
+    pub fn progress(&self) -> f32 {
+        progress(self.started.elapsed().as_secs_f32(), self.duration)
+    }
+
+    /// Has the new shader completely replaced the old one?
+    pub fn is_finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+
+    /// Blend the old shader's render into the new shader's render.
+    pub fn crossfade(
+        &self,
+        old: &image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+        new: &image::ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    ) -> image::ImageBuffer<image::Rgba<u8>, Vec<u8>> {
+        let amount = self.progress();
+        if old.dimensions() == new.dimensions() {
+            let mut blended = image::RgbaImage::new(new.width(), new.height());
+            crate::pixels::crossfade_row(old, new, amount, &mut blended);
+            return blended;
+        }
+
+        image::RgbaImage::from_fn(new.width(), new.height(), |x, y| {
+            let Some(new_pixel) = new.get_pixel_checked(x, y) else {
+                return [0, 0, 0, 0].into();
+            };
+            let Some(old_pixel) = old.get_pixel_checked(x, y) else {
+                return *new_pixel;
+            };
+            crate::compositor::Compositor::crossfade_pixel(old_pixel.0, new_pixel.0, amount).into()
+        })
+    }
+}
+
+/// The eased progress of a transition, from `0.0` to `1.0`.
+fn progress(elapsed: f32, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        return 1.0;
+    }
+    crate::utils::smoothstep(0.0, duration, elapsed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transition_progress() {
+        assert!(progress(0.0, 1.0) < f32::EPSILON);
+        assert!((progress(0.5, 1.0) - 0.5).abs() < f32::EPSILON);
+        assert!((progress(2.0, 1.0) - 1.0).abs() < f32::EPSILON);
+        assert!((progress(0.0, 0.0) - 1.0).abs() < f32::EPSILON);
+    }
+}
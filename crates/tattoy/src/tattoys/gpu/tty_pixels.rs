@@ -0,0 +1,106 @@
+//! A cache of the TTY converted to pixels, shared by all the shader tattoys.
+//!
+//! Every shader with `upload_tty_as_pixels` enabled needs the same image of the TTY. So the first
+//! shader to need an image of the latest PTY output builds it, and the others reuse it.
+//!
+//! Each shader has its own copy of the screen, which can briefly be behind the PTY whilst the
+//! shader still has output messages to handle. So rather than keying images by the global PTY
+//! sequence, which would let a shader cache an old screen's image as the newest one, images are
+//! keyed by a fingerprint of the screen that they were built from. Fingerprinting only looks at
+//! each cell once, so it's much cheaper than building the image.
+
+use std::hash::{Hash as _, Hasher as _};
+
+use shadow_terminal::termwiz;
+
+/// Which image of the TTY is cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Key {
+    /// A fingerprint of everything about the screen that affects its image.
+    pub fingerprint: u64,
+    /// Whether text is drawn as its foreground colour, rather than its background colour.
+    pub is_characters: bool,
+}
+
+impl Key {
+    /// The key of the image of a screen.
+    pub fn new(
+        screen: &termwiz::surface::Surface,
+        default_background: termwiz::color::SrgbaTuple,
+        is_characters: bool,
+    ) -> Self {
+        let mut hasher = std::hash::DefaultHasher::new();
+        screen.dimensions().hash(&mut hasher);
+        default_background.to_srgb_u8().hash(&mut hasher);
+        for line in screen.get_screen_cells() {
+            for cell in line {
+                // Only text is drawn differently from blank cells.
+                let is_text = is_characters && cell.str() != " ";
+                is_text.hash(&mut hasher);
+                if is_text {
+                    cell.attrs().foreground().hash(&mut hasher);
+                } else {
+                    cell.attrs().background().hash(&mut hasher);
+                }
+            }
+        }
+
+        Self {
+            fingerprint: hasher.finish(),
+            is_characters,
+        }
+    }
+}
+
+/// The most recent TTY images, one for each way of drawing text.
+#[derive(Debug, Default)]
+pub(crate) struct Cache {
+    /// The cached images and their fingerprints, by whether they draw text.
+    images: std::collections::BTreeMap<bool, (u64, image::RgbaImage)>,
+}
+
+impl Cache {
+    /// Get a copy of the cached image, if there's one for the key.
+    pub fn get(&self, key: Key) -> Option<image::RgbaImage> {
+        let (fingerprint, image) = self.images.get(&key.is_characters)?;
+        (*fingerprint == key.fingerprint).then(|| image.clone())
+    }
+
+    /// Cache an image, replacing the older image that drew text the same way.
+    pub fn insert(&mut self, key: Key, image: image::RgbaImage) {
+        self.images
+            .insert(key.is_characters, (key.fingerprint, image));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(text: &str, is_characters: bool) -> Key {
+        let mut screen = termwiz::surface::Surface::new(3, 1);
+        screen.add_change(text);
+        Key::new(
+            &screen,
+            termwiz::color::SrgbaTuple(0.0, 0.0, 0.0, 1.0),
+            is_characters,
+        )
+    }
+
+    #[test]
+    fn images_are_only_reused_for_the_same_screen() {
+        let mut cache = Cache::default();
+        cache.insert(key("ab", true), image::RgbaImage::new(3, 2));
+
+        assert!(cache.get(key("ab", true)).is_some());
+        assert!(cache.get(key("a", true)).is_none());
+        assert!(cache.get(key("ab", false)).is_none());
+    }
+
+    #[test]
+    fn only_colours_change_the_image() {
+        assert_eq!(key("ab", true), key("xy", true));
+        assert_eq!(key("a", false), key("ab", false));
+        assert_ne!(key("a", true), key("ab", true));
+    }
+}
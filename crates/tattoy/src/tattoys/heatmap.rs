@@ -0,0 +1,275 @@
+//! A heatmap of where on the screen things happen. Every time a cell's text changes it gets a
+//! little hotter, for the whole session. The `toggle_heatmap` keybinding shows and hides it.
+//!
+//! Changes are found in the same way as they are for showing repaints. The heat is relative to
+//! the hottest cell, so even a quiet session has a hot spot.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the heatmap. It's beneath the terminal's text so that it's still readable.
+const LAYER: i16 = crate::layers::Group::Background.layer(8);
+
+/// User-configurable settings for the heatmap.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the heatmap. It still needs showing with the `toggle_heatmap` keybinding.
+    pub enabled: bool,
+    /// The opacity of the heatmap.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// The colour of cells that have hardly changed.
+    pub cold_colour: crate::surface::Colour,
+    /// The colour of the cells that have changed the most.
+    pub hot_colour: crate::surface::Colour,
+    /// Whether the heatmap is shown at startup, without needing the keybinding.
+    pub is_shown: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.4,
+            cold_colour: (0.1, 0.2, 0.9, 0.3),
+            hot_colour: (0.9, 0.1, 0.1, 1.0),
+            is_shown: false,
+        }
+    }
+}
+
+/// How many times each cell has changed.
+#[derive(Debug, Default)]
+struct Counts {
+    /// The counts, by row.
+    rows: Vec<Vec<u32>>,
+    /// The highest count of any cell.
+    max: u32,
+}
+
+impl Counts {
+    /// Count the cells that changed. Cells that are outside the counts, because they're from a
+    /// bigger screen, grow the counts to fit.
+    fn add(&mut self, edits: &[(usize, usize, super::repaints::Edit)]) {
+        for &(x, y, _) in edits {
+            if self.rows.len() <= y {
+                self.rows.resize_with(y + 1, Vec::new);
+            }
+            let Some(row) = self.rows.get_mut(y) else {
+                continue;
+            };
+            if row.len() <= x {
+                row.resize(x + 1, 0);
+            }
+            if let Some(count) = row.get_mut(x) {
+                *count = count.saturating_add(1);
+                self.max = self.max.max(*count);
+            }
+        }
+    }
+
+    /// How hot a cell is, from 0.0 for never changed to 1.0 for the hottest cell.
+    fn heat(&self, x: usize, y: usize) -> f32 {
+        let count = self
+            .rows
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or(0);
+        if self.max == 0 {
+            return 0.0;
+        }
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_possible_truncation,
+            reason = "The ratio is always between 0 and 1"
+        )]
+        let heat = (f64::from(count) / f64::from(self.max)) as f32;
+        heat
+    }
+}
+
+/// The colour of some heat, between the cold and hot colours.
+fn heat_colour(
+    heat: f32,
+    cold: crate::surface::Colour,
+    hot: crate::surface::Colour,
+) -> crate::surface::Colour {
+    let mix = |from: f32, to: f32| (to - from).mul_add(heat, from);
+    (
+        mix(cold.0, hot.0),
+        mix(cold.1, hot.1),
+        mix(cold.2, hot.2),
+        mix(cold.3, hot.3),
+    )
+}
+
+/// `Heatmap`
+pub(crate) struct Heatmap {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The screen as it was after the previous update.
+    previous: super::repaints::Cells,
+    /// How many times each cell has changed.
+    counts: Counts,
+    /// Whether the heatmap is shown.
+    is_shown: bool,
+    /// Whether something has changed that needs rendering.
+    is_dirty: bool,
+}
+
+impl Heatmap {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let config = state.config.read().await.heatmap.clone();
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "heatmap".to_owned(),
+            state,
+            LAYER,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_below_text();
+        Self {
+            tattoy,
+            previous: Vec::new(),
+            counts: Counts::default(),
+            is_shown: config.is_shown,
+            is_dirty: true,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut heatmap = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = heatmap.tattoy.sleep_until_next_frame_tick() => {
+                    heatmap.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    heatmap.handle_protocol_message(&message);
+                    let is_changed = super::tattoyer::Tattoyer::is_screen_output_changed(&message);
+                    heatmap.tattoy.handle_common_protocol_messages(message)?;
+                    if is_changed {
+                        heatmap.compare();
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    fn handle_protocol_message(&mut self, message: &crate::run::Protocol) {
+        if self.is_shown && super::tattoyer::Tattoyer::is_screen_output_changed(message) {
+            self.is_dirty = true;
+        }
+
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only care about a few messages"
+        )]
+        match message {
+            crate::run::Protocol::KeybindEvent(
+                crate::config::input::KeybindingAction::ToggleHeatmap,
+            ) => {
+                self.is_shown = !self.is_shown;
+                tracing::debug!("Toggling the heatmap to: {}", self.is_shown);
+                self.is_dirty = true;
+            }
+            crate::run::Protocol::Config(_) => self.is_dirty = true,
+            _ => (),
+        }
+    }
+
+    /// Count the cells that the latest update to the screen changed.
+    fn compare(&mut self) {
+        let current = super::repaints::snapshot(&mut self.tattoy);
+        if super::repaints::is_same_size(&self.previous, &current) {
+            self.counts
+                .add(&super::repaints::diff(&self.previous, &current));
+        }
+        self.previous = current;
+    }
+
+    /// Render if anything has changed.
+    async fn tick(&mut self) -> Result<()> {
+        if !self.is_dirty {
+            return Ok(());
+        }
+        self.is_dirty = false;
+
+        let config = self.tattoy.state.config.read().await.heatmap.clone();
+        self.render(&config).await
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config) -> Result<()> {
+        if !self.is_shown || self.tattoy.is_scrolling() {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+        for y in 0..usize::from(self.tattoy.height) {
+            for x in 0..usize::from(self.tattoy.width) {
+                let heat = self.counts.heat(x, y);
+                if heat <= 0.0 {
+                    continue;
+                }
+                let colour = heat_colour(heat, config.cold_colour, config.hot_colour);
+                self.tattoy
+                    .surface
+                    .add_text(x, y, " ".to_owned(), Some(colour), None);
+            }
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn the_hottest_cell_is_the_most_changed() {
+        let added = super::super::repaints::Edit::Added;
+        let mut counts = Counts::default();
+        counts.add(&[(1, 0, added), (3, 2, added), (3, 2, added)]);
+        assert_eq!(counts.max, 2);
+        assert!((counts.heat(3, 2) - 1.0).abs() < f32::EPSILON);
+        assert!((counts.heat(1, 0) - 0.5).abs() < f32::EPSILON);
+        assert!(counts.heat(0, 0).abs() < f32::EPSILON);
+        assert!(counts.heat(99, 99).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn heat_goes_from_cold_to_hot() {
+        let cold = (0.0, 0.0, 1.0, 0.5);
+        let hot = (1.0, 0.0, 0.0, 1.0);
+        assert_eq!(heat_colour(0.0, cold, hot), cold);
+        assert_eq!(heat_colour(1.0, cold, hot), hot);
+        let warm = heat_colour(0.5, cold, hot);
+        assert!((warm.0 - 0.5).abs() < f32::EPSILON);
+        assert!((warm.3 - 0.75).abs() < f32::EPSILON);
+    }
+}
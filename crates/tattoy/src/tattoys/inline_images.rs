@@ -0,0 +1,346 @@
+//! Inline images from iTerm2's `OSC 1337 File` sequences, like the ones that `imgcat` sends.
+//!
+//! The sequences never reach the user's terminal, because the shadow terminal parses them. But it
+//! does attach each image to the cells that it covers, just like iTerm2 or WezTerm do. So the
+//! images are drawn from those cells, which means that they're always where the cursor was, and
+//! that they scroll with the rest of the output. Each cell only has a few pixels, depending on
+//! `render.pixel_glyphs`, so the images are shown at a much lower resolution than the original.
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+
+/// The layer of the images. They're part of the PTY's output, so they're just above its text.
+const LAYER: i16 = crate::layers::Group::Effects.layer(1);
+
+/// User-configurable settings for inline images.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable inline images.
+    pub enabled: bool,
+    /// The opacity of the images.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// The part of an image that covers a cell, in texture coordinates from `0.0` to `1.0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Region {
+    /// The left edge.
+    left: f32,
+    /// The top edge.
+    top: f32,
+    /// The right edge.
+    right: f32,
+    /// The bottom edge.
+    bottom: f32,
+}
+
+impl Region {
+    /// A part of the region, when it's split into a grid, for the pixels of a cell.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "Pixel encoders only have small blocks"
+    )]
+    fn part(self, column: usize, row: usize, (columns, rows): (usize, usize)) -> Self {
+        let width = (self.right - self.left) / columns.max(1) as f32;
+        let height = (self.bottom - self.top) / rows.max(1) as f32;
+        let left = (column as f32).mul_add(width, self.left);
+        let top = (row as f32).mul_add(height, self.top);
+        Self {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        }
+    }
+}
+
+/// The pixel of an image at a texture coordinate.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss,
+    reason = "Texture coordinates are clamped and images are never that big"
+)]
+fn to_pixel(fraction: f32, size: u32) -> u32 {
+    ((fraction.clamp(0.0, 1.0) * size as f32) as u32).min(size.saturating_sub(1))
+}
+
+/// The average colour of a region of an image.
+#[expect(
+    clippy::as_conversions,
+    clippy::cast_precision_loss,
+    reason = "The sums are only used to get an approximate average"
+)]
+fn average(image: &image::RgbaImage, region: Region) -> crate::surface::Colour {
+    let (width, height) = image.dimensions();
+    let left = to_pixel(region.left, width);
+    let right = to_pixel(region.right, width).max(left + 1).min(width);
+    let top = to_pixel(region.top, height);
+    let bottom = to_pixel(region.bottom, height).max(top + 1).min(height);
+
+    let mut sums = [0.0_f32; 4];
+    let mut count = 0.0_f32;
+    for y in top..bottom {
+        for x in left..right {
+            let Some(pixel) = image.get_pixel_checked(x, y) else {
+                continue;
+            };
+            for (sum, channel) in sums.iter_mut().zip(pixel.0) {
+                *sum += f32::from(channel);
+            }
+            count += 1.0;
+        }
+    }
+    if count == 0.0 {
+        return (0.0, 0.0, 0.0, 0.0);
+    }
+    let [red, green, blue, alpha] = sums.map(|sum| sum / count / 255.0);
+    (red, green, blue, alpha)
+}
+
+/// Decode an image that the shadow terminal has attached to a cell.
+fn decode(data: &termwiz::image::ImageDataType) -> Option<image::RgbaImage> {
+    #[expect(
+        clippy::wildcard_enum_match_arm,
+        reason = "Other kinds of image data can't come from OSC 1337"
+    )]
+    match data {
+        // The formats that can be decoded come from the `image` features that `xcap` enables.
+        termwiz::image::ImageDataType::EncodedFile(bytes) => match image::load_from_memory(bytes) {
+            Ok(decoded) => Some(decoded.into_rgba8()),
+            Err(error) => {
+                tracing::debug!("Couldn't decode inline image: {error:?}");
+                None
+            }
+        },
+        termwiz::image::ImageDataType::Rgba8 {
+            data,
+            width,
+            height,
+            ..
+        } => image::RgbaImage::from_raw(*width, *height, data.clone()),
+        termwiz::image::ImageDataType::AnimRgba8 {
+            frames,
+            width,
+            height,
+            ..
+        } => image::RgbaImage::from_raw(*width, *height, frames.first()?.clone()),
+        _ => None,
+    }
+}
+
+/// `InlineImages`
+pub(crate) struct InlineImages {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The decoded images, by the hash of their data. Images that can't be decoded are kept as
+    /// `None`, so that they're not decoded again.
+    images: std::collections::HashMap<[u8; 32], Option<image::RgbaImage>>,
+    /// Whether anything is showing.
+    is_showing: bool,
+}
+
+impl InlineImages {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.inline_images.opacity;
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "inline_images".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        Self {
+            tattoy,
+            images: std::collections::HashMap::new(),
+            is_showing: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut inline_images = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_changed = super::tattoyer::Tattoyer::is_screen_output_changed(&message)
+                        || super::tattoyer::Tattoyer::is_scrollback_output_changed(&message)
+                        || matches!(message, crate::run::Protocol::Config(_));
+                    inline_images.tattoy.handle_common_protocol_messages(message)?;
+                    if is_changed {
+                        inline_images.render().await?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The lines that are currently visible, taking scrolling into account.
+    fn visible_lines(&mut self) -> Vec<Vec<termwiz::cell::Cell>> {
+        let height = usize::from(self.tattoy.height);
+        if !self.tattoy.is_scrolling() {
+            return self
+                .tattoy
+                .screen
+                .surface
+                .screen_cells()
+                .into_iter()
+                .map(|line| line.to_vec())
+                .collect();
+        }
+
+        let position = self.tattoy.scrollback.position;
+        let scrollback = self.tattoy.scrollback.surface.screen_cells();
+        let start = scrollback.len().saturating_sub(height + position);
+        scrollback
+            .into_iter()
+            .skip(start)
+            .take(height)
+            .map(|line| line.to_vec())
+            .collect()
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        let lines = self.visible_lines();
+        let has_images = lines
+            .iter()
+            .flatten()
+            .any(|cell| cell.attrs().images().is_some());
+        if !has_images {
+            self.images.clear();
+            if self.is_showing {
+                self.is_showing = false;
+                return self.tattoy.send_blank_output().await;
+            }
+            return Ok(());
+        }
+
+        let config = self.tattoy.state.config.read().await;
+        self.tattoy.opacity = config.inline_images.opacity;
+        let encoder = config.render.pixel_glyphs.encoder();
+        drop(config);
+        let block_size = encoder.block_size();
+        self.tattoy.initialise_surface();
+        let mut seen = std::collections::HashSet::new();
+        for (y, line) in lines.iter().enumerate() {
+            for (x, cell) in line.iter().enumerate() {
+                for image_cell in cell.attrs().images().unwrap_or_default() {
+                    let data = image_cell.image_data();
+                    let hash = data.hash();
+                    seen.insert(hash);
+                    let decoded = self
+                        .images
+                        .entry(hash)
+                        .or_insert_with(|| decode(&data.data()));
+                    let Some(decoded) = decoded.as_ref() else {
+                        continue;
+                    };
+
+                    let (top_left, bottom_right) =
+                        (image_cell.top_left(), image_cell.bottom_right());
+                    let region = Region {
+                        left: top_left.x,
+                        top: top_left.y,
+                        right: bottom_right.x,
+                        bottom: bottom_right.y,
+                    };
+                    let block: Vec<_> = (0..block_size.1)
+                        .flat_map(|row| (0..block_size.0).map(move |column| (column, row)))
+                        .map(|(column, row)| average(decoded, region.part(column, row, block_size)))
+                        .collect();
+                    self.tattoy.surface.add_glyph(x, y, encoder.encode(&block));
+                }
+            }
+        }
+        self.images.retain(|hash, _| seen.contains(hash));
+
+        self.is_showing = true;
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cells_show_the_average_of_their_part_of_the_image() {
+        let image = image::RgbaImage::from_fn(4, 4, |x, _| {
+            if x < 2 {
+                [255, 0, 0, 255].into()
+            } else {
+                [0, 0, 255, 255].into()
+            }
+        });
+        let whole = Region {
+            left: 0.0,
+            top: 0.0,
+            right: 1.0,
+            bottom: 1.0,
+        };
+        assert_eq!(average(&image, whole), (0.5, 0.0, 0.5, 1.0));
+        let left = Region {
+            right: 0.5,
+            ..whole
+        };
+        assert_eq!(
+            average(&image, left.part(0, 0, (1, 2))),
+            (1.0, 0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            whole.part(1, 1, (2, 4)),
+            Region {
+                left: 0.5,
+                top: 0.25,
+                right: 1.0,
+                bottom: 0.5,
+            }
+        );
+    }
+
+    #[test]
+    fn tiny_regions_still_cover_a_pixel() {
+        let image = image::RgbaImage::from_pixel(3, 3, [0, 255, 0, 255].into());
+        let region = Region {
+            left: 0.99,
+            top: 0.99,
+            right: 1.0,
+            bottom: 1.0,
+        };
+        assert_eq!(average(&image, region), (0.0, 1.0, 0.0, 1.0));
+        assert_eq!(to_pixel(2.0, 3), 2);
+    }
+}
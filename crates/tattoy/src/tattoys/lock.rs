@@ -0,0 +1,314 @@
+//! Lock the terminal, either with a keybinding or after a period of inactivity. Whilst locked the
+//! terminal's content is completely covered and no input is sent to the PTY until the configured
+//! passphrase is typed, followed by `Enter`.
+//!
+//! This is only meant to stop casual snooping in shared spaces. The passphrase is kept in plain
+//! text in the config, and anyone with access to the machine can still kill Tattoy.
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+
+/// The layer of the lock screen. It covers everything, even the screensaver.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(850);
+
+/// The number of pixels in each row of the terminal.
+const PIXELS_PER_LINE: usize = 2;
+
+/// User-configurable settings for the lock screen.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the lock screen.
+    pub enabled: bool,
+    /// The passphrase that unlocks the terminal. The terminal can't be locked without one.
+    pub passphrase: String,
+    /// Lock automatically after this many minutes without any input or output. `0.0` never locks
+    /// automatically.
+    pub idle_minutes: f32,
+    /// The colour that covers the terminal.
+    pub background: crate::surface::Colour,
+    /// The colour of the lock screen's text.
+    pub foreground: crate::surface::Colour,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            passphrase: String::new(),
+            idle_minutes: 0.0,
+            background: (0.05, 0.05, 0.1, 1.0),
+            foreground: crate::surface::WHITE,
+        }
+    }
+}
+
+impl Config {
+    /// Whether the terminal should now be locked because of inactivity.
+    fn is_idle(&self, inactivity: std::time::Duration) -> bool {
+        if self.idle_minutes <= 0.0 {
+            return false;
+        }
+        let idle =
+            std::time::Duration::try_from_secs_f32(self.idle_minutes * 60.0).unwrap_or_default();
+        inactivity >= idle
+    }
+}
+
+/// The state of the lock, shared between the lock screen tattoy and the input handler.
+#[derive(Debug, Default)]
+pub(crate) struct LockState {
+    /// Whether the terminal is locked.
+    pub is_locked: bool,
+    /// What has been typed so far.
+    typed: String,
+    /// The number of wrong passphrases since the terminal was locked.
+    failed_attempts: u32,
+}
+
+impl LockState {
+    /// Lock the terminal.
+    pub fn lock(&mut self) {
+        *self = Self {
+            is_locked: true,
+            ..Self::default()
+        };
+    }
+
+    /// Handle a keypress whilst locked. Returns `true` if the passphrase was just typed and the
+    /// terminal has been unlocked.
+    pub fn handle_key(&mut self, key_event: &termwiz::input::KeyEvent, passphrase: &str) -> bool {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "Only a few keys mean anything to the lock screen"
+        )]
+        match key_event.key {
+            termwiz::input::KeyCode::Char(character) => self.typed.push(character),
+            termwiz::input::KeyCode::Backspace => {
+                self.typed.pop();
+            }
+            termwiz::input::KeyCode::Escape => self.typed.clear(),
+            termwiz::input::KeyCode::Enter => {
+                if !passphrase.is_empty() && self.typed == passphrase {
+                    *self = Self::default();
+                    return true;
+                }
+                self.typed.clear();
+                self.failed_attempts += 1;
+            }
+            _ => (),
+        }
+
+        false
+    }
+
+    /// The text shown on the lock screen.
+    fn prompt(&self) -> Vec<String> {
+        let mut lines = vec![
+            "🔒 Locked".to_owned(),
+            format!("Passphrase: {}", "•".repeat(self.typed.chars().count())),
+        ];
+        if self.failed_attempts > 0 {
+            lines.push(format!("Wrong passphrase ({})", self.failed_attempts));
+        }
+        lines
+    }
+}
+
+/// `Lock`
+pub(crate) struct Lock {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// Whether the lock screen was showing on the last frame.
+    is_showing: bool,
+}
+
+impl Lock {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let mut tattoy =
+            super::tattoyer::Tattoyer::new("lock".to_owned(), state, LAYER, 1.0, output_channel)
+                .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            is_showing: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut lock = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = lock.tattoy.sleep_until_next_frame_tick() => {
+                    lock.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if matches!(
+                        message,
+                        crate::run::Protocol::KeybindEvent(
+                            crate::config::input::KeybindingAction::LockScreen
+                        )
+                    ) {
+                        lock.lock().await;
+                    }
+                    lock.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lock the terminal, as long as there's a passphrase to unlock it with.
+    async fn lock(&self) {
+        let passphrase = self
+            .tattoy
+            .state
+            .config
+            .read()
+            .await
+            .lock
+            .passphrase
+            .clone();
+        if passphrase.is_empty() {
+            self.tattoy
+                .state
+                .send_notification(
+                    "Can't lock without a passphrase",
+                    crate::tattoys::notifications::message::Level::Warn,
+                    Some("Set `passphrase` in the `[lock]` section of your config".to_owned()),
+                    false,
+                )
+                .await;
+            return;
+        }
+
+        tracing::debug!("Locking the terminal");
+        self.tattoy.state.lock.write().await.lock();
+    }
+
+    /// Lock if the terminal has been idle, and render the lock screen.
+    async fn tick(&mut self) -> Result<()> {
+        let config = self.tattoy.state.config.read().await.lock.clone();
+        let is_locked = self.tattoy.state.lock.read().await.is_locked;
+        if !is_locked && !config.passphrase.is_empty() {
+            let inactivity = self.tattoy.state.get_inactivity().await;
+            if config.is_idle(inactivity) {
+                self.lock().await;
+            }
+        }
+
+        let lock_state = self.tattoy.state.lock.read().await;
+        if !lock_state.is_locked {
+            drop(lock_state);
+            if self.is_showing {
+                self.is_showing = false;
+                self.tattoy.send_blank_output().await?;
+            }
+            return Ok(());
+        }
+        let prompt = lock_state.prompt();
+        drop(lock_state);
+
+        self.is_showing = true;
+        self.render(&config, prompt).await
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config, prompt: Vec<String>) -> Result<()> {
+        self.tattoy.initialise_surface();
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+        for y in 0..height * PIXELS_PER_LINE {
+            self.tattoy
+                .surface
+                .add_pixel_row(0, y, std::iter::repeat_n(config.background, width));
+        }
+
+        let top = height.saturating_sub(prompt.len()).div_euclid(2);
+        for (offset, line) in prompt.into_iter().enumerate() {
+            let x = width
+                .saturating_sub(self.tattoy.text_width(&line))
+                .div_euclid(2);
+            self.tattoy.surface.add_text(
+                x,
+                top + offset,
+                line,
+                Some(config.background),
+                Some(config.foreground),
+            );
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(key: termwiz::input::KeyCode) -> termwiz::input::KeyEvent {
+        termwiz::input::KeyEvent {
+            key,
+            modifiers: termwiz::input::Modifiers::NONE,
+        }
+    }
+
+    fn type_text(lock: &mut LockState, text: &str, passphrase: &str) -> bool {
+        for character in text.chars() {
+            lock.handle_key(&key(termwiz::input::KeyCode::Char(character)), passphrase);
+        }
+        lock.handle_key(&key(termwiz::input::KeyCode::Enter), passphrase)
+    }
+
+    #[test]
+    fn only_the_passphrase_unlocks() {
+        let mut lock = LockState::default();
+        lock.lock();
+
+        assert!(!type_text(&mut lock, "wrong", "secret"));
+        assert!(lock.is_locked);
+        assert_eq!(lock.prompt().last().unwrap(), "Wrong passphrase (1)");
+
+        lock.handle_key(&key(termwiz::input::KeyCode::Char('x')), "secret");
+        lock.handle_key(&key(termwiz::input::KeyCode::Backspace), "secret");
+        assert!(type_text(&mut lock, "secret", "secret"));
+        assert!(!lock.is_locked);
+    }
+
+    #[test]
+    fn an_empty_passphrase_never_unlocks() {
+        let mut lock = LockState::default();
+        lock.lock();
+        assert!(!type_text(&mut lock, "", ""));
+        assert!(lock.is_locked);
+    }
+
+    #[test]
+    fn idle_locking_can_be_disabled() {
+        let minute = std::time::Duration::from_secs(60);
+        let mut config = Config::default();
+        assert!(!config.is_idle(minute * 100));
+
+        config.idle_minutes = 5.0;
+        assert!(!config.is_idle(minute));
+        assert!(config.is_idle(minute * 5));
+    }
+}
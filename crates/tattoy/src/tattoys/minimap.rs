@@ -8,7 +8,7 @@ use shadow_terminal::termwiz;
 use super::tattoyer::Tattoyer;
 
 /// User-configurable settings for the minimap
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
 #[serde(default)]
 pub(crate) struct Config {
     /// Enable/disable the minimap
@@ -68,7 +68,7 @@ impl Minimap {
         let tattoy = Tattoyer::new(
             "minimap".to_owned(),
             Arc::clone(&state),
-            90,
+            crate::layers::Group::Effects.layer(89),
             1.0,
             output_channel,
         )
@@ -103,7 +103,9 @@ impl Minimap {
         loop {
             tokio::select! {
                 () = minimap.tattoy.sleep_until_next_frame_tick(), if minimap.needs_rerendering() => {
-                    minimap.render().await?;
+                    if !minimap.tattoy.is_paused_for_output_burst().await {
+                        minimap.render().await?;
+                    }
                 },
                 result = protocol.recv() => {
                     if matches!(result, Ok(crate::run::Protocol::End)) {
@@ -358,13 +360,15 @@ impl Minimap {
         let image = self.tattoy.convert_pty_to_pixel_image(&kind, true).await?;
 
         let max_width = self.state.config.read().await.minimap.max_width;
-        let minimap = image
-            .resize(
-                max_width.into(),
-                (self.tattoy.height * 2).into(),
-                image::imageops::Lanczos3,
-            )
-            .to_rgba32f();
+        let height = u32::from(self.tattoy.height) * 2;
+        let minimap = self
+            .tattoy
+            .compute(move || {
+                image
+                    .resize(max_width.into(), height, image::imageops::Lanczos3)
+                    .to_rgba32f()
+            })
+            .await?;
 
         match kind {
             shadow_terminal::output::native::SurfaceKind::Scrollback => self.scrollback = minimap,
@@ -5,16 +5,21 @@ use palette::Darken as _;
 use shadow_terminal::termwiz;
 
 /// User-configurable settings for the background command.
-#[derive(serde::Deserialize, Debug, Clone, Default)]
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Default)]
 pub(crate) struct Config {
     /// Enable/disable the display of notifications
     pub enabled: bool,
     /// The transparency of the notifications
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub opacity: f32,
     /// The minimum level of notifications to display
     pub level: super::message::Level,
     /// The amount of time to display a notification
     pub duration: f32,
+    /// Whether to ignore the notifications that applications send with `OSC 5522` escape
+    /// sequences.
+    #[serde(default)]
+    pub ignore_escape_sequences: bool,
 }
 
 /// `Notifications`
@@ -40,7 +45,7 @@ impl Notifications {
         let tattoy = crate::tattoys::tattoyer::Tattoyer::new(
             "notifications".to_owned(),
             state,
-            200,
+            crate::layers::Group::Overlay.layer(100),
             opacity,
             output_channel,
         )
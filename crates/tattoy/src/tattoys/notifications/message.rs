@@ -1,7 +1,9 @@
 //! A single notification message.
 
 /// The urgency level of the notification.
-#[derive(serde::Deserialize, Debug, Clone, Default, Ord, Eq, PartialEq, PartialOrd)]
+#[derive(
+    serde::Deserialize, schemars::JsonSchema, Debug, Clone, Default, Ord, Eq, PartialEq, PartialOrd,
+)]
 #[serde(rename_all = "lowercase")]
 #[repr(u8)]
 pub(crate) enum Level {
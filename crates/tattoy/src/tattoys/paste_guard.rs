@@ -0,0 +1,317 @@
+//! Ask for confirmation before sending suspicious pastes to the PTY. A paste is suspicious when it
+//! has lots of lines, or when it contains control characters. Control characters are how
+//! "paste-jacking" works: text copied from a web page can secretly contain a newline, or even the
+//! end of a bracketed paste, that runs a command as soon as it's pasted.
+//!
+//! Whilst a paste is waiting for confirmation all other input is swallowed.
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+
+/// The layer of the confirmation. It's above the screensaver, but below the lock screen.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(840);
+
+/// The number of lines of the paste that are previewed.
+const PREVIEW_LINES: usize = 3;
+
+/// User-configurable settings for paste confirmations.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable paste confirmations.
+    pub enabled: bool,
+    /// Pastes with more lines than this need confirming. `0` never confirms because of size.
+    pub max_lines: usize,
+    /// Whether pastes that contain control characters, other than newlines and tabs, need
+    /// confirming.
+    pub control_characters: bool,
+    /// The background colour of the confirmation.
+    pub background: crate::surface::Colour,
+    /// The colour of the confirmation's text.
+    pub foreground: crate::surface::Colour,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_lines: 5,
+            control_characters: true,
+            background: (0.3, 0.1, 0.1, 1.0),
+            foreground: crate::surface::WHITE,
+        }
+    }
+}
+
+impl Config {
+    /// Why a paste needs confirming, if it does.
+    pub fn reason(&self, paste: &str) -> Option<String> {
+        if self.control_characters
+            && paste
+                .chars()
+                .any(|character| character.is_control() && !matches!(character, '\n' | '\r' | '\t'))
+        {
+            return Some("Paste contains control characters".to_owned());
+        }
+
+        let lines = paste.lines().count();
+        if self.max_lines > 0 && lines > self.max_lines {
+            return Some(format!("Paste {lines} lines?"));
+        }
+
+        None
+    }
+}
+
+/// The decision about a paste that's waiting for confirmation.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Decision {
+    /// Send the paste to the PTY.
+    Paste(String),
+    /// Forget the paste.
+    Cancel,
+    /// Keep waiting for the user to decide.
+    Undecided,
+}
+
+/// A paste that's waiting for confirmation, shared between the tattoy and the input handler.
+#[derive(Debug, Default)]
+pub(crate) struct PendingPaste {
+    /// The paste, and why it needs confirming.
+    pending: Option<(String, String)>,
+}
+
+impl PendingPaste {
+    /// Whether there's a paste waiting for confirmation.
+    pub const fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Hold the paste until the user confirms it.
+    pub fn hold(&mut self, paste: String, reason: String) {
+        self.pending = Some((paste, reason));
+    }
+
+    /// Handle a keypress whilst a paste is waiting. `Enter` or `y` confirms the paste, `Escape`
+    /// or `n` cancels it.
+    pub fn handle_key(&mut self, key_event: &termwiz::input::KeyEvent) -> Decision {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "Only a few keys mean anything to the confirmation"
+        )]
+        match key_event.key {
+            termwiz::input::KeyCode::Enter | termwiz::input::KeyCode::Char('y' | 'Y') => self
+                .pending
+                .take()
+                .map_or(Decision::Cancel, |(paste, _)| Decision::Paste(paste)),
+            termwiz::input::KeyCode::Escape | termwiz::input::KeyCode::Char('n' | 'N') => {
+                self.pending = None;
+                Decision::Cancel
+            }
+            _ => Decision::Undecided,
+        }
+    }
+
+    /// The text of the confirmation.
+    fn prompt(&self) -> Option<Vec<String>> {
+        let (paste, reason) = self.pending.as_ref()?;
+        let mut lines = vec![reason.clone(), String::new()];
+        lines.extend(
+            paste
+                .lines()
+                .take(PREVIEW_LINES)
+                .map(|line| line.escape_debug().to_string()),
+        );
+        if paste.lines().count() > PREVIEW_LINES {
+            lines.push("…".to_owned());
+        }
+        lines.push(String::new());
+        lines.push("Enter/y: paste    Esc/n: cancel".to_owned());
+        Some(lines)
+    }
+}
+
+/// `PasteGuard`
+pub(crate) struct PasteGuard {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// Whether the confirmation was showing on the last frame.
+    is_showing: bool,
+}
+
+impl PasteGuard {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "paste_guard".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            is_showing: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut guard = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = guard.tattoy.sleep_until_next_frame_tick() => {
+                    guard.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    guard.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render the confirmation whenever there's a paste waiting.
+    async fn tick(&mut self) -> Result<()> {
+        let prompt = self.tattoy.state.pending_paste.read().await.prompt();
+        let Some(prompt) = prompt else {
+            if self.is_showing {
+                self.is_showing = false;
+                self.tattoy.send_blank_output().await?;
+            }
+            return Ok(());
+        };
+
+        let config = self.tattoy.state.config.read().await.paste_guard.clone();
+        self.is_showing = true;
+        self.render(&config, prompt).await
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config, prompt: Vec<String>) -> Result<()> {
+        self.tattoy.initialise_surface();
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height);
+
+        let padding = 2;
+        let box_width = prompt
+            .iter()
+            .map(|line| self.tattoy.text_width(line))
+            .max()
+            .unwrap_or_default()
+            .saturating_add(padding * 2)
+            .min(width);
+        let box_height = prompt.len().saturating_add(2).min(height);
+        let left = width.saturating_sub(box_width).div_euclid(2);
+        let top = height.saturating_sub(box_height).div_euclid(2);
+
+        for y in top..top + box_height {
+            self.tattoy.surface.add_text(
+                left,
+                y,
+                " ".repeat(box_width),
+                Some(config.background),
+                Some(config.foreground),
+            );
+        }
+        for (offset, line) in prompt.into_iter().enumerate() {
+            let line = line
+                .chars()
+                .take(box_width.saturating_sub(padding * 2))
+                .collect::<String>();
+            self.tattoy.surface.add_text(
+                left + padding,
+                top + 1 + offset,
+                line,
+                Some(config.background),
+                Some(config.foreground),
+            );
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(key: termwiz::input::KeyCode) -> termwiz::input::KeyEvent {
+        termwiz::input::KeyEvent {
+            key,
+            modifiers: termwiz::input::Modifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn suspicious_pastes_need_confirming() {
+        let config = Config::default();
+        assert_eq!(config.reason("ls -la\n"), None);
+        assert_eq!(
+            config.reason("a\nb\nc\nd\ne\nf\n").unwrap(),
+            "Paste 6 lines?"
+        );
+        assert_eq!(
+            config.reason("echo hi\x1b[201~rm -rf ~\n").unwrap(),
+            "Paste contains control characters"
+        );
+
+        let lenient = Config {
+            max_lines: 0,
+            control_characters: false,
+            ..Config::default()
+        };
+        assert_eq!(lenient.reason("a\nb\nc\nd\ne\nf\n\x1b"), None);
+    }
+
+    #[test]
+    fn pastes_wait_for_a_decision() {
+        let mut pending = PendingPaste::default();
+        pending.hold("secret".to_owned(), "Paste?".to_owned());
+        assert!(pending.is_pending());
+
+        assert_eq!(
+            pending.handle_key(&key(termwiz::input::KeyCode::Char('x'))),
+            Decision::Undecided
+        );
+        assert_eq!(
+            pending.handle_key(&key(termwiz::input::KeyCode::Enter)),
+            Decision::Paste("secret".to_owned())
+        );
+        assert!(!pending.is_pending());
+
+        pending.hold("secret".to_owned(), "Paste?".to_owned());
+        assert_eq!(
+            pending.handle_key(&key(termwiz::input::KeyCode::Escape)),
+            Decision::Cancel
+        );
+        assert!(!pending.is_pending());
+    }
+
+    #[test]
+    fn previews_show_control_characters() {
+        let mut pending = PendingPaste::default();
+        pending.hold("a\x1bb\n2\n3\n4\n".to_owned(), "Paste?".to_owned());
+        let prompt = pending.prompt().unwrap();
+        assert_eq!(prompt.get(2).unwrap(), "a\\u{1b}b");
+        assert_eq!(prompt.get(5).unwrap(), "…");
+    }
+}
@@ -6,23 +6,53 @@ use std::io::{Read as _, Write as _};
 use color_eyre::eyre::{ContextCompat as _, Result};
 
 /// The default compositing layer the plugin is rendered to. Can be manually set inn the config.
-const DEFAULT_LAYER: i16 = -10;
+const DEFAULT_LAYER: i16 = crate::layers::Group::Background.layer(9);
 /// The default transparency for the plugin output.
 const DEFAULT_OPACITY: f32 = 1.0;
 
 /// User-configurable settings for the minimap
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
 pub struct Config {
     /// The name of the plugin. Can be any string.
     name: String,
     /// The path to the plugin executable.
     path: std::path::PathBuf,
     /// The layer upon which the plugin is rendered.
+    #[schemars(range(min = crate::layers::MIN_LAYER, max = crate::layers::MAX_LAYER))]
     layer: Option<i16>,
     /// The transparency of the plugin output.
+    #[schemars(range(min = 0.0, max = 1.0))]
     opacity: Option<f32>,
     /// Whether the plugin is enabled.
     pub enabled: Option<bool>,
+    /// Only render the plugin output into cells that don't have a background colour.
+    #[serde(default)]
+    pub under_text_only: bool,
+    /// Only render the plugin output into cells that don't have any text at all.
+    #[serde(default)]
+    pub only_in_blank_cells: bool,
+    /// Linearly change the plugin output's opacity across the rows, or columns.
+    pub opacity_gradient: Option<crate::opacity_gradient::Gradient>,
+}
+
+impl Config {
+    /// The name of the plugin, which is also the ID of its tattoy.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Make sure the configured layer is within the allowed range. Plugins are allowed to render
+    /// to the PTY's own layer, so that they can replace the PTY's text altogether.
+    pub(crate) fn validate_layer(&mut self) -> Option<String> {
+        let layer = self.layer?;
+        let (valid, message) = crate::layers::validate(
+            &format!("plugins.{}", self.name),
+            layer,
+            crate::layers::Placement::Anywhere,
+        );
+        self.layer = Some(valid);
+        message
+    }
 }
 
 /// Plugins
@@ -373,6 +403,9 @@ impl Plugin {
 
     /// Tick the render
     async fn render(&mut self, output: tattoy_protocol::PluginOutputMessages) -> Result<()> {
+        if let tattoy_protocol::PluginOutputMessages::Celebrate { kind, coordinates } = output {
+            return self.celebrate(kind, coordinates);
+        }
         self.tattoy.initialise_surface();
 
         tracing::debug!("Rendering from plugin message");
@@ -427,4 +460,18 @@ impl Plugin {
 
         Ok(())
     }
+
+    /// Pass a plugin's celebration on to the celebration tattoy.
+    fn celebrate(&self, kind: String, coordinates: (u32, u32)) -> Result<()> {
+        let kind: crate::tattoys::celebration::Kind =
+            serde_json::from_value(serde_json::Value::String(kind))?;
+        self.tattoy
+            .state
+            .protocol_tx
+            .send(crate::run::Protocol::Celebrate {
+                kind,
+                origin: (coordinates.0.try_into()?, coordinates.1.try_into()?),
+            })?;
+        Ok(())
+    }
 }
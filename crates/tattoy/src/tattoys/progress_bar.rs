@@ -0,0 +1,223 @@
+//! Detect progress, like "Downloading... 42%", on the line of the terminal's cursor and show it as
+//! a slim bar along the top or bottom edge of the terminal. The progress is also sent to the host
+//! terminal as an `OSC 9;4` sequence, so terminals that support it can show it in the taskbar.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the progress bar. It needs to be seen over the terminal's text.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(20);
+
+/// The number of pixels in each row of the terminal.
+const PIXELS_PER_LINE: usize = 2;
+
+/// Which edge of the terminal the bar is shown along.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Edge {
+    /// The top of the terminal.
+    #[default]
+    Top,
+    /// The bottom of the terminal.
+    Bottom,
+}
+
+/// User-configurable settings for the progress bar.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the progress bar.
+    pub enabled: bool,
+    /// The opacity of the bar.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// Which edge the bar is shown along.
+    pub position: Edge,
+    /// The colour of the progress.
+    pub colour: crate::surface::Colour,
+    /// The colour of the rest of the bar.
+    pub background: crate::surface::Colour,
+    /// Whether to send the progress to the host terminal, for showing in the taskbar.
+    pub taskbar: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 1.0,
+            position: Edge::default(),
+            colour: (0.3, 0.8, 0.4, 1.0),
+            background: (0.3, 0.3, 0.3, 0.5),
+            taskbar: true,
+        }
+    }
+}
+
+/// Find the progress in a line of text. It's the last percentage in the line, so that lines like
+/// "50% of 2 files, 10% of current" are taken from the most recently updated part. Finished
+/// progress, 100%, isn't progress.
+fn find_progress(line: &str) -> Option<u8> {
+    static PERCENTAGE: std::sync::LazyLock<regex::Regex> = std::sync::LazyLock::new(|| {
+        #[expect(clippy::unwrap_used, reason = "It's a hardcoded valid regex")]
+        regex::Regex::new(r"\b(\d{1,3})(\.\d+)?\s?%").unwrap()
+    });
+
+    let captures = PERCENTAGE.captures_iter(line).last()?;
+    let percentage = captures.get(1)?.as_str().parse::<u8>().ok()?;
+    (percentage < 100).then_some(percentage)
+}
+
+/// `ProgressBar`
+pub(crate) struct ProgressBar {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The progress currently being shown.
+    progress: Option<u8>,
+    /// Whether the screen has changed since we last looked for progress.
+    is_dirty: bool,
+}
+
+impl ProgressBar {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.progress_bar.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "progress_bar".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            progress: None,
+            is_dirty: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut progress_bar = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = progress_bar.tattoy.sleep_until_next_frame_tick() => {
+                    progress_bar.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if super::tattoyer::Tattoyer::is_screen_output_changed(&message)
+                        || matches!(
+                            message,
+                            crate::run::Protocol::Resize { .. } | crate::run::Protocol::Config(_)
+                        )
+                    {
+                        progress_bar.is_dirty = true;
+                    }
+                    progress_bar.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The text of the line that the cursor is on.
+    fn cursor_line(&self) -> String {
+        let (_, y) = self.tattoy.screen.surface.cursor_position();
+        let cells = self.tattoy.screen.surface.get_screen_cells();
+        cells
+            .get(y)
+            .map(|line| line.iter().map(|cell| cell.str()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Look for progress and render if it's changed.
+    async fn tick(&mut self) -> Result<()> {
+        if !self.is_dirty {
+            return Ok(());
+        }
+        self.is_dirty = false;
+
+        let progress = find_progress(&self.cursor_line());
+        if progress != self.progress {
+            self.progress = progress;
+            self.set_taskbar(progress).await?;
+        }
+
+        let config = self.tattoy.state.config.read().await.progress_bar.clone();
+        self.render(&config, progress).await
+    }
+
+    /// Tell the host terminal about the progress.
+    async fn set_taskbar(&self, progress: Option<u8>) -> Result<()> {
+        if !self.tattoy.state.config.read().await.progress_bar.taskbar {
+            return Ok(());
+        }
+        self.tattoy
+            .state
+            .protocol_tx
+            .send(crate::run::Protocol::Progress(progress))?;
+        Ok(())
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config, progress: Option<u8>) -> Result<()> {
+        let Some(progress) = progress else {
+            return self.tattoy.send_blank_output().await;
+        };
+
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+        let width = usize::from(self.tattoy.width);
+        let y = match config.position {
+            Edge::Top => 0,
+            Edge::Bottom => (usize::from(self.tattoy.height) * PIXELS_PER_LINE).saturating_sub(1),
+        };
+        let filled = (width * usize::from(progress)).div_euclid(100);
+        for x in 0..width {
+            let colour = if x < filled {
+                config.colour
+            } else {
+                config.background
+            };
+            self.tattoy.surface.add_pixel(x, y, colour)?;
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_progress() {
+        assert_eq!(find_progress("Downloading... 42%"), Some(42));
+        assert_eq!(find_progress("[#####     ] 50.5 % eta 3s"), Some(50));
+        assert_eq!(find_progress("2 of 3 files 66%, current 7%"), Some(7));
+    }
+
+    #[test]
+    fn ignores_non_progress() {
+        assert_eq!(find_progress("$ ls"), None);
+        assert_eq!(find_progress("Done 100%"), None);
+        assert_eq!(find_progress("1000%"), None);
+    }
+}
@@ -28,7 +28,7 @@ impl RandomWalker {
         let tattoy = super::tattoyer::Tattoyer::new(
             "random_walker".to_owned(),
             state,
-            -10,
+            crate::layers::Group::Background.layer(9),
             1.0,
             output_channel,
         )
@@ -0,0 +1,251 @@
+//! Briefly tint the cells of the screen that change, green for text that's added and red for text
+//! that's removed, fading away over a moment. It's a bit of fun, but it's also a way to see which
+//! apps repaint far more of the screen than they need to.
+//!
+//! Cells are compared between every update of the PTY's screen. Resizes change everything, so
+//! they don't count.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the tints. They're beneath the terminal's text so that it's still readable.
+const LAYER: i16 = crate::layers::Group::Background.layer(7);
+
+/// User-configurable settings for showing repaints.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable showing repaints.
+    pub enabled: bool,
+    /// The opacity of the tints.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// How long, in seconds, the tints take to fade away.
+    pub duration: f32,
+    /// The colour of cells whose text was added or changed.
+    pub added_colour: crate::surface::Colour,
+    /// The colour of cells whose text was removed.
+    pub removed_colour: crate::surface::Colour,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.5,
+            duration: 1.0,
+            added_colour: (0.2, 0.8, 0.2, 1.0),
+            removed_colour: (0.9, 0.2, 0.2, 1.0),
+        }
+    }
+}
+
+/// How a cell changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Edit {
+    /// The cell has new text, either where it was empty or replacing other text.
+    Added,
+    /// The cell's text was removed.
+    Removed,
+}
+
+/// The text of every cell of the screen, by row.
+pub(crate) type Cells = Vec<Vec<String>>;
+
+/// Whether a cell doesn't show anything.
+fn is_blank(text: &str) -> bool {
+    text.trim().is_empty()
+}
+
+/// A copy of the text of every cell of the PTY's screen, as a tattoy knows it.
+pub(crate) fn snapshot(tattoy: &mut super::tattoyer::Tattoyer) -> Cells {
+    tattoy
+        .screen
+        .surface
+        .screen_cells()
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.str().to_owned()).collect())
+        .collect()
+}
+
+/// Whether two copies of the screen are the same size, so that they can be compared.
+pub(crate) fn is_same_size(old: &[Vec<String>], new: &[Vec<String>]) -> bool {
+    old.len() == new.len() && old.first().map(Vec::len) == new.first().map(Vec::len)
+}
+
+/// The cells that changed between two copies of the screen, as `(x, y, edit)`.
+pub(crate) fn diff(old: &[Vec<String>], new: &[Vec<String>]) -> Vec<(usize, usize, Edit)> {
+    let mut edits = Vec::new();
+    for (y, (old_row, new_row)) in old.iter().zip(new).enumerate() {
+        for (x, (before, after)) in old_row.iter().zip(new_row).enumerate() {
+            if before == after || (is_blank(before) && is_blank(after)) {
+                continue;
+            }
+            let edit = if is_blank(after) {
+                Edit::Removed
+            } else {
+                Edit::Added
+            };
+            edits.push((x, y, edit));
+        }
+    }
+    edits
+}
+
+/// What's left of a tint's alpha after it's been fading for `age`.
+fn faded_alpha(alpha: f32, age: std::time::Duration, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        return 0.0;
+    }
+    alpha * (1.0 - age.as_secs_f32() / duration).clamp(0.0, 1.0)
+}
+
+/// `Repaints`
+pub(crate) struct Repaints {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The screen as it was after the previous update.
+    previous: Cells,
+    /// The cells that have changed recently, with when they last changed.
+    edits: std::collections::HashMap<(usize, usize), (Edit, std::time::Instant)>,
+}
+
+impl Repaints {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.repaints.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "repaints".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_below_text();
+        Self {
+            tattoy,
+            previous: Vec::new(),
+            edits: std::collections::HashMap::new(),
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut repaints = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = repaints.tattoy.sleep_until_next_frame_tick() => {
+                    repaints.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    let is_resize = matches!(message, crate::run::Protocol::Resize { .. });
+                    let is_changed = super::tattoyer::Tattoyer::is_screen_output_changed(&message);
+                    repaints.tattoy.handle_common_protocol_messages(message)?;
+                    if is_changed {
+                        repaints.compare(is_resize);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Find the cells that the latest update to the screen changed.
+    fn compare(&mut self, is_resize: bool) {
+        let current = snapshot(&mut self.tattoy);
+        if is_resize || !is_same_size(&self.previous, &current) {
+            self.edits.clear();
+        } else {
+            let now = std::time::Instant::now();
+            for (x, y, edit) in diff(&self.previous, &current) {
+                self.edits.insert((x, y), (edit, now));
+            }
+        }
+        self.previous = current;
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        if self.edits.is_empty() {
+            return Ok(());
+        }
+
+        let config = self.tattoy.state.config.read().await.repaints.clone();
+        let fade = std::time::Duration::try_from_secs_f32(config.duration).unwrap_or_default();
+        self.edits
+            .retain(|_, (_, changed)| changed.elapsed() < fade);
+        if self.tattoy.is_scrolling() || self.edits.is_empty() {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+        for (&(x, y), &(edit, changed)) in &self.edits {
+            let colour = match edit {
+                Edit::Added => config.added_colour,
+                Edit::Removed => config.removed_colour,
+            };
+            let alpha = faded_alpha(colour.3, changed.elapsed(), config.duration);
+            self.tattoy.surface.add_text(
+                x,
+                y,
+                " ".to_owned(),
+                Some((colour.0, colour.1, colour.2, alpha)),
+                None,
+            );
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cells(rows: &[&str]) -> Cells {
+        rows.iter()
+            .map(|row| row.chars().map(|character| character.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn added_and_removed_text_is_found() {
+        let old = cells(&["ab  ", "cd  "]);
+        let new = cells(&["ax  ", " d e"]);
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                (1, 0, Edit::Added),
+                (0, 1, Edit::Removed),
+                (3, 1, Edit::Added)
+            ]
+        );
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn tints_fade_away() {
+        let second = std::time::Duration::from_secs(1);
+        assert!((faded_alpha(0.8, std::time::Duration::ZERO, 1.0) - 0.8).abs() < f32::EPSILON);
+        assert!((faded_alpha(0.8, second / 2, 1.0) - 0.4).abs() < f32::EPSILON);
+        assert!(faded_alpha(0.8, second * 2, 1.0).abs() < f32::EPSILON);
+        assert!(faded_alpha(0.8, second, 0.0).abs() < f32::EPSILON);
+    }
+}
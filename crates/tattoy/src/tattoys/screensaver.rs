@@ -0,0 +1,423 @@
+//! A full-screen screensaver that hides the terminal after a period of inactivity. Inactivity
+//! means neither typing nor any output from the PTY. Any keypress dismisses the screensaver, and
+//! that keypress is swallowed rather than being sent to the PTY.
+
+use color_eyre::eyre::Result;
+use rand::Rng as _;
+
+/// The layer of the screensaver. It needs to cover absolutely everything.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(800);
+
+/// The number of pixels in each row of the terminal.
+const PIXELS_PER_LINE: usize = 2;
+
+/// The number of stars in the starfield.
+const STARS: usize = 200;
+
+/// What the screensaver shows.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Kind {
+    /// Flying through space.
+    #[default]
+    Starfield,
+    /// Just the background colour.
+    Blank,
+    /// A Shadertoy-like shader, see `shader`.
+    Shader,
+}
+
+/// User-configurable settings for the screensaver.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the screensaver.
+    pub enabled: bool,
+    /// How long, in minutes, without any input or output before the screensaver starts.
+    pub idle_minutes: f32,
+    /// What the screensaver shows.
+    pub kind: Kind,
+    /// The shader to use when the kind is `shader`. Relative to Tattoy's config directory.
+    pub shader: std::path::PathBuf,
+    /// The colour behind the built-in animations.
+    pub background: crate::surface::Colour,
+    /// How fast the stars fly towards you.
+    pub speed: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_minutes: 10.0,
+            kind: Kind::default(),
+            shader: format!(
+                "{}/{}",
+                crate::config::main::SHADER_DIRECTORY_NAME,
+                crate::config::main::DEFAULT_SHADER_FILENAME
+            )
+            .into(),
+            background: (0.0, 0.0, 0.0, 1.0),
+            speed: 0.3,
+        }
+    }
+}
+
+impl Config {
+    /// How long the terminal needs to be idle before the screensaver starts.
+    fn idle_duration(&self) -> std::time::Duration {
+        std::time::Duration::try_from_secs_f32(self.idle_minutes.max(0.0) * 60.0)
+            .unwrap_or_default()
+    }
+}
+
+/// Start the screensaver if the terminal has been idle for long enough. Returns whether the
+/// screensaver is active. It's only ever dismissed by the input handler, so that the dismissing
+/// keypress can be swallowed.
+async fn update_is_active(state: &crate::shared_state::SharedState, config: &Config) -> bool {
+    if state.get_is_screensaver_active().await {
+        return true;
+    }
+
+    if state.get_inactivity().await < config.idle_duration() {
+        return false;
+    }
+
+    tracing::debug!("Starting screensaver");
+    state.set_is_screensaver_active(true).await;
+    true
+}
+
+/// A single star, in 3D space. The viewer is at the origin looking down the z axis.
+#[derive(Debug, Clone, Copy)]
+struct Star {
+    /// Horizontal position, from `-1.0` to `1.0`.
+    x: f32,
+    /// Vertical position, from `-1.0` to `1.0`.
+    y: f32,
+    /// Distance from the viewer, from `0.0` to `1.0`.
+    z: f32,
+}
+
+impl Star {
+    /// A new star somewhere in the distance.
+    fn random(z: f32) -> Self {
+        let mut rng = rand::thread_rng();
+        Self {
+            x: rng.gen_range(-1.0..1.0),
+            y: rng.gen_range(-1.0..1.0),
+            z,
+        }
+    }
+
+    /// Move the star towards the viewer. Returns `false` once it has gone past.
+    fn step(&mut self, distance: f32) -> bool {
+        self.z -= distance;
+        self.z > 0.01
+    }
+
+    /// Where the star is on the screen, if it's on the screen at all.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss,
+        reason = "The position is checked to be on the screen"
+    )]
+    fn project(self, width: usize, height: usize) -> Option<(usize, usize)> {
+        let centre_x = width as f32 / 2.0;
+        let centre_y = height as f32 / 2.0;
+        let x = centre_x + (self.x / self.z) * centre_x;
+        let y = centre_y + (self.y / self.z) * centre_y;
+        if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    /// The brightness of the star, closer stars are brighter.
+    fn brightness(self) -> f32 {
+        (1.0 - self.z).clamp(0.0, 1.0)
+    }
+}
+
+/// A field of stars flying towards the viewer.
+#[derive(Debug)]
+struct Starfield {
+    /// All the stars.
+    stars: Vec<Star>,
+}
+
+impl Starfield {
+    /// Instantiate with stars spread at all distances.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "The number of stars is small"
+    )]
+    fn new() -> Self {
+        Self {
+            stars: (0..STARS)
+                .map(|index| Star::random((index as f32 + 1.0) / STARS as f32))
+                .collect(),
+        }
+    }
+
+    /// Move all the stars, replacing the ones that have flown past.
+    fn step(&mut self, distance: f32, width: usize, height: usize) {
+        for star in &mut self.stars {
+            if !star.step(distance) || star.project(width, height).is_none() {
+                *star = Star::random(1.0);
+            }
+        }
+    }
+}
+
+/// `Screensaver`
+pub(crate) struct Screensaver {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The starfield animation.
+    starfield: Starfield,
+    /// Whether the screensaver was showing on the last frame.
+    is_showing: bool,
+}
+
+impl Screensaver {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "screensaver".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            starfield: Starfield::new(),
+            is_showing: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut screensaver = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = screensaver.tattoy.sleep_until_next_frame_tick() => {
+                    screensaver.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    screensaver.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start, animate or hide the screensaver.
+    async fn tick(&mut self) -> Result<()> {
+        let config = self.tattoy.state.config.read().await.screensaver.clone();
+        if !update_is_active(&self.tattoy.state, &config).await {
+            if self.is_showing {
+                self.is_showing = false;
+                self.tattoy.send_blank_output().await?;
+            }
+            return Ok(());
+        }
+
+        if !self.is_showing {
+            self.is_showing = true;
+            self.starfield = Starfield::new();
+        }
+        self.render(&config).await
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config) -> Result<()> {
+        self.tattoy.initialise_surface();
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height) * PIXELS_PER_LINE;
+        for y in 0..height {
+            self.tattoy
+                .surface
+                .add_pixel_row(0, y, std::iter::repeat_n(config.background, width));
+        }
+
+        if config.kind == Kind::Starfield {
+            let frame_rate = self.tattoy.target_frame_rate.max(1);
+            #[expect(
+                clippy::as_conversions,
+                clippy::cast_precision_loss,
+                reason = "Frame rates are small"
+            )]
+            let distance = config.speed / frame_rate as f32;
+            self.starfield.step(distance, width, height);
+            for star in &self.starfield.stars {
+                if let Some((x, y)) = star.project(width, height) {
+                    let brightness = star.brightness();
+                    self.tattoy.surface.add_pixel(
+                        x,
+                        y,
+                        (brightness, brightness, brightness, 1.0),
+                    )?;
+                }
+            }
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+/// The screensaver when it's showing a shader.
+pub(crate) struct ScreensaverShader {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// All the special GPU handling code.
+    gpu: super::gpu::pipeline::GPU,
+    /// Whether the screensaver was showing on the last frame.
+    is_showing: bool,
+}
+
+impl crate::tattoys::gpu::shaderer::Shaderer for ScreensaverShader {
+    fn tattoy(&self) -> &crate::tattoys::tattoyer::Tattoyer {
+        &self.tattoy
+    }
+
+    fn tattoy_mut(&mut self) -> &mut crate::tattoys::tattoyer::Tattoyer {
+        &mut self.tattoy
+    }
+
+    fn gpu(&self) -> &super::gpu::pipeline::GPU {
+        &self.gpu
+    }
+
+    fn gpu_mut(&mut self) -> &mut super::gpu::pipeline::GPU {
+        &mut self.gpu
+    }
+
+    async fn is_upload_tty_as_pixels(&self) -> bool {
+        false
+    }
+
+    fn is_upload_tty_with_characters(&self) -> bool {
+        false
+    }
+
+    fn is_cyclable(&self) -> bool {
+        false
+    }
+
+    async fn get_layer(&self) -> i16 {
+        LAYER
+    }
+
+    async fn get_opacity(&self) -> f32 {
+        1.0
+    }
+
+    async fn render_handler(&mut self) -> Result<()> {
+        let config = self.tattoy.state.config.read().await.screensaver.clone();
+        if !update_is_active(&self.tattoy.state, &config).await {
+            if self.is_showing {
+                self.is_showing = false;
+                self.tattoy.send_blank_output().await?;
+            }
+            return Ok(());
+        }
+
+        self.is_showing = true;
+        self.render().await
+    }
+
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+        _index: usize,
+    ) -> Result<Self> {
+        let config_directory = state.config_path.read().await.clone();
+        let config = state.config.read().await.screensaver.clone();
+        let tty_size = *state.tty_size.read().await;
+        let mut gpu = super::gpu::pipeline::GPU::new(
+            config_directory.join(&config.shader),
+            tty_size.width,
+            tty_size.height * 2,
+            state.protocol_tx.clone(),
+            state.get_gpu_device().await?,
+        )
+        .await?;
+        gpu.set_pixel_aspect(state.config.read().await.pixel_aspect);
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "screensaver".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Ok(Self {
+            tattoy,
+            gpu,
+            is_showing: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stars_fly_towards_the_viewer() {
+        let mut star = Star {
+            x: 0.5,
+            y: 0.5,
+            z: 1.0,
+        };
+        assert_eq!(star.project(100, 100), Some((75, 75)));
+        assert!(star.step(0.5));
+        assert!(star.project(100, 100).is_none());
+        assert!(star.brightness() > 0.4);
+        assert!(!star.step(0.5));
+    }
+
+    #[tokio::test]
+    async fn starts_after_being_idle() {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(16);
+        let state = crate::shared_state::SharedState::init(1, 1, protocol_tx)
+            .await
+            .unwrap();
+        let config = Config {
+            idle_minutes: 1.0,
+            ..Config::default()
+        };
+        assert!(!update_is_active(&state, &config).await);
+
+        *state.last_activity.write().await = tokio::time::Instant::now()
+            .checked_sub(std::time::Duration::from_secs(61))
+            .unwrap();
+        assert!(update_is_active(&state, &config).await);
+        assert!(state.get_is_screensaver_active().await);
+    }
+}
@@ -14,9 +14,15 @@ impl Scrollbar {
         output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: std::sync::Arc<crate::shared_state::SharedState>,
     ) -> Self {
-        let tattoy =
-            super::tattoyer::Tattoyer::new("scrollbar".to_owned(), state, 100, 1.0, output_channel)
-                .await;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "scrollbar".to_owned(),
+            state,
+            crate::layers::Group::Overlay.layer(0),
+            1.0,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
         Self { tattoy }
     }
 
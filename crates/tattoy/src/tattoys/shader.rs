@@ -10,7 +10,7 @@ use crate::tattoys::tattoyer::Tattoyer;
     clippy::struct_excessive_bools,
     reason = "We need the bools for the config"
 )]
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
 #[serde(default)]
 pub(crate) struct Config {
     /// Enable/disable the shaders on and off
@@ -18,9 +18,16 @@ pub(crate) struct Config {
     /// The path to a given GLSL shader file.
     pub path: std::path::PathBuf,
     /// The opacity of the rendered shader layer.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub opacity: f32,
     /// The layer (or z-index) into which the shaders are rendered.
+    #[schemars(range(min = crate::layers::MIN_LAYER, max = crate::layers::MAX_LAYER))]
     pub layer: i16,
+    /// Overrides the global frame rate for just this shader.
+    pub frame_rate: Option<u32>,
+    /// How long, in seconds, to crossfade from the old shader when switching to a new one. `0.0`
+    /// switches immediately.
+    pub transition_duration: f32,
     /// The shader is still sent and run on the GPU but it's not rendered to a layer on the
     /// terminal. This is most likely useful in conjunction with `render_shader_colours_to_text`,
     /// as "contents" of the shader are rendered via the terminal's text.
@@ -32,6 +39,62 @@ pub(crate) struct Config {
     /// position. This would most likely be used in conjunction with auto contrast enabled,
     /// otherwise the text won't actually be readable.
     pub render_shader_colours_to_text: bool,
+    /// Upload a mask of where the terminal's text is, so that shaders can read it with
+    /// `iTextMask()`.
+    pub upload_text_mask: bool,
+    /// Upload the colours and attributes of every cell of the terminal, so that shaders can do
+    /// text-aware effects.
+    pub upload_cell_metadata: bool,
+    /// Upload the cells that text most recently appeared in, so that shaders can draw effects
+    /// wherever text appears.
+    pub upload_cell_changes: bool,
+    /// The path to an optional GLSL compute shader that runs before every frame, for simulations
+    /// like fluids or particles. The shader can read what it outputs with `iCompute()`.
+    pub compute_path: Option<std::path::PathBuf>,
+    /// Use a different shader, or opacity, when the shell is in certain directories. The first
+    /// matching rule wins.
+    pub directories: Vec<DirectoryRule>,
+    /// Switch to another shader from `rotate_directory` this often, like `"15m"`. When it isn't
+    /// set the shader only changes when the user changes it.
+    pub rotate_every: Option<String>,
+    /// Whether shaders are rotated through in a random or alphabetical order.
+    pub rotate_order: super::gpu::rotation::Order,
+    /// The directory of shaders to rotate through. Defaults to the directory of `path`.
+    pub rotate_directory: Option<std::path::PathBuf>,
+    /// Globs of shader filenames that are never rotated to, like `wip_*`.
+    pub rotate_exclude: Vec<String>,
+    /// Only render the shader into cells that don't have a background colour, so that it never
+    /// interferes with coloured TUI apps like `htop`.
+    pub under_text_only: bool,
+    /// Only render the shader into cells that don't have any text at all, so that it flows
+    /// around the text.
+    pub only_in_blank_cells: bool,
+    /// Render the shader at a fixed aspect ratio, width over height, like `1.78` for 16:9. It's
+    /// shown in the middle of the terminal with empty bars either side, rather than stretched.
+    #[schemars(range(min = 0.0))]
+    pub aspect_ratio: Option<f32>,
+    /// Move the shader down with the text when scrolling back through the scrollback, rather than
+    /// keeping it fixed to the screen. Shaders also get how far back they are as `iScrollOffset`.
+    pub follow_scrollback: bool,
+    /// Only ever change the background colours of cells, never adding any glyphs of its own. Each
+    /// cell gets the average colour of its pixels, so it's lower resolution, but copying text from
+    /// the terminal and selecting it always behave as if there were no shader at all.
+    pub background_only: bool,
+    /// Linearly change the shader's opacity from the top row to the bottom row, or across the
+    /// columns.
+    pub opacity_gradient: Option<crate::opacity_gradient::Gradient>,
+}
+
+/// A shader that is used automatically when the shell is in a matching directory.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
+pub(crate) struct DirectoryRule {
+    /// A glob for the directories, like `~/work/**`.
+    pub glob: String,
+    /// The shader to use in the matching directories.
+    pub path: std::path::PathBuf,
+    /// Overrides the shader's opacity in the matching directories.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: Option<f32>,
 }
 
 impl Default for Config {
@@ -46,19 +109,113 @@ impl Default for Config {
             .into(),
             opacity: 0.75,
             layer: -10,
+            frame_rate: None,
+            transition_duration: 0.5,
             render: true,
             upload_tty_as_pixels: true,
             render_shader_colours_to_text: false,
+            upload_text_mask: false,
+            upload_cell_metadata: false,
+            upload_cell_changes: false,
+            compute_path: None,
+            directories: Vec::new(),
+            rotate_every: None,
+            rotate_order: super::gpu::rotation::Order::default(),
+            rotate_directory: None,
+            rotate_exclude: Vec::new(),
+            under_text_only: false,
+            only_in_blank_cells: false,
+            aspect_ratio: None,
+            follow_scrollback: false,
+            background_only: false,
+            opacity_gradient: None,
         }
     }
 }
 
+impl Config {
+    /// The first directory rule that matches the directory.
+    pub fn directory_rule(&self, directory: &std::path::Path) -> Option<&DirectoryRule> {
+        self.directories
+            .iter()
+            .find(|rule| crate::cwd::is_glob_match(&rule.glob, directory))
+    }
+
+    /// How the shader is rotated, if it is.
+    pub fn rotation_settings(
+        &self,
+        config_directory: &std::path::Path,
+    ) -> Result<Option<super::gpu::rotation::Settings>> {
+        super::gpu::rotation::Settings::from_config(
+            config_directory,
+            self.rotate_every.as_deref(),
+            self.rotate_order,
+            self.rotate_directory.as_deref(),
+            &self.path,
+            &self.rotate_exclude,
+        )
+    }
+}
+
 /// `Shaders`
 pub(crate) struct Shaders {
     /// The base Tattoy struct
     tattoy: Tattoyer,
     /// All the special GPU handling code.
     gpu: super::gpu::pipeline::GPU,
+    /// Which of the configured shaders this is. `0` is the `[shader]` config section, higher
+    /// indexes are entries in the `[[shaders]]` array.
+    index: usize,
+    /// The shader path from the config. This can differ from the shader that's actually running
+    /// because the user can cycle through shaders with keybindings.
+    configured_path: std::path::PathBuf,
+    /// The directory rule that matches the shell's current directory, if any.
+    directory_rule: Option<DirectoryRule>,
+    /// Rotating through the shaders in a directory.
+    rotation: Option<super::gpu::rotation::Rotation>,
+}
+
+impl Shaders {
+    /// The tattoy ID for the shader at the given index.
+    pub fn id(index: usize) -> String {
+        if index == 0 {
+            "shader".to_owned()
+        } else {
+            format!("shader_{index}")
+        }
+    }
+
+    /// Get a copy of this shader's config.
+    async fn config(&self) -> Config {
+        self.tattoy
+            .state
+            .config
+            .read()
+            .await
+            .shader_at(self.index)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The shader that should be running, taking the directory rules into account.
+    fn wanted_path(&self) -> &std::path::Path {
+        self.directory_rule
+            .as_ref()
+            .map_or(&self.configured_path, |rule| &rule.path)
+    }
+
+    /// Switch shaders if the user's config or the shell's directory mean that a different shader
+    /// should be running. The switch crossfades just like cycling shaders does.
+    async fn switch_if_changed(&mut self, previous: &std::path::Path) -> Result<()> {
+        if self.wanted_path() == previous {
+            return Ok(());
+        }
+
+        tracing::info!("Switching shader to: {:?}", self.wanted_path());
+        let config_directory = self.tattoy.state.config_path.read().await.clone();
+        let path = config_directory.join(self.wanted_path());
+        self.gpu.switch_shader(path).await
+    }
 }
 
 impl crate::tattoys::gpu::shaderer::Shaderer for Shaders {
@@ -79,46 +236,153 @@ impl crate::tattoys::gpu::shaderer::Shaderer for Shaders {
     }
 
     async fn is_upload_tty_as_pixels(&self) -> bool {
-        self.tattoy
-            .state
-            .config
-            .read()
-            .await
-            .shader
-            .upload_tty_as_pixels
+        self.config().await.upload_tty_as_pixels
     }
 
     fn is_upload_tty_with_characters(&self) -> bool {
         true
     }
 
+    async fn is_upload_text_mask(&self) -> bool {
+        self.config().await.upload_text_mask
+    }
+
+    async fn is_upload_cell_metadata(&self) -> bool {
+        self.config().await.upload_cell_metadata
+    }
+
+    async fn is_upload_cell_changes(&self) -> bool {
+        self.config().await.upload_cell_changes
+    }
+
+    fn is_cyclable(&self) -> bool {
+        self.index == 0
+    }
+
     async fn get_layer(&self) -> i16 {
-        self.tattoy().state.config.read().await.shader.layer
+        self.config().await.layer
     }
 
     async fn get_opacity(&self) -> f32 {
-        self.tattoy().state.config.read().await.shader.opacity
+        self.directory_rule
+            .as_ref()
+            .and_then(|rule| rule.opacity)
+            .unwrap_or(self.config().await.opacity)
+    }
+
+    async fn handle_config_update(&mut self, config: &crate::config::main::Config) -> Result<()> {
+        let Some(shader) = config.shader_at(self.index) else {
+            return Ok(());
+        };
+
+        self.gpu.transition_duration = shader.transition_duration;
+        self.gpu.set_letterbox(shader.aspect_ratio).await?;
+        self.gpu
+            .set_follow_scrollback(shader.follow_scrollback)
+            .await?;
+        if shader.path != self.configured_path {
+            tracing::info!("Shader path changed in config to: {:?}", shader.path);
+        }
+
+        let config_directory = self.tattoy.state.config_path.read().await.clone();
+        self.gpu
+            .set_compute_shader(
+                shader
+                    .compute_path
+                    .as_ref()
+                    .map(|path| config_directory.join(path)),
+            )
+            .await?;
+        let settings = super::gpu::rotation::or_report(
+            &self.tattoy.state,
+            shader.rotation_settings(&config_directory),
+        )
+        .await;
+        self.rotation = super::gpu::rotation::Rotation::update(self.rotation.take(), settings);
+
+        let previous = self.wanted_path().to_owned();
+        self.configured_path.clone_from(&shader.path);
+        let directory = self.tattoy.state.cwd.read().await.clone();
+        self.directory_rule =
+            directory.and_then(|directory| shader.directory_rule(&directory).cloned());
+        self.switch_if_changed(&previous).await
+    }
+
+    fn rotation_mut(&mut self) -> Option<&mut super::gpu::rotation::Rotation> {
+        // Directory rules choose a specific shader, so they take priority over rotating.
+        if self.directory_rule.is_some() {
+            return None;
+        }
+        self.rotation.as_mut()
+    }
+
+    async fn handle_directory_change(&mut self, directory: &std::path::Path) -> Result<()> {
+        let config = self.config().await;
+        let previous = self.wanted_path().to_owned();
+        self.directory_rule = config.directory_rule(directory).cloned();
+        self.switch_if_changed(&previous).await
+    }
+
+    async fn render_handler(&mut self) -> Result<()> {
+        if let Some(frame_rate) = self.config().await.frame_rate {
+            self.tattoy.target_frame_rate = frame_rate.max(1);
+        }
+        self.render().await
     }
 
     /// Instantiate
     async fn new(
         output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
         state: std::sync::Arc<crate::shared_state::SharedState>,
+        index: usize,
     ) -> Result<Self> {
         let config_directory = state.config_path.read().await.clone();
-        let shader_path = state.config.read().await.shader.path.clone();
+        let config = state
+            .config
+            .read()
+            .await
+            .shader_at(index)
+            .cloned()
+            .unwrap_or_default();
         let tty_size = *state.tty_size.read().await;
-        let gpu = super::gpu::pipeline::GPU::new(
-            config_directory.join(shader_path),
+        let mut gpu = super::gpu::pipeline::GPU::new(
+            config_directory.join(&config.path),
             tty_size.width,
             tty_size.height * 2,
             state.protocol_tx.clone(),
+            state.get_gpu_device().await?,
         )
         .await?;
-        let layer = state.config.read().await.shader.layer;
-        let opacity = state.config.read().await.shader.opacity;
-        let tattoy =
-            Tattoyer::new("shader".to_owned(), state, layer, opacity, output_channel).await;
-        Ok(Self { tattoy, gpu })
+        gpu.set_pixel_aspect(state.config.read().await.pixel_aspect);
+        gpu.set_letterbox(config.aspect_ratio).await?;
+        gpu.set_follow_scrollback(config.follow_scrollback).await?;
+        gpu.transition_duration = config.transition_duration;
+        gpu.set_compute_shader(
+            config
+                .compute_path
+                .as_ref()
+                .map(|path| config_directory.join(path)),
+        )
+        .await?;
+        let rotation =
+            super::gpu::rotation::or_report(&state, config.rotation_settings(&config_directory))
+                .await
+                .map(super::gpu::rotation::Rotation::new);
+        let tattoy = Tattoyer::new(
+            Self::id(index),
+            state,
+            config.layer,
+            config.opacity,
+            output_channel,
+        )
+        .await;
+        Ok(Self {
+            tattoy,
+            gpu,
+            index,
+            configured_path: config.path,
+            directory_rule: None,
+            rotation,
+        })
     }
 }
@@ -35,7 +35,7 @@ impl StartupLogo {
         let tattoy = super::tattoyer::Tattoyer::new(
             "startup_logo".to_owned(),
             state,
-            200,
+            crate::layers::Group::Overlay.layer(100),
             1.0,
             output_channel,
         )
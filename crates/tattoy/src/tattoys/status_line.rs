@@ -0,0 +1,355 @@
+//! A status line, like Vim's or tmux's, that's always shown on the top or bottom row of the
+//! terminal. The PTY is made a row shorter, so that the status line never covers any of its
+//! output.
+//!
+//! The status line is a template, where these variables are replaced:
+//!
+//! * `{cwd}`: the shell's current directory, with your home directory shortened to `~`.
+//! * `{git_branch}`: the git branch of the current directory.
+//! * `{time}`: the time, using the `time_format` setting.
+//! * `{hostname}`: the name of the computer.
+//! * `{scroll}`: how far up the scrollback you are, like "42/1000", or nothing.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the status line.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(3);
+
+/// Which row the status line is on.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Position {
+    /// The top row, above the PTY.
+    Top,
+    /// The bottom row, below the PTY.
+    #[default]
+    Bottom,
+}
+
+/// User-configurable settings for the status line.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the status line.
+    pub enabled: bool,
+    /// The opacity of the status line.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// Which row the status line is on.
+    pub position: Position,
+    /// The text of the status line, with variables like `{cwd}`.
+    pub template: String,
+    /// A `strftime`-style format for the `{time}` variable.
+    pub time_format: String,
+    /// The time's offset from UTC, in minutes. When not set, the system's offset is used.
+    pub utc_offset_minutes: Option<i64>,
+    /// How often, in seconds, the status line is updated.
+    pub update_interval: f32,
+    /// The colour of the text.
+    pub foreground: crate::surface::Colour,
+    /// The colour of the whole row.
+    pub background: crate::surface::Colour,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 1.0,
+            position: Position::default(),
+            template: " {cwd}  {git_branch}  {scroll}  {hostname}  {time} ".to_owned(),
+            time_format: "%H:%M".to_owned(),
+            utc_offset_minutes: None,
+            update_interval: 1.0,
+            foreground: crate::surface::WHITE,
+            background: (0.1, 0.1, 0.1, 1.0),
+        }
+    }
+}
+
+impl Config {
+    /// How many rows of the user's terminal the status line takes away from the PTY and panes.
+    pub const fn reserved_rows(&self) -> u16 {
+        if self.enabled {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// How many rows of the user's terminal are above the PTY and panes.
+    pub fn rows_above_pty(&self) -> u16 {
+        if self.position == Position::Top {
+            self.reserved_rows()
+        } else {
+            0
+        }
+    }
+}
+
+/// Replace every `{name}` in the template with its value. Unknown variables are left as they are.
+pub(crate) fn fill_template(template: &str, variables: &[(&str, &str)]) -> String {
+    variables
+        .iter()
+        .fold(template.to_owned(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+/// Parse the contents of a repo's `.git/HEAD` file. It's either a reference to a branch or, when
+/// the `HEAD` is detached, a commit hash.
+fn parse_head(head: &str) -> Option<String> {
+    let head = head.trim();
+    if let Some(reference) = head.strip_prefix("ref: ") {
+        return Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_owned(),
+        );
+    }
+    (!head.is_empty()).then(|| head.chars().take(7).collect())
+}
+
+/// The git branch of a directory, by looking for the `.git` of the repo it's in. Reading the
+/// `HEAD` is much cheaper than running `git`, so it can be done on every update.
+async fn git_branch(directory: &std::path::Path) -> Option<String> {
+    for ancestor in directory.ancestors() {
+        let dot_git = ancestor.join(".git");
+        let git_directory = match tokio::fs::read_to_string(&dot_git).await {
+            // Worktrees and submodules have a `.git` file that points to the real git directory.
+            Ok(pointer) => ancestor.join(pointer.trim().strip_prefix("gitdir: ")?),
+            Err(_) if dot_git.is_dir() => dot_git,
+            Err(_) => continue,
+        };
+        let head = tokio::fs::read_to_string(git_directory.join("HEAD"))
+            .await
+            .ok()?;
+        return parse_head(&head);
+    }
+
+    None
+}
+
+/// The name of the computer. There's no way to get it in the standard library, so we ask
+/// `hostname`.
+async fn hostname() -> String {
+    let result = tokio::process::Command::new("hostname")
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await;
+    match result {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_owned()
+        }
+        Ok(_) => String::new(),
+        Err(error) => {
+            tracing::debug!("Couldn't get the hostname: {error:?}");
+            String::new()
+        }
+    }
+}
+
+/// `StatusLine`
+pub(crate) struct StatusLine {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The name of the computer.
+    hostname: String,
+    /// The system's offset from UTC, in minutes.
+    system_utc_offset: i64,
+    /// The text that was last rendered.
+    text: String,
+    /// When the status line was last updated.
+    last_update: Option<tokio::time::Instant>,
+}
+
+impl StatusLine {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.status_line.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "status_line".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            hostname: hostname().await,
+            system_utc_offset: super::widget::system_utc_offset().await,
+            text: String::new(),
+            last_update: None,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut status_line = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = status_line.tattoy.sleep_until_next_frame_tick() => {
+                    status_line.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if matches!(
+                        message,
+                        crate::run::Protocol::Resize { .. }
+                            | crate::run::Protocol::Config(_)
+                            | crate::run::Protocol::DirectoryChanged(_)
+                    ) || super::tattoyer::Tattoyer::is_scrollback_output_changed(&message)
+                    {
+                        status_line.last_update = None;
+                    }
+                    status_line.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Update the status line if it's due.
+    async fn tick(&mut self) -> Result<()> {
+        let config = self.tattoy.state.config.read().await.status_line.clone();
+        let interval = std::time::Duration::try_from_secs_f32(config.update_interval.max(0.1))
+            .unwrap_or_default();
+        let is_due = self
+            .last_update
+            .is_none_or(|last_update| last_update.elapsed() >= interval);
+        if !is_due {
+            return Ok(());
+        }
+
+        let is_first_update = self.last_update.is_none();
+        self.last_update = Some(tokio::time::Instant::now());
+        let text = self.build_text(&config).await?;
+        if text == self.text && !is_first_update {
+            return Ok(());
+        }
+        self.text = text;
+
+        self.render(&config).await
+    }
+
+    /// Fill in the template's variables.
+    async fn build_text(&self, config: &Config) -> Result<String> {
+        let maybe_directory = self.tattoy.state.cwd.read().await.clone();
+        let cwd = maybe_directory
+            .as_ref()
+            .map(|directory| match dirs::home_dir() {
+                Some(home) => match directory.strip_prefix(&home) {
+                    Ok(relative) if relative.as_os_str().is_empty() => "~".to_owned(),
+                    Ok(relative) => format!("~/{}", relative.display()),
+                    Err(_) => directory.display().to_string(),
+                },
+                None => directory.display().to_string(),
+            })
+            .unwrap_or_default();
+        let branch = match maybe_directory {
+            Some(directory) => git_branch(&directory).await.unwrap_or_default(),
+            None => String::new(),
+        };
+
+        let unix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+        let offset = config.utc_offset_minutes.unwrap_or(self.system_utc_offset);
+        let local = i64::try_from(unix.as_secs())? + offset * 60;
+        let time = super::widget::DateTime::from_unix(local).format(&config.time_format);
+
+        let scroll = if self.tattoy.is_scrolling() {
+            let lines = self.tattoy.scrollback.surface.dimensions().1;
+            format!("{}/{lines}", self.tattoy.scrollback.position)
+        } else {
+            String::new()
+        };
+
+        Ok(fill_template(
+            &config.template,
+            &[
+                ("cwd", &cwd),
+                ("git_branch", &branch),
+                ("time", &time),
+                ("hostname", &self.hostname),
+                ("scroll", &scroll),
+            ],
+        ))
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config) -> Result<()> {
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+
+        let width = usize::from(self.tattoy.width);
+        let y = match config.position {
+            Position::Top => 0,
+            Position::Bottom => usize::from(self.tattoy.height).saturating_sub(1),
+        };
+        let text: String = self
+            .text
+            .chars()
+            .chain(core::iter::repeat(' '))
+            .take(width)
+            .collect();
+        self.tattoy
+            .surface
+            .add_text(0, y, text, Some(config.background), Some(config.foreground));
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fills_templates() {
+        assert_eq!(
+            fill_template(
+                " {cwd} ({git_branch}) {unknown} {cwd}",
+                &[("cwd", "~/tattoy"), ("git_branch", "main")]
+            ),
+            " ~/tattoy (main) {unknown} ~/tattoy"
+        );
+    }
+
+    #[test]
+    fn parses_git_heads() {
+        assert_eq!(
+            parse_head("ref: refs/heads/feature/status\n"),
+            Some("feature/status".to_owned())
+        );
+        assert_eq!(parse_head("1234567890abcdef\n"), Some("1234567".to_owned()));
+        assert_eq!(parse_head(""), None);
+    }
+
+    #[test]
+    fn only_reserves_a_row_when_enabled() {
+        let mut config = Config::default();
+        assert_eq!(config.reserved_rows(), 0);
+        config.enabled = true;
+        assert_eq!((config.reserved_rows(), config.rows_above_pty()), (1, 0));
+        config.position = Position::Top;
+        assert_eq!(config.rows_above_pty(), 1);
+    }
+}
@@ -0,0 +1,98 @@
+//! The watermark that's shown in stream mode, like a channel's name or logo text.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the watermark. It's above everything but the other overlays, so that it can't be
+/// covered up by the PTY's text.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(4);
+
+/// `StreamWatermark`
+pub(crate) struct StreamWatermark {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// Whether something has changed that needs rendering.
+    is_dirty: bool,
+}
+
+impl StreamWatermark {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.stream_mode.watermark_opacity;
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "stream_watermark".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        Self {
+            tattoy,
+            is_dirty: true,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut watermark = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = watermark.tattoy.sleep_until_next_frame_tick() => {
+                    if watermark.is_dirty {
+                        watermark.render().await?;
+                    }
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if matches!(
+                        message,
+                        crate::run::Protocol::Config(_) | crate::run::Protocol::Resize { .. }
+                    ) {
+                        watermark.is_dirty = true;
+                    }
+                    watermark.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.is_dirty = false;
+        let config = self.tattoy.state.config.read().await.stream_mode.clone();
+        let is_stream_mode = *self.tattoy.state.is_stream_mode.read().await;
+        if !is_stream_mode || config.watermark.is_empty() {
+            return self.tattoy.send_blank_output().await;
+        }
+        self.tattoy.opacity = config.watermark_opacity;
+        self.tattoy.initialise_surface();
+
+        let text = format!(" {} ", config.watermark);
+        let (x, y) = config.watermark_position.place(
+            self.tattoy.width.into(),
+            self.tattoy.height.into(),
+            self.tattoy.text_width(&text),
+        );
+        self.tattoy
+            .surface
+            .add_text(x, y, text, None, Some(config.watermark_colour));
+
+        self.tattoy.send_output().await
+    }
+}
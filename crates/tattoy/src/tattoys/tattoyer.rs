@@ -8,6 +8,8 @@ pub(crate) struct Tattoyer {
     pub id: String,
     /// The compositing layer that the tattoy is rendered to. 0 is the PTY screen itself.
     pub layer: i16,
+    /// Where the tattoy is allowed to be rendered relative to the PTY's text.
+    pub placement: crate::layers::Placement,
     /// The transparency of layer.
     pub opacity: f32,
     /// The application shared state
@@ -30,6 +32,20 @@ pub(crate) struct Tattoyer {
     pub last_frame_tick: tokio::time::Instant,
     /// The last known position of an active scroll.
     pub last_scroll_position: usize,
+    /// Whether the config, normally because of a scene, has turned this tattoy off. It keeps
+    /// running so that it can be turned back on, but its frames aren't sent.
+    pub is_switched_off: bool,
+    /// Whether the blank frame that removes this tattoy from the screen has been sent since it was
+    /// turned off.
+    pub is_cleared: bool,
+    /// The rows of the user's terminal that the status line takes from the PTY.
+    pub reserved_rows: u16,
+    /// How the widths of Unicode characters are measured.
+    pub unicode: crate::unicode::Config,
+    /// The braille dots that the tattoy draws with, when it's been configured to.
+    pub braille: Option<crate::braille::Canvas>,
+    /// Whether the terminal was split into panes, as of the latest screen update.
+    is_split: bool,
 }
 
 impl Tattoyer {
@@ -42,10 +58,18 @@ impl Tattoyer {
         output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
     ) -> Self {
         let tty_size = state.get_tty_size().await;
-        let target_frame_rate = state.config.read().await.frame_rate;
+        let config = state.config.read().await;
+        let target_frame_rate = config.frame_rate;
+        let is_switched_off = config.is_tattoy_enabled(&id) == Some(false);
+        let reserved_rows = config.status_line.reserved_rows();
+        let unicode = config.unicode;
+        let is_braille = config.render.braille.contains(&id);
+        drop(config);
+        let is_split = state.panes.read().await.is_split();
         Self {
             id: id.clone(),
             layer,
+            placement: crate::layers::Placement::default(),
             opacity,
             state,
             output_channel,
@@ -57,11 +81,31 @@ impl Tattoyer {
             target_frame_rate,
             last_frame_tick: tokio::time::Instant::now(),
             last_scroll_position: 0,
+            is_switched_off,
+            is_cleared: false,
+            reserved_rows,
+            unicode,
+            braille: is_braille.then(crate::braille::Canvas::default),
+            is_split,
         }
     }
 
-    /// Create an empty surface ready for building a new frame.
+    /// Create an empty surface ready for building a new frame. The current surface is cleared
+    /// and reused if it's still the right size.
     pub fn initialise_surface(&mut self) {
+        let width = usize::from(self.width);
+        let height = usize::from(self.height);
+        if let Some(braille) = self.braille.as_mut() {
+            braille.reset(width, height);
+        }
+        if self.surface.width == width && self.surface.height == height {
+            self.surface.id.clone_from(&self.id);
+            self.surface.layer = self.layer;
+            self.surface.opacity = self.opacity;
+            self.surface.clear();
+            return;
+        }
+
         self.surface = crate::surface::Surface::new(
             self.id.clone(),
             self.width.into(),
@@ -71,6 +115,59 @@ impl Tattoyer {
         );
     }
 
+    /// Draw a point at pixel coords, where a cell is 1x2 pixels. The coords can be fractions, which
+    /// are only used when the tattoy is drawn with braille dots. Points beyond the edge of the
+    /// surface are clipped.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "Negative values are checked for and positions are always small"
+    )]
+    pub fn add_point(&mut self, x: f32, y: f32, colour: crate::surface::Colour) {
+        if let Some(braille) = self.braille.as_mut() {
+            braille.add_point(x, y, colour);
+            return;
+        }
+        if x < 0.0 || y < 0.0 {
+            return;
+        }
+        let (pixel_x, pixel_y) = (x.floor() as usize, y.floor() as usize);
+        if pixel_x < self.surface.width && pixel_y < self.surface.height * 2 {
+            // The coords have just been checked, so this can't fail.
+            self.surface.add_pixel(pixel_x, pixel_y, colour).ok();
+        }
+    }
+
+    /// Draw any braille dots onto the surface, ready for it be sent.
+    fn draw_braille(&mut self) {
+        if let Some(braille) = self.braille.as_ref() {
+            braille.draw(&mut self.surface);
+        }
+    }
+
+    /// Set the layer that the tattoy is rendered to, honouring the tattoy's placement.
+    pub const fn set_layer(&mut self, layer: i16) {
+        self.layer = self.placement.apply(layer);
+        self.surface.layer = self.layer;
+    }
+
+    /// Set where the tattoy is allowed to be rendered relative to the PTY's text.
+    pub const fn set_placement(&mut self, placement: crate::layers::Placement) {
+        self.placement = placement;
+        self.set_layer(self.layer);
+    }
+
+    /// Make sure the tattoy is always rendered above the PTY's text.
+    pub const fn keep_above_text(&mut self) {
+        self.set_placement(crate::layers::Placement::AboveText);
+    }
+
+    /// Make sure the tattoy is always rendered below the PTY's text.
+    pub const fn keep_below_text(&mut self) {
+        self.set_placement(crate::layers::Placement::BelowText);
+    }
+
     /// Keep track of the size of the underlying terminal.
     pub const fn set_tty_size(&mut self, width: u16, height: u16) {
         self.width = width;
@@ -96,13 +193,32 @@ impl Tattoyer {
                 self.set_tty_size(width, height);
             }
             crate::run::Protocol::Output(output) => self.handle_pty_output(output)?,
-            crate::run::Protocol::Config(config) => self.target_frame_rate = config.frame_rate,
+            crate::run::Protocol::Config(config) => {
+                self.target_frame_rate = config.frame_rate;
+                self.is_switched_off = config.is_tattoy_enabled(&self.id) == Some(false);
+                self.reserved_rows = config.status_line.reserved_rows();
+                self.unicode = config.unicode;
+                let is_braille = config.render.braille.contains(&self.id);
+                if is_braille != self.braille.is_some() {
+                    self.braille = is_braille.then(crate::braille::Canvas::default);
+                }
+            }
+            crate::run::Protocol::ResetTattoy(id) if id == self.id => {
+                tracing::warn!("Resetting '{id}' as it stalled the renderer");
+                self.initialise_surface();
+                self.is_cleared = false;
+            }
             _ => (),
         }
 
         Ok(())
     }
 
+    /// The number of columns that some text takes on the screen.
+    pub fn text_width(&self, text: &str) -> usize {
+        self.unicode.width(text)
+    }
+
     /// Whether the user is scolling.
     pub const fn is_scrolling(&self) -> bool {
         self.scrollback.position != 0
@@ -139,10 +255,19 @@ impl Tattoyer {
                     self.screen
                         .surface
                         .resize(screen_diff.size.0, screen_diff.size.1);
-                    self.set_tty_size(
-                        screen_diff.size.0.try_into()?,
-                        screen_diff.size.1.try_into()?,
-                    );
+                    // Panes are arranged side-by-side, so the screen's height is always the TTY's
+                    // height, less any row taken by the status line. But its width may only be
+                    // the width of the main pane, so it's only the TTY's width when there aren't
+                    // any other panes. This isn't async, so the layout's lock is only tried, and when
+                    // it's busy the previous layout is assumed.
+                    if let Ok(panes) = self.state.panes.try_read() {
+                        self.is_split = panes.is_split();
+                    }
+                    let pty_height: u16 = screen_diff.size.1.try_into()?;
+                    self.height = pty_height + self.reserved_rows;
+                    if !self.is_split {
+                        self.width = screen_diff.size.0.try_into()?;
+                    }
                     self.screen.surface.add_changes(screen_diff.changes);
                 }
                 _ => (),
@@ -164,11 +289,51 @@ impl Tattoyer {
         Ok(())
     }
 
-    /// Send the final surface to the main renderer.
+    /// Send the final surface to the main renderer. The surface is handed over rather than
+    /// copied, so afterwards `self.surface` is a spare from the pool, with old content, that
+    /// must be initialised before building the next frame.
     pub(crate) async fn send_output(&mut self) -> Result<()> {
-        self.output_channel
-            .send(crate::run::FrameUpdate::TattoySurface(self.surface.clone()))
-            .await?;
+        self.draw_braille();
+        let spare = self
+            .state
+            .surface_pool
+            .write()
+            .await
+            .take(&self.id, self.surface.width, self.surface.height)
+            .unwrap_or_else(|| {
+                crate::surface::Surface::new(self.id.clone(), 0, 0, self.layer, self.opacity)
+            });
+        let surface = std::mem::replace(&mut self.surface, spare);
+        self.send_frame(surface).await
+    }
+
+    /// Send a copy of the final surface to the main renderer, for tattoys that build each frame
+    /// on top of the last one.
+    pub(crate) async fn send_output_and_keep(&mut self) -> Result<()> {
+        self.draw_braille();
+        self.send_frame(self.surface.clone()).await
+    }
+
+    /// Send a frame to the renderer, following the tattoy's backpressure policy.
+    async fn send_frame(&mut self, mut surface: crate::surface::Surface) -> Result<()> {
+        if self.is_switched_off {
+            if self.is_cleared {
+                return Ok(());
+            }
+            surface.width = 0;
+            surface.height = 0;
+            self.is_cleared = true;
+        } else {
+            self.is_cleared = false;
+        }
+
+        let config = self.state.config.read().await;
+        let policy = config.backpressure.policy(&self.id);
+        if self.is_decorative() {
+            surface.clear_margins(&config.render.margins);
+        }
+        drop(config);
+        crate::backpressure::send_frame(&self.state, &self.output_channel, policy, surface).await?;
 
         self.last_scroll_position = self.scrollback.position;
 
@@ -183,6 +348,67 @@ impl Tattoyer {
         self.send_output().await
     }
 
+    /// Whether the tattoy is a decoration, rather than the PTY's text, the panes or an overlay like
+    /// notifications. Decorations are kept out of the margins.
+    fn is_decorative(&self) -> bool {
+        self.id != crate::panes::manager::ID
+            && matches!(
+                crate::layers::Group::of(self.layer),
+                Some(crate::layers::Group::Background | crate::layers::Group::Effects)
+            )
+    }
+
+    /// Whether this tattoy is one of the expensive ones that pause during bursts of output from the
+    /// PTY, and there's currently a burst.
+    pub async fn is_paused_for_output_burst(&self) -> bool {
+        if !*self.state.is_output_bursting.read().await {
+            return false;
+        }
+        self.state
+            .config
+            .read()
+            .await
+            .output_burst
+            .tattoys
+            .contains(&self.id)
+    }
+
+    /// Whether the renderer has composited this tattoy's last frame. Tattoys that render on every
+    /// frame tick can skip rendering until it has, rather than rendering frames that would only
+    /// be dropped.
+    pub async fn is_ready_for_frame(&self) -> bool {
+        self.state.frame_pacing.write().await.is_ready(&self.id)
+    }
+
+    /// Run compute-heavy work, like blurring or resizing images, on a thread of the blocking pool
+    /// rather than on the async executor, where it would hold up every other tattoy. Only as many
+    /// jobs as there are CPUs are run at once, so that tattoys can't starve Tattoy of threads
+    /// either.
+    pub async fn compute<T, F>(&self, work: F) -> Result<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = self.state.compute_permits.acquire().await?;
+        let (result, duration) = tokio::task::spawn_blocking(move || {
+            let started = std::time::Instant::now();
+            let result = work();
+            (result, started.elapsed())
+        })
+        .await
+        .map_err(|error| {
+            color_eyre::eyre::eyre!("'{}' compute job didn't finish: {error}", self.id)
+        })?;
+        drop(permit);
+        self.state
+            .metrics
+            .write()
+            .await
+            .tattoy_cpu(&self.id, duration);
+
+        Ok(result)
+    }
+
     /// Sleep until the next frame render is due.
     pub async fn sleep_until_next_frame_tick(&mut self) {
         let target = crate::renderer::ONE_MICROSECOND.wrapping_div(self.target_frame_rate.into());
@@ -333,23 +559,41 @@ impl Tattoyer {
     }
 
     /// Depending on whether the `upload_tty_as_pixels` config is set by the user, decide what to
-    /// send the GPU in order to represent the terminal contents.
+    /// send the GPU in order to represent the terminal contents. Images of the TTY are shared with
+    /// the other shaders, so each screen is only converted once.
     pub async fn get_tty_image_for_upload(
         &mut self,
         is_upload_tty_as_pixels: bool,
         is_upload_characters: bool,
     ) -> Result<image::RgbaImage> {
-        let image = if is_upload_tty_as_pixels {
-            self.convert_pty_to_pixel_image(
+        if !is_upload_tty_as_pixels {
+            return Ok(self.pure_black_image());
+        }
+
+        let default_background = *self.state.default_background.read().await;
+        let key = crate::tattoys::gpu::tty_pixels::Key::new(
+            &self.screen.surface,
+            default_background,
+            is_upload_characters,
+        );
+        let maybe_cached = self.state.tty_pixels.read().await.get(key);
+        if let Some(image) = maybe_cached {
+            return Ok(image);
+        }
+
+        let image: image::RgbaImage = self
+            .convert_pty_to_pixel_image(
                 &shadow_terminal::output::native::SurfaceKind::Screen,
                 is_upload_characters,
             )
             .await?
             .flipv()
-            .into()
-        } else {
-            self.pure_black_image()
-        };
+            .into();
+        self.state
+            .tty_pixels
+            .write()
+            .await
+            .insert(key, image.clone());
 
         Ok(image)
     }
@@ -363,3 +607,52 @@ impl Tattoyer {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn compute_jobs_wait_for_a_permit() {
+        let (protocol_tx, _) = tokio::sync::broadcast::channel(1);
+        let state = crate::shared_state::SharedState::init(1, 1, protocol_tx)
+            .await
+            .unwrap();
+        let (output, _) = tokio::sync::mpsc::channel(1);
+        let tattoy = Tattoyer::new(
+            "test".to_owned(),
+            std::sync::Arc::clone(&state),
+            1,
+            1.0,
+            output,
+        )
+        .await;
+
+        // Leave just one permit, so that only one job can run at a time.
+        let spare = state.compute_permits.available_permits().saturating_sub(1);
+        let _held = state
+            .compute_permits
+            .acquire_many(u32::try_from(spare).unwrap())
+            .await
+            .unwrap();
+
+        let running = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let most = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let job = || {
+            let running = std::sync::Arc::clone(&running);
+            let most = std::sync::Arc::clone(&most);
+            move || {
+                let ordering = std::sync::atomic::Ordering::SeqCst;
+                let now = running.fetch_add(1, ordering).saturating_add(1);
+                most.fetch_max(now, ordering);
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                running.fetch_sub(1, ordering);
+            }
+        };
+
+        let (first, second) = tokio::join!(tattoy.compute(job()), tattoy.compute(job()));
+        first.unwrap();
+        second.unwrap();
+        assert_eq!(most.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,390 @@
+//! A Pomodoro-style countdown timer. It's started, paused and reset with keybindings, it shows a
+//! small badge in a corner of the terminal, and flashes the whole screen when it finishes.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the timer. The badge needs to be readable over the terminal's text.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(10);
+
+/// The number of pixels in each row of the terminal.
+const PIXELS_PER_LINE: usize = 2;
+
+/// User-configurable settings for the timer.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the timer.
+    pub enabled: bool,
+    /// The opacity of the badge and flash.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// The length of a work session, in minutes.
+    pub work_minutes: f32,
+    /// The length of a break, in minutes.
+    pub break_minutes: f32,
+    /// Which corner the badge is shown in.
+    pub position: crate::utils::Corner,
+    /// The colour of the badge's text.
+    pub foreground: crate::surface::Colour,
+    /// The colour of the badge's background.
+    pub background: crate::surface::Colour,
+    /// The colour of the flash when the timer finishes.
+    pub flash_colour: crate::surface::Colour,
+    /// How long the flash lasts, in seconds.
+    pub flash_duration: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 1.0,
+            work_minutes: 25.0,
+            break_minutes: 5.0,
+            position: crate::utils::Corner::BottomRight,
+            foreground: crate::surface::WHITE,
+            background: (0.6, 0.1, 0.1, 1.0),
+            flash_colour: crate::surface::WHITE,
+            flash_duration: 1.0,
+        }
+    }
+}
+
+/// Whether the timer is counting down a work session or a break.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Time to concentrate.
+    #[default]
+    Work,
+    /// Time to relax.
+    Break,
+}
+
+impl Phase {
+    /// The length of the phase.
+    fn duration(self, config: &Config) -> std::time::Duration {
+        let minutes = match self {
+            Self::Work => config.work_minutes,
+            Self::Break => config.break_minutes,
+        };
+        std::time::Duration::try_from_secs_f32(minutes.max(0.0) * 60.0).unwrap_or_default()
+    }
+
+    /// The label shown in the badge.
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Work => "Work",
+            Self::Break => "Break",
+        }
+    }
+
+    /// The other phase.
+    const fn next(self) -> Self {
+        match self {
+            Self::Work => Self::Break,
+            Self::Break => Self::Work,
+        }
+    }
+}
+
+/// The countdown itself, kept separate from the tattoy so that it can be tested.
+#[derive(Debug, Default)]
+struct Countdown {
+    /// The current phase.
+    phase: Phase,
+    /// The time that was left when the countdown was last paused. `None` means that the timer
+    /// has been reset.
+    remaining_when_paused: Option<std::time::Duration>,
+    /// When the countdown was last started or resumed. `None` means that it's paused.
+    resumed_at: Option<tokio::time::Instant>,
+}
+
+impl Countdown {
+    /// Start, or pause, the countdown.
+    fn toggle(&mut self, config: &Config, now: tokio::time::Instant) {
+        if self.resumed_at.is_some() {
+            self.remaining_when_paused = Some(self.remaining(config, now));
+            self.resumed_at = None;
+        } else {
+            if self.remaining_when_paused.is_none() {
+                self.remaining_when_paused = Some(self.phase.duration(config));
+            }
+            self.resumed_at = Some(now);
+        }
+    }
+
+    /// Stop the countdown and go back to the beginning of a work session.
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Has the countdown been started since it was last reset?
+    const fn is_started(&self) -> bool {
+        self.remaining_when_paused.is_some()
+    }
+
+    /// Is the countdown currently counting down?
+    const fn is_running(&self) -> bool {
+        self.resumed_at.is_some()
+    }
+
+    /// The time left in the current phase.
+    fn remaining(&self, config: &Config, now: tokio::time::Instant) -> std::time::Duration {
+        let remaining = self
+            .remaining_when_paused
+            .unwrap_or_else(|| self.phase.duration(config));
+        match self.resumed_at {
+            Some(resumed_at) => remaining.saturating_sub(now.saturating_duration_since(resumed_at)),
+            None => remaining,
+        }
+    }
+
+    /// If the current phase has just finished then move to the next one, ready to be started.
+    /// Returns the phase that finished.
+    fn check_finished(&mut self, config: &Config, now: tokio::time::Instant) -> Option<Phase> {
+        if !self.is_running() || !self.remaining(config, now).is_zero() {
+            return None;
+        }
+
+        let finished = self.phase;
+        self.phase = finished.next();
+        self.remaining_when_paused = Some(self.phase.duration(config));
+        self.resumed_at = None;
+        Some(finished)
+    }
+
+    /// The text of the badge.
+    fn badge(&self, config: &Config, now: tokio::time::Instant) -> String {
+        let seconds = self.remaining(config, now).as_secs_f32().ceil();
+        let minutes = (seconds / 60.0).floor();
+        let seconds = seconds - minutes * 60.0;
+        let paused = if self.is_running() { "" } else { " (paused)" };
+        format!(" {} {minutes:02}:{seconds:02}{paused} ", self.phase.label())
+    }
+}
+
+/// `Timer`
+pub(crate) struct Timer {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The countdown.
+    countdown: Countdown,
+    /// When the last finished flash started.
+    flash_started: Option<tokio::time::Instant>,
+    /// Whether something has changed that needs rendering.
+    is_dirty: bool,
+}
+
+impl Timer {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.timer.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "timer".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            countdown: Countdown::default(),
+            flash_started: None,
+            is_dirty: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut timer = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = timer.tattoy.sleep_until_next_frame_tick() => {
+                    timer.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if let crate::run::Protocol::KeybindEvent(event) = &message {
+                        timer.handle_keybind(event).await;
+                    }
+                    if matches!(message, crate::run::Protocol::Resize { .. }) {
+                        timer.is_dirty = true;
+                    }
+                    timer.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start, pause or reset the timer.
+    async fn handle_keybind(&mut self, event: &crate::config::input::KeybindingAction) {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only care about the timer's keybindings"
+        )]
+        match event {
+            crate::config::input::KeybindingAction::TimerToggle => {
+                let config = self.tattoy.state.config.read().await.timer.clone();
+                self.countdown.toggle(&config, tokio::time::Instant::now());
+                self.is_dirty = true;
+            }
+            crate::config::input::KeybindingAction::TimerReset => {
+                self.countdown.reset();
+                self.is_dirty = true;
+            }
+            _ => (),
+        }
+    }
+
+    /// Check whether the timer has finished and render if needed.
+    async fn tick(&mut self) -> Result<()> {
+        let config = self.tattoy.state.config.read().await.timer.clone();
+        let now = tokio::time::Instant::now();
+
+        if let Some(finished) = self.countdown.check_finished(&config, now) {
+            self.flash_started = Some(now);
+            let title = match finished {
+                Phase::Work => "Time for a break",
+                Phase::Break => "Break's over, back to work",
+            };
+            self.tattoy
+                .state
+                .send_notification(
+                    title,
+                    crate::tattoys::notifications::message::Level::Info,
+                    None,
+                    false,
+                )
+                .await;
+        }
+
+        let is_flashing = self.flash_started.is_some();
+        if self.is_dirty || self.countdown.is_running() || is_flashing {
+            self.is_dirty = false;
+            self.render(&config, now).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config, now: tokio::time::Instant) -> Result<()> {
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+
+        if let Some(flash_started) = self.flash_started {
+            let age = now.saturating_duration_since(flash_started).as_secs_f32();
+            let duration = config.flash_duration.max(0.01);
+            if age < duration {
+                self.render_flash(config, 1.0 - age / duration)?;
+            } else {
+                self.flash_started = None;
+            }
+        }
+
+        if self.countdown.is_started() {
+            self.render_badge(config, now);
+        } else if self.flash_started.is_none() {
+            return self.tattoy.send_blank_output().await;
+        }
+
+        self.tattoy.send_output().await
+    }
+
+    /// Cover the whole terminal with the flash colour, fading out as it gets older.
+    fn render_flash(&mut self, config: &Config, strength: f32) -> Result<()> {
+        let mut colour = config.flash_colour;
+        colour.3 *= strength;
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height) * PIXELS_PER_LINE;
+        for y in 0..height {
+            for x in 0..width {
+                self.tattoy.surface.add_pixel(x, y, colour)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render the countdown badge in the configured corner.
+    fn render_badge(&mut self, config: &Config, now: tokio::time::Instant) {
+        let text = self.countdown.badge(config, now);
+        let (x, y) = config.position.place(
+            self.tattoy.width.into(),
+            self.tattoy.height.into(),
+            text.len(),
+        );
+
+        self.tattoy
+            .surface
+            .add_text(x, y, text, Some(config.background), Some(config.foreground));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            work_minutes: 1.0,
+            break_minutes: 0.5,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn counts_down_and_pauses() {
+        let config = config();
+        let start = tokio::time::Instant::now();
+        let mut countdown = Countdown::default();
+        assert!(!countdown.is_started());
+
+        countdown.toggle(&config, start);
+        let later = start + std::time::Duration::from_secs(10);
+        assert_eq!(countdown.badge(&config, later), " Work 00:50 ");
+
+        countdown.toggle(&config, later);
+        let much_later = later + std::time::Duration::from_secs(100);
+        assert_eq!(
+            countdown.badge(&config, much_later),
+            " Work 00:50 (paused) "
+        );
+        assert!(countdown.check_finished(&config, much_later).is_none());
+
+        countdown.reset();
+        assert!(!countdown.is_started());
+    }
+
+    #[test]
+    fn finishing_moves_to_the_next_phase() {
+        let config = config();
+        let start = tokio::time::Instant::now();
+        let mut countdown = Countdown::default();
+        countdown.toggle(&config, start);
+
+        let end = start + std::time::Duration::from_secs(60);
+        assert_eq!(countdown.check_finished(&config, end), Some(Phase::Work));
+        assert_eq!(countdown.phase, Phase::Break);
+        assert!(!countdown.is_running());
+        assert_eq!(countdown.badge(&config, end), " Break 00:30 (paused) ");
+    }
+}
@@ -0,0 +1,306 @@
+//! A small widget showing how fast you're typing, in words per minute, and how many keys you've
+//! pressed. A word is the standard five characters, so the speed doesn't depend on what's typed.
+//!
+//! Only counts are kept, never what was typed. The session's statistics are also available from
+//! the remote control API with `GET /typing_speed`, whilst this tattoy is running.
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+
+/// The layer of the widget.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(6);
+
+/// The number of characters in a standard word.
+const CHARACTERS_PER_WORD: f64 = 5.0;
+
+/// Pauses longer than this aren't counted as time spent typing.
+const IDLE_GAP: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How often the widget is updated, as the speed slows down even when nothing's typed.
+const UPDATE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// User-configurable settings for the typing speed widget.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the typing speed widget.
+    pub enabled: bool,
+    /// The opacity of the widget.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// Which corner the widget is shown in.
+    pub position: crate::utils::Corner,
+    /// The colour of the widget's text.
+    pub colour: crate::surface::Colour,
+    /// How far back, in seconds, the live speed is measured over.
+    pub window: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.7,
+            position: crate::utils::Corner::TopRight,
+            colour: (0.7, 0.7, 0.7, 1.0),
+            window: 30.0,
+        }
+    }
+}
+
+/// A count as a float, for working out speeds.
+fn to_float(count: impl TryInto<u32>) -> f64 {
+    f64::from(count.try_into().unwrap_or(u32::MAX))
+}
+
+/// The words per minute of some characters typed over some time.
+fn words_per_minute(characters: f64, duration: std::time::Duration) -> f64 {
+    let minutes = duration.as_secs_f64() / 60.0;
+    if minutes <= 0.0 {
+        return 0.0;
+    }
+    characters / CHARACTERS_PER_WORD / minutes
+}
+
+/// The session's typing statistics.
+#[derive(Debug, Default)]
+pub(crate) struct Stats {
+    /// Every key that's been pressed.
+    keystrokes: u64,
+    /// The keys that typed a character.
+    characters: u64,
+    /// The time spent typing, not counting pauses.
+    typing: std::time::Duration,
+    /// When the last key was pressed.
+    last_keystroke: Option<std::time::Instant>,
+    /// When each of the recently typed characters was typed, oldest first.
+    recent: std::collections::VecDeque<std::time::Instant>,
+    /// The fastest live speed so far.
+    peak_wpm: f64,
+}
+
+impl Stats {
+    /// A key was pressed. `is_character` is whether it typed something.
+    pub fn keystroke(&mut self, is_character: bool, now: std::time::Instant, window: f32) {
+        self.keystrokes += 1;
+        if let Some(last) = self.last_keystroke {
+            let gap = now.saturating_duration_since(last);
+            if gap < IDLE_GAP {
+                self.typing += gap;
+            }
+        }
+        self.last_keystroke = Some(now);
+
+        if is_character {
+            self.characters += 1;
+            self.recent.push_back(now);
+        }
+        self.peak_wpm = self.peak_wpm.max(self.live_wpm(now, window));
+    }
+
+    /// The speed over the last `window` seconds.
+    pub fn live_wpm(&mut self, now: std::time::Instant, window: f32) -> f64 {
+        let window = std::time::Duration::try_from_secs_f32(window).unwrap_or_default();
+        while self
+            .recent
+            .front()
+            .is_some_and(|typed| now.saturating_duration_since(*typed) > window)
+        {
+            self.recent.pop_front();
+        }
+        words_per_minute(to_float(self.recent.len()), window)
+    }
+
+    /// The speed over all the time spent typing.
+    pub fn average_wpm(&self) -> f64 {
+        words_per_minute(to_float(self.characters), self.typing)
+    }
+
+    /// The statistics, for the remote control API.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "keystrokes": self.keystrokes,
+            "characters": self.characters,
+            "typing_seconds": self.typing.as_secs_f64(),
+            "average_wpm": self.average_wpm(),
+            "peak_wpm": self.peak_wpm,
+        })
+    }
+}
+
+/// Whether a key press types a character, rather than being a shortcut or moving the cursor.
+fn is_character(key: &termwiz::input::KeyEvent) -> bool {
+    let is_plain = key.modifiers.is_empty() || key.modifiers == termwiz::input::Modifiers::SHIFT;
+    matches!(key.key, termwiz::input::KeyCode::Char(character) if !character.is_control())
+        && is_plain
+}
+
+/// `TypingSpeed`
+pub(crate) struct TypingSpeed {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The text that's currently shown, so that it's only rendered when it changes.
+    text: String,
+    /// Whether something has changed that needs rendering.
+    is_dirty: bool,
+}
+
+impl TypingSpeed {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.typing_speed.opacity;
+        let tattoy = super::tattoyer::Tattoyer::new(
+            "typing_speed".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        Self {
+            tattoy,
+            text: String::new(),
+            is_dirty: true,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut typing_speed = Self::new(output, std::sync::Arc::clone(&state)).await;
+        let mut updates = tokio::time::interval(UPDATE_INTERVAL);
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                _ = updates.tick() => {
+                    typing_speed.is_dirty = true;
+                },
+                () = typing_speed.tattoy.sleep_until_next_frame_tick() => {
+                    typing_speed.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    typing_speed.handle_protocol_message(&message).await;
+                    typing_speed.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle messages from the main Tattoy app.
+    async fn handle_protocol_message(&mut self, message: &crate::run::Protocol) {
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We only care about a few messages"
+        )]
+        match message {
+            crate::run::Protocol::Input(crate::raw_input::ParsedInput {
+                event: termwiz::input::InputEvent::Key(key),
+                ..
+            }) => {
+                let window = self.tattoy.state.config.read().await.typing_speed.window;
+                self.tattoy.state.typing_speed.write().await.keystroke(
+                    is_character(key),
+                    std::time::Instant::now(),
+                    window,
+                );
+                self.is_dirty = true;
+            }
+            crate::run::Protocol::Config(_) | crate::run::Protocol::Resize { .. } => {
+                self.text.clear();
+                self.is_dirty = true;
+            }
+            _ => (),
+        }
+    }
+
+    /// Render if anything has changed.
+    async fn tick(&mut self) -> Result<()> {
+        if !self.is_dirty {
+            return Ok(());
+        }
+        self.is_dirty = false;
+
+        let config = self.tattoy.state.config.read().await.typing_speed.clone();
+        let mut stats = self.tattoy.state.typing_speed.write().await;
+        let text = format!(
+            " {:.0} wpm · {} keys ",
+            stats.live_wpm(std::time::Instant::now(), config.window),
+            stats.keystrokes
+        );
+        drop(stats);
+        if text == self.text {
+            return Ok(());
+        }
+        self.text.clone_from(&text);
+        self.render(&config, text).await
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config, text: String) -> Result<()> {
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+        let (x, y) = config.position.place(
+            self.tattoy.width.into(),
+            self.tattoy.height.into(),
+            self.tattoy.text_width(&text),
+        );
+        self.tattoy
+            .surface
+            .add_text(x, y, text, None, Some(config.colour));
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn speeds_are_in_standard_words() {
+        let start = std::time::Instant::now();
+        let mut stats = Stats::default();
+        for tenth in 0..50 {
+            let now = start + std::time::Duration::from_millis(100) * tenth;
+            stats.keystroke(true, now, 60.0);
+        }
+        stats.keystroke(false, start + std::time::Duration::from_secs(5), 60.0);
+
+        assert_eq!(stats.keystrokes, 51);
+        assert_eq!(stats.characters, 50);
+        assert!((stats.average_wpm() - 120.0).abs() < 0.01);
+        let later = start + std::time::Duration::from_secs(5);
+        assert!((stats.live_wpm(later, 60.0) - 10.0).abs() < 0.01);
+        assert!(stats.live_wpm(later + std::time::Duration::from_secs(61), 60.0) < 0.01);
+    }
+
+    #[test]
+    fn only_plain_keys_are_characters() {
+        let key = |key, modifiers| termwiz::input::KeyEvent { key, modifiers };
+        let none = termwiz::input::Modifiers::NONE;
+        assert!(is_character(&key(termwiz::input::KeyCode::Char('a'), none)));
+        assert!(is_character(&key(
+            termwiz::input::KeyCode::Char('A'),
+            termwiz::input::Modifiers::SHIFT
+        )));
+        assert!(!is_character(&key(
+            termwiz::input::KeyCode::Char('c'),
+            termwiz::input::Modifiers::CTRL
+        )));
+        assert!(!is_character(&key(termwiz::input::KeyCode::Enter, none)));
+    }
+}
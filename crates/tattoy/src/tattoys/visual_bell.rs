@@ -0,0 +1,306 @@
+//! A visual bell. The edges of the terminal flash, or a bell icon is shown in a corner, whenever
+//! the PTY's command prints a BEL character or one of the user's chosen output events happens. Every ring is also broadcast as
+//! `Protocol::Bell`, so that shaders can pulse using the `iTimeBell` uniform.
+//!
+//! Rings are rate limited so that a burst of events doesn't strobe the screen.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the bell. It needs to be seen over the terminal's text.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(15);
+
+/// The number of pixels in each row of the terminal.
+const PIXELS_PER_LINE: usize = 2;
+
+/// The icon for the `icon` style.
+const ICON: &str = " 🔔 ";
+
+/// The number of cells the icon takes up, the bell emoji is double width.
+const ICON_WIDTH: usize = 4;
+
+/// How the bell is shown.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Style {
+    /// Flash the edges of the terminal.
+    #[default]
+    Flash,
+    /// Show a bell icon in a corner of the terminal.
+    Icon,
+    /// Don't draw anything, only pulse the shaders.
+    Shader,
+}
+
+/// User-configurable settings for the visual bell.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the visual bell.
+    pub enabled: bool,
+    /// Ring the bell when the PTY's command prints a BEL character, like with `printf '\a'`.
+    pub on_bel: bool,
+    /// How the bell is shown.
+    pub style: Style,
+    /// The names of the output events (see `[[events]]`) that ring the bell.
+    pub events: Vec<String>,
+    /// The colour of the flash and the icon's background.
+    pub colour: crate::surface::Colour,
+    /// How thick, in pixels, the flashing edges are.
+    pub thickness: usize,
+    /// How long, in seconds, the bell is shown for.
+    pub duration: f32,
+    /// The shortest time, in seconds, between rings. Rings that come sooner are ignored.
+    pub minimum_interval: f32,
+    /// Which corner the icon is shown in.
+    pub position: crate::utils::Corner,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            on_bel: true,
+            style: Style::default(),
+            events: vec!["build_failed".to_owned()],
+            colour: (1.0, 0.8, 0.2, 0.8),
+            thickness: 2,
+            duration: 0.3,
+            minimum_interval: 1.0,
+            position: crate::utils::Corner::TopRight,
+        }
+    }
+}
+
+/// Keeps track of rings so that they can be rate limited.
+#[derive(Debug, Default)]
+struct Ringer {
+    /// When the bell last rang.
+    rang_at: Option<tokio::time::Instant>,
+}
+
+impl Ringer {
+    /// Ring the bell, unless it rang too recently. Returns whether it rang.
+    fn ring(&mut self, config: &Config, now: tokio::time::Instant) -> bool {
+        let is_too_soon = self.rang_at.is_some_and(|rang_at| {
+            now.saturating_duration_since(rang_at).as_secs_f32() < config.minimum_interval
+        });
+        if is_too_soon {
+            return false;
+        }
+
+        self.rang_at = Some(now);
+        true
+    }
+
+    /// How strongly the bell should be shown, from `1.0` just after it rang, fading to `0.0`.
+    /// `None` once it's finished.
+    fn strength(&self, config: &Config, now: tokio::time::Instant) -> Option<f32> {
+        let age = now.saturating_duration_since(self.rang_at?).as_secs_f32();
+        let duration = config.duration.max(0.01);
+        (age < duration).then(|| 1.0 - age / duration)
+    }
+}
+
+/// `VisualBell`
+pub(crate) struct VisualBell {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The rate limited rings.
+    ringer: Ringer,
+    /// Whether the bell was showing on the last frame.
+    is_showing: bool,
+}
+
+impl VisualBell {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "visual_bell".to_owned(),
+            state,
+            LAYER,
+            1.0,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            ringer: Ringer::default(),
+            is_showing: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut bell = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = bell.tattoy.sleep_until_next_frame_tick() => {
+                    bell.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if let crate::run::Protocol::OutputEvent(name) = &message {
+                        bell.handle_output_event(name).await?;
+                    }
+                    if matches!(message, crate::run::Protocol::BellCharacter) {
+                        bell.handle_bell_character().await?;
+                    }
+                    bell.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ring the bell if the event is one of the user's chosen ones.
+    async fn handle_output_event(&mut self, name: &str) -> Result<()> {
+        let config = self.tattoy.state.config.read().await.visual_bell.clone();
+        if !config.events.iter().any(|event| event == name) {
+            return Ok(());
+        }
+
+        tracing::debug!("Ringing the visual bell for '{name}'");
+        self.ring(&config)
+    }
+
+    /// Ring the bell for a BEL character, if the user wants that.
+    async fn handle_bell_character(&mut self) -> Result<()> {
+        let config = self.tattoy.state.config.read().await.visual_bell.clone();
+        if !config.on_bel {
+            return Ok(());
+        }
+
+        tracing::debug!("Ringing the visual bell for a BEL character");
+        self.ring(&config)
+    }
+
+    /// Ring the bell, unless it rang too recently.
+    fn ring(&mut self, config: &Config) -> Result<()> {
+        if !self.ringer.ring(config, tokio::time::Instant::now()) {
+            return Ok(());
+        }
+
+        self.tattoy
+            .state
+            .protocol_tx
+            .send(crate::run::Protocol::Bell)?;
+        Ok(())
+    }
+
+    /// Render the bell whilst it's ringing.
+    async fn tick(&mut self) -> Result<()> {
+        let mut config = self.tattoy.state.config.read().await.visual_bell.clone();
+        // Flashing the edges of the whole terminal is too much motion.
+        if config.style == Style::Flash && self.tattoy.state.is_reduced_motion().await {
+            config.style = Style::Icon;
+        }
+        let strength = self
+            .ringer
+            .strength(&config, tokio::time::Instant::now())
+            .filter(|_| config.style != Style::Shader);
+
+        let Some(strength) = strength else {
+            if self.is_showing {
+                self.is_showing = false;
+                self.tattoy.send_blank_output().await?;
+            }
+            return Ok(());
+        };
+
+        self.is_showing = true;
+        self.render(&config, strength).await
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config, strength: f32) -> Result<()> {
+        self.tattoy.initialise_surface();
+        let mut colour = config.colour;
+        colour.3 *= strength;
+
+        match config.style {
+            Style::Flash => self.render_edges(config.thickness, colour)?,
+            Style::Icon => {
+                let (x, y) = config.position.place(
+                    self.tattoy.width.into(),
+                    self.tattoy.height.into(),
+                    ICON_WIDTH,
+                );
+                self.tattoy
+                    .surface
+                    .add_text(x, y, ICON.to_owned(), Some(colour), None);
+            }
+            Style::Shader => (),
+        }
+
+        self.tattoy.send_output().await
+    }
+
+    /// Colour the pixels around the edges of the terminal.
+    fn render_edges(&mut self, thickness: usize, colour: crate::surface::Colour) -> Result<()> {
+        let width = usize::from(self.tattoy.width);
+        let height = usize::from(self.tattoy.height) * PIXELS_PER_LINE;
+        for y in 0..height {
+            for x in 0..width {
+                let is_edge = x < thickness
+                    || y < thickness
+                    || x >= width.saturating_sub(thickness)
+                    || y >= height.saturating_sub(thickness);
+                if is_edge {
+                    self.tattoy.surface.add_pixel(x, y, colour)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rings_are_rate_limited() {
+        let config = Config::default();
+        let mut ringer = Ringer::default();
+        let start = tokio::time::Instant::now();
+
+        assert!(ringer.ring(&config, start));
+        assert!(!ringer.ring(&config, start + std::time::Duration::from_millis(500)));
+        assert!(ringer.ring(&config, start + std::time::Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn rings_fade_out() {
+        let config = Config::default();
+        let mut ringer = Ringer::default();
+        let start = tokio::time::Instant::now();
+        assert!(ringer.strength(&config, start).is_none());
+
+        ringer.ring(&config, start);
+        assert_eq!(ringer.strength(&config, start), Some(1.0));
+        let fading = ringer
+            .strength(&config, start + std::time::Duration::from_millis(150))
+            .unwrap();
+        assert!(fading > 0.4 && fading < 0.6);
+        assert!(ringer
+            .strength(&config, start + std::time::Duration::from_millis(300))
+            .is_none());
+    }
+}
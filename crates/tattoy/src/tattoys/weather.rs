@@ -0,0 +1,500 @@
+//! Ambient weather: falling snow, rain that splashes on the bottom of the terminal, or drifting
+//! leaves. Snow and leaves can optionally settle on top of the terminal's text.
+
+use color_eyre::eyre::Result;
+use rand::Rng as _;
+
+/// The layer of the weather. It's beneath the text so that particles never hide any characters.
+const LAYER: i16 = crate::layers::Group::Background.layer(1);
+
+/// The number of pixels in each row of the terminal.
+const PIXELS_PER_LINE: u16 = 2;
+
+/// The number of frames that a raindrop's splash lasts for.
+const SPLASH_LIFETIME: u8 = 4;
+
+/// The kinds of weather.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Kind {
+    /// Slowly drifting snowflakes.
+    #[default]
+    Snow,
+    /// Fast raindrops that splash.
+    Rain,
+    /// Swaying autumn leaves.
+    Leaves,
+}
+
+impl Kind {
+    /// The fastest that a particle can fall, in pixels per frame.
+    const fn terminal_velocity(self) -> f32 {
+        match self {
+            Self::Snow => 0.3,
+            Self::Rain => 1.5,
+            Self::Leaves => 0.4,
+        }
+    }
+
+    /// How much a particle sways from side to side.
+    const fn sway(self) -> f32 {
+        match self {
+            Self::Snow => 0.15,
+            Self::Rain => 0.0,
+            Self::Leaves => 0.4,
+        }
+    }
+
+    /// Whether the particles pile up when they land.
+    const fn is_settling(self) -> bool {
+        matches!(self, Self::Snow | Self::Leaves)
+    }
+
+    /// The colour of a new particle.
+    fn colour(self) -> crate::surface::Colour {
+        match self {
+            Self::Snow => (0.95, 0.95, 1.0, 0.9),
+            Self::Rain => (0.5, 0.6, 1.0, 0.8),
+            Self::Leaves => {
+                let leaves = [
+                    (0.8, 0.35, 0.1, 1.0),
+                    (0.9, 0.6, 0.1, 1.0),
+                    (0.6, 0.2, 0.1, 1.0),
+                    (0.7, 0.5, 0.2, 1.0),
+                ];
+                let choice = rand::thread_rng().gen_range(0..leaves.len());
+                leaves.get(choice).copied().unwrap_or(crate::surface::WHITE)
+            }
+        }
+    }
+}
+
+/// User-configurable settings for the weather.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the weather.
+    pub enabled: bool,
+    /// Which kind of weather.
+    pub kind: Kind,
+    /// The opacity of the particles.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// How many particles there are, from `0.0` to `1.0`.
+    pub density: f32,
+    /// The horizontal speed of the particles, in pixels per frame. Negative values blow to the
+    /// left.
+    pub wind: f32,
+    /// How quickly particles speed up as they fall, in pixels per frame per frame.
+    pub gravity: f32,
+    /// Whether snow and leaves pile up on top of the terminal's text.
+    pub accumulate: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            kind: Kind::default(),
+            opacity: 1.0,
+            density: 0.3,
+            wind: 0.05,
+            gravity: 0.02,
+            accumulate: true,
+        }
+    }
+}
+
+/// A single snowflake, raindrop, splash or leaf.
+#[derive(Debug, Clone)]
+struct Particle {
+    /// Horizontal position, in pixels.
+    x: f32,
+    /// Vertical position, in pixels.
+    y: f32,
+    /// Vertical speed.
+    velocity: f32,
+    /// Extra horizontal speed, only used by splashes.
+    drift: f32,
+    /// Where the particle is in its sway.
+    phase: f32,
+    /// The number of frames left before the particle disappears, only used by splashes.
+    lifetime: Option<u8>,
+    /// The colour of the particle.
+    colour: crate::surface::Colour,
+}
+
+/// A pixel coordinate.
+type Pixel = (usize, usize);
+
+/// The simulation of all the particles. It's kept separate from the tattoy so that it can be
+/// tested without a running Tattoy.
+#[derive(Default)]
+struct Sky {
+    /// Width in pixels.
+    width: u16,
+    /// Height in pixels.
+    height: u16,
+    /// All the particles still in the air.
+    particles: Vec<Particle>,
+    /// All the particles that have landed.
+    settled: std::collections::BTreeMap<Pixel, crate::surface::Colour>,
+}
+
+impl Sky {
+    /// Start a new, empty sky for a terminal of the given size.
+    fn new(columns: u16, rows: u16) -> Self {
+        Self {
+            width: columns,
+            height: rows.saturating_mul(PIXELS_PER_LINE),
+            ..Default::default()
+        }
+    }
+
+    /// The most settled particles that are allowed. Stops piles from filling the whole terminal.
+    fn maximum_settled(&self) -> usize {
+        usize::from(self.width) * 4
+    }
+
+    /// Convert a particle's position to a pixel, if it's within the sky.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "Negative values are checked for and f32s are only ever small positions"
+    )]
+    fn to_pixel(&self, x: f32, y: f32) -> Option<Pixel> {
+        if x < 0.0 || y < 0.0 || x >= f32::from(self.width) || y >= f32::from(self.height) {
+            return None;
+        }
+        Some((x.floor() as usize, y.floor() as usize))
+    }
+
+    /// Is there something at the pixel that a particle can land on?
+    fn is_solid(&self, pixel: Pixel, is_text: &impl Fn(Pixel) -> bool) -> bool {
+        pixel.1 >= usize::from(self.height) || self.settled.contains_key(&pixel) || is_text(pixel)
+    }
+
+    /// Add new particles along the top of the sky.
+    fn spawn(&mut self, config: &Config) {
+        let chance_per_column = f64::from(config.density.clamp(0.0, 1.0)) * 0.02;
+        if chance_per_column <= 0.0 {
+            return;
+        }
+
+        for column in 0..self.width {
+            if !rand::thread_rng().gen_bool(chance_per_column) {
+                continue;
+            }
+            self.particles.push(Particle {
+                x: f32::from(column) + rand::thread_rng().gen_range(0.0..1.0),
+                y: 0.0,
+                velocity: 0.0,
+                drift: 0.0,
+                phase: rand::thread_rng().gen_range(0.0..core::f32::consts::TAU),
+                lifetime: None,
+                colour: config.kind.colour(),
+            });
+        }
+    }
+
+    /// Move every particle on by one frame.
+    fn step(&mut self, config: &Config, is_text: &impl Fn(Pixel) -> bool) {
+        let mut splashes = Vec::new();
+        let mut remaining = Vec::with_capacity(self.particles.len());
+        let particles = core::mem::take(&mut self.particles);
+
+        for mut particle in particles {
+            let is_splash = particle.lifetime.is_some();
+            if let Some(lifetime) = particle.lifetime {
+                if lifetime == 0 {
+                    continue;
+                }
+                particle.lifetime = Some(lifetime.saturating_sub(1));
+            }
+
+            let terminal_velocity = if is_splash {
+                f32::INFINITY
+            } else {
+                config.kind.terminal_velocity()
+            };
+            particle.velocity = (particle.velocity + config.gravity).min(terminal_velocity);
+            particle.phase += 0.1;
+            let next_x = config.kind.sway().mul_add(
+                particle.phase.sin() * 0.1,
+                particle.x + config.wind + particle.drift,
+            );
+            let next_y = particle.y + particle.velocity;
+
+            let Some(current) = self.to_pixel(particle.x, particle.y) else {
+                if particle.y < 0.0 && !is_splash {
+                    particle.x = next_x;
+                    particle.y = next_y;
+                    remaining.push(particle);
+                }
+                continue;
+            };
+
+            let below = self.next_solid_pixel(current, next_y, is_text);
+            if let Some(landing) = below {
+                if is_splash {
+                    continue;
+                }
+                if config.kind == Kind::Rain {
+                    splashes.extend(Self::splash(landing, particle.colour));
+                }
+                if config.kind.is_settling()
+                    && self.settled.len() < self.maximum_settled()
+                    && (config.accumulate || landing.1 + 1 >= usize::from(self.height))
+                {
+                    self.settled.insert(landing, particle.colour);
+                }
+                continue;
+            }
+
+            particle.x = next_x;
+            particle.y = next_y;
+            if self.to_pixel(particle.x, particle.y).is_some() || particle.y < 0.0 {
+                remaining.push(particle);
+            }
+        }
+
+        remaining.extend(splashes);
+        self.particles = remaining;
+    }
+
+    /// Check every pixel between a particle's current and next positions. If there's a solid
+    /// pixel then return the pixel just above it, which is where the particle lands.
+    fn next_solid_pixel(
+        &self,
+        current: Pixel,
+        next_y: f32,
+        is_text: &impl Fn(Pixel) -> bool,
+    ) -> Option<Pixel> {
+        let (x, y) = current;
+        let next_row = self
+            .to_pixel(0.0, next_y)
+            .map_or(usize::from(self.height), |pixel| pixel.1);
+
+        for row in (y + 1)..=next_row {
+            if self.is_solid((x, row), is_text) {
+                return Some((x, row - 1));
+            }
+        }
+
+        None
+    }
+
+    /// A raindrop's splash: 2 droplets that bounce up and outwards.
+    #[expect(
+        clippy::as_conversions,
+        clippy::cast_precision_loss,
+        reason = "Pixel coordinates are always small enough to fit in an f32"
+    )]
+    fn splash(pixel: Pixel, colour: crate::surface::Colour) -> [Particle; 2] {
+        let droplet = |drift: f32| Particle {
+            x: pixel.0 as f32 + 0.5,
+            y: pixel.1 as f32,
+            velocity: -0.6,
+            drift,
+            phase: 0.0,
+            lifetime: Some(SPLASH_LIFETIME),
+            colour,
+        };
+        [droplet(-0.4), droplet(0.4)]
+    }
+
+    /// Remove settled particles that are no longer resting on anything, eg: when the text
+    /// underneath them has scrolled away.
+    fn unsettle(&mut self, is_text: &impl Fn(Pixel) -> bool) {
+        let mut is_changed = true;
+        while is_changed {
+            let unsupported: Vec<Pixel> = self
+                .settled
+                .keys()
+                .filter(|pixel| {
+                    let below = (pixel.0, pixel.1 + 1);
+                    !self.is_solid(below, is_text) || is_text(**pixel)
+                })
+                .copied()
+                .collect();
+            is_changed = !unsupported.is_empty();
+            for pixel in unsupported {
+                self.settled.remove(&pixel);
+            }
+        }
+    }
+}
+
+/// `Weather`
+pub(crate) struct Weather {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The particle simulation.
+    sky: Sky,
+}
+
+impl Weather {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.weather.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "weather".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_below_text();
+        let sky = Sky::new(tattoy.width, tattoy.height);
+        Self { tattoy, sky }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut weather = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = weather.tattoy.sleep_until_next_frame_tick() => {
+                    weather.render().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    weather.handle_protocol_message(&message);
+                    weather.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Custom behaviour for protocol messages.
+    fn handle_protocol_message(&mut self, message: &crate::run::Protocol) {
+        #[expect(
+            clippy::single_match,
+            clippy::wildcard_enum_match_arm,
+            reason = "We're ready to add handlers for other messages"
+        )]
+        match message {
+            crate::run::Protocol::Resize { width, height } => {
+                self.sky = Sky::new(*width, *height);
+            }
+            _ => (),
+        }
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        let config = self.tattoy.state.config.read().await.weather.clone();
+        self.tattoy.opacity = config.opacity;
+
+        let cells = self.tattoy.screen.surface.get_screen_cells();
+        let is_text = |pixel: Pixel| config.accumulate && is_text_at(&cells, pixel);
+        self.sky.unsettle(&is_text);
+        self.sky.spawn(&config);
+        self.sky.step(&config, &is_text);
+        drop(cells);
+
+        self.tattoy.initialise_surface();
+        for (pixel, colour) in &self.sky.settled {
+            self.tattoy.surface.add_pixel(pixel.0, pixel.1, *colour)?;
+        }
+        for particle in &self.sky.particles {
+            self.tattoy
+                .add_point(particle.x, particle.y, particle.colour);
+        }
+
+        self.tattoy.send_output().await
+    }
+}
+
+/// Is the pixel covered by the terminal's text?
+fn is_text_at(cells: &[&[shadow_terminal::termwiz::cell::Cell]], pixel: Pixel) -> bool {
+    cells
+        .get(pixel.1.div_euclid(PIXELS_PER_LINE.into()))
+        .and_then(|line| line.get(pixel.0))
+        .is_some_and(super::gpu::text_mask::is_text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn particle_at(x: f32, y: f32) -> Particle {
+        Particle {
+            x,
+            y,
+            velocity: 1.0,
+            drift: 0.0,
+            phase: 0.0,
+            lifetime: None,
+            colour: crate::surface::WHITE,
+        }
+    }
+
+    fn calm_config(kind: Kind) -> Config {
+        Config {
+            kind,
+            density: 0.0,
+            wind: 0.0,
+            gravity: 0.0,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn snow_settles_on_text() {
+        let mut sky = Sky::new(3, 3);
+        sky.particles.push(particle_at(1.5, 0.0));
+        let is_text = |pixel: Pixel| pixel.1 >= 4;
+        let config = calm_config(Kind::Snow);
+        for _ in 0..30 {
+            sky.step(&config, &is_text);
+        }
+
+        assert!(sky.particles.is_empty());
+        assert!(sky.settled.contains_key(&(1, 3)));
+    }
+
+    #[test]
+    fn settled_snow_falls_when_text_goes() {
+        let mut sky = Sky::new(3, 3);
+        sky.settled.insert((1, 3), crate::surface::WHITE);
+        sky.unsettle(&|pixel: Pixel| pixel.1 >= 4);
+        assert!(sky.settled.contains_key(&(1, 3)));
+
+        sky.unsettle(&|_| false);
+        assert!(sky.settled.is_empty());
+    }
+
+    #[test]
+    fn rain_splashes_instead_of_settling() {
+        let mut sky = Sky::new(3, 3);
+        sky.particles.push(particle_at(1.5, 5.0));
+        let config = calm_config(Kind::Rain);
+        sky.step(&config, &|_| false);
+
+        assert!(sky.settled.is_empty());
+        assert_eq!(sky.particles.len(), 2);
+        assert!(sky
+            .particles
+            .iter()
+            .all(|particle| particle.lifetime.is_some()));
+    }
+}
@@ -0,0 +1,433 @@
+//! A small corner badge showing the current weather, like "☀ 21°C".
+//!
+//! The weather comes from an online provider, either Open-Meteo or wttr.in. Other data sources
+//! can be added by implementing the `Provider` trait. Network failures are only logged, the badge
+//! just keeps showing the last known weather, or nothing at all.
+
+use color_eyre::eyre::{ContextCompat as _, Result};
+
+/// The layer of the widget. It needs to be readable over the terminal's text.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(5);
+
+/// The online weather services that Tattoy knows about.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ProviderKind {
+    /// <https://open-meteo.com>, needs a latitude and longitude.
+    OpenMeteo,
+    /// <https://wttr.in>, can guess your location from your IP address.
+    #[default]
+    Wttr,
+}
+
+/// Units for the temperature.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Units {
+    /// Degrees Celsius.
+    #[default]
+    Celsius,
+    /// Degrees Fahrenheit.
+    Fahrenheit,
+}
+
+/// User-configurable settings for the weather widget.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the widget.
+    pub enabled: bool,
+    /// The opacity of the widget.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// Where the weather comes from.
+    pub provider: ProviderKind,
+    /// The location for wttr.in, like a city name. When empty wttr.in guesses the location.
+    pub location: String,
+    /// The latitude for Open-Meteo.
+    pub latitude: Option<f32>,
+    /// The longitude for Open-Meteo.
+    pub longitude: Option<f32>,
+    /// Units for the temperature.
+    pub units: Units,
+    /// How often the weather is fetched, in minutes.
+    pub update_interval: f32,
+    /// Which corner the widget is shown in.
+    pub position: crate::utils::Corner,
+    /// The colour of the widget's text.
+    pub foreground: crate::surface::Colour,
+    /// The colour of the widget's background.
+    pub background: crate::surface::Colour,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 1.0,
+            provider: ProviderKind::default(),
+            location: String::new(),
+            latitude: None,
+            longitude: None,
+            units: Units::default(),
+            update_interval: 30.0,
+            position: crate::utils::Corner::TopLeft,
+            foreground: crate::surface::WHITE,
+            background: (0.1, 0.1, 0.1, 0.8),
+        }
+    }
+}
+
+/// A rough description of the weather.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Condition {
+    /// Clear skies.
+    Clear,
+    /// Some clouds.
+    PartlyCloudy,
+    /// Lots of clouds.
+    Cloudy,
+    /// Fog or mist.
+    Fog,
+    /// Any kind of rain.
+    Rain,
+    /// Any kind of snow.
+    Snow,
+    /// Thunderstorms.
+    Storm,
+    /// The provider's description isn't one that we know about.
+    Unknown,
+}
+
+impl Condition {
+    /// A single-width icon for the condition.
+    const fn icon(self) -> &'static str {
+        match self {
+            Self::Clear => "☀",
+            Self::PartlyCloudy | Self::Cloudy => "☁",
+            Self::Fog => "≡",
+            Self::Rain => "☂",
+            Self::Snow => "❄",
+            Self::Storm => "☈",
+            Self::Unknown => "?",
+        }
+    }
+}
+
+/// The current weather.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Conditions {
+    /// The temperature in degrees Celsius.
+    pub temperature: f32,
+    /// A rough description of the weather.
+    pub condition: Condition,
+}
+
+impl Conditions {
+    /// The text of the badge.
+    fn badge(&self, units: Units) -> String {
+        let (temperature, symbol) = match units {
+            Units::Celsius => (self.temperature, "C"),
+            Units::Fahrenheit => (self.temperature.mul_add(1.8, 32.0), "F"),
+        };
+        format!(" {} {temperature:.0}°{symbol} ", self.condition.icon())
+    }
+}
+
+/// A source of weather data.
+pub(crate) trait Provider: Send + Sync {
+    /// The URL to fetch the current weather from.
+    fn url(&self) -> String;
+
+    /// Parse the provider's response.
+    fn parse(&self, body: &str) -> Result<Conditions>;
+}
+
+/// <https://open-meteo.com>
+pub(crate) struct OpenMeteo {
+    /// The latitude of the location.
+    latitude: f32,
+    /// The longitude of the location.
+    longitude: f32,
+}
+
+impl OpenMeteo {
+    /// Convert a WMO weather code.
+    const fn condition(code: i64) -> Condition {
+        match code {
+            0 => Condition::Clear,
+            1 | 2 => Condition::PartlyCloudy,
+            3 => Condition::Cloudy,
+            45 | 48 => Condition::Fog,
+            51..=67 | 80..=82 => Condition::Rain,
+            71..=77 | 85 | 86 => Condition::Snow,
+            95..=99 => Condition::Storm,
+            _ => Condition::Unknown,
+        }
+    }
+}
+
+impl Provider for OpenMeteo {
+    fn url(&self) -> String {
+        format!(
+            "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current=temperature_2m,weather_code",
+            self.latitude, self.longitude
+        )
+    }
+
+    fn parse(&self, body: &str) -> Result<Conditions> {
+        let json: serde_json::Value = serde_json::from_str(body)?;
+        let current = json.get("current").context("No current weather")?;
+        #[expect(
+            clippy::as_conversions,
+            clippy::cast_possible_truncation,
+            reason = "Temperatures are small"
+        )]
+        let temperature = current
+            .get("temperature_2m")
+            .and_then(serde_json::Value::as_f64)
+            .context("No temperature")? as f32;
+        let code = current
+            .get("weather_code")
+            .and_then(serde_json::Value::as_i64)
+            .context("No weather code")?;
+
+        Ok(Conditions {
+            temperature,
+            condition: Self::condition(code),
+        })
+    }
+}
+
+/// <https://wttr.in>
+pub(crate) struct Wttr {
+    /// The location, like a city name. Can be empty.
+    location: String,
+}
+
+impl Wttr {
+    /// Convert a World Weather Online weather code.
+    const fn condition(code: i64) -> Condition {
+        match code {
+            113 => Condition::Clear,
+            116 => Condition::PartlyCloudy,
+            119 | 122 => Condition::Cloudy,
+            143 | 248 | 260 => Condition::Fog,
+            200 | 386 | 389 | 392 | 395 => Condition::Storm,
+            179 | 227 | 230 | 320 | 323 | 326 | 329 | 332 | 335 | 338 | 350 | 362 | 365 | 368
+            | 371 | 374 | 377 => Condition::Snow,
+            176..=377 => Condition::Rain,
+            _ => Condition::Unknown,
+        }
+    }
+}
+
+impl Provider for Wttr {
+    fn url(&self) -> String {
+        format!(
+            "https://wttr.in/{}?format=j1",
+            self.location.replace(' ', "+")
+        )
+    }
+
+    fn parse(&self, body: &str) -> Result<Conditions> {
+        let json: serde_json::Value = serde_json::from_str(body)?;
+        let current = json
+            .get("current_condition")
+            .and_then(|conditions| conditions.get(0))
+            .context("No current weather")?;
+        let field = |name: &str| -> Result<&str> {
+            current
+                .get(name)
+                .and_then(serde_json::Value::as_str)
+                .with_context(|| format!("No {name}"))
+        };
+
+        Ok(Conditions {
+            temperature: field("temp_C")?.parse()?,
+            condition: Self::condition(field("weatherCode")?.parse()?),
+        })
+    }
+}
+
+/// Make the provider that the user has configured.
+pub(crate) fn provider(config: &Config) -> Option<Box<dyn Provider>> {
+    match config.provider {
+        ProviderKind::OpenMeteo => {
+            let (Some(latitude), Some(longitude)) = (config.latitude, config.longitude) else {
+                tracing::warn!("The Open-Meteo weather provider needs a latitude and longitude");
+                return None;
+            };
+            Some(Box::new(OpenMeteo {
+                latitude,
+                longitude,
+            }))
+        }
+        ProviderKind::Wttr => Some(Box::new(Wttr {
+            location: config.location.clone(),
+        })),
+    }
+}
+
+/// Fetch the current weather from the configured provider.
+async fn fetch(config: Config) -> Result<Conditions> {
+    let provider = provider(&config).context("No weather provider")?;
+    let body = crate::utils::curl(&[], &provider.url()).await?;
+    provider.parse(&body)
+}
+
+/// `WeatherWidget`
+pub(crate) struct WeatherWidget {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The last known weather.
+    conditions: Option<Conditions>,
+    /// Whether something has changed that needs rendering.
+    is_dirty: bool,
+}
+
+impl WeatherWidget {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.weather_widget.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "weather_widget".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            conditions: None,
+            is_dirty: false,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut widget = Self::new(output, std::sync::Arc::clone(&state)).await;
+        let (fetched_tx, mut fetched_rx) = tokio::sync::mpsc::channel(1);
+        let mut next_fetch = tokio::time::Instant::now();
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = widget.tattoy.sleep_until_next_frame_tick() => {
+                    if widget.is_dirty {
+                        widget.render().await?;
+                    }
+                },
+                () = tokio::time::sleep_until(next_fetch) => {
+                    let config = state.config.read().await.weather_widget.clone();
+                    let interval = config.update_interval.max(1.0) * 60.0;
+                    next_fetch = tokio::time::Instant::now()
+                        + std::time::Duration::try_from_secs_f32(interval).unwrap_or_default();
+                    let sender = fetched_tx.clone();
+                    tokio::spawn(async move {
+                        match fetch(config).await {
+                            Ok(conditions) => {
+                                if let Err(error) = sender.send(conditions).await {
+                                    tracing::debug!("Couldn't send new weather: {error:?}");
+                                }
+                            }
+                            Err(error) => tracing::debug!("Couldn't fetch weather: {error:?}"),
+                        }
+                    });
+                },
+                Some(conditions) = fetched_rx.recv() => {
+                    tracing::trace!("New weather: {conditions:?}");
+                    widget.is_dirty |= widget.conditions.as_ref() != Some(&conditions);
+                    widget.conditions = Some(conditions);
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if matches!(
+                        message,
+                        crate::run::Protocol::Resize { .. } | crate::run::Protocol::Config(_)
+                    ) {
+                        widget.is_dirty = true;
+                    }
+                    widget.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tick the render
+    async fn render(&mut self) -> Result<()> {
+        self.is_dirty = false;
+        let Some(conditions) = self.conditions.clone() else {
+            return Ok(());
+        };
+        let config = self.tattoy.state.config.read().await.weather_widget.clone();
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+
+        let text = conditions.badge(config.units);
+        let (x, y) = config.position.place(
+            self.tattoy.width.into(),
+            self.tattoy.height.into(),
+            self.tattoy.text_width(&text),
+        );
+        self.tattoy
+            .surface
+            .add_text(x, y, text, Some(config.background), Some(config.foreground));
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_open_meteo() {
+        let provider = OpenMeteo {
+            latitude: 51.5,
+            longitude: -0.1,
+        };
+        let body = r#"{"current": {"time": "2025-01-01T12:00", "temperature_2m": 7.4, "weather_code": 61}}"#;
+        let conditions = provider.parse(body).unwrap();
+        assert_eq!(conditions.condition, Condition::Rain);
+        assert_eq!(conditions.badge(Units::Celsius), " ☂ 7°C ");
+    }
+
+    #[test]
+    fn parses_wttr() {
+        let provider = Wttr {
+            location: "New York".to_owned(),
+        };
+        assert_eq!(provider.url(), "https://wttr.in/New+York?format=j1");
+        let body = r#"{"current_condition": [{"temp_C": "20", "weatherCode": "113"}]}"#;
+        let conditions = provider.parse(body).unwrap();
+        assert_eq!(conditions.condition, Condition::Clear);
+        assert_eq!(conditions.badge(Units::Fahrenheit), " ☀ 68°F ");
+    }
+
+    #[test]
+    fn bad_responses_are_errors() {
+        let provider = Wttr {
+            location: String::new(),
+        };
+        assert!(provider.parse("<html>Service unavailable</html>").is_err());
+        assert!(provider.parse(r#"{"current_condition": []}"#).is_err());
+    }
+}
@@ -0,0 +1,485 @@
+//! A small corner widget showing the time and some system stats: load average, RAM usage and
+//! battery level. A lighter alternative to running a whole separate status bar.
+//!
+//! The system stats are currently only available on Linux. Stats that can't be found are just
+//! left out of the widget.
+
+use color_eyre::eyre::Result;
+
+/// The layer of the widget. It needs to be readable over the terminal's text.
+const LAYER: i16 = crate::layers::Group::Overlay.layer(5);
+
+/// Abbreviated day names, starting from Sunday.
+const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// Full day names, starting from Sunday.
+const FULL_DAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// Abbreviated month names.
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Full month names.
+const FULL_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// The things that the widget can show.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Item {
+    /// The current time, using the `clock_format` setting.
+    Clock,
+    /// The 1 minute load average.
+    Load,
+    /// The percentage of RAM in use.
+    Ram,
+    /// The battery's charge.
+    Battery,
+}
+
+/// User-configurable settings for the widget.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Enable/disable the widget.
+    pub enabled: bool,
+    /// The opacity of the widget.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    /// What to show, in order.
+    pub items: Vec<Item>,
+    /// A `strftime`-style format for the clock.
+    pub clock_format: String,
+    /// The clock's offset from UTC, in minutes. When not set, the system's offset is used.
+    pub utc_offset_minutes: Option<i64>,
+    /// The text between each item.
+    pub separator: String,
+    /// How often the widget is updated, in seconds.
+    pub update_interval: f32,
+    /// Which corner the widget is shown in.
+    pub position: crate::utils::Corner,
+    /// The colour of the widget's text.
+    pub foreground: crate::surface::Colour,
+    /// The colour of the widget's background.
+    pub background: crate::surface::Colour,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 1.0,
+            items: vec![Item::Clock, Item::Load, Item::Ram, Item::Battery],
+            clock_format: "%H:%M".to_owned(),
+            utc_offset_minutes: None,
+            separator: " | ".to_owned(),
+            update_interval: 1.0,
+            position: crate::utils::Corner::BottomLeft,
+            foreground: crate::surface::WHITE,
+            background: (0.1, 0.1, 0.1, 0.8),
+        }
+    }
+}
+
+/// A calendar date and wall clock time.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct DateTime {
+    /// The year, eg: 2025.
+    year: i64,
+    /// The month, from 1 to 12.
+    month: i64,
+    /// The day of the month, from 1 to 31.
+    day: i64,
+    /// The hour, from 0 to 23.
+    hour: i64,
+    /// The minute, from 0 to 59.
+    minute: i64,
+    /// The second, from 0 to 59.
+    second: i64,
+    /// The day of the week, where 0 is Sunday.
+    weekday: i64,
+}
+
+impl DateTime {
+    /// Convert seconds since the Unix epoch. This is Howard Hinnant's `civil_from_days()`
+    /// algorithm.
+    pub(crate) fn from_unix(seconds: i64) -> Self {
+        let days = seconds.div_euclid(86400);
+        let seconds_of_day = seconds.rem_euclid(86400);
+
+        let shifted = days + 719_468;
+        let era = shifted.div_euclid(146_097);
+        let day_of_era = shifted.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era.div_euclid(1460) + day_of_era.div_euclid(36524)
+            - day_of_era.div_euclid(146_096))
+        .div_euclid(365);
+        let day_of_year = day_of_era
+            - (365 * year_of_era + year_of_era.div_euclid(4) - year_of_era.div_euclid(100));
+        let shifted_month = (5 * day_of_year + 2).div_euclid(153);
+        let day = day_of_year - (153 * shifted_month + 2).div_euclid(5) + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: seconds_of_day.div_euclid(3600),
+            minute: seconds_of_day.rem_euclid(3600).div_euclid(60),
+            second: seconds_of_day.rem_euclid(60),
+            // The epoch was a Thursday.
+            weekday: (days + 4).rem_euclid(7),
+        }
+    }
+
+    /// Format the time with `strftime`-style specifiers. Unknown specifiers are left as they are.
+    pub(crate) fn format(&self, format: &str) -> String {
+        let name = |names: &[&'static str], index: i64| -> &'static str {
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| names.get(index))
+                .copied()
+                .unwrap_or("?")
+        };
+        let hour_12 = match self.hour.rem_euclid(12) {
+            0 => 12,
+            hour => hour,
+        };
+
+        let mut output = String::new();
+        let mut characters = format.chars();
+        while let Some(character) = characters.next() {
+            if character != '%' {
+                output.push(character);
+                continue;
+            }
+
+            let Some(specifier) = characters.next() else {
+                output.push('%');
+                break;
+            };
+            let formatted = match specifier {
+                'Y' => self.year.to_string(),
+                'y' => format!("{:02}", self.year.rem_euclid(100)),
+                'm' => format!("{:02}", self.month),
+                'd' => format!("{:02}", self.day),
+                'e' => format!("{:>2}", self.day),
+                'H' => format!("{:02}", self.hour),
+                'I' => format!("{hour_12:02}"),
+                'M' => format!("{:02}", self.minute),
+                'S' => format!("{:02}", self.second),
+                'p' => if self.hour < 12 { "AM" } else { "PM" }.to_owned(),
+                'a' => name(&DAYS, self.weekday).to_owned(),
+                'A' => name(&FULL_DAYS, self.weekday).to_owned(),
+                'b' => name(&MONTHS, self.month - 1).to_owned(),
+                'B' => name(&FULL_MONTHS, self.month - 1).to_owned(),
+                '%' => "%".to_owned(),
+                unknown => format!("%{unknown}"),
+            };
+            output.push_str(&formatted);
+        }
+
+        output
+    }
+}
+
+/// Parse the output of `date +%z`, eg: "+0530", into minutes.
+fn parse_utc_offset(offset: &str) -> Option<i64> {
+    let offset = offset.trim();
+    let (sign, digits) = match offset.strip_prefix('-') {
+        Some(digits) => (-1, digits),
+        None => (1, offset.strip_prefix('+').unwrap_or(offset)),
+    };
+    if digits.len() != 4 {
+        return None;
+    }
+    let hours: i64 = digits.get(0..2)?.parse().ok()?;
+    let minutes: i64 = digits.get(2..4)?.parse().ok()?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+/// Ask the system for its offset from UTC. There's no way to do this in the standard library, so
+/// we ask `date`.
+pub(crate) async fn system_utc_offset() -> i64 {
+    let result = tokio::process::Command::new("date")
+        .arg("+%z")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await;
+    match result {
+        Ok(output) => {
+            parse_utc_offset(&String::from_utf8_lossy(&output.stdout)).unwrap_or_else(|| {
+                tracing::warn!("Couldn't parse UTC offset from `date`, using UTC");
+                0
+            })
+        }
+        Err(error) => {
+            tracing::warn!("Couldn't get UTC offset from `date`, using UTC: {error:?}");
+            0
+        }
+    }
+}
+
+/// The 1 minute load average, from the contents of `/proc/loadavg`.
+fn parse_load(loadavg: &str) -> Option<String> {
+    let load = loadavg.split_whitespace().next()?;
+    Some(format!("load {load}"))
+}
+
+/// The percentage of RAM in use, from the contents of `/proc/meminfo`.
+fn parse_ram(meminfo: &str) -> Option<String> {
+    let field = |name: &str| -> Option<f32> {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|rest| rest.trim_start_matches(':').split_whitespace().next())
+            .and_then(|kilobytes| kilobytes.parse().ok())
+    };
+    let total = field("MemTotal")?;
+    let available = field("MemAvailable")?;
+    if total <= 0.0 {
+        return None;
+    }
+    Some(format!("ram {:.0}%", (1.0 - available / total) * 100.0))
+}
+
+/// Read the charge of the first battery that the system knows about.
+async fn read_battery() -> Option<String> {
+    let mut entries = tokio::fs::read_dir("/sys/class/power_supply").await.ok()?;
+    let mut supplies = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        supplies.push(entry.path());
+    }
+    supplies.sort();
+
+    for supply in supplies {
+        let Ok(kind) = tokio::fs::read_to_string(supply.join("type")).await else {
+            continue;
+        };
+        if kind.trim() != "Battery" {
+            continue;
+        }
+        let capacity = tokio::fs::read_to_string(supply.join("capacity"))
+            .await
+            .ok()?;
+        let status = tokio::fs::read_to_string(supply.join("status"))
+            .await
+            .unwrap_or_default();
+        let charging = if status.trim() == "Charging" { "+" } else { "" };
+        return Some(format!("bat {}%{charging}", capacity.trim()));
+    }
+
+    None
+}
+
+/// `Widget`
+pub(crate) struct Widget {
+    /// The base Tattoy struct
+    tattoy: super::tattoyer::Tattoyer,
+    /// The system's offset from UTC, in minutes.
+    system_utc_offset: i64,
+    /// The text that was last rendered.
+    text: String,
+    /// When the widget was last updated.
+    last_update: Option<tokio::time::Instant>,
+}
+
+impl Widget {
+    /// Instantiate
+    async fn new(
+        output_channel: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Self {
+        let opacity = state.config.read().await.widget.opacity;
+        let mut tattoy = super::tattoyer::Tattoyer::new(
+            "widget".to_owned(),
+            state,
+            LAYER,
+            opacity,
+            output_channel,
+        )
+        .await;
+        tattoy.keep_above_text();
+        Self {
+            tattoy,
+            system_utc_offset: system_utc_offset().await,
+            text: String::new(),
+            last_update: None,
+        }
+    }
+
+    /// Our main entrypoint.
+    pub(crate) async fn start(
+        output: tokio::sync::mpsc::Sender<crate::run::FrameUpdate>,
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> Result<()> {
+        let mut protocol = state.protocol_tx.subscribe();
+        let mut widget = Self::new(output, std::sync::Arc::clone(&state)).await;
+
+        #[expect(
+            clippy::integer_division_remainder_used,
+            reason = "This is caused by the `tokio::select!`"
+        )]
+        loop {
+            tokio::select! {
+                () = widget.tattoy.sleep_until_next_frame_tick() => {
+                    widget.tick().await?;
+                },
+                Ok(message) = protocol.recv() => {
+                    if matches!(message, crate::run::Protocol::End) {
+                        break;
+                    }
+                    if matches!(
+                        message,
+                        crate::run::Protocol::Resize { .. } | crate::run::Protocol::Config(_)
+                    ) {
+                        widget.last_update = None;
+                    }
+                    widget.tattoy.handle_common_protocol_messages(message)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Update the widget if it's due.
+    async fn tick(&mut self) -> Result<()> {
+        let config = self.tattoy.state.config.read().await.widget.clone();
+        let interval = std::time::Duration::try_from_secs_f32(config.update_interval.max(0.1))
+            .unwrap_or_default();
+        let is_due = self
+            .last_update
+            .is_none_or(|last_update| last_update.elapsed() >= interval);
+        if !is_due {
+            return Ok(());
+        }
+
+        let is_first_update = self.last_update.is_none();
+        self.last_update = Some(tokio::time::Instant::now());
+        let text = self.build_text(&config).await?;
+        if text == self.text && !is_first_update {
+            return Ok(());
+        }
+        self.text = text;
+
+        self.render(&config).await
+    }
+
+    /// Build the text of the widget.
+    async fn build_text(&self, config: &Config) -> Result<String> {
+        let mut parts = Vec::new();
+        for item in &config.items {
+            let maybe_part = match item {
+                Item::Clock => Some(self.clock(config)?),
+                Item::Load => tokio::fs::read_to_string("/proc/loadavg")
+                    .await
+                    .ok()
+                    .and_then(|loadavg| parse_load(&loadavg)),
+                Item::Ram => tokio::fs::read_to_string("/proc/meminfo")
+                    .await
+                    .ok()
+                    .and_then(|meminfo| parse_ram(&meminfo)),
+                Item::Battery => read_battery().await,
+            };
+            parts.extend(maybe_part);
+        }
+
+        Ok(format!(" {} ", parts.join(&config.separator)))
+    }
+
+    /// The current time, formatted by the user's config.
+    fn clock(&self, config: &Config) -> Result<String> {
+        let unix = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+        let offset = config.utc_offset_minutes.unwrap_or(self.system_utc_offset);
+        let local = i64::try_from(unix.as_secs())? + offset * 60;
+        Ok(DateTime::from_unix(local).format(&config.clock_format))
+    }
+
+    /// Tick the render
+    async fn render(&mut self, config: &Config) -> Result<()> {
+        self.tattoy.opacity = config.opacity;
+        self.tattoy.initialise_surface();
+
+        let (x, y) = config.position.place(
+            self.tattoy.width.into(),
+            self.tattoy.height.into(),
+            self.tattoy.text_width(&self.text),
+        );
+        self.tattoy.surface.add_text(
+            x,
+            y,
+            self.text.clone(),
+            Some(config.background),
+            Some(config.foreground),
+        );
+
+        self.tattoy.send_output().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formats_times() {
+        let format = "%Y-%m-%d %H:%M:%S %a %b %I%p %%";
+        assert_eq!(
+            DateTime::from_unix(0).format(format),
+            "1970-01-01 00:00:00 Thu Jan 12AM %"
+        );
+        assert_eq!(
+            DateTime::from_unix(1_700_000_000).format(format),
+            "2023-11-14 22:13:20 Tue Nov 10PM %"
+        );
+        assert_eq!(
+            DateTime::from_unix(951_782_400).format("%A %e %B %y"),
+            "Tuesday 29 February 00"
+        );
+    }
+
+    #[test]
+    fn parses_utc_offsets() {
+        assert_eq!(parse_utc_offset("+0530\n"), Some(330));
+        assert_eq!(parse_utc_offset("-0100"), Some(-60));
+        assert_eq!(parse_utc_offset("nonsense"), None);
+    }
+
+    #[test]
+    fn parses_system_stats() {
+        assert_eq!(
+            parse_load("0.52 0.58 0.59 1/467 12345\n"),
+            Some("load 0.52".to_owned())
+        );
+        let meminfo =
+            "MemTotal:       16000000 kB\nMemFree:  1000000 kB\nMemAvailable:    4000000 kB\n";
+        assert_eq!(parse_ram(meminfo), Some("ram 75%".to_owned()));
+        assert_eq!(parse_ram("MemTotal: 100 kB\n"), None);
+    }
+}
@@ -1,11 +1,25 @@
 //! Handle parsed input events
 
-use color_eyre::eyre::{ContextCompat as _, Result};
+use color_eyre::eyre::Result;
 use shadow_terminal::termwiz;
 
 impl crate::terminal_proxy::proxy::Proxy {
     /// Handle input from the end user.
     pub async fn handle_input(&self, input: &crate::raw_input::ParsedInput) -> Result<()> {
+        if self.handle_lock_input(&input.event).await?
+            || self.handle_screensaver_input(&input.event).await?
+        {
+            return Ok(());
+        }
+        self.state.touch_last_activity().await;
+
+        if self.handle_paste_guard_input(&input.event).await? {
+            return Ok(());
+        }
+        if matches!(input.event, termwiz::input::InputEvent::Paste(_)) {
+            return self.forward_paste(input.to_owned()).await;
+        }
+
         if self.handle_tattoy_input_event(&input.event).await? {
             tracing::trace!(
                 "Not forwarding input because Tattoy received a known input event: {:?}",
@@ -17,27 +31,133 @@ impl crate::terminal_proxy::proxy::Proxy {
         self.forward_input_to_pty(input.to_owned()).await
     }
 
-    /// Forward raw input bytes to the underlying PTY.
-    async fn forward_input_to_pty(&self, input: crate::raw_input::ParsedInput) -> Result<()> {
-        // If the input is from an OSC paste event, then only forward the contents of the paste,
-        // and not the surrounding OSC codes.
-        let bytes = if let termwiz::input::InputEvent::Paste(string) = input.event {
-            string.into_bytes()
-        } else {
-            input.bytes
-        };
+    /// Whilst the terminal is locked all input is swallowed, and keypresses are collected until
+    /// the passphrase is typed.
+    async fn handle_lock_input(&self, event: &termwiz::input::InputEvent) -> Result<bool> {
+        if !self.state.lock.read().await.is_locked {
+            return Ok(false);
+        }
 
-        tracing::trace!(
-            "Terminal proxy received input bytes: {}",
-            String::from_utf8_lossy(&bytes)
-        );
+        if let termwiz::input::InputEvent::Key(key_event) = event {
+            let passphrase = self.state.config.read().await.lock.passphrase.clone();
+            let is_unlocked = self
+                .state
+                .lock
+                .write()
+                .await
+                .handle_key(key_event, &passphrase);
+            if is_unlocked {
+                tracing::debug!("Unlocked the terminal");
+                self.state.set_is_screensaver_active(false).await;
+                self.state.touch_last_activity().await;
+                self.tattoy_protocol.send(crate::run::Protocol::Repaint)?;
+            }
+        }
+
+        Ok(Self::is_users_input(event))
+    }
+
+    /// Whilst the screensaver is active all input is swallowed, and any keypress dismisses it.
+    async fn handle_screensaver_input(&self, event: &termwiz::input::InputEvent) -> Result<bool> {
+        if !self.state.get_is_screensaver_active().await {
+            return Ok(false);
+        }
 
-        for chunk in bytes.chunks(128) {
-            let mut buffer: crate::raw_input::BytesFromSTDIN = [0; 128];
-            for (i, chunk_byte) in chunk.iter().enumerate() {
-                let buffer_byte = buffer.get_mut(i).context("Couldn't get byte from buffer")?;
-                *buffer_byte = *chunk_byte;
+        if matches!(event, termwiz::input::InputEvent::Key(_)) {
+            tracing::debug!("Dismissing screensaver");
+            self.state.set_is_screensaver_active(false).await;
+            self.state.touch_last_activity().await;
+            self.tattoy_protocol.send(crate::run::Protocol::Repaint)?;
+        }
+
+        Ok(Self::is_users_input(event))
+    }
+
+    /// Whilst a paste is waiting for confirmation all input is swallowed, and keypresses decide
+    /// whether it's sent. New suspicious pastes are held until they're confirmed.
+    async fn handle_paste_guard_input(&self, event: &termwiz::input::InputEvent) -> Result<bool> {
+        if self.state.pending_paste.read().await.is_pending() {
+            let termwiz::input::InputEvent::Key(key_event) = event else {
+                return Ok(Self::is_users_input(event));
+            };
+            let decision = self.state.pending_paste.write().await.handle_key(key_event);
+            match decision {
+                crate::tattoys::paste_guard::Decision::Paste(paste) => {
+                    tracing::debug!("Paste confirmed");
+                    self.forward_paste(crate::raw_input::ParsedInput {
+                        bytes: Vec::new(),
+                        event: termwiz::input::InputEvent::Paste(paste),
+                    })
+                    .await?;
+                    self.tattoy_protocol.send(crate::run::Protocol::Repaint)?;
+                }
+                crate::tattoys::paste_guard::Decision::Cancel => {
+                    tracing::debug!("Paste cancelled");
+                    self.tattoy_protocol.send(crate::run::Protocol::Repaint)?;
+                }
+                crate::tattoys::paste_guard::Decision::Undecided => (),
             }
+            return Ok(true);
+        }
+
+        let termwiz::input::InputEvent::Paste(paste) = event else {
+            return Ok(false);
+        };
+        let config = self.state.config.read().await.paste_guard.clone();
+        if !config.enabled {
+            return Ok(false);
+        }
+        let Some(reason) = config.reason(paste) else {
+            return Ok(false);
+        };
+
+        tracing::debug!("Holding paste for confirmation: {reason}");
+        self.state
+            .pending_paste
+            .write()
+            .await
+            .hold(paste.clone(), reason);
+        Ok(true)
+    }
+
+    /// Pastes are sent to the PTY in one go, without Tattoy interpreting any of them. Like typing,
+    /// they end scrolling. Only copy mode swallows them, because it captures all input.
+    async fn forward_paste(&self, input: crate::raw_input::ParsedInput) -> Result<()> {
+        if self.state.get_is_copy_mode().await {
+            return Ok(());
+        }
+        if self.state.get_is_scrolling().await {
+            self.shadow_terminal.scroll_cancel()?;
+        }
+        self.forward_input_to_pty(input).await
+    }
+
+    /// Is the input event something that the user did, rather than something like a resize?
+    const fn is_users_input(event: &termwiz::input::InputEvent) -> bool {
+        !matches!(
+            event,
+            termwiz::input::InputEvent::Resized { .. } | termwiz::input::InputEvent::Wake
+        )
+    }
+
+    /// Forward raw input bytes to the underlying PTY. If another pane has focus then the input is
+    /// sent to the pane manager instead.
+    async fn forward_input_to_pty(&self, input: crate::raw_input::ParsedInput) -> Result<()> {
+        if !self.state.panes.read().await.is_main_focused() {
+            self.tattoy_protocol
+                .send(crate::run::Protocol::PaneInput(input))?;
+            return Ok(());
+        }
+
+        if matches!(input.event, termwiz::input::InputEvent::Key(_)) {
+            self.state
+                .latency
+                .write()
+                .await
+                .keystroke(std::time::Instant::now());
+        }
+
+        for buffer in input.pty_chunks()? {
             tracing::trace!(
                 "Proxying input to shadow terminal from Tattoy: {}",
                 String::from_utf8_lossy(&buffer)
@@ -53,12 +173,13 @@ impl crate::terminal_proxy::proxy::Proxy {
 
     /// Is the input event specific to Tattoy (eg toggling tattoys etc)?
     async fn handle_tattoy_input_event(&self, event: &termwiz::input::InputEvent) -> Result<bool> {
+        let is_copy_mode = self.state.get_is_copy_mode().await;
         let is_input_event = match event {
             termwiz::input::InputEvent::Key(key_event) => {
                 self.handle_tattoy_key_event(key_event).await?
             }
             termwiz::input::InputEvent::Mouse(mouse_event) => {
-                self.handle_mouse_scrolling_input(mouse_event).await?
+                !is_copy_mode && self.handle_mouse_scrolling_input(mouse_event).await?
             }
             termwiz::input::InputEvent::PixelMouse(_pixel_mouse_event) => false,
             termwiz::input::InputEvent::Resized {
@@ -68,7 +189,7 @@ impl crate::terminal_proxy::proxy::Proxy {
             termwiz::input::InputEvent::Paste(_) | termwiz::input::InputEvent::Wake => false,
         };
 
-        Ok(is_input_event || self.state.get_is_scrolling().await)
+        Ok(is_input_event || is_copy_mode || self.state.get_is_scrolling().await)
     }
 
     /// Handle a key event that we have a keybinding for.
@@ -83,6 +204,13 @@ impl crate::terminal_proxy::proxy::Proxy {
         };
         drop(keybindings);
 
+        // Copy mode captures all input, so the only keybinding it respects is the one to exit it.
+        if trigger != crate::config::input::KeybindingAction::ToggleCopyMode
+            && self.state.get_is_copy_mode().await
+        {
+            return Ok(true);
+        }
+
         match trigger {
             crate::config::input::KeybindingAction::ToggleTattoy => {
                 let existing = *self.state.is_rendering_enabled.read().await;
@@ -141,9 +269,101 @@ impl crate::terminal_proxy::proxy::Proxy {
                     ))?;
                 Ok(true)
             }
+            crate::config::input::KeybindingAction::ToggleHeatmap => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ToggleHeatmap,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::TimerToggle => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::TimerToggle,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::TimerReset => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::TimerReset,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::LockScreen => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::LockScreen,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::RevealSecrets => {
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::RevealSecrets,
+                    ))?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::NextScene => {
+                crate::scenes::switch_to_next(&self.state).await;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::ToggleColourBlindness => {
+                let mut is_toggled = self.state.is_colour_blindness_toggled.write().await;
+                *is_toggled = !*is_toggled;
+                drop(is_toggled);
+                self.tattoy_protocol.send(crate::run::Protocol::Repaint)?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::Detach => self.detach().await,
+            crate::config::input::KeybindingAction::ToggleStreamMode => {
+                crate::stream_mode::toggle(&self.state).await?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::SplitPane => {
+                self.split_pane().await?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::FocusNextPane => {
+                self.state.panes.write().await.focus_next();
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::PanesChanged)?;
+                Ok(true)
+            }
+            crate::config::input::KeybindingAction::ToggleCopyMode => {
+                let existing = self.state.get_is_copy_mode().await;
+                tracing::debug!("Toggling copy mode to: {}", !existing);
+                self.state.set_is_copy_mode(!existing).await;
+                self.tattoy_protocol
+                    .send(crate::run::Protocol::KeybindEvent(
+                        crate::config::input::KeybindingAction::ToggleCopyMode,
+                    ))?;
+                Ok(true)
+            }
         }
     }
 
+    /// Add a new pane to the layout. The pane manager is responsible for actually starting it.
+    async fn split_pane(&self) -> Result<()> {
+        let width = self.state.get_tty_size().await.width;
+        let is_split = self.state.panes.write().await.split(width.into());
+        if !is_split {
+            self.state
+                .send_notification(
+                    "Not enough room for another pane",
+                    crate::tattoys::notifications::message::Level::Warn,
+                    None,
+                    false,
+                )
+                .await;
+            return Ok(());
+        }
+
+        self.tattoy_protocol
+            .send(crate::run::Protocol::PanesChanged)?;
+        Ok(())
+    }
+
     /// Because Tattoy is a wrapper around a headless, in-memory terminal, it can't rely on the
     /// user's actual terminal (Kitty, Alacritty, iTerm, etc) to do scrolling. So Tattoy forwards
     /// scrolling events to the shadow terminal and renders its own scrollbars etc.
@@ -66,6 +66,7 @@ impl Proxy {
                 result = &mut proxy.shadow_terminal.task_handle => {
                     if let Err(error) = result {
                         tracing::error!("{error:?}");
+                        proxy.state.exit_summary.write().await.pty_failed();
                     }
                     break;
                 }
@@ -100,7 +101,9 @@ impl Proxy {
                     shadow_terminal::output::native::CompleteSurface::Screen(screen) => {
                         let mut shadow_tty_screen = self.state.shadow_tty_screen.write().await;
                         *shadow_tty_screen = screen.surface;
+                        let cursor = shadow_tty_screen.cursor_position();
                         drop(shadow_tty_screen);
+                        self.state.cursor_history.write().await.record(cursor);
 
                         let is_alternate_screen = matches!(
                             screen.mode,
@@ -117,6 +120,7 @@ impl Proxy {
         }
 
         self.send_pty_surface_notifications(output).await;
+        self.state.touch_last_activity().await;
 
         let mut pty_sequence = self.state.pty_sequence.write().await;
         *pty_sequence += 1;
@@ -193,12 +197,16 @@ impl Proxy {
     /// Reconstruct the alternate screen surface from a diff of changes.
     async fn reconstruct_screen_diff(&self, diff: shadow_terminal::output::native::ScreenDiff) {
         let mut shadow_tty_screen = self.state.shadow_tty_screen.write().await;
-        let size = self.state.get_tty_size().await;
 
+        // The PTY can be smaller than the user's terminal, eg: when it's split into panes or
+        // there's a status line.
         if shadow_tty_screen.dimensions() != diff.size {
-            shadow_tty_screen.resize(size.width.into(), size.height.into());
+            shadow_tty_screen.resize(diff.size.0, diff.size.1);
         }
         shadow_tty_screen.add_changes(diff.changes);
+        let cursor = shadow_tty_screen.cursor_position();
+        drop(shadow_tty_screen);
+        self.state.cursor_history.write().await.record(cursor);
     }
 
     /// Handle protocol messages from Tattoy.
@@ -209,7 +217,12 @@ impl Proxy {
                 self.shadow_terminal.kill()?;
             }
             crate::run::Protocol::Resize { width, height } => {
-                self.shadow_terminal.resize(width, height)?;
+                self.resize_to_main_pane(width, height).await?;
+            }
+            crate::run::Protocol::PanesChanged | crate::run::Protocol::Config(_) => {
+                let tty_size = self.state.get_tty_size().await;
+                self.resize_to_main_pane(tty_size.width, tty_size.height)
+                    .await?;
             }
             crate::run::Protocol::Input(input) => {
                 self.handle_input(&input).await?;
@@ -220,6 +233,27 @@ impl Proxy {
         Ok(())
     }
 
+    /// Resize the shadow terminal to fit the main pane. When the terminal isn't split then this
+    /// is just the size of the user's terminal, less any row taken by the status line.
+    async fn resize_to_main_pane(&self, width: u16, height: u16) -> Result<()> {
+        let reserved_rows = self.state.config.read().await.status_line.reserved_rows();
+        let height = height.saturating_sub(reserved_rows);
+        let maybe_region = self
+            .state
+            .panes
+            .read()
+            .await
+            .region(0, width.into(), height.into());
+        match maybe_region {
+            Some(region) => self
+                .shadow_terminal
+                .resize(region.width.try_into()?, region.height.try_into()?)?,
+            None => self.shadow_terminal.resize(width, height)?,
+        }
+
+        Ok(())
+    }
+
     // TODO:
     // It is a bit odd that we send 3 notifications about new PTY output. I'm sure the
     // receiver of the `Protocol::Output` message could do everything that the receiver of the
@@ -0,0 +1,154 @@
+//! Always give the user's terminal back in a usable state, however Tattoy exits.
+//!
+//! Tattoy puts the user's terminal into raw mode and enables modes like focus reporting and the
+//! kitty keyboard protocol. When Tattoy exits normally the renderer undoes all of that, but when
+//! something panics the renderer may never get the chance. So the terminal's original settings
+//! are saved at startup and restored when Tattoy exits, along with a sequence that turns off
+//! every mode that Tattoy, or an app in the PTY, could have left on.
+//!
+//! Panics in tattoys don't end Tattoy, so they're only logged and kept. Their reports are shown
+//! once the terminal's been restored, if Tattoy exits with an error.
+
+use shadow_terminal::termwiz;
+use termwiz::terminal::Terminal as _;
+
+/// The user's terminal, as it was before Tattoy changed any of its settings. Termwiz remembers
+/// the settings that a terminal had when it was created, so this is what they're restored from.
+static ORIGINAL_TERMINAL: std::sync::OnceLock<std::sync::Mutex<termwiz::terminal::SystemTerminal>> =
+    std::sync::OnceLock::new();
+
+/// Whether the kitty keyboard protocol was enabled, as disabling it when it wasn't could disable
+/// it for whatever ran Tattoy.
+static IS_KITTY_KEYBOARD: core::sync::atomic::AtomicBool =
+    core::sync::atomic::AtomicBool::new(false);
+
+/// The reports of the panics that have happened so far.
+static PANIC_REPORTS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+/// Remember the user's terminal's current settings. This needs to happen before anything puts the
+/// terminal into raw mode. Not having a terminal isn't an error, eg: when printing the config
+/// schema to a file.
+pub(crate) fn save_original() {
+    let terminal = match crate::renderer::Renderer::get_termwiz_terminal() {
+        Ok(terminal) => terminal,
+        Err(error) => {
+            tracing::warn!("Couldn't save the user's terminal settings: {error:?}");
+            return;
+        }
+    };
+    if ORIGINAL_TERMINAL
+        .set(std::sync::Mutex::new(terminal))
+        .is_err()
+    {
+        tracing::warn!("The user's original terminal settings were already saved");
+    }
+}
+
+/// Remember that the kitty keyboard protocol was enabled, so that it's disabled again on exit.
+pub(crate) fn enabled_kitty_keyboard() {
+    IS_KITTY_KEYBOARD.store(true, core::sync::atomic::Ordering::Relaxed);
+}
+
+/// The sequence that turns off all the modes that could have been left on: synchronised output,
+/// the alternate screen, mouse reporting, bracketed paste, a hidden cursor, text attributes,
+/// focus reporting and the kitty keyboard protocol.
+pub(crate) fn sequence(is_kitty_keyboard: bool) -> String {
+    let escape = crate::utils::ESCAPE;
+    let modes: String = ["2026", "1049", "1000", "1002", "1003", "1006", "2004"]
+        .iter()
+        .map(|mode| format!("{escape}[?{mode}l"))
+        .collect();
+    let kitty_keyboard = if is_kitty_keyboard {
+        crate::kitty_keyboard::disable_sequence()
+    } else {
+        String::new()
+    };
+    format!(
+        "{modes}{escape}[?25h{}{}{kitty_keyboard}",
+        crate::utils::RESET_COLOUR,
+        crate::focus::disable_sequence()
+    )
+}
+
+/// Put the user's terminal back how it was before Tattoy started. It's safe to call more than
+/// once, and it never fails, as it's the last thing that happens before exiting.
+pub(crate) fn restore() {
+    let Some(mutex) = ORIGINAL_TERMINAL.get() else {
+        return;
+    };
+    // A poisoned lock still holds the terminal, and restoring it matters more than ever then.
+    let mut terminal = mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+    let is_kitty_keyboard = IS_KITTY_KEYBOARD.swap(false, core::sync::atomic::Ordering::Relaxed);
+    let result = std::io::Write::write_all(&mut *terminal, sequence(is_kitty_keyboard).as_bytes())
+        .and_then(|()| std::io::Write::flush(&mut *terminal));
+    if let Err(error) = result {
+        tracing::error!("Couldn't reset the user's terminal modes: {error:?}");
+    }
+    if let Err(error) = terminal.set_cooked_mode() {
+        tracing::error!("Couldn't restore the user's terminal settings: {error:?}");
+    }
+}
+
+/// A one line report of a panic.
+pub(crate) fn panic_report(info: &std::panic::PanicHookInfo<'_>) -> String {
+    let message = if let Some(message) = info.payload().downcast_ref::<String>() {
+        message
+    } else if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message
+    } else {
+        "Caught a panic with an unknown type."
+    };
+    let location = match info.location() {
+        Some(location) => format!(
+            "{}@{}:{}",
+            location.file(),
+            location.line(),
+            location.column()
+        ),
+        None => "Unknown location".to_owned(),
+    };
+    format!("({location}): {message:?}")
+}
+
+/// Keep a panic's report, to show the user if Tattoy exits because of it.
+pub(crate) fn record_panic(report: String) {
+    PANIC_REPORTS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .push(report);
+}
+
+/// All the panic reports kept so far.
+pub(crate) fn take_panic_reports() -> Vec<String> {
+    core::mem::take(
+        &mut *PANIC_REPORTS
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn the_sequence_turns_every_mode_off() {
+        let kitty_keyboard = crate::kitty_keyboard::disable_sequence();
+        let everything = sequence(true);
+        assert!(everything.contains("\x1b[?1049l"));
+        assert!(everything.contains("\x1b[?25h"));
+        assert!(everything.contains("\x1b[?1006l"));
+        assert!(everything.ends_with(&kitty_keyboard));
+        assert!(!sequence(false).contains(&kitty_keyboard));
+    }
+
+    #[test]
+    fn panic_reports_are_only_taken_once() {
+        record_panic("a test panic".to_owned());
+        assert!(take_panic_reports().contains(&"a test panic".to_owned()));
+        assert!(!take_panic_reports().contains(&"a test panic".to_owned()));
+    }
+}
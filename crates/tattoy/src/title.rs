@@ -0,0 +1,212 @@
+//! Forward the title that the PTY sets, with the `OSC 0` and `OSC 2` sequences, to the user's
+//! terminal, so that it's shown in the user's tab or window title as it would be without Tattoy.
+//!
+//! The title can also be augmented with a template, where these variables are replaced:
+//!
+//! * `{title}`: the title set by the PTY.
+//! * `{scene}`: the name of the current scene, or nothing.
+//! * `{spinner}`: a spinner, and a space, whilst the PTY is busy outputting, most likely because
+//!   a command is running. Otherwise nothing.
+
+use color_eyre::eyre::Result;
+use shadow_terminal::termwiz;
+
+/// How often the title is checked for changes, which is also the speed of the spinner.
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// The frames of the spinner.
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// User-configurable settings for the title.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to set the title of the user's terminal.
+    pub enabled: bool,
+    /// The title, with variables like `{title}`.
+    pub template: String,
+    /// How long the PTY's output needs to be quiet, in seconds, before it's no longer busy.
+    pub busy_delay: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            template: "{spinner}{title}".to_owned(),
+            busy_delay: 0.5,
+        }
+    }
+}
+
+/// The last title set in some changes to the PTY's screen.
+fn title_in_changes(changes: &[termwiz::surface::Change]) -> Option<String> {
+    changes.iter().rev().find_map(|change| match change {
+        termwiz::surface::Change::Title(title) => Some(title.clone()),
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "We're only looking for titles"
+        )]
+        _ => None,
+    })
+}
+
+/// The last title set in some output from the PTY, if it set one.
+fn title_in(output: &shadow_terminal::output::native::Output) -> Option<String> {
+    match output {
+        shadow_terminal::output::native::Output::Diff(
+            shadow_terminal::output::native::SurfaceDiff::Screen(diff),
+        ) => title_in_changes(&diff.changes),
+        shadow_terminal::output::native::Output::Complete(
+            shadow_terminal::output::native::CompleteSurface::Screen(screen),
+        ) => Some(screen.surface.title().to_owned()),
+        #[expect(
+            clippy::wildcard_enum_match_arm,
+            reason = "Only the screen has a title"
+        )]
+        _ => None,
+    }
+}
+
+/// The `OSC 2` sequence that sets the title of the user's terminal. Control characters would end
+/// the sequence early, so they're removed.
+pub(crate) fn sequence(title: &str) -> String {
+    let title: String = title
+        .chars()
+        .filter(|character| !character.is_control())
+        .collect();
+    format!("{}]2;{title}{}", crate::utils::ESCAPE, crate::utils::BELL)
+}
+
+/// Keeps the title of the user's terminal up to date.
+pub(crate) struct Title {
+    /// The application shared state
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+    /// The title that the PTY set.
+    pty_title: String,
+    /// When the PTY's output last changed.
+    output_changed_at: Option<tokio::time::Instant>,
+    /// The frame of the spinner.
+    spinner_frame: usize,
+    /// The title that was last sent to the user's terminal.
+    sent: Option<String>,
+}
+
+impl Title {
+    /// Start the task that keeps the title up to date.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let mut protocol = state.protocol_tx.subscribe();
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            let mut title = Self {
+                state,
+                pty_title: String::new(),
+                output_changed_at: None,
+                spinner_frame: 0,
+                sent: None,
+            };
+
+            #[expect(
+                clippy::integer_division_remainder_used,
+                reason = "This is caused by the `tokio::select!`"
+            )]
+            loop {
+                tokio::select! {
+                    now = interval.tick() => title.update(now).await?,
+                    message = protocol.recv() => match message {
+                        Ok(crate::run::Protocol::End)
+                        | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        Ok(message) => title.handle_protocol_message(&message),
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Title tracking lagged behind by {skipped} messages");
+                        }
+                    }
+                }
+            }
+
+            tracing::debug!("Leaving title tracking loop");
+            Ok(())
+        })
+    }
+
+    /// Keep track of the PTY's title and whether it's busy.
+    fn handle_protocol_message(&mut self, message: &crate::run::Protocol) {
+        if crate::tattoys::tattoyer::Tattoyer::is_screen_output_changed(message) {
+            self.output_changed_at = Some(tokio::time::Instant::now());
+        }
+        if let crate::run::Protocol::Output(output) = message {
+            if let Some(pty_title) = title_in(output) {
+                self.pty_title = pty_title;
+            }
+        }
+    }
+
+    /// Send the title to the user's terminal if it's changed.
+    async fn update(&mut self, now: tokio::time::Instant) -> Result<()> {
+        let config = self.state.config.read().await.title.clone();
+        if !config.enabled {
+            return Ok(());
+        }
+
+        let busy_delay =
+            std::time::Duration::try_from_secs_f32(config.busy_delay.max(0.0)).unwrap_or_default();
+        let is_busy = self
+            .output_changed_at
+            .is_some_and(|changed_at| now.saturating_duration_since(changed_at) < busy_delay);
+        let spinner = if is_busy {
+            self.spinner_frame = (self.spinner_frame + 1).rem_euclid(SPINNER.len());
+            SPINNER
+                .get(self.spinner_frame)
+                .map(|frame| format!("{frame} "))
+                .unwrap_or_default()
+        } else {
+            String::new()
+        };
+        let scene = self.state.scene.read().await.clone().unwrap_or_default();
+
+        let title = crate::tattoys::status_line::fill_template(
+            &config.template,
+            &[
+                ("title", &self.pty_title),
+                ("scene", &scene),
+                ("spinner", &spinner),
+            ],
+        );
+        if self.sent.as_ref() == Some(&title) {
+            return Ok(());
+        }
+
+        tracing::trace!("Setting the title to: {title}");
+        self.state
+            .protocol_tx
+            .send(crate::run::Protocol::Title(title.clone()))?;
+        self.sent = Some(title);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_the_last_title_in_output() {
+        let changes = [
+            termwiz::surface::Change::Title("first".into()),
+            termwiz::surface::Change::Text("ls\r\n".into()),
+            termwiz::surface::Change::Title("vim".into()),
+        ];
+        assert_eq!(title_in_changes(&changes), Some("vim".to_owned()));
+        assert_eq!(
+            title_in_changes(&[termwiz::surface::Change::Text("ls".into())]),
+            None
+        );
+    }
+
+    #[test]
+    fn title_sequences_are_escaped() {
+        assert_eq!(sequence("vim\x07 main.rs"), "\x1b]2;vim main.rs\x07");
+    }
+}
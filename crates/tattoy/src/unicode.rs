@@ -0,0 +1,74 @@
+//! Measuring text by how many columns it takes on the screen, rather than by how many characters
+//! it has. CJK characters and most emoji are 2 columns wide, whilst an emoji ZWJ sequence, or a
+//! letter with combining marks, is a single grapheme cluster that fits in a single cell.
+//!
+//! Some characters, like "…" and "•", have an "ambiguous" East Asian width. Most terminals show
+//! them 1 column wide, but terminals set up for CJK often show them 2 columns wide. There's no
+//! way to ask the user's terminal which it does, so it's configurable.
+
+use shadow_terminal::termwiz;
+
+/// The version of Unicode whose width tables are used.
+const UNICODE_VERSION: u8 = 14;
+
+/// How wide characters with an ambiguous East Asian width are.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum AmbiguousWidth {
+    /// 1 column, like most terminals.
+    #[default]
+    Narrow,
+    /// 2 columns, like most CJK terminals.
+    Wide,
+}
+
+/// User-configurable settings for Unicode text.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// How wide your terminal shows characters with an ambiguous East Asian width.
+    pub ambiguous_width: AmbiguousWidth,
+    /// Whether your terminal shows right-to-left scripts, like Arabic and Hebrew, reversed. When
+    /// it does, tattoys that cover parts of the text follow the reordering, see `crate::bidi`.
+    pub bidi: bool,
+}
+
+impl Config {
+    /// The Unicode version, in the form that Termwiz uses to measure text.
+    pub const fn version(self) -> termwiz::cell::UnicodeVersion {
+        termwiz::cell::UnicodeVersion {
+            version: UNICODE_VERSION,
+            ambiguous_are_wide: matches!(self.ambiguous_width, AmbiguousWidth::Wide),
+        }
+    }
+
+    /// The number of columns that some text takes on the screen.
+    pub fn width(self, text: &str) -> usize {
+        termwiz::cell::unicode_column_width(text, Some(self.version()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn text_is_measured_in_columns() {
+        let unicode = Config::default();
+        assert_eq!(unicode.width("tattoy"), 6);
+        assert_eq!(unicode.width("刺青"), 4);
+        assert_eq!(unicode.width("e\u{301}"), 1);
+        assert_eq!(unicode.width("👩\u{200d}🚀"), 2);
+    }
+
+    #[test]
+    fn ambiguous_characters_can_be_wide() {
+        let narrow = Config::default();
+        let wide = Config {
+            ambiguous_width: AmbiguousWidth::Wide,
+            ..Config::default()
+        };
+        assert_eq!(narrow.width("…"), 1);
+        assert_eq!(wide.width("…"), 2);
+    }
+}
@@ -35,6 +35,62 @@ pub(crate) fn smoothstep(edge0: f32, edge1: f32, mut x: f32) -> f32 {
     x * x * 2.0f32.mul_add(-x, 3.0)
 }
 
+/// A corner of the terminal, for small things like badges and widgets.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Corner {
+    /// The top-left corner.
+    TopLeft,
+    /// The top-right corner.
+    TopRight,
+    /// The bottom-left corner.
+    BottomLeft,
+    /// The bottom-right corner.
+    BottomRight,
+}
+
+impl Corner {
+    /// The position of the first cell of some text so that it sits in the corner.
+    pub(crate) const fn place(
+        self,
+        width: usize,
+        height: usize,
+        text_width: usize,
+    ) -> (usize, usize) {
+        let x = match self {
+            Self::TopLeft | Self::BottomLeft => 0,
+            Self::TopRight | Self::BottomRight => width.saturating_sub(text_width),
+        };
+        let y = match self {
+            Self::TopLeft | Self::TopRight => 0,
+            Self::BottomLeft | Self::BottomRight => height.saturating_sub(1),
+        };
+        (x, y)
+    }
+}
+
+/// Make an HTTP request. We use `curl` so that we don't need to bundle a whole HTTP and TLS
+/// stack. Returns the body of the response.
+pub(crate) async fn curl(args: &[&str], url: &str) -> color_eyre::eyre::Result<String> {
+    let output = tokio::process::Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time", "10"])
+        .args(args)
+        .arg(url)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        color_eyre::eyre::bail!(
+            "curl {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
 /// A simple hash function.
 pub(crate) fn simple_hash(input: &[u8]) -> u64 {
     let mut hash: u64 = 0;
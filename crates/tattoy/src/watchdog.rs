@@ -0,0 +1,277 @@
+//! Notice when the renderer gets stuck, eg: waiting on a lock that's never released.
+//!
+//! The renderer keeps a heartbeat of what it's doing. Whenever it's been doing the same thing for
+//! longer than the threshold, the watchdog logs what that was, along with which of the shared
+//! state's locks are in use. If the renderer was handling a particular tattoy's frame at the time,
+//! that tattoy is told to start again from a blank frame.
+//!
+//! The watchdog can't unstick the renderer itself. A renderer that's only slow throws away the
+//! tattoy's old frame once it gets round to the reset message, but a deadlocked one stays stuck,
+//! so the report is for finding out why.
+//!
+//! The heartbeat has to be readable even when the renderer is stuck holding a lock, so it's kept
+//! behind a standard mutex that's only ever held for as long as it takes to copy it.
+
+use color_eyre::eyre::Result;
+
+/// How often the heartbeat is checked, as a fraction of the threshold.
+const CHECKS_PER_THRESHOLD: u32 = 4;
+
+/// User-configurable settings for the watchdog.
+#[derive(serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// Whether to watch for the renderer getting stuck.
+    pub enabled: bool,
+    /// How long, in milliseconds, the renderer can spend on one thing before it's considered
+    /// stuck.
+    pub threshold: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 5000,
+        }
+    }
+}
+
+/// Something that the renderer is in the middle of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Stage {
+    /// What the renderer is doing, eg: "compositing".
+    pub name: &'static str,
+    /// The tattoy whose frame is involved, if any.
+    pub tattoy: Option<String>,
+    /// When the renderer started doing it.
+    pub since: std::time::Instant,
+}
+
+/// What the renderer is currently doing.
+#[derive(Debug, Default)]
+pub(crate) struct Heartbeat {
+    /// `None` when the renderer is waiting for something to do.
+    stage: Option<Stage>,
+}
+
+impl Heartbeat {
+    /// The renderer started doing something new.
+    pub fn enter(&mut self, name: &'static str, tattoy: Option<&str>) {
+        let is_same = self
+            .stage
+            .as_ref()
+            .is_some_and(|stage| stage.name == name && stage.tattoy.as_deref() == tattoy);
+        if is_same {
+            return;
+        }
+        self.stage = Some(Stage {
+            name,
+            tattoy: tattoy.map(ToOwned::to_owned),
+            since: std::time::Instant::now(),
+        });
+    }
+
+    /// The renderer is waiting for something to do, which is never a stall.
+    pub fn idle(&mut self) {
+        self.stage = None;
+    }
+
+    /// What the renderer is stuck on, if it's been doing the same thing for too long.
+    pub fn stalled(&self, threshold: std::time::Duration) -> Option<Stage> {
+        self.stage
+            .as_ref()
+            .filter(|stage| stage.since.elapsed() > threshold)
+            .cloned()
+    }
+}
+
+/// Record what the renderer is doing.
+pub(crate) fn enter(
+    state: &crate::shared_state::SharedState,
+    name: &'static str,
+    tattoy: Option<&str>,
+) {
+    state
+        .heartbeat
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .enter(name, tattoy);
+}
+
+/// Record that the renderer is waiting for something to do.
+pub(crate) fn idle(state: &crate::shared_state::SharedState) {
+    state
+        .heartbeat
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .idle();
+}
+
+/// Watches the renderer's heartbeat.
+pub(crate) struct Watchdog {
+    /// The application shared state
+    state: std::sync::Arc<crate::shared_state::SharedState>,
+    /// The watchdog's settings. They're kept from config updates, rather than read from the shared
+    /// state, as the config's lock could be the one that's stuck.
+    config: Config,
+    /// When the stall that's already been reported started, so that it's only reported once.
+    reported: Option<std::time::Instant>,
+}
+
+impl Watchdog {
+    /// Start the task that checks the heartbeat.
+    pub(crate) fn start(
+        state: std::sync::Arc<crate::shared_state::SharedState>,
+    ) -> tokio::task::JoinHandle<Result<()>> {
+        tokio::spawn(async move {
+            let mut protocol = state.protocol_tx.subscribe();
+            let config = state.config.read().await.watchdog.clone();
+            let mut watchdog = Self {
+                state,
+                config,
+                reported: None,
+            };
+
+            #[expect(
+                clippy::integer_division_remainder_used,
+                reason = "This is caused by the `tokio::select!`"
+            )]
+            loop {
+                tokio::select! {
+                    () = tokio::time::sleep(watchdog.threshold() / CHECKS_PER_THRESHOLD) => {
+                        if watchdog.config.enabled {
+                            watchdog.check().await?;
+                        }
+                    },
+                    message = protocol.recv() => match message {
+                        Ok(crate::run::Protocol::End)
+                        | Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        Ok(crate::run::Protocol::Config(config)) => watchdog.config = config.watchdog,
+                        Ok(_) => (),
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Watchdog lagged behind by {skipped} messages");
+                        }
+                    }
+                }
+            }
+
+            tracing::debug!("Leaving watchdog loop");
+            Ok(())
+        })
+    }
+
+    /// How long the renderer can spend on one thing.
+    fn threshold(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.config.threshold.max(1))
+    }
+
+    /// Report a stalled renderer, and tell the tattoy that it's stuck on to start again.
+    async fn check(&mut self) -> Result<()> {
+        let maybe_stalled = self
+            .state
+            .heartbeat
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .stalled(self.threshold());
+        let Some(stage) = maybe_stalled else {
+            self.reported = None;
+            return Ok(());
+        };
+        if self.reported == Some(stage.since) {
+            return Ok(());
+        }
+        self.reported = Some(stage.since);
+
+        let tattoy = stage
+            .tattoy
+            .as_ref()
+            .map_or_else(String::new, |id| format!(" the frame from '{id}'"));
+        tracing::error!(
+            "The renderer has been stuck {}{tattoy} for {:?}. Locks in use: {}",
+            stage.name,
+            stage.since.elapsed(),
+            self.locks_in_use().join(", ")
+        );
+
+        let Some(id) = stage.tattoy else {
+            return Ok(());
+        };
+        self.state
+            .protocol_tx
+            .send(crate::run::Protocol::ResetTattoy(id.clone()))?;
+        self.state
+            .send_notification(
+                "Tattoy stalled",
+                crate::tattoys::notifications::message::Level::Error,
+                Some(format!("Rendering '{id}' got stuck, see the logs for why.")),
+                false,
+            )
+            .await;
+        Ok(())
+    }
+
+    /// The shared state's locks that something is holding right now. One that's always in use is
+    /// a good sign of a deadlock.
+    fn locks_in_use(&self) -> Vec<&'static str> {
+        let state = &self.state;
+        let locks = [
+            ("config", state.config.try_write().is_err()),
+            ("tty_size", state.tty_size.try_write().is_err()),
+            (
+                "shadow_tty_screen",
+                state.shadow_tty_screen.try_write().is_err(),
+            ),
+            (
+                "shadow_tty_scrollback",
+                state.shadow_tty_scrollback.try_write().is_err(),
+            ),
+            ("panes", state.panes.try_write().is_err()),
+            ("surface_pool", state.surface_pool.try_write().is_err()),
+            ("stashed_frames", state.stashed_frames.try_write().is_err()),
+            ("frame_pacing", state.frame_pacing.try_write().is_err()),
+            ("live_controls", state.live_controls.try_write().is_err()),
+            ("metrics", state.metrics.try_write().is_err()),
+        ];
+        locks
+            .into_iter()
+            .filter(|(_, is_in_use)| *is_in_use)
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_busy_renderers_stall() {
+        let mut heartbeat = Heartbeat::default();
+        assert!(heartbeat.stalled(std::time::Duration::ZERO).is_none());
+
+        heartbeat.enter("compositing", Some("shader"));
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        let stage = heartbeat.stalled(std::time::Duration::ZERO).unwrap();
+        assert_eq!(stage.name, "compositing");
+        assert_eq!(stage.tattoy.as_deref(), Some("shader"));
+        assert!(heartbeat
+            .stalled(std::time::Duration::from_secs(60))
+            .is_none());
+
+        heartbeat.idle();
+        assert!(heartbeat.stalled(std::time::Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn doing_the_same_thing_keeps_the_start_time() {
+        let mut heartbeat = Heartbeat::default();
+        heartbeat.enter("painting", None);
+        let since = heartbeat.stage.as_ref().unwrap().since;
+        heartbeat.enter("painting", None);
+        assert_eq!(heartbeat.stage.as_ref().unwrap().since, since);
+
+        heartbeat.enter("compositing", Some("minimap"));
+        assert_ne!(heartbeat.stage.as_ref().unwrap().name, "painting");
+    }
+}